@@ -35,6 +35,7 @@ fn create_text_generation_model() -> TextGenerationModel {
         diversity_penalty: None,
         num_return_sequences: 5,
         device: Device::cuda_if_available(),
+        ..Default::default()
     };
     TextGenerationModel::new(config).unwrap()
 }
@@ -43,7 +44,7 @@ fn generation_forward_pass(iters: u64, model: &TextGenerationModel, data: &[&str
     let mut duration = Duration::new(0, 0);
     for _i in 0..iters {
         let start = Instant::now();
-        let _ = model.generate(data, None);
+        let _ = model.generate(data, None).unwrap();
         duration = duration.checked_add(start.elapsed()).unwrap();
     }
     duration
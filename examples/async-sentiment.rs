@@ -47,7 +47,7 @@ impl SentimentClassifier {
 
         while let Ok((texts, sender)) = receiver.recv() {
             let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
-            let sentiments = model.predict(texts);
+            let sentiments = model.predict(texts)?;
             sender.send(sentiments).expect("sending results");
         }
 
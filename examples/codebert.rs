@@ -48,7 +48,7 @@ fn main() -> anyhow::Result<()> {
     ];
 
     //    Run model
-    let output = sequence_classification_model.predict(input);
+    let output = sequence_classification_model.predict(input)?;
     for label in output {
         println!("{:?}", label);
     }
@@ -12,16 +12,19 @@
 // limitations under the License.
 
 use crate::common::dropout::Dropout;
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use tch::{nn, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for BART attention layers
 /// Stores the cached value of key, value and key padding mask to avoid recalculation (e.g. at each generation step)
 pub struct LayerState {
     /// Cached keys
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_key: Tensor,
     /// Cached values
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_value: Tensor,
 }
 
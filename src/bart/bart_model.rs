@@ -17,7 +17,7 @@ use crate::bart::encoder::BartEncoder;
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::kind::get_negative_infinity;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
@@ -30,6 +30,7 @@ use rust_tokenizers::vocab::{RobertaVocab, Vocab};
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tch::nn::{embedding, EmbeddingConfig};
 use tch::{nn, Device, Kind, Tensor};
 
@@ -366,6 +367,19 @@ fn _shift_tokens_right(input_ids: &Tensor, pad_token_id: i64) -> Tensor {
     output
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # Parts of a BART model to create
+/// Creating only the encoder (e.g. to compute embeddings) or only the decoder of a model roughly halves its memory
+/// usage: the variables of the other part are not created, and their weights are skipped when loading a checkpoint.
+pub enum BartModelParts {
+    /// Encoder and decoder
+    Full,
+    /// Encoder only, the model can only be used to encode inputs
+    EncoderOnly,
+    /// Decoder only, the encoder hidden states must be provided to the forward pass
+    DecoderOnly,
+}
+
 /// # BART Base model
 /// Base architecture for BART model. Usually complemented with a task-specific head, such as a language model head.
 /// It is made of the following blocks:
@@ -374,8 +388,8 @@ fn _shift_tokens_right(input_ids: &Tensor, pad_token_id: i64) -> Tensor {
 /// caching is implemented for the decoder to avoid recalculating static states (encoder key/values and previously calculated decoder key/values)
 /// - `pad_token_id`: padding token id
 pub struct BartModel {
-    pub(crate) encoder: BartEncoder,
-    decoder: BartDecoder,
+    encoder: Option<BartEncoder>,
+    decoder: Option<BartDecoder>,
     pub(crate) embeddings: nn::Embedding,
     pad_token_id: i64,
 }
@@ -403,6 +417,36 @@ impl BartModel {
     /// let bart: BartModel = BartModel::new(&p.root() / "bart", &config);
     /// ```
     pub fn new<'p, P>(p: P, config: &BartConfig) -> BartModel
+    where
+        P: Borrow<nn::Path<'p>>,
+    {
+        Self::new_with_parts(p, config, BartModelParts::Full)
+    }
+
+    /// Build a new `BartModel` made of only some of its parts (e.g. the encoder only)
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Variable store path for the root of the BART model
+    /// * `config` - `BartConfig` object defining the model architecture
+    /// * `parts` - `BartModelParts` parts of the model to create
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::bart::{BartConfig, BartModel, BartModelParts};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// let config_path = Path::new("path/to/config.json");
+    /// let device = Device::Cpu;
+    /// let p = nn::VarStore::new(device);
+    /// let config = BartConfig::from_file(config_path);
+    /// let bart_encoder: BartModel =
+    ///     BartModel::new_with_parts(&p.root() / "model", &config, BartModelParts::EncoderOnly);
+    /// ```
+    pub fn new_with_parts<'p, P>(p: P, config: &BartConfig, parts: BartModelParts) -> BartModel
     where
         P: Borrow<nn::Path<'p>>,
     {
@@ -420,8 +464,18 @@ impl BartModel {
             embedding_config,
         );
 
-        let encoder = BartEncoder::new(p / "encoder", config);
-        let decoder = BartDecoder::new(p / "decoder", config);
+        let encoder = match parts {
+            BartModelParts::Full | BartModelParts::EncoderOnly => {
+                Some(BartEncoder::new(p / "encoder", config))
+            }
+            BartModelParts::DecoderOnly => None,
+        };
+        let decoder = match parts {
+            BartModelParts::Full | BartModelParts::DecoderOnly => {
+                Some(BartDecoder::new(p / "decoder", config))
+            }
+            BartModelParts::EncoderOnly => None,
+        };
 
         BartModel {
             encoder,
@@ -453,6 +507,7 @@ impl BartModel {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -508,7 +563,7 @@ impl BartModel {
             decoder_input_ids.unwrap_or_else(|| calc_decoder_input_ids.as_ref().unwrap());
 
         let calc_encoder_output = if encoder_output.is_none() {
-            Some(self.encoder.forward_t(
+            Some(self.get_encoder().forward_t(
                 input_ids.unwrap(),
                 attention_mask,
                 &self.embeddings,
@@ -531,25 +586,58 @@ impl BartModel {
 
         let encoder_output = encoder_output.unwrap_or_else(|| calc_hidden_states.as_ref().unwrap());
 
-        let decoder_output = self.decoder.forward_t(
-            decoder_input_ids,
-            encoder_output,
-            attention_mask,
-            decoder_attention_mask,
-            &self.embeddings,
-            layer_states,
-            train,
-        );
+        let decoder_output = self
+            .decoder
+            .as_ref()
+            .expect("The decoder of the model was not created, the forward pass requires a decoder")
+            .forward_t(
+                decoder_input_ids,
+                encoder_output,
+                attention_mask,
+                decoder_attention_mask,
+                &self.embeddings,
+                layer_states,
+                train,
+            );
         BartModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
         }
     }
+
+    /// Encodes the input ids, returning the last hidden state of the encoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *source_sequence_length*)
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *source_sequence_length*). Positions with a mask with value 0 will be masked.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *source_sequence_length*, *hidden_size*) representing the activations of the last encoder hidden state
+    pub fn encode(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        self.get_encoder()
+            .forward_t(input_ids, attention_mask, &self.embeddings, train)
+            .hidden_state
+    }
+
+    fn get_encoder(&self) -> &BartEncoder {
+        self.encoder.as_ref().expect(
+            "The encoder of the model was not created, the encoder hidden states must be provided",
+        )
+    }
 }
 
 /// # BART Model for conditional generation
@@ -614,6 +702,7 @@ impl BartForConditionalGeneration {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -676,15 +765,7 @@ impl BartForConditionalGeneration {
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
-        self.base_model
-            .encoder
-            .forward_t(
-                input_ids,
-                attention_mask,
-                &self.base_model.embeddings,
-                false,
-            )
-            .hidden_state
+        self.base_model.encode(input_ids, attention_mask, false)
     }
 }
 
@@ -815,6 +896,7 @@ impl BartForSequenceClassification {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -888,6 +970,7 @@ impl BartForSequenceClassification {
             cache: None,
             all_decoder_hidden_states: base_model_output.all_decoder_hidden_states,
             all_decoder_attentions: base_model_output.all_decoder_attentions,
+            all_cross_attentions: base_model_output.all_cross_attentions,
             all_encoder_hidden_states: base_model_output.all_encoder_hidden_states,
             all_encoder_attentions: base_model_output.all_encoder_attentions,
         }
@@ -1013,6 +1096,8 @@ pub struct BartModelOutput {
     pub all_decoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the decoder
     pub all_decoder_attentions: Option<Vec<Tensor>>,
+    /// Cross-attention weights (attention to the encoder hidden states) for all layers of the decoder
+    pub all_cross_attentions: Option<Vec<Tensor>>,
     /// Hidden states for all layers of the encoder
     pub all_encoder_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all layers of the encoder
@@ -1022,7 +1107,7 @@ pub struct BartModelOutput {
 /// # Language generation model based on the Bart architecture
 pub struct BartGenerator {
     model: BartForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -1074,6 +1159,7 @@ impl BartGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<BartGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let merges_path = generate_config
             .merges_resource
@@ -1099,15 +1185,20 @@ impl BartGenerator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<BartGenerator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
-        let config = BartConfig::from_file(config_path);
+        let mut config = BartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = BartForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -1242,7 +1333,7 @@ impl PrivateLanguageGenerator<BartForConditionalGeneration, RobertaVocab, Robert
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -1259,8 +1350,6 @@ impl PrivateLanguageGenerator<BartForConditionalGeneration, RobertaVocab, Robert
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self
@@ -1268,17 +1357,11 @@ impl PrivateLanguageGenerator<BartForConditionalGeneration, RobertaVocab, Robert
                 .convert_tokens_to_ids(&[RobertaVocab::unknown_value()])[0],
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
@@ -127,6 +127,7 @@ impl DecoderLayer {
     ) -> (
         Tensor,
         Option<Tensor>,
+        Option<Tensor>,
         (Option<LayerState>, Option<LayerState>),
     ) {
         let (output, attention_weights, new_self_layer_states) =
@@ -135,13 +136,14 @@ impl DecoderLayer {
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
         let output = output.apply(&self.self_attention_layer_norm);
 
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
         let output1 = output1.apply(&self.encoder_attention_layer_norm);
         let output2 = (self.activation.get_fn())(&output1.apply(&self.fc1));
@@ -153,6 +155,7 @@ impl DecoderLayer {
         (
             output2.apply(&self.final_layer_norm),
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -291,6 +294,11 @@ impl BartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -303,6 +311,7 @@ impl BartDecoder {
             };
 
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
 
         for (layer_idx, layer) in self.layers.iter().enumerate() {
             let layer_state = match &next_decoder_cache {
@@ -319,14 +328,18 @@ impl BartDecoder {
             );
             hidden_state = temp.0;
             attention_weights = temp.1;
+            cross_attention_weights = temp.2;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy());
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
@@ -336,6 +349,7 @@ impl BartDecoder {
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
         }
     }
 }
@@ -352,4 +366,6 @@ pub struct BartDecoderOutput {
     pub all_hidden_states: Option<Vec<Tensor>>,
     /// Attention weights for all intermediate layers
     pub all_attentions: Option<Vec<Tensor>>,
+    /// Cross-attention weights (attention to the encoder hidden states) for all intermediate layers
+    pub all_cross_attentions: Option<Vec<Tensor>>,
 }
@@ -65,8 +65,8 @@ mod encoder;
 pub use attention::LayerState;
 pub use bart_model::{
     BartConfig, BartConfigResources, BartForConditionalGeneration, BartForSequenceClassification,
-    BartGenerator, BartMergesResources, BartModel, BartModelOutput, BartModelResources,
-    BartVocabResources,
+    BartGenerator, BartMergesResources, BartModel, BartModelOutput, BartModelParts,
+    BartModelResources, BartVocabResources,
 };
 
 pub(crate) use attention::BartAttention;
@@ -16,6 +16,8 @@ use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::get_shape_and_device_from_ids_embeddings_pair;
 use crate::common::linear::{linear_no_bias, LinearNoBias};
+use crate::common::loss::token_cross_entropy;
+use crate::common::weights::estimated_memory_bytes;
 use crate::{
     bert::embeddings::{BertEmbedding, BertEmbeddings},
     common::activations::TensorFunction,
@@ -153,12 +155,39 @@ pub struct BertConfig {
     pub output_attentions: Option<bool>,
     pub output_hidden_states: Option<bool>,
     pub is_decoder: Option<bool>,
+    pub num_labels: Option<i64>,
     pub id2label: Option<HashMap<i64, String>>,
     pub label2id: Option<HashMap<String, i64>>,
 }
 
 impl Config for BertConfig {}
 
+impl BertConfig {
+    /// Returns the number of parameters of a `BertModel` (including its pooler) built from this configuration,
+    /// excluding the task-specific heads.
+    pub fn num_parameters(&self) -> i64 {
+        let hidden_size = self.hidden_size;
+        let embeddings =
+            (self.vocab_size + self.max_position_embeddings + self.type_vocab_size + 2)
+                * hidden_size;
+        let attention = 4 * (hidden_size * hidden_size + hidden_size) + 2 * hidden_size;
+        let feed_forward =
+            2 * hidden_size * self.intermediate_size + self.intermediate_size + 3 * hidden_size;
+        let pooler = hidden_size * hidden_size + hidden_size;
+        embeddings + self.num_hidden_layers * (attention + feed_forward) + pooler
+    }
+
+    /// Estimates the memory in bytes required to load the weights of a `BertModel` built from this configuration
+    /// (see `rust_bert::weights::estimated_memory_bytes`).
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Data type of the parameters (e.g. `Kind::Float` or `Kind::Half`)
+    pub fn estimated_memory_bytes(&self, kind: Kind) -> i64 {
+        estimated_memory_bytes(self.num_parameters(), kind)
+    }
+}
+
 impl Default for BertConfig {
     fn default() -> Self {
         BertConfig {
@@ -176,6 +205,7 @@ impl Default for BertConfig {
             output_attentions: None,
             output_hidden_states: None,
             is_decoder: None,
+            num_labels: None,
             id2label: None,
             label2id: None,
         }
@@ -649,6 +679,61 @@ impl BertForMaskedLM {
             all_attentions: base_model_output.all_attentions,
         }
     }
+
+    /// Computes the masked language modeling loss for labeled inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *sequence_length*)
+    /// * `attention_mask` - Optional mask of shape (*batch size*, *sequence_length*). Masked position have value 0, non-masked value 1. If None set to 1
+    /// * `labels` - Target token ids of shape (*batch size*, *sequence_length*). Positions with a label of -100 are ignored in the loss computation.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` scalar mean cross-entropy over the non-ignored positions
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_bert::bert::{BertForMaskedLM, BertConfig};
+    /// # use tch::{nn, Device, Tensor, Kind};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = BertConfig::from_file(config_path);
+    /// # let bert_model = BertForMaskedLM::new(&vs.root(), &config);
+    /// let (batch_size, sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, sequence_length], (Kind::Int64, device));
+    /// let mask = Tensor::ones(&[batch_size, sequence_length], (Kind::Int64, device));
+    /// let labels = Tensor::rand(&[batch_size, sequence_length], (Kind::Int64, device));
+    ///
+    /// let loss = bert_model.forward_loss(&input_tensor, Some(&mask), &labels, true);
+    /// loss.backward();
+    /// ```
+    pub fn forward_loss(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        labels: &Tensor,
+        train: bool,
+    ) -> Tensor {
+        let prediction_scores = self
+            .forward_t(
+                Some(input_ids),
+                attention_mask,
+                None,
+                None,
+                None,
+                None,
+                None,
+                train,
+            )
+            .prediction_scores;
+        token_cross_entropy(&prediction_scores, labels)
+    }
 }
 
 /// # BERT for sequence classification
@@ -698,12 +783,13 @@ impl BertForSequenceClassification {
         let num_labels = config
             .id2label
             .as_ref()
+            .map(|id2label| id2label.len() as i64)
+            .or(config.num_labels)
             .ok_or_else(|| {
                 RustBertError::InvalidConfigurationError(
                     "num_labels not provided in configuration".to_string(),
                 )
-            })?
-            .len() as i64;
+            })?;
         let classifier = nn::linear(
             p / "classifier",
             config.hidden_size,
@@ -0,0 +1,28 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tch::{Reduction, Tensor};
+
+/// Label value of the positions excluded from the loss computation (follows the Python Transformers convention)
+pub(crate) const IGNORE_LABEL_INDEX: i64 = -100;
+
+/// Mean token-level cross-entropy between `logits` of shape (*batch size*, *sequence_length*, *vocab_size*)
+/// and `labels` of shape (*batch size*, *sequence_length*). Positions labeled with `IGNORE_LABEL_INDEX` do not contribute.
+pub(crate) fn token_cross_entropy(logits: &Tensor, labels: &Tensor) -> Tensor {
+    let vocab_size = *logits.size().last().unwrap();
+    logits.view([-1, vocab_size]).cross_entropy_loss::<Tensor>(
+        &labels.view([-1]),
+        None,
+        Reduction::Mean,
+        IGNORE_LABEL_INDEX,
+        0.0,
+    )
+}
@@ -5,8 +5,11 @@ pub(crate) mod embeddings;
 pub mod error;
 pub(crate) mod kind;
 pub(crate) mod linear;
+pub(crate) mod loss;
 pub mod resources;
 pub(crate) mod summary;
+pub(crate) mod tensor_serde;
+pub mod weights;
 
 pub use activations::Activation;
 pub use config::Config;
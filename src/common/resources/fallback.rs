@@ -0,0 +1,86 @@
+use super::*;
+use crate::common::error::RustBertError;
+use crate::resources::RemoteResource;
+use std::path::PathBuf;
+
+/// # Remote resource with fallback locations
+/// Ordered list of remote resources (e.g. a primary model location followed by its mirrors). The resources are
+/// tried in order until one of them can be downloaded.
+#[derive(PartialEq, Eq, Clone)]
+pub struct FallbackResource {
+    /// Remote resources tried in order
+    pub resources: Vec<RemoteResource>,
+}
+
+impl FallbackResource {
+    /// Creates a new FallbackResource from an ordered list of remote resources. Note that this does not
+    /// download the resource (only declares the remote and local locations)
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - `Vec<RemoteResource>` Remote resources tried in order
+    ///
+    /// # Returns
+    ///
+    /// * `FallbackResource` FallbackResource object
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::resources::{FallbackResource, RemoteResource};
+    /// let config_resource = FallbackResource::new(vec![
+    ///     RemoteResource::new("http://config_json_location", "configs"),
+    ///     RemoteResource::new("http://config_json_mirror_location", "configs"),
+    /// ]);
+    /// ```
+    pub fn new(resources: Vec<RemoteResource>) -> FallbackResource {
+        FallbackResource { resources }
+    }
+}
+
+impl ResourceProvider for FallbackResource {
+    /// Gets the local path of the first remote resource that can be downloaded and cached.
+    /// The error of the last resource is returned if none of them can be downloaded.
+    ///
+    /// # Returns
+    ///
+    /// * `PathBuf` pointing to the resource file
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::resources::{FallbackResource, RemoteResource, ResourceProvider};
+    /// let config_resource = FallbackResource::new(vec![
+    ///     RemoteResource::new("http://config_json_location", "configs"),
+    ///     RemoteResource::new("http://config_json_mirror_location", "configs"),
+    /// ]);
+    /// let config_path = config_resource.get_local_path();
+    /// ```
+    fn get_local_path(&self) -> Result<PathBuf, RustBertError> {
+        let mut last_error = RustBertError::IOError(
+            "No remote resource provided for the fallback resource".to_string(),
+        );
+        for resource in &self.resources {
+            match resource.get_local_path() {
+                Ok(local_path) => return Ok(local_path),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Checks that at least one of the remote resources is reachable, returning the error of the last
+    /// resource otherwise. The resources are not downloaded.
+    fn validate(&self) -> Result<(), RustBertError> {
+        let mut last_error = RustBertError::IOError(
+            "No remote resource provided for the fallback resource".to_string(),
+        );
+        for resource in &self.resources {
+            match resource.validate() {
+                Ok(()) => return Ok(()),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+}
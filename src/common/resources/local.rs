@@ -29,6 +29,18 @@ impl ResourceProvider for LocalResource {
     fn get_local_path(&self) -> Result<PathBuf, RustBertError> {
         Ok(self.local_path.clone())
     }
+
+    /// Checks that the local resource file exists.
+    fn validate(&self) -> Result<(), RustBertError> {
+        if self.local_path.is_file() {
+            Ok(())
+        } else {
+            Err(RustBertError::IOError(format!(
+                "Local resource {} does not exist",
+                self.local_path.display()
+            )))
+        }
+    }
 }
 
 impl From<PathBuf> for LocalResource {
@@ -37,7 +49,7 @@ impl From<PathBuf> for LocalResource {
     }
 }
 
-impl From<PathBuf> for Box<dyn ResourceProvider + Send> {
+impl From<PathBuf> for Box<dyn ResourceProvider + Send + Sync> {
     fn from(local_path: PathBuf) -> Self {
         Box::new(LocalResource { local_path })
     }
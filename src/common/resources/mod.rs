@@ -8,11 +8,12 @@
 //! - (optional) merges files for BPE-based tokenizers
 //!
 //! These are expected in the pipelines configurations or are used as utilities to reference to the
-//! resource location. Two types of resources are pre-defined:
+//! resource location. Three types of resources are pre-defined:
 //! - LocalResource: points to a local file
 //! - RemoteResource: points to a remote file via a URL
+//! - FallbackResource: points to an ordered list of remote files, tried until one can be downloaded
 //!
-//! For both types of resources, the local location of the file can be retrieved using
+//! For all types of resources, the local location of the file can be retrieved using
 //! `get_local_path`, allowing to reference the resource file location regardless if it is a remote
 //! or local resource. Default implementations for a number of `RemoteResources` are available as
 //! pre-trained models in each model module.
@@ -42,9 +43,34 @@ pub trait ResourceProvider {
     /// let config_path = config_resource.get_local_path();
     /// ```
     fn get_local_path(&self) -> Result<PathBuf, RustBertError>;
+
+    /// Checks that the resource is available, without downloading it when possible.
+    /// The default implementation resolves the local path of the resource.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RustBertError>` - an error describing why the resource is not available
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::resources::{LocalResource, ResourceProvider};
+    /// use std::path::PathBuf;
+    /// let config_resource = LocalResource {
+    ///     local_path: PathBuf::from("path/to/config.json"),
+    /// };
+    /// assert!(config_resource.validate().is_err());
+    /// ```
+    fn validate(&self) -> Result<(), RustBertError> {
+        self.get_local_path().map(|_| ())
+    }
 }
 
 #[cfg(feature = "remote")]
 mod remote;
 #[cfg(feature = "remote")]
 pub use remote::RemoteResource;
+#[cfg(feature = "remote")]
+mod fallback;
+#[cfg(feature = "remote")]
+pub use fallback::FallbackResource;
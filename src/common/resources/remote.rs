@@ -93,6 +93,29 @@ impl ResourceProvider for RemoteResource {
             .cached_path_with_options(&self.url, &Options::default().subdir(&self.cache_subdir))?;
         Ok(cached_path)
     }
+
+    /// Checks that the remote resource is reachable by sending a HEAD request to its URL.
+    /// The resource is not downloaded.
+    fn validate(&self) -> Result<(), RustBertError> {
+        let response = reqwest::blocking::Client::new()
+            .head(&self.url)
+            .send()
+            .map_err(|error| {
+                RustBertError::IOError(format!(
+                    "Remote resource {} is not reachable: {}",
+                    self.url, error
+                ))
+            })?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(RustBertError::IOError(format!(
+                "Remote resource {} is not available (HTTP status {})",
+                self.url,
+                response.status()
+            )))
+        }
+    }
 }
 
 lazy_static! {
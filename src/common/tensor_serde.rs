@@ -0,0 +1,166 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Tensor serialization
+//! Serde support for the tensors held by serializable states (e.g. a paused beam search), used as
+//! `#[serde(with = "crate::common::tensor_serde")]` on fields of type `Tensor`, `Option<Tensor>`, `Vec<Tensor>`
+//! or nested combinations of these. Tensors are serialized with their kind, shape and raw data and are
+//! deserialized on the CPU.
+
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tch::{Device, Kind, Tensor};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum TensorKind {
+    Uint8,
+    Int8,
+    Int16,
+    Int,
+    Int64,
+    Half,
+    Float,
+    Double,
+    Bool,
+    BFloat16,
+}
+
+impl TensorKind {
+    fn from_kind(kind: Kind) -> Result<Self, String> {
+        Ok(match kind {
+            Kind::Uint8 => TensorKind::Uint8,
+            Kind::Int8 => TensorKind::Int8,
+            Kind::Int16 => TensorKind::Int16,
+            Kind::Int => TensorKind::Int,
+            Kind::Int64 => TensorKind::Int64,
+            Kind::Half => TensorKind::Half,
+            Kind::Float => TensorKind::Float,
+            Kind::Double => TensorKind::Double,
+            Kind::Bool => TensorKind::Bool,
+            Kind::BFloat16 => TensorKind::BFloat16,
+            _ => return Err(format!("Type not supported: cannot serialize {:?}", kind)),
+        })
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            TensorKind::Uint8 => Kind::Uint8,
+            TensorKind::Int8 => Kind::Int8,
+            TensorKind::Int16 => Kind::Int16,
+            TensorKind::Int => Kind::Int,
+            TensorKind::Int64 => Kind::Int64,
+            TensorKind::Half => Kind::Half,
+            TensorKind::Float => Kind::Float,
+            TensorKind::Double => Kind::Double,
+            TensorKind::Bool => Kind::Bool,
+            TensorKind::BFloat16 => Kind::BFloat16,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// Kind, shape and raw data of a tensor
+pub(crate) struct TensorData {
+    kind: TensorKind,
+    shape: Vec<i64>,
+    data: Vec<u8>,
+}
+
+/// Values holding tensors, converted to serializable data
+pub(crate) trait SerializeTensors: Sized {
+    type Data: Serialize + DeserializeOwned;
+
+    fn to_data(&self) -> Result<Self::Data, String>;
+
+    fn from_data(data: Self::Data) -> Result<Self, String>;
+}
+
+impl SerializeTensors for Tensor {
+    // Undefined tensors (e.g. placeholders before a first generation step) have no data
+    type Data = Option<TensorData>;
+
+    fn to_data(&self) -> Result<Self::Data, String> {
+        if !self.defined() {
+            return Ok(None);
+        }
+        let kind = TensorKind::from_kind(self.kind())?;
+        let tensor = self.to_device(Device::Cpu).contiguous();
+        let numel = tensor.numel();
+        let mut data = vec![0u8; numel * self.kind().elt_size_in_bytes()];
+        tensor.copy_data_u8(&mut data, numel);
+        Ok(Some(TensorData {
+            kind,
+            shape: tensor.size(),
+            data,
+        }))
+    }
+
+    fn from_data(data: Self::Data) -> Result<Self, String> {
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(Tensor::new()),
+        };
+        let kind = data.kind.kind();
+        let numel = data.shape.iter().product::<i64>();
+        if numel < 0 || numel as usize * kind.elt_size_in_bytes() != data.data.len() {
+            return Err(format!(
+                "Tensor data of {} bytes does not match the shape {:?} and type {:?}",
+                data.data.len(),
+                data.shape,
+                kind
+            ));
+        }
+        Ok(Tensor::of_data_size(&data.data, &data.shape, kind))
+    }
+}
+
+impl<T: SerializeTensors> SerializeTensors for Option<T> {
+    type Data = Option<T::Data>;
+
+    fn to_data(&self) -> Result<Self::Data, String> {
+        self.as_ref().map(T::to_data).transpose()
+    }
+
+    fn from_data(data: Self::Data) -> Result<Self, String> {
+        data.map(T::from_data).transpose()
+    }
+}
+
+impl<T: SerializeTensors> SerializeTensors for Vec<T> {
+    type Data = Vec<T::Data>;
+
+    fn to_data(&self) -> Result<Self::Data, String> {
+        self.iter().map(T::to_data).collect()
+    }
+
+    fn from_data(data: Self::Data) -> Result<Self, String> {
+        data.into_iter().map(T::from_data).collect()
+    }
+}
+
+pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: SerializeTensors,
+    S: Serializer,
+{
+    value
+        .to_data()
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: SerializeTensors,
+    D: Deserializer<'de>,
+{
+    T::from_data(T::Data::deserialize(deserializer)?).map_err(D::Error::custom)
+}
@@ -0,0 +1,139 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Weights loading utilities
+//! Loading of model weights whose parameter names differ from the names expected by the models
+//! (e.g. checkpoints converted with a different export tool).
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::bert::{BertConfig, BertForMaskedLM};
+//! use rust_bert::weights::load_weights;
+//! use rust_bert::Config;
+//! use std::collections::HashMap;
+//! use tch::{nn, Device};
+//!
+//! let config = BertConfig::from_file("path/to/config.json");
+//! let mut vs = nn::VarStore::new(Device::Cpu);
+//! let _model = BertForMaskedLM::new(vs.root(), &config);
+//!
+//! let name_mapping = HashMap::from([(
+//!     "bert.embeddings.word_embeddings.embedding".to_string(),
+//!     "bert.embeddings.word_embeddings.weight".to_string(),
+//! )]);
+//! load_weights(&mut vs, "path/to/model.ot", Some(&name_mapping))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The memory required by the weights of a model can be estimated before loading it from its configuration,
+//! e.g. to select a device with enough memory available.
+//!
+//! ```no_run
+//! use rust_bert::bert::BertConfig;
+//! use tch::Kind;
+//!
+//! let config = BertConfig::default();
+//! let required_bytes = config.estimated_memory_bytes(Kind::Half);
+//! ```
+
+use crate::RustBertError;
+use std::collections::HashMap;
+use std::path::Path;
+use tch::nn::VarStore;
+use tch::{no_grad, Kind, Tensor};
+
+/// Loads the weights stored at `weights_path` into the variables of a `VarStore`, renaming the
+/// stored parameters with an optional name mapping (from stored name to expected name) first.
+/// Stored parameters that are not mapped and not expected by the model are ignored with a warning.
+///
+/// # Arguments
+///
+/// * `var_store` - `VarStore` holding the variables of the model
+/// * `weights_path` - Path to the weights file (`.ot` or `.npz`)
+/// * `name_mapping` - Optional mapping from the parameter names of the weights file to the names expected by the model
+///
+/// # Returns
+/// * `Result<(), RustBertError>` Error if an expected variable is missing after renaming, or if its shape does not match the stored parameter
+pub fn load_weights<P: AsRef<Path>>(
+    var_store: &mut VarStore,
+    weights_path: P,
+    name_mapping: Option<&HashMap<String, String>>,
+) -> Result<(), RustBertError> {
+    let weights_path = weights_path.as_ref();
+    let stored_tensors = match weights_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("npz") => Tensor::read_npz(weights_path)?,
+        _ => Tensor::load_multi(weights_path)?,
+    };
+    let mut stored_tensors = stored_tensors
+        .into_iter()
+        .map(|(name, tensor)| {
+            let name = name_mapping
+                .and_then(|name_mapping| name_mapping.get(&name).cloned())
+                .unwrap_or(name);
+            (name, tensor)
+        })
+        .collect::<HashMap<String, Tensor>>();
+
+    let mut variables = var_store.variables().into_iter().collect::<Vec<_>>();
+    variables.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+    for (name, mut variable) in variables {
+        let stored_tensor = stored_tensors.remove(&name).ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Cannot find the variable {} in the weights file {} after renaming",
+                name,
+                weights_path.display()
+            ))
+        })?;
+        if stored_tensor.size() != variable.size() {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The shape of the variable {} ({:?}) does not match the shape of the stored parameter ({:?})",
+                name,
+                variable.size(),
+                stored_tensor.size()
+            )));
+        }
+        no_grad(|| variable.f_copy_(&stored_tensor))?;
+    }
+
+    let mut unused_names = stored_tensors.into_keys().collect::<Vec<String>>();
+    if !unused_names.is_empty() {
+        unused_names.sort();
+        eprintln!(
+            "Warning: the following parameters of {} are not used by the model: {}",
+            weights_path.display(),
+            unused_names.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Overhead factor applied to the size of the parameters when estimating the memory required by a model,
+/// accounting for the allocator fragmentation and the buffers allocated when loading the weights.
+pub const MEMORY_OVERHEAD_FACTOR: f64 = 1.2;
+
+/// Estimates the memory required to load the weights of a model, as the number of parameters multiplied
+/// by the size of the data type and the `MEMORY_OVERHEAD_FACTOR`. The activations and cache allocated
+/// at inference time are not included.
+///
+/// # Arguments
+///
+/// * `num_parameters` - Number of parameters of the model
+/// * `kind` - Data type of the parameters (e.g. `Kind::Float` or `Kind::Half`)
+///
+/// # Returns
+/// * `i64` Estimated memory in bytes
+pub fn estimated_memory_bytes(num_parameters: i64, kind: Kind) -> i64 {
+    (num_parameters as f64 * kind.elt_size_in_bytes() as f64 * MEMORY_OVERHEAD_FACTOR) as i64
+}
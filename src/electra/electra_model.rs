@@ -191,6 +191,7 @@ impl ElectraModel {
             output_attentions: config.output_attentions,
             output_hidden_states: config.output_hidden_states,
             is_decoder: None,
+            num_labels: None,
             id2label: config.id2label.clone(),
             label2id: config.label2id.clone(),
         };
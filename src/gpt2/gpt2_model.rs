@@ -15,6 +15,7 @@
 use crate::common::activations::Activation;
 use crate::common::dropout::Dropout;
 use crate::common::embeddings::process_ids_embeddings_pair;
+use crate::common::weights::estimated_memory_bytes;
 use crate::gpt2::transformer::Block;
 use crate::pipelines::common::{ModelType, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
@@ -202,6 +203,29 @@ pub struct Gpt2Config {
 
 impl Config for Gpt2Config {}
 
+impl Gpt2Config {
+    /// Returns the number of parameters of a `Gpt2Model` built from this configuration, including the stored
+    /// attention masks and excluding the task-specific heads (the language model head shares the token embeddings).
+    pub fn num_parameters(&self) -> i64 {
+        let n_embd = self.n_embd;
+        let embeddings = (self.vocab_size + self.n_positions) * n_embd;
+        let attention = 4 * n_embd * n_embd + 4 * n_embd + self.n_ctx * self.n_ctx;
+        let mlp = 8 * n_embd * n_embd + 5 * n_embd;
+        let layer_norms = 4 * n_embd;
+        embeddings + self.n_layer * (attention + mlp + layer_norms) + 2 * n_embd
+    }
+
+    /// Estimates the memory in bytes required to load the weights of a `Gpt2Model` built from this configuration
+    /// (see `rust_bert::weights::estimated_memory_bytes`).
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Data type of the parameters (e.g. `Kind::Float` or `Kind::Half`)
+    pub fn estimated_memory_bytes(&self, kind: Kind) -> i64 {
+        estimated_memory_bytes(self.num_parameters(), kind)
+    }
+}
+
 impl Default for Gpt2Config {
     fn default() -> Self {
         Gpt2Config {
@@ -707,6 +731,7 @@ impl GPT2Generator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<GPT2Generator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let merges_path = generate_config
             .merges_resource
@@ -734,14 +759,18 @@ impl GPT2Generator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<GPT2Generator, RustBertError> {
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = Gpt2Config::from_file(config_path);
+        let mut config = Gpt2Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = GPT2LMHeadModel::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -807,6 +836,10 @@ impl PrivateLanguageGenerator<GPT2LMHeadModel, Gpt2Vocab, Gpt2Tokenizer> for GPT
         self.max_position_embeddings
     }
 
+    fn supports_kv_cache_window(&self) -> bool {
+        true
+    }
+
     fn prepare_inputs_for_generation<'a>(
         &self,
         input_ids: Tensor,
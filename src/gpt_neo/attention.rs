@@ -13,16 +13,19 @@
 use crate::common::dropout::Dropout;
 use crate::gpt_neo::gpt_neo_model::AttentionLayerType;
 use crate::gpt_neo::GptNeoConfig;
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use tch::{nn, Kind, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for GPT-Neo attention layers
 /// Stores the cached value of key and value
 pub struct LayerState {
     /// Cached keys
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_key: Tensor,
     /// Cached values
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_value: Option<Tensor>,
 }
 
@@ -682,6 +682,7 @@ impl GptNeoGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<GptNeoGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let merges_path = generate_config
             .merges_resource
@@ -709,13 +710,17 @@ impl GptNeoGenerator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<GptNeoGenerator, RustBertError> {
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
-        let config = GptNeoConfig::from_file(config_path);
+        let mut config = GptNeoConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = GptNeoForCausalLM::new(var_store.root(), &config)?;
         var_store.load(weights_path)?;
 
@@ -235,7 +235,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -271,7 +271,7 @@
 //!
 //! let conversation_id =
 //!     conversation_manager.create("Going to the movies tonight - any suggestions?");
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -302,7 +302,7 @@
 //!
 //! let prefix = None; // Optional prefix to append prompts with, will be excluded from the generated output
 //!
-//! let output = model.generate(&[input_context_1, input_context_2], prefix);
+//! let output = model.generate(&[input_context_1, input_context_2], prefix)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -418,7 +418,7 @@
 //!     "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
 //!     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
 //! ];
-//! let output = sentiment_model.predict(&input);
+//! let output = sentiment_model.predict(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -722,4 +722,5 @@ pub mod xlnet;
 
 pub use common::error::RustBertError;
 pub use common::resources;
+pub use common::weights;
 pub use common::{Activation, Config};
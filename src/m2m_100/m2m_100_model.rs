@@ -14,7 +14,7 @@ use crate::m2m_100::decoder::M2M100Decoder;
 use crate::m2m_100::encoder::M2M100Encoder;
 use crate::m2m_100::LayerState;
 use crate::mbart::{MBartConfig, MBartModelOutput};
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
@@ -26,6 +26,7 @@ use crate::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{M2M100Tokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::{M2M100Vocab, Vocab};
 use std::borrow::Borrow;
+use std::sync::Arc;
 use tch::nn::{embedding, EmbeddingConfig};
 use tch::{nn, Kind, Tensor};
 
@@ -215,6 +216,7 @@ impl M2M100Model {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -313,6 +315,7 @@ impl M2M100Model {
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
         }
@@ -384,6 +387,7 @@ impl M2M100ForConditionalGeneration {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -564,7 +568,7 @@ impl LMHeadModel for M2M100ForConditionalGeneration {
 /// # Language generation model based on the M2M100 architecture
 pub struct M2M100Generator {
     model: M2M100ForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -616,6 +620,7 @@ impl M2M100Generator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<M2M100Generator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let merges_path = generate_config
             .merges_resource
@@ -641,16 +646,21 @@ impl M2M100Generator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<M2M100Generator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = M2M100Config::from_file(config_path);
+        let mut config = M2M100Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = M2M100ForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -783,7 +793,7 @@ impl PrivateLanguageGenerator<M2M100ForConditionalGeneration, M2M100Vocab, M2M10
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -800,8 +810,6 @@ impl PrivateLanguageGenerator<M2M100ForConditionalGeneration, M2M100Vocab, M2M10
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self
@@ -809,17 +817,11 @@ impl PrivateLanguageGenerator<M2M100ForConditionalGeneration, M2M100Vocab, M2M10
                 .convert_tokens_to_ids(&[M2M100Vocab::unknown_value()])[0],
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
@@ -12,7 +12,7 @@
 // limitations under the License.
 
 use crate::bart::{BartConfig, BartModel, BartModelOutput, LayerState};
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
@@ -24,6 +24,7 @@ use crate::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{MarianTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::MarianVocab;
 use std::borrow::Borrow;
+use std::sync::Arc;
 use tch::nn::Init;
 use tch::{nn, Kind, Tensor};
 
@@ -595,6 +596,7 @@ impl MarianForConditionalGeneration {
     ///   - `cache` - `(Option<Tensor>, Option<Vec<&LayerState, &LayerState>>)` of length *n_layer* containing the encoder padding mask and past keys and values for both the self attention and the encoder cross attention of each layer of the decoder.
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -661,15 +663,7 @@ impl MarianForConditionalGeneration {
     }
 
     pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
-        self.base_model
-            .encoder
-            .forward_t(
-                input_ids,
-                attention_mask,
-                &self.base_model.embeddings,
-                false,
-            )
-            .hidden_state
+        self.base_model.encode(input_ids, attention_mask, false)
     }
 }
 
@@ -784,7 +778,7 @@ impl LMHeadModel for MarianForConditionalGeneration {
 /// # Language generation model based on the Marian architecture for machine translation
 pub struct MarianGenerator {
     model: MarianForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -836,6 +830,7 @@ impl MarianGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<MarianGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let sentence_piece_path = generate_config
             .merges_resource
@@ -862,16 +857,21 @@ impl MarianGenerator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<MarianGenerator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = BartConfig::from_file(config_path);
+        let mut config = BartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = MarianForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -1010,7 +1010,7 @@ impl PrivateLanguageGenerator<MarianForConditionalGeneration, MarianVocab, Maria
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -1027,24 +1027,16 @@ impl PrivateLanguageGenerator<MarianForConditionalGeneration, MarianVocab, Maria
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self._get_tokenizer().get_unk_id(),
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
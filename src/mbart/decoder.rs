@@ -122,6 +122,7 @@ impl MBartDecoderLayer {
     ) -> (
         Tensor,
         Option<Tensor>,
+        Option<Tensor>,
         (Option<LayerState>, Option<LayerState>),
     ) {
         let output = x.apply(&self.self_attention_layer_norm);
@@ -136,13 +137,14 @@ impl MBartDecoderLayer {
         let output: Tensor = output.apply_t(&self.dropout, train) + x;
 
         let output1 = output.apply(&self.encoder_attention_layer_norm);
-        let (output1, _, new_encoder_layer_states) = self.encoder_attention.forward_t(
-            &output1,
-            Some(encoder_hidden_states),
-            encoder_attention_mask,
-            layer_states.1,
-            train,
-        );
+        let (output1, cross_attention_weights, new_encoder_layer_states) =
+            self.encoder_attention.forward_t(
+                &output1,
+                Some(encoder_hidden_states),
+                encoder_attention_mask,
+                layer_states.1,
+                train,
+            );
         let output1: Tensor = output1.apply_t(&self.dropout, train) + output;
 
         let output2 = output1.apply(&self.final_layer_norm);
@@ -155,6 +157,7 @@ impl MBartDecoderLayer {
         (
             output2,
             attention_weights,
+            cross_attention_weights,
             (new_self_layer_states, new_encoder_layer_states),
         )
     }
@@ -276,6 +279,11 @@ impl MBartDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -288,6 +296,7 @@ impl MBartDecoder {
             };
 
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
 
         for (layer_idx, layer) in self.layers.iter().enumerate() {
             let layer_state = match &next_decoder_cache {
@@ -304,14 +313,18 @@ impl MBartDecoder {
             );
             hidden_state = temp.0;
             attention_weights = temp.1;
+            cross_attention_weights = temp.2;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy());
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
@@ -321,6 +334,7 @@ impl MBartDecoder {
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
         }
     }
 }
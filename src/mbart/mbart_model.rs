@@ -15,7 +15,7 @@ use crate::common::dropout::Dropout;
 use crate::mbart::decoder::MBartDecoder;
 use crate::mbart::encoder::MBartEncoder;
 use crate::mbart::LayerState;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
@@ -29,6 +29,7 @@ use rust_tokenizers::vocab::{MBart50Vocab, Vocab};
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tch::kind::Kind::Int64;
 use tch::nn::{embedding, EmbeddingConfig, Init};
 use tch::{nn, Tensor};
@@ -313,6 +314,7 @@ impl MBartModel {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -407,6 +409,7 @@ impl MBartModel {
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
         }
@@ -487,6 +490,7 @@ impl MBartForConditionalGeneration {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -638,6 +642,7 @@ impl MBartForSequenceClassification {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -711,6 +716,7 @@ impl MBartForSequenceClassification {
             cache: None,
             all_decoder_hidden_states: base_model_output.all_decoder_hidden_states,
             all_decoder_attentions: base_model_output.all_decoder_attentions,
+            all_cross_attentions: base_model_output.all_cross_attentions,
             all_encoder_hidden_states: base_model_output.all_encoder_hidden_states,
             all_encoder_attentions: base_model_output.all_encoder_attentions,
         }
@@ -828,7 +834,7 @@ pub type MBartModelOutput = BartModelOutput;
 /// # Language generation model based on the MBart architecture
 pub struct MBartGenerator {
     model: MBartForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -880,6 +886,7 @@ impl MBartGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<MBartGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
 
         let tokenizer = TokenizerOption::from_file(
@@ -896,16 +903,21 @@ impl MBartGenerator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<MBartGenerator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = MBartConfig::from_file(config_path);
+        let mut config = MBartConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = MBartForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -1038,7 +1050,7 @@ impl PrivateLanguageGenerator<MBartForConditionalGeneration, MBart50Vocab, MBart
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -1055,8 +1067,6 @@ impl PrivateLanguageGenerator<MBartForConditionalGeneration, MBart50Vocab, MBart
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self
@@ -1064,17 +1074,11 @@ impl PrivateLanguageGenerator<MBartForConditionalGeneration, MBart50Vocab, MBart
                 .convert_tokens_to_ids(&[MBart50Vocab::unknown_value()])[0],
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
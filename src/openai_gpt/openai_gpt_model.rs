@@ -18,7 +18,9 @@ use crate::common::linear::{linear_no_bias, LinearNoBias};
 use crate::gpt2::Gpt2Config;
 use crate::openai_gpt::transformer::Block;
 use crate::pipelines::common::{ModelType, TokenizerOption};
-use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
+use crate::pipelines::generation_utils::private_generation_utils::{
+    PreparedInput, PrivateLanguageGenerator,
+};
 use crate::pipelines::generation_utils::{
     Cache, GenerateConfig, LMHeadModel, LMModelOutput, LanguageGenerator,
 };
@@ -469,6 +471,7 @@ impl OpenAIGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<OpenAIGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
         let merges_path = generate_config
             .merges_resource
@@ -496,14 +499,18 @@ impl OpenAIGenerator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<OpenAIGenerator, RustBertError> {
-        generate_config.validate();
+        generate_config.validate()?;
+        generate_config.validate_token_ids(&tokenizer)?;
 
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
         let mut var_store = nn::VarStore::new(device);
-        let config = Gpt2Config::from_file(config_path);
+        let mut config = Gpt2Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = OpenAIGPTLMHeadModel::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -570,6 +577,26 @@ impl PrivateLanguageGenerator<OpenAIGPTLMHeadModel, OpenAiGptVocab, OpenAiGptTok
     fn get_max_positions_embeddings(&self) -> i64 {
         self.max_position_embeddings
     }
+
+    fn prepare_inputs_for_generation<'a>(
+        &self,
+        input_ids: Tensor,
+        _encoder_outputs: Option<&'a Tensor>,
+        past: Cache,
+        attention_mask: Tensor,
+    ) -> PreparedInput<'a> {
+        // The positions of left-padded sequences start after their padding
+        let position_ids = (attention_mask.totype(Int64).cumsum(-1, Int64) - 1)
+            .masked_fill(&attention_mask.eq(0), 1);
+        PreparedInput {
+            prepared_input: Some(input_ids),
+            prepared_attention_mask: Some(attention_mask),
+            prepared_encoder_output: None,
+            prepared_decoder_input: None,
+            prepared_position_ids: Some(position_ids),
+            prepared_past: past,
+        }
+    }
 }
 
 impl LanguageGenerator<OpenAIGPTLMHeadModel, OpenAiGptVocab, OpenAiGptTokenizer>
@@ -136,6 +136,11 @@ impl PegasusDecoder {
         } else {
             None
         };
+        let mut all_cross_attentions: Option<Vec<Tensor>> = if self.output_attentions {
+            Some(Vec::with_capacity(self.layers.len()))
+        } else {
+            None
+        };
         let mut next_decoder_cache: Option<Vec<(Option<LayerState>, Option<LayerState>)>> =
             if self.output_past {
                 if old_layer_states.is_some() {
@@ -148,6 +153,7 @@ impl PegasusDecoder {
             };
 
         let mut attention_weights: Option<Tensor>;
+        let mut cross_attention_weights: Option<Tensor>;
 
         for (layer_idx, layer) in self.layers.iter().enumerate() {
             let layer_state = match &next_decoder_cache {
@@ -164,14 +170,18 @@ impl PegasusDecoder {
             );
             hidden_state = temp.0;
             attention_weights = temp.1;
+            cross_attention_weights = temp.2;
             if let Some(hidden_states) = all_hidden_states.borrow_mut() {
                 hidden_states.push(hidden_state.as_ref().copy());
             };
             if let Some(attentions) = all_attentions.borrow_mut() {
                 attentions.push(std::mem::take(&mut attention_weights.unwrap()));
             };
+            if let Some(cross_attentions) = all_cross_attentions.borrow_mut() {
+                cross_attentions.push(std::mem::take(&mut cross_attention_weights.unwrap()));
+            };
             if let Some(value) = &mut next_decoder_cache {
-                value[layer_idx] = temp.2
+                value[layer_idx] = temp.3
             };
         }
 
@@ -183,6 +193,7 @@ impl PegasusDecoder {
             next_decoder_cache,
             all_hidden_states,
             all_attentions,
+            all_cross_attentions,
         }
     }
 }
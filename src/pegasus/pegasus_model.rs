@@ -16,7 +16,7 @@ use crate::mbart::MBartConfig;
 use crate::pegasus::decoder::PegasusDecoder;
 use crate::pegasus::encoder::PegasusEncoder;
 use crate::pegasus::LayerState;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
@@ -27,6 +27,7 @@ use crate::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{PegasusTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::PegasusVocab;
 use std::borrow::Borrow;
+use std::sync::Arc;
 use tch::nn::{embedding, EmbeddingConfig, Init};
 use tch::{nn, Tensor};
 
@@ -171,6 +172,7 @@ impl PegasusModel {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -255,6 +257,7 @@ impl PegasusModel {
             cache: decoder_output.next_decoder_cache,
             all_decoder_hidden_states: decoder_output.all_hidden_states,
             all_decoder_attentions: decoder_output.all_attentions,
+            all_cross_attentions: decoder_output.all_cross_attentions,
             all_encoder_hidden_states,
             all_encoder_attentions,
         }
@@ -342,6 +345,7 @@ impl PegasusForConditionalGeneration {
     ///   - `all_encoder_attentions` - `Option<Vec<Tensor>>` of length *num_encoder_layers* with shape (*batch size*, *source_sequence_length*, *hidden_size*)
     ///   - `all_decoder_hidden_states` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
     ///   - `all_decoder_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *target_sequence_length*, *hidden_size*)
+    ///   - `all_cross_attentions` - `Option<Vec<Tensor>>` of length *num_decoder_layers* with shape (*batch size*, *num_heads*, *target_sequence_length*, *source_sequence_length*)
     ///
     /// # Example
     ///
@@ -547,7 +551,7 @@ impl LMHeadModel for PegasusForConditionalGeneration {
 /// # Language generation model based on the Pegasus architecture
 pub struct PegasusConditionalGenerator {
     model: PegasusForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -599,6 +603,7 @@ impl PegasusConditionalGenerator {
     pub fn new(
         generate_config: GenerateConfig,
     ) -> Result<PegasusConditionalGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
 
         let tokenizer = TokenizerOption::from_file(
@@ -615,15 +620,20 @@ impl PegasusConditionalGenerator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<PegasusConditionalGenerator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
-        let config = PegasusConfig::from_file(config_path);
+        let mut config = PegasusConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = PegasusForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -757,7 +767,7 @@ impl PrivateLanguageGenerator<PegasusForConditionalGeneration, PegasusVocab, Peg
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -774,8 +784,6 @@ impl PrivateLanguageGenerator<PegasusForConditionalGeneration, PegasusVocab, Peg
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self
@@ -783,17 +791,11 @@ impl PrivateLanguageGenerator<PegasusForConditionalGeneration, PegasusVocab, Peg
                 .convert_tokens_to_ids(&[PegasusVocab::pad_value()])[0],
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
@@ -20,6 +20,7 @@ use crate::albert::AlbertConfig;
 use crate::bart::BartConfig;
 use crate::bert::BertConfig;
 use crate::common::error::RustBertError;
+use crate::common::resources::ResourceProvider;
 use crate::deberta::DebertaConfig;
 use crate::deberta_v2::DebertaV2Config;
 use crate::distilbert::DistilBertConfig;
@@ -41,21 +42,28 @@ use crate::t5::T5Config;
 use crate::xlnet::XLNetConfig;
 use crate::Config;
 use rust_tokenizers::tokenizer::{
-    AlbertTokenizer, BertTokenizer, DeBERTaTokenizer, DeBERTaV2Tokenizer, FNetTokenizer,
-    Gpt2Tokenizer, M2M100Tokenizer, MBart50Tokenizer, MarianTokenizer, MultiThreadedTokenizer,
-    OpenAiGptTokenizer, PegasusTokenizer, ProphetNetTokenizer, ReformerTokenizer, RobertaTokenizer,
-    T5Tokenizer, Tokenizer, TruncationStrategy, XLMRobertaTokenizer, XLNetTokenizer,
+    truncate_sequences, AlbertTokenizer, BertTokenizer, DeBERTaTokenizer, DeBERTaV2Tokenizer,
+    FNetTokenizer, Gpt2Tokenizer, M2M100Tokenizer, MBart50Tokenizer, MarianTokenizer,
+    MultiThreadedTokenizer, OpenAiGptTokenizer, PegasusTokenizer, ProphetNetTokenizer,
+    ReformerTokenizer, RobertaTokenizer, T5Tokenizer, Tokenizer, TruncationStrategy,
+    XLMRobertaTokenizer, XLNetTokenizer,
 };
 use rust_tokenizers::vocab::{
     AlbertVocab, BertVocab, DeBERTaV2Vocab, DeBERTaVocab, FNetVocab, Gpt2Vocab, M2M100Vocab,
     MBart50Vocab, MarianVocab, OpenAiGptVocab, PegasusVocab, ProphetNetVocab, ReformerVocab,
     RobertaVocab, T5Vocab, Vocab, XLMRobertaVocab, XLNetVocab,
 };
-use rust_tokenizers::{TokenIdsWithOffsets, TokenizedInput, TokensWithOffsets};
+use rust_tokenizers::{
+    TokenIdsWithOffsets, TokenIdsWithSpecialTokens, TokenizedInput, TokensWithOffsets,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tch::{Cuda, Device, Kind, Tensor};
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 /// # Identifies the type of model
@@ -138,6 +146,188 @@ pub enum ConfigOption {
     FNet(FNetConfig),
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// # Side on which sequences are padded when batched
+pub enum PaddingSide {
+    /// Padding tokens are placed before the content (expected by decoder-only models for generation)
+    Left,
+    /// Padding tokens are placed after the content
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// # Special token ids of a tokenizer
+/// Ids of the special tokens of the vocabulary, `None` for the tokens not used by the tokenizer.
+pub struct SpecialTokenIds {
+    /// Beginning of sequence token id
+    pub bos: Option<i64>,
+    /// End of sequence token id
+    pub eos: Option<i64>,
+    /// Padding token id
+    pub pad: Option<i64>,
+    /// Unknown token id
+    pub unk: Option<i64>,
+    /// Mask token id
+    pub mask: Option<i64>,
+    /// Separator token id
+    pub sep: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+/// # Encoded input with its normalized text
+/// Output of `TokenizerOption::encode_with_normalized_text`, holding the normalized text the tokenizer
+/// operated on (e.g. after lowercasing and accent stripping) alongside the encoded input.
+pub struct EncodingWithNormalizedText {
+    /// Encoded input
+    pub tokenized_input: TokenizedInput,
+    /// Text after normalization, before its split into sub-tokens
+    pub normalized_text: String,
+}
+
+#[derive(Debug, Clone)]
+/// # Encoded input with the word index of its tokens
+/// Output of `TokenizerOption::encode_with_word_ids`, mapping each token back to the input word it was split from.
+pub struct EncodingWithWordIds {
+    /// Encoded input
+    pub tokenized_input: TokenizedInput,
+    /// Index of the word each token belongs to (`None` for special tokens)
+    pub word_ids: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// # Handling of out-of-vocabulary tokens
+/// Processing of the parts of the input the tokenizer cannot represent, mapped to its unknown token
+/// (see `TokenizerOption::with_oov_policy`). Byte-level BPE tokenizers (GPT2, RoBERTa, BART, DeBERTa)
+/// represent any input and are not affected by the policy.
+pub enum OovPolicy {
+    /// Out-of-vocabulary tokens are replaced by the unknown token (default)
+    #[default]
+    Unk,
+    /// Out-of-vocabulary tokens are removed
+    Drop,
+    /// Out-of-vocabulary tokens are replaced by the tokens of their UTF-8 bytes (e.g. `<0xE2>`), for vocabularies
+    /// with byte tokens
+    ByteFallback,
+}
+
+/// # Tokenizer implemented outside of the crate
+/// Interface to plug a custom tokenizer (e.g. a proprietary tokenizer) into the pipelines with
+/// `TokenizerOption::Custom`. The ids produced must match the vocabulary of the model used by the pipeline.
+/// The encoding of texts (truncation and addition of the special tokens) is derived from these methods.
+pub trait CustomTokenizer: Send + Sync {
+    /// Returns the type of model the tokenizer is used with
+    fn model_type(&self) -> ModelType;
+
+    /// Tokenizes a text, returning the tokens with their offsets (in characters) in the text
+    fn tokenize_with_offsets(&self, text: &str) -> TokensWithOffsets;
+
+    /// Converts tokens to their ids in the vocabulary
+    fn convert_tokens_to_ids(&self, tokens: &[&str]) -> Vec<i64>;
+
+    /// Converts token ids to their string representation in the vocabulary
+    fn convert_ids_to_tokens(&self, token_ids: &[i64]) -> Vec<String>;
+
+    /// Decodes a sequence of token ids to a text
+    fn decode(
+        &self,
+        token_ids: &[i64],
+        skip_special_tokens: bool,
+        clean_up_tokenization_spaces: bool,
+    ) -> String;
+
+    /// Returns the number of tokens in the vocabulary
+    fn vocab_size(&self) -> usize;
+
+    /// Returns the unknown token id
+    fn unk_id(&self) -> i64;
+
+    /// Returns `true` if the token id is one of the ids of the vocabulary. By default, the vocabulary ids are
+    /// assumed to be contiguous (from 0 to `vocab_size` excluded).
+    fn is_valid_id(&self, id: i64) -> bool {
+        id >= 0 && (id as usize) < self.vocab_size()
+    }
+
+    /// Tokenizes a text
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_with_offsets(text).tokens
+    }
+
+    /// Builds the model input from one or two sequences of token ids. By default, the sequences are concatenated
+    /// without special tokens and the tokens of the second sequence are assigned to the segment 1.
+    fn build_input_with_special_tokens(
+        &self,
+        tokens_ids_with_offsets_1: TokenIdsWithOffsets,
+        tokens_ids_with_offsets_2: Option<TokenIdsWithOffsets>,
+    ) -> TokenIdsWithSpecialTokens {
+        let mut output = TokenIdsWithSpecialTokens {
+            segment_ids: vec![0; tokens_ids_with_offsets_1.ids.len()],
+            special_tokens_mask: vec![0; tokens_ids_with_offsets_1.ids.len()],
+            token_ids: tokens_ids_with_offsets_1.ids,
+            token_offsets: tokens_ids_with_offsets_1.offsets,
+            reference_offsets: tokens_ids_with_offsets_1.reference_offsets,
+            mask: tokens_ids_with_offsets_1.masks,
+        };
+        if let Some(tokens_ids_with_offsets_2) = tokens_ids_with_offsets_2 {
+            let length = tokens_ids_with_offsets_2.ids.len();
+            output.segment_ids.extend(vec![1; length]);
+            output.special_tokens_mask.extend(vec![0; length]);
+            output.token_ids.extend(tokens_ids_with_offsets_2.ids);
+            output
+                .token_offsets
+                .extend(tokens_ids_with_offsets_2.offsets);
+            output
+                .reference_offsets
+                .extend(tokens_ids_with_offsets_2.reference_offsets);
+            output.mask.extend(tokens_ids_with_offsets_2.masks);
+        }
+        output
+    }
+
+    /// Returns the padding token id, if any
+    fn pad_id(&self) -> Option<i64> {
+        None
+    }
+
+    /// Returns the separator token id, if any
+    fn sep_id(&self) -> Option<i64> {
+        None
+    }
+
+    /// Returns the mask token id, if any
+    fn mask_id(&self) -> Option<i64> {
+        None
+    }
+
+    /// Returns the mask token, if any
+    fn mask_value(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the beginning of sequence token id, if any
+    fn bos_id(&self) -> Option<i64> {
+        None
+    }
+
+    /// Returns the end of sequence token id, if any
+    fn eos_id(&self) -> Option<i64> {
+        None
+    }
+
+    /// Returns `true` if the token id corresponds to a special token. By default, the unknown, padding, separator,
+    /// mask, beginning and end of sequence tokens are special tokens.
+    fn is_special_token_id(&self, token_id: i64) -> bool {
+        token_id == self.unk_id()
+            || [
+                self.pad_id(),
+                self.sep_id(),
+                self.mask_id(),
+                self.bos_id(),
+                self.eos_id(),
+            ]
+            .contains(&Some(token_id))
+    }
+}
+
 /// # Abstraction that holds a particular tokenizer, can be of any of the supported models
 pub enum TokenizerOption {
     /// Bert Tokenizer
@@ -176,6 +366,10 @@ pub enum TokenizerOption {
     FNet(FNetTokenizer),
     /// Bart Tokenizer
     Bart(RobertaTokenizer),
+    /// Custom tokenizer implemented outside of the crate
+    Custom(Box<dyn CustomTokenizer>),
+    /// Tokenizer handling its out-of-vocabulary tokens according to an `OovPolicy` (see `TokenizerOption::with_oov_policy`)
+    WithOovPolicy(Box<TokenizerOption>, OovPolicy),
 }
 
 impl ConfigOption {
@@ -287,6 +481,57 @@ impl ConfigOption {
         }
     }
 
+    /// Returns the label mapping (`id2label`) of the configuration, or `None` if it is not provided.
+    pub fn get_label_mapping_opt(&self) -> Option<&HashMap<i64, String>> {
+        match self {
+            Self::Bart(config) => config.id2label.as_ref(),
+            Self::Bert(config) => config.id2label.as_ref(),
+            Self::Deberta(config) => config.id2label.as_ref(),
+            Self::DebertaV2(config) => config.id2label.as_ref(),
+            Self::DistilBert(config) => config.id2label.as_ref(),
+            Self::Electra(config) => config.id2label.as_ref(),
+            Self::Marian(config) => config.id2label.as_ref(),
+            Self::MobileBert(config) => config.id2label.as_ref(),
+            Self::Albert(config) => config.id2label.as_ref(),
+            Self::XLNet(config) => config.id2label.as_ref(),
+            Self::Reformer(config) => config.id2label.as_ref(),
+            Self::ProphetNet(config) => config.id2label.as_ref(),
+            Self::Longformer(config) => config.id2label.as_ref(),
+            Self::MBart(config) => config.id2label.as_ref(),
+            Self::M2M100(config) => config.id2label.as_ref(),
+            Self::FNet(config) => config.id2label.as_ref(),
+            Self::Roberta(config) => config.id2label.as_ref(),
+            Self::T5(_)
+            | Self::OpenAiGpt(_)
+            | Self::GPT2(_)
+            | Self::GPTNeo(_)
+            | Self::Pegasus(_) => None,
+        }
+    }
+
+    /// Returns the number of labels (`num_labels`) of the configuration, if provided explicitly.
+    pub fn get_num_labels(&self) -> Option<i64> {
+        match self {
+            Self::Bart(config) => config.num_labels,
+            Self::Bert(config) | Self::Roberta(config) => config.num_labels,
+            Self::GPT2(config) => config.num_labels,
+            _ => None,
+        }
+    }
+
+    /// Returns the maximum sequence length supported by the model, if any.
+    ///
+    /// This is the number of position embeddings for models using absolute positions. T5 relies on
+    /// relative positions and has no hard limit: the practical limit set by the `n_positions` field of its
+    /// configuration is returned instead, defaulting to the 512 tokens used for training the original
+    /// checkpoints. XLNet has no limit and returns `None`.
+    pub fn get_max_sequence_length(&self) -> Option<usize> {
+        match self {
+            Self::T5(config) => Some(config.n_positions.unwrap_or(512) as usize),
+            _ => self.get_max_len().map(|max_len| max_len as usize),
+        }
+    }
+
     pub fn get_max_len(&self) -> Option<i64> {
         match self {
             Self::Bart(config) => Some(config.max_position_embeddings),
@@ -373,6 +618,12 @@ impl TryFrom<&ConfigOption> for T5Config {
 
 impl TokenizerOption {
     /// Interface method to load a tokenizer from file
+    ///
+    /// `strip_accents` and `add_prefix_space` are optional and default to the tokenizer behaviour when `None`.
+    /// An error is returned if they are set for a tokenizer that does not support them.
+    /// `add_prefix_space` is supported by the RoBERTa-based BPE tokenizers (RoBERTa, Longformer, BART) and the
+    /// DeBERTa V2 tokenizer: a space is prepended to each text before tokenization, so that the first word of
+    /// single inputs and of both sequences of paired inputs is encoded as a word preceded by a space (e.g. for pre-split words).
     pub fn from_file(
         model_type: ModelType,
         vocab_path: &str,
@@ -544,23 +795,54 @@ impl TokenizerOption {
                                 model_type)));
                 }
                 if strip_accents.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(format!(
+                        "Optional input `strip_accents` set to value {} but cannot be used by {:?}",
+                        strip_accents.unwrap(),
+                        model_type
+                    )));
+                }
+                TokenizerOption::Reformer(ReformerTokenizer::from_file(vocab_path, lower_case)?)
+            }
+            ModelType::GPT2 | ModelType::GPTNeo => {
+                if strip_accents.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(format!(
+                        "Optional input `strip_accents` set to value {} but cannot be used by {:?}",
+                        strip_accents.unwrap(),
+                        model_type
+                    )));
+                }
+                if add_prefix_space.is_some() {
                     return Err(RustBertError::InvalidConfigurationError(
                         format!("Optional input `add_prefix_space` set to value {} but cannot be used by {:?}",
                                 add_prefix_space.unwrap(),
                                 model_type)));
                 }
-                TokenizerOption::Reformer(ReformerTokenizer::from_file(vocab_path, lower_case)?)
+                TokenizerOption::GPT2(Gpt2Tokenizer::from_file(
+                    vocab_path,
+                    merges_path.expect("No merges specified!"),
+                    lower_case,
+                )?)
+            }
+            ModelType::OpenAiGpt => {
+                if strip_accents.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(format!(
+                        "Optional input `strip_accents` set to value {} but cannot be used by {:?}",
+                        strip_accents.unwrap(),
+                        model_type
+                    )));
+                }
+                if add_prefix_space.is_some() {
+                    return Err(RustBertError::InvalidConfigurationError(
+                        format!("Optional input `add_prefix_space` set to value {} but cannot be used by {:?}",
+                                add_prefix_space.unwrap(),
+                                model_type)));
+                }
+                TokenizerOption::OpenAiGpt(OpenAiGptTokenizer::from_file(
+                    vocab_path,
+                    merges_path.expect("No merges specified!"),
+                    lower_case,
+                )?)
             }
-            ModelType::GPT2 | ModelType::GPTNeo => TokenizerOption::GPT2(Gpt2Tokenizer::from_file(
-                vocab_path,
-                merges_path.expect("No merges specified!"),
-                lower_case,
-            )?),
-            ModelType::OpenAiGpt => TokenizerOption::OpenAiGpt(OpenAiGptTokenizer::from_file(
-                vocab_path,
-                merges_path.expect("No merges specified!"),
-                lower_case,
-            )?),
             ModelType::ProphetNet => {
                 if add_prefix_space.is_some() {
                     return Err(RustBertError::InvalidConfigurationError(
@@ -635,6 +917,26 @@ impl TokenizerOption {
         Ok(tokenizer)
     }
 
+    /// Checks that the tokenizer can be used by a model of the given type (e.g. when provided to
+    /// build a pipeline), returning an `InvalidConfigurationError` otherwise. Model types sharing a
+    /// tokenizer (e.g. BERT and DistilBERT) accept the same tokenizers.
+    pub(crate) fn validate_model_type(&self, model_type: ModelType) -> Result<(), RustBertError> {
+        let tokenizer_model_type = |model_type: ModelType| match model_type {
+            ModelType::DistilBert | ModelType::Electra | ModelType::MobileBert => ModelType::Bert,
+            ModelType::Longformer => ModelType::Roberta,
+            ModelType::GPTNeo => ModelType::GPT2,
+            _ => model_type,
+        };
+        if tokenizer_model_type(self.model_type()) != tokenizer_model_type(model_type) {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "A {:?} tokenizer cannot be used by a {:?} model",
+                self.model_type(),
+                model_type
+            )));
+        }
+        Ok(())
+    }
+
     /// Returns the model type
     pub fn model_type(&self) -> ModelType {
         match *self {
@@ -656,6 +958,8 @@ impl TokenizerOption {
             Self::MBart50(_) => ModelType::MBart,
             Self::M2M100(_) => ModelType::M2M100,
             Self::FNet(_) => ModelType::FNet,
+            Self::Custom(ref tokenizer) => tokenizer.model_type(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.model_type(),
         }
     }
 
@@ -671,132 +975,138 @@ impl TokenizerOption {
         S: AsRef<str> + Sync,
     {
         match *self {
-            Self::Bert(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Bert(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Deberta(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::DebertaV2(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::DebertaV2(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Roberta(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Roberta(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Bart(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Bart(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Marian(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Marian(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::T5(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::T5(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::XLMRoberta(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Albert(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Albert(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::XLNet(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::XLNet(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::GPT2(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::GPT2(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::OpenAiGpt(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::OpenAiGpt(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Reformer(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Reformer(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::ProphetNet(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::ProphetNet(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Pegasus(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::Pegasus(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::MBart50(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::M2M100(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::FNet(ref tokenizer) => MultiThreadedTokenizer::encode_list(
+            Self::FNet(ref tokenizer) => encode_list_with_parallelism(
                 tokenizer,
                 text_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
+            Self::Custom(_) | Self::WithOovPolicy(..) => text_list
+                .iter()
+                .map(|text| {
+                    self.encode_pair(text.as_ref(), None, max_len, truncation_strategy, stride)
+                })
+                .collect(),
         }
     }
 
@@ -809,136 +1119,147 @@ impl TokenizerOption {
         stride: usize,
     ) -> Vec<TokenizedInput> {
         match *self {
-            Self::Bert(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Bert(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Deberta(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::DebertaV2(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::DebertaV2(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Roberta(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Roberta(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Bart(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Bart(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Marian(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Marian(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::T5(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::T5(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::XLMRoberta(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Albert(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Albert(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::XLNet(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::XLNet(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::GPT2(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::GPT2(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::OpenAiGpt(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::OpenAiGpt(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Reformer(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Reformer(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::ProphetNet(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::ProphetNet(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::Pegasus(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::Pegasus(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::MBart50(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::M2M100(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
-            Self::FNet(ref tokenizer) => MultiThreadedTokenizer::encode_pair_list(
+            Self::FNet(ref tokenizer) => encode_pair_list_with_parallelism(
                 tokenizer,
                 text_pair_list,
                 max_len,
                 truncation_strategy,
                 stride,
             ),
+            Self::Custom(_) | Self::WithOovPolicy(..) => text_pair_list
+                .iter()
+                .map(|(text_1, text_2)| {
+                    self.encode_pair(text_1, Some(text_2), max_len, truncation_strategy, stride)
+                })
+                .collect(),
         }
     }
 
     /// Interface method for pair encoding (single input)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input cannot be truncated to `max_len` with the given `truncation_strategy`
+    /// (see `try_encode_pair` for a fallible alternative).
     pub fn encode_pair(
         &self,
         text_1: &str,
@@ -1002,9 +1323,37 @@ impl TokenizerOption {
             Self::FNet(ref tokenizer) => {
                 tokenizer.encode(text_1, text_2, max_len, truncation_strategy, stride)
             }
+            Self::Custom(_) | Self::WithOovPolicy(..) => self
+                .try_encode_pair(text_1, text_2, max_len, truncation_strategy, stride)
+                .unwrap_or_else(|error| panic!("{}", error)),
         }
     }
 
+    /// Encodes a single input or a pair of inputs, returning an error instead of panicking if the
+    /// input cannot be truncated to `max_len` with the given `truncation_strategy`
+    /// (e.g. `TruncationStrategy::DoNotTruncate` for an input longer than `max_len`).
+    ///
+    /// # Arguments
+    ///
+    /// * `text_1` - First input to encode
+    /// * `text_2` - Optional second input, encoded as a pair with the first input
+    /// * `max_len` - Maximum sequence length, including special tokens, of the encoded input
+    /// * `truncation_strategy` - `TruncationStrategy` applied if the input exceeds `max_len`
+    /// * `stride` - Number of overlapping tokens of the overflowing tokens
+    ///
+    /// # Returns
+    /// * `Result<TokenizedInput, RustBertError>` encoded input
+    pub fn try_encode_pair(
+        &self,
+        text_1: &str,
+        text_2: Option<&str>,
+        max_len: usize,
+        truncation_strategy: &TruncationStrategy,
+        stride: usize,
+    ) -> Result<TokenizedInput, RustBertError> {
+        encode_from_tokens(self, text_1, text_2, max_len, truncation_strategy, stride)
+    }
+
     /// Interface method to tokenization
     pub fn tokenize(&self, text: &str) -> Vec<String> {
         match *self {
@@ -1026,6 +1375,8 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.tokenize(text),
             Self::M2M100(ref tokenizer) => tokenizer.tokenize(text),
             Self::FNet(ref tokenizer) => tokenizer.tokenize(text),
+            Self::Custom(ref tokenizer) => tokenizer.tokenize(text),
+            Self::WithOovPolicy(..) => self.tokenize_with_offsets(text).tokens,
         }
     }
 
@@ -1050,6 +1401,10 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::M2M100(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
             Self::FNet(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
+            Self::Custom(ref tokenizer) => tokenizer.tokenize_with_offsets(text),
+            Self::WithOovPolicy(ref tokenizer, oov_policy) => {
+                tokenizer.tokenize_with_oov_policy(text, oov_policy)
+            }
         }
     }
 
@@ -1085,9 +1440,120 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
             Self::FNet(ref tokenizer) => MultiThreadedTokenizer::tokenize_list(tokenizer, text),
+            Self::Custom(ref tokenizer) => text
+                .iter()
+                .map(|text| tokenizer.tokenize(text.as_ref()))
+                .collect(),
+            Self::WithOovPolicy(..) => text
+                .iter()
+                .map(|text| self.tokenize(text.as_ref()))
+                .collect(),
         }
     }
 
+    /// Interface method to decoding, never failing on invalid UTF-8. For byte-level BPE tokenizers (GPT2, RoBERTa,
+    /// BART, DeBERTa), the byte sequence represented by the tokens is reconstructed and invalid UTF-8 sequences
+    /// (e.g. a multi-byte character split across tokens and truncated by the end of the generation) are replaced
+    /// by `U+FFFD`. The other tokenizers always decode to valid text.
+    ///
+    /// # Returns
+    ///
+    /// * `(String, usize)` - the decoded text and the number of invalid bytes replaced
+    pub fn decode_lossy(
+        &self,
+        token_ids: &[i64],
+        skip_special_tokens: bool,
+        clean_up_tokenization_spaces: bool,
+    ) -> (String, usize) {
+        let is_byte_level = matches!(
+            *self,
+            Self::GPT2(_) | Self::Roberta(_) | Self::Bart(_) | Self::Deberta(_)
+        );
+        if !is_byte_level {
+            return (
+                self.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces),
+                0,
+            );
+        }
+        let token_ids = token_ids
+            .iter()
+            .filter(|token_id| !(skip_special_tokens && self.is_special_token_id(**token_id)))
+            .copied()
+            .collect::<Vec<i64>>();
+        let mut bytes = Vec::new();
+        for token in self.convert_ids_to_tokens(&token_ids) {
+            for character in token.chars() {
+                match byte_level_char_to_byte(character) {
+                    Some(byte) => bytes.push(byte),
+                    None => bytes.extend(character.to_string().as_bytes()),
+                }
+            }
+        }
+        let (text, num_replaced_bytes) = decode_utf8_lossy(&bytes);
+        if num_replaced_bytes == 0 {
+            return (
+                self.decode(&token_ids, false, clean_up_tokenization_spaces),
+                0,
+            );
+        }
+        let text = if clean_up_tokenization_spaces {
+            match *self {
+                Self::GPT2(ref tokenizer) => Tokenizer::clean_up_tokenization(tokenizer, text),
+                Self::Roberta(ref tokenizer) | Self::Bart(ref tokenizer) => {
+                    Tokenizer::clean_up_tokenization(tokenizer, text)
+                }
+                Self::Deberta(ref tokenizer) => Tokenizer::clean_up_tokenization(tokenizer, text),
+                _ => unreachable!(),
+            }
+        } else {
+            text
+        };
+        (text, num_replaced_bytes)
+    }
+
+    /// Interface method to decoding (see `decode_lossy`), replacing the unknown tokens by a placeholder.
+    /// Unknown tokens are replaced even if special tokens are skipped, and are dropped if the placeholder is empty.
+    ///
+    /// # Returns
+    ///
+    /// * `(String, usize)` - the decoded text and the number of invalid bytes replaced
+    pub fn decode_lossy_with_unk_replacement(
+        &self,
+        token_ids: &[i64],
+        skip_special_tokens: bool,
+        clean_up_tokenization_spaces: bool,
+        unk_replacement: &str,
+    ) -> (String, usize) {
+        let unk_id = self.get_unk_id();
+        if unk_replacement.is_empty() {
+            let token_ids = token_ids
+                .iter()
+                .filter(|token_id| **token_id != unk_id)
+                .copied()
+                .collect::<Vec<i64>>();
+            return self.decode_lossy(
+                &token_ids,
+                skip_special_tokens,
+                clean_up_tokenization_spaces,
+            );
+        }
+        let token_ids = token_ids
+            .iter()
+            .filter(|token_id| {
+                **token_id == unk_id
+                    || !(skip_special_tokens && self.is_special_token_id(**token_id))
+            })
+            .copied()
+            .collect::<Vec<i64>>();
+        let unk_token = self.convert_ids_to_tokens(&[unk_id]).remove(0);
+        let (text, num_replaced_bytes) =
+            self.decode_lossy(&token_ids, false, clean_up_tokenization_spaces);
+        (
+            text.replace(unk_token.as_str(), unk_replacement),
+            num_replaced_bytes,
+        )
+    }
+
     /// Interface method to decoding
     pub fn decode(
         &self,
@@ -1150,9 +1616,137 @@ impl TokenizerOption {
             Self::FNet(ref tokenizer) => {
                 tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
             }
+            Self::Custom(ref tokenizer) => {
+                tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
+            }
+            Self::WithOovPolicy(ref tokenizer, _) => {
+                tokenizer.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
+            }
+        }
+    }
+
+    /// Interface method to decoding (see `decode`), controlling the leading space implied by the SentencePiece
+    /// word boundary marker (`▁`) of the first decoded token. Some SentencePiece tokenizers (e.g. T5) emit this space
+    /// while others trim it; the output is unchanged if the first token does not start with the marker.
+    ///
+    /// # Arguments
+    ///
+    /// * `token_ids` - `&[i64]` token ids to decode
+    /// * `skip_special_tokens` - `bool` flag indicating if special tokens should be removed from the output
+    /// * `clean_up_tokenization_spaces` - `bool` flag indicating if the tokenization spaces should be cleaned up
+    /// * `leading_space` - `bool` flag indicating if the output should start with the space of the first token marker
+    ///
+    /// # Returns
+    ///
+    /// * `String` - the decoded text
+    pub fn decode_with_leading_space(
+        &self,
+        token_ids: &[i64],
+        skip_special_tokens: bool,
+        clean_up_tokenization_spaces: bool,
+        leading_space: bool,
+    ) -> String {
+        let text = self.decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces);
+        let starts_with_marker = token_ids
+            .iter()
+            .find(|token_id| !(skip_special_tokens && self.is_special_token_id(**token_id)))
+            .map_or(false, |token_id| {
+                self.convert_ids_to_tokens(&[*token_id])[0].starts_with('\u{2581}')
+            });
+        if !starts_with_marker {
+            return text;
+        }
+        match (leading_space, text.strip_prefix(' ')) {
+            (false, Some(stripped_text)) => stripped_text.to_string(),
+            (true, None) => format!(" {}", text),
+            _ => text,
         }
     }
 
+    /// Pads a batch of token ids to the length of its longest sequence
+    ///
+    /// # Arguments
+    ///
+    /// * `token_ids` - `Vec<Vec<i64>>` token ids of the sequences to pad
+    /// * `pad_token_id` - `Option<i64>` padding token id. If not provided, will use the PAD token of the tokenizer, or the UNK token if the tokenizer has no PAD token
+    /// * `padding_side` - `PaddingSide` side on which the padding tokens are placed
+    ///
+    /// # Returns
+    /// * `(Tensor, Tensor)` padded token ids and attention mask (0 for padding positions, 1 otherwise), of shape (*batch size*, *sequence_length*).
+    /// An empty batch returns tensors of shape (0, 0).
+    pub fn pad_token_ids(
+        &self,
+        token_ids: Vec<Vec<i64>>,
+        pad_token_id: Option<i64>,
+        padding_side: PaddingSide,
+    ) -> (Tensor, Tensor) {
+        let pad_token_id = pad_token_id
+            .or_else(|| self.get_pad_id())
+            .unwrap_or_else(|| self.get_unk_id());
+        if token_ids.is_empty() {
+            return (
+                Tensor::zeros(&[0, 0], (Kind::Int64, Device::Cpu)),
+                Tensor::zeros(&[0, 0], (Kind::Int64, Device::Cpu)),
+            );
+        }
+        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap_or(0);
+
+        let (token_ids, attention_masks): (Vec<Tensor>, Vec<Tensor>) = token_ids
+            .into_iter()
+            .map(|input| {
+                let padding_length = max_len - input.len();
+                let padding = vec![pad_token_id; padding_length];
+                let padding_mask = vec![0i64; padding_length];
+                let mask = vec![1i64; input.len()];
+                let (input, mask) = match padding_side {
+                    PaddingSide::Left => ([padding, input].concat(), [padding_mask, mask].concat()),
+                    PaddingSide::Right => {
+                        ([input, padding].concat(), [mask, padding_mask].concat())
+                    }
+                };
+                (Tensor::of_slice(&input), Tensor::of_slice(&mask))
+            })
+            .unzip();
+
+        (
+            Tensor::stack(&token_ids, 0),
+            Tensor::stack(&attention_masks, 0),
+        )
+    }
+
+    /// Encode a batch of texts into padded token ids and attention mask tensors, ready to be passed to a model
+    ///
+    /// # Arguments
+    ///
+    /// * `text_list` - Texts to encode
+    /// * `max_len` - Maximum sequence length, including special tokens, of the encoded texts
+    /// * `truncation_strategy` - `TruncationStrategy` applied to the texts exceeding `max_len`
+    /// * `padding_side` - `PaddingSide` side on which the padding tokens are placed
+    /// * `device` - `Device` on which the tensors are placed
+    ///
+    /// # Returns
+    /// * `(Tensor, Tensor)` padded token ids and attention mask (0 for padding positions, 1 otherwise), of shape (*batch size*, *sequence_length*).
+    /// An empty batch returns tensors of shape (0, 0).
+    pub fn encode_batch<S>(
+        &self,
+        text_list: &[S],
+        max_len: usize,
+        truncation_strategy: &TruncationStrategy,
+        padding_side: PaddingSide,
+        device: Device,
+    ) -> (Tensor, Tensor)
+    where
+        S: AsRef<str> + Sync,
+    {
+        let token_ids = self
+            .encode_list(text_list, max_len, truncation_strategy, 0)
+            .into_iter()
+            .map(|input| input.token_ids)
+            .collect::<Vec<Vec<i64>>>();
+        let (token_ids, attention_mask) = self.pad_token_ids(token_ids, None, padding_side);
+        (token_ids.to(device), attention_mask.to(device))
+    }
+
     /// Interface method to build input with special tokens
     pub fn build_input_with_special_tokens(
         &self,
@@ -1232,6 +1826,16 @@ impl TokenizerOption {
                 token_ids_with_offsets_1,
                 token_ids_with_offsets_2,
             ),
+            Self::Custom(ref tokenizer) => tokenizer.build_input_with_special_tokens(
+                token_ids_with_offsets_1,
+                token_ids_with_offsets_2,
+            ),
+            Self::WithOovPolicy(ref tokenizer, _) => {
+                return tokenizer.build_input_with_special_tokens(
+                    token_ids_with_offsets_1,
+                    token_ids_with_offsets_2,
+                )
+            }
         };
         TokenizedInput {
             token_ids: token_ids_with_special_tokens.token_ids,
@@ -1269,6 +1873,266 @@ impl TokenizerOption {
             Self::MBart50(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::M2M100(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
             Self::FNet(ref tokenizer) => tokenizer.convert_tokens_to_ids(tokens),
+            Self::Custom(ref tokenizer) => tokenizer.convert_tokens_to_ids(
+                &tokens
+                    .iter()
+                    .map(|token| token.as_ref())
+                    .collect::<Vec<&str>>(),
+            ),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.convert_tokens_to_ids(tokens),
+        }
+    }
+
+    /// Interface method to convert token ids to their string representation in the vocabulary
+    pub fn convert_ids_to_tokens(&self, token_ids: &[i64]) -> Vec<String> {
+        match *self {
+            Self::Bert(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Deberta(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::DebertaV2(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Roberta(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Bart(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::XLMRoberta(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Marian(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::T5(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Albert(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::XLNet(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::GPT2(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::OpenAiGpt(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Reformer(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::ProphetNet(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Pegasus(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::MBart50(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::M2M100(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::FNet(ref tokenizer) => token_ids
+                .iter()
+                .map(|token_id| MultiThreadedTokenizer::vocab(tokenizer).id_to_token(token_id))
+                .collect(),
+            Self::Custom(ref tokenizer) => tokenizer.convert_ids_to_tokens(token_ids),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.convert_ids_to_tokens(token_ids),
+        }
+    }
+
+    /// Interface method returning the number of tokens in the vocabulary
+    pub fn get_vocab_size(&self) -> usize {
+        match *self {
+            Self::Bert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::DebertaV2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Roberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Bart(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::XLMRoberta(ref tokenizer) => {
+                MultiThreadedTokenizer::vocab(tokenizer).values.len()
+            }
+            Self::Marian(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::T5(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Albert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::XLNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::GPT2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::OpenAiGpt(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Reformer(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::ProphetNet(ref tokenizer) => {
+                MultiThreadedTokenizer::vocab(tokenizer).values.len()
+            }
+            Self::Pegasus(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::FNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer).values.len(),
+            Self::Custom(ref tokenizer) => tokenizer.vocab_size(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_vocab_size(),
+        }
+    }
+
+    /// Returns `true` if the token id is one of the ids of the vocabulary (regular or special tokens),
+    /// e.g. to validate the token ids used to build logit masks
+    pub fn is_valid_id(&self, id: i64) -> bool {
+        match *self {
+            Self::Bert(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Deberta(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::DebertaV2(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Roberta(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Bart(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::XLMRoberta(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Marian(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::T5(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Albert(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::XLNet(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::GPT2(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::OpenAiGpt(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Reformer(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::ProphetNet(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Pegasus(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::MBart50(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::M2M100(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::FNet(ref tokenizer) => {
+                let vocab = MultiThreadedTokenizer::vocab(tokenizer);
+                vocab.indices.contains_key(&id) || vocab.special_indices.contains_key(&id)
+            }
+            Self::Custom(ref tokenizer) => tokenizer.is_valid_id(id),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.is_valid_id(id),
+        }
+    }
+
+    /// Interface method to check if a token id corresponds to a special token
+    pub fn is_special_token_id(&self, token_id: i64) -> bool {
+        match *self {
+            Self::Bert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Deberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::DebertaV2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Roberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Bart(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::XLMRoberta(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Marian(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::T5(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Albert(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::XLNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::GPT2(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::OpenAiGpt(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Reformer(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::ProphetNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Pegasus(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::MBart50(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::M2M100(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::FNet(ref tokenizer) => MultiThreadedTokenizer::vocab(tokenizer)
+                .special_indices
+                .contains_key(&token_id),
+            Self::Custom(ref tokenizer) => tokenizer.is_special_token_id(token_id),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.is_special_token_id(token_id),
         }
     }
 
@@ -1347,6 +2211,8 @@ impl TokenizerOption {
                 .special_values
                 .get(FNetVocab::unknown_value())
                 .expect("UNK token not found in vocabulary"),
+            Self::Custom(ref tokenizer) => tokenizer.unk_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_unk_id(),
         }
     }
 
@@ -1446,6 +2312,8 @@ impl TokenizerOption {
             Self::Reformer(_) => None,
             Self::GPT2(_) => None,
             Self::OpenAiGpt(_) => None,
+            Self::Custom(ref tokenizer) => tokenizer.pad_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_pad_id(),
         }
     }
 
@@ -1530,6 +2398,8 @@ impl TokenizerOption {
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
             Self::Pegasus(_) => None,
+            Self::Custom(ref tokenizer) => tokenizer.sep_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_sep_id(),
         }
     }
 
@@ -1614,6 +2484,8 @@ impl TokenizerOption {
             Self::GPT2(_) => None,
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
+            Self::Custom(ref tokenizer) => tokenizer.mask_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_mask_id(),
         }
     }
 
@@ -1638,6 +2510,8 @@ impl TokenizerOption {
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
             Self::Pegasus(_) => None,
+            Self::Custom(tokenizer) => tokenizer.mask_value(),
+            Self::WithOovPolicy(tokenizer, _) => tokenizer.get_mask_value(),
         }
     }
 
@@ -1707,6 +2581,8 @@ impl TokenizerOption {
             Self::OpenAiGpt(_) => None,
             Self::Reformer(_) => None,
             Self::Pegasus(_) => Some(0),
+            Self::Custom(ref tokenizer) => tokenizer.bos_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_bos_id(),
         }
     }
 
@@ -1801,6 +2677,1162 @@ impl TokenizerOption {
             Self::Bert(_) => None,
             Self::ProphetNet(_) => None,
             Self::OpenAiGpt(_) => None,
+            Self::Custom(ref tokenizer) => tokenizer.eos_id(),
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_eos_id(),
         }
     }
+
+    /// Returns the ids of the special tokens (BOS, EOS, PAD, UNK, MASK, SEP) of the tokenizer,
+    /// e.g. to build custom logits constraints or post-processing.
+    ///
+    /// # Returns
+    /// * `SpecialTokenIds` ids of the special tokens, `None` for the tokens not used by the tokenizer
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    ///
+    /// let tokenizer =
+    ///     TokenizerOption::from_file(ModelType::T5, "path/to/spiece.model", None, false, None, None)?;
+    /// let eos_token_id = tokenizer.get_special_token_ids().eos;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_special_token_ids(&self) -> SpecialTokenIds {
+        SpecialTokenIds {
+            bos: self.get_bos_id(),
+            eos: self.get_eos_id(),
+            pad: self.get_pad_id(),
+            unk: Some(self.get_unk_id()),
+            mask: self.get_mask_id(),
+            sep: self.get_sep_id(),
+        }
+    }
+
+    /// Returns the normalized text a tokenizer operates on (after text cleaning, lowercasing, accent
+    /// stripping or unicode normalization, depending on the tokenizer), before its split into sub-tokens.
+    /// Whitespace characters are replaced by spaces, and words mapped to the unknown token are kept as in the input text.
+    /// This helps understanding why the token offsets do not line up with the raw input.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to normalize
+    ///
+    /// # Returns
+    /// * `String` normalized text
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    ///
+    /// let tokenizer =
+    ///     TokenizerOption::from_file(ModelType::Bert, "path/to/vocab.txt", None, true, None, None)?;
+    /// let normalized_text = tokenizer.get_normalized_text("Héllo WORLD");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_normalized_text(&self, text: &str) -> String {
+        match *self {
+            // Byte-level BPE tokenizers do not split the text on whitespaces: the decoded tokens
+            // exactly cover the normalized text
+            Self::GPT2(_) | Self::Roberta(_) | Self::Bart(_) | Self::Deberta(_) => {
+                let tokens = self.tokenize(text);
+                self.decode(&self.convert_tokens_to_ids(&tokens), false, false)
+            }
+            // The out-of-vocabulary policy does not change the normalization of the text
+            Self::WithOovPolicy(ref tokenizer, _) => tokenizer.get_normalized_text(text),
+            _ => {
+                let tokens_with_offsets = self.tokenize_with_offsets(text);
+                let unk_token = self.convert_ids_to_tokens(&[self.get_unk_id()]).remove(0);
+                let characters = text.chars().collect::<Vec<char>>();
+                let mut normalized_text = String::with_capacity(text.len());
+                let mut position = 0;
+                for ((token, offset), reference_offsets) in tokens_with_offsets
+                    .tokens
+                    .iter()
+                    .zip(tokens_with_offsets.offsets.iter())
+                    .zip(tokens_with_offsets.reference_offsets.iter())
+                {
+                    if let Some(offset) = offset {
+                        let (begin, end) = (offset.begin as usize, offset.end as usize);
+                        if begin > position {
+                            normalized_text.extend(
+                                characters[position..begin]
+                                    .iter()
+                                    .filter(|character| character.is_whitespace())
+                                    .map(|_| ' '),
+                            );
+                        }
+                        if *token == unk_token {
+                            normalized_text.extend(&characters[begin.max(position)..end]);
+                            position = position.max(end);
+                            continue;
+                        }
+                        position = position.max(end);
+                    }
+                    // Sub-token markers (e.g. `##`) are prepended to the normalized characters
+                    // and do not map to the input text
+                    let token_characters = token.chars().collect::<Vec<char>>();
+                    let normalized_start = token_characters
+                        .len()
+                        .saturating_sub(reference_offsets.len());
+                    normalized_text.extend(token_characters[normalized_start..].iter().map(
+                        |character| {
+                            if *character == '\u{2581}' {
+                                ' '
+                            } else {
+                                *character
+                            }
+                        },
+                    ));
+                }
+                normalized_text
+            }
+        }
+    }
+
+    /// Encodes a text, returning the normalized text the tokenizer operated on alongside the encoded input
+    /// (see `get_normalized_text`).
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to encode
+    /// * `max_len` - Maximum sequence length, including special tokens, of the encoded text
+    /// * `truncation_strategy` - `TruncationStrategy` applied if the text exceeds `max_len`
+    /// * `stride` - Number of overlapping tokens of the overflowing tokens
+    ///
+    /// # Returns
+    /// * `EncodingWithNormalizedText` encoded input and normalized text
+    pub fn encode_with_normalized_text(
+        &self,
+        text: &str,
+        max_len: usize,
+        truncation_strategy: &TruncationStrategy,
+        stride: usize,
+    ) -> EncodingWithNormalizedText {
+        EncodingWithNormalizedText {
+            tokenized_input: self.encode_pair(text, None, max_len, truncation_strategy, stride),
+            normalized_text: self.get_normalized_text(text),
+        }
+    }
+
+    /// Encodes an input made of words, keeping track of the word each token belongs to (e.g. to align token-level
+    /// predictions with pre-tokenized, CoNLL-style, words). The words are joined by single spaces before tokenization,
+    /// so that byte-level BPE tokenizers (GPT2, RoBERTa, BART, DeBERTa) encode all words but the first with a prefix space,
+    /// as they would in running text. Tokens are mapped back to their word using their offsets.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Elements of the input. If `is_split_into_words` is true, each element is a word, kept as-is.
+    /// Otherwise, the elements are split on whitespace into words.
+    /// * `is_split_into_words` - `bool` flag indicating if the input is already split into words
+    /// * `max_len` - Maximum sequence length, including special tokens, of the encoded input
+    /// * `truncation_strategy` - `TruncationStrategy` applied if the input exceeds `max_len`
+    /// * `stride` - Number of overlapping tokens of the overflowing tokens
+    ///
+    /// # Returns
+    /// * `Result<EncodingWithWordIds, RustBertError>` encoded input and word index of each token
+    pub fn encode_with_word_ids<S>(
+        &self,
+        inputs: &[S],
+        is_split_into_words: bool,
+        max_len: usize,
+        truncation_strategy: &TruncationStrategy,
+        stride: usize,
+    ) -> Result<EncodingWithWordIds, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let words = if is_split_into_words {
+            inputs
+                .iter()
+                .map(|word| word.as_ref())
+                .collect::<Vec<&str>>()
+        } else {
+            inputs
+                .iter()
+                .flat_map(|text| text.as_ref().split_whitespace())
+                .collect::<Vec<&str>>()
+        };
+
+        // Character span of each word in the joined text
+        let mut word_spans = Vec::with_capacity(words.len());
+        let mut position = 0u32;
+        for word in words.iter() {
+            let word_length = word.chars().count() as u32;
+            word_spans.push((position, position + word_length));
+            position += word_length + 1;
+        }
+        let text = words.join(" ");
+
+        let tokens = self.tokenize_with_offsets(&text);
+        let token_ids = TokenIdsWithOffsets {
+            ids: self.convert_tokens_to_ids(&tokens.tokens),
+            offsets: tokens.offsets,
+            reference_offsets: tokens.reference_offsets,
+            masks: tokens.masks,
+        };
+        let tokenized_input =
+            self.truncate_and_build_input(token_ids, max_len, truncation_strategy, stride)?;
+
+        // Tokens are attributed to the word containing their last character
+        let word_ids = tokenized_input
+            .token_offsets
+            .iter()
+            .map(|offset| {
+                offset.and_then(|offset| {
+                    let last_position = offset.end.saturating_sub(1);
+                    word_spans.iter().position(|(begin, end)| {
+                        (*begin <= last_position) && (last_position < *end)
+                    })
+                })
+            })
+            .collect();
+
+        Ok(EncodingWithWordIds {
+            tokenized_input,
+            word_ids,
+        })
+    }
+
+    /// Sets the handling of the tokens out of the vocabulary of the tokenizer (mapped to its unknown token),
+    /// applied by the tokenization (`tokenize`, `tokenize_with_offsets`, `tokenize_list`) and encoding
+    /// (`encode_list`, `encode_pair_list`, `encode_pair`) methods. Byte-level BPE tokenizers (GPT2, RoBERTa,
+    /// BART, DeBERTa) represent any input and are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `oov_policy` - `OovPolicy` handling of the out-of-vocabulary tokens
+    ///
+    /// # Returns
+    /// * `Result<TokenizerOption, RustBertError>` tokenizer applying the policy. Fails if `OovPolicy::ByteFallback` is
+    /// requested and the vocabulary has no byte tokens (e.g. `<0xE2>`).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, OovPolicy, TokenizerOption};
+    ///
+    /// let tokenizer =
+    ///     TokenizerOption::from_file(ModelType::Bert, "path/to/vocab.txt", None, true, None, None)?
+    ///         .with_oov_policy(OovPolicy::Drop)?;
+    /// let tokens = tokenizer.tokenize("Hello \u{1F980} world");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_oov_policy(self, oov_policy: OovPolicy) -> Result<Self, RustBertError> {
+        let tokenizer = match self {
+            Self::WithOovPolicy(tokenizer, _) => *tokenizer,
+            tokenizer => tokenizer,
+        };
+        let is_byte_level = matches!(
+            tokenizer,
+            Self::GPT2(_) | Self::Roberta(_) | Self::Bart(_) | Self::Deberta(_)
+        );
+        if oov_policy == OovPolicy::Unk || is_byte_level {
+            return Ok(tokenizer);
+        }
+        if oov_policy == OovPolicy::ByteFallback {
+            let unk_id = tokenizer.get_unk_id();
+            if let Some(byte_token) = (0..=u8::MAX)
+                .map(|byte| format!("<0x{:02X}>", byte))
+                .find(|byte_token| tokenizer.convert_tokens_to_ids(&[byte_token])[0] == unk_id)
+            {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "Byte fallback is not supported by the {:?} tokenizer vocabulary (missing byte token {})",
+                    tokenizer.model_type(),
+                    byte_token
+                )));
+            }
+        }
+        Ok(Self::WithOovPolicy(Box::new(tokenizer), oov_policy))
+    }
+
+    /// Returns the handling of the out-of-vocabulary tokens of the tokenizer (see `with_oov_policy`)
+    pub fn oov_policy(&self) -> OovPolicy {
+        match *self {
+            Self::WithOovPolicy(_, oov_policy) => oov_policy,
+            _ => OovPolicy::Unk,
+        }
+    }
+
+    /// Tokenizes a text, handling the out-of-vocabulary tokens according to an `OovPolicy`. The byte tokens
+    /// are checked to be in the vocabulary when the policy is set.
+    fn tokenize_with_oov_policy(&self, text: &str, oov_policy: OovPolicy) -> TokensWithOffsets {
+        let tokens = self.tokenize_with_offsets(text);
+        if oov_policy == OovPolicy::Unk {
+            return tokens;
+        }
+        let unk_id = self.get_unk_id();
+        let token_ids = self.convert_tokens_to_ids(&tokens.tokens);
+        if !token_ids.contains(&unk_id) {
+            return tokens;
+        }
+
+        let characters = text.chars().collect::<Vec<char>>();
+        let mut output = TokensWithOffsets {
+            tokens: Vec::with_capacity(tokens.tokens.len()),
+            offsets: Vec::with_capacity(tokens.offsets.len()),
+            reference_offsets: Vec::with_capacity(tokens.reference_offsets.len()),
+            masks: Vec::with_capacity(tokens.masks.len()),
+        };
+        for ((((token, token_id), offset), reference_offsets), mask) in tokens
+            .tokens
+            .into_iter()
+            .zip(token_ids)
+            .zip(tokens.offsets)
+            .zip(tokens.reference_offsets)
+            .zip(tokens.masks)
+        {
+            if token_id != unk_id {
+                output.tokens.push(token);
+                output.offsets.push(offset);
+                output.reference_offsets.push(reference_offsets);
+                output.masks.push(mask);
+                continue;
+            }
+            if oov_policy == OovPolicy::Drop {
+                continue;
+            }
+            // The unknown token is replaced by the tokens of the bytes of the characters it covers
+            let token_text = offset
+                .and_then(|offset| characters.get(offset.begin as usize..offset.end as usize))
+                .map(|token_characters| token_characters.iter().collect::<String>())
+                .unwrap_or(token);
+            for byte in token_text.bytes() {
+                output.tokens.push(format!("<0x{:02X}>", byte));
+                output.offsets.push(offset);
+                output.reference_offsets.push(reference_offsets.clone());
+                output.masks.push(mask);
+            }
+        }
+        output
+    }
+
+    /// Returns the out-of-vocabulary rate of a text: the proportion of its non-whitespace characters covered by
+    /// unknown tokens or byte fallback tokens (e.g. `<0xE2>`), e.g. to flag out-of-domain inputs.
+    /// Byte-level BPE tokenizers (GPT2, RoBERTa, BART, DeBERTa) represent any input and always return 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to analyze
+    ///
+    /// # Returns
+    /// * `f32` proportion of the characters of the text mapped to unknown or byte tokens, between 0 and 1 (0 for empty texts)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    ///
+    /// let tokenizer =
+    ///     TokenizerOption::from_file(ModelType::Bert, "path/to/vocab.txt", None, true, None, None)?;
+    /// let oov_rate = tokenizer.oov_rate("Hello \u{1F980} world");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn oov_rate(&self, text: &str) -> f32 {
+        let characters = text.chars().collect::<Vec<char>>();
+        let num_characters = characters
+            .iter()
+            .filter(|character| !character.is_whitespace())
+            .count();
+        if num_characters == 0 {
+            return 0.0;
+        }
+        let tokens = self.tokenize_with_offsets(text);
+        let unk_id = self.get_unk_id();
+        let mut is_oov = vec![false; characters.len()];
+        for ((token, token_id), offset) in tokens
+            .tokens
+            .iter()
+            .zip(self.convert_tokens_to_ids(&tokens.tokens))
+            .zip(tokens.offsets.iter())
+        {
+            if let Some(offset) = offset {
+                if token_id == unk_id || byte_fallback_value(token).is_some() {
+                    let end = (offset.end as usize).min(characters.len());
+                    let begin = (offset.begin as usize).min(end);
+                    is_oov[begin..end].iter_mut().for_each(|oov| *oov = true);
+                }
+            }
+        }
+        let num_oov_characters = characters
+            .iter()
+            .zip(is_oov)
+            .filter(|(character, oov)| *oov && !character.is_whitespace())
+            .count();
+        num_oov_characters as f32 / num_characters as f32
+    }
+
+    fn truncate_and_build_input(
+        &self,
+        token_ids: TokenIdsWithOffsets,
+        max_len: usize,
+        truncation_strategy: &TruncationStrategy,
+        stride: usize,
+    ) -> Result<TokenizedInput, RustBertError> {
+        let num_special_tokens = self
+            .build_input_with_special_tokens(
+                TokenIdsWithOffsets {
+                    ids: vec![],
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                },
+                None,
+            )
+            .token_ids
+            .len();
+        let num_truncated_tokens =
+            (token_ids.ids.len() + num_special_tokens).saturating_sub(max_len);
+        let (token_ids, _, overflowing_tokens, _) = truncate_sequences(
+            token_ids,
+            None,
+            num_truncated_tokens,
+            truncation_strategy,
+            stride,
+        )?;
+        let mut tokenized_input = self.build_input_with_special_tokens(token_ids, None);
+        tokenized_input.overflowing_tokens = overflowing_tokens;
+        tokenized_input.num_truncated_tokens = num_truncated_tokens;
+        Ok(tokenized_input)
+    }
+}
+
+/// Encodes a text or a pair of texts from the tokens of a custom tokenizer or of a tokenizer with an `OovPolicy`,
+/// truncating the sequences to `max_len` tokens (including the special tokens) following the encoding of the
+/// built-in tokenizers.
+fn encode_from_tokens(
+    tokenizer: &TokenizerOption,
+    text_1: &str,
+    text_2: Option<&str>,
+    max_len: usize,
+    truncation_strategy: &TruncationStrategy,
+    stride: usize,
+) -> Result<TokenizedInput, RustBertError> {
+    let tokenize = |text: &str| {
+        let tokens = tokenizer.tokenize_with_offsets(text);
+        TokenIdsWithOffsets {
+            ids: tokenizer.convert_tokens_to_ids(&tokens.tokens),
+            offsets: tokens.offsets,
+            reference_offsets: tokens.reference_offsets,
+            masks: tokens.masks,
+        }
+    };
+    let empty_input = || TokenIdsWithOffsets {
+        ids: vec![],
+        offsets: vec![],
+        reference_offsets: vec![],
+        masks: vec![],
+    };
+    let token_ids_1 = tokenize(text_1);
+    let token_ids_2 = text_2.map(tokenize);
+    let num_special_tokens = tokenizer
+        .build_input_with_special_tokens(empty_input(), token_ids_2.as_ref().map(|_| empty_input()))
+        .token_ids
+        .len();
+    let total_length = token_ids_1.ids.len()
+        + token_ids_2
+            .as_ref()
+            .map_or(0, |token_ids| token_ids.ids.len())
+        + num_special_tokens;
+    let num_truncated_tokens = total_length.saturating_sub(max_len);
+    let (token_ids_1, token_ids_2, overflowing_tokens, _) = truncate_sequences(
+        token_ids_1,
+        token_ids_2,
+        num_truncated_tokens,
+        truncation_strategy,
+        stride,
+    )?;
+    let output = tokenizer.build_input_with_special_tokens(token_ids_1, token_ids_2);
+    Ok(TokenizedInput {
+        token_ids: output.token_ids,
+        segment_ids: output.segment_ids,
+        special_tokens_mask: output.special_tokens_mask,
+        overflowing_tokens,
+        num_truncated_tokens,
+        token_offsets: output.token_offsets,
+        reference_offsets: output.reference_offsets,
+        mask: output.mask,
+    })
+}
+
+static PARALLEL_TOKENIZATION: AtomicBool = AtomicBool::new(true);
+static PARALLEL_TOKENIZATION_MIN_BATCH_SIZE: AtomicUsize = AtomicUsize::new(2);
+
+/// Configures the parallel tokenization of batches of inputs by `TokenizerOption::encode_list`
+/// and `TokenizerOption::encode_pair_list`. If enabled, batches of at least `min_batch_size` inputs
+/// are tokenized across the rayon thread pool, while smaller batches are tokenized on the calling thread.
+/// The order of the inputs is preserved in both cases.
+///
+/// This setting is process-wide: it applies to all tokenizers of the process, and the last value
+/// set takes precedence. By default, batches of at least 2 inputs are tokenized in parallel.
+///
+/// # Arguments
+///
+/// * `parallel_tokenization` - `bool` flag enabling parallel tokenization
+/// * `min_batch_size` - `usize` minimum number of inputs of a batch for it to be tokenized in parallel
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::common::set_parallel_tokenization;
+///
+/// set_parallel_tokenization(true, 64);
+/// ```
+pub fn set_parallel_tokenization(parallel_tokenization: bool, min_batch_size: usize) {
+    PARALLEL_TOKENIZATION.store(parallel_tokenization, Ordering::Relaxed);
+    PARALLEL_TOKENIZATION_MIN_BATCH_SIZE.store(min_batch_size, Ordering::Relaxed);
+}
+
+fn use_parallel_tokenization(batch_size: usize) -> bool {
+    PARALLEL_TOKENIZATION.load(Ordering::Relaxed)
+        && batch_size >= PARALLEL_TOKENIZATION_MIN_BATCH_SIZE.load(Ordering::Relaxed)
+}
+
+fn encode_list_with_parallelism<T, V, S>(
+    tokenizer: &T,
+    text_list: &[S],
+    max_len: usize,
+    truncation_strategy: &TruncationStrategy,
+    stride: usize,
+) -> Vec<TokenizedInput>
+where
+    T: MultiThreadedTokenizer<V>,
+    V: Vocab,
+    S: AsRef<str> + Sync,
+{
+    if use_parallel_tokenization(text_list.len()) {
+        MultiThreadedTokenizer::encode_list(
+            tokenizer,
+            text_list,
+            max_len,
+            truncation_strategy,
+            stride,
+        )
+    } else {
+        Tokenizer::encode_list(tokenizer, text_list, max_len, truncation_strategy, stride)
+    }
+}
+
+fn encode_pair_list_with_parallelism<T, V>(
+    tokenizer: &T,
+    text_pair_list: &[(&str, &str)],
+    max_len: usize,
+    truncation_strategy: &TruncationStrategy,
+    stride: usize,
+) -> Vec<TokenizedInput>
+where
+    T: MultiThreadedTokenizer<V>,
+    V: Vocab,
+{
+    if use_parallel_tokenization(text_pair_list.len()) {
+        MultiThreadedTokenizer::encode_pair_list(
+            tokenizer,
+            text_pair_list,
+            max_len,
+            truncation_strategy,
+            stride,
+        )
+    } else {
+        Tokenizer::encode_pair_list(
+            tokenizer,
+            text_pair_list,
+            max_len,
+            truncation_strategy,
+            stride,
+        )
+    }
+}
+
+/// Sets the number of threads used by Torch for intra-op parallelism on CPU, if provided.
+///
+/// This setting is process-wide: it applies to all models of the process (including those
+/// created previously), and the last value set takes precedence.
+pub(crate) fn set_num_threads(num_threads: Option<i32>) -> Result<(), RustBertError> {
+    if let Some(num_threads) = num_threads {
+        if num_threads < 1 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The number of threads must be strictly positive, got {}",
+                num_threads
+            )));
+        }
+        tch::set_num_threads(num_threads);
+    }
+    Ok(())
+}
+
+/// Blocks until all pending operations on the given device are completed.
+/// This is a no-op for devices other than CUDA.
+pub(crate) fn synchronize_device(device: Device) {
+    if let Device::Cuda(device_index) = device {
+        Cuda::synchronize(device_index as i64);
+    }
+}
+
+/// Releases the memory of a device after the tensors it held were dropped (e.g. when unloading a model).
+/// The device is synchronized so that the memory of the dropped tensors is freed, and on CUDA devices
+/// the cached memory of the Torch allocator is returned to the device.
+pub(crate) fn release_device_memory(device: Device) {
+    synchronize_device(device);
+    if let Device::Cuda(_) = device {
+        empty_cuda_cache();
+    }
+}
+
+/// Returns the unused memory cached by the Torch CUDA caching allocator to the device, making it available to
+/// other processes (equivalent of `torch.cuda.empty_cache()`). The memory still held by tensors is not affected.
+///
+/// `c10::cuda::CUDACachingAllocator::emptyCache` is not exposed by the Torch C API: it is looked up in the
+/// Torch CUDA library loaded by the process. This is a no-op if Torch was built without CUDA support or on
+/// platforms other than Unix.
+pub fn empty_cuda_cache() {
+    #[cfg(unix)]
+    {
+        const EMPTY_CACHE_SYMBOL: &[u8] = b"_ZN3c104cuda20CUDACachingAllocator10emptyCacheEv\0";
+        // Safety: the symbol is the `void emptyCache()` free function of the Torch CUDA caching allocator,
+        // taking no argument and returning nothing.
+        unsafe {
+            let symbol = libc::dlsym(
+                libc::RTLD_DEFAULT,
+                EMPTY_CACHE_SYMBOL.as_ptr() as *const libc::c_char,
+            );
+            if !symbol.is_null() {
+                let empty_cache = std::mem::transmute::<*mut libc::c_void, extern "C" fn()>(symbol);
+                empty_cache();
+            }
+        }
+    }
+}
+
+/// Runs a forward pass in training mode (with dropout, tracking gradients) or in evaluation mode
+/// (without dropout, under `no_grad`). The `forward` closure receives the `train` flag of the model.
+pub(crate) fn forward_in_mode<T>(train: bool, forward: impl FnOnce(bool) -> T) -> T {
+    if train {
+        forward(true)
+    } else {
+        tch::no_grad(|| forward(false))
+    }
+}
+
+/// Unfreezes the variables of a var store in training mode, so that they track gradients, and
+/// freezes them in evaluation mode.
+pub(crate) fn set_var_store_mode(var_store: &mut tch::nn::VarStore, train: bool) {
+    if train {
+        var_store.unfreeze();
+    } else {
+        var_store.freeze();
+    }
+}
+
+/// Truncates a string slice to at most `max_bytes` bytes, rounding down to the nearest
+/// character boundary so that multi-byte characters are never split.
+///
+/// # Arguments
+///
+/// * `text` - text to truncate
+/// * `max_bytes` - maximum length of the output in bytes
+///
+/// # Returns
+///
+/// * `&str` - the longest prefix of `text` not exceeding `max_bytes` bytes
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::common::truncate_to_char_boundary;
+///
+/// // "é" is encoded over 2 bytes: truncating after 4 bytes would split it
+/// assert_eq!(truncate_to_char_boundary("Café au lait", 4), "Caf");
+/// ```
+pub fn truncate_to_char_boundary(text: &str, max_bytes: usize) -> &str {
+    if max_bytes >= text.len() {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Truncates a sequence of tokens to at most `max_length` tokens, rounding down so that the
+/// byte-fallback tokens of SentencePiece models (e.g. `<0xF0>`) encoding a single multi-byte
+/// character are never split.
+///
+/// # Arguments
+///
+/// * `tokens` - tokens to truncate
+/// * `max_length` - maximum number of tokens of the output
+///
+/// # Returns
+///
+/// * `&[S]` - the longest prefix of `tokens` not exceeding `max_length` tokens and not ending within a byte-fallback sequence
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::common::truncate_tokens_to_byte_fallback_boundary;
+///
+/// // "😀" is encoded over 4 byte-fallback tokens: truncating after 3 tokens would split it
+/// let tokens = ["▁Hi", "<0xF0>", "<0x9F>", "<0x98>", "<0x80>"];
+/// assert_eq!(
+///     truncate_tokens_to_byte_fallback_boundary(&tokens, 3),
+///     ["▁Hi"]
+/// );
+/// ```
+pub fn truncate_tokens_to_byte_fallback_boundary<S: AsRef<str>>(
+    tokens: &[S],
+    max_length: usize,
+) -> &[S] {
+    if max_length >= tokens.len() {
+        return tokens;
+    }
+    let mut sequence_start = max_length;
+    while sequence_start > 0 && byte_fallback_value(tokens[sequence_start - 1].as_ref()).is_some() {
+        sequence_start -= 1;
+    }
+    let mut end = sequence_start;
+    let mut position = sequence_start;
+    while position < max_length {
+        let byte = byte_fallback_value(tokens[position].as_ref()).unwrap();
+        // Continuation and invalid bytes are not part of a valid sequence and are kept as single tokens
+        position += match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        if position <= max_length {
+            end = position;
+        }
+    }
+    &tokens[..end]
+}
+
+fn byte_fallback_value(token: &str) -> Option<u8> {
+    let hex_value = token.strip_prefix("<0x")?.strip_suffix('>')?;
+    if hex_value.len() != 2 {
+        return None;
+    }
+    u8::from_str_radix(hex_value, 16).ok()
+}
+
+/// Decodes bytes as UTF-8, replacing each invalid sequence with the replacement character `U+FFFD`
+/// (as `String::from_utf8_lossy`) and counting the number of bytes replaced.
+///
+/// # Arguments
+///
+/// * `bytes` - bytes to decode
+///
+/// # Returns
+///
+/// * `(String, usize)` - the decoded text and the number of invalid bytes replaced
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::common::decode_utf8_lossy;
+///
+/// // The 2-bytes character "é" is truncated after its first byte
+/// let (text, num_replaced_bytes) = decode_utf8_lossy(&[b'C', b'a', b'f', 0xC3]);
+/// assert_eq!(text, "Caf\u{FFFD}");
+/// assert_eq!(num_replaced_bytes, 1);
+/// ```
+pub fn decode_utf8_lossy(bytes: &[u8]) -> (String, usize) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut num_replaced_bytes = 0;
+    let mut remaining_bytes = bytes;
+    loop {
+        match std::str::from_utf8(remaining_bytes) {
+            Ok(valid_text) => {
+                text.push_str(valid_text);
+                return (text, num_replaced_bytes);
+            }
+            Err(error) => {
+                let (valid_bytes, invalid_bytes) = remaining_bytes.split_at(error.valid_up_to());
+                text.push_str(std::str::from_utf8(valid_bytes).unwrap());
+                text.push(char::REPLACEMENT_CHARACTER);
+                let num_invalid_bytes = error.error_len().unwrap_or(invalid_bytes.len());
+                num_replaced_bytes += num_invalid_bytes;
+                remaining_bytes = &invalid_bytes[num_invalid_bytes..];
+            }
+        }
+    }
+}
+
+/// Converts a character of a byte-level BPE vocabulary (GPT2, RoBERTa) to the byte it represents.
+/// Printable bytes are represented by the character with the same code point, the remaining bytes
+/// are mapped to the code points following 255, in increasing order.
+fn byte_level_char_to_byte(character: char) -> Option<u8> {
+    let is_printable_byte = |byte: u32| matches!(byte, 33..=126 | 161..=172 | 174..=255);
+    let code_point = character as u32;
+    if code_point < 256 {
+        return if is_printable_byte(code_point) {
+            Some(code_point as u8)
+        } else {
+            None
+        };
+    }
+    (0..256u32)
+        .filter(|byte| !is_printable_byte(*byte))
+        .nth((code_point - 256) as usize)
+        .map(|byte| byte as u8)
+}
+
+/// Computes the mean entropy of the attention distributions of each layer and head.
+/// The entropy of each attention distribution (over the attended positions) is averaged over
+/// the batch and query positions. Values range from 0 (attention focused on a single position)
+/// to `ln(seq_len)` (uniform attention over the attended sequence). Note that padding positions are
+/// included in the average.
+///
+/// # Arguments
+///
+/// * `attentions` - attention weights returned by a model when `output_attentions` is enabled,
+/// with one tensor of shape (*batch size*, *number of heads*, *query length*, *key length*) per layer
+///
+/// # Returns
+///
+/// * `Result<Vec<Vec<f32>>, RustBertError>` - mean attention entropy (in nats) indexed by layer and head
+///
+/// # Example
+///
+/// ```no_run
+/// # use tch::{Device, Kind, Tensor};
+/// use rust_bert::pipelines::common::attention_entropy;
+///
+/// // Uniform attention over 4 positions for 1 layer, 2 heads
+/// let attentions = vec![Tensor::ones(&[1, 2, 4, 4], (Kind::Float, Device::Cpu)) / 4];
+/// let entropy = attention_entropy(&attentions).unwrap();
+/// assert!((entropy[0][0] - 4f32.ln()).abs() < 1e-6);
+/// ```
+pub fn attention_entropy(attentions: &[Tensor]) -> Result<Vec<Vec<f32>>, RustBertError> {
+    attentions
+        .iter()
+        .map(|layer_attentions| {
+            if layer_attentions.dim() != 4 {
+                return Err(RustBertError::ValueError(format!(
+                    "Attention weights must be of shape (batch size, number of heads, query length, key length), got {:?}",
+                    layer_attentions.size()
+                )));
+            }
+            let probabilities = layer_attentions.to_kind(Kind::Float);
+            let entropy = -(&probabilities * probabilities.clamp_min(1e-12).log()).sum_dim_intlist(
+                [-1].as_slice(),
+                false,
+                Kind::Float,
+            );
+            let head_entropy = entropy.mean_dim([0, 2].as_slice(), false, Kind::Float);
+            Ok(head_entropy
+                .iter::<f64>()?
+                .map(|value| value as f32)
+                .collect::<Vec<f32>>())
+        })
+        .collect()
+}
+
+/// Ranks the attention heads by the attention mass they place on a set of tokens, e.g. the tokens of an entity
+/// predicted by a token classification model or the `[CLS]` token pooled by a sequence classification head.
+/// The score of a head is the attention weight on the given key positions, summed over these positions and averaged
+/// over the batch and query positions. Scores range from 0 (no attention on the tokens) to 1 (all attention on the tokens).
+///
+/// # Arguments
+///
+/// * `attentions` - attention weights returned by a model when `output_attentions` is enabled,
+/// with one tensor of shape (*batch size*, *number of heads*, *query length*, *key length*) per layer
+/// * `token_positions` - positions of the relevant tokens in the input sequence
+/// * `top_k` - maximum number of attention heads returned
+///
+/// # Returns
+///
+/// * `Result<Vec<(usize, usize, f32)>, RustBertError>` - layer, head and score of the `top_k` heads with the highest score,
+/// ranked by decreasing score
+///
+/// # Example
+///
+/// ```no_run
+/// # use tch::{Device, Kind, Tensor};
+/// use rust_bert::pipelines::common::top_attention_heads;
+///
+/// // Uniform attention over 4 positions for 1 layer, 2 heads
+/// let attentions = vec![Tensor::ones(&[1, 2, 4, 4], (Kind::Float, Device::Cpu)) / 4];
+/// let top_heads = top_attention_heads(&attentions, &[0, 1], 1).unwrap();
+/// assert_eq!(top_heads.len(), 1);
+/// assert!((top_heads[0].2 - 0.5).abs() < 1e-6);
+/// ```
+pub fn top_attention_heads(
+    attentions: &[Tensor],
+    token_positions: &[i64],
+    top_k: usize,
+) -> Result<Vec<(usize, usize, f32)>, RustBertError> {
+    let mut head_scores = Vec::new();
+    for (layer, layer_attentions) in attentions.iter().enumerate() {
+        if layer_attentions.dim() != 4 {
+            return Err(RustBertError::ValueError(format!(
+                "Attention weights must be of shape (batch size, number of heads, query length, key length), got {:?}",
+                layer_attentions.size()
+            )));
+        }
+        let key_length = layer_attentions.size()[3];
+        if let Some(position) = token_positions
+            .iter()
+            .find(|position| **position < 0 || **position >= key_length)
+        {
+            return Err(RustBertError::ValueError(format!(
+                "Token position {} is out of range for attention weights over {} positions",
+                position, key_length
+            )));
+        }
+        let positions = Tensor::of_slice(token_positions).to(layer_attentions.device());
+        let attention_mass = layer_attentions
+            .to_kind(Kind::Float)
+            .index_select(-1, &positions)
+            .sum_dim_intlist([-1].as_slice(), false, Kind::Float)
+            .mean_dim([0, 2].as_slice(), false, Kind::Float);
+        head_scores.extend(
+            attention_mass
+                .iter::<f64>()?
+                .enumerate()
+                .map(|(head, score)| (layer, head, score as f32)),
+        );
+    }
+    head_scores.sort_by(|(_, _, score_a), (_, _, score_b)| score_b.total_cmp(score_a));
+    head_scores.truncate(top_k);
+    Ok(head_scores)
+}
+
+/// Checks that the resources of a model are available and consistent before loading it.
+/// All resources are checked for availability without downloading them (a HEAD request is sent for remote resources).
+/// The configuration and vocabulary (and merges, if any) are then loaded to check that the configuration is valid JSON
+/// defining a `vocab_size`, and that the tokenizer vocabulary fits in the model vocabulary. The model weights are not downloaded.
+///
+/// # Arguments
+///
+/// * `model_type` - `ModelType` of the model
+/// * `config_resource` - configuration resource
+/// * `vocab_resource` - vocabulary resource
+/// * `merges_resource` - optional merges resource (for BPE tokenizers)
+/// * `model_resource` - model weights resource
+///
+/// # Returns
+///
+/// * `Result<(), RustBertError>` - a descriptive error if a resource is unavailable or inconsistent
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::gpt2::{
+///     Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
+/// };
+/// use rust_bert::pipelines::common::{validate_resources, ModelType};
+/// use rust_bert::resources::RemoteResource;
+///
+/// validate_resources(
+///     ModelType::GPT2,
+///     &RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2),
+///     &RemoteResource::from_pretrained(Gpt2VocabResources::GPT2),
+///     Some(&RemoteResource::from_pretrained(Gpt2MergesResources::GPT2)),
+///     &RemoteResource::from_pretrained(Gpt2ModelResources::GPT2),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn validate_resources(
+    model_type: ModelType,
+    config_resource: &dyn ResourceProvider,
+    vocab_resource: &dyn ResourceProvider,
+    merges_resource: Option<&dyn ResourceProvider>,
+    model_resource: &dyn ResourceProvider,
+) -> Result<(), RustBertError> {
+    config_resource.validate()?;
+    vocab_resource.validate()?;
+    if let Some(merges_resource) = merges_resource {
+        merges_resource.validate()?;
+    }
+    model_resource.validate()?;
+
+    let config_path = config_resource.get_local_path()?;
+    let config: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&config_path)?)).map_err(|error| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Could not parse configuration {}: {}",
+                config_path.display(),
+                error
+            ))
+        })?;
+    let config_vocab_size = config
+        .get("vocab_size")
+        .and_then(|vocab_size| vocab_size.as_u64())
+        .ok_or_else(|| {
+            RustBertError::InvalidConfigurationError(format!(
+                "Configuration {} does not define a `vocab_size`",
+                config_path.display()
+            ))
+        })? as usize;
+
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource
+        .map(|merges_resource| merges_resource.get_local_path())
+        .transpose()?;
+    let tokenizer = TokenizerOption::from_file(
+        model_type,
+        path_to_str(&vocab_path)?,
+        merges_path.as_deref().map(path_to_str).transpose()?,
+        false,
+        None,
+        None,
+    )?;
+    let tokenizer_vocab_size = tokenizer.get_vocab_size();
+    if tokenizer_vocab_size > config_vocab_size {
+        return Err(RustBertError::InvalidConfigurationError(format!(
+            "The vocabulary size of the tokenizer ({}) exceeds the vocabulary size of the model configuration ({})",
+            tokenizer_vocab_size, config_vocab_size
+        )));
+    }
+    Ok(())
+}
+
+fn path_to_str(path: &Path) -> Result<&str, RustBertError> {
+    path.to_str().ok_or_else(|| {
+        RustBertError::InvalidConfigurationError(format!(
+            "The resource path {} is not valid UTF-8",
+            path.display()
+        ))
+    })
+}
+
+/// Collapses the runs of whitespace characters (including newlines) of a text into single spaces
+/// and trims its leading and trailing whitespace.
+pub(crate) fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Truncates a text to at most `max_chars` characters, cutting at the last whitespace before the
+/// limit so that no word is split. If `ellipsis` is set, an ellipsis character (`…`) counted in the
+/// limit is appended to the truncated texts. Texts within the limit are returned unchanged.
+pub(crate) fn truncate_to_word_boundary(text: &str, max_chars: usize, ellipsis: bool) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let text_chars = if ellipsis && max_chars > 0 {
+        max_chars - 1
+    } else {
+        max_chars
+    };
+    // Cutting before the first character past the limit splits a word unless this character is a whitespace
+    let (end, next_character) = text
+        .char_indices()
+        .nth(text_chars)
+        .expect("the text is longer than the limit");
+    let truncated = if next_character.is_whitespace() {
+        &text[..end]
+    } else {
+        text[..end]
+            .rfind(char::is_whitespace)
+            .map_or("", |position| &text[..position])
+    };
+    let truncated = truncated.trim_end();
+    if ellipsis && max_chars > 0 {
+        format!("{}\u{2026}", truncated)
+    } else {
+        truncated.to_string()
+    }
+}
+
+/// Splits the input texts into the texts to be processed by a model and a flag marking the
+/// empty (or whitespace-only) inputs, for which no forward pass is run.
+pub(crate) fn split_empty_inputs<S>(texts: &[S]) -> (Vec<&str>, Vec<bool>)
+where
+    S: AsRef<str>,
+{
+    let empty_inputs = texts
+        .iter()
+        .map(|text| text.as_ref().trim().is_empty())
+        .collect::<Vec<bool>>();
+    let non_empty_texts = texts
+        .iter()
+        .zip(empty_inputs.iter())
+        .filter(|(_, &is_empty)| !is_empty)
+        .map(|(text, _)| text.as_ref())
+        .collect::<Vec<&str>>();
+    (non_empty_texts, empty_inputs)
+}
+
+/// Merges the outputs generated for the non-empty inputs (see `split_empty_inputs`) with the
+/// output returned for the empty inputs, restoring the order of the inputs. Models returning
+/// several outputs per input (e.g. `num_return_sequences` > 1) return as many empty outputs.
+pub(crate) fn merge_empty_outputs<T, F>(
+    outputs: Vec<T>,
+    empty_inputs: &[bool],
+    empty_output: F,
+) -> Vec<T>
+where
+    F: Fn() -> T,
+{
+    let num_non_empty_inputs = empty_inputs.iter().filter(|&&is_empty| !is_empty).count();
+    let outputs_per_input = if num_non_empty_inputs > 0 {
+        outputs.len() / num_non_empty_inputs
+    } else {
+        1
+    };
+    let mut outputs = outputs.into_iter();
+    let mut merged_outputs = Vec::with_capacity(empty_inputs.len() * outputs_per_input);
+    for &is_empty in empty_inputs {
+        for _ in 0..outputs_per_input {
+            if is_empty {
+                merged_outputs.push(empty_output());
+            } else if let Some(output) = outputs.next() {
+                merged_outputs.push(output);
+            }
+        }
+    }
+    merged_outputs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate_to_word_boundary_words() {
+        let text = "The cat was found in a field near the town of Keflavik";
+
+        //    The limit falls within "town": the text is cut before this word
+        let truncated = truncate_to_word_boundary(text, 40, false);
+        assert_eq!(truncated, "The cat was found in a field near the");
+        assert!(text.starts_with(&format!("{} ", truncated)));
+
+        //    The limit falls right after "town"
+        assert_eq!(
+            truncate_to_word_boundary(text, 42, false),
+            "The cat was found in a field near the town"
+        );
+
+        //    The ellipsis counts in the limit
+        let truncated = truncate_to_word_boundary(text, 43, true);
+        assert_eq!(
+            truncated,
+            "The cat was found in a field near the town\u{2026}"
+        );
+        assert_eq!(truncated.chars().count(), 43);
+
+        //    Texts within the limit are not truncated
+        assert_eq!(truncate_to_word_boundary(text, 200, true), text);
+        //    A first word longer than the limit is dropped
+        assert_eq!(truncate_to_word_boundary(text, 2, false), "");
+        assert_eq!(truncate_to_word_boundary(text, 0, true), "");
+        //    Characters are counted rather than bytes
+        assert_eq!(
+            truncate_to_word_boundary("caf\u{e9} au lait", 7, false),
+            "caf\u{e9} au"
+        );
+    }
 }
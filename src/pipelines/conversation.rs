@@ -29,14 +29,14 @@
 //!
 //! let conversation_id =
 //!     conversation_manager.create("Going to the movies tonight - any suggestions?");
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //!
 //! let _ = conversation_manager
 //!     .get(&conversation_id)
 //!     .unwrap()
 //!     .add_user_input("Is it an action movie?")?;
 //!
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //!
 //! # Ok(())
 //! # }
@@ -55,9 +55,11 @@
 //! from the 3rd party utilization of the pretrained system.
 use crate::common::error::RustBertError;
 use crate::gpt2::GPT2Generator;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    set_num_threads, truncate_to_word_boundary, ModelType, PaddingSide, TokenizerOption,
+};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator, ScoreType};
 use crate::resources::ResourceProvider;
 use std::collections::HashMap;
 use tch::{Device, Kind, Tensor};
@@ -76,17 +78,19 @@ pub struct ConversationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: DialoGPT-medium)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: DialoGPT-medium)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: DialoGPT-medium)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: DialoGPT-medium)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Minimum sequence length (default: 0)
     pub min_length: i64,
     /// Maximum sequence length (default: 20)
     pub max_length: Option<i64>,
+    /// Maximum number of generated tokens, regardless of the length of the input. Takes precedence over `max_length` when set (default: None)
+    pub max_new_tokens: Option<i64>,
     /// Minimum free length available for generated responses (default: 32)
     pub min_length_for_response: i64,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
@@ -97,24 +101,75 @@ pub struct ConversationConfig {
     pub num_beams: i64,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
     pub temperature: f64,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature`. The last value persists for the steps past the end of the schedule (default: None)
+    pub temperature_schedule: Option<Vec<f64>>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling. If provided, the
+    /// temperature is raised towards `adaptive_temperature_max` with the fraction of repeated tokens in this window (default: None)
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature (default: 2.0)
+    pub adaptive_temperature_max: f64,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
     pub top_k: i64,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
     pub top_p: f64,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token, applied after the temperature (default: None)
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
     pub repetition_penalty: f64,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence, penalizing the tokens
+    /// repeated often more than the multiplicative `repetition_penalty` (default: 0.0)
+    pub frequency_penalty: f64,
+    /// Presence penalty subtracted from the logit of the tokens present in the sequence, regardless of their number of
+    /// occurrences (default: 0.0)
+    pub presence_penalty: f64,
     /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
     pub no_repeat_ngram_size: i64,
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking (default: None, the full sequence is considered)
+    pub repetition_window: Option<usize>,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
+    /// Score used to rank the sequences returned by beam search, and returned as their score (default: `ScoreType::Normalized`)
+    pub rank_by: ScoreType,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Strings terminating the generation of a sequence when generated (default: empty)
+    pub stop_strings: Vec<String>,
+    /// Opening and closing characters terminating the generation of a sequence once the brackets opened in the generated text
+    /// are balanced, accounting for nested brackets. Only supported by greedy decoding and sampling (default: None)
+    pub stop_brackets: Option<(char, char)>,
+    /// Bias added to the logits of the given token ids at every generation step (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled (default: 0)
+    pub greedy_prefix_len: usize,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection (default: false)
+    pub sanitize_logits: bool,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection, for the numerical stability of models producing extreme logits. Banned tokens are still excluded (default: None)
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance. Only supported by greedy decoding and sampling (default: None)
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)` (default: 0.0, disabled)
+    pub guidance_scale: f64,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention, trading quality for bounded memory. Only supported by GPT2 and T5 (default: None, unbounded cache)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache never evicted by the cache window (attention sink), counted within the window (default: 0)
+    pub kv_cache_sink_tokens: usize,
+    /// Device the key/value cache is kept on between generation steps, transferred to the model device at each step at the cost of a higher latency (default: None, the cache stays on the model device)
+    pub kv_cache_device: Option<Device>,
+    /// Flag indicating if special tokens should be removed from the decoded responses (default: true)
+    pub skip_special_tokens: bool,
+    /// Maximum number of characters of the decoded responses. Longer responses are truncated at the last whitespace
+    /// before the limit so that no word is split, the conversation history keeping the full responses (default: None, no truncation)
+    pub max_output_chars: Option<usize>,
+    /// Flag indicating if an ellipsis (`…`), counted in `max_output_chars`, should be appended to the truncated responses (default: false)
+    pub output_ellipsis: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
 }
 
 #[cfg(feature = "remote")]
@@ -136,20 +191,44 @@ impl Default for ConversationConfig {
             ))),
             min_length: 0,
             max_length: Some(1000),
+            max_new_tokens: None,
             min_length_for_response: 64,
             do_sample: true,
             early_stopping: false,
             num_beams: 1,
             temperature: 1.0,
+            temperature_schedule: None,
+            adaptive_temperature_window: None,
+            adaptive_temperature_max: 2.0,
             top_k: 50,
             top_p: 0.9,
+            min_p: None,
             repetition_penalty: 1.0,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 0,
+            repetition_window: None,
             num_return_sequences: 1,
+            rank_by: ScoreType::Normalized,
             num_beam_groups: None,
             diversity_penalty: None,
+            stop_strings: vec![],
+            stop_brackets: None,
+            logit_bias: HashMap::new(),
+            greedy_prefix_len: 0,
+            sanitize_logits: false,
+            logit_clamp: None,
+            negative_prompt: None,
+            guidance_scale: 0.0,
+            kv_cache_window: None,
+            kv_cache_sink_tokens: 0,
+            kv_cache_device: None,
+            skip_special_tokens: true,
+            max_output_chars: None,
+            output_ellipsis: false,
             device: Device::cuda_if_available(),
+            num_threads: None,
         }
     }
 }
@@ -163,18 +242,44 @@ impl From<ConversationConfig> for GenerateConfig {
             vocab_resource: config.vocab_resource,
             min_length: config.min_length,
             max_length: config.max_length,
+            max_new_tokens: config.max_new_tokens,
             do_sample: config.do_sample,
             early_stopping: config.early_stopping,
             num_beams: config.num_beams,
             temperature: config.temperature,
+            temperature_schedule: config.temperature_schedule,
+            adaptive_temperature_window: config.adaptive_temperature_window,
+            adaptive_temperature_max: config.adaptive_temperature_max,
             top_k: config.top_k,
             top_p: config.top_p,
+            min_p: config.min_p,
             repetition_penalty: config.repetition_penalty,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            repetition_window: config.repetition_window,
             num_return_sequences: config.num_return_sequences,
+            rank_by: config.rank_by,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            stop_strings: config.stop_strings,
+            stop_brackets: config.stop_brackets,
+            logit_bias: config.logit_bias,
+            greedy_prefix_len: config.greedy_prefix_len,
+            sanitize_logits: config.sanitize_logits,
+            logit_clamp: config.logit_clamp,
+            negative_prompt: config.negative_prompt,
+            guidance_scale: config.guidance_scale,
+            kv_cache_window: config.kv_cache_window,
+            kv_cache_sink_tokens: config.kv_cache_sink_tokens,
+            kv_cache_device: config.kv_cache_device,
+            padding_side: PaddingSide::Left,
+            skip_special_tokens: config.skip_special_tokens,
+            keep_pad_tokens: false,
+            unk_replacement: None,
+            control_characters: None,
+            output_attentions: false,
             device: config.device,
         }
     }
@@ -729,19 +834,26 @@ impl ConversationOption {
         }
     }
 
+    /// Interface method to unload() of the particular models.
+    pub fn unload(self) {
+        match self {
+            Self::GPT2(model) => model.unload(),
+        }
+    }
+
     /// Interface method to generate_from_ids_and_past() of the particular models.
     pub fn generate_from_ids_and_past(
         &self,
         input_ids: Tensor,
         attention_mask: Option<Tensor>,
-    ) -> Vec<Vec<i64>> {
-        match *self {
+    ) -> Result<Vec<Vec<i64>>, RustBertError> {
+        Ok(match *self {
             Self::GPT2(ref model) => model
-                .generate_from_ids_and_past(input_ids, attention_mask, None)
+                .generate_from_ids_and_past(input_ids, attention_mask, None)?
                 .into_iter()
                 .map(|output| output.indices)
                 .collect(),
-        }
+        })
     }
 }
 
@@ -751,6 +863,9 @@ pub struct ConversationModel {
     model: ConversationOption,
     eos_token_id: i64,
     max_allowed_context_length: Option<i64>,
+    skip_special_tokens: bool,
+    max_output_chars: Option<usize>,
+    output_ellipsis: bool,
     device: Device,
 }
 
@@ -774,9 +889,13 @@ impl ConversationModel {
     pub fn new(
         conversation_config: ConversationConfig,
     ) -> Result<ConversationModel, RustBertError> {
+        set_num_threads(conversation_config.num_threads)?;
         let max_allowed_length = conversation_config
             .max_length
             .map(|max_length| max_length - conversation_config.min_length_for_response);
+        let skip_special_tokens = conversation_config.skip_special_tokens;
+        let max_output_chars = conversation_config.max_output_chars;
+        let output_ellipsis = conversation_config.output_ellipsis;
         let device = conversation_config.device;
         let model = ConversationOption::new(conversation_config)?;
         let eos_token_id = model.get_eos_id()?;
@@ -784,10 +903,18 @@ impl ConversationModel {
             model,
             eos_token_id,
             max_allowed_context_length: max_allowed_length,
+            skip_special_tokens,
+            max_output_chars,
+            output_ellipsis,
             device,
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        self.model.unload();
+    }
+
     /// Perform a multi-turn conversation based on user input
     ///
     /// # Arguments
@@ -795,7 +922,7 @@ impl ConversationModel {
     /// * `conversation_manager` - `&mut ConversationManager` Conversation manager keeping track of active conversations
     ///
     /// # Returns
-    /// * `HashMap<&Uuid, &str>` Responses from the model for each active conversation, referenced by Uuid
+    /// * `Result<HashMap<&Uuid, &str>, RustBertError>` Responses from the model for each active conversation, referenced by Uuid
     ///
     /// # Example
     ///
@@ -808,14 +935,14 @@ impl ConversationModel {
     /// let mut conversation_manager = ConversationManager::new();
     /// conversation_manager.create("Hello, how are you?");
     ///
-    /// let output = model.generate_responses(&mut conversation_manager);
+    /// let output = model.generate_responses(&mut conversation_manager)?;
     /// # Ok(())
     /// # }
     /// ```
     pub fn generate_responses<'a>(
         &self,
         conversation_manager: &'a mut ConversationManager,
-    ) -> HashMap<&'a Uuid, &'a str> {
+    ) -> Result<HashMap<&'a Uuid, &'a str>, RustBertError> {
         let (active_uuid, active_conversations) = conversation_manager.get_active_conversations();
         if !active_uuid.is_empty() {
             let texts = active_conversations
@@ -834,7 +961,7 @@ impl ConversationModel {
             let input_length = *input_tensor.size().last().unwrap() as usize;
             let mut generated = self
                 .model
-                .generate_from_ids_and_past(input_tensor, Some(attention_mask));
+                .generate_from_ids_and_past(input_tensor, Some(attention_mask))?;
             let removed_padding_quantities = self.clean_padding_indices(&mut generated);
 
             let mut output = HashMap::with_capacity(active_uuid.len());
@@ -849,21 +976,26 @@ impl ConversationModel {
                 .zip(removed_padding_quantities.into_iter())
             {
                 let generated_response = &generated_sequence[input_length - removed_padding.0..];
-                conversation
-                    .generated_responses
-                    .push(
-                        self.model
-                            .get_tokenizer()
-                            .decode(generated_response, true, true),
-                    );
+                let response = self.model.get_tokenizer().decode(
+                    generated_response,
+                    self.skip_special_tokens,
+                    true,
+                );
+                let response = match self.max_output_chars {
+                    Some(max_output_chars) => {
+                        truncate_to_word_boundary(&response, max_output_chars, self.output_ellipsis)
+                    }
+                    None => response,
+                };
+                conversation.generated_responses.push(response);
                 conversation.history.push(conversation_promp_ids);
                 conversation.history.push(generated_response.to_vec());
                 conversation.mark_processed();
                 output.insert(uuid, conversation.get_last_response().unwrap());
             }
-            output
+            Ok(output)
         } else {
-            HashMap::new()
+            Ok(HashMap::new())
         }
     }
 
@@ -1035,9 +1167,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = ConversationConfig::default();
-        let _: Box<dyn Send> = Box::new(ConversationModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(ConversationModel::new(config));
     }
 }
@@ -0,0 +1,380 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Cross-encoder re-ranking pipeline
+//! Scores the relevance of documents to a query by encoding each (query, document) pair with a sequence
+//! classification model, and returns the documents ranked by decreasing relevance (e.g. to re-rank the
+//! candidates retrieved with sentence embeddings). The query is tokenized once and shared by all candidates.
+//!
+//! Models with a single output label (e.g. MS MARCO cross-encoders) are scored with the sigmoid of their logit.
+//! For models with several labels, the probability of the last label is used as relevance score (e.g. the
+//! entailment probability of Natural Language Inference models).
+//!
+//! ```no_run
+//! use rust_bert::pipelines::cross_encoder::CrossEncoderModel;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let cross_encoder = CrossEncoderModel::new(Default::default())?;
+//! let query = "How many people live in Berlin?";
+//! let documents = [
+//!     "Berlin is well known for its museums.",
+//!     "Berlin has a population of 3,520,031 registered inhabitants.",
+//! ];
+//! let output = cross_encoder.rank(query, &documents)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::common::{
+    release_device_memory, set_num_threads, ConfigOption, ModelType, PaddingSide, TokenizerOption,
+};
+use crate::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationOption,
+};
+use rust_tokenizers::tokenizer::{truncate_sequences, TruncationStrategy};
+use rust_tokenizers::TokenIdsWithOffsets;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tch::kind::Kind::Bool;
+use tch::nn::VarStore;
+use tch::{no_grad, Device, Kind, Tensor};
+
+/// Configuration for the cross-encoder model
+pub type CrossEncoderConfig = SequenceClassificationConfig;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// # Document ranked by a `CrossEncoderModel`
+pub struct RankedDocument {
+    /// Index of the document in the input documents
+    pub index: usize,
+    /// Text of the document
+    pub text: String,
+    /// Relevance score of the document for the query
+    pub score: f64,
+}
+
+#[derive(Debug)]
+/// # Encoded (query, document) pairs of a `CrossEncoderModel`
+/// Output of `CrossEncoderModel::encode`, that may be modified before being scored with `CrossEncoderModel::score`.
+/// The tensors are of shape (*number of documents*, *sequence length*) and placed on the device of the model.
+pub struct CrossEncoderInput {
+    /// Token ids of the pairs, padded on the right
+    pub input_ids: Tensor,
+    /// Attention mask, with a value of 0 for padding positions
+    pub attention_mask: Tensor,
+    /// Token type (segment) ids, 0 for the query tokens and 1 for the document tokens (depending on the tokenizer).
+    /// They are only passed to the models trained with segment embeddings (BERT, ALBERT, MobileBERT).
+    pub token_type_ids: Tensor,
+}
+
+/// # CrossEncoderModel to re-rank documents for a query
+pub struct CrossEncoderModel {
+    tokenizer: Arc<TokenizerOption>,
+    sequence_classifier: SequenceClassificationOption,
+    var_store: VarStore,
+    max_length: usize,
+}
+
+impl CrossEncoderModel {
+    /// Build a new `CrossEncoderModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `CrossEncoderConfig` object containing the resource references (model, vocabulary, configuration) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::cross_encoder::CrossEncoderModel;
+    ///
+    /// let model = CrossEncoderModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(config: CrossEncoderConfig) -> Result<CrossEncoderModel, RustBertError> {
+        let vocab_path = config.vocab_resource.get_local_path()?;
+        let merges_path = if let Some(merges_resource) = &config.merges_resource {
+            Some(merges_resource.get_local_path()?)
+        } else {
+            None
+        };
+
+        let tokenizer = TokenizerOption::from_file(
+            config.model_type,
+            vocab_path.to_str().unwrap(),
+            merges_path.as_deref().map(|path| path.to_str().unwrap()),
+            config.lower_case,
+            config.strip_accents,
+            config.add_prefix_space,
+        )?;
+        Self::new_with_tokenizer(config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `CrossEncoderModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `CrossEncoderConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    pub fn new_with_tokenizer(
+        config: CrossEncoderConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<CrossEncoderModel, RustBertError> {
+        set_num_threads(config.num_threads)?;
+        tokenizer.validate_model_type(config.model_type)?;
+        let config_path = config.config_resource.get_local_path()?;
+        let weights_path = config.model_resource.get_local_path()?;
+
+        let mut var_store = VarStore::new(config.device);
+        let model_config = ConfigOption::from_file(config.model_type, config_path);
+        let max_length = model_config.get_max_sequence_length().unwrap_or(usize::MAX);
+        let sequence_classifier =
+            SequenceClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
+        var_store.load(weights_path)?;
+        Ok(CrossEncoderModel {
+            tokenizer,
+            sequence_classifier,
+            var_store,
+            max_length,
+        })
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
+    /// Scores the relevance of documents to a query and ranks them by decreasing score.
+    /// The (query, document) pairs are truncated to the maximum sequence length of the model, starting with the longest sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - `&str` query the documents are ranked for
+    /// * `documents` - `&[&str]` candidate documents
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<RankedDocument>, RustBertError>` documents with their index and relevance score, ranked by decreasing score
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::cross_encoder::CrossEncoderModel;
+    ///
+    /// let cross_encoder = CrossEncoderModel::new(Default::default())?;
+    /// let output = cross_encoder.rank(
+    ///     "Where is the Eiffel tower?",
+    ///     &["The Eiffel tower is located in Paris.", "The tower is 324 metres tall."],
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rank<S>(
+        &self,
+        query: &str,
+        documents: &[S],
+    ) -> Result<Vec<RankedDocument>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        if documents.is_empty() {
+            return Ok(vec![]);
+        }
+        let input = self.encode(query, documents)?;
+        let mut ranked_documents = self
+            .score(&input)?
+            .into_iter()
+            .zip(documents.iter())
+            .enumerate()
+            .map(|(index, (score, document))| RankedDocument {
+                index,
+                text: document.as_ref().to_string(),
+                score,
+            })
+            .collect::<Vec<RankedDocument>>();
+        ranked_documents.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        Ok(ranked_documents)
+    }
+
+    /// Encodes the (query, document) pairs scored by the model, exposing their token type ids (e.g. to inspect or
+    /// override them before scoring the pairs with `score`). The pairs are truncated to the maximum sequence length
+    /// of the model, starting with the longest sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - `&str` query the documents are encoded with
+    /// * `documents` - `&[&str]` candidate documents, at least one document must be provided
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CrossEncoderInput, RustBertError>` token ids, attention mask and token type ids of the pairs
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::cross_encoder::CrossEncoderModel;
+    ///
+    /// let cross_encoder = CrossEncoderModel::new(Default::default())?;
+    /// let mut input = cross_encoder.encode(
+    ///     "Where is the Eiffel tower?",
+    ///     &["The Eiffel tower is located in Paris."],
+    /// )?;
+    /// input.token_type_ids = input.token_type_ids.zeros_like();
+    /// let scores = cross_encoder.score(&input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn encode<S>(
+        &self,
+        query: &str,
+        documents: &[S],
+    ) -> Result<CrossEncoderInput, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        if documents.is_empty() {
+            return Err(RustBertError::ValueError(
+                "At least one document must be provided".to_string(),
+            ));
+        }
+        let query_ids = self
+            .tokenizer
+            .convert_tokens_to_ids(&self.tokenizer.tokenize(query));
+        let sequence_pair_added_tokens = self
+            .tokenizer
+            .build_input_with_special_tokens(
+                TokenIdsWithOffsets {
+                    ids: vec![],
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                },
+                Some(TokenIdsWithOffsets {
+                    ids: vec![],
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                }),
+            )
+            .token_ids
+            .len();
+
+        let mut token_ids = Vec::with_capacity(documents.len());
+        let mut segment_ids = Vec::with_capacity(documents.len());
+        for document in documents {
+            let document_ids = self
+                .tokenizer
+                .convert_tokens_to_ids(&self.tokenizer.tokenize(document.as_ref()));
+            let num_truncated_tokens =
+                (query_ids.len() + document_ids.len() + sequence_pair_added_tokens)
+                    .saturating_sub(self.max_length);
+            let (query_input, document_input, _, _) = truncate_sequences(
+                TokenIdsWithOffsets {
+                    ids: query_ids.clone(),
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                },
+                Some(TokenIdsWithOffsets {
+                    ids: document_ids,
+                    offsets: vec![],
+                    reference_offsets: vec![],
+                    masks: vec![],
+                }),
+                num_truncated_tokens,
+                &TruncationStrategy::LongestFirst,
+                0,
+            )?;
+            let tokenized_input = self
+                .tokenizer
+                .build_input_with_special_tokens(query_input, document_input);
+            segment_ids.push(
+                tokenized_input
+                    .segment_ids
+                    .into_iter()
+                    .map(|segment_id| segment_id as i64)
+                    .collect::<Vec<i64>>(),
+            );
+            token_ids.push(tokenized_input.token_ids);
+        }
+
+        let device = self.var_store.device();
+        let (input_ids, attention_mask) =
+            self.tokenizer
+                .pad_token_ids(token_ids, None, PaddingSide::Right);
+        let (token_type_ids, _) =
+            self.tokenizer
+                .pad_token_ids(segment_ids, Some(0), PaddingSide::Right);
+        Ok(CrossEncoderInput {
+            input_ids: input_ids.to(device),
+            attention_mask: attention_mask.to(device),
+            token_type_ids: token_type_ids.to(device),
+        })
+    }
+
+    /// Scores encoded (query, document) pairs (see `encode`), allowing to provide custom token type ids.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&CrossEncoderInput` encoded pairs
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<f64>, RustBertError>` relevance score of each pair, in the order of the encoded documents
+    pub fn score(&self, input: &CrossEncoderInput) -> Result<Vec<f64>, RustBertError> {
+        let device = self.var_store.device();
+        // Only the models trained with segment embeddings receive the token type ids
+        let token_type_ids = match self.sequence_classifier.model_type() {
+            ModelType::Bert | ModelType::Albert | ModelType::MobileBert => {
+                Some(input.token_type_ids.to(device))
+            }
+            _ => None,
+        };
+
+        let scores = no_grad(|| {
+            let logits = self.sequence_classifier.forward_t(
+                Some(&input.input_ids.to(device)),
+                Some(&input.attention_mask.to_kind(Bool).to(device)),
+                token_type_ids.as_ref(),
+                None,
+                None,
+                false,
+            );
+            let scores = if logits.size()[1] == 1 {
+                logits.squeeze_dim(1).sigmoid()
+            } else {
+                logits.softmax(-1, Kind::Float).select(1, -1)
+            };
+            scores.to_kind(Kind::Double).to(Device::Cpu)
+        });
+        Ok(scores.iter::<f64>()?.collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
+    fn test() {
+        let config = CrossEncoderConfig::default();
+        let _: Box<dyn Send + Sync> = Box::new(CrossEncoderModel::new(config));
+    }
+}
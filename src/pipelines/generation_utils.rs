@@ -47,7 +47,7 @@
 //! let output = gpt2_generator.generate(
 //!     Some(&[input_context, second_input_context]),
 //!     Some(generate_options),
-//! );
+//! )?;
 //! # Ok(())
 //! # }
 //! ```
@@ -66,6 +66,9 @@
 //! # ;
 //! ```
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use rust_tokenizers::tokenizer::Tokenizer;
 use rust_tokenizers::vocab::Vocab;
 use tch::kind::Kind::Int64;
@@ -76,7 +79,7 @@ use crate::common::error::RustBertError;
 use crate::common::resources::ResourceProvider;
 use crate::gpt_neo::LayerState as GPTNeoLayerState;
 use crate::pipelines::generation_utils::private_generation_utils::{
-    InternalGenerateOptions, PrivateLanguageGenerator,
+    BeamSearchLoopState, GenerationPrompts, GenerationStart, PrivateLanguageGenerator,
 };
 use crate::prophetnet::LayerState as ProphetNetLayerState;
 use crate::reformer::LayerState as ReformerLayerState;
@@ -84,7 +87,9 @@ use crate::t5::LayerState as T5LayerState;
 use crate::xlnet::LayerState as XLNetLayerState;
 
 use self::ordered_float::OrderedFloat;
-use crate::pipelines::common::TokenizerOption;
+use crate::pipelines::common::{release_device_memory, PaddingSide, TokenizerOption};
+use crate::Config;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "remote")]
 use crate::{
@@ -94,20 +99,30 @@ use crate::{
 
 extern crate ordered_float;
 
+macro_rules! unpack_config {
+    ($field_name:ident, $generate_options: ident, $generate_config: ident) => {
+        $generate_options.map_or($generate_config.$field_name, |opts| {
+            opts.$field_name.unwrap_or($generate_config.$field_name)
+        })
+    };
+}
+
 /// # Configuration for text generation
 pub struct GenerateConfig {
     /// Model weights resource (default: pretrained GPT2 model)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained GPT2 model)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained GPT2 model)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: pretrained GPT2 model)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
-    /// Minimum sequence length (default: 0)
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
+    /// Minimum sequence length, must not exceed `max_length` (default: 0)
     pub min_length: i64,
     /// Maximum sequence length (default: 20)
     pub max_length: Option<i64>,
+    /// Maximum number of generated tokens, regardless of the length of the prompt. Takes precedence over `max_length` when set (default: None)
+    pub max_new_tokens: Option<i64>,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
     pub do_sample: bool,
     /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated (default: false)
@@ -116,22 +131,98 @@ pub struct GenerateConfig {
     pub num_beams: i64,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
     pub temperature: f64,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature` (e.g. decreasing for a sequence
+    /// starting creatively and ending more conservatively). The last value persists for the steps past the end of the schedule (default: None)
+    pub temperature_schedule: Option<Vec<f64>>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling. If provided, the
+    /// temperature of each sequence is raised from its base temperature towards `adaptive_temperature_max` proportionally to
+    /// the fraction of repeated tokens in this window, to help escaping repetition loops (default: None)
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature when all the tokens of its window are identical (default: 2.0)
+    pub adaptive_temperature_max: f64,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
     pub top_k: i64,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
     pub top_p: f64,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token, applied after the temperature (default: None)
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
     pub repetition_penalty: f64,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence (limited to the
+    /// `repetition_window` if provided), penalizing the tokens repeated often more than the tokens seen once. Applied after
+    /// the multiplicative `repetition_penalty`, negative values encourage repetitions (default: 0.0)
+    pub frequency_penalty: f64,
+    /// Presence penalty subtracted once from the logit of every token present in the sequence (limited to the
+    /// `repetition_window` if provided), regardless of its number of occurrences. Applied after the multiplicative
+    /// `repetition_penalty`, negative values encourage repetitions (default: 0.0)
+    pub presence_penalty: f64,
     /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
     pub no_repeat_ngram_size: i64,
-    /// Number of sequences to return for each prompt text (default: 1)
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking, tokens preceding
+    /// this window are neither penalized nor blocked (e.g. for long-form generation) (default: None, the full sequence is considered)
+    pub repetition_window: Option<usize>,
+    /// Number of sequences to return for each prompt text. Independent samples are drawn when sampling,
+    /// beam search returns at most `num_beams` sequences (default: 1)
     pub num_return_sequences: i64,
+    /// Score used to rank the sequences returned by beam search, and returned as their score. The hypotheses kept during
+    /// the search are selected with their length-normalized score in both cases (default: `ScoreType::Normalized`)
+    pub rank_by: ScoreType,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Strings terminating the generation of a sequence when generated. The stop string and the tokens overlapping with it are removed from the output (default: empty)
+    pub stop_strings: Vec<String>,
+    /// Opening and closing characters (e.g. `('{', '}')`) terminating the generation of a sequence once the brackets opened in the
+    /// generated text are balanced, accounting for nested brackets (e.g. for JSON or code). Closing characters generated before an
+    /// opening character are ignored. Only supported by greedy decoding and sampling, an `InvalidConfigurationError` being
+    /// returned for beam search (default: None)
+    pub stop_brackets: Option<(char, char)>,
+    /// Bias added to the logits of the given token ids at every generation step. Large negative values ban tokens, large positive values encourage them (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled (default: 0)
+    pub greedy_prefix_len: usize,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection (default: false)
+    pub sanitize_logits: bool,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection, for the numerical stability of models producing extreme logits. Banned tokens are still excluded (default: None)
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance. Only supported by greedy decoding and sampling, an `InvalidConfigurationError` being returned for beam search (default: None)
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)` (default: 0.0, disabled)
+    pub guidance_scale: f64,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention. The oldest
+    /// cached keys and values are dropped beyond this window, bounding the memory used by long generations at the cost
+    /// of a lower quality: the generated tokens no longer attend to the tokens outside of the window. Only supported by
+    /// GPT2 and T5 (default: None, unbounded cache)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache that are never evicted by the cache window (attention sink,
+    /// StreamingLLM). These positions are counted within `kv_cache_window`, the window keeping the most recent
+    /// `kv_cache_window - kv_cache_sink_tokens` positions after them. Keeping the first tokens in the cache stabilizes the
+    /// attention of long windowed generations (default: 0)
+    pub kv_cache_sink_tokens: usize,
+    /// Device the key/value cache is kept on between generation steps (e.g. the CPU for a model placed on the GPU),
+    /// reducing the memory used on the model device by long generations. The cache is transferred to the model
+    /// device before each step and back after it, adding the transfer of the full cache to the latency of every
+    /// generated token (default: None, the cache stays on the model device)
+    pub kv_cache_device: Option<Device>,
+    /// Side on which the prompts of a batch are padded. Decoder-only models generate from the end of the prompts and
+    /// must be padded on the left, their position ids being computed from the attention mask. Encoder-decoder models
+    /// always pad their prompts on the right and ignore this setting (default: `PaddingSide::Left`)
+    pub padding_side: PaddingSide,
+    /// Flag indicating if special tokens should be removed from the decoded output (default: true)
+    pub skip_special_tokens: bool,
+    /// Flag indicating if the padding tokens of the generated sequences should be kept in the decoded output, e.g. for
+    /// debugging. Padding tokens are otherwise always removed, even if the other special tokens are kept (default: false)
+    pub keep_pad_tokens: bool,
+    /// Placeholder replacing the unknown tokens in the decoded output, an empty placeholder drops them (default: None, unknown tokens are decoded as the other tokens)
+    pub unk_replacement: Option<String>,
+    /// Handling of the control characters (other than newlines and tabs) of the decoded output, e.g. to serialize it as JSON (default: None, control characters are kept)
+    pub control_characters: Option<ControlCharacters>,
+    /// Flag indicating if the model should be created with attention weights in its outputs, e.g. to derive
+    /// translation word alignments from the cross-attention weights (default: false)
+    pub output_attentions: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
 }
@@ -148,75 +239,746 @@ impl Default for GenerateConfig {
             ))),
             min_length: 0,
             max_length: Some(56),
+            max_new_tokens: None,
             do_sample: true,
             early_stopping: true,
             num_beams: 5,
             temperature: 1.0,
+            temperature_schedule: None,
+            adaptive_temperature_window: None,
+            adaptive_temperature_max: 2.0,
             top_k: 0,
             top_p: 0.9,
+            min_p: None,
             repetition_penalty: 1.0,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 3,
+            repetition_window: None,
             num_return_sequences: 1,
+            rank_by: ScoreType::Normalized,
             num_beam_groups: None,
             diversity_penalty: None,
+            stop_strings: vec![],
+            stop_brackets: None,
+            logit_bias: HashMap::new(),
+            greedy_prefix_len: 0,
+            sanitize_logits: false,
+            logit_clamp: None,
+            negative_prompt: None,
+            guidance_scale: 0.0,
+            kv_cache_window: None,
+            kv_cache_sink_tokens: 0,
+            kv_cache_device: None,
+            padding_side: PaddingSide::Left,
+            skip_special_tokens: true,
+            keep_pad_tokens: false,
+            unk_replacement: None,
+            control_characters: None,
+            output_attentions: false,
             device: Device::cuda_if_available(),
         }
     }
 }
 
 impl GenerateConfig {
-    pub(crate) fn validate(&self) {
-        assert!(self.temperature > 0f64, "temperature must positive");
-        assert!(
-            (self.top_p >= 0f64) & (self.top_p <= 1f64),
-            "top_p must be 0 and 1"
-        );
-        assert!(
-            self.repetition_penalty >= 1f64,
-            "repetition_penalty must be greater than 1"
-        );
-        assert!(
-            self.length_penalty > 0f64,
-            "length_penalty must be strictly greater than 0"
-        );
-        assert!(
-            self.num_return_sequences > 0i64,
-            "num_return_sequences must be strictly greater than 0"
-        );
-        assert!(
-            self.num_beams > 0i64,
-            "num_beams must be strictly greater than 0"
-        );
+    pub(crate) fn validate_token_ids(
+        &self,
+        tokenizer: &TokenizerOption,
+    ) -> Result<(), RustBertError> {
+        check_token_ids(tokenizer, self.logit_bias.keys().copied(), "logit_bias")
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), RustBertError> {
+        GenerationSettings::new(self, None).validate()
+    }
+}
+
+/// Settings of a generation resulting from the generate options, then the configuration of the model, validated before
+/// loading the model and before generating.
+struct GenerationSettings<'a> {
+    do_sample: bool,
+    num_beams: i64,
+    num_return_sequences: i64,
+    num_beam_groups: Option<i64>,
+    min_length: i64,
+    max_length: Option<i64>,
+    max_new_tokens: Option<i64>,
+    temperature: f64,
+    temperature_schedule: Option<&'a [f64]>,
+    adaptive_temperature_window: Option<usize>,
+    adaptive_temperature_max: f64,
+    top_p: f64,
+    min_p: Option<f64>,
+    repetition_penalty: f64,
+    length_penalty: f64,
+    repetition_window: Option<usize>,
+    logit_clamp: Option<(f64, f64)>,
+    kv_cache_window: Option<usize>,
+    kv_cache_sink_tokens: usize,
+    stop_strings: &'a [String],
+    stop_brackets: Option<(char, char)>,
+    negative_prompt: Option<&'a str>,
+}
+
+impl<'a> GenerationSettings<'a> {
+    fn new(config: &'a GenerateConfig, generate_options: Option<GenerateOptions<'a>>) -> Self {
+        // The maximum length of the generate options has priority over their maximum number of new tokens, then over
+        // the maximum number of new tokens of the configuration and finally over its maximum length
+        let (max_length, max_new_tokens) = match generate_options {
+            Some(GenerateOptions {
+                max_length: Some(max_length),
+                ..
+            }) => (Some(max_length), None),
+            Some(GenerateOptions {
+                max_new_tokens: Some(max_new_tokens),
+                ..
+            }) => (None, Some(max_new_tokens)),
+            _ => match config.max_new_tokens {
+                Some(max_new_tokens) => (None, Some(max_new_tokens)),
+                None => (config.max_length, None),
+            },
+        };
+        GenerationSettings {
+            do_sample: unpack_config!(do_sample, generate_options, config),
+            num_beams: unpack_config!(num_beams, generate_options, config),
+            num_return_sequences: unpack_config!(num_return_sequences, generate_options, config),
+            num_beam_groups: generate_options.map_or(config.num_beam_groups, |opts| {
+                opts.num_beam_groups.or(config.num_beam_groups)
+            }),
+            min_length: unpack_config!(min_length, generate_options, config),
+            max_length,
+            max_new_tokens,
+            temperature: unpack_config!(temperature, generate_options, config),
+            temperature_schedule: generate_options
+                .and_then(|opts| opts.temperature_schedule)
+                .or(config.temperature_schedule.as_deref()),
+            adaptive_temperature_window: generate_options.map_or(
+                config.adaptive_temperature_window,
+                |opts| {
+                    opts.adaptive_temperature_window
+                        .or(config.adaptive_temperature_window)
+                },
+            ),
+            adaptive_temperature_max: unpack_config!(
+                adaptive_temperature_max,
+                generate_options,
+                config
+            ),
+            top_p: unpack_config!(top_p, generate_options, config),
+            min_p: generate_options.map_or(config.min_p, |opts| opts.min_p.or(config.min_p)),
+            repetition_penalty: unpack_config!(repetition_penalty, generate_options, config),
+            length_penalty: unpack_config!(length_penalty, generate_options, config),
+            repetition_window: generate_options.map_or(config.repetition_window, |opts| {
+                opts.repetition_window.or(config.repetition_window)
+            }),
+            logit_clamp: generate_options.map_or(config.logit_clamp, |opts| {
+                opts.logit_clamp.or(config.logit_clamp)
+            }),
+            kv_cache_window: generate_options.map_or(config.kv_cache_window, |opts| {
+                opts.kv_cache_window.or(config.kv_cache_window)
+            }),
+            kv_cache_sink_tokens: unpack_config!(kv_cache_sink_tokens, generate_options, config),
+            stop_strings: generate_options
+                .and_then(|opts| opts.stop_strings)
+                .unwrap_or(config.stop_strings.as_slice()),
+            stop_brackets: generate_options.map_or(config.stop_brackets, |opts| {
+                opts.stop_brackets.or(config.stop_brackets)
+            }),
+            negative_prompt: generate_options
+                .and_then(|opts| opts.negative_prompt)
+                .or(config.negative_prompt.as_deref()),
+        }
+    }
+
+    /// Returns the maximum length of the generated sequences for prompts of `prompt_length` tokens
+    fn max_length(&self, prompt_length: i64) -> Option<i64> {
+        self.max_length.or_else(|| {
+            self.max_new_tokens
+                .map(|max_new_tokens| prompt_length + max_new_tokens)
+        })
+    }
 
+    fn validate(&self) -> Result<(), RustBertError> {
+        check_setting(self.min_length >= 0, || {
+            format!("min_length ({}) must not be negative", self.min_length)
+        })?;
+        if let Some(max_new_tokens) = self.max_new_tokens {
+            check_setting(max_new_tokens > 0, || {
+                "max_new_tokens must be strictly greater than 0".to_string()
+            })?;
+        }
+        // The prompt length is not known when the number of new tokens is capped
+        if let Some(max_length) = self.max_length {
+            check_setting(self.min_length <= max_length, || {
+                format!(
+                    "min_length ({}) must not exceed max_length ({})",
+                    self.min_length, max_length
+                )
+            })?;
+        }
+        check_setting(self.temperature > 0f64, || {
+            "temperature must be strictly greater than 0".to_string()
+        })?;
+        check_setting((self.top_p >= 0f64) & (self.top_p <= 1f64), || {
+            "top_p must be between 0 and 1".to_string()
+        })?;
+        check_setting(self.repetition_penalty >= 1f64, || {
+            "repetition_penalty must be greater than 1".to_string()
+        })?;
+        check_setting(self.length_penalty > 0f64, || {
+            "length_penalty must be strictly greater than 0".to_string()
+        })?;
+        check_setting(self.num_return_sequences > 0i64, || {
+            "num_return_sequences must be strictly greater than 0".to_string()
+        })?;
+        check_setting(self.num_beams > 0i64, || {
+            "num_beams must be strictly greater than 0".to_string()
+        })?;
+        // Independent samples are drawn for each returned sequence when sampling. Without sampling, the
+        // returned sequences are the best hypotheses of the beam search and are limited by the number of beams.
         if !self.do_sample {
             if self.num_beams == 1 {
-                assert_eq!(
-                    self.num_return_sequences, 1,
-                    "num_return_sequences must be set to 1 for greedy decoding"
-                )
+                check_setting(self.num_return_sequences == 1, || {
+                    "num_return_sequences must be set to 1 for greedy decoding".to_string()
+                })?;
             } else {
-                assert!(
-                    self.num_beams >= self.num_return_sequences,
-                    "num_return_sequences must be lower than the number of beams"
-                )
+                check_setting(self.num_beams >= self.num_return_sequences, || {
+                    "num_return_sequences must be lower than the number of beams".to_string()
+                })?;
             }
         }
-        if let Some(num_beam_groups_value) = self.num_beam_groups {
-            if num_beam_groups_value > 1 {
-                assert_eq!(
-                    self.num_beams % num_beam_groups_value,
-                    0,
-                    "num_beam_groups must be a multiple of num_beam_groups"
+        if let Some(num_beam_groups) = self.num_beam_groups {
+            check_setting(
+                num_beam_groups <= 1 || self.num_beams % num_beam_groups == 0,
+                || "num_beams must be a multiple of num_beam_groups".to_string(),
+            )?;
+        }
+        if let Some(min_p) = self.min_p {
+            check_setting((min_p >= 0f64) & (min_p <= 1f64), || {
+                "min_p must be between 0 and 1".to_string()
+            })?;
+        }
+        if let Some(temperature_schedule) = self.temperature_schedule {
+            check_setting(
+                !temperature_schedule.is_empty()
+                    && temperature_schedule
+                        .iter()
+                        .all(|temperature| *temperature > 0f64),
+                || "temperature_schedule must contain positive values".to_string(),
+            )?;
+        }
+        if let Some(adaptive_temperature_window) = self.adaptive_temperature_window {
+            check_setting(adaptive_temperature_window > 1, || {
+                "adaptive_temperature_window must be greater than 1".to_string()
+            })?;
+            check_setting(self.adaptive_temperature_max > 0f64, || {
+                "adaptive_temperature_max must be positive".to_string()
+            })?;
+        }
+        if let Some(kv_cache_window) = self.kv_cache_window {
+            check_setting(self.kv_cache_sink_tokens < kv_cache_window, || {
+                format!(
+                    "The key/value cache window ({}) must be strictly greater than 0 and than the number of sink tokens ({})",
+                    kv_cache_window, self.kv_cache_sink_tokens
                 )
-            }
+            })?;
+        }
+        if let Some(repetition_window) = self.repetition_window {
+            check_setting(repetition_window > 0, || {
+                "repetition_window must be strictly greater than 0".to_string()
+            })?;
+        }
+        if let Some((min, max)) = self.logit_clamp {
+            check_setting(min <= max, || {
+                "logit_clamp minimum must not exceed its maximum".to_string()
+            })?;
+        }
+        check_setting(
+            self.stop_strings
+                .iter()
+                .all(|stop_string| !stop_string.is_empty()),
+            || "stop_strings must not contain empty strings".to_string(),
+        )?;
+        if let Some((open, close)) = self.stop_brackets {
+            check_setting(open != close, || {
+                "stop_brackets must have different opening and closing characters".to_string()
+            })?;
+            check_setting(self.num_beams == 1, || {
+                "stop_brackets are only supported by greedy decoding and sampling".to_string()
+            })?;
+        }
+        if self.negative_prompt.is_some() {
+            check_setting(self.num_beams == 1, || {
+                "negative_prompt is only supported by greedy decoding and sampling".to_string()
+            })?;
         }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+/// Returns an `InvalidConfigurationError` with the given message if the condition on a generation setting does not hold
+fn check_setting<F>(condition: bool, message: F) -> Result<(), RustBertError>
+where
+    F: FnOnce() -> String,
+{
+    if condition {
+        Ok(())
+    } else {
+        Err(RustBertError::InvalidConfigurationError(message()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// # Early stopping setting of a generation configuration file
+/// Either a flag, or a stopping condition (e.g. `"never"`) of the beam search.
+pub enum EarlyStoppingSetting {
+    /// Early stopping flag
+    Flag(bool),
+    /// Stopping condition, only `"never"` disables early stopping
+    Condition(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// # Generation configuration file
+/// Default generation settings bundled with a model, deserialized from a `generation_config.json`
+/// file following the [Transformers library](https://github.com/huggingface/transformers) format.
+/// Settings missing from the file, or not supported by this crate, are ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::generation_utils::{GenerateConfigBuilder, GenerationConfigFile};
+/// use rust_bert::Config;
+///
+/// let generation_config = GenerationConfigFile::from_file("path/to/generation_config.json");
+/// //    Explicit settings take precedence over the generation configuration file
+/// let generate_config = GenerateConfigBuilder::from_generation_config(&generation_config)
+///     .with_num_beams(3)
+///     .build();
+/// ```
+pub struct GenerationConfigFile {
+    pub min_length: Option<i64>,
+    pub max_length: Option<i64>,
+    pub max_new_tokens: Option<i64>,
+    pub do_sample: Option<bool>,
+    pub early_stopping: Option<EarlyStoppingSetting>,
+    pub num_beams: Option<i64>,
+    pub temperature: Option<f64>,
+    pub top_k: Option<i64>,
+    pub top_p: Option<f64>,
+    pub repetition_penalty: Option<f64>,
+    pub length_penalty: Option<f64>,
+    pub no_repeat_ngram_size: Option<i64>,
+    pub num_return_sequences: Option<i64>,
+    pub num_beam_groups: Option<i64>,
+    pub diversity_penalty: Option<f64>,
+}
+
+impl Config for GenerationConfigFile {}
+
+impl GenerationConfigFile {
+    /// Overrides the settings of a `GenerateConfig` with the settings provided in the generation configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `generate_config` - `GenerateConfig` to update
+    pub fn apply_to(&self, generate_config: &mut GenerateConfig) {
+        if let Some(min_length) = self.min_length {
+            generate_config.min_length = min_length;
+        }
+        if let Some(max_length) = self.max_length {
+            generate_config.max_length = Some(max_length);
+        }
+        if let Some(max_new_tokens) = self.max_new_tokens {
+            generate_config.max_new_tokens = Some(max_new_tokens);
+        }
+        if let Some(do_sample) = self.do_sample {
+            generate_config.do_sample = do_sample;
+        }
+        if let Some(early_stopping) = &self.early_stopping {
+            generate_config.early_stopping = match early_stopping {
+                EarlyStoppingSetting::Flag(flag) => *flag,
+                EarlyStoppingSetting::Condition(condition) => condition != "never",
+            };
+        }
+        if let Some(num_beams) = self.num_beams {
+            generate_config.num_beams = num_beams;
+        }
+        if let Some(temperature) = self.temperature {
+            generate_config.temperature = temperature;
+        }
+        if let Some(top_k) = self.top_k {
+            generate_config.top_k = top_k;
+        }
+        if let Some(top_p) = self.top_p {
+            generate_config.top_p = top_p;
+        }
+        if let Some(repetition_penalty) = self.repetition_penalty {
+            generate_config.repetition_penalty = repetition_penalty;
+        }
+        if let Some(length_penalty) = self.length_penalty {
+            generate_config.length_penalty = length_penalty;
+        }
+        if let Some(no_repeat_ngram_size) = self.no_repeat_ngram_size {
+            generate_config.no_repeat_ngram_size = no_repeat_ngram_size;
+        }
+        if let Some(num_return_sequences) = self.num_return_sequences {
+            generate_config.num_return_sequences = num_return_sequences;
+        }
+        if let Some(num_beam_groups) = self.num_beam_groups {
+            generate_config.num_beam_groups = Some(num_beam_groups);
+        }
+        if let Some(diversity_penalty) = self.diversity_penalty {
+            generate_config.diversity_penalty = Some(diversity_penalty);
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+/// # Builder for text generation configurations
+/// Allows creating a `GenerateConfig` by overriding a few settings, the fields not specified take the
+/// value of `GenerateConfig::default()`.
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::generation_utils::GenerateConfigBuilder;
+/// use tch::Device;
+///
+/// let generate_config = GenerateConfigBuilder::new()
+///     .with_max_length(Some(32))
+///     .with_do_sample(false)
+///     .with_num_beams(3)
+///     .with_device(Device::Cpu)
+///     .build();
+/// ```
+pub struct GenerateConfigBuilder {
+    config: GenerateConfig,
+}
+
+#[cfg(feature = "remote")]
+impl Default for GenerateConfigBuilder {
+    fn default() -> Self {
+        GenerateConfigBuilder::new()
+    }
+}
+
+#[cfg(feature = "remote")]
+impl GenerateConfigBuilder {
+    /// Build a new `GenerateConfigBuilder`, starting from the default generation configuration
+    pub fn new() -> GenerateConfigBuilder {
+        GenerateConfigBuilder {
+            config: GenerateConfig::default(),
+        }
+    }
+
+    /// Build a new `GenerateConfigBuilder`, starting from the default generation configuration updated with
+    /// the settings of a generation configuration file. Settings specified on the builder take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `generation_config` - `GenerationConfigFile` bundled with the model
+    pub fn from_generation_config(
+        generation_config: &GenerationConfigFile,
+    ) -> GenerateConfigBuilder {
+        let mut config = GenerateConfig::default();
+        generation_config.apply_to(&mut config);
+        GenerateConfigBuilder { config }
+    }
+
+    /// Specify the model weights resource
+    pub fn with_model_resource<R>(mut self, model_resource: R) -> Self
+    where
+        R: ResourceProvider + Send + Sync + 'static,
+    {
+        self.config.model_resource = Box::new(model_resource);
+        self
+    }
+
+    /// Specify the model configuration resource
+    pub fn with_config_resource<R>(mut self, config_resource: R) -> Self
+    where
+        R: ResourceProvider + Send + Sync + 'static,
+    {
+        self.config.config_resource = Box::new(config_resource);
+        self
+    }
+
+    /// Specify the tokenizer vocabulary resource
+    pub fn with_vocab_resource<R>(mut self, vocab_resource: R) -> Self
+    where
+        R: ResourceProvider + Send + Sync + 'static,
+    {
+        self.config.vocab_resource = Box::new(vocab_resource);
+        self
+    }
+
+    /// Specify the tokenizer merges resource
+    pub fn with_merges_resource<R>(mut self, merges_resource: Option<R>) -> Self
+    where
+        R: ResourceProvider + Send + Sync + 'static,
+    {
+        self.config.merges_resource = merges_resource.map(|r| Box::new(r) as Box<_>);
+        self
+    }
+
+    /// Specify the minimum sequence length
+    pub fn with_min_length(mut self, min_length: i64) -> Self {
+        self.config.min_length = min_length;
+        self
+    }
+
+    /// Specify the maximum sequence length
+    pub fn with_max_length(mut self, max_length: Option<i64>) -> Self {
+        self.config.max_length = max_length;
+        self
+    }
+
+    /// Specify the maximum number of generated tokens, taking precedence over the maximum sequence length
+    pub fn with_max_new_tokens(mut self, max_new_tokens: Option<i64>) -> Self {
+        self.config.max_new_tokens = max_new_tokens;
+        self
+    }
+
+    /// Specify the sampling flag
+    pub fn with_do_sample(mut self, do_sample: bool) -> Self {
+        self.config.do_sample = do_sample;
+        self
+    }
+
+    /// Specify the early stopping flag for beam search
+    pub fn with_early_stopping(mut self, early_stopping: bool) -> Self {
+        self.config.early_stopping = early_stopping;
+        self
+    }
+
+    /// Specify the number of beams for beam search
+    pub fn with_num_beams(mut self, num_beams: i64) -> Self {
+        self.config.num_beams = num_beams;
+        self
+    }
+
+    /// Specify the number of tokens decoded greedily before sampling
+    pub fn with_greedy_prefix_len(mut self, greedy_prefix_len: usize) -> Self {
+        self.config.greedy_prefix_len = greedy_prefix_len;
+        self
+    }
+
+    /// Specify if NaN and infinite logits should be replaced before sampling or greedy selection
+    pub fn with_sanitize_logits(mut self, sanitize_logits: bool) -> Self {
+        self.config.sanitize_logits = sanitize_logits;
+        self
+    }
+
+    /// Specify the range the logits are clamped to before the softmax and sampling or greedy selection
+    pub fn with_logit_clamp(mut self, min: f64, max: f64) -> Self {
+        self.config.logit_clamp = Some((min, max));
+        self
+    }
+
+    /// Specify the maximum number of past positions kept in the key/value cache of the decoder self-attention
+    pub fn with_kv_cache_window(mut self, kv_cache_window: usize) -> Self {
+        self.config.kv_cache_window = Some(kv_cache_window);
+        self
+    }
+
+    /// Specify the number of first positions of the key/value cache that are never evicted by the cache window
+    pub fn with_kv_cache_sink_tokens(mut self, kv_cache_sink_tokens: usize) -> Self {
+        self.config.kv_cache_sink_tokens = kv_cache_sink_tokens;
+        self
+    }
+
+    /// Specify the device the key/value cache is kept on between generation steps
+    pub fn with_kv_cache_device(mut self, kv_cache_device: Device) -> Self {
+        self.config.kv_cache_device = Some(kv_cache_device);
+        self
+    }
+
+    /// Specify the side on which the prompts of a batch are padded
+    pub fn with_padding_side(mut self, padding_side: PaddingSide) -> Self {
+        self.config.padding_side = padding_side;
+        self
+    }
+
+    /// Specify the sampling temperature
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.config.temperature = temperature;
+        self
+    }
+
+    /// Specify the temperatures applied at each generation step when sampling
+    pub fn with_temperature_schedule(mut self, temperature_schedule: Vec<f64>) -> Self {
+        self.config.temperature_schedule = Some(temperature_schedule);
+        self
+    }
+
+    /// Specify the window and maximum temperature of the repetition-aware adaptive temperature
+    pub fn with_adaptive_temperature(
+        mut self,
+        adaptive_temperature_window: usize,
+        adaptive_temperature_max: f64,
+    ) -> Self {
+        self.config.adaptive_temperature_window = Some(adaptive_temperature_window);
+        self.config.adaptive_temperature_max = adaptive_temperature_max;
+        self
+    }
+
+    /// Specify the top-k value for sampling
+    pub fn with_top_k(mut self, top_k: i64) -> Self {
+        self.config.top_k = top_k;
+        self
+    }
+
+    /// Specify the top-p value for nucleus sampling
+    pub fn with_top_p(mut self, top_p: f64) -> Self {
+        self.config.top_p = top_p;
+        self
+    }
+
+    /// Specify the min-p value for sampling
+    pub fn with_min_p(mut self, min_p: f64) -> Self {
+        self.config.min_p = Some(min_p);
+        self
+    }
+
+    /// Specify the repetition penalty
+    pub fn with_repetition_penalty(mut self, repetition_penalty: f64) -> Self {
+        self.config.repetition_penalty = repetition_penalty;
+        self
+    }
+
+    /// Specify the frequency penalty, subtracted from the logit of a token for each of its occurrences
+    pub fn with_frequency_penalty(mut self, frequency_penalty: f64) -> Self {
+        self.config.frequency_penalty = frequency_penalty;
+        self
+    }
+
+    /// Specify the presence penalty, subtracted from the logit of the tokens already present
+    pub fn with_presence_penalty(mut self, presence_penalty: f64) -> Self {
+        self.config.presence_penalty = presence_penalty;
+        self
+    }
+
+    /// Specify the exponential length penalty
+    pub fn with_length_penalty(mut self, length_penalty: f64) -> Self {
+        self.config.length_penalty = length_penalty;
+        self
+    }
+
+    /// Specify the number of allowed repetitions of n-grams
+    pub fn with_no_repeat_ngram_size(mut self, no_repeat_ngram_size: i64) -> Self {
+        self.config.no_repeat_ngram_size = no_repeat_ngram_size;
+        self
+    }
+
+    /// Specify the number of most recent tokens considered by the repetition penalty and n-gram repetition blocking
+    pub fn with_repetition_window(mut self, repetition_window: usize) -> Self {
+        self.config.repetition_window = Some(repetition_window);
+        self
+    }
+
+    /// Specify the number of sequences to return for each prompt
+    pub fn with_num_return_sequences(mut self, num_return_sequences: i64) -> Self {
+        self.config.num_return_sequences = num_return_sequences;
+        self
+    }
+
+    /// Specify the score used to rank the sequences returned by beam search
+    pub fn with_rank_by(mut self, rank_by: ScoreType) -> Self {
+        self.config.rank_by = rank_by;
+        self
+    }
+
+    /// Specify the number of beam groups for diverse beam search
+    pub fn with_num_beam_groups(mut self, num_beam_groups: i64) -> Self {
+        self.config.num_beam_groups = Some(num_beam_groups);
+        self
+    }
+
+    /// Specify the diversity penalty for diverse beam search
+    pub fn with_diversity_penalty(mut self, diversity_penalty: f64) -> Self {
+        self.config.diversity_penalty = Some(diversity_penalty);
+        self
+    }
+
+    /// Specify the strings terminating the generation of a sequence
+    pub fn with_stop_strings(mut self, stop_strings: Vec<String>) -> Self {
+        self.config.stop_strings = stop_strings;
+        self
+    }
+
+    /// Specify the opening and closing characters terminating the generation of a sequence once balanced
+    pub fn with_stop_brackets(mut self, open: char, close: char) -> Self {
+        self.config.stop_brackets = Some((open, close));
+        self
+    }
+
+    /// Specify the bias added to the logits of the given token ids
+    pub fn with_logit_bias(mut self, logit_bias: HashMap<i64, f64>) -> Self {
+        self.config.logit_bias = logit_bias;
+        self
+    }
+
+    /// Specify the negative prompt used for classifier-free guidance
+    pub fn with_negative_prompt(mut self, negative_prompt: &str) -> Self {
+        self.config.negative_prompt = Some(negative_prompt.to_string());
+        self
+    }
+
+    /// Specify the classifier-free guidance scale
+    pub fn with_guidance_scale(mut self, guidance_scale: f64) -> Self {
+        self.config.guidance_scale = guidance_scale;
+        self
+    }
+
+    /// Specify if special tokens should be removed from the decoded output
+    pub fn with_skip_special_tokens(mut self, skip_special_tokens: bool) -> Self {
+        self.config.skip_special_tokens = skip_special_tokens;
+        self
+    }
+
+    /// Specify if the padding tokens should be kept in the decoded output
+    pub fn with_keep_pad_tokens(mut self, keep_pad_tokens: bool) -> Self {
+        self.config.keep_pad_tokens = keep_pad_tokens;
+        self
+    }
+
+    /// Specify the placeholder replacing the unknown tokens in the decoded output
+    pub fn with_unk_replacement(mut self, unk_replacement: &str) -> Self {
+        self.config.unk_replacement = Some(unk_replacement.to_string());
+        self
+    }
+
+    /// Specify the handling of the control characters (other than newlines and tabs) of the decoded output
+    pub fn with_control_characters(mut self, control_characters: ControlCharacters) -> Self {
+        self.config.control_characters = Some(control_characters);
+        self
+    }
+
+    /// Specify if the model should be created with attention weights in its outputs
+    pub fn with_output_attentions(mut self, output_attentions: bool) -> Self {
+        self.config.output_attentions = output_attentions;
+        self
+    }
+
+    /// Specify the device to place the model on
+    pub fn with_device(mut self, device: Device) -> Self {
+        self.config.device = device;
+        self
+    }
+
+    /// Create the `GenerateConfig` from the builder
+    pub fn build(self) -> GenerateConfig {
+        self.config
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Cache {
-    GPT2Cache(Option<Vec<Tensor>>),
+    GPT2Cache(#[serde(with = "crate::common::tensor_serde")] Option<Vec<Tensor>>),
     BARTCache(Option<Vec<(Option<BartLayerState>, Option<BartLayerState>)>>),
     T5Cache(Option<Vec<(Option<T5LayerState>, Option<T5LayerState>)>>),
     XLNetCache(Option<Vec<Option<XLNetLayerState>>>),
@@ -226,36 +988,397 @@ pub enum Cache {
     None,
 }
 
+impl Cache {
+    /// Returns the number of past positions stored in the decoder self-attention cache, if any
+    pub fn cached_length(&self) -> Option<i64> {
+        match self {
+            Cache::GPT2Cache(Some(layer_pasts)) => {
+                layer_pasts.first().map(|layer_past| layer_past.size()[3])
+            }
+            Cache::T5Cache(Some(layer_states)) => layer_states
+                .first()
+                .and_then(|(self_attention_state, _)| self_attention_state.as_ref())
+                .map(|layer_state| layer_state.prev_key.size()[2]),
+            _ => None,
+        }
+    }
+
+    /// Drops the oldest positions of the decoder self-attention cache, keeping at most `window` positions.
+    /// The cross-attention cache of encoder-decoder models is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - maximum number of past positions to keep
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error if the cache of the model does not support windowing
+    pub fn truncate_to_window(&mut self, window: i64) -> Result<(), RustBertError> {
+        self.truncate_to_window_with_sink_tokens(window, 0)
+    }
+
+    /// Drops the oldest positions of the decoder self-attention cache but its first `sink_tokens` positions (attention
+    /// sink), keeping at most `window` positions: the sink positions followed by the `window - sink_tokens` most recent ones.
+    /// The cross-attention cache of encoder-decoder models is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - maximum number of past positions to keep, including the sink positions
+    /// * `sink_tokens` - number of first positions that are never dropped, lower than `window`
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error if the cache of the model does not support windowing or if the sink does not fit in the window
+    pub fn truncate_to_window_with_sink_tokens(
+        &mut self,
+        window: i64,
+        sink_tokens: i64,
+    ) -> Result<(), RustBertError> {
+        if sink_tokens < 0 || sink_tokens >= window {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The number of sink tokens ({}) must be lower than the key/value cache window ({})",
+                sink_tokens, window
+            )));
+        }
+        let truncate = |tensor: &Tensor, dim: i64| {
+            let length = tensor.size()[dim as usize];
+            if length > window {
+                let recent =
+                    tensor.narrow(dim, length - (window - sink_tokens), window - sink_tokens);
+                if sink_tokens > 0 {
+                    Tensor::cat(&[tensor.narrow(dim, 0, sink_tokens), recent], dim)
+                } else {
+                    recent
+                }
+            } else {
+                tensor.shallow_clone()
+            }
+        };
+        match self {
+            Cache::GPT2Cache(Some(layer_pasts)) => {
+                for layer_past in layer_pasts.iter_mut() {
+                    *layer_past = truncate(layer_past, 3);
+                }
+            }
+            Cache::T5Cache(Some(layer_states)) => {
+                for layer_state in layer_states
+                    .iter_mut()
+                    .filter_map(|(self_attention_state, _)| self_attention_state.as_mut())
+                {
+                    layer_state.prev_key = truncate(&layer_state.prev_key, 2);
+                    layer_state.prev_value = truncate(&layer_state.prev_value, 2);
+                }
+            }
+            Cache::GPT2Cache(None) | Cache::T5Cache(None) | Cache::None => {}
+            _ => {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "The key/value cache window is only supported by GPT2 and T5 models".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a cache made of the given batch entries of the decoder self-attention cache, e.g. to repeat a cached prompt
+    /// for every sequence of a batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_indices` - `Tensor` of indices of the batch entries to select, possibly repeated
+    ///
+    /// # Returns
+    /// * `Result<Cache, RustBertError>` Error if the cache of the model does not support batch selection or if an index
+    /// is out of the batch of the cache
+    pub fn select_batch(&self, batch_indices: &Tensor) -> Result<Cache, RustBertError> {
+        match self {
+            Cache::GPT2Cache(layer_pasts) => {
+                if let Some(layer_past) = layer_pasts
+                    .as_ref()
+                    .and_then(|layer_pasts| layer_pasts.first())
+                {
+                    let cache_batch_size = layer_past.size()[1];
+                    if batch_indices.numel() > 0
+                        && (i64::from(batch_indices.min()) < 0
+                            || i64::from(batch_indices.max()) >= cache_batch_size)
+                    {
+                        return Err(RustBertError::ValueError(format!(
+                            "The batch indices must be lower than the batch size of the cache ({})",
+                            cache_batch_size
+                        )));
+                    }
+                }
+                Ok(Cache::GPT2Cache(layer_pasts.as_ref().map(|layer_pasts| {
+                    layer_pasts
+                        .iter()
+                        .map(|layer_past| {
+                            layer_past
+                                .index_select(1, &batch_indices.to_device(layer_past.device()))
+                        })
+                        .collect()
+                })))
+            }
+            Cache::None => Ok(Cache::None),
+            _ => Err(RustBertError::InvalidConfigurationError(
+                "Starting the generation from a cache is only supported by GPT2 models".into(),
+            )),
+        }
+    }
+
+    /// Keeps the given positions of the decoder self-attention cache for each batch entry, e.g. to drop the oldest
+    /// positions of the sequences of a batch independently. The cross-attention cache of encoder-decoder models is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - `Tensor` of shape (*batch size*, *number of kept positions*) with the positions to keep for each batch entry
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error if the cache of the model does not support the selection of positions
+    pub fn select_positions(&mut self, positions: &Tensor) -> Result<(), RustBertError> {
+        let kept_positions = positions.size()[1];
+        match self {
+            Cache::GPT2Cache(Some(layer_pasts)) => {
+                for layer_past in layer_pasts.iter_mut() {
+                    let size = layer_past.size();
+                    let index = positions
+                        .to_device(layer_past.device())
+                        .view([1, size[1], 1, kept_positions, 1])
+                        .expand(&[size[0], size[1], size[2], kept_positions, size[4]], true);
+                    *layer_past = layer_past.gather(3, &index, false);
+                }
+            }
+            Cache::T5Cache(Some(layer_states)) => {
+                for layer_state in layer_states
+                    .iter_mut()
+                    .filter_map(|(self_attention_state, _)| self_attention_state.as_mut())
+                {
+                    let size = layer_state.prev_key.size();
+                    let index = positions
+                        .to_device(layer_state.prev_key.device())
+                        .view([size[0], 1, kept_positions, 1])
+                        .expand(&[size[0], size[1], kept_positions, size[3]], true);
+                    layer_state.prev_key = layer_state.prev_key.gather(2, &index, false);
+                    layer_state.prev_value = layer_state.prev_value.gather(2, &index, false);
+                }
+            }
+            Cache::GPT2Cache(None) | Cache::T5Cache(None) | Cache::None => {}
+            _ => {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "The selection of cache positions is only supported by GPT2 and T5 models"
+                        .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the most recent positions of the decoder self-attention cache, keeping its first `length` positions
+    /// (e.g. to discard the cache of the padding generated after the end of a sequence).
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - number of past positions to keep
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error if the cache of the model does not support truncation
+    pub fn truncate_to_length(&mut self, length: i64) -> Result<(), RustBertError> {
+        match self {
+            Cache::GPT2Cache(Some(layer_pasts)) => {
+                for layer_past in layer_pasts.iter_mut() {
+                    let cached_length = layer_past.size()[3];
+                    *layer_past = layer_past.narrow(3, 0, length.min(cached_length));
+                }
+            }
+            Cache::GPTNeoCache(Some(layer_states)) => {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    let cached_length = layer_state.prev_key.size()[2];
+                    layer_state.prev_key =
+                        layer_state.prev_key.narrow(2, 0, length.min(cached_length));
+                    layer_state.prev_value = layer_state
+                        .prev_value
+                        .as_ref()
+                        .map(|prev_value| prev_value.narrow(2, 0, length.min(cached_length)));
+                }
+            }
+            Cache::GPT2Cache(None) | Cache::GPTNeoCache(None) | Cache::None => {}
+            _ => {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "The cache truncation is only supported by GPT2 and GPT-Neo models".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the tensors of the cache to a device
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - `Device` to move the cached tensors to
+    pub fn to_device(&mut self, device: Device) {
+        let move_bart_state = |layer_state: &mut Option<BartLayerState>| {
+            if let Some(layer_state) = layer_state {
+                layer_state.prev_key = layer_state.prev_key.to_device(device);
+                layer_state.prev_value = layer_state.prev_value.to_device(device);
+            }
+        };
+        let move_t5_state = |layer_state: &mut Option<T5LayerState>| {
+            if let Some(layer_state) = layer_state {
+                layer_state.prev_key = layer_state.prev_key.to_device(device);
+                layer_state.prev_value = layer_state.prev_value.to_device(device);
+            }
+        };
+        let move_prophetnet_state = |layer_state: &mut Option<ProphetNetLayerState>| {
+            if let Some(layer_state) = layer_state {
+                layer_state.prev_key = layer_state.prev_key.to_device(device);
+                layer_state.prev_value = layer_state.prev_value.to_device(device);
+            }
+        };
+        match self {
+            Cache::GPT2Cache(Some(layer_pasts)) => {
+                for layer_past in layer_pasts.iter_mut() {
+                    *layer_past = layer_past.to_device(device);
+                }
+            }
+            Cache::BARTCache(Some(layer_states)) => {
+                for (self_attention_state, cross_attention_state) in layer_states.iter_mut() {
+                    move_bart_state(self_attention_state);
+                    move_bart_state(cross_attention_state);
+                }
+            }
+            Cache::T5Cache(Some(layer_states)) => {
+                for (self_attention_state, cross_attention_state) in layer_states.iter_mut() {
+                    move_t5_state(self_attention_state);
+                    move_t5_state(cross_attention_state);
+                }
+            }
+            Cache::XLNetCache(Some(layer_states)) => {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    layer_state.prev_content = layer_state.prev_content.to_device(device);
+                }
+            }
+            Cache::ReformerCache(Some(layer_states)) => {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    layer_state.prev_states = layer_state.prev_states.to_device(device);
+                    layer_state.prev_buckets = layer_state
+                        .prev_buckets
+                        .as_ref()
+                        .map(|prev_buckets| prev_buckets.to_device(device));
+                }
+            }
+            Cache::ProphetNetCache(Some(layer_states)) => {
+                for (self_attention_state, cross_attention_state) in layer_states.iter_mut() {
+                    move_prophetnet_state(self_attention_state);
+                    move_prophetnet_state(cross_attention_state);
+                }
+            }
+            Cache::GPTNeoCache(Some(layer_states)) => {
+                for layer_state in layer_states.iter_mut().flatten() {
+                    layer_state.prev_key = layer_state.prev_key.to_device(device);
+                    layer_state.prev_value = layer_state
+                        .prev_value
+                        .as_ref()
+                        .map(|prev_value| prev_value.to_device(device));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+/// # Cached prompt
+/// Token ids of a prompt and cache of the decoder self-attention for all prompt tokens but the last, built with
+/// `LanguageGenerator::cache_prompt`. A prompt shared by several generations (e.g. a system prompt) is encoded once,
+/// the generation starting from its cache with `LanguageGenerator::generate_from_prompt_cache`.
+pub struct PromptCache {
+    token_ids: Vec<i64>,
+    past: Cache,
+}
+
+impl PromptCache {
+    /// Returns the token ids of the cached prompt
+    pub fn token_ids(&self) -> &[i64] {
+        &self.token_ids
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// # Paused beam search
+/// Beams, finished hypotheses and cache of a beam search paused by `LanguageGenerator::generate_beam_search_steps`,
+/// with the lengths of the prompts it was started from. The search is continued with `LanguageGenerator::resume_beam_search`,
+/// without encoding the prompts again. The state can be serialized (e.g. to checkpoint a long generation): its tensors
+/// are deserialized on the CPU and moved to the device of the generator when the search is resumed.
+pub struct BeamSearchState {
+    num_beams: i64,
+    prompts: GenerationPrompts,
+    loop_state: BeamSearchLoopState,
+}
+
+impl BeamSearchState {
+    /// Returns the current length of the sequences of the beams
+    pub fn current_length(&self) -> i64 {
+        self.loop_state.current_length
+    }
+}
+
+#[derive(Debug)]
+/// # Progress of a beam search generated step by step
+pub enum BeamSearchProgress {
+    /// The beam search was paused before completion
+    Paused(BeamSearchState),
+    /// The beam search completed, with the generated indices (see `LanguageGenerator::generate_indices`)
+    Finished(Vec<GeneratedIndicesOutput>),
+}
+
 pub(crate) mod private_generation_utils {
     use std::cmp::{max, min};
-    use std::collections::HashMap;
+    use std::collections::{HashMap, VecDeque};
     use std::mem;
 
     use rust_tokenizers::tokenizer::{truncate_sequences, Tokenizer, TruncationStrategy};
     use rust_tokenizers::vocab::Vocab;
     use rust_tokenizers::TokenIdsWithOffsets;
-    use tch::{nn, Device, Kind, Tensor};
+    use tch::{nn, no_grad, Device, Kind, Tensor};
 
-    use crate::pipelines::common::TokenizerOption;
+    use crate::common::error::RustBertError;
+    use crate::common::tensor_serde::SerializeTensors;
+    use crate::pipelines::common::{
+        synchronize_device, truncate_tokens_to_byte_fallback_boundary, PaddingSide, TokenizerOption,
+    };
     use crate::pipelines::generation_utils::{
-        BeamHypotheses, Cache, GenerateConfig, LMHeadModel, PrefixAllowedFunction,
+        eos_step, BeamHypotheses, BeamSearchState, Cache, ControlCharacters, EntropyStage,
+        FinishReason, GenerateConfig, GenerateOptions, GeneratedIndicesOutput, GenerationControl,
+        GenerationSettings, GenerationTiming, GenerationUsage, LMHeadModel, PrefixAllowedFunction,
+        PromptCache, ScoreType, StepTrace, TopTokenScore, TraceCandidate, NUM_TRACE_CANDIDATES,
     };
+    use serde::{Deserialize, Serialize};
+    use std::time::Instant;
+    use tch::kind::Kind::Int64;
 
     use super::ordered_float::OrderedFloat;
     use crate::common::kind::get_positive_infinity;
 
+    /// Value replacing the NaN and infinite logits when sanitizing the logits (representable in half precision)
+    const SANITIZED_LOGIT_VALUE: f64 = -1e4;
+
     pub struct InternalGenerateOptions<'a> {
         pub min_length: i64,
         pub max_length: Option<i64>,
         pub do_sample: bool,
         pub temperature: f64,
+        pub temperature_schedule: Option<&'a [f64]>,
+        pub adaptive_temperature_window: Option<i64>,
+        pub adaptive_temperature_max: f64,
         pub top_k: i64,
         pub top_p: f64,
+        pub min_p: Option<f64>,
         pub repetition_penalty: f64,
+        pub frequency_penalty: f64,
+        pub presence_penalty: f64,
         pub no_repeat_ngram_size: i64,
+        pub repetition_window: Option<i64>,
         pub pad_token_id: Option<i64>,
         pub eos_token_ids: Option<Vec<i64>>,
         pub num_return_sequences: i64,
+        pub rank_by: ScoreType,
         pub early_stopping: bool,
         pub num_beams: i64,
         pub length_penalty: f64,
@@ -263,6 +1386,46 @@ pub(crate) mod private_generation_utils {
         pub diversity_penalty: Option<f64>,
         pub forced_bos_token_id: Option<i64>,
         pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+        pub stop_strings: Option<&'a [String]>,
+        pub stop_brackets: Option<(char, char)>,
+        pub logit_bias: Option<&'a HashMap<i64, f64>>,
+        pub greedy_prefix_len: usize,
+        pub sanitize_logits: bool,
+        pub logit_clamp: Option<(f64, f64)>,
+        pub negative_prompt_guidance: Option<NegativePromptGuidance>,
+        pub kv_cache_window: Option<i64>,
+        pub kv_cache_sink_tokens: i64,
+        pub kv_cache_device: Option<Device>,
+        pub shrink_finished_batch: bool,
+        pub initial_past: Option<Cache>,
+        /// Callback invoked after every generated token of a single sequence (see `LanguageGenerator::generate_with_callback`)
+        pub step_callback: Option<&'a mut dyn FnMut(&[i64]) -> GenerationControl>,
+    }
+
+    impl InternalGenerateOptions<'_> {
+        /// Returns the temperature dividing the logits at a generation step, if any. The temperature
+        /// schedule takes precedence over the temperature, which is only applied if higher than 1.
+        pub fn step_temperature(&self, step: usize) -> Option<f64> {
+            match self.temperature_schedule {
+                Some(temperature_schedule) => temperature_schedule
+                    .get(step)
+                    .or_else(|| temperature_schedule.last())
+                    .copied(),
+                None if self.temperature > 1f64 => Some(self.temperature),
+                None => None,
+            }
+        }
+    }
+
+    /// Inputs of the negative prompt branch of the classifier-free guidance
+    pub struct NegativePromptGuidance {
+        /// Negative prompt token ids (decoder-only models), extended with the generated tokens
+        pub input_ids: Tensor,
+        /// Attention mask of the negative prompt
+        pub attention_mask: Tensor,
+        /// Encoded negative prompt (encoder-decoder models)
+        pub encoder_outputs: Option<Tensor>,
+        pub guidance_scale: f64,
     }
 
     pub struct PreparedInput<'a> {
@@ -274,10 +1437,120 @@ pub(crate) mod private_generation_utils {
         pub prepared_past: Cache,
     }
 
+    /// Inputs of a generation: encoded prompts, or the state of a paused beam search
+    pub enum GenerationStart {
+        Prompts {
+            input_ids: Tensor,
+            attention_mask: Option<Tensor>,
+        },
+        PausedBeamSearch(BeamSearchState),
+    }
+
+    /// Batch dimensions and prompt statistics used to build the generated outputs, saved with a paused beam search
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GenerationPrompts {
+        pub(super) batch_size: i64,
+        pub(super) effective_batch_size: i64,
+        pub(super) cur_len: i64,
+        pub(super) prompt_lengths: Vec<i64>,
+        pub(super) prompt_attention_masks: Option<Vec<Vec<i64>>>,
+    }
+
+    /// Variables of the beam search loop, saved between two steps when the search is paused
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct BeamSearchLoopState {
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) input_ids: Tensor,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) attention_mask: Tensor,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) encoder_outputs: Option<Tensor>,
+        pub(super) past: Cache,
+        pub(super) current_length: i64,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) beam_scores: Tensor,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) beam_tokens: Tensor,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) beam_indices: Tensor,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) current_tokens: Tensor,
+        pub(super) hypotheses: Vec<BeamHypotheses>,
+        pub(super) finished_hypotheses: Vec<Option<BeamHypotheses>>,
+        #[serde(with = "crate::common::tensor_serde")]
+        pub(super) saved_beam_scores: Option<Vec<Tensor>>,
+        pub(super) trace_output: Option<Vec<Vec<StepTrace>>>,
+        pub(super) finished_trace_output: Vec<Vec<StepTrace>>,
+        pub(super) active_batch_indices: Vec<usize>,
+        pub(super) done: Vec<bool>,
+    }
+
+    impl BeamSearchLoopState {
+        /// Moves the tensors of the state to a device (e.g. once deserialized on the CPU)
+        pub fn to_device(&mut self, device: Device) {
+            let move_tensor = |tensor: &mut Tensor| {
+                if tensor.defined() {
+                    *tensor = tensor.to_device(device);
+                }
+            };
+            move_tensor(&mut self.input_ids);
+            move_tensor(&mut self.attention_mask);
+            self.encoder_outputs.iter_mut().for_each(move_tensor);
+            self.past.to_device(device);
+            move_tensor(&mut self.beam_scores);
+            move_tensor(&mut self.beam_tokens);
+            move_tensor(&mut self.beam_indices);
+            move_tensor(&mut self.current_tokens);
+            for hypotheses in self
+                .hypotheses
+                .iter_mut()
+                .chain(self.finished_hypotheses.iter_mut().flatten())
+            {
+                for (_, tokens, scores, _) in hypotheses.beams.iter_mut() {
+                    move_tensor(tokens);
+                    scores.iter_mut().for_each(move_tensor);
+                }
+            }
+            self.saved_beam_scores
+                .iter_mut()
+                .flatten()
+                .for_each(move_tensor);
+        }
+    }
+
+    impl SerializeTensors for (f64, Tensor, Option<Tensor>, FinishReason) {
+        type Data = (
+            f64,
+            <Tensor as SerializeTensors>::Data,
+            <Option<Tensor> as SerializeTensors>::Data,
+            FinishReason,
+        );
+
+        fn to_data(&self) -> Result<Self::Data, String> {
+            Ok((self.0, self.1.to_data()?, self.2.to_data()?, self.3))
+        }
+
+        fn from_data(data: Self::Data) -> Result<Self, String> {
+            Ok((
+                data.0,
+                Tensor::from_data(data.1)?,
+                Option::<Tensor>::from_data(data.2)?,
+                data.3,
+            ))
+        }
+    }
+
     pub struct GeneratedOutputWithScores {
         pub indices: Tensor,
         pub scores: Option<Vec<f64>>,
         pub token_scores: Option<Vec<Vec<f64>>>,
+        pub top_token_scores: Option<Vec<Vec<TopTokenScore>>>,
+        pub entropy: Option<Vec<Vec<f32>>>,
+        pub trace: Option<Vec<Vec<StepTrace>>>,
+        pub sequence_lengths: Vec<i64>,
+        pub finish_reasons: Vec<FinishReason>,
+        pub past: Cache,
+        pub paused_beam_search: Option<BeamSearchLoopState>,
     }
 
     pub trait PrivateLanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>> {
@@ -303,6 +1576,11 @@ pub(crate) mod private_generation_utils {
         ) {
         }
 
+        /// Returns `true` if the cache of the model can be truncated to a key/value cache window
+        fn supports_kv_cache_window(&self) -> bool {
+            false
+        }
+
         fn encode(&self, _input_ids: &Tensor, _attention_mask: Option<&Tensor>) -> Option<Tensor> {
             None
         }
@@ -324,31 +1602,78 @@ pub(crate) mod private_generation_utils {
             }
         }
 
+        /// Encodes the prompts of a generation, truncated to the maximum length of the generation for decoder-only
+        /// models. Returns `None` for an empty list of prompts.
+        fn encode_generation_inputs<S>(
+            &self,
+            prompt_texts: Option<&[S]>,
+            generate_options: Option<GenerateOptions>,
+        ) -> Option<(Tensor, Option<Tensor>)>
+        where
+            S: AsRef<str> + Sync,
+        {
+            let eos_token_ids = PrivateLanguageGenerator::get_eos_ids(self);
+
+            let config = PrivateLanguageGenerator::get_config(self);
+
+            // The prompts are not truncated when the number of generated tokens is capped instead of the sequence length
+            let max_length = GenerationSettings::new(config, generate_options).max_length;
+            let encoding_max_len = if self.is_encoder_decoder() {
+                Some(self.get_max_positions_embeddings())
+            } else {
+                max_length
+            };
+            let pad_token_id = match self.get_pad_id() {
+                Some(value) => Some(value),
+                None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
+            };
+
+            match prompt_texts {
+                Some(prompts) if !prompts.is_empty() => {
+                    let (input_ids, attention_mask) =
+                        self.encode_prompt_text(prompts, encoding_max_len, pad_token_id);
+                    Some((input_ids, Some(attention_mask)))
+                }
+                None => match self.get_bos_id() {
+                    Some(bos_id) => Some((
+                        Tensor::ones(&[1, 1], (Kind::Int64, self.get_var_store().device())) * bos_id,
+                        None,
+                    )),
+                    None => panic!(
+                        "A model with a BOS token must be used to start generation with an empty input"
+                    ),
+                },
+                _ => None,
+            }
+        }
+
         fn encode_prompt_text<S>(
             &self,
             prompt_text: &[S],
             max_len: Option<i64>,
             pad_token_id: Option<i64>,
-        ) -> Tensor
+        ) -> (Tensor, Tensor)
         where
             S: AsRef<str> + Sync,
         {
             let tokens = self._get_tokenizer().tokenize_list(prompt_text);
             let token_ids = tokens
-                .into_iter()
-                .map(|prompt_tokens| self._get_tokenizer().convert_tokens_to_ids(&prompt_tokens))
+                .iter()
+                .map(|prompt_tokens| self._get_tokenizer().convert_tokens_to_ids(prompt_tokens))
                 .collect::<Vec<Vec<i64>>>();
 
-            let num_truncated_tokens = token_ids
+            // Byte-fallback sequences encoding a single character are never split by the truncation
+            let num_truncated_tokens = tokens
                 .iter()
-                .map(|token_ids| {
+                .map(|prompt_tokens| {
                     max_len
                         .map(|max_len| {
-                            if token_ids.len() > max_len as usize {
-                                token_ids.len() - max_len as usize
-                            } else {
-                                0
-                            }
+                            prompt_tokens.len()
+                                - truncate_tokens_to_byte_fallback_boundary(
+                                    prompt_tokens,
+                                    max_len as usize,
+                                )
+                                .len()
                         })
                         .unwrap_or(0)
                 })
@@ -376,35 +1701,397 @@ pub(crate) mod private_generation_utils {
                 })
                 .collect::<Vec<Vec<i64>>>();
 
-            let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
+            // Decoder-only models generate from the end of the prompt and are padded on the left (validated before generating)
+            let (token_ids, attention_mask) = self._get_tokenizer().pad_token_ids(
+                token_ids,
+                pad_token_id,
+                self.get_config().padding_side,
+            );
+            let device = self.get_var_store().device();
+            (token_ids.to(device), attention_mask.to(device))
+        }
 
-            let pad_token = match pad_token_id {
-                Some(value) => value,
-                None => self._get_tokenizer().get_unk_id(),
+        fn decode_generated_indices(
+            &self,
+            indices: &[i64],
+            skip_special_tokens: bool,
+            keep_pad_tokens: bool,
+            unk_replacement: Option<&str>,
+            control_characters: Option<ControlCharacters>,
+        ) -> (String, usize) {
+            let tokenizer = self._get_tokenizer();
+            let indices = if keep_pad_tokens {
+                indices.to_vec()
+            } else {
+                self.strip_pad_tokens(indices)
+            };
+            let indices = indices.as_slice();
+            let (text, num_replaced_bytes) = match unk_replacement {
+                Some(unk_replacement) => tokenizer.decode_lossy_with_unk_replacement(
+                    indices,
+                    skip_special_tokens,
+                    true,
+                    unk_replacement,
+                ),
+                None => tokenizer.decode_lossy(indices, skip_special_tokens, true),
             };
+            match control_characters {
+                Some(control_characters) => (control_characters.apply(&text), num_replaced_bytes),
+                None => (text, num_replaced_bytes),
+            }
+        }
 
-            let token_ids = token_ids
-                .into_iter()
-                .map(|input| {
-                    let mut temp = vec![pad_token; max_len - input.len()];
-                    temp.extend(input);
-                    temp
+        /// Removes the padding tokens from generated token ids. The padding tokens of the prompts and of the
+        /// sequences finished before the others are not always special tokens of the tokenizer, and would otherwise
+        /// be decoded when special tokens are kept.
+        fn strip_pad_tokens(&self, indices: &[i64]) -> Vec<i64> {
+            match self.get_pad_id() {
+                Some(pad_token_id) => indices
+                    .iter()
+                    .filter(|token_id| **token_id != pad_token_id)
+                    .copied()
+                    .collect(),
+                None => indices.to_vec(),
+            }
+        }
+
+        /// Builds the input ids and attention mask of the cached prompt followed by each suffix, and extends the
+        /// prompt cache with all tokens but the last of each sequence
+        fn prepare_prompt_cache_input<S>(
+            &self,
+            prompt_cache: &PromptCache,
+            suffixes: &[S],
+        ) -> Result<(Tensor, Tensor, Cache), RustBertError>
+        where
+            S: AsRef<str> + Sync,
+        {
+            let device = self.get_var_store().device();
+            let tokenizer = self._get_tokenizer();
+            let pad_token_id = match self.get_pad_id() {
+                Some(value) => Some(value),
+                None => self.get_eos_ids().map(|eos_ids| eos_ids[0]),
+            };
+            let batch_size = suffixes.len() as i64;
+            let (cached_ids, last_prompt_token) = prompt_cache
+                .token_ids
+                .split_at(prompt_cache.token_ids.len() - 1);
+            let cached_length = cached_ids.len() as i64;
+
+            // The last prompt token is followed by the suffix, padding is placed between the cached prompt and these tokens
+            let pending_ids = tokenizer
+                .tokenize_list(suffixes)
+                .iter()
+                .map(|suffix_tokens| {
+                    [
+                        last_prompt_token,
+                        tokenizer.convert_tokens_to_ids(suffix_tokens).as_slice(),
+                    ]
+                    .concat()
                 })
-                .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-                .collect::<Vec<Tensor>>();
-            Tensor::stack(&token_ids, 0)
+                .collect::<Vec<Vec<i64>>>();
+            let (pending_ids, pending_mask) =
+                tokenizer.pad_token_ids(pending_ids, pad_token_id, PaddingSide::Left);
+            let pending_length = *pending_ids.size().last().unwrap();
+            let input_ids = Tensor::cat(
+                &[
+                    Tensor::of_slice(cached_ids)
+                        .unsqueeze(0)
+                        .expand(&[batch_size, cached_length], true),
+                    pending_ids,
+                ],
+                1,
+            )
+            .to(device);
+            let attention_mask = Tensor::cat(
+                &[
+                    Tensor::ones(
+                        &[batch_size, cached_length],
+                        (Kind::Int64, pending_mask.device()),
+                    ),
+                    pending_mask,
+                ],
+                1,
+            )
+            .to(device);
+
+            // Extends the cache of the prompt with all pending tokens but the last
+            let mut past = prompt_cache
+                .past
+                .select_batch(&Tensor::zeros(&[batch_size], (Kind::Int64, device)))?;
+            if pending_length > 1 {
+                let position_ids = (attention_mask.cumsum(-1, Kind::Int64) - 1)
+                    .masked_fill(&attention_mask.eq(0), 1)
+                    .narrow(1, cached_length, pending_length - 1);
+                past = no_grad(|| {
+                    self.get_model().forward_t(
+                        Some(&input_ids.narrow(1, cached_length, pending_length - 1)),
+                        past,
+                        Some(&attention_mask.narrow(1, 0, cached_length + pending_length - 1)),
+                        None,
+                        Some(&position_ids),
+                        None,
+                        None,
+                        None,
+                        false,
+                    )
+                })?
+                .cache;
+            }
+            Ok((input_ids, attention_mask, past))
+        }
+
+        fn prepare_negative_prompt_guidance(
+            &self,
+            negative_prompt: &str,
+            guidance_scale: f64,
+            batch_size: i64,
+            pad_token_id: Option<i64>,
+        ) -> NegativePromptGuidance {
+            let (mut input_ids, mut attention_mask) =
+                self.encode_prompt_text(&[negative_prompt], None, pad_token_id);
+            // An empty negative prompt corresponds to an unconditional generation
+            if *input_ids.size().last().unwrap() == 0 {
+                input_ids = Tensor::ones(&[1, 1], (Kind::Int64, input_ids.device()))
+                    * self
+                        .get_bos_id()
+                        .expect("`bos_token_id` has to be defined for an empty negative prompt.");
+                attention_mask = input_ids.ones_like();
+            }
+            let encoder_outputs = if self.is_encoder_decoder() {
+                Some(
+                    self.encode(&input_ids, Some(&attention_mask))
+                        .unwrap()
+                        .repeat(&[batch_size, 1, 1]),
+                )
+            } else {
+                None
+            };
+            NegativePromptGuidance {
+                input_ids: input_ids.repeat(&[batch_size, 1]),
+                attention_mask: attention_mask.repeat(&[batch_size, 1]),
+                encoder_outputs,
+                guidance_scale,
+            }
+        }
+
+        fn compute_perplexity(&self, text: &str) -> Result<f64, RustBertError> {
+            let tokenizer = self._get_tokenizer();
+            let mut token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text));
+            token_ids.truncate(self.get_max_positions_embeddings() as usize);
+            if token_ids.len() < 2 {
+                return Err(RustBertError::ValueError(
+                    "The perplexity computation requires a text of at least 2 tokens".to_string(),
+                ));
+            }
+            let sequence_length = token_ids.len() as i64;
+            let input_ids = Tensor::of_slice(&token_ids)
+                .unsqueeze(0)
+                .to(self.get_var_store().device());
+
+            let lm_logits = no_grad(|| {
+                self.get_model().forward_t(
+                    Some(&input_ids),
+                    Cache::None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+            })?
+            .lm_logits;
+
+            // The logits at each position predict the next token of the input
+            let logits = lm_logits
+                .get(0)
+                .slice(0, 0, sequence_length - 1, 1)
+                .to_kind(Kind::Float);
+            let labels = input_ids.get(0).slice(0, 1, sequence_length, 1);
+            let mean_cross_entropy = logits.cross_entropy_for_logits(&labels);
+            Ok(mean_cross_entropy.double_value(&[]).exp())
+        }
+
+        fn compute_continuation_log_probabilities(
+            &self,
+            prompt: &str,
+            continuation: &str,
+        ) -> Result<Vec<f64>, RustBertError> {
+            let tokenizer = self._get_tokenizer();
+            let mut prompt_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prompt));
+            let continuation_ids =
+                tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(continuation));
+            if prompt_ids.is_empty() || continuation_ids.is_empty() {
+                return Err(RustBertError::ValueError(
+                    "The continuation scoring requires a prompt and a continuation of at least 1 token"
+                        .to_string(),
+                ));
+            }
+            let max_positions = self.get_max_positions_embeddings() as usize;
+            if continuation_ids.len() >= max_positions {
+                return Err(RustBertError::ValueError(format!(
+                    "The continuation ({} tokens) must be shorter than the model maximum number of positions ({})",
+                    continuation_ids.len(),
+                    max_positions
+                )));
+            }
+            // The prompt is truncated from the left so that the continuation is always scored in full
+            let prompt_capacity = max_positions - continuation_ids.len();
+            if prompt_ids.len() > prompt_capacity {
+                prompt_ids.drain(..prompt_ids.len() - prompt_capacity);
+            }
+            let prompt_length = prompt_ids.len() as i64;
+            let continuation_length = continuation_ids.len() as i64;
+            prompt_ids.extend(continuation_ids);
+            let input_ids = Tensor::of_slice(&prompt_ids)
+                .unsqueeze(0)
+                .to(self.get_var_store().device());
+
+            let lm_logits = no_grad(|| {
+                self.get_model().forward_t(
+                    Some(&input_ids),
+                    Cache::None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+            })?
+            .lm_logits;
+
+            // The logits at the position preceding each continuation token predict that token
+            let log_probabilities = lm_logits
+                .get(0)
+                .slice(
+                    0,
+                    prompt_length - 1,
+                    prompt_length + continuation_length - 1,
+                    1,
+                )
+                .to_kind(Kind::Float)
+                .log_softmax(-1, Kind::Float);
+            let labels =
+                input_ids
+                    .get(0)
+                    .slice(0, prompt_length, prompt_length + continuation_length, 1);
+            let token_log_probabilities = log_probabilities
+                .gather(1, &labels.unsqueeze(-1), false)
+                .squeeze_dim(-1);
+            Ok(token_log_probabilities.iter::<f64>()?.collect::<Vec<f64>>())
+        }
+
+        fn apply_logit_bias(&self, scores: &mut Tensor, logit_bias: &HashMap<i64, f64>) {
+            let (token_ids, biases): (Vec<i64>, Vec<f64>) = logit_bias.iter().unzip();
+            let bias = Tensor::of_slice(&biases)
+                .to_kind(scores.kind())
+                .to_device(scores.device())
+                .unsqueeze(0)
+                .repeat(&[scores.size()[0], 1]);
+            let _ = scores.index_add_(
+                1,
+                &Tensor::of_slice(&token_ids).to_device(scores.device()),
+                &bias,
+            );
+        }
+
+        fn apply_kv_cache_window(
+            &self,
+            prepared_input: &mut PreparedInput,
+            kv_cache_window: i64,
+            kv_cache_sink_tokens: i64,
+        ) {
+            // The support of the cache window by the model and the number of sink tokens are validated before generating
+            if !self.is_encoder_decoder() {
+                // The attention mask of decoder-only models covers the past positions followed by the input ones.
+                // The sink positions of each sequence start after its left padding, and the mask is cropped as the cache.
+                // The position ids are computed from the full mask when preparing the inputs and are not cropped.
+                if let (Some(cached_length), Some(attention_mask)) = (
+                    prepared_input.prepared_past.cached_length(),
+                    prepared_input.prepared_attention_mask.as_mut(),
+                ) {
+                    if cached_length > kv_cache_window {
+                        let device = attention_mask.device();
+                        let past_mask = attention_mask.narrow(1, 0, cached_length);
+                        let padding_lengths = past_mask.cumsum(1, Int64).eq(0).sum_dim_intlist(
+                            [1].as_slice(),
+                            false,
+                            Int64,
+                        );
+                        let window_positions = Tensor::arange(kv_cache_window, (Int64, device));
+                        let recent_positions =
+                            (&window_positions + (cached_length - kv_cache_window)).unsqueeze(0);
+                        let sink_positions =
+                            window_positions.unsqueeze(0) + padding_lengths.unsqueeze(1);
+                        // Sequences fitting in the window (but for their padding) keep their most recent positions
+                        let is_sink = window_positions
+                            .lt(kv_cache_sink_tokens)
+                            .unsqueeze(0)
+                            .logical_and(
+                                &(-&padding_lengths + cached_length)
+                                    .gt(kv_cache_window)
+                                    .unsqueeze(1),
+                            );
+                        let positions = sink_positions.where_self(&is_sink, &recent_positions);
+                        let _ = prepared_input.prepared_past.select_positions(&positions);
+                        let mask_length = attention_mask.size()[1];
+                        *attention_mask = Tensor::cat(
+                            &[
+                                past_mask.gather(1, &positions, false),
+                                attention_mask.narrow(
+                                    1,
+                                    cached_length,
+                                    mask_length - cached_length,
+                                ),
+                            ],
+                            1,
+                        );
+                    }
+                    return;
+                }
+            }
+            let _ = prepared_input
+                .prepared_past
+                .truncate_to_window_with_sink_tokens(kv_cache_window, kv_cache_sink_tokens);
+        }
+
+        /// Replaces NaN and infinite logits by a large negative value, preventing invalid values
+        /// returned by the model from corrupting the sampling or greedy selection of the batch.
+        fn replace_invalid_logits(&self, scores: &mut Tensor) {
+            let _ = scores.nan_to_num_(
+                SANITIZED_LOGIT_VALUE,
+                SANITIZED_LOGIT_VALUE,
+                SANITIZED_LOGIT_VALUE,
+            );
         }
 
+        /// Penalizes the tokens previously generated by each hypothesis, limited to the last `repetition_window`
+        /// tokens if provided. Positions masked by the (optional) attention mask, such as the left padding of batched
+        /// prompts, are ignored.
         fn enforce_repetition_penalty(
             &self,
             next_token_logits: &mut Tensor,
             batch_size: i64,
             num_beams: i64,
             prev_output_tokens: &Tensor,
+            attention_mask: Option<&Tensor>,
             repetition_penalty: f64,
+            repetition_window: Option<i64>,
         ) {
+            let sequence_length = *prev_output_tokens.size().last().unwrap();
+            let window_start = repetition_window.map_or(0, |repetition_window| {
+                max(sequence_length - repetition_window, 0)
+            });
             for i in 0..(batch_size * num_beams) {
-                for token_position in 0..prev_output_tokens.get(i).size()[0] {
+                for token_position in window_start..sequence_length {
+                    if let Some(attention_mask) = attention_mask {
+                        if attention_mask.int64_value(&[i, token_position]) == 0 {
+                            continue;
+                        }
+                    }
                     let token = prev_output_tokens.get(i).int64_value(&[token_position]);
                     let updated_value = &next_token_logits.double_value(&[i, token]);
                     if updated_value < &0f64 {
@@ -428,23 +2115,130 @@ pub(crate) mod private_generation_utils {
             }
         }
 
+        /// Subtracts the frequency penalty times the number of occurrences of each token in the previous tokens of each
+        /// hypothesis, and the presence penalty for the tokens occurring at least once, from its logit. Limited to the
+        /// last `repetition_window` tokens if provided, ignoring the positions masked by the (optional) attention mask.
+        fn enforce_frequency_presence_penalty(
+            &self,
+            next_token_logits: &mut Tensor,
+            prev_output_tokens: &Tensor,
+            attention_mask: Option<&Tensor>,
+            frequency_penalty: f64,
+            presence_penalty: f64,
+            repetition_window: Option<i64>,
+        ) {
+            let sequence_length = *prev_output_tokens.size().last().unwrap();
+            let window_start = repetition_window.map_or(0, |repetition_window| {
+                max(sequence_length - repetition_window, 0)
+            });
+            let window_length = sequence_length - window_start;
+            let tokens = prev_output_tokens
+                .narrow(1, window_start, window_length)
+                .to_device(next_token_logits.device());
+            let occurrences = match attention_mask {
+                Some(attention_mask) => attention_mask
+                    .narrow(1, window_start, window_length)
+                    .to_kind(next_token_logits.kind())
+                    .to_device(next_token_logits.device()),
+                None => tokens.ones_like().to_kind(next_token_logits.kind()),
+            };
+            let counts = next_token_logits
+                .zeros_like()
+                .scatter_add(1, &tokens, &occurrences);
+            let penalty = &counts * frequency_penalty
+                + counts.gt(0).to_kind(next_token_logits.kind()) * presence_penalty;
+            *next_token_logits -= penalty;
+        }
+
+        /// Divides the logits by the temperature of the generation step. With an adaptive temperature, the
+        /// temperature of each hypothesis is raised from the step temperature towards the maximum temperature
+        /// proportionally to the fraction of repeated tokens among its last `adaptive_temperature_window` tokens,
+        /// ignoring the positions masked by the (optional) attention mask.
+        fn apply_temperature(
+            &self,
+            next_token_logits: &mut Tensor,
+            prev_output_tokens: &Tensor,
+            attention_mask: Option<&Tensor>,
+            gen_opt: &InternalGenerateOptions,
+            step: usize,
+        ) {
+            let step_temperature = gen_opt.step_temperature(step);
+            let adaptive_temperature_window = match gen_opt.adaptive_temperature_window {
+                Some(adaptive_temperature_window) => adaptive_temperature_window,
+                None => {
+                    if let Some(temperature) = step_temperature {
+                        *next_token_logits /= temperature;
+                    }
+                    return;
+                }
+            };
+            let base_temperature = step_temperature.unwrap_or(1f64);
+            let sequence_length = *prev_output_tokens.size().last().unwrap();
+            let window_start = max(sequence_length - adaptive_temperature_window, 0);
+            let window_length = sequence_length - window_start;
+            let tokens = prev_output_tokens
+                .narrow(1, window_start, window_length)
+                .to_device(next_token_logits.device());
+            let occurrences = match attention_mask {
+                Some(attention_mask) => attention_mask
+                    .narrow(1, window_start, window_length)
+                    .to_kind(Kind::Float)
+                    .to_device(next_token_logits.device()),
+                None => tokens.ones_like().to_kind(Kind::Float),
+            };
+            let num_tokens = occurrences.sum_dim_intlist([1].as_slice(), false, Kind::Float);
+            let num_distinct_tokens = next_token_logits
+                .zeros_like()
+                .to_kind(Kind::Float)
+                .scatter_add(1, &tokens, &occurrences)
+                .gt(0)
+                .sum_dim_intlist([1].as_slice(), false, Kind::Float);
+            // A window of n tokens contains at most n - 1 repeated tokens
+            let repetition_ratio =
+                ((&num_tokens - num_distinct_tokens) / (num_tokens - 1).clamp_min(1)).clamp(0, 1);
+            let temperatures = repetition_ratio
+                * (gen_opt.adaptive_temperature_max - base_temperature).max(0f64)
+                + base_temperature;
+            *next_token_logits /= temperatures.unsqueeze(-1).to_kind(next_token_logits.kind());
+        }
+
+        /// Returns the tokens completing an ngram already present in each hypothesis. The ngrams are
+        /// tracked independently for each hypothesis, ignoring the positions masked by the (optional)
+        /// attention mask, such as the left padding of batched prompts, and the ngrams not contained in
+        /// the last `repetition_window` tokens if provided.
         fn get_banned_tokens(
             &self,
             input_ids: &Tensor,
+            attention_mask: Option<&Tensor>,
             no_repeat_ngram_size: i64,
             cur_len: i64,
+            repetition_window: Option<i64>,
         ) -> Vec<Vec<i64>> {
             //        Ported from hugging face's transformers and fairseq (https://github.com/pytorch/fairseq/blob/master/fairseq/sequence_generator.py)
             if cur_len + 1 < no_repeat_ngram_size {
                 vec![vec![]]
             } else {
                 let input_ids = input_ids.to(Device::Cpu);
+                let padding_lengths = attention_mask.map(|attention_mask| {
+                    attention_mask
+                        .eq(0)
+                        .cumprod(1, Kind::Int64)
+                        .sum_dim_intlist([1].as_slice(), false, Kind::Int64)
+                        .to(Device::Cpu)
+                });
                 let num_hypothesis = *input_ids.size().first().unwrap();
                 let mut banned_tokens: Vec<Vec<i64>> = Vec::with_capacity(num_hypothesis as usize);
                 for hypothesis_index in 0..num_hypothesis {
                     let hypothesis_input_ids = input_ids.get(hypothesis_index);
                     let mut generated_ngram: HashMap<Vec<i64>, Vec<i64>> = HashMap::new();
-                    let input: Vec<i64> = (0..hypothesis_input_ids.size1().unwrap()).collect();
+                    let padding_length = padding_lengths.as_ref().map_or(0, |padding_lengths| {
+                        padding_lengths.int64_value(&[hypothesis_index])
+                    });
+                    let sequence_length = hypothesis_input_ids.size1().unwrap();
+                    let window_start = repetition_window
+                        .map_or(0, |repetition_window| sequence_length - repetition_window);
+                    let input: Vec<i64> =
+                        (max(padding_length, window_start)..sequence_length).collect();
                     let hypothesis_input_ids = hypothesis_input_ids
                         .iter::<i64>()
                         .unwrap()
@@ -534,6 +2328,15 @@ pub(crate) mod private_generation_utils {
             }
         }
 
+        fn min_p_filtering(&self, logits: &mut Tensor, min_p: f64) {
+            //        Min-p filtering: removes the tokens with a probability lower than `min_p` times
+            //        the probability of the most likely token
+            let probabilities = logits.softmax(-1, Kind::Float);
+            let (max_probabilities, _) = probabilities.max_dim(-1, true);
+            let indices_to_remove = probabilities.lt_tensor(&(max_probabilities * min_p));
+            let _ = logits.masked_fill_(&indices_to_remove, f64::NEG_INFINITY);
+        }
+
         fn run_hamming_diversity_penalty(
             &self,
             scores: &mut Tensor,
@@ -741,6 +2544,61 @@ pub(crate) mod private_generation_utils {
             }
         }
 
+        fn get_stop_string_truncation(
+            &self,
+            token_ids: &[i64],
+            generation_start: usize,
+            stop_strings: &[String],
+        ) -> Option<usize> {
+            // Only the tail of the sequence is decoded: a stop string generated earlier would have
+            // terminated the sequence at a previous step. The tail is decoded as a whole to catch
+            // stop strings spanning over several tokens.
+            let window_size = stop_strings
+                .iter()
+                .map(|stop_string| stop_string.chars().count())
+                .max()
+                .unwrap_or(0)
+                + 1;
+            let tail_start = max(
+                generation_start,
+                token_ids.len().saturating_sub(window_size),
+            );
+            let tokenizer = self._get_tokenizer();
+            let tail_text = tokenizer.decode(&token_ids[tail_start..], true, false);
+            let stop_position = stop_strings
+                .iter()
+                .filter_map(|stop_string| tail_text.find(stop_string.as_str()))
+                .min()?;
+            // Keep the longest prefix of tokens whose text ends before the stop string
+            (tail_start..token_ids.len()).rev().find(|&keep_length| {
+                tokenizer
+                    .decode(&token_ids[tail_start..keep_length], true, false)
+                    .len()
+                    <= stop_position
+            })
+        }
+
+        /// Updates the depth of the brackets opened in the generated text with the text of a generated token,
+        /// returning `true` once the opened brackets are balanced
+        fn update_bracket_depth(
+            &self,
+            depth: &mut usize,
+            token_text: &str,
+            (open, close): (char, char),
+        ) -> bool {
+            for character in token_text.chars() {
+                if character == open {
+                    *depth += 1;
+                } else if character == close && *depth > 0 {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
         fn generate_no_beam_search(
             &self,
             input_ids: Tensor,
@@ -748,9 +2606,12 @@ pub(crate) mod private_generation_utils {
             cur_len: i64,
             batch_size: i64,
             attention_mask: Tensor,
-            gen_opt: InternalGenerateOptions,
+            mut gen_opt: InternalGenerateOptions,
             prefix_allowed_tokens_fn: Option<PrefixAllowedFunction>,
             output_scores: bool,
+            output_top_token_scores: bool,
+            output_entropy: Option<EntropyStage>,
+            trace: bool,
         ) -> GeneratedOutputWithScores {
             let mut unfinished_sentences =
                 Tensor::ones(&[batch_size], (Kind::Int64, self.get_var_store().device()));
@@ -761,19 +2622,63 @@ pub(crate) mod private_generation_utils {
             let mut static_bad_words_mask: Option<Tensor> = None;
             let mut attention_mask = attention_mask.copy();
             let mut input_ids = input_ids.copy();
-            let mut past: Cache = Cache::None;
+            let mut past: Cache = gen_opt.initial_past.take().unwrap_or(Cache::None);
             let mut outputs: Tensor;
             let mut current_length = cur_len;
             let mut token_scores_output: Option<Vec<Tensor>> =
                 if output_scores { Some(vec![]) } else { None };
+            // (top token id, logit, probability, unfinished flag) of each sequence for each generation step
+            let mut top_token_scores_output: Option<Vec<Tensor>> = if output_top_token_scores {
+                Some(vec![])
+            } else {
+                None
+            };
+            // (entropy, unfinished flag) of each sequence for each generation step
+            let mut entropy_output: Option<Vec<Tensor>> = output_entropy.map(|_| Vec::new());
+            let mut trace_output: Option<Vec<Vec<StepTrace>>> = if trace {
+                Some(vec![vec![]; batch_size as usize])
+            } else {
+                None
+            };
+            let padding_token_id = gen_opt
+                .pad_token_id
+                .unwrap_or_else(|| self._get_tokenizer().get_unk_id());
+            let mut stopped_by_string = vec![false; batch_size as usize];
+            let mut stopped_by_brackets = vec![false; batch_size as usize];
+            let mut bracket_depths = vec![0usize; batch_size as usize];
+            let mut stopped_by_callback = vec![false; batch_size as usize];
+            // Tokens injected by the step callback, appended to the sequence in place of the generated tokens
+            let mut injected_tokens: VecDeque<i64> = VecDeque::new();
+            // Inputs and cache of the negative prompt branch for classifier-free guidance
+            let mut negative_input_ids = gen_opt
+                .negative_prompt_guidance
+                .as_ref()
+                .map(|guidance| guidance.input_ids.copy());
+            let mut negative_attention_mask = gen_opt
+                .negative_prompt_guidance
+                .as_ref()
+                .map(|guidance| guidance.attention_mask.copy());
+            let mut negative_past: Cache = Cache::None;
 
             loop {
-                let prepared_input = self.prepare_inputs_for_generation(
+                let mut prepared_input = self.prepare_inputs_for_generation(
                     input_ids.copy(),
                     encoder_outputs.as_ref(),
                     past,
                     attention_mask.copy(),
                 );
+                if let Some(kv_cache_window) = gen_opt.kv_cache_window {
+                    self.apply_kv_cache_window(
+                        &mut prepared_input,
+                        kv_cache_window,
+                        gen_opt.kv_cache_sink_tokens,
+                    );
+                }
+                if gen_opt.kv_cache_device.is_some() {
+                    prepared_input
+                        .prepared_past
+                        .to_device(self.get_var_store().device());
+                }
                 let temp = self
                     .get_model()
                     .forward_t(
@@ -790,8 +2695,89 @@ pub(crate) mod private_generation_utils {
                     .unwrap();
                 outputs = temp.lm_logits;
                 past = temp.cache;
+                if let Some(kv_cache_device) = gen_opt.kv_cache_device {
+                    past.to_device(kv_cache_device);
+                }
 
                 let mut next_token_logits = outputs.select(1, -1);
+                if let Some(top_token_scores) = top_token_scores_output.as_mut() {
+                    // Scores of the top token predicted by the model, before any logits processing
+                    let raw_logits = next_token_logits.to_kind(Kind::Float);
+                    let (top_logits, top_ids) = raw_logits.max_dim(-1, false);
+                    let (top_probabilities, _) =
+                        raw_logits.softmax(-1, Kind::Float).max_dim(-1, false);
+                    top_token_scores.push(Tensor::stack(
+                        &[
+                            top_ids.to_kind(Kind::Double),
+                            top_logits.to_kind(Kind::Double),
+                            top_probabilities.to_kind(Kind::Double),
+                            unfinished_sentences.to_kind(Kind::Double),
+                        ],
+                        -1,
+                    ));
+                }
+                if let (Some(entropy_output), Some(EntropyStage::RawLogits)) =
+                    (entropy_output.as_mut(), output_entropy)
+                {
+                    entropy_output
+                        .push(self.entropy_step(&next_token_logits, &unfinished_sentences));
+                }
+                if let (Some(guidance), Some(negative_input_ids), Some(negative_attention_mask)) = (
+                    gen_opt.negative_prompt_guidance.as_ref(),
+                    negative_input_ids.as_ref(),
+                    negative_attention_mask.as_ref(),
+                ) {
+                    // Encoder-decoder models share the generated decoder inputs with the negative branch
+                    let mut negative_input = self.prepare_inputs_for_generation(
+                        if self.is_encoder_decoder() {
+                            input_ids.copy()
+                        } else {
+                            negative_input_ids.copy()
+                        },
+                        guidance.encoder_outputs.as_ref(),
+                        negative_past,
+                        negative_attention_mask.copy(),
+                    );
+                    if let Some(kv_cache_window) = gen_opt.kv_cache_window {
+                        self.apply_kv_cache_window(
+                            &mut negative_input,
+                            kv_cache_window,
+                            gen_opt.kv_cache_sink_tokens,
+                        );
+                    }
+                    if gen_opt.kv_cache_device.is_some() {
+                        negative_input
+                            .prepared_past
+                            .to_device(self.get_var_store().device());
+                    }
+                    let negative_output = self
+                        .get_model()
+                        .forward_t(
+                            negative_input.prepared_input.as_ref(),
+                            negative_input.prepared_past,
+                            negative_input.prepared_attention_mask.as_ref(),
+                            None,
+                            negative_input.prepared_position_ids.as_ref(),
+                            None,
+                            negative_input.prepared_encoder_output,
+                            negative_input.prepared_decoder_input.as_ref(),
+                            false,
+                        )
+                        .unwrap();
+                    negative_past = negative_output.cache;
+                    if let Some(kv_cache_device) = gen_opt.kv_cache_device {
+                        negative_past.to_device(kv_cache_device);
+                    }
+                    let negative_logits = negative_output.lm_logits.select(1, -1);
+                    next_token_logits = &next_token_logits
+                        + (&next_token_logits - negative_logits) * guidance.guidance_scale;
+                }
+                // The attention mask of decoder-only models covers the prompt padding
+                let decoder_attention_mask = if self.is_encoder_decoder() {
+                    None
+                } else {
+                    Some(&attention_mask)
+                };
                 // Reduce probability for repeated inputs
                 if gen_opt.repetition_penalty > 1f64 {
                     self.enforce_repetition_penalty(
@@ -799,10 +2785,36 @@ pub(crate) mod private_generation_utils {
                         batch_size,
                         1,
                         &input_ids,
+                        decoder_attention_mask,
                         gen_opt.repetition_penalty,
+                        gen_opt.repetition_window,
+                    )
+                }
+                if gen_opt.frequency_penalty != 0f64 || gen_opt.presence_penalty != 0f64 {
+                    self.enforce_frequency_presence_penalty(
+                        &mut next_token_logits,
+                        &input_ids,
+                        decoder_attention_mask,
+                        gen_opt.frequency_penalty,
+                        gen_opt.presence_penalty,
+                        gen_opt.repetition_window,
                     )
                 }
 
+                if let Some(logit_bias) = gen_opt.logit_bias {
+                    self.apply_logit_bias(&mut next_token_logits, logit_bias);
+                }
+
+                // Replace invalid (NaN or infinite) values of the model or biased logits
+                if gen_opt.sanitize_logits {
+                    self.replace_invalid_logits(&mut next_token_logits);
+                }
+
+                // Bound extreme logits before the softmax
+                if let Some((min, max)) = gen_opt.logit_clamp {
+                    let _ = next_token_logits.clamp_(min, max);
+                }
+
                 // Get bad word_ids and set their probability to 0
                 if gen_opt.bad_word_ids.is_some() {
                     // Calculate static bad words masks if not set yet
@@ -826,8 +2838,10 @@ pub(crate) mod private_generation_utils {
                 if gen_opt.no_repeat_ngram_size > 0 {
                     let banned_tokens = self.get_banned_tokens(
                         &input_ids,
+                        decoder_attention_mask,
                         gen_opt.no_repeat_ngram_size,
                         current_length,
+                        gen_opt.repetition_window,
                     );
                     for (batch_index, index_banned_token) in
                         (0..banned_tokens.len() as i64).zip(banned_tokens)
@@ -868,10 +2882,19 @@ pub(crate) mod private_generation_utils {
                     gen_opt.forced_bos_token_id,
                 );
 
-                // Top-k and top-p sampling
-                let next_token = if gen_opt.do_sample {
-                    if gen_opt.temperature > 1f64 {
-                        next_token_logits /= gen_opt.temperature;
+                // Top-k and top-p sampling, after the greedily decoded prefix
+                let sample_next_token = gen_opt.do_sample
+                    && (current_length - cur_len) as usize >= gen_opt.greedy_prefix_len;
+                let next_token = if sample_next_token {
+                    self.apply_temperature(
+                        &mut next_token_logits,
+                        &input_ids,
+                        decoder_attention_mask,
+                        &gen_opt,
+                        (current_length - cur_len) as usize,
+                    );
+                    if let Some(min_p) = gen_opt.min_p {
+                        self.min_p_filtering(&mut next_token_logits, min_p);
                     }
                     self.top_k_top_p_filtering(
                         &mut next_token_logits,
@@ -884,29 +2907,57 @@ pub(crate) mod private_generation_utils {
                 } else {
                     next_token_logits.argmax(-1, false)
                 };
+                let injected_token = injected_tokens.pop_front();
+                let next_token = match injected_token {
+                    Some(token_id) => next_token.full_like(token_id),
+                    None => next_token,
+                };
+
+                if let (Some(entropy_output), Some(EntropyStage::ProcessedLogits)) =
+                    (entropy_output.as_mut(), output_entropy)
+                {
+                    entropy_output
+                        .push(self.entropy_step(&next_token_logits, &unfinished_sentences));
+                }
+
+                if let Some(trace_output) = trace_output.as_mut() {
+                    self.trace_no_beam_search_step(
+                        trace_output,
+                        &next_token_logits,
+                        &next_token,
+                        &unfinished_sentences,
+                    );
+                }
 
                 if let Some(prev_scores) = token_scores_output.as_mut() {
                     let finished_mask = unfinished_sentences.eq(0);
-                    prev_scores.push(
-                        next_token_logits
-                            .log_softmax(-1, next_token_logits.kind())
-                            .gather(1, &next_token.reshape(&[-1, 1]), true)
-                            .squeeze()
-                            .masked_fill(&finished_mask, 0),
-                    );
+                    let step_scores = next_token_logits
+                        .log_softmax(-1, next_token_logits.kind())
+                        .gather(1, &next_token.reshape(&[-1, 1]), true)
+                        .squeeze()
+                        .masked_fill(&finished_mask, 0);
+                    // Injected tokens are not scored
+                    prev_scores.push(if injected_token.is_some() {
+                        step_scores.zeros_like()
+                    } else {
+                        step_scores
+                    });
                 };
 
                 // Add tokens to unfinished sentences
-                let tokens_to_add = match &gen_opt.eos_token_ids {
-                    Some(_) => {
-                        next_token * &unfinished_sentences
-                            - gen_opt.pad_token_id.unwrap() * (&unfinished_sentences - 1)
-                    }
-                    None => next_token,
+                let tokens_to_add = if gen_opt.eos_token_ids.is_some()
+                    | gen_opt.stop_strings.is_some()
+                    | gen_opt.stop_brackets.is_some()
+                {
+                    next_token * &unfinished_sentences
+                        - padding_token_id * (&unfinished_sentences - 1)
+                } else {
+                    next_token
                 };
 
                 input_ids = Tensor::cat(&[input_ids, tokens_to_add.unsqueeze(-1)], -1);
-                if gen_opt.eos_token_ids.is_some() {
+                // Injected tokens do not terminate the sequence
+                if gen_opt.eos_token_ids.is_some() && injected_token.is_none() {
                     for eos_token_id in gen_opt.eos_token_ids.as_ref().unwrap() {
                         let sentence_with_eos =
                             tokens_to_add.eq(*eos_token_id).to_kind(Kind::Int64);
@@ -923,6 +2974,91 @@ pub(crate) mod private_generation_utils {
                         break;
                     }
                 }
+                if let Some(stop_strings) = gen_opt.stop_strings {
+                    for sentence_index in 0..batch_size {
+                        if unfinished_sentences.int64_value(&[sentence_index]) == 0 {
+                            continue;
+                        }
+                        let sentence_ids = input_ids
+                            .get(sentence_index)
+                            .iter::<i64>()
+                            .unwrap()
+                            .collect::<Vec<i64>>();
+                        if let Some(keep_length) = self.get_stop_string_truncation(
+                            &sentence_ids,
+                            cur_len as usize,
+                            stop_strings,
+                        ) {
+                            // Tokens overlapping with the stop string are replaced by padding
+                            let _ = input_ids
+                                .get(sentence_index)
+                                .slice(0, keep_length as i64, None, 1)
+                                .fill_(padding_token_id);
+                            let _ = unfinished_sentences.get(sentence_index).fill_(0);
+                            let _ = sentence_lengths
+                                .get(sentence_index)
+                                .fill_(current_length + 1);
+                            stopped_by_string[sentence_index as usize] = true;
+                        }
+                    }
+                    if i64::from(unfinished_sentences.max()) == 0 {
+                        break;
+                    }
+                }
+                if let Some(stop_brackets) = gen_opt.stop_brackets {
+                    // The bracket depth of each sequence is updated with its last generated token only
+                    let added_token_ids = input_ids
+                        .select(1, -1)
+                        .iter::<i64>()
+                        .unwrap()
+                        .collect::<Vec<i64>>();
+                    for sentence_index in 0..batch_size {
+                        if unfinished_sentences.int64_value(&[sentence_index]) == 0 {
+                            continue;
+                        }
+                        let token_text = self._get_tokenizer().decode(
+                            &added_token_ids[sentence_index as usize..sentence_index as usize + 1],
+                            true,
+                            false,
+                        );
+                        if self.update_bracket_depth(
+                            &mut bracket_depths[sentence_index as usize],
+                            &token_text,
+                            stop_brackets,
+                        ) {
+                            let _ = unfinished_sentences.get(sentence_index).fill_(0);
+                            let _ = sentence_lengths
+                                .get(sentence_index)
+                                .fill_(current_length + 1);
+                            stopped_by_brackets[sentence_index as usize] = true;
+                        }
+                    }
+                    if i64::from(unfinished_sentences.max()) == 0 {
+                        break;
+                    }
+                }
+                if let Some(step_callback) = gen_opt.step_callback.as_mut() {
+                    if injected_token.is_none() {
+                        let completion = input_ids
+                            .get(0)
+                            .slice(0, cur_len, None, 1)
+                            .iter::<i64>()
+                            .unwrap()
+                            .collect::<Vec<i64>>();
+                        match step_callback(&completion) {
+                            GenerationControl::Continue => {}
+                            GenerationControl::InjectTokens(token_ids) => {
+                                injected_tokens.extend(token_ids)
+                            }
+                            GenerationControl::Stop => {
+                                let _ = unfinished_sentences.get(0).fill_(0);
+                                let _ = sentence_lengths.get(0).fill_(current_length + 1);
+                                stopped_by_callback[0] = true;
+                                break;
+                            }
+                        }
+                    }
+                }
                 if !self.is_encoder_decoder() {
                     attention_mask = Tensor::cat(
                         &[
@@ -935,6 +3071,28 @@ pub(crate) mod private_generation_utils {
                         ],
                         -1,
                     );
+                    if let (Some(negative_input_ids), Some(negative_attention_mask)) = (
+                        negative_input_ids.as_mut(),
+                        negative_attention_mask.as_mut(),
+                    ) {
+                        *negative_input_ids = Tensor::cat(
+                            &[
+                                negative_input_ids.shallow_clone(),
+                                input_ids.select(1, -1).unsqueeze(-1),
+                            ],
+                            -1,
+                        );
+                        *negative_attention_mask = Tensor::cat(
+                            &[
+                                negative_attention_mask.shallow_clone(),
+                                negative_attention_mask
+                                    .select(1, -1)
+                                    .ones_like()
+                                    .unsqueeze(-1),
+                            ],
+                            -1,
+                        );
+                    }
                 }
                 current_length += 1;
                 if let Some(max_length) = gen_opt.max_length {
@@ -972,10 +3130,90 @@ pub(crate) mod private_generation_utils {
                     })
                     .collect()
             });
+            let top_token_scores_output = top_token_scores_output.map(|step_scores| {
+                Tensor::stack(&step_scores, 1)
+                    .split(1, 0)
+                    .iter()
+                    .map(|sequence_scores| {
+                        sequence_scores
+                            .squeeze_dim(0)
+                            .split(1, 0)
+                            .iter()
+                            .map(|step_score| {
+                                step_score
+                                    .squeeze_dim(0)
+                                    .iter::<f64>()
+                                    .unwrap()
+                                    .collect::<Vec<f64>>()
+                            })
+                            .filter(|step_score| step_score[3] > 0.0)
+                            .map(|step_score| TopTokenScore {
+                                token_id: step_score[0] as i64,
+                                logit: step_score[1],
+                                probability: step_score[2],
+                            })
+                            .collect::<Vec<TopTokenScore>>()
+                    })
+                    .collect()
+            });
+            let entropy_output = entropy_output.map(|step_entropies| {
+                Tensor::stack(&step_entropies, 1)
+                    .split(1, 0)
+                    .iter()
+                    .map(|sequence_entropies| {
+                        sequence_entropies
+                            .squeeze_dim(0)
+                            .split(1, 0)
+                            .iter()
+                            .map(|step_entropy| {
+                                step_entropy
+                                    .squeeze_dim(0)
+                                    .iter::<f64>()
+                                    .unwrap()
+                                    .collect::<Vec<f64>>()
+                            })
+                            .filter(|step_entropy| step_entropy[1] > 0.0)
+                            .map(|step_entropy| step_entropy[0] as f32)
+                            .collect::<Vec<f32>>()
+                    })
+                    .collect()
+            });
+            let finish_reasons = unfinished_sentences
+                .iter::<i64>()
+                .unwrap()
+                .zip(stopped_by_string)
+                .zip(stopped_by_brackets)
+                .zip(stopped_by_callback)
+                .map(
+                    |(
+                        ((unfinished, stopped_by_string), stopped_by_brackets),
+                        stopped_by_callback,
+                    )| {
+                        if stopped_by_callback {
+                            FinishReason::Callback
+                        } else if stopped_by_string {
+                            FinishReason::StopString
+                        } else if stopped_by_brackets {
+                            FinishReason::StopBrackets
+                        } else if unfinished == 0 {
+                            FinishReason::Stop
+                        } else {
+                            FinishReason::Length
+                        }
+                    },
+                )
+                .collect();
             GeneratedOutputWithScores {
                 indices: input_ids,
                 scores: scores_output,
                 token_scores: token_scores_output,
+                top_token_scores: top_token_scores_output,
+                entropy: entropy_output,
+                trace: trace_output,
+                sequence_lengths: sentence_lengths.iter::<i64>().unwrap().collect(),
+                finish_reasons,
+                past,
+                paused_beam_search: None,
             }
         }
 
@@ -986,12 +3224,24 @@ pub(crate) mod private_generation_utils {
             cur_len: i64,
             batch_size: i64,
             mut attention_mask: Tensor,
-            gen_opt: InternalGenerateOptions,
+            mut gen_opt: InternalGenerateOptions,
             prefix_allowed_tokens_fn: Option<PrefixAllowedFunction>,
             output_scores: bool,
+            trace: bool,
+            beam_search_state: Option<BeamSearchLoopState>,
+            max_steps: Option<usize>,
         ) -> GeneratedOutputWithScores {
             let num_beam_groups = gen_opt.num_beam_groups.unwrap_or(1);
             let num_sub_beams = gen_opt.num_beams / num_beam_groups;
+            // Inputs with a finished beam search may be removed from the batch: `batch_size` is the number of
+            // inputs still being generated and `active_batch_indices` maps them to their original batch index
+            let original_batch_size = batch_size;
+            let mut batch_size = batch_size;
+            let mut active_batch_indices = (0..batch_size as usize).collect::<Vec<usize>>();
+            let mut finished_hypotheses: Vec<Option<BeamHypotheses>> =
+                (0..batch_size).map(|_| None).collect();
+            let mut finished_trace_output: Vec<Vec<StepTrace>> =
+                vec![vec![]; if trace { batch_size as usize } else { 0 }];
             let diversity_penalty = gen_opt.diversity_penalty.unwrap_or(5.5);
             let (bad_word_ids_length_1, bad_word_ids_length_greater_than_1) =
                 self.split_bad_word_ids(gen_opt.bad_word_ids);
@@ -1028,15 +3278,42 @@ pub(crate) mod private_generation_utils {
             );
             let mut saved_beam_scores: Option<Vec<Tensor>> =
                 if output_scores { Some(vec![]) } else { None };
+            let mut trace_output: Option<Vec<Vec<StepTrace>>> = if trace {
+                Some(vec![vec![]; batch_size as usize])
+            } else {
+                None
+            };
             let mut current_tokens = Tensor::new();
 
-            let mut past: Cache = Cache::None;
+            let mut past: Cache = gen_opt.initial_past.take().unwrap_or(Cache::None);
             let mut done = vec![false; batch_size as usize];
 
             let mut outputs: Tensor;
             let mut encoder_outputs = encoder_outputs;
             let mut current_length = cur_len;
 
+            // A paused beam search continues from its saved state
+            if let Some(state) = beam_search_state {
+                input_ids = state.input_ids;
+                attention_mask = state.attention_mask;
+                encoder_outputs = state.encoder_outputs;
+                past = state.past;
+                current_length = state.current_length;
+                beam_scores = state.beam_scores;
+                beam_tokens = state.beam_tokens;
+                beam_indices = state.beam_indices;
+                current_tokens = state.current_tokens;
+                hypotheses = state.hypotheses;
+                finished_hypotheses = state.finished_hypotheses;
+                saved_beam_scores = state.saved_beam_scores;
+                trace_output = state.trace_output;
+                finished_trace_output = state.finished_trace_output;
+                active_batch_indices = state.active_batch_indices;
+                batch_size = active_batch_indices.len() as i64;
+                done = state.done;
+            }
+            let mut num_steps = 0;
+
             loop {
                 if num_beam_groups > 1 {
                     current_tokens = Tensor::zeros(
@@ -1044,12 +3321,24 @@ pub(crate) mod private_generation_utils {
                         (input_ids.kind(), input_ids.device()),
                     );
                 }
-                let prepared_input = self.prepare_inputs_for_generation(
+                let mut prepared_input = self.prepare_inputs_for_generation(
                     input_ids.copy(),
                     encoder_outputs.as_ref(),
                     past,
                     attention_mask.copy(),
                 );
+                if let Some(kv_cache_window) = gen_opt.kv_cache_window {
+                    self.apply_kv_cache_window(
+                        &mut prepared_input,
+                        kv_cache_window,
+                        gen_opt.kv_cache_sink_tokens,
+                    );
+                }
+                if gen_opt.kv_cache_device.is_some() {
+                    prepared_input
+                        .prepared_past
+                        .to_device(self.get_var_store().device());
+                }
                 let temp = self
                     .get_model()
                     .forward_t(
@@ -1067,6 +3356,20 @@ pub(crate) mod private_generation_utils {
                 outputs = temp.lm_logits;
                 past = temp.cache;
 
+                // The trace of a step is only recorded for the inputs still being generated
+                let traced_batch_indices = trace_output.as_mut().map(|trace_output| {
+                    (0..batch_size)
+                        .filter(|&batch_index| !done[batch_index as usize])
+                        .map(|batch_index| {
+                            trace_output[batch_index as usize].push(StepTrace {
+                                candidates: vec![],
+                                chosen: vec![],
+                            });
+                            batch_index
+                        })
+                        .collect::<Vec<i64>>()
+                });
+
                 for beam_group_index in 0..num_beam_groups {
                     let group_start_index = beam_group_index * num_sub_beams;
                     let group_end_index = min(group_start_index + num_sub_beams, gen_opt.num_beams);
@@ -1090,6 +3393,16 @@ pub(crate) mod private_generation_utils {
                     } else {
                         (None, None)
                     };
+                    let group_attention_mask = if self.is_encoder_decoder() {
+                        None
+                    } else {
+                        Some(match &batch_group_indices {
+                            Some(batch_group_indices) => {
+                                attention_mask.index_select(0, batch_group_indices)
+                            }
+                            None => attention_mask.shallow_clone(),
+                        })
+                    };
 
                     let mut next_token_logits = if num_beam_groups <= 1 {
                         outputs.select(1, -1)
@@ -1103,15 +3416,45 @@ pub(crate) mod private_generation_utils {
                         self.enforce_repetition_penalty(
                             &mut next_token_logits,
                             batch_size,
-                            1,
+                            group_size,
                             group_input_ids.as_ref().unwrap_or(&input_ids),
+                            group_attention_mask.as_ref(),
                             gen_opt.repetition_penalty,
+                            gen_opt.repetition_window,
+                        )
+                    }
+                    if gen_opt.frequency_penalty != 0f64 || gen_opt.presence_penalty != 0f64 {
+                        self.enforce_frequency_presence_penalty(
+                            &mut next_token_logits,
+                            group_input_ids.as_ref().unwrap_or(&input_ids),
+                            group_attention_mask.as_ref(),
+                            gen_opt.frequency_penalty,
+                            gen_opt.presence_penalty,
+                            gen_opt.repetition_window,
                         )
                     }
 
-                    if gen_opt.temperature > 1f64 {
-                        next_token_logits /= gen_opt.temperature;
+                    if let Some(logit_bias) = gen_opt.logit_bias {
+                        self.apply_logit_bias(&mut next_token_logits, logit_bias);
+                    }
+
+                    // Replace invalid (NaN or infinite) values of the model or biased logits
+                    if gen_opt.sanitize_logits {
+                        self.replace_invalid_logits(&mut next_token_logits);
+                    }
+
+                    // Bound extreme logits before the softmax
+                    if let Some((min, max)) = gen_opt.logit_clamp {
+                        let _ = next_token_logits.clamp_(min, max);
                     }
+
+                    self.apply_temperature(
+                        &mut next_token_logits,
+                        group_input_ids.as_ref().unwrap_or(&input_ids),
+                        group_attention_mask.as_ref(),
+                        &gen_opt,
+                        (current_length - cur_len) as usize,
+                    );
                     self.prepare_scores_for_generation(
                         &mut next_token_logits,
                         current_length,
@@ -1153,8 +3496,10 @@ pub(crate) mod private_generation_utils {
                     if gen_opt.no_repeat_ngram_size > 0 {
                         let banned_tokens = self.get_banned_tokens(
                             group_input_ids.as_ref().unwrap_or(&input_ids),
+                            group_attention_mask.as_ref(),
                             gen_opt.no_repeat_ngram_size,
                             current_length,
+                            gen_opt.repetition_window,
                         );
                         for (batch_index, index_banned_token) in
                             (0..banned_tokens.len() as i64).zip(banned_tokens)
@@ -1201,7 +3546,12 @@ pub(crate) mod private_generation_utils {
                             beam_scores.unsqueeze(-1).expand_as(&scores)
                         });
 
-                    let (next_scores, next_tokens) = if gen_opt.do_sample {
+                    let sample_next_tokens = gen_opt.do_sample
+                        && (current_length - cur_len) as usize >= gen_opt.greedy_prefix_len;
+                    let (next_scores, next_tokens) = if sample_next_tokens {
+                        if let Some(min_p) = gen_opt.min_p {
+                            self.min_p_filtering(&mut next_scores, min_p);
+                        }
                         self.top_k_top_p_filtering(
                             &mut next_scores,
                             gen_opt.top_k,
@@ -1236,6 +3586,40 @@ pub(crate) mod private_generation_utils {
                     if let Some(eos_token_id) = eos_token_ids {
                         eos_mask -= token_id_tensor.eq(eos_token_id[0]).to_kind(Kind::Int64);
                     }
+                    let mut stop_string_truncations = HashMap::new();
+                    if let Some(stop_strings) = gen_opt.stop_strings {
+                        // Candidates completing a stop string are treated as finished hypotheses
+                        let beam_input_ids = group_input_ids.as_ref().unwrap_or(&input_ids);
+                        for batch_index in 0..batch_size {
+                            if done[batch_index as usize] {
+                                continue;
+                            }
+                            for beam_index_pos in 0..2 * group_size {
+                                if eos_mask.int64_value(&[batch_index, beam_index_pos]) == 0 {
+                                    continue;
+                                }
+                                let effective_beam_id = effective_beam_ids_tensor
+                                    .int64_value(&[batch_index, beam_index_pos]);
+                                let mut candidate_ids = beam_input_ids
+                                    .get(effective_beam_id)
+                                    .iter::<i64>()
+                                    .unwrap()
+                                    .collect::<Vec<i64>>();
+                                candidate_ids.push(
+                                    token_id_tensor.int64_value(&[batch_index, beam_index_pos]),
+                                );
+                                if let Some(keep_length) = self.get_stop_string_truncation(
+                                    &candidate_ids,
+                                    cur_len as usize,
+                                    stop_strings,
+                                ) {
+                                    let _ = eos_mask.get(batch_index).get(beam_index_pos).fill_(0);
+                                    stop_string_truncations
+                                        .insert((batch_index, beam_index_pos), keep_length as i64);
+                                }
+                            }
+                        }
+                    }
                     let eos_mask2 = eos_mask
                         .cumsum(1, Kind::Int64)
                         .le(group_size)
@@ -1245,6 +3629,35 @@ pub(crate) mod private_generation_utils {
                     let group_beam_scores = next_scores.masked_select(&eos_mask2);
                     let group_beam_tokens = token_id_tensor.masked_select(&eos_mask2);
                     let group_beam_indices = effective_beam_ids_tensor.masked_select(&eos_mask2);
+                    if let (Some(trace_output), Some(traced_batch_indices)) =
+                        (trace_output.as_mut(), traced_batch_indices.as_ref())
+                    {
+                        let chosen_beam_ids = beam_ids_tensor.masked_select(&eos_mask2);
+                        for &batch_index in traced_batch_indices {
+                            let step_trace = trace_output[batch_index as usize].last_mut().unwrap();
+                            for candidate_index in 0..2 * group_size {
+                                step_trace.candidates.push(TraceCandidate {
+                                    beam_index: group_start_index
+                                        + beam_ids_tensor
+                                            .int64_value(&[batch_index, candidate_index]),
+                                    token_id: token_id_tensor
+                                        .int64_value(&[batch_index, candidate_index]),
+                                    score: next_scores
+                                        .double_value(&[batch_index, candidate_index]),
+                                });
+                            }
+                            for chosen_index in
+                                batch_index * group_size..(batch_index + 1) * group_size
+                            {
+                                step_trace.chosen.push(TraceCandidate {
+                                    beam_index: group_start_index
+                                        + chosen_beam_ids.int64_value(&[chosen_index]),
+                                    token_id: group_beam_tokens.int64_value(&[chosen_index]),
+                                    score: group_beam_scores.double_value(&[chosen_index]),
+                                });
+                            }
+                        }
+                    }
                     let eos_pos = (eos_mask.ones_like() - eos_mask).nonzero();
 
                     for eos_idx in 0..eos_pos.size()[0] {
@@ -1267,11 +3680,27 @@ pub(crate) mod private_generation_utils {
                                         .get(effective_beam_id)
                                         .copy()
                                 });
-                            hypotheses[batch_index as usize].add(
-                                input_ids.get(effective_beam_id).copy(),
-                                beam_token_score,
-                                saved_beam_scores,
-                            );
+                            match stop_string_truncations.get(&(batch_index, beam_index_pos)) {
+                                Some(&keep_length) => hypotheses[batch_index as usize].add(
+                                    group_input_ids
+                                        .as_ref()
+                                        .unwrap_or(&input_ids)
+                                        .get(effective_beam_id)
+                                        .slice(0, 0, keep_length, 1)
+                                        .copy(),
+                                    beam_token_score,
+                                    saved_beam_scores.map(|scores| {
+                                        scores.slice(0, 0, keep_length - cur_len, 1).copy()
+                                    }),
+                                    FinishReason::StopString,
+                                ),
+                                None => hypotheses[batch_index as usize].add(
+                                    input_ids.get(effective_beam_id).copy(),
+                                    beam_token_score,
+                                    saved_beam_scores,
+                                    FinishReason::Stop,
+                                ),
+                            }
                         }
                     }
 
@@ -1332,6 +3761,57 @@ pub(crate) mod private_generation_utils {
                     break;
                 }
 
+                if gen_opt.shrink_finished_batch && done.iter().any(|&x| x) {
+                    // Remove the inputs with a finished beam search from the batch
+                    let kept_positions = Tensor::of_slice(
+                        &(0..batch_size)
+                            .filter(|&batch_index| !done[batch_index as usize])
+                            .flat_map(|batch_index| {
+                                batch_index * gen_opt.num_beams
+                                    ..(batch_index + 1) * gen_opt.num_beams
+                            })
+                            .collect::<Vec<i64>>(),
+                    )
+                    .to(input_ids.device());
+                    beam_indices = beam_indices.index_select(0, &kept_positions);
+                    beam_tokens = beam_tokens.index_select(0, &kept_positions);
+                    beam_scores = beam_scores.index_select(0, &kept_positions);
+                    attention_mask = attention_mask.index_select(0, &kept_positions);
+                    if let Some(scores_output) = saved_beam_scores.as_mut() {
+                        for step_scores in scores_output.iter_mut() {
+                            *step_scores = step_scores.index_select(0, &kept_positions);
+                        }
+                    }
+                    static_bad_words_mask = None;
+
+                    let mut kept_hypotheses = Vec::with_capacity(hypotheses.len());
+                    let mut kept_trace_output = Vec::with_capacity(hypotheses.len());
+                    let mut kept_batch_indices = Vec::with_capacity(hypotheses.len());
+                    for (position, hypothesis) in hypotheses.into_iter().enumerate() {
+                        let original_index = active_batch_indices[position];
+                        let hypothesis_trace = trace_output
+                            .as_mut()
+                            .map(|trace_output| mem::take(&mut trace_output[position]));
+                        if done[position] {
+                            finished_hypotheses[original_index] = Some(hypothesis);
+                            if let Some(hypothesis_trace) = hypothesis_trace {
+                                finished_trace_output[original_index] = hypothesis_trace;
+                            }
+                        } else {
+                            kept_hypotheses.push(hypothesis);
+                            kept_trace_output.extend(hypothesis_trace);
+                            kept_batch_indices.push(original_index);
+                        }
+                    }
+                    hypotheses = kept_hypotheses;
+                    if let Some(trace_output) = trace_output.as_mut() {
+                        *trace_output = kept_trace_output;
+                    }
+                    active_batch_indices = kept_batch_indices;
+                    batch_size = active_batch_indices.len() as i64;
+                    done = vec![false; batch_size as usize];
+                }
+
                 input_ids = Tensor::cat(
                     &[
                         input_ids.index_select(0, &beam_indices),
@@ -1347,6 +3827,10 @@ pub(crate) mod private_generation_utils {
                     }
                 }
                 encoder_outputs = self.reorder_cache(&mut past, encoder_outputs, &beam_indices);
+                // The cache is reordered on the model device before being moved to the cache device
+                if let Some(kv_cache_device) = gen_opt.kv_cache_device {
+                    past.to_device(kv_cache_device);
+                }
 
                 if !self.is_encoder_decoder() {
                     attention_mask = Tensor::cat(
@@ -1361,6 +3845,39 @@ pub(crate) mod private_generation_utils {
                         -1,
                     );
                 }
+
+                num_steps += 1;
+                if max_steps == Some(num_steps) {
+                    return GeneratedOutputWithScores {
+                        indices: Tensor::new(),
+                        scores: None,
+                        token_scores: None,
+                        top_token_scores: None,
+                        entropy: None,
+                        trace: None,
+                        sequence_lengths: vec![],
+                        finish_reasons: vec![],
+                        past: Cache::None,
+                        paused_beam_search: Some(BeamSearchLoopState {
+                            input_ids,
+                            attention_mask,
+                            encoder_outputs,
+                            past,
+                            current_length,
+                            beam_scores,
+                            beam_tokens,
+                            beam_indices,
+                            current_tokens,
+                            hypotheses,
+                            finished_hypotheses,
+                            saved_beam_scores,
+                            trace_output,
+                            finished_trace_output,
+                            active_batch_indices,
+                            done,
+                        }),
+                    };
+                }
             }
 
             let mut batch_index = 0i64;
@@ -1386,10 +3903,30 @@ pub(crate) mod private_generation_utils {
                         final_tokens,
                         final_score,
                         beam_saved_token_scores,
+                        FinishReason::Length,
                     );
                 }
                 batch_index += 1;
             }
+
+            // Restore the original order of the inputs
+            for (position, (hypothesis, original_index)) in hypotheses
+                .into_iter()
+                .zip(active_batch_indices.iter())
+                .enumerate()
+            {
+                finished_hypotheses[*original_index] = Some(hypothesis);
+                if let Some(trace_output) = trace_output.as_mut() {
+                    finished_trace_output[*original_index] = mem::take(&mut trace_output[position]);
+                }
+            }
+            let hypotheses = finished_hypotheses
+                .into_iter()
+                .map(|hypothesis| hypothesis.unwrap())
+                .collect::<Vec<BeamHypotheses>>();
+            let trace_output = trace_output.map(|_| finished_trace_output);
+            let batch_size = original_batch_size;
+
             let (output_batch_size, output_num_return_sequences_per_batch) = if gen_opt.do_sample {
                 (batch_size, 1)
             } else {
@@ -1413,16 +3950,26 @@ pub(crate) mod private_generation_utils {
             } else {
                 None
             };
+            let mut finish_reasons = Vec::with_capacity(output_batch_size as usize);
+            let mut sequences_trace_output = trace_output
+                .as_ref()
+                .map(|_| Vec::with_capacity(output_batch_size as usize));
             for (hypothesis_index, hypothesis) in hypotheses.iter().enumerate() {
                 let mut sorted_hypotheses = hypothesis.clone();
+                // The raw scores are recovered from the length-normalized scores of the hypotheses
+                if gen_opt.rank_by == ScoreType::Raw {
+                    for (score, sequence, _, _) in sorted_hypotheses.beams.iter_mut() {
+                        *score *= (sequence.size()[0] as f64).powf(gen_opt.length_penalty);
+                    }
+                }
                 sorted_hypotheses
                     .beams
-                    .sort_by_key(|(score, _, _)| OrderedFloat(*score));
+                    .sort_by_key(|(score, _, _, _)| OrderedFloat(*score));
                 for j in 0..output_num_return_sequences_per_batch {
                     let effective_batch_index =
                         output_num_return_sequences_per_batch * hypothesis_index as i64 + j;
 
-                    let (best_score, best_hyp, best_token_scores) =
+                    let (best_score, best_hyp, best_token_scores, finish_reason) =
                         sorted_hypotheses.beams.pop().unwrap();
                     let _ = sentence_lengths.index_fill_(
                         0,
@@ -1430,6 +3977,12 @@ pub(crate) mod private_generation_utils {
                         *best_hyp.size().first().unwrap(),
                     );
                     best_ids.push(best_hyp);
+                    finish_reasons.push(finish_reason);
+                    if let (Some(sequences_trace_output), Some(trace_output)) =
+                        (sequences_trace_output.as_mut(), trace_output.as_ref())
+                    {
+                        sequences_trace_output.push(trace_output[hypothesis_index].clone());
+                    }
                     if let Some(current_best_scores) = &mut scores_output {
                         current_best_scores.push(best_score);
                     }
@@ -1444,82 +3997,1015 @@ pub(crate) mod private_generation_utils {
                     }
                 }
             }
-            let sentence_max_length = gen_opt
-                .max_length
-                .map(|max_length| min(i64::from(sentence_lengths.max()) + 1, max_length))
-                .unwrap_or(i64::from(sentence_lengths.max()) + 1);
+            let sentence_max_length = gen_opt
+                .max_length
+                .map(|max_length| min(i64::from(sentence_lengths.max()) + 1, max_length))
+                .unwrap_or(i64::from(sentence_lengths.max()) + 1);
+
+            let mut decoded = input_ids.new_empty(
+                &[output_batch_size, sentence_max_length],
+                (Kind::Int64, input_ids.device()),
+            );
+            if i64::from(sentence_lengths.max()) != i64::from(sentence_lengths.min()) {
+                let _ = decoded.fill_(
+                    gen_opt
+                        .pad_token_id
+                        .unwrap_or_else(|| gen_opt.eos_token_ids.as_ref().unwrap()[0]),
+                );
+            }
+            let mut sequence_lengths = Vec::with_capacity(best_ids.len());
+            for (hypothesis_index, best_id) in best_ids.iter().enumerate() {
+                let _ = decoded.get(hypothesis_index as i64).index_copy_(
+                    0,
+                    &Tensor::arange_start(
+                        0,
+                        i64::from(sentence_lengths.get(hypothesis_index as i64)),
+                        (Kind::Int64, input_ids.device()),
+                    ),
+                    best_id,
+                );
+                let sentence_length = i64::from(sentence_lengths.get(hypothesis_index as i64));
+                let sentence_length_max = gen_opt
+                    .max_length
+                    .unwrap_or_else(|| i64::from(sentence_lengths.max()));
+                if sentence_length < sentence_length_max {
+                    let _ = decoded.get(hypothesis_index as i64).index_fill_(
+                        0,
+                        &Tensor::of_slice(&[sentence_length]).to_device(input_ids.device()),
+                        gen_opt.eos_token_ids.as_ref().unwrap()[0],
+                    );
+                    sequence_lengths.push(sentence_length + 1);
+                } else {
+                    sequence_lengths.push(sentence_length);
+                }
+            }
+            GeneratedOutputWithScores {
+                indices: decoded,
+                scores: scores_output,
+                token_scores: token_scores_output,
+                top_token_scores: None,
+                entropy: None,
+                trace: sequences_trace_output,
+                sequence_lengths,
+                finish_reasons,
+                past: Cache::None,
+                paused_beam_search: None,
+            }
+        }
+
+        /// Returns the (entropy, unfinished flag) of the next token distribution of each sequence
+        fn entropy_step(
+            &self,
+            next_token_logits: &Tensor,
+            unfinished_sentences: &Tensor,
+        ) -> Tensor {
+            let log_probabilities = next_token_logits.log_softmax(-1, Kind::Float);
+            let probabilities = log_probabilities.exp();
+            // Tokens filtered out (with a null probability) do not contribute to the entropy
+            let entropy = -(&probabilities
+                * log_probabilities.masked_fill(&probabilities.eq(0.0), 0.0))
+            .sum_dim_intlist([-1].as_slice(), false, Kind::Float);
+            Tensor::stack(
+                &[
+                    entropy.to_kind(Kind::Double),
+                    unfinished_sentences.to_kind(Kind::Double),
+                ],
+                -1,
+            )
+        }
+
+        fn trace_no_beam_search_step(
+            &self,
+            trace_output: &mut [Vec<StepTrace>],
+            next_token_logits: &Tensor,
+            next_token: &Tensor,
+            unfinished_sentences: &Tensor,
+        ) {
+            let log_probabilities = next_token_logits.log_softmax(-1, Kind::Float);
+            let num_candidates = min(NUM_TRACE_CANDIDATES, log_probabilities.size()[1]);
+            let (candidate_scores, candidate_ids) =
+                log_probabilities.topk(num_candidates, -1, true, true);
+            let chosen_scores = log_probabilities
+                .gather(1, &next_token.reshape(&[-1, 1]), false)
+                .squeeze_dim(1);
+            for (sequence_index, sequence_trace) in trace_output.iter_mut().enumerate() {
+                let sequence_index = sequence_index as i64;
+                if unfinished_sentences.int64_value(&[sequence_index]) == 0 {
+                    continue;
+                }
+                let candidates = (0..num_candidates)
+                    .map(|candidate_index| TraceCandidate {
+                        beam_index: 0,
+                        token_id: candidate_ids.int64_value(&[sequence_index, candidate_index]),
+                        score: candidate_scores.double_value(&[sequence_index, candidate_index]),
+                    })
+                    .collect();
+                let chosen = vec![TraceCandidate {
+                    beam_index: 0,
+                    token_id: next_token.int64_value(&[sequence_index]),
+                    score: chosen_scores.double_value(&[sequence_index]),
+                }];
+                sequence_trace.push(StepTrace { candidates, chosen });
+            }
+        }
+
+        fn reorder_cache(
+            &self,
+            past: &mut Cache,
+            _encoder_outputs: Option<Tensor>,
+            _beam_indices: &Tensor,
+        ) -> Option<Tensor> {
+            match past {
+                Cache::None => None,
+                _ => {
+                    panic!("Not implemented");
+                }
+            }
+        }
+
+        /// Encodes the prompts of a generation and expands its inputs for the returned sequences and beams, returning
+        /// the generation prompts with the expanded inputs, attention mask, encoder outputs and initial cache
+        #[allow(clippy::too_many_arguments)]
+        fn prepare_generation_prompts(
+            &self,
+            mut input_ids: Tensor,
+            attention_mask: Option<Tensor>,
+            generate_options: Option<GenerateOptions>,
+            do_sample: bool,
+            num_return_sequences: i64,
+            num_beams: i64,
+            pad_token_id: Option<i64>,
+        ) -> Result<
+            (
+                GenerationPrompts,
+                Tensor,
+                Tensor,
+                Option<Tensor>,
+                Option<Cache>,
+            ),
+            RustBertError,
+        > {
+            let decoder_start_token_id =
+                generate_options.and_then(|opts| opts.decoder_start_token_id);
+            let decoder_input_ids = generate_options.and_then(|opts| opts.decoder_input_ids);
+            let output_attention_mask =
+                generate_options.map_or(false, |opts| opts.output_attention_mask);
+            // Masks provided by the caller may be boolean, integer or floating point: non-zero positions are attended
+            let mut attention_mask =
+                attention_mask.map(|attention_mask| attention_mask.ne(0).to_kind(Int64));
+            let input_id_size = input_ids.size();
+            let mut input_ids_len = *input_id_size.last().unwrap();
+            if input_ids_len == 0 {
+                input_ids = Tensor::ones(
+                    &[*input_id_size.first().unwrap(), 1],
+                    (Int64, input_ids.device()),
+                ) * self
+                    .get_bos_id()
+                    .expect("`bos_token_id` has to be defined when no `input_ids` are provided.");
+                attention_mask = Some(Tensor::ones(
+                    &[*input_id_size.first().unwrap(), 1],
+                    (Int64, input_ids.device()),
+                ));
+                input_ids_len += 1;
+            }
+
+            if let Some(decoder_input_ids) = decoder_input_ids {
+                assert_eq!(
+                    decoder_input_ids.size()[0],
+                    input_id_size[0],
+                    "decoder_input_ids must have the same batch size as the input_ids"
+                );
+                if !self.is_encoder_decoder() {
+                    let decoder_input_ids = decoder_input_ids.to_device(input_ids.device());
+                    attention_mask = attention_mask.map(|attention_mask| {
+                        Tensor::cat(&[attention_mask, decoder_input_ids.ones_like()], 1)
+                    });
+                    input_ids = Tensor::cat(&[input_ids, decoder_input_ids], 1);
+                }
+            }
+
+            let cur_len = if !self.is_encoder_decoder() {
+                *input_ids.size().last().unwrap()
+            } else {
+                decoder_input_ids.map_or(1, |decoder_input_ids| {
+                    *decoder_input_ids.size().last().unwrap()
+                })
+            };
+            let batch_size = *input_ids.size().first().unwrap();
+
+            let (effective_batch_size, effective_batch_mult) = match do_sample {
+                true => (batch_size * num_return_sequences, num_return_sequences),
+                false => (batch_size, 1),
+            };
+
+            let attention_mask = match attention_mask {
+                Some(value) => value,
+                None => match pad_token_id {
+                    Some(pad_id) => input_ids.ne(pad_id).to_kind(Int64),
+                    None => input_ids.ones_like().to_kind(Int64),
+                },
+            };
+            let prompt_lengths = attention_mask
+                .sum_dim_intlist([1].as_slice(), false, Int64)
+                .iter::<i64>()
+                .unwrap()
+                .collect::<Vec<i64>>();
+            let prompt_attention_masks = if output_attention_mask {
+                Some(
+                    (0..batch_size)
+                        .map(|prompt_index| {
+                            attention_mask
+                                .get(prompt_index)
+                                .iter::<i64>()
+                                .unwrap()
+                                .collect::<Vec<i64>>()
+                        })
+                        .collect::<Vec<Vec<i64>>>(),
+                )
+            } else {
+                None
+            };
+
+            // The provided cache is repeated for each returned sequence and beam, as the input ids
+            let initial_past = match generate_options.and_then(|opts| opts.past) {
+                Some(past) => {
+                    if self.is_encoder_decoder() || decoder_input_ids.is_some() {
+                        return Err(RustBertError::InvalidConfigurationError(
+                        "A cache can only be provided to decoder-only models, without `decoder_input_ids`"
+                            .into(),
+                    ));
+                    }
+                    let expanded_batch_indices =
+                        Tensor::arange(batch_size, (Int64, input_ids.device()))
+                            .view((-1, 1))
+                            .repeat(&[1, num_beams * effective_batch_mult])
+                            .view(-1);
+                    Some(past.select_batch(&expanded_batch_indices)?)
+                }
+                None => None,
+            };
+
+            let encoder_outputs = if self.is_encoder_decoder() {
+                let encoder_outputs = self.encode(&input_ids, Some(&attention_mask)).unwrap();
+                let expanded_batch_indices =
+                    Tensor::arange(batch_size, (Int64, input_ids.device()))
+                        .view((-1, 1))
+                        .repeat(&[1, num_beams * effective_batch_mult])
+                        .view(-1);
+                Some(encoder_outputs.index_select(0, &expanded_batch_indices))
+            } else {
+                None
+            };
+
+            let (input_ids, attention_mask) = if !self.is_encoder_decoder() {
+                if (num_return_sequences > 1) | (num_beams > 1) {
+                    (
+                        input_ids
+                            .unsqueeze(1)
+                            .expand(
+                                &[batch_size, effective_batch_mult * num_beams, cur_len],
+                                true,
+                            )
+                            .contiguous()
+                            .view((effective_batch_size * num_beams, cur_len)),
+                        attention_mask
+                            .unsqueeze(1)
+                            .expand(
+                                &[batch_size, effective_batch_mult * num_beams, cur_len],
+                                true,
+                            )
+                            .contiguous()
+                            .view((effective_batch_size * num_beams, cur_len)),
+                    )
+                } else {
+                    (input_ids, attention_mask)
+                }
+            } else {
+                let input_ids = match decoder_input_ids {
+                    Some(decoder_input_ids) => decoder_input_ids
+                        .to_device(input_ids.device())
+                        .unsqueeze(1)
+                        .expand(
+                            &[batch_size, effective_batch_mult * num_beams, cur_len],
+                            true,
+                        )
+                        .contiguous()
+                        .view((effective_batch_size * num_beams, cur_len)),
+                    None => {
+                        let decoder_start_token_id = decoder_start_token_id.unwrap_or_else(|| {
+                            self.get_decoder_start_id()
+                                .expect("decoder start id must be specified for encoder decoders")
+                        });
+                        Tensor::full(
+                            &[effective_batch_size * num_beams, 1],
+                            decoder_start_token_id,
+                            (Int64, input_ids.device()),
+                        )
+                    }
+                };
+                let attention_mask = if (num_return_sequences > 1) | (num_beams > 1) {
+                    attention_mask
+                        .unsqueeze(1)
+                        .expand(
+                            &[batch_size, effective_batch_mult * num_beams, input_ids_len],
+                            true,
+                        )
+                        .contiguous()
+                        .view((effective_batch_size * num_beams, input_ids_len))
+                } else {
+                    attention_mask
+                };
+                (input_ids, attention_mask)
+            };
+            let prompts = GenerationPrompts {
+                batch_size,
+                effective_batch_size,
+                cur_len,
+                prompt_lengths,
+                prompt_attention_masks,
+            };
+            Ok((
+                prompts,
+                input_ids,
+                attention_mask,
+                encoder_outputs,
+                initial_past,
+            ))
+        }
+
+        /// Generates token indices from encoded inputs (see `LanguageGenerator::generate_from_ids_and_past_with_cache`),
+        /// or continues a paused beam search. A beam search is paused after `max_steps` generation steps, its state being
+        /// returned instead of the generated indices. The generate options are validated before
+        /// generating, an error being returned if they are invalid.
+        fn generate_from_ids_and_past_with_beam_search_state(
+            &self,
+            start: GenerationStart,
+            generate_options: Option<GenerateOptions>,
+            max_steps: Option<usize>,
+            step_callback: Option<&mut dyn FnMut(&[i64]) -> GenerationControl>,
+        ) -> Result<(Vec<GeneratedIndicesOutput>, Cache, Option<BeamSearchState>), RustBertError>
+        {
+            let eos_token_ids = self.get_eos_ids().cloned();
+
+            let config = self.get_config();
+            let settings = GenerationSettings::new(config, generate_options);
+            settings.validate()?;
+            if let Some(generate_options) = generate_options {
+                generate_options.validate_token_ids(self._get_tokenizer())?;
+            }
+
+            // Set generation options. Priority goes to options provided to the `generate` method, then
+            // model configuration, then default values.
+            let do_sample = unpack_config!(do_sample, generate_options, config);
+            let num_return_sequences =
+                unpack_config!(num_return_sequences, generate_options, config);
+            let rank_by = unpack_config!(rank_by, generate_options, config);
+            let num_beams = unpack_config!(num_beams, generate_options, config);
+            let min_length = unpack_config!(min_length, generate_options, config);
+            let early_stopping = unpack_config!(early_stopping, generate_options, config);
+            let temperature = unpack_config!(temperature, generate_options, config);
+            let temperature_schedule = settings.temperature_schedule;
+            let adaptive_temperature_window = settings
+                .adaptive_temperature_window
+                .map(|adaptive_temperature_window| adaptive_temperature_window as i64);
+            let adaptive_temperature_max = settings.adaptive_temperature_max;
+            let top_k = unpack_config!(top_k, generate_options, config);
+            let top_p = unpack_config!(top_p, generate_options, config);
+            let min_p = generate_options.map_or(config.min_p, |opts| opts.min_p.or(config.min_p));
+            let repetition_penalty = unpack_config!(repetition_penalty, generate_options, config);
+            let frequency_penalty = unpack_config!(frequency_penalty, generate_options, config);
+            let presence_penalty = unpack_config!(presence_penalty, generate_options, config);
+            let length_penalty = unpack_config!(length_penalty, generate_options, config);
+            let no_repeat_ngram_size =
+                unpack_config!(no_repeat_ngram_size, generate_options, config);
+            let repetition_window = generate_options
+                .map_or(config.repetition_window, |opts| {
+                    opts.repetition_window.or(config.repetition_window)
+                })
+                .map(|repetition_window| repetition_window as i64);
+            let greedy_prefix_len = unpack_config!(greedy_prefix_len, generate_options, config);
+            let sanitize_logits = unpack_config!(sanitize_logits, generate_options, config);
+            let logit_clamp = generate_options.map_or(config.logit_clamp, |opts| {
+                opts.logit_clamp.or(config.logit_clamp)
+            });
+            let kv_cache_window = generate_options
+                .map_or(config.kv_cache_window, |opts| {
+                    opts.kv_cache_window.or(config.kv_cache_window)
+                })
+                .map(|kv_cache_window| kv_cache_window as i64);
+            let kv_cache_sink_tokens =
+                unpack_config!(kv_cache_sink_tokens, generate_options, config) as i64;
+            if kv_cache_window.is_some() && !self.supports_kv_cache_window() {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "The key/value cache window is only supported by GPT2 and T5 models".into(),
+                ));
+            }
+            if config.padding_side == PaddingSide::Right && !self.is_encoder_decoder() {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "Decoder-only models generate from the end of the prompts and must be padded on the left"
+                        .into(),
+                ));
+            }
+            let kv_cache_device = generate_options.map_or(config.kv_cache_device, |opts| {
+                opts.kv_cache_device.or(config.kv_cache_device)
+            });
+            let num_beam_groups = generate_options.map_or(config.num_beam_groups, |opts| {
+                opts.num_beam_groups.or(config.num_beam_groups)
+            });
+            let diversity_penalty = generate_options.map_or(config.diversity_penalty, |opts| {
+                opts.diversity_penalty.or(config.diversity_penalty)
+            });
+            let forced_bos_token_id = generate_options.and_then(|opts| opts.forced_bos_token_id);
+            let bad_word_ids = generate_options.and_then(|opts| opts.bad_word_ids);
+            let stop_strings = generate_options
+                .and_then(|opts| opts.stop_strings)
+                .unwrap_or(config.stop_strings.as_slice());
+            let stop_strings = if !stop_strings.is_empty() {
+                Some(stop_strings)
+            } else {
+                None
+            };
+            let logit_bias = generate_options
+                .and_then(|opts| opts.logit_bias)
+                .unwrap_or(&config.logit_bias);
+            let logit_bias = if !logit_bias.is_empty() {
+                Some(logit_bias)
+            } else {
+                None
+            };
+            let prefix_allowed_tokens_fn =
+                generate_options.and_then(|opts| opts.prefix_allowed_tokens_fn);
+            let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
+            let output_top_token_scores =
+                generate_options.map_or(false, |opts| opts.output_top_token_scores);
+            let output_entropy = generate_options.and_then(|opts| opts.output_entropy);
+            let trace = generate_options.map_or(false, |opts| opts.trace);
+            let output_timing = generate_options.map_or(false, |opts| opts.output_timing);
+            let pad_to_length = generate_options.and_then(|opts| opts.pad_to_length);
+            let shrink_finished_batch =
+                generate_options.map_or(false, |opts| opts.shrink_finished_batch);
+
+            let pad_token_id = match self.get_pad_id() {
+                Some(value) => Some(value),
+                None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
+            };
+            // The generation is terminated by the hard stop tokens (defaulting to the end of sequence
+            // tokens of the model), excluding the soft stop tokens
+            let eos_token_ids = generate_options
+                .and_then(|opts| opts.stop_token_ids)
+                .map(|stop_token_ids| stop_token_ids.to_vec())
+                .or(eos_token_ids);
+            let eos_token_ids = match generate_options.and_then(|opts| opts.soft_stop_token_ids) {
+                Some(soft_stop_token_ids) => eos_token_ids
+                    .map(|eos_token_ids| {
+                        eos_token_ids
+                            .into_iter()
+                            .filter(|eos_token_id| !soft_stop_token_ids.contains(eos_token_id))
+                            .collect::<Vec<i64>>()
+                    })
+                    .filter(|eos_token_ids| !eos_token_ids.is_empty()),
+                None => eos_token_ids,
+            };
+
+            // A paused beam search continues from its saved inputs and cache, without encoding the prompts again
+            let (prompts, input_ids, attention_mask, encoder_outputs, initial_past, loop_state) =
+                match start {
+                    GenerationStart::Prompts {
+                        input_ids,
+                        attention_mask,
+                    } => {
+                        let (prompts, input_ids, attention_mask, encoder_outputs, initial_past) =
+                            self.prepare_generation_prompts(
+                                input_ids,
+                                attention_mask,
+                                generate_options,
+                                do_sample,
+                                num_return_sequences,
+                                num_beams,
+                                pad_token_id,
+                            )?;
+                        (
+                            prompts,
+                            input_ids,
+                            attention_mask,
+                            encoder_outputs,
+                            initial_past,
+                            None,
+                        )
+                    }
+                    GenerationStart::PausedBeamSearch(beam_search_state) => {
+                        let mut loop_state = beam_search_state.loop_state;
+                        loop_state.to_device(self.get_var_store().device());
+                        let input_ids = loop_state.input_ids.shallow_clone();
+                        let attention_mask = loop_state.attention_mask.shallow_clone();
+                        (
+                            beam_search_state.prompts,
+                            input_ids,
+                            attention_mask,
+                            None,
+                            None,
+                            Some(loop_state),
+                        )
+                    }
+                };
+            let (cur_len, batch_size, effective_batch_size) = (
+                prompts.cur_len,
+                prompts.batch_size,
+                prompts.effective_batch_size,
+            );
+
+            let max_length = settings.max_length(cur_len);
+
+            if max_length.is_none() & eos_token_ids.is_none() {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "No maximum length given for a model without an EOS token. \
+                This would lead to an infinite generation loop. Please provide a `max_length` or `max_new_tokens`"
+                        .into(),
+                ));
+            }
+
+            if let Some(pad_to_length) = pad_to_length {
+                if pad_token_id.is_none() {
+                    return Err(RustBertError::InvalidConfigurationError(
+                        "Padding the output to `pad_to_length` requires a pad or end of sequence token"
+                            .into(),
+                    ));
+                }
+                match max_length {
+                    Some(max_length) if pad_to_length as i64 >= max_length => {}
+                    _ => {
+                        return Err(RustBertError::InvalidConfigurationError(format!(
+                            "`pad_to_length` ({}) must be at least the maximum length of the generation ({:?})",
+                            pad_to_length, max_length
+                        )));
+                    }
+                }
+            }
 
-            let mut decoded = input_ids.new_empty(
-                &[output_batch_size, sentence_max_length],
-                (Kind::Int64, input_ids.device()),
+            let stop_brackets = generate_options.map_or(config.stop_brackets, |opts| {
+                opts.stop_brackets.or(config.stop_brackets)
+            });
+
+            let negative_prompt = generate_options
+                .and_then(|opts| opts.negative_prompt)
+                .or(config.negative_prompt.as_deref());
+            let guidance_scale = unpack_config!(guidance_scale, generate_options, config);
+            let negative_prompt_guidance = match negative_prompt {
+                Some(negative_prompt) if guidance_scale != 0.0 => {
+                    Some(self.prepare_negative_prompt_guidance(
+                        negative_prompt,
+                        guidance_scale,
+                        effective_batch_size,
+                        pad_token_id,
+                    ))
+                }
+                _ => None,
+            };
+
+            let gen_opt = InternalGenerateOptions {
+                min_length,
+                max_length,
+                do_sample,
+                temperature,
+                temperature_schedule,
+                adaptive_temperature_window,
+                adaptive_temperature_max,
+                top_k,
+                top_p,
+                min_p,
+                repetition_penalty,
+                frequency_penalty,
+                presence_penalty,
+                no_repeat_ngram_size,
+                repetition_window,
+                pad_token_id,
+                eos_token_ids,
+                num_return_sequences,
+                rank_by,
+                early_stopping,
+                num_beams,
+                length_penalty,
+                num_beam_groups,
+                diversity_penalty,
+                forced_bos_token_id,
+                bad_word_ids,
+                stop_strings,
+                stop_brackets,
+                logit_bias,
+                greedy_prefix_len,
+                sanitize_logits,
+                logit_clamp,
+                negative_prompt_guidance,
+                kv_cache_window,
+                kv_cache_sink_tokens,
+                kv_cache_device,
+                // The batch indices passed to the prefix allowed tokens function must remain stable
+                shrink_finished_batch: shrink_finished_batch && prefix_allowed_tokens_fn.is_none(),
+                initial_past,
+                step_callback,
+            };
+
+            let generation_start = Instant::now();
+            let generated_output_with_scores = no_grad(|| {
+                if num_beams > 1 {
+                    self.generate_beam_search(
+                        input_ids,
+                        encoder_outputs,
+                        cur_len,
+                        effective_batch_size,
+                        attention_mask,
+                        gen_opt,
+                        prefix_allowed_tokens_fn,
+                        output_scores,
+                        trace,
+                        loop_state,
+                        max_steps,
+                    )
+                } else {
+                    self.generate_no_beam_search(
+                        input_ids,
+                        encoder_outputs,
+                        cur_len,
+                        effective_batch_size,
+                        attention_mask,
+                        gen_opt,
+                        prefix_allowed_tokens_fn,
+                        output_scores,
+                        output_top_token_scores,
+                        output_entropy,
+                        trace,
+                    )
+                }
+            });
+            let elapsed = if output_timing {
+                synchronize_device(self.get_var_store().device());
+                Some(generation_start.elapsed())
+            } else {
+                None
+            };
+            if let Some(loop_state) = generated_output_with_scores.paused_beam_search {
+                let beam_search_state = BeamSearchState {
+                    num_beams,
+                    prompts,
+                    loop_state,
+                };
+                return Ok((vec![], Cache::None, Some(beam_search_state)));
+            }
+            let (
+                decoded,
+                scores,
+                mut token_scores,
+                mut top_token_scores,
+                mut entropy,
+                mut trace,
+                sequence_lengths,
+                finish_reasons,
+            ) = (
+                generated_output_with_scores.indices,
+                generated_output_with_scores.scores,
+                generated_output_with_scores.token_scores,
+                generated_output_with_scores.top_token_scores,
+                generated_output_with_scores.entropy,
+                generated_output_with_scores.trace,
+                generated_output_with_scores.sequence_lengths,
+                generated_output_with_scores.finish_reasons,
             );
-            if i64::from(sentence_lengths.max()) != i64::from(sentence_lengths.min()) {
-                let _ = decoded.fill_(
-                    gen_opt
-                        .pad_token_id
-                        .unwrap_or_else(|| gen_opt.eos_token_ids.as_ref().unwrap()[0]),
-                );
+            let mut past = generated_output_with_scores.past;
+            if kv_cache_window.is_some() {
+                past = Cache::None;
+            } else if kv_cache_device.is_some() {
+                past.to_device(self.get_var_store().device());
             }
-            for (hypothesis_index, best_id) in best_ids.iter().enumerate() {
-                let _ = decoded.get(hypothesis_index as i64).index_copy_(
-                    0,
-                    &Tensor::arange_start(
-                        0,
-                        i64::from(sentence_lengths.get(hypothesis_index as i64)),
-                        (Kind::Int64, input_ids.device()),
+            let num_sequences = *decoded.size().first().unwrap();
+            let sequences_per_prompt = num_sequences / batch_size;
+            let mut output = Vec::with_capacity(num_sequences as usize);
+            for sequence_index in 0..num_sequences {
+                let mut indices = decoded
+                    .as_ref()
+                    .get(sequence_index)
+                    .iter::<i64>()
+                    .unwrap()
+                    .collect::<Vec<i64>>();
+                // The padding length and token are validated before generating
+                let sequence_length =
+                    pad_to_length
+                        .zip(pad_token_id)
+                        .map(|(pad_to_length, pad_token_id)| {
+                            let sequence_length =
+                                sequence_lengths[sequence_index as usize] as usize;
+                            indices.truncate(sequence_length);
+                            indices.resize(pad_to_length, pad_token_id);
+                            sequence_length
+                        });
+                let score = scores
+                    .as_ref()
+                    .map(|scores_value| scores_value[sequence_index as usize]);
+
+                let token_scores = token_scores
+                    .as_mut()
+                    .map(|token_scores| std::mem::take(&mut token_scores[sequence_index as usize]));
+                let top_token_scores = top_token_scores.as_mut().map(|top_token_scores| {
+                    std::mem::take(&mut top_token_scores[sequence_index as usize])
+                });
+                let entropy = entropy
+                    .as_mut()
+                    .map(|entropy| std::mem::take(&mut entropy[sequence_index as usize]));
+                let trace = trace
+                    .as_mut()
+                    .map(|trace| std::mem::take(&mut trace[sequence_index as usize]));
+
+                let prompt_tokens = prompts.prompt_lengths
+                    [(sequence_index / sequences_per_prompt) as usize]
+                    as usize;
+                let completion_tokens =
+                    (sequence_lengths[sequence_index as usize] - cur_len).max(0) as usize;
+                let usage = GenerationUsage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                };
+
+                output.push(GeneratedIndicesOutput {
+                    indices,
+                    sequence_length,
+                    score,
+                    token_scores,
+                    top_token_scores,
+                    entropy,
+                    trace,
+                    usage,
+                    finish_reason: finish_reasons[sequence_index as usize],
+                    eos_step: eos_step(finish_reasons[sequence_index as usize], completion_tokens),
+                    timing: elapsed.map(|elapsed| GenerationTiming {
+                        tokens_generated: completion_tokens,
+                        elapsed,
+                    }),
+                    attention_mask: prompts.prompt_attention_masks.as_ref().map(
+                        |attention_masks| {
+                            attention_masks[(sequence_index / sequences_per_prompt) as usize]
+                                .clone()
+                        },
                     ),
-                    best_id,
-                );
-                let sentence_length = i64::from(sentence_lengths.get(hypothesis_index as i64));
-                let sentence_length_max = gen_opt
-                    .max_length
-                    .unwrap_or_else(|| i64::from(sentence_lengths.max()));
-                if sentence_length < sentence_length_max {
-                    let _ = decoded.get(hypothesis_index as i64).index_fill_(
-                        0,
-                        &Tensor::of_slice(&[sentence_length]).to_device(input_ids.device()),
-                        gen_opt.eos_token_ids.as_ref().unwrap()[0],
-                    );
-                }
-            }
-            GeneratedOutputWithScores {
-                indices: decoded,
-                scores: scores_output,
-                token_scores: token_scores_output,
+                });
             }
+            Ok((output, past, None))
         }
+    }
+}
 
-        fn reorder_cache(
-            &self,
-            past: &mut Cache,
-            _encoder_outputs: Option<Tensor>,
-            _beam_indices: &Tensor,
-        ) -> Option<Tensor> {
-            match past {
-                Cache::None => None,
-                _ => {
-                    panic!("Not implemented");
-                }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # Handling of control characters
+/// Processing of the control characters of the decoded outputs, which may break their display or serialization.
+/// Newlines and tabs are always kept.
+pub enum ControlCharacters {
+    /// Control characters are removed from the output
+    Strip,
+    /// Control characters are replaced by their `\uXXXX` escape sequence (e.g. `\u001b` for the escape character)
+    Escape,
+}
+
+impl ControlCharacters {
+    /// Applies the control characters handling to a text
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to process
+    ///
+    /// # Returns
+    /// * `String` text with its control characters (other than newlines and tabs) stripped or escaped
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::generation_utils::ControlCharacters;
+    ///
+    /// assert_eq!(ControlCharacters::Strip.apply("a\u{7}b\n"), "ab\n");
+    /// assert_eq!(ControlCharacters::Escape.apply("a\u{7}b"), "a\\u0007b");
+    /// ```
+    pub fn apply(self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        for character in text.chars() {
+            if !character.is_control() || matches!(character, '\n' | '\t') {
+                output.push(character);
+            } else if let ControlCharacters::Escape = self {
+                output.push_str(&format!("\\u{:04x}", character as u32));
             }
         }
+        output
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// # Reason for a generated sequence to stop
+pub enum FinishReason {
+    /// The sequence was terminated by an end of sequence token
+    Stop,
+    /// The sequence reached the maximum generation length
+    Length,
+    /// The sequence was terminated by one of the stop strings
+    StopString,
+    /// The generation was stopped by the step callback (see `LanguageGenerator::generate_with_callback`)
+    Callback,
+    /// The brackets opened in the generated text were balanced (see `GenerateConfig::stop_brackets`)
+    StopBrackets,
+    /// The prompt was empty and no sequence was generated for it
+    EmptyInput,
+}
+
+/// Index of the end of sequence token of a sequence with `completion_tokens` generated tokens, the last one
+/// for sequences terminated by an end of sequence token
+fn eos_step(finish_reason: FinishReason, completion_tokens: usize) -> Option<usize> {
+    match finish_reason {
+        FinishReason::Stop => completion_tokens.checked_sub(1),
+        FinishReason::Length
+        | FinishReason::StopString
+        | FinishReason::Callback
+        | FinishReason::StopBrackets
+        | FinishReason::EmptyInput => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// # Action returned by the step callback of `LanguageGenerator::generate_with_callback`
+pub enum GenerationControl {
+    /// Continue the generation
+    Continue,
+    /// Append the given token ids to the sequence (e.g. the result of a tool call) and continue the generation after them
+    InjectTokens(Vec<i64>),
+    /// Stop the generation
+    Stop,
+}
+
 #[derive(Debug, Clone)]
 /// # Generated text output
-/// Contains generated text and an optional log-likelihood score for the generated sequence
+/// Contains generated text, an optional log-likelihood score for the generated sequence, optional scores of the top token
+/// and entropy of the next token distribution at each generation step, the number of prompt and generated tokens, the reason
+/// the generation stopped, the index of the end of sequence token among the generated tokens (`eos_step`, `None` if the
+/// sequence was not terminated by an end of sequence token), an optional generation timing and the optional attention mask
+/// of the prompt seen by the model
 pub struct GeneratedTextOutput {
     pub text: String,
     pub score: Option<f64>,
+    pub top_token_scores: Option<Vec<TopTokenScore>>,
+    pub entropy: Option<Vec<f32>>,
+    pub trace: Option<Vec<StepTrace>>,
+    pub usage: GenerationUsage,
+    pub finish_reason: FinishReason,
+    pub eos_step: Option<usize>,
+    pub timing: Option<GenerationTiming>,
+    pub attention_mask: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Clone)]
+/// # Generated bytes output
+/// Contains the UTF-8 bytes of the generated text, the number of invalid bytes replaced by `U+FFFD` when decoding
+/// the generated tokens (e.g. for a multi-byte character truncated by the end of the generation) and an optional
+/// log-likelihood score for the generated sequence
+pub struct GeneratedBytesOutput {
+    pub bytes: Vec<u8>,
+    pub num_replaced_bytes: usize,
+    pub score: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
 /// # Generated indices output
-/// Contains generated indices and an optional log-likelihood score for the generated sequence and individual tokens
+/// Contains generated indices, an optional log-likelihood score for the generated sequence and individual tokens,
+/// optional scores of the top token and entropy of the next token distribution at each generation step, the number of prompt
+/// and generated tokens, the reason the generation stopped, the index of the end of sequence token among the generated tokens
+/// (`eos_step`, `None` if the sequence was not terminated by an end of sequence token), an optional generation timing,
+/// the length of the sequence before padding when the output is padded to a fixed length and the optional attention mask
+/// of the prompt seen by the model
 pub struct GeneratedIndicesOutput {
     pub indices: Vec<i64>,
+    pub sequence_length: Option<usize>,
     pub score: Option<f64>,
     pub token_scores: Option<Vec<f64>>,
+    pub top_token_scores: Option<Vec<TopTokenScore>>,
+    pub entropy: Option<Vec<f32>>,
+    pub trace: Option<Vec<StepTrace>>,
+    pub usage: GenerationUsage,
+    pub finish_reason: FinishReason,
+    pub eos_step: Option<usize>,
+    pub timing: Option<GenerationTiming>,
+    pub attention_mask: Option<Vec<i64>>,
+}
+
+impl GeneratedIndicesOutput {
+    /// Output returned for an empty prompt, with a score of 0 if `output_scores` is true
+    fn empty(output_scores: bool) -> Self {
+        GeneratedIndicesOutput {
+            indices: vec![],
+            sequence_length: None,
+            score: if output_scores { Some(0.0) } else { None },
+            token_scores: if output_scores { Some(vec![]) } else { None },
+            top_token_scores: None,
+            entropy: None,
+            trace: None,
+            usage: GenerationUsage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            },
+            finish_reason: FinishReason::EmptyInput,
+            eos_step: None,
+            timing: None,
+            attention_mask: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # Generation usage
+/// Number of tokens consumed and produced for a generated sequence
+pub struct GenerationUsage {
+    /// Number of tokens of the prompt (excluding padding)
+    pub prompt_tokens: usize,
+    /// Number of tokens generated, including the end of sequence token if one was generated.
+    /// Decoder start tokens and `decoder_input_ids` provided to the generation are not counted.
+    pub completion_tokens: usize,
+    /// Sum of the prompt and completion tokens
+    pub total_tokens: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # Generation timing
+/// Number of tokens generated for a sequence and duration of the generation, e.g. for benchmarking
+pub struct GenerationTiming {
+    /// Number of tokens generated for the sequence (see `GenerationUsage::completion_tokens`)
+    pub tokens_generated: usize,
+    /// Duration of the generation loop, shared by all sequences of the batch generated together
+    pub elapsed: Duration,
+}
+
+impl GenerationTiming {
+    /// Returns the number of tokens generated per second for the sequence
+    pub fn tokens_per_second(&self) -> f64 {
+        self.tokens_generated as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # Entropy stage
+/// Distribution of the next token whose entropy is returned at each generation step
+pub enum EntropyStage {
+    /// Distribution predicted by the model, before any logits processing (default)
+    RawLogits,
+    /// Distribution the next token is selected from, after the logits processing (repetition penalty, biases,
+    /// temperature, top-k/top-p filtering...)
+    ProcessedLogits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// # Score type
+/// Score of the hypotheses of beam search
+pub enum ScoreType {
+    /// Cumulative log-probability divided by the sequence length raised to the power of the `length_penalty` (default)
+    #[default]
+    Normalized,
+    /// Cumulative log-probability of the sequence, favoring shorter sequences
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// # Top token score
+/// Raw logit and normalized probability of the most likely token predicted by the model at a generation step,
+/// computed before any logits processing (repetition penalty, temperature, top-k/top-p filtering...).
+/// The top token may differ from the generated token when sampling or when the logits processing modifies the ranking.
+pub struct TopTokenScore {
+    /// Id of the most likely token
+    pub token_id: i64,
+    /// Raw logit of the most likely token
+    pub logit: f64,
+    /// Probability of the most likely token (softmax of the raw logits)
+    pub probability: f64,
+}
+
+/// Number of candidates traced at each step of greedy decoding and sampling
+pub(crate) const NUM_TRACE_CANDIDATES: i64 = 5;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// # Generation trace candidate
+/// Token considered to extend a beam at a generation step, with its score. The score is the log-probability of
+/// the token for greedy decoding and sampling, and the cumulative log-probability of the extended beam for beam search.
+pub struct TraceCandidate {
+    /// Index of the beam extended by the candidate (always 0 for greedy decoding and sampling)
+    pub beam_index: i64,
+    /// Id of the candidate token
+    pub token_id: i64,
+    /// Score of the candidate after logits processing
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// # Generation step trace
+/// Candidates considered at a generation step and the candidates chosen to continue the generation.
+/// For beam search, the top `2 x num_beams` candidates are considered and the i-th chosen candidate becomes the
+/// i-th beam of the next step. For greedy decoding and sampling, the top 5 candidates are considered.
+pub struct StepTrace {
+    /// Top candidates considered at this step
+    pub candidates: Vec<TraceCandidate>,
+    /// Candidates chosen to continue the generation
+    pub chosen: Vec<TraceCandidate>,
 }
 
 pub type PrefixAllowedFunction<'a> = &'a dyn Fn(i64, &Tensor) -> Vec<i64>;
@@ -1528,6 +5014,10 @@ pub type PrefixAllowedFunction<'a> = &'a dyn Fn(i64, &Tensor) -> Vec<i64>;
 /// should return a vector of allowed tokens. This is useful for controlled generation, i.e.
 /// deterministic generation of a token continuation if a sequence of token occurs.
 
+/// Type alias for a function rescoring a finished hypothesis. This function takes the decoded text
+/// and the generation score of a hypothesis and returns its updated score.
+pub type FinalRescorer<'a> = &'a dyn Fn(&str, f64) -> f64;
+
 #[derive(Clone, Copy, Default)]
 /// # Generation options for text generation.
 /// When provided to a `generate` method, these options will take priority over the `GenerateConfig` used to create the
@@ -1541,11 +5031,14 @@ pub struct GenerateOptions<'a> {
     /// Maximum number of new tokens to generate (useful for causal generation models).
     /// Only one of `max_length` and `max_new_tokens` should be provided.
     /// When both are given, `max_new_tokens` is ignored and the `max_length` setting is used.
+    /// Either setting overrides the `max_length` and `max_new_tokens` of the `GenerateConfig`.
     pub max_new_tokens: Option<i64>,
     /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated
     pub early_stopping: Option<bool>,
     /// Number of sequences to return for each prompt text
     pub num_return_sequences: Option<i64>,
+    /// Score used to rank the sequences returned by beam search
+    pub rank_by: Option<ScoreType>,
     /// Number of beams for beam search
     pub num_beams: Option<i64>,
     pub num_beam_groups: Option<i64>,
@@ -1553,36 +5046,155 @@ pub struct GenerateOptions<'a> {
     pub do_sample: Option<bool>,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance
     pub temperature: Option<f64>,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature`. The last value persists past the end of the schedule
+    pub temperature_schedule: Option<&'a [f64]>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature
+    pub adaptive_temperature_max: Option<f64>,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature
     pub top_k: Option<i64>,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p
     pub top_p: Option<f64>,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated.
     pub repetition_penalty: Option<f64>,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence
+    pub frequency_penalty: Option<f64>,
+    /// Presence penalty subtracted from the logit of the tokens present in the sequence
+    pub presence_penalty: Option<f64>,
     /// Exponential penalty based on the length of the hypotheses generated
     pub length_penalty: Option<f64>,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature
     pub no_repeat_ngram_size: Option<i64>,
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking
+    pub repetition_window: Option<usize>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups
     pub diversity_penalty: Option<f64>,
     /// Decoder start token id
     pub decoder_start_token_id: Option<i64>,
+    /// Token ids already generated, of shape (*batch size*, *sequence_length*), from which the generation is continued
+    /// (e.g. a prefix of the `indices` returned by a previous generation). For encoder-decoder models, these are used as
+    /// decoder input instead of the decoder start token and must start with it. For decoder-only models, these are appended
+    /// to the prompt. The cache is rebuilt from these tokens at the first generation step.
+    pub decoder_input_ids: Option<&'a Tensor>,
     /// Forced first token generated
     pub forced_bos_token_id: Option<i64>,
     /// Function to control the generation process. The function should take a `batch_id` (i64) and a tensor of token_ids already generated and returns a `Vec<i64>` of allowed tokens.
     pub prefix_allowed_tokens_fn: Option<PrefixAllowedFunction<'a>>,
     /// List of bad word ids (may be a sequence of word ids) that will be banned during the generation
     pub bad_word_ids: Option<&'a Vec<Vec<i64>>>,
+    /// Strings terminating the generation of a sequence when generated
+    pub stop_strings: Option<&'a [String]>,
+    /// Opening and closing characters terminating the generation of a sequence once balanced. Only supported by greedy decoding and sampling
+    pub stop_brackets: Option<(char, char)>,
+    /// Token ids terminating the generation of a sequence (hard stops), replacing the end of sequence tokens of the model
+    pub stop_token_ids: Option<&'a [i64]>,
+    /// Token ids that do not terminate the generation of a sequence (soft stops), even if they are end of sequence
+    /// tokens of the model or hard stop tokens. This allows continuing the generation past intermediate separators.
+    pub soft_stop_token_ids: Option<&'a [i64]>,
+    /// Bias added to the logits of the given token ids at every generation step
+    pub logit_bias: Option<&'a HashMap<i64, f64>>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled
+    pub greedy_prefix_len: Option<usize>,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection
+    pub sanitize_logits: Option<bool>,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance (greedy decoding and sampling only)
+    pub negative_prompt: Option<&'a str>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)`
+    pub guidance_scale: Option<f64>,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention (GPT2 and T5 only)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache never evicted by the cache window, counted within the window
+    pub kv_cache_sink_tokens: Option<usize>,
+    /// Device the key/value cache is kept on between generation steps, transferred to the model device at each step
+    pub kv_cache_device: Option<Device>,
+    /// Cache of the decoder self-attention for all input positions but the last, with the batch size of the input ids
+    /// (GPT2 only). The generation starts from this cache instead of encoding the full input (see `LanguageGenerator::generate_from_prompt_cache`).
+    pub past: Option<&'a Cache>,
+    /// Flag indicating if special tokens should be removed from the decoded output
+    pub skip_special_tokens: Option<bool>,
+    /// Flag indicating if the padding tokens should be kept in the decoded output
+    pub keep_pad_tokens: Option<bool>,
+    /// Placeholder replacing the unknown tokens in the decoded output, an empty placeholder drops them
+    pub unk_replacement: Option<&'a str>,
+    /// Handling of the control characters (other than newlines and tabs) of the decoded output
+    pub control_characters: Option<ControlCharacters>,
     /// Flag indicating if text generation scores should be returned
     pub output_scores: bool,
+    /// Flag indicating if the raw logit and probability of the top token at each generation step should be returned.
+    /// Scores are returned as raw `f64` values. Not supported for beam search (no scores are returned when `num_beams` > 1).
+    pub output_top_token_scores: bool,
+    /// Stage of the logits processing at which the entropy (in nats) of the next token distribution is computed and
+    /// returned for each generation step, typically to flag uncertain generations. No entropy is returned if `None`.
+    /// Not supported for beam search (no entropy is returned when `num_beams` > 1).
+    pub output_entropy: Option<EntropyStage>,
+    /// Flag indicating if a trace of the candidates considered and chosen at each generation step should be returned.
+    /// This is intended for debugging and significantly slows down the generation.
+    pub trace: bool,
+    /// Flag indicating if the number of generated tokens and the duration of the generation should be returned
+    pub output_timing: bool,
+    /// Flag indicating if the attention mask of the prompt seen by the model, after truncation, padding and conversion
+    /// to integers, should be returned (e.g. to debug batched inputs). Returned for each generated sequence.
+    pub output_attention_mask: bool,
+    /// Length the returned indices are padded to with the padding token (e.g. for storage in a fixed-shape array),
+    /// the length of each sequence before padding being returned as `sequence_length`. It must be at least the maximum
+    /// generation length, an `InvalidConfigurationError` being returned otherwise.
+    /// Only applies to the indices returned by `generate_indices` and `generate_from_ids_and_past`.
+    pub pad_to_length: Option<usize>,
+    /// Flag indicating if the inputs whose beam search is finished should be removed from the batch, speeding up the
+    /// generation of the remaining inputs. The output is identical to the generation without removal.
+    /// Only applies to beam search, and is ignored when a `prefix_allowed_tokens_fn` is provided.
+    pub shrink_finished_batch: bool,
+    /// Function rescoring the finished hypotheses from their decoded text and generation score before selecting the
+    /// returned sequences (e.g. to add a bonus for hypotheses matching a pattern). For beam search, all `num_beams`
+    /// finished hypotheses of each input are rescored. Only applies to the `generate` method returning decoded texts.
+    pub final_rescorer: Option<FinalRescorer<'a>>,
 }
 
-macro_rules! unpack_config {
-    ($field_name:ident, $generate_options: ident, $generate_config: ident) => {
-        $generate_options.map_or($generate_config.$field_name, |opts| {
-            opts.$field_name.unwrap_or($generate_config.$field_name)
-        })
-    };
+impl<'a> GenerateOptions<'a> {
+    /// Checks that the token ids of the generation constraints (`forced_bos_token_id`, `bad_word_ids` and
+    /// `logit_bias`) are in the vocabulary of the tokenizer. The generation methods return the same error on
+    /// invalid token ids.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokenizer` - `&TokenizerOption` tokenizer of the model the options are used with
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error naming the first token id outside of the vocabulary
+    pub fn validate_token_ids(&self, tokenizer: &TokenizerOption) -> Result<(), RustBertError> {
+        check_token_ids(tokenizer, self.forced_bos_token_id, "forced_bos_token_id")?;
+        if let Some(bad_word_ids) = self.bad_word_ids {
+            check_token_ids(
+                tokenizer,
+                bad_word_ids.iter().flatten().copied(),
+                "bad_word_ids",
+            )?;
+        }
+        if let Some(logit_bias) = self.logit_bias {
+            check_token_ids(tokenizer, logit_bias.keys().copied(), "logit_bias")?;
+        }
+        Ok(())
+    }
+}
+
+fn check_token_ids<I>(tokenizer: &TokenizerOption, ids: I, name: &str) -> Result<(), RustBertError>
+where
+    I: IntoIterator<Item = i64>,
+{
+    match ids.into_iter().find(|id| !tokenizer.is_valid_id(*id)) {
+        Some(id) => Err(RustBertError::InvalidConfigurationError(format!(
+            "{} contains the token id {}, outside of the vocabulary of {} tokens",
+            name,
+            id,
+            tokenizer.get_vocab_size()
+        ))),
+        None => Ok(()),
+    }
 }
 
 /// # Common trait for text generation models.
@@ -1594,11 +5206,12 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     ///
     /// # Arguments
     ///
-    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`). Empty prompt strings are not passed to the model and return empty sequences with a `FinishReason::EmptyInput` finish reason.
     /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
     ///
     /// # Returns
-    /// * `Vec<TextOutput>` Vector of length *number_of_prompts* x *num_return_sequences* containing TextOutput with the generated texts and the generation score if `output_scores` is true.
+    /// * `Result<Vec<TextOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing TextOutput with the generated texts and the generation score if `output_scores` is true,
+    /// or an error if the generate options are invalid.
     ///
     /// # Example
     ///
@@ -1659,7 +5272,7 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     /// let output = gpt2_generator.generate(
     ///     Some(&[input_context, second_input_context]),
     ///     Some(generate_options),
-    /// );
+    /// )?;
     /// # Ok(())
     /// # }
     /// ```
@@ -1680,32 +5293,294 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
         &self,
         prompt_texts: Option<&[S]>,
         generate_options: Option<GenerateOptions>,
-    ) -> Vec<GeneratedTextOutput>
+    ) -> Result<Vec<GeneratedTextOutput>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        let indices_outputs = self.generate_indices(prompt_texts, generate_options);
+        let config = PrivateLanguageGenerator::get_config(self);
+        let skip_special_tokens = unpack_config!(skip_special_tokens, generate_options, config);
+        let keep_pad_tokens = unpack_config!(keep_pad_tokens, generate_options, config);
+        let unk_replacement = generate_options
+            .and_then(|opts| opts.unk_replacement)
+            .or(config.unk_replacement.as_deref());
+        let control_characters = generate_options.map_or(config.control_characters, |opts| {
+            opts.control_characters.or(config.control_characters)
+        });
+        let final_rescorer = generate_options.and_then(|opts| opts.final_rescorer);
+        let num_return_sequences = unpack_config!(num_return_sequences, generate_options, config);
+        let num_generated_sequences = match final_rescorer {
+            // All finished beam hypotheses are rescored before selecting the returned sequences
+            Some(_) if !unpack_config!(do_sample, generate_options, config) => {
+                unpack_config!(num_beams, generate_options, config).max(num_return_sequences)
+            }
+            _ => num_return_sequences,
+        };
+        let indices_generate_options = match final_rescorer {
+            Some(_) => Some(GenerateOptions {
+                num_return_sequences: Some(num_generated_sequences),
+                output_scores: true,
+                ..generate_options.unwrap_or_default()
+            }),
+            None => generate_options,
+        };
+
+        let indices_outputs = self.generate_indices(prompt_texts, indices_generate_options)?;
         let mut output = Vec::with_capacity(indices_outputs.len());
         for generated_sequence in indices_outputs {
             output.push(GeneratedTextOutput {
                 text: self
-                    ._get_tokenizer()
-                    .decode(&generated_sequence.indices, true, true),
+                    .decode_generated_indices(
+                        &generated_sequence.indices,
+                        skip_special_tokens,
+                        keep_pad_tokens,
+                        unk_replacement,
+                        control_characters,
+                    )
+                    .0,
                 score: generated_sequence.score,
+                top_token_scores: generated_sequence.top_token_scores,
+                entropy: generated_sequence.entropy,
+                trace: generated_sequence.trace,
+                usage: generated_sequence.usage,
+                finish_reason: generated_sequence.finish_reason,
+                eos_step: generated_sequence.eos_step,
+                timing: generated_sequence.timing,
+                attention_mask: generated_sequence.attention_mask,
             });
         }
-        output
+
+        if let Some(final_rescorer) = final_rescorer {
+            let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
+            let mut rescored_output = Vec::with_capacity(output.len());
+            let mut output = output.into_iter().peekable();
+            while output.peek().is_some() {
+                let mut hypotheses = output
+                    .by_ref()
+                    .take(num_generated_sequences as usize)
+                    .map(|hypothesis| {
+                        let score = final_rescorer(&hypothesis.text, hypothesis.score.unwrap());
+                        (score, hypothesis)
+                    })
+                    .collect::<Vec<(f64, GeneratedTextOutput)>>();
+                hypotheses
+                    .sort_by(|(score_a, _), (score_b, _)| score_b.partial_cmp(score_a).unwrap());
+                rescored_output.extend(
+                    hypotheses
+                        .into_iter()
+                        .take(num_return_sequences as usize)
+                        .map(|(score, hypothesis)| GeneratedTextOutput {
+                            score: if output_scores { Some(score) } else { None },
+                            ..hypothesis
+                        }),
+                );
+            }
+            return Ok(rescored_output);
+        }
+        Ok(output)
+    }
+
+    /// Generate text based on a vector of prompt texts, returning the UTF-8 bytes of the generated texts (e.g. to pass
+    /// them across an FFI boundary without re-validation). Invalid UTF-8 sequences produced by the generated tokens are
+    /// replaced by `U+FFFD`, and the number of bytes replaced is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`). Empty prompt strings are not passed to the model and return empty sequences with a `FinishReason::EmptyInput` finish reason.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<GeneratedBytesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing the UTF-8 bytes of the generated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let input_context = "The dog";
+    ///
+    /// let output = gpt2_generator.generate_bytes(Some(&[input_context]), None)?;
+    /// for generated_sequence in output {
+    ///     assert!(std::str::from_utf8(&generated_sequence.bytes).is_ok());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_bytes<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedBytesOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let config = PrivateLanguageGenerator::get_config(self);
+        let skip_special_tokens = unpack_config!(skip_special_tokens, generate_options, config);
+        let keep_pad_tokens = unpack_config!(keep_pad_tokens, generate_options, config);
+        let unk_replacement = generate_options
+            .and_then(|opts| opts.unk_replacement)
+            .or(config.unk_replacement.as_deref());
+        let control_characters = generate_options.map_or(config.control_characters, |opts| {
+            opts.control_characters.or(config.control_characters)
+        });
+        Ok(self
+            .generate_indices(prompt_texts, generate_options)?
+            .into_iter()
+            .map(|generated_sequence| {
+                let (text, num_replaced_bytes) = self.decode_generated_indices(
+                    &generated_sequence.indices,
+                    skip_special_tokens,
+                    keep_pad_tokens,
+                    unk_replacement,
+                    control_characters,
+                );
+                GeneratedBytesOutput {
+                    bytes: text.into_bytes(),
+                    num_replaced_bytes,
+                    score: generated_sequence.score,
+                }
+            })
+            .collect())
+    }
+
+    /// Generate text and the corresponding token ids based on a vector of prompt texts, avoiding the re-tokenization of the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`). Empty prompt strings are not passed to the model and return empty sequences with a `FinishReason::EmptyInput` finish reason.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `skip_special_tokens` - `bool` Flag indicating if special tokens should be excluded from the returned token ids and text
+    ///
+    /// # Returns
+    /// * `Result<Vec<(Vec<i64>, String)>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing the generated token ids and the corresponding decoded text
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let input_context = "The dog";
+    ///
+    /// let output = gpt2_generator.generate_with_ids(Some(&[input_context]), None, true)?;
+    /// for (token_ids, text) in output {
+    ///     println!("{:?}: {}", token_ids, text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_with_ids<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+        skip_special_tokens: bool,
+    ) -> Result<Vec<(Vec<i64>, String)>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let tokenizer = self._get_tokenizer();
+        let config = PrivateLanguageGenerator::get_config(self);
+        let keep_pad_tokens = unpack_config!(keep_pad_tokens, generate_options, config);
+        Ok(self
+            .generate_indices(prompt_texts, generate_options)?
+            .into_iter()
+            .map(|generated_sequence| {
+                let indices = if keep_pad_tokens {
+                    generated_sequence.indices
+                } else {
+                    self.strip_pad_tokens(&generated_sequence.indices)
+                };
+                let text = tokenizer.decode(&indices, skip_special_tokens, true);
+                let token_ids = if skip_special_tokens {
+                    indices
+                        .into_iter()
+                        .filter(|token_id| !tokenizer.is_special_token_id(*token_id))
+                        .collect()
+                } else {
+                    indices
+                };
+                (token_ids, text)
+            })
+            .collect())
+    }
+
+    /// Generate token indices and scores of all returned sequences grouped by prompt (e.g. to collect distillation data).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`). Empty prompt strings are not passed to the model and return empty sequences with a `FinishReason::EmptyInput` finish reason.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator. The scores are always computed.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Vec<(Vec<i64>, f32)>>, RustBertError>` Vector of length *number_of_prompts* containing for each prompt the *num_return_sequences* generated token ids and their score
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{GenerateOptions, LanguageGenerator};
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let generate_options = GenerateOptions {
+    ///     num_beams: Some(3),
+    ///     num_return_sequences: Some(3),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let output = gpt2_generator
+    ///     .generate_grouped_indices(Some(&["The dog", "The cat was"]), Some(generate_options))?;
+    /// for (token_ids, score) in &output[0] {
+    ///     println!("{:?}: {}", token_ids, score);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_grouped_indices<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<Vec<(Vec<i64>, f32)>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let config = PrivateLanguageGenerator::get_config(self);
+        let num_return_sequences = unpack_config!(num_return_sequences, generate_options, config);
+        let generate_options = GenerateOptions {
+            output_scores: true,
+            ..generate_options.unwrap_or_default()
+        };
+        Ok(self
+            .generate_indices(prompt_texts, Some(generate_options))?
+            .chunks(num_return_sequences as usize)
+            .map(|generated_sequences| {
+                generated_sequences
+                    .iter()
+                    .map(|generated_sequence| {
+                        (
+                            generated_sequence.indices.clone(),
+                            generated_sequence.score.unwrap() as f32,
+                        )
+                    })
+                    .collect()
+            })
+            .collect())
     }
 
     /// Generate token indices without decoding (useful for token-level operations before returning final text or as validation step during training).
     ///
     /// # Arguments
     ///
-    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. An empty prompt to the model may be passed if the model implement a `bos_id`.
+    /// * `prompt_texts` - `Option<Vec<&str>>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`). Empty prompt strings are not passed to the model and return empty sequences with a `FinishReason::EmptyInput` finish reason.
     /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
     ///
     /// # Returns
-    /// * `Vec<IndicesOutput>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true,
+    /// or an error if the generate options are invalid.
     ///
     /// # Example
     ///
@@ -1766,7 +5641,7 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     /// let output = gpt2_generator.generate_indices(
     ///     Some(&[input_context, second_input_context]),
     ///     Some(generate_options),
-    /// );
+    /// )?;
     /// # Ok(())
     /// # }
     /// ```
@@ -1774,42 +5649,46 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
         &self,
         prompt_texts: Option<&[S]>,
         generate_options: Option<GenerateOptions>,
-    ) -> Vec<GeneratedIndicesOutput>
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        let eos_token_ids = self.get_eos_ids();
-
-        let config = self.get_config();
-
-        let max_length = generate_options.map_or(config.max_length, |generate_options| {
-            generate_options.max_length
-        });
-        let encoding_max_len = if self.is_encoder_decoder() {
-            Some(self.get_max_positions_embeddings())
-        } else {
-            max_length
-        };
-        let pad_token_id = match self.get_pad_id() {
-            Some(value) => Some(value),
-            None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
-        };
-
-        let input_ids = match prompt_texts {
-            Some(prompts) if !prompts.is_empty() => {
-                self.encode_prompt_text(prompts, encoding_max_len, pad_token_id)
-            }
-            None => match self.get_bos_id() {
-                Some(bos_id) => {
-                    Tensor::ones(&[1, 1], (Int64, self.get_var_store().device())) * bos_id
+        // Empty prompts are not passed to the model: empty sequences are returned in their place
+        if let Some(prompts) = prompt_texts {
+            if prompts.iter().any(|prompt| prompt.as_ref().is_empty()) {
+                let config = PrivateLanguageGenerator::get_config(self);
+                let num_return_sequences =
+                    unpack_config!(num_return_sequences, generate_options, config) as usize;
+                let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
+                let non_empty_prompts = prompts
+                    .iter()
+                    .map(|prompt| prompt.as_ref())
+                    .filter(|prompt| !prompt.is_empty())
+                    .collect::<Vec<&str>>();
+                let mut generated_sequences = self
+                    .generate_indices(Some(non_empty_prompts.as_slice()), generate_options)?
+                    .into_iter();
+
+                let mut output = Vec::with_capacity(prompts.len() * num_return_sequences);
+                for prompt in prompts {
+                    if prompt.as_ref().is_empty() {
+                        output.extend(
+                            (0..num_return_sequences)
+                                .map(|_| GeneratedIndicesOutput::empty(output_scores)),
+                        );
+                    } else {
+                        output.extend(generated_sequences.by_ref().take(num_return_sequences));
+                    }
                 }
-                None => panic!(
-                    "A model with a BOS token must be used to start generation with an empty input"
-                ),
-            },
-            _ => return Vec::new(),
-        };
-        self.generate_from_ids_and_past(input_ids, None, generate_options)
+                return Ok(output);
+            }
+        }
+        let (input_ids, attention_mask) =
+            match self.encode_generation_inputs(prompt_texts, generate_options) {
+                Some(inputs) => inputs,
+                None => return Ok(Vec::new()),
+            };
+        self.generate_from_ids_and_past(input_ids, attention_mask, generate_options)
     }
 
     /// Generate token indices given a list of indices (useful when the input has been pre-tokenized).
@@ -1818,10 +5697,12 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     /// # Arguments
     ///
     /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `attention_mask` - `Option<Tensor>` Optional mask of the input, with a value of 0 for masked (padding) positions. Boolean, integer and floating point masks are accepted (default: positions not equal to the padding token)
     /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
     ///
     /// # Returns
-    /// * `Vec<IndicesOutput>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true.
+    /// * `Result<Vec<IndicesOutput>, RustBertError>` Vector of length *number_of_prompts* x *num_return_sequences* containing IndicesOutput with the generated indices and the generation score if `output_scores` is true,
+    /// or an error if the generate options are invalid.
     ///
     /// # Example
     ///
@@ -1858,238 +5739,456 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     ///     input_tensor,
     ///     Some(input_mask),
     ///     Some(generate_options),
-    /// );
+    /// )?;
     /// # Ok(())
     /// # }
     /// ```
     fn generate_from_ids_and_past(
         &self,
-        mut input_ids: Tensor,
-        mut attention_mask: Option<Tensor>,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
         generate_options: Option<GenerateOptions>,
-    ) -> Vec<GeneratedIndicesOutput> {
-        let eos_token_ids = PrivateLanguageGenerator::get_eos_ids(self).cloned();
-
-        let config = PrivateLanguageGenerator::get_config(self);
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError> {
+        Ok(self
+            .generate_from_ids_and_past_with_cache(input_ids, attention_mask, generate_options)?
+            .0)
+    }
 
-        // Set generation options. Priority goes to options provided to the `generate` method, then
-        // model configuration, then default values.
-        let do_sample = unpack_config!(do_sample, generate_options, config);
-        let num_return_sequences = unpack_config!(num_return_sequences, generate_options, config);
-        let num_beams = unpack_config!(num_beams, generate_options, config);
-        let min_length = unpack_config!(min_length, generate_options, config);
-        let early_stopping = unpack_config!(early_stopping, generate_options, config);
-        let temperature = unpack_config!(temperature, generate_options, config);
-        let top_k = unpack_config!(top_k, generate_options, config);
-        let top_p = unpack_config!(top_p, generate_options, config);
-        let repetition_penalty = unpack_config!(repetition_penalty, generate_options, config);
-        let length_penalty = unpack_config!(length_penalty, generate_options, config);
-        let no_repeat_ngram_size = unpack_config!(no_repeat_ngram_size, generate_options, config);
-        let num_beam_groups = generate_options.map_or(config.num_beam_groups, |opts| {
-            opts.num_beam_groups.or(config.num_beam_groups)
-        });
-        let diversity_penalty = generate_options.map_or(config.diversity_penalty, |opts| {
-            opts.diversity_penalty.or(config.diversity_penalty)
-        });
-        let decoder_start_token_id = generate_options.and_then(|opts| opts.decoder_start_token_id);
-        let forced_bos_token_id = generate_options.and_then(|opts| opts.forced_bos_token_id);
-        let bad_word_ids = generate_options.and_then(|opts| opts.bad_word_ids);
-        let prefix_allowed_tokens_fn =
-            generate_options.and_then(|opts| opts.prefix_allowed_tokens_fn);
-        let output_scores = generate_options.map_or(false, |opts| opts.output_scores);
-
-        let pad_token_id = match self.get_pad_id() {
-            Some(value) => Some(value),
-            None => eos_token_ids.as_ref().map(|eos_ids| eos_ids[0]),
-        };
+    /// Generate token indices given a list of indices, also returning the cache of the decoder at the end of the
+    /// generation (e.g. for inspection, or to continue the generation with the `past` generate option).
+    /// The cache covers all positions of the generated sequences but the last one, including the padding of the sequences
+    /// finished before the others. It is not available for beam search and generations with a key/value cache window,
+    /// `Cache::None` being returned instead. See `generate_resumable` for a higher-level interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - `Tensor` pre-tokenized and encoded input for generation.
+    /// * `attention_mask` - `Option<Tensor>` Optional mask of the input, with a value of 0 for masked (padding) positions (default: positions not equal to the padding token)
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<(Vec<IndicesOutput>, Cache), RustBertError>` Generated indices and scores (see `generate_from_ids_and_past`) and cache of the decoder
+    fn generate_from_ids_and_past_with_cache(
+        &self,
+        input_ids: Tensor,
+        attention_mask: Option<Tensor>,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<(Vec<GeneratedIndicesOutput>, Cache), RustBertError> {
+        let (output, past, _) = self.generate_from_ids_and_past_with_beam_search_state(
+            GenerationStart::Prompts {
+                input_ids,
+                attention_mask,
+            },
+            generate_options,
+            None,
+            None,
+        )?;
+        Ok((output, past))
+    }
 
-        let input_id_size = input_ids.size();
-        let mut input_ids_len = *input_id_size.last().unwrap();
-        if input_ids_len == 0 {
-            input_ids = Tensor::ones(
-                &[*input_id_size.first().unwrap(), 1],
-                (Int64, input_ids.device()),
-            ) * self
-                .get_bos_id()
-                .expect("`bos_token_id` has to be defined when no `input_ids` are provided.");
-            attention_mask = Some(Tensor::ones(
-                &[*input_id_size.first().unwrap(), 1],
-                (Int64, input_ids.device()),
+    /// Encode a prompt shared by several generations (e.g. a system prompt) and store its key/value cache, to start
+    /// generations from the cached state with `generate_from_prompt_cache` without encoding the prompt again.
+    /// Only supported by decoder-only models with GPT2 caches.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_text` - `&str` prompt to encode, must not be empty
+    ///
+    /// # Returns
+    /// * `Result<PromptCache, RustBertError>` Cached prompt, or an error if the model does not support prompt caching
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let prompt_cache = gpt2_generator.cache_prompt("You are a helpful assistant.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn cache_prompt(&self, prompt_text: &str) -> Result<PromptCache, RustBertError> {
+        if self.is_encoder_decoder() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Prompt caching is only supported by decoder-only models".into(),
+            ));
+        }
+        let tokenizer = self._get_tokenizer();
+        let token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(prompt_text));
+        if token_ids.is_empty() {
+            return Err(RustBertError::ValueError(
+                "The prompt to cache must not be empty".into(),
             ));
-            input_ids_len += 1;
         }
 
-        let cur_len = if !self.is_encoder_decoder() {
-            *input_ids.size().last().unwrap()
+        // The last prompt token is processed with the suffixes, its logits predicting the first generated token
+        let past = if token_ids.len() > 1 {
+            let input_ids = Tensor::of_slice(&token_ids[..token_ids.len() - 1])
+                .unsqueeze(0)
+                .to(self.get_var_store().device());
+            no_grad(|| {
+                self.get_model().forward_t(
+                    Some(&input_ids),
+                    Cache::None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+            })?
+            .cache
         } else {
-            1
+            Cache::None
         };
-        let batch_size = *input_ids.size().first().unwrap();
+        if !matches!(past, Cache::GPT2Cache(_) | Cache::None) {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Prompt caching is only supported by GPT2 models".into(),
+            ));
+        }
+        Ok(PromptCache { token_ids, past })
+    }
 
-        let (effective_batch_size, effective_batch_mult) = match do_sample {
-            true => (batch_size * num_return_sequences, num_return_sequences),
-            false => (batch_size, 1),
-        };
+    /// Generate token indices continuing a cached prompt followed by each suffix. The suffixes are encoded starting from
+    /// the cache of the prompt, which is left unchanged and can be reused for further generations. The output is the same
+    /// as the generation from the full prompts (cached prompt followed by the suffix).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_cache` - `&PromptCache` cached prompt built with `cache_prompt`
+    /// * `suffixes` - `&[S]` texts appended to the cached prompt, one sequence being generated for each suffix
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<GeneratedIndicesOutput>, RustBertError>` Vector of length *number_of_suffixes* x *num_return_sequences* containing the generated indices, including the prompt and suffix
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let prompt_cache = gpt2_generator.cache_prompt("You are a helpful assistant.")?;
+    /// let output = gpt2_generator.generate_from_prompt_cache(
+    ///     &prompt_cache,
+    ///     &[" What is the capital of France?", " Who wrote Hamlet?"],
+    ///     None,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_from_prompt_cache<S>(
+        &self,
+        prompt_cache: &PromptCache,
+        suffixes: &[S],
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if suffixes.is_empty() {
+            return Ok(vec![]);
+        }
+        let (input_ids, attention_mask, past) =
+            self.prepare_prompt_cache_input(prompt_cache, suffixes)?;
 
-        let attention_mask = match attention_mask {
-            Some(value) => value,
-            None => match pad_token_id {
-                Some(pad_id) => input_ids.ne(pad_id).to_kind(Int64),
-                None => input_ids.ones_like().to_kind(Int64),
-            },
+        let generate_options = GenerateOptions {
+            past: Some(&past),
+            ..generate_options.unwrap_or_default()
         };
+        self.generate_from_ids_and_past(input_ids, Some(attention_mask), Some(generate_options))
+    }
 
-        let encoder_outputs = if self.is_encoder_decoder() {
-            let encoder_outputs = self.encode(&input_ids, Some(&attention_mask)).unwrap();
-            let expanded_batch_indices = Tensor::arange(batch_size, (Int64, input_ids.device()))
-                .view((-1, 1))
-                .repeat(&[1, num_beams * effective_batch_mult])
-                .view(-1);
-            Some(encoder_outputs.index_select(0, &expanded_batch_indices))
-        } else {
-            None
-        };
+    /// Generate token indices continuing a cached prompt followed by a suffix, also returning the cache of each generated
+    /// sequence. The returned `PromptCache` can be passed back to `generate_resumable` or `generate_from_prompt_cache`
+    /// to resume the generation from any generated sequence, e.g. to continue a conversation with a new user turn
+    /// without encoding the previous turns again.
+    /// Only supported by greedy decoding and sampling, without key/value cache window.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_cache` - `&PromptCache` cached prompt built with `cache_prompt` or returned by a previous generation
+    /// * `suffix` - `&str` text appended to the cached prompt, may be empty
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<(GeneratedIndicesOutput, PromptCache)>, RustBertError>` Vector of length *num_return_sequences* containing the generated indices (including the prompt and suffix) and the cache of each sequence
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::LanguageGenerator;
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let prompt_cache = gpt2_generator.cache_prompt("User: Hello!")?;
+    /// let (first_turn, cache) = gpt2_generator
+    ///     .generate_resumable(&prompt_cache, " Bot:", None)?
+    ///     .remove(0);
+    /// let (second_turn, _) = gpt2_generator
+    ///     .generate_resumable(&cache, " User: How are you? Bot:", None)?
+    ///     .remove(0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_resumable(
+        &self,
+        prompt_cache: &PromptCache,
+        suffix: &str,
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<(GeneratedIndicesOutput, PromptCache)>, RustBertError> {
+        let config = PrivateLanguageGenerator::get_config(self);
+        if unpack_config!(num_beams, generate_options, config) > 1 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Resumable generation is not supported by beam search".into(),
+            ));
+        }
+        if generate_options
+            .map_or(config.kv_cache_window, |opts| {
+                opts.kv_cache_window.or(config.kv_cache_window)
+            })
+            .is_some()
+        {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Resumable generation is not supported with a key/value cache window".into(),
+            ));
+        }
 
-        let (input_ids, attention_mask) = if !self.is_encoder_decoder() {
-            if (num_return_sequences > 1) | (num_beams > 1) {
-                (
-                    input_ids
-                        .unsqueeze(1)
-                        .expand(
-                            &[batch_size, effective_batch_mult * num_beams, cur_len],
-                            true,
-                        )
-                        .contiguous()
-                        .view((effective_batch_size * num_beams, cur_len)),
-                    attention_mask
-                        .unsqueeze(1)
-                        .expand(
-                            &[batch_size, effective_batch_mult * num_beams, cur_len],
-                            true,
-                        )
-                        .contiguous()
-                        .view((effective_batch_size * num_beams, cur_len)),
-                )
-            } else {
-                (input_ids, attention_mask)
-            }
-        } else {
-            let decoder_start_token_id = decoder_start_token_id.unwrap_or_else(|| {
-                self.get_decoder_start_id()
-                    .expect("decoder start id must be specified for encoder decoders")
-            });
-            let input_ids = Tensor::full(
-                &[effective_batch_size * num_beams, 1],
-                decoder_start_token_id,
-                (Int64, input_ids.device()),
-            );
-            let attention_mask = if (num_return_sequences > 1) | (num_beams > 1) {
-                attention_mask
-                    .unsqueeze(1)
-                    .expand(
-                        &[batch_size, effective_batch_mult * num_beams, input_ids_len],
-                        true,
-                    )
-                    .contiguous()
-                    .view((effective_batch_size * num_beams, input_ids_len))
-            } else {
-                attention_mask
-            };
-            (input_ids, attention_mask)
+        let (input_ids, attention_mask, past) =
+            self.prepare_prompt_cache_input(prompt_cache, &[suffix])?;
+        let generate_options = GenerateOptions {
+            past: Some(&past),
+            ..generate_options.unwrap_or_default()
         };
+        let (output, generated_past) = self.generate_from_ids_and_past_with_cache(
+            input_ids,
+            Some(attention_mask),
+            Some(generate_options),
+        )?;
 
-        let max_length = if let Some(generate_options) = generate_options {
-            match (generate_options.max_length, generate_options.max_new_tokens) {
-                (Some(max_length), _) => Some(max_length),
-                (None, Some(max_new_tokens)) => {
-                    Some(max_new_tokens + input_ids.size().last().unwrap())
-                }
-                (None, None) => config.max_length,
-            }
-        } else {
-            config.max_length
-        };
+        // The cache of each sequence excludes its padding and last token
+        output
+            .into_iter()
+            .enumerate()
+            .map(|(sequence_index, generated_sequence)| {
+                let sequence_length = generated_sequence.usage.total_tokens;
+                let mut past =
+                    generated_past.select_batch(&Tensor::of_slice(&[sequence_index as i64]))?;
+                past.truncate_to_length(sequence_length as i64 - 1)?;
+                let prompt_cache = PromptCache {
+                    token_ids: generated_sequence.indices[..sequence_length].to_vec(),
+                    past,
+                };
+                Ok((generated_sequence, prompt_cache))
+            })
+            .collect()
+    }
 
-        if max_length.is_none() & eos_token_ids.is_none() {
-            panic!("No maximum length given for a model without an EOS token. \
-            This would lead to an infinite generation loop. Please provide a `max_length` or `max_new_tokens`")
+    /// Generate token indices with beam search, pausing the search after a number of generation steps (e.g. to
+    /// checkpoint a long generation). The returned state holds the beams, finished hypotheses and cache of the search
+    /// and is passed to `resume_beam_search` to continue it. A paused and resumed beam search generates the same
+    /// output as an uninterrupted search with the same generate options, unless sampling is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_texts` - `Option<&[&str]>` Optional vector of text prompts. If `None`, generation starts from the BOS token (the model must implement a `bos_id`).
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `max_steps` - `usize` number of generation steps before pausing the search
+    ///
+    /// # Returns
+    /// * `Result<BeamSearchProgress, RustBertError>` State of the paused search, or generated indices (see `generate_indices`)
+    /// if the search completed within `max_steps` steps. Fails if the generation does not use beam search.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{BeamSearchProgress, LanguageGenerator};
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let mut progress =
+    ///     gpt2_generator.generate_beam_search_steps(Some(&["The dog"]), None, 8)?;
+    /// let output = loop {
+    ///     match progress {
+    ///         BeamSearchProgress::Paused(state) => {
+    ///             progress = gpt2_generator.resume_beam_search(state, None, Some(8))?
+    ///         }
+    ///         BeamSearchProgress::Finished(output) => break output,
+    ///     }
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_beam_search_steps<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        generate_options: Option<GenerateOptions>,
+        max_steps: usize,
+    ) -> Result<BeamSearchProgress, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let config = PrivateLanguageGenerator::get_config(self);
+        if unpack_config!(num_beams, generate_options, config) <= 1 {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Pausing the generation is only supported by beam search".into(),
+            ));
         }
-
-        let gen_opt = InternalGenerateOptions {
-            min_length,
-            max_length,
-            do_sample,
-            temperature,
-            top_k,
-            top_p,
-            repetition_penalty,
-            no_repeat_ngram_size,
-            pad_token_id,
-            eos_token_ids,
-            num_return_sequences,
-            early_stopping,
-            num_beams,
-            length_penalty,
-            num_beam_groups,
-            diversity_penalty,
-            forced_bos_token_id,
-            bad_word_ids,
-        };
-
-        let generated_output_with_scores = no_grad(|| {
-            if num_beams > 1 {
-                self.generate_beam_search(
-                    input_ids,
-                    encoder_outputs,
-                    cur_len,
-                    effective_batch_size,
-                    attention_mask,
-                    gen_opt,
-                    prefix_allowed_tokens_fn,
-                    output_scores,
-                )
-            } else {
-                self.generate_no_beam_search(
+        if max_steps == 0 {
+            return Err(RustBertError::ValueError(
+                "The number of steps before pausing must be strictly greater than 0".into(),
+            ));
+        }
+        let (input_ids, attention_mask) =
+            match self.encode_generation_inputs(prompt_texts, generate_options) {
+                Some(inputs) => inputs,
+                None => return Ok(BeamSearchProgress::Finished(vec![])),
+            };
+        let (output, _, beam_search_state) = self
+            .generate_from_ids_and_past_with_beam_search_state(
+                GenerationStart::Prompts {
                     input_ids,
-                    encoder_outputs,
-                    cur_len,
-                    effective_batch_size,
                     attention_mask,
-                    gen_opt,
-                    prefix_allowed_tokens_fn,
-                    output_scores,
-                )
-            }
-        });
-        let (decoded, scores, mut token_scores) = (
-            generated_output_with_scores.indices,
-            generated_output_with_scores.scores,
-            generated_output_with_scores.token_scores,
-        );
-        let num_sequences = *decoded.size().first().unwrap();
-        let mut output = Vec::with_capacity(num_sequences as usize);
-        for sequence_index in 0..num_sequences {
-            let indices = decoded
-                .as_ref()
-                .get(sequence_index)
-                .iter::<i64>()
-                .unwrap()
-                .collect::<Vec<i64>>();
-            let score = scores
-                .as_ref()
-                .map(|scores_value| scores_value[sequence_index as usize]);
-
-            let token_scores = token_scores
-                .as_mut()
-                .map(|token_scores| std::mem::take(&mut token_scores[sequence_index as usize]));
+                },
+                generate_options,
+                Some(max_steps),
+                None,
+            )?;
+        Ok(match beam_search_state {
+            Some(beam_search_state) => BeamSearchProgress::Paused(beam_search_state),
+            None => BeamSearchProgress::Finished(output),
+        })
+    }
 
-            output.push(GeneratedIndicesOutput {
-                indices,
-                score,
-                token_scores,
-            });
+    /// Continue a beam search paused by `generate_beam_search_steps`, optionally pausing it again after a number of
+    /// generation steps. The generate options must be the same as the ones the search was started with.
+    ///
+    /// # Arguments
+    ///
+    /// * `beam_search_state` - `BeamSearchState` state of the paused search
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator
+    /// * `max_steps` - `Option<usize>` number of generation steps before pausing the search again (default: None, the search runs to completion)
+    ///
+    /// # Returns
+    /// * `Result<BeamSearchProgress, RustBertError>` State of the paused search, or generated indices (see `generate_indices`)
+    /// if the search completed. Fails if the number of beams or the output of scores or traces differs from the paused search.
+    fn resume_beam_search(
+        &self,
+        beam_search_state: BeamSearchState,
+        generate_options: Option<GenerateOptions>,
+        max_steps: Option<usize>,
+    ) -> Result<BeamSearchProgress, RustBertError> {
+        let config = PrivateLanguageGenerator::get_config(self);
+        let loop_state = &beam_search_state.loop_state;
+        if unpack_config!(num_beams, generate_options, config) != beam_search_state.num_beams
+            || generate_options.map_or(false, |opts| opts.output_scores)
+                != loop_state.saved_beam_scores.is_some()
+            || generate_options.map_or(false, |opts| opts.trace)
+                != loop_state.trace_output.is_some()
+        {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The number of beams and the output of scores and traces must match the paused beam search"
+                    .into(),
+            ));
         }
-        output
+        if max_steps == Some(0) {
+            return Err(RustBertError::ValueError(
+                "The number of steps before pausing must be strictly greater than 0".into(),
+            ));
+        }
+        let (output, _, beam_search_state) = self
+            .generate_from_ids_and_past_with_beam_search_state(
+                GenerationStart::PausedBeamSearch(beam_search_state),
+                generate_options,
+                max_steps,
+                None,
+            )?;
+        Ok(match beam_search_state {
+            Some(beam_search_state) => BeamSearchProgress::Paused(beam_search_state),
+            None => BeamSearchProgress::Finished(output),
+        })
+    }
+
+    /// Generate a single sequence with a callback invoked after every generated token, allowing to inspect the
+    /// generation and to inject tokens (e.g. the results of tool calls) before continuing it.
+    /// Injected tokens are appended to the sequence and processed by the model, updating its key/value cache, before the
+    /// next token is generated. They are not subject to the end of sequence tokens, are not scored and are counted as
+    /// completion tokens.
+    ///
+    /// The generation uses greedy decoding or sampling with all the other generate options (penalties, logits
+    /// processing, stop conditions), a single beam and a single returned sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt_text` - `&str` prompt to continue, an empty prompt starts the generation from the BOS token.
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options. If not (or partially) provided, will use the settings provided when creating the generator. `num_beams` and `num_return_sequences` are set to 1.
+    /// * `callback` - `&mut dyn FnMut(&[i64]) -> GenerationControl` called with the completion token ids (generated and injected) after every generated token
+    ///
+    /// # Returns
+    /// * `Result<GeneratedIndicesOutput, RustBertError>` containing the generated sequence (including the prompt for decoder-only models),
+    /// or an error if the generate options are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::GPT2Generator;
+    /// use rust_bert::pipelines::generation_utils::{GenerationControl, LanguageGenerator};
+    ///
+    /// let gpt2_generator = GPT2Generator::new(Default::default())?;
+    /// let tool_result = gpt2_generator
+    ///     .get_tokenizer()
+    ///     .convert_tokens_to_ids(&gpt2_generator.get_tokenizer().tokenize(" 42"));
+    /// let mut injected = false;
+    /// let output = gpt2_generator.generate_with_callback("The answer is", None, &mut |completion| {
+    ///     if !injected && completion.len() == 1 {
+    ///         injected = true;
+    ///         GenerationControl::InjectTokens(tool_result.clone())
+    ///     } else {
+    ///         GenerationControl::Continue
+    ///     }
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn generate_with_callback(
+        &self,
+        prompt_text: &str,
+        generate_options: Option<GenerateOptions>,
+        callback: &mut dyn FnMut(&[i64]) -> GenerationControl,
+    ) -> Result<GeneratedIndicesOutput, RustBertError> {
+        let generate_options = GenerateOptions {
+            num_beams: Some(1),
+            num_return_sequences: Some(1),
+            ..generate_options.unwrap_or_default()
+        };
+        let prompt_texts = [prompt_text];
+        let prompt_texts = if prompt_text.is_empty() {
+            None
+        } else {
+            Some(prompt_texts.as_slice())
+        };
+        let (input_ids, attention_mask) = self
+            .encode_generation_inputs(prompt_texts, Some(generate_options))
+            .expect("A single prompt is encoded");
+        let (mut output, _, _) = self.generate_from_ids_and_past_with_beam_search_state(
+            GenerationStart::Prompts {
+                input_ids,
+                attention_mask,
+            },
+            Some(generate_options),
+            None,
+            Some(callback),
+        )?;
+        Ok(output.remove(0))
     }
 
     /// Returns a reference to the text generator's tokenizer
@@ -2143,15 +6242,30 @@ pub trait LanguageGenerator<T: LMHeadModel, V: Vocab, U: Tokenizer<V>>:
     fn set_device(&mut self, device: Device) {
         self.get_var_store_mut().set_device(device);
     }
+
+    /// Unloads the generator, releasing the memory held by its weights.
+    ///
+    /// The generator is dropped and its device synchronized, so that the memory held by its tensors
+    /// is freed by the time this method returns. On CUDA devices the memory cached by the Torch
+    /// allocator is then returned to the device (see `empty_cuda_cache`).
+    fn unload(self)
+    where
+        Self: Sized,
+    {
+        let device = self.get_var_store().device();
+        drop(self);
+        release_device_memory(device);
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct BeamHypotheses {
     max_length: Option<i64>,
     length_penalty: f64,
     early_stopping: bool,
     num_beams: i64,
-    beams: Vec<(f64, Tensor, Option<Tensor>)>,
+    #[serde(with = "crate::common::tensor_serde")]
+    beams: Vec<(f64, Tensor, Option<Tensor>, FinishReason)>,
     worst_score: f64,
 }
 
@@ -2165,16 +6279,17 @@ impl Clone for BeamHypotheses {
             beams: self
                 .beams
                 .iter()
-                .map(|(score, tensor, scores_tensor)| {
+                .map(|(score, tensor, scores_tensor, finish_reason)| {
                     (
                         *score,
                         tensor.copy(),
                         scores_tensor
                             .as_ref()
                             .map(|scores_tensor| scores_tensor.copy()),
+                        *finish_reason,
                     )
                 })
-                .collect::<Vec<(f64, Tensor, Option<Tensor>)>>(),
+                .collect::<Vec<(f64, Tensor, Option<Tensor>, FinishReason)>>(),
             worst_score: self.worst_score,
         }
     }
@@ -2206,6 +6321,7 @@ impl BeamHypotheses {
         hypothesis: Tensor,
         sum_log_probabilities: f64,
         token_scores: Option<Tensor>,
+        finish_reason: FinishReason,
     ) {
         let score =
             sum_log_probabilities / ((hypothesis.size()[0] as f64).powf(self.length_penalty));
@@ -2221,20 +6337,21 @@ impl BeamHypotheses {
                     None,
                 )
             });
-            self.beams.push((score, hypothesis, token_scores));
+            self.beams
+                .push((score, hypothesis, token_scores, finish_reason));
             if self.len() > self.num_beams {
                 let (worst_score_position, _) = self
                     .beams
                     .iter()
                     .enumerate()
-                    .min_by_key(|(_, (score, _, _))| OrderedFloat(*score))
+                    .min_by_key(|(_, (score, _, _, _))| OrderedFloat(*score))
                     .unwrap();
                 let _ = self.beams.remove(worst_score_position);
             }
             self.worst_score = self
                 .beams
                 .iter()
-                .min_by_key(|(score, _, _)| OrderedFloat(*score))
+                .min_by_key(|(score, _, _, _)| OrderedFloat(*score))
                 .unwrap()
                 .0;
         }
@@ -0,0 +1,305 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Hybrid (extractive + abstractive) summarization pipeline
+//! Summarization of documents exceeding the maximum input length of the abstractive summarization models.
+//! The sentences of each document are first ranked by centrality (sum of the cosine similarities
+//! of their sentence embeddings with the embeddings of the other sentences of the document).
+//! The most central sentences fitting in the token budget of the summarization model are extracted,
+//! and passed in their original order to a `SummarizationModel` generating the abstractive summary.
+//! Documents fitting in the token budget are summarized directly.
+//! Sentences are split by a `PunctuationSentenceSplitter` by default, a custom `SentenceSplitter` can be
+//! provided in the `HybridSummarizationConfig`.
+//!
+//! By default, the sentences are ranked using a `AllMiniLmL6V2` sentence embeddings model and the
+//! summary is generated by a BART model finetuned on CNN/DM.
+//! Both models can be customized by overwriting the `sentence_embeddings_config` and `summarization_config`
+//! of the `HybridSummarizationConfig`.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use rust_bert::pipelines::hybrid_summarization::HybridSummarizationModel;
+//! let model = HybridSummarizationModel::new(Default::default())?;
+//!
+//! let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+//! from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+//! from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+//! a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+//! habitable zone — not too hot and not too cold for liquid water to exist."];
+//!
+//! let output = model.summarize(&input)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+#[cfg(feature = "remote")]
+use crate::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
+use crate::pipelines::sentence_embeddings::{SentenceEmbeddingsConfig, SentenceEmbeddingsModel};
+use crate::pipelines::sentence_splitter::{PunctuationSentenceSplitter, SentenceSplitter};
+use crate::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+use std::cmp::min;
+use tch::Kind;
+
+/// # Configuration for hybrid summarization
+/// Contains the configurations of the sentence embeddings model used to extract the most relevant
+/// sentences and of the summarization model generating the summary from these sentences.
+pub struct HybridSummarizationConfig {
+    /// `SentenceEmbeddingsConfig` defining the sentence embeddings model used to rank the sentences
+    pub sentence_embeddings_config: SentenceEmbeddingsConfig,
+    /// `SummarizationConfig` defining the summarization model generating the summary
+    pub summarization_config: SummarizationConfig,
+    /// Optional maximum number of sentences extracted from a document (default: None, only limited by the token budget)
+    pub num_sentences: Option<usize>,
+    /// Optional token budget for the extracted sentences, in tokens of the summarization model
+    /// (default: None, set to the maximum input length of the summarization model)
+    pub max_input_tokens: Option<usize>,
+    /// Optional `SentenceSplitter` splitting the documents into sentences (default: None, using a `PunctuationSentenceSplitter` for English)
+    pub sentence_splitter: Option<Box<dyn SentenceSplitter>>,
+}
+
+#[cfg(feature = "remote")]
+impl Default for HybridSummarizationConfig {
+    fn default() -> Self {
+        Self {
+            sentence_embeddings_config: SentenceEmbeddingsConfig::from(
+                SentenceEmbeddingsModelType::AllMiniLmL6V2,
+            ),
+            summarization_config: SummarizationConfig::default(),
+            num_sentences: None,
+            max_input_tokens: None,
+            sentence_splitter: None,
+        }
+    }
+}
+
+/// # HybridSummarizationModel to summarize long documents
+/// It contains a sentence embeddings model extracting the most relevant sentences of a document
+/// and a summarization model generating a summary from the extracted sentences.
+pub struct HybridSummarizationModel {
+    sentence_embeddings_model: SentenceEmbeddingsModel,
+    summarization_model: SummarizationModel,
+    sentence_splitter: Box<dyn SentenceSplitter>,
+    num_sentences: Option<usize>,
+    max_input_tokens: usize,
+}
+
+impl HybridSummarizationModel {
+    /// Build a new `HybridSummarizationModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `HybridSummarizationConfig` object containing the sentence embeddings and summarization configurations
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::hybrid_summarization::HybridSummarizationModel;
+    ///
+    /// let model = HybridSummarizationModel::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        config: HybridSummarizationConfig,
+    ) -> Result<HybridSummarizationModel, RustBertError> {
+        let sentence_embeddings_model =
+            SentenceEmbeddingsModel::new(config.sentence_embeddings_config)?;
+        let summarization_model = SummarizationModel::new(config.summarization_config)?;
+
+        let model_max_input_tokens = summarization_model.max_input_tokens();
+        let max_input_tokens = match config.max_input_tokens {
+            Some(max_input_tokens) if max_input_tokens > model_max_input_tokens => {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "The token budget ({}) exceeds the maximum input length of the summarization model ({})",
+                    max_input_tokens, model_max_input_tokens
+                )));
+            }
+            Some(max_input_tokens) => max_input_tokens,
+            None => model_max_input_tokens,
+        };
+        let sentence_splitter = config
+            .sentence_splitter
+            .unwrap_or_else(|| Box::new(PunctuationSentenceSplitter::default()));
+
+        Ok(HybridSummarizationModel {
+            sentence_embeddings_model,
+            summarization_model,
+            sentence_splitter,
+            num_sentences: config.num_sentences,
+            max_input_tokens,
+        })
+    }
+
+    /// Unloads the sentence embeddings and summarization models, releasing the memory held by their weights.
+    pub fn unload(self) {
+        self.sentence_embeddings_model.unload();
+        self.summarization_model.unload();
+    }
+
+    /// Extract the most relevant sentences of the documents provided, fitting in the token budget
+    /// of the summarization model.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of documents to extract sentences from.
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Extracted sentences for each document, in their original order
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::hybrid_summarization::HybridSummarizationModel;
+    /// let model = HybridSummarizationModel::new(Default::default())?;
+    ///
+    /// let input = ["This is a first sentence. This is a second sentence."];
+    ///
+    /// let output = model.extract(&input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract<S>(&self, texts: &[S]) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        texts
+            .iter()
+            .map(|text| self.extract_document(text.as_ref()))
+            .collect()
+    }
+
+    /// Summarize the documents provided, extracting their most relevant sentences first if they
+    /// exceed the token budget of the summarization model.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - `&[&str]` Array of documents to summarize.
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Summarized texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::hybrid_summarization::HybridSummarizationModel;
+    /// let model = HybridSummarizationModel::new(Default::default())?;
+    ///
+    /// let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+    /// from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+    /// from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+    /// a planet circling a star in the constellation Leo."];
+    ///
+    /// let output = model.summarize(&input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summarize<S>(&self, texts: &[S]) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let extracted_texts = self.extract(texts)?;
+        self.summarization_model.summarize(&extracted_texts)
+    }
+
+    fn extract_document(&self, text: &str) -> Result<String, RustBertError> {
+        let tokenizer = self.summarization_model.get_tokenizer();
+        let sentences = self.sentence_splitter.split(text);
+        let sentence_lengths = sentences
+            .iter()
+            .map(|sentence| tokenizer.tokenize(sentence).len())
+            .collect::<Vec<usize>>();
+        let max_sentences = self.num_sentences.map_or(sentences.len(), |num_sentences| {
+            min(num_sentences, sentences.len())
+        });
+
+        if max_sentences == sentences.len()
+            && sentence_lengths.iter().sum::<usize>() <= self.max_input_tokens
+        {
+            return Ok(text.to_string());
+        }
+
+        let ranked_indices = self.rank_sentences(&sentences)?;
+        let mut selected_indices = Vec::with_capacity(max_sentences);
+        let mut input_tokens = 0;
+        for &sentence_index in ranked_indices.iter() {
+            if selected_indices.len() == max_sentences {
+                break;
+            }
+            let sentence_length = sentence_lengths[sentence_index];
+            if input_tokens + sentence_length <= self.max_input_tokens {
+                selected_indices.push(sentence_index);
+                input_tokens += sentence_length;
+            }
+        }
+        // If no sentence fits in the token budget, the most central sentence is truncated
+        if selected_indices.is_empty() && !ranked_indices.is_empty() {
+            return Ok(self.truncate_sentence(sentences[ranked_indices[0]]));
+        }
+        selected_indices.sort_unstable();
+        Ok(selected_indices
+            .into_iter()
+            .map(|index| sentences[index])
+            .collect::<Vec<&str>>()
+            .join(" "))
+    }
+
+    /// Truncates a sentence to the token budget of the summarization model.
+    fn truncate_sentence(&self, sentence: &str) -> String {
+        let tokenized_sentence = self
+            .summarization_model
+            .get_tokenizer()
+            .tokenize_with_offsets(sentence);
+        let end = tokenized_sentence
+            .offsets
+            .iter()
+            .take(self.max_input_tokens)
+            .flatten()
+            .last()
+            .map_or(0, |offset| offset.end as usize);
+        sentence.chars().take(end).collect()
+    }
+
+    /// Returns the sentence indices sorted by decreasing centrality.
+    fn rank_sentences(&self, sentences: &[&str]) -> Result<Vec<usize>, RustBertError> {
+        let embeddings = self
+            .sentence_embeddings_model
+            .encode_as_tensor(sentences)?
+            .embeddings;
+        let embeddings = &embeddings
+            / embeddings
+                .norm_scalaropt_dim(2, &[1], true)
+                .clamp_min(1e-12);
+        let centrality = embeddings
+            .matmul(&embeddings.transpose(0, 1))
+            .sum_dim_intlist([1].as_slice(), false, Kind::Float);
+        let ranked_indices = centrality.argsort(0, true);
+        Ok(ranked_indices
+            .iter::<i64>()?
+            .map(|index| index as usize)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
+    fn test() {
+        let config = HybridSummarizationConfig::default();
+        let _: Box<dyn Send + Sync> = Box::new(HybridSummarizationModel::new(config));
+    }
+}
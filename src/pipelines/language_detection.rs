@@ -0,0 +1,238 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Language detection pipeline
+//! Detects the language of texts, e.g. to select the source language of a translation. The languages
+//! are returned with their scores, ranked by decreasing score.
+//!
+//! Two detectors are available, both implementing the `LanguageDetector` trait:
+//! - `LanguageDetectionModel`, a sequence classification model fine-tuned for language identification,
+//! loaded from resources. The labels of the model (`id2label` of the configuration) must be ISO 639-1 language codes (e.g. "en", "fr").
+//! - `ZeroShotLanguageDetector`, relying on a zero-shot classification model and a set of candidate languages,
+//! usable without a dedicated model.
+//!
+//! Custom detectors can be provided by implementing the `LanguageDetector` trait.
+//!
+//! ```no_run
+//! use rust_bert::pipelines::language_detection::{LanguageDetector, ZeroShotLanguageDetector};
+//! use rust_bert::pipelines::translation::Language;
+//! use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let language_detector = ZeroShotLanguageDetector::new(
+//!     ZeroShotClassificationModel::new(Default::default())?,
+//!     vec![Language::English, Language::French, Language::German],
+//! );
+//! let input = ["The quick brown fox jumps over the lazy dog."];
+//! let output = language_detector.detect_languages(&input)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Output: \
+//! ```no_run
+//! # use rust_bert::pipelines::translation::Language;
+//! # let output =
+//! [[
+//!     (Language::English, 0.98),
+//!     (Language::German, 0.02),
+//!     (Language::French, 0.01),
+//! ]]
+//! # ;
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
+use crate::pipelines::translation::Language;
+use crate::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+
+/// # Language detection
+/// Shared interface of the language detectors. Implementations must return, for each input text,
+/// the candidate languages and their scores ranked by decreasing score.
+pub trait LanguageDetector {
+    /// Detects the languages of texts
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts
+    ///
+    /// # Returns
+    /// * `Vec<Vec<(Language, f32)>>` Candidate languages and their scores for each input text, ranked by decreasing score
+    fn detect_languages(&self, input: &[&str]) -> Result<Vec<Vec<(Language, f32)>>, RustBertError>;
+}
+
+/// Configuration for the language detection model
+pub type LanguageDetectionConfig = SequenceClassificationConfig;
+
+/// # LanguageDetectionModel to detect the language of texts
+/// Sequence classification model fine-tuned for language identification, with ISO 639-1 language codes as labels.
+pub struct LanguageDetectionModel {
+    sequence_classification_model: SequenceClassificationModel,
+}
+
+impl LanguageDetectionModel {
+    /// Build a new `LanguageDetectionModel`
+    ///
+    /// # Arguments
+    ///
+    /// * `language_detection_config` - `LanguageDetectionConfig` object containing the resource references (model, vocabulary, configuration) and device placement (CPU/GPU)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::language_detection::{
+    ///     LanguageDetectionConfig, LanguageDetectionModel,
+    /// };
+    /// use rust_bert::resources::LocalResource;
+    /// use std::path::PathBuf;
+    ///
+    /// let config = LanguageDetectionConfig::new(
+    ///     ModelType::XLMRoberta,
+    ///     LocalResource::from(PathBuf::from("path/to/rust_model.ot")),
+    ///     LocalResource::from(PathBuf::from("path/to/config.json")),
+    ///     LocalResource::from(PathBuf::from("path/to/sentencepiece.bpe.model")),
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// );
+    /// let language_detection_model = LanguageDetectionModel::new(config)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        language_detection_config: LanguageDetectionConfig,
+    ) -> Result<LanguageDetectionModel, RustBertError> {
+        let sequence_classification_model =
+            SequenceClassificationModel::new(language_detection_config)?;
+        Ok(LanguageDetectionModel {
+            sequence_classification_model,
+        })
+    }
+}
+
+impl LanguageDetector for LanguageDetectionModel {
+    /// Detects the languages of texts. Labels of the model that are not valid language codes are ignored.
+    fn detect_languages(&self, input: &[&str]) -> Result<Vec<Vec<(Language, f32)>>, RustBertError> {
+        Ok(self
+            .sequence_classification_model
+            .predict_label_scores(input)?
+            .into_iter()
+            .map(|labels| {
+                labels
+                    .into_iter()
+                    .filter_map(|label| {
+                        Language::from_iso_639_1_code(&label.text)
+                            .map(|language| (language, label.score as f32))
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// # ZeroShotLanguageDetector to detect the language of texts
+/// Zero-shot classification of texts among a set of candidate languages, using the hypothesis
+/// "This text is written in {language}.". The scores of the candidate languages are independent.
+pub struct ZeroShotLanguageDetector {
+    zero_shot_classification_model: ZeroShotClassificationModel,
+    candidate_languages: Vec<Language>,
+    max_length: usize,
+}
+
+impl ZeroShotLanguageDetector {
+    /// Build a new `ZeroShotLanguageDetector`
+    ///
+    /// # Arguments
+    ///
+    /// * `zero_shot_classification_model` - `ZeroShotClassificationModel` used for the classification
+    /// * `candidate_languages` - `Vec<Language>` languages the texts are classified into
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::language_detection::ZeroShotLanguageDetector;
+    /// use rust_bert::pipelines::translation::Language;
+    /// use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+    ///
+    /// let language_detector = ZeroShotLanguageDetector::new(
+    ///     ZeroShotClassificationModel::new(Default::default())?,
+    ///     vec![Language::English, Language::French],
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        zero_shot_classification_model: ZeroShotClassificationModel,
+        candidate_languages: Vec<Language>,
+    ) -> ZeroShotLanguageDetector {
+        ZeroShotLanguageDetector {
+            zero_shot_classification_model,
+            candidate_languages,
+            max_length: 128,
+        }
+    }
+}
+
+impl LanguageDetector for ZeroShotLanguageDetector {
+    fn detect_languages(&self, input: &[&str]) -> Result<Vec<Vec<(Language, f32)>>, RustBertError> {
+        let language_names = self
+            .candidate_languages
+            .iter()
+            .map(|language| language.to_string())
+            .collect::<Vec<String>>();
+        let labels = language_names
+            .iter()
+            .map(|language_name| language_name.as_str())
+            .collect::<Vec<&str>>();
+        let output = self.zero_shot_classification_model.predict_multilabel(
+            input,
+            labels,
+            Some(Box::new(|language_name: &str| {
+                format!("This text is written in {}.", language_name)
+            })),
+            self.max_length,
+        )?;
+        Ok(output
+            .into_iter()
+            .map(|labels| {
+                let mut languages = labels
+                    .into_iter()
+                    .map(|label| {
+                        (
+                            self.candidate_languages[label.id as usize],
+                            label.score as f32,
+                        )
+                    })
+                    .collect::<Vec<(Language, f32)>>();
+                languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                languages
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
+    fn test() {
+        let config = LanguageDetectionConfig::default();
+        let _: Box<dyn Send + Sync> = Box::new(LanguageDetectionModel::new(config));
+    }
+}
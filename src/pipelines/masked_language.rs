@@ -50,7 +50,9 @@ use crate::common::error::RustBertError;
 use crate::deberta::DebertaForMaskedLM;
 use crate::deberta_v2::DebertaV2ForMaskedLM;
 use crate::fnet::FNetForMaskedLM;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    release_device_memory, set_num_threads, ConfigOption, ModelType, TokenizerOption,
+};
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForMaskedLM;
 #[cfg(feature = "remote")]
@@ -61,8 +63,9 @@ use crate::{
 use rust_tokenizers::tokenizer::TruncationStrategy;
 use rust_tokenizers::TokenizedInput;
 use std::borrow::Borrow;
+use std::sync::Arc;
 use tch::nn::VarStore;
-use tch::{nn, no_grad, Device, Tensor};
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[derive(Debug, Clone)]
 /// Output container for masked language model pipeline.
@@ -81,13 +84,13 @@ pub struct MaskedLanguageConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BERT model on CoNLL)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BERT model on CoNLL)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BERT model on CoNLL)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: None)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
     pub lower_case: bool,
     /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
@@ -98,6 +101,8 @@ pub struct MaskedLanguageConfig {
     pub mask_token: Option<String>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
 }
 
 impl MaskedLanguageConfig {
@@ -124,9 +129,9 @@ impl MaskedLanguageConfig {
         mask_token: impl Into<Option<String>>,
     ) -> MaskedLanguageConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         MaskedLanguageConfig {
             model_type,
@@ -139,6 +144,7 @@ impl MaskedLanguageConfig {
             add_prefix_space: add_prefix_space.into(),
             mask_token: mask_token.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
         }
     }
 }
@@ -354,9 +360,12 @@ impl MaskedLanguageOption {
     }
 }
 
+/// Number of masked variants of a text processed at once when computing its pseudo-log-likelihood
+const PSEUDO_LOG_LIKELIHOOD_BATCH_SIZE: usize = 32;
+
 /// # MaskedLanguageModel for Masked Language (e.g. Fill Mask)
 pub struct MaskedLanguageModel {
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     language_encode: MaskedLanguageOption,
     mask_token: Option<String>,
     var_store: VarStore,
@@ -381,15 +390,12 @@ impl MaskedLanguageModel {
     /// # }
     /// ```
     pub fn new(config: MaskedLanguageConfig) -> Result<MaskedLanguageModel, RustBertError> {
-        let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
             None
         };
-        let device = config.device;
 
         let tokenizer = TokenizerOption::from_file(
             config.model_type,
@@ -399,12 +405,51 @@ impl MaskedLanguageModel {
             config.strip_accents,
             config.add_prefix_space,
         )?;
+        Self::new_with_tokenizer(config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `MaskedLanguageModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `MaskedLanguageConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::masked_language::MaskedLanguageModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Bert,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let model = MaskedLanguageModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        config: MaskedLanguageConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<MaskedLanguageModel, RustBertError> {
+        set_num_threads(config.num_threads)?;
+        tokenizer.validate_model_type(config.model_type)?;
+        let config_path = config.config_resource.get_local_path()?;
+        let weights_path = config.model_resource.get_local_path()?;
+        let device = config.device;
+
         let mut var_store = VarStore::new(device);
         let model_config = ConfigOption::from_file(config.model_type, config_path);
-        let max_length = model_config
-            .get_max_len()
-            .map(|v| v as usize)
-            .unwrap_or(usize::MAX);
+        let max_length = model_config.get_max_sequence_length().unwrap_or(usize::MAX);
 
         let language_encode =
             MaskedLanguageOption::new(config.model_type, var_store.root(), &model_config)?;
@@ -419,6 +464,23 @@ impl MaskedLanguageModel {
         })
     }
 
+    /// Returns the maximum sequence length supported by the model (see `ConfigOption::get_max_sequence_length`),
+    /// or `None` if the model has no limit.
+    pub fn max_sequence_length(&self) -> Option<usize> {
+        if self.max_length == usize::MAX {
+            None
+        } else {
+            Some(self.max_length)
+        }
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
     /// Replace custom user-provided mask token by language model mask token.
     fn replace_mask_token<'a, S>(
         &self,
@@ -466,6 +528,83 @@ impl MaskedLanguageModel {
         Tensor::stack(tokenized_input_tensors.as_slice(), 0).to(self.var_store.device())
     }
 
+    /// Runs the model on the input texts, returning the logits and the mask of the masked token positions.
+    fn forward_masked<'a, S>(&self, input: S) -> Result<(Tensor, Tensor), RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let input_tensor = if let Some(mask_token) = &self.mask_token {
+            let input_with_replaced_mask = self.replace_mask_token(input.as_ref(), mask_token)?;
+            self.prepare_for_model(
+                input_with_replaced_mask
+                    .iter()
+                    .map(|w| w.as_str())
+                    .collect::<Vec<&str>>(),
+            )
+        } else {
+            self.prepare_for_model(input.as_ref())
+        };
+
+        let output = no_grad(|| {
+            self.language_encode.forward_t(
+                Some(&input_tensor),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+        });
+        // get the position of mask_token in input texts
+        let mask_token_id =
+            self.tokenizer
+                .get_mask_id()
+                .ok_or_else(|| RustBertError::InvalidConfigurationError(
+                    "Tokenizer does not have a mask token id, Please use a tokenizer/model with a mask token.".into(),
+                ))?;
+        let mask_token_mask = input_tensor.eq(mask_token_id);
+        Ok((output, mask_token_mask))
+    }
+
+    /// Runs the model on texts and returns its raw logits over the vocabulary for every token of the inputs (e.g. to
+    /// apply custom operations without running the model a second time). The mask token of the configuration, if any,
+    /// is replaced by the mask token of the tokenizer as for the predictions.
+    ///
+    /// This is an advanced API: the logits are returned on the device of the model (e.g. a GPU) and must be moved
+    /// with `Tensor::to` before being combined with tensors on another device or read on the CPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Tensor, RustBertError>` logits of shape (*batch size*, *sequence length*, *vocabulary size*), on the device of the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::masked_language::MaskedLanguageModel;
+    /// use tch::Device;
+    /// let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+    ///
+    /// let logits = mask_language_model
+    ///     .forward_logits(&["Looks like one [MASK] is missing"])?
+    ///     .to(Device::Cpu);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_logits<'a, S>(&self, input: S) -> Result<Tensor, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (output, _) = self.forward_masked(input.as_ref())?;
+        Ok(output)
+    }
+
     /// Mask texts
     ///
     /// # Arguments
@@ -502,38 +641,7 @@ impl MaskedLanguageModel {
     where
         S: AsRef<[&'a str]>,
     {
-        let input_tensor = if let Some(mask_token) = &self.mask_token {
-            let input_with_replaced_mask = self.replace_mask_token(input.as_ref(), mask_token)?;
-            self.prepare_for_model(
-                input_with_replaced_mask
-                    .iter()
-                    .map(|w| w.as_str())
-                    .collect::<Vec<&str>>(),
-            )
-        } else {
-            self.prepare_for_model(input.as_ref())
-        };
-
-        let output = no_grad(|| {
-            self.language_encode.forward_t(
-                Some(&input_tensor),
-                None,
-                None,
-                None,
-                None,
-                None,
-                None,
-                false,
-            )
-        });
-        // get the position of mask_token in input texts
-        let mask_token_id =
-            self.tokenizer
-                .get_mask_id()
-                .ok_or_else(|| RustBertError::InvalidConfigurationError(
-                    "Tokenizer does not have a mask token id, Please use a tokenizer/model with a mask token.".into(),
-                ))?;
-        let mask_token_mask = input_tensor.eq(mask_token_id);
+        let (output, mask_token_mask) = self.forward_masked(input.as_ref())?;
         let mut output_tokens = Vec::with_capacity(input.as_ref().len());
         for input_id in 0..input.as_ref().len() as i64 {
             let mut sequence_tokens = vec![];
@@ -552,15 +660,189 @@ impl MaskedLanguageModel {
         }
         Ok(output_tokens)
     }
+
+    /// Score candidate words for the masked positions of the input texts. Contrary to `predict`,
+    /// which returns the most likely token of the vocabulary, this returns the probability of each of
+    /// the candidate tokens provided at every masked position.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to mask.
+    /// * `candidate_ids` - `&[i64]` Token ids of the candidate words, scored at each masked position.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Vec<MaskedToken>>>` For each input text and each masked position, the candidate tokens
+    /// (in the order provided) with their probability as score
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::masked_language::MaskedLanguageModel;
+    /// //    Set-up model
+    /// let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+    ///
+    /// //    Define input
+    /// let input = ["It was a very nice and [MASK] day"];
+    /// let candidate_ids = [2204, 2919];
+    ///
+    /// //    Run model
+    /// let output = mask_language_model.score_candidates(&input, &candidate_ids)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn score_candidates<'a, S>(
+        &self,
+        input: S,
+        candidate_ids: &[i64],
+    ) -> Result<Vec<Vec<Vec<MaskedToken>>>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (output, mask_token_mask) = self.forward_masked(input.as_ref())?;
+        let candidate_index = Tensor::of_slice(candidate_ids).to(output.device());
+        let candidate_texts = candidate_ids
+            .iter()
+            .map(|id| self.tokenizer.decode(&[*id], false, true))
+            .collect::<Vec<String>>();
+
+        let mut output_tokens = Vec::with_capacity(input.as_ref().len());
+        for input_id in 0..input.as_ref().len() as i64 {
+            let mut sequence_tokens = vec![];
+            let sequence_mask = mask_token_mask.get(input_id);
+            if bool::from(sequence_mask.any()) {
+                let mask_probabilities = output
+                    .get(input_id)
+                    .index_select(0, &sequence_mask.argwhere().squeeze_dim(1))
+                    .softmax(-1, Kind::Float)
+                    .index_select(1, &candidate_index);
+                for mask_index in 0..mask_probabilities.size()[0] {
+                    let mask_tokens = candidate_ids
+                        .iter()
+                        .zip(candidate_texts.iter())
+                        .zip(mask_probabilities.get(mask_index).iter::<f64>()?)
+                        .map(|((id, text), score)| MaskedToken {
+                            text: text.clone(),
+                            id: *id,
+                            score,
+                        })
+                        .collect::<Vec<MaskedToken>>();
+                    sequence_tokens.push(mask_tokens);
+                }
+            }
+            output_tokens.push(sequence_tokens);
+        }
+        Ok(output_tokens)
+    }
+
+    /// Computes the pseudo-log-likelihood of a text, scoring its fluency with the masked language model: each token
+    /// is masked in turn, and the log-probabilities of the original tokens at their masked position are summed.
+    /// The masked variants of the text are processed by batches. Texts exceeding the maximum sequence length of the
+    /// model are truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to score
+    ///
+    /// # Returns
+    ///
+    /// * `f64` pseudo-log-likelihood of the text (0 for a text without tokens). Longer texts have lower scores.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::masked_language::MaskedLanguageModel;
+    /// //    Set-up model
+    /// let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+    ///
+    /// //    Run model
+    /// let score = mask_language_model.pseudo_log_likelihood("The cat sat on the mat.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pseudo_log_likelihood(&self, text: &str) -> Result<f64, RustBertError> {
+        let mask_token_id =
+            self.tokenizer
+                .get_mask_id()
+                .ok_or_else(|| RustBertError::InvalidConfigurationError(
+                    "Tokenizer does not have a mask token id, Please use a tokenizer/model with a mask token.".into(),
+                ))?;
+        let tokenized_input = self.tokenizer.encode_pair(
+            text,
+            None,
+            self.max_length,
+            &TruncationStrategy::LongestFirst,
+            0,
+        );
+        let token_ids = tokenized_input.token_ids;
+        let positions = tokenized_input
+            .special_tokens_mask
+            .iter()
+            .enumerate()
+            .filter(|(_, special_token)| **special_token == 0)
+            .map(|(position, _)| position)
+            .collect::<Vec<usize>>();
+
+        let device = self.var_store.device();
+        let mut pseudo_log_likelihood = 0.0;
+        for batch_positions in positions.chunks(PSEUDO_LOG_LIKELIHOOD_BATCH_SIZE) {
+            // Each variant of the batch masks one of the positions
+            let mut masked_token_ids = Vec::with_capacity(batch_positions.len() * token_ids.len());
+            let mut target_ids = Vec::with_capacity(batch_positions.len());
+            for &position in batch_positions {
+                let mut variant_token_ids = token_ids.clone();
+                variant_token_ids[position] = mask_token_id;
+                masked_token_ids.extend(variant_token_ids);
+                target_ids.push(token_ids[position]);
+            }
+            let batch_size = batch_positions.len() as i64;
+            let input_tensor = Tensor::of_slice(&masked_token_ids)
+                .view((batch_size, token_ids.len() as i64))
+                .to(device);
+            let position_index = Tensor::of_slice(
+                &batch_positions
+                    .iter()
+                    .map(|&position| position as i64)
+                    .collect::<Vec<i64>>(),
+            )
+            .to(device);
+            let target_index = Tensor::of_slice(&target_ids).to(device);
+
+            pseudo_log_likelihood += no_grad(|| {
+                let output = self.language_encode.forward_t(
+                    Some(&input_tensor),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                );
+                output
+                    .index(&[
+                        Some(Tensor::arange(batch_size, (Kind::Int64, device))),
+                        Some(position_index),
+                    ])
+                    .log_softmax(-1, Kind::Float)
+                    .gather(1, &target_index.unsqueeze(1), false)
+                    .sum(Kind::Double)
+                    .double_value(&[])
+            });
+        }
+        Ok(pseudo_log_likelihood)
+    }
 }
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = MaskedLanguageConfig::default();
-        let _: Box<dyn Send> = Box::new(MaskedLanguageModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(MaskedLanguageModel::new(config));
     }
 }
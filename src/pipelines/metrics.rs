@@ -0,0 +1,337 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Generation metrics
+//! BLEU and chrF scores of generated texts (e.g. translations) against one or more references, at the
+//! sentence and corpus level. Corpus-level scores aggregate the statistics of all sentences before
+//! computing the score, rather than averaging the sentence scores.
+//!
+//! BLEU operates on word n-grams (up to 4-grams, uniform weights, without smoothing) of the texts split
+//! on whitespace: texts can be pre-tokenized by joining their tokens with spaces (e.g. the output of
+//! `TokenizerOption::tokenize`). chrF operates on character n-grams (up to 6-grams, β = 2), ignoring whitespace.
+//! Both scores range from 0 to 1.
+//!
+//! ```no_run
+//! use rust_bert::pipelines::metrics::{corpus_bleu, sentence_chrf};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let hypotheses = ["the cat sat on the mat"];
+//! let references = [vec!["the cat is sitting on the mat", "a cat sat on the mat"]];
+//! let bleu = corpus_bleu(&hypotheses, &references)?;
+//! let chrf = sentence_chrf("the cat sat on the mat", &["the cat is sitting on the mat"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::RustBertError;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+const BLEU_MAX_ORDER: usize = 4;
+const CHRF_MAX_ORDER: usize = 6;
+const CHRF_BETA: f64 = 2.0;
+
+#[derive(Default)]
+struct BleuStatistics {
+    matches: [usize; BLEU_MAX_ORDER],
+    totals: [usize; BLEU_MAX_ORDER],
+    hypothesis_length: usize,
+    reference_length: usize,
+}
+
+impl BleuStatistics {
+    fn new<S: AsRef<str>>(hypothesis: &str, references: &[S]) -> BleuStatistics {
+        let hypothesis = hypothesis.split_whitespace().collect::<Vec<&str>>();
+        let references = references
+            .iter()
+            .map(|reference| reference.as_ref().split_whitespace().collect::<Vec<&str>>())
+            .collect::<Vec<Vec<&str>>>();
+
+        let mut statistics = BleuStatistics {
+            hypothesis_length: hypothesis.len(),
+            // Length of the closest reference, the shortest one in case of ties
+            reference_length: references
+                .iter()
+                .map(|reference| reference.len())
+                .min_by_key(|length| ((*length as i64 - hypothesis.len() as i64).abs(), *length))
+                .unwrap_or(0),
+            ..Default::default()
+        };
+        for order in 1..=BLEU_MAX_ORDER {
+            let reference_counts = references
+                .iter()
+                .map(|reference| ngram_counts(reference, order))
+                .collect::<Vec<HashMap<&[&str], usize>>>();
+            let (matches, total) =
+                clipped_matches(&ngram_counts(&hypothesis, order), &reference_counts);
+            statistics.matches[order - 1] = matches;
+            statistics.totals[order - 1] = total;
+        }
+        statistics
+    }
+
+    fn add(&mut self, other: &BleuStatistics) {
+        for order in 0..BLEU_MAX_ORDER {
+            self.matches[order] += other.matches[order];
+            self.totals[order] += other.totals[order];
+        }
+        self.hypothesis_length += other.hypothesis_length;
+        self.reference_length += other.reference_length;
+    }
+
+    fn score(&self) -> f64 {
+        if self.hypothesis_length == 0 || self.matches.iter().any(|matches| *matches == 0) {
+            return 0.0;
+        }
+        let log_precision = self
+            .matches
+            .iter()
+            .zip(self.totals.iter())
+            .map(|(matches, total)| (*matches as f64 / *total as f64).ln())
+            .sum::<f64>()
+            / BLEU_MAX_ORDER as f64;
+        let brevity_penalty = if self.hypothesis_length > self.reference_length {
+            1.0
+        } else {
+            (1.0 - self.reference_length as f64 / self.hypothesis_length as f64).exp()
+        };
+        brevity_penalty * log_precision.exp()
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct ChrfStatistics {
+    matches: [usize; CHRF_MAX_ORDER],
+    hypothesis_totals: [usize; CHRF_MAX_ORDER],
+    reference_totals: [usize; CHRF_MAX_ORDER],
+}
+
+impl ChrfStatistics {
+    fn new(hypothesis: &[char], reference: &str) -> ChrfStatistics {
+        let reference = reference
+            .chars()
+            .filter(|character| !character.is_whitespace())
+            .collect::<Vec<char>>();
+        let mut statistics = ChrfStatistics::default();
+        for order in 1..=CHRF_MAX_ORDER {
+            let (matches, hypothesis_total) = clipped_matches(
+                &ngram_counts(hypothesis, order),
+                &[ngram_counts(&reference, order)],
+            );
+            statistics.matches[order - 1] = matches;
+            statistics.hypothesis_totals[order - 1] = hypothesis_total;
+            statistics.reference_totals[order - 1] = reference.len().saturating_sub(order - 1);
+        }
+        statistics
+    }
+
+    fn add(&mut self, other: &ChrfStatistics) {
+        for order in 0..CHRF_MAX_ORDER {
+            self.matches[order] += other.matches[order];
+            self.hypothesis_totals[order] += other.hypothesis_totals[order];
+            self.reference_totals[order] += other.reference_totals[order];
+        }
+    }
+
+    fn score(&self) -> f64 {
+        // Precision and recall are averaged over the orders with n-grams in both texts
+        let mut precision = 0.0;
+        let mut recall = 0.0;
+        let mut effective_order = 0;
+        for order in 0..CHRF_MAX_ORDER {
+            if self.hypothesis_totals[order] > 0 && self.reference_totals[order] > 0 {
+                precision += self.matches[order] as f64 / self.hypothesis_totals[order] as f64;
+                recall += self.matches[order] as f64 / self.reference_totals[order] as f64;
+                effective_order += 1;
+            }
+        }
+        if effective_order == 0 || precision + recall == 0.0 {
+            return 0.0;
+        }
+        precision /= effective_order as f64;
+        recall /= effective_order as f64;
+        let beta_squared = CHRF_BETA * CHRF_BETA;
+        (1.0 + beta_squared) * precision * recall / (beta_squared * precision + recall)
+    }
+}
+
+fn ngram_counts<T: Eq + Hash>(tokens: &[T], order: usize) -> HashMap<&[T], usize> {
+    let mut counts = HashMap::new();
+    for ngram in tokens.windows(order) {
+        *counts.entry(ngram).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Returns the number of hypothesis n-grams matching the references, each n-gram being counted at most
+/// as many times as it appears in a single reference, and the total number of hypothesis n-grams.
+fn clipped_matches<T: Eq + Hash>(
+    hypothesis_counts: &HashMap<&[T], usize>,
+    reference_counts: &[HashMap<&[T], usize>],
+) -> (usize, usize) {
+    hypothesis_counts
+        .iter()
+        .fold((0, 0), |(matches, total), (ngram, count)| {
+            let max_reference_count = reference_counts
+                .iter()
+                .filter_map(|counts| counts.get(ngram))
+                .max()
+                .copied()
+                .unwrap_or(0);
+            (matches + (*count).min(max_reference_count), total + count)
+        })
+}
+
+fn best_chrf_statistics<S: AsRef<str>>(hypothesis: &str, references: &[S]) -> ChrfStatistics {
+    let hypothesis = hypothesis
+        .chars()
+        .filter(|character| !character.is_whitespace())
+        .collect::<Vec<char>>();
+    references
+        .iter()
+        .map(|reference| ChrfStatistics::new(&hypothesis, reference.as_ref()))
+        .max_by(|a, b| a.score().total_cmp(&b.score()))
+        .unwrap_or_default()
+}
+
+fn check_corpus_length(num_hypotheses: usize, num_references: usize) -> Result<(), RustBertError> {
+    if num_hypotheses != num_references {
+        return Err(RustBertError::ValueError(format!(
+            "Got {} hypotheses for {} sets of references",
+            num_hypotheses, num_references
+        )));
+    }
+    Ok(())
+}
+
+/// Computes the BLEU score of a hypothesis against its references
+///
+/// # Arguments
+///
+/// * `hypothesis` - `&str` generated text, split on whitespace
+/// * `references` - `&[S]` reference texts, split on whitespace
+///
+/// # Returns
+/// * `f64` BLEU score, between 0 and 1 (0 if the hypothesis shares no n-gram of an order with the references)
+pub fn sentence_bleu<S>(hypothesis: &str, references: &[S]) -> f64
+where
+    S: AsRef<str>,
+{
+    BleuStatistics::new(hypothesis, references).score()
+}
+
+/// Computes the corpus-level BLEU score of hypotheses against their references
+///
+/// # Arguments
+///
+/// * `hypotheses` - `&[H]` generated texts, split on whitespace
+/// * `references` - `&[R]` reference texts of each hypothesis, split on whitespace
+///
+/// # Returns
+/// * `Result<f64, RustBertError>` BLEU score, between 0 and 1. Fails if the number of hypotheses and sets of references differ.
+pub fn corpus_bleu<H, R, S>(hypotheses: &[H], references: &[R]) -> Result<f64, RustBertError>
+where
+    H: AsRef<str>,
+    R: AsRef<[S]>,
+    S: AsRef<str>,
+{
+    check_corpus_length(hypotheses.len(), references.len())?;
+    let mut statistics = BleuStatistics::default();
+    for (hypothesis, references) in hypotheses.iter().zip(references.iter()) {
+        statistics.add(&BleuStatistics::new(
+            hypothesis.as_ref(),
+            references.as_ref(),
+        ));
+    }
+    Ok(statistics.score())
+}
+
+/// Computes the chrF score of a hypothesis against its references, using the best matching reference
+///
+/// # Arguments
+///
+/// * `hypothesis` - `&str` generated text
+/// * `references` - `&[S]` reference texts
+///
+/// # Returns
+/// * `f64` chrF score, between 0 and 1
+pub fn sentence_chrf<S>(hypothesis: &str, references: &[S]) -> f64
+where
+    S: AsRef<str>,
+{
+    best_chrf_statistics(hypothesis, references).score()
+}
+
+/// Computes the corpus-level chrF score of hypotheses against their references, using the best matching
+/// reference of each hypothesis
+///
+/// # Arguments
+///
+/// * `hypotheses` - `&[H]` generated texts
+/// * `references` - `&[R]` reference texts of each hypothesis
+///
+/// # Returns
+/// * `Result<f64, RustBertError>` chrF score, between 0 and 1. Fails if the number of hypotheses and sets of references differ.
+pub fn corpus_chrf<H, R, S>(hypotheses: &[H], references: &[R]) -> Result<f64, RustBertError>
+where
+    H: AsRef<str>,
+    R: AsRef<[S]>,
+    S: AsRef<str>,
+{
+    check_corpus_length(hypotheses.len(), references.len())?;
+    let mut statistics = ChrfStatistics::default();
+    for (hypothesis, references) in hypotheses.iter().zip(references.iter()) {
+        statistics.add(&best_chrf_statistics(
+            hypothesis.as_ref(),
+            references.as_ref(),
+        ));
+    }
+    Ok(statistics.score())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bleu_chrf() -> Result<(), RustBertError> {
+        let hypotheses = [
+            "It is a guide to action which ensures that the military always obeys the commands of the party",
+            "he read the book because he was interested in world history",
+        ];
+        let references = [
+            vec![
+                "It is a guide to action that ensures that the military will forever heed Party commands",
+                "It is the guiding principle which guarantees the military forces always being under the command of the Party",
+                "It is the practical guide for the army always to heed the directions of the party",
+            ],
+            vec!["he was interested in world history because he read the book"],
+        ];
+
+        //    BLEU reference values computed with NLTK
+        assert!((sentence_bleu(hypotheses[0], &references[0]) - 0.504567).abs() < 1e-6);
+        assert!((corpus_bleu(&hypotheses, &references)? - 0.592078).abs() < 1e-6);
+        assert_eq!(sentence_bleu("the cat", &["the cat sat on the mat"]), 0.0);
+        assert!(corpus_bleu(&hypotheses, &references[..1]).is_err());
+
+        //    chrF
+        assert!((sentence_chrf(hypotheses[1], &[hypotheses[1]]) - 1.0).abs() < 1e-9);
+        assert_eq!(sentence_chrf("abc", &["xyz"]), 0.0);
+        let chrf = corpus_chrf(&hypotheses, &references)?;
+        assert!(chrf > 0.0 && chrf < 1.0);
+        assert!(
+            sentence_chrf(hypotheses[0], &references[0])
+                >= sentence_chrf(hypotheses[0], &references[0][2..])
+        );
+
+        Ok(())
+    }
+}
@@ -121,7 +121,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -154,7 +154,7 @@
 //!
 //! let conversation_id =
 //!     conversation_manager.create("Going to the movies tonight - any suggestions?");
-//! let output = conversation_model.generate_responses(&mut conversation_manager);
+//! let output = conversation_model.generate_responses(&mut conversation_manager)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -181,7 +181,7 @@
 //!
 //! let prefix = None; // Optional prefix to append prompts with, will be excluded from the generated output
 //!
-//! let output = model.generate(&[input_context_1, input_context_2], prefix);
+//! let output = model.generate(&[input_context_1, input_context_2], prefix)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -289,7 +289,7 @@
 //!     "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
 //!     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
 //! ];
-//! let output = sentiment_model.predict(&input);
+//! let output = sentiment_model.predict(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -473,16 +473,47 @@
 //! ]
 //! # ;
 //! ```
+//!
+//! #### Sharing a pipeline across threads
+//! The pipelines are `Send` and `Sync`: the state mutated during inference (e.g. the key/value
+//! cache of the generation models) is created for each call rather than stored in the model.
+//! A loaded pipeline can therefore be wrapped in an `Arc` and used concurrently from several threads.
+//!
+//! ```no_run
+//! use rust_bert::pipelines::sentiment::SentimentModel;
+//! use std::sync::Arc;
+//! use std::thread;
+//! # fn main() -> anyhow::Result<()> {
+//! let sentiment_model = Arc::new(SentimentModel::new(Default::default())?);
+//!
+//! let handles = (0..4)
+//!     .map(|_| {
+//!         let sentiment_model = Arc::clone(&sentiment_model);
+//!         thread::spawn(move || sentiment_model.predict(["Probably my all-time favorite movie."]))
+//!     })
+//!     .collect::<Vec<_>>();
+//! for handle in handles {
+//!     assert_eq!(handle.join().unwrap()?.len(), 1);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
 pub mod common;
 pub mod conversation;
+pub mod cross_encoder;
 pub mod generation_utils;
+pub mod hybrid_summarization;
 pub mod keywords_extraction;
+pub mod language_detection;
 pub mod masked_language;
+pub mod metrics;
 pub mod ner;
+pub mod pipeline_chain;
 pub mod pos_tagging;
 pub mod question_answering;
 pub mod sentence_embeddings;
+pub mod sentence_splitter;
 pub mod sentiment;
 pub mod sequence_classification;
 pub mod summarization;
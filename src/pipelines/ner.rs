@@ -127,11 +127,14 @@
 //! Dutch| XLM_ROBERTA_NER_NL |
 
 use crate::common::error::RustBertError;
+use crate::pipelines::sentence_splitter::SentenceSplitter;
 use crate::pipelines::token_classification::{
-    Token, TokenClassificationConfig, TokenClassificationModel,
+    Token, TokenClassificationConfig, TokenClassificationModel, TokenScores,
 };
-use rust_tokenizers::Offset;
+use rust_tokenizers::{Mask, Offset};
 use serde::{Deserialize, Serialize};
+use std::cmp::{min, Ordering};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// # Entity generated by a `NERModel`
@@ -152,6 +155,7 @@ type NERConfig = TokenClassificationConfig;
 /// # NERModel to extract named entities
 pub struct NERModel {
     token_classification_model: TokenClassificationModel,
+    max_entities: Option<usize>,
 }
 
 impl NERModel {
@@ -175,9 +179,33 @@ impl NERModel {
         let model = TokenClassificationModel::new(ner_config)?;
         Ok(NERModel {
             token_classification_model: model,
+            max_entities: None,
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `TokenClassificationModel::unload`).
+    pub fn unload(self) {
+        self.token_classification_model.unload();
+    }
+
+    /// Sets the temperature dividing the token logits before the softmax, calibrating the reported
+    /// entity scores (see `TokenClassificationModel::set_score_temperature`).
+    pub fn set_score_temperature(&mut self, score_temperature: f64) -> Result<(), RustBertError> {
+        self.token_classification_model
+            .set_score_temperature(score_temperature)
+    }
+
+    /// Sets the maximum number of entities returned for each input. The entities with the highest scores
+    /// are kept, in their order of appearance in the input. `None` (default) returns all entities.
+    pub fn set_max_entities(&mut self, max_entities: Option<usize>) {
+        self.max_entities = max_entities;
+    }
+
+    /// Returns the maximum sequence length supported by the model (see `TokenClassificationModel::max_sequence_length`).
+    pub fn max_sequence_length(&self) -> Option<usize> {
+        self.token_classification_model.max_sequence_length()
+    }
+
     /// Extract entities from a text
     ///
     /// # Arguments
@@ -204,6 +232,16 @@ impl NERModel {
     /// # }
     /// ```
     pub fn predict<S>(&self, input: &[S]) -> Vec<Vec<Entity>>
+    where
+        S: AsRef<str>,
+    {
+        self.extract_entities(input)
+            .into_iter()
+            .map(|entities| self.keep_top_entities(entities))
+            .collect::<Vec<Vec<Entity>>>()
+    }
+
+    fn extract_entities<S>(&self, input: &[S]) -> Vec<Vec<Entity>>
     where
         S: AsRef<str>,
     {
@@ -225,6 +263,130 @@ impl NERModel {
             .collect::<Vec<Vec<Entity>>>()
     }
 
+    /// Keeps the `max_entities` highest-scoring entities, preserving their order
+    fn keep_top_entities(&self, entities: Vec<Entity>) -> Vec<Entity> {
+        match self.max_entities {
+            Some(max_entities) if entities.len() > max_entities => {
+                let mut positions = (0..entities.len()).collect::<Vec<usize>>();
+                positions.sort_by(|&a, &b| {
+                    entities[b]
+                        .score
+                        .partial_cmp(&entities[a].score)
+                        .unwrap_or(Ordering::Equal)
+                });
+                let mut kept = vec![false; entities.len()];
+                for position in positions.into_iter().take(max_entities) {
+                    kept[position] = true;
+                }
+                entities
+                    .into_iter()
+                    .zip(kept)
+                    .filter(|(_, kept)| *kept)
+                    .map(|(entity, _)| entity)
+                    .collect()
+            }
+            _ => entities,
+        }
+    }
+
+    /// Returns the full label distribution of the (sub-)tokens of a text, e.g. to decode the entity
+    /// labels with a custom strategy (see `TokenClassificationModel::predict_scores`).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to extract the label distributions from.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<TokenScores>>` containing the tokens with the probabilities of all labels for each input provided
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::ner::NERModel;
+    ///
+    /// let ner_model = NERModel::new(Default::default())?;
+    /// let input = ["My name is Amy. I live in Paris."];
+    /// let output = ner_model.predict_scores(&input);
+    /// let label_mapping = ner_model.get_label_mapping();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_scores<S>(&self, input: &[S]) -> Vec<Vec<TokenScores>>
+    where
+        S: AsRef<str>,
+    {
+        self.token_classification_model.predict_scores(input, false)
+    }
+
+    /// Returns the mapping from label index to entity label of the model
+    pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
+        self.token_classification_model.get_label_mapping()
+    }
+
+    /// Extract entities from documents processed sentence by sentence, e.g. for documents exceeding
+    /// the maximum sequence length of the model. The entity offsets refer to the positions in the documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of documents to extract entities from.
+    /// * `sentence_splitter` - `&dyn SentenceSplitter` splitting the documents into sentences.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<Entity>>` containing extracted entities for each document
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::ner::NERModel;
+    /// use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+    ///
+    /// let ner_model = NERModel::new(Default::default())?;
+    /// let sentence_splitter = PunctuationSentenceSplitter::default();
+    /// let input = ["Dr. Amy Smith lives in Paris. She works in London."];
+    /// let output = ner_model.predict_sentences(&input, &sentence_splitter);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_sentences<S>(
+        &self,
+        input: &[S],
+        sentence_splitter: &dyn SentenceSplitter,
+    ) -> Vec<Vec<Entity>>
+    where
+        S: AsRef<str>,
+    {
+        let mut sentences = Vec::new();
+        let mut sentence_positions = Vec::new();
+        for (document_index, text) in input.iter().enumerate() {
+            let text = text.as_ref();
+            for span in sentence_splitter.sentence_spans(text) {
+                let char_offset = text[..span.start].chars().count() as u32;
+                sentences.push(&text[span]);
+                sentence_positions.push((document_index, char_offset));
+            }
+        }
+
+        let mut entities = input.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+        for ((document_index, char_offset), sentence_entities) in sentence_positions
+            .into_iter()
+            .zip(self.extract_entities(&sentences))
+        {
+            entities[document_index].extend(sentence_entities.into_iter().map(|mut entity| {
+                entity.offset.begin += char_offset;
+                entity.offset.end += char_offset;
+                entity
+            }));
+        }
+        entities
+            .into_iter()
+            .map(|document_entities| self.keep_top_entities(document_entities))
+            .collect()
+    }
+
     /// Extract full entities from a text performing entity chunking. Follows the algorithm for entities
     /// chunking described in [Erik F. Tjong Kim Sang, Jorn Veenstra, Representing Text Chunks](https://www.aclweb.org/anthology/E99-1023/)
     /// The proposed implementation is inspired by the [Python seqeval library](https://github.com/chakki-works/seqeval) (shared under MIT license).
@@ -279,7 +441,76 @@ impl NERModel {
         let mut entities: Vec<Vec<Entity>> = Vec::new();
 
         for sequence_tokens in tokens {
-            entities.push(Self::consolidate_entities(&sequence_tokens));
+            entities.push(self.keep_top_entities(Self::consolidate_entities(&sequence_tokens)));
+        }
+        entities
+    }
+
+    /// Extract full entities from a text, decoding the most likely sequence of word labels with a
+    /// Viterbi decoding enforcing valid BIO transitions (see `viterbi_decode`) instead of
+    /// classifying each word independently. The labels of the words are given by their first sub-token.
+    /// This is suited for models trained with the BIO (IOB2) or BIOES tagging schemes, where entities
+    /// do not start with an `I-` label.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to extract entities from.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Entity>` containing consolidated extracted entities
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::ner::NERModel;
+    ///
+    /// let ner_model = NERModel::new(Default::default())?;
+    /// let input = ["Asked John Smith about Acme Corp"];
+    /// let output = ner_model.predict_full_entities_viterbi(&input);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_full_entities_viterbi(&self, input: &[&str]) -> Vec<Vec<Entity>> {
+        let label_mapping = self.get_label_mapping();
+        let mut entities: Vec<Vec<Entity>> = Vec::new();
+
+        for sequence_tokens in self.predict_scores(input) {
+            let mut words: Vec<TokenScores> = Vec::new();
+            for token in sequence_tokens {
+                match words.last_mut() {
+                    Some(word) if token.mask == Mask::Continuation => {
+                        word.text.push_str(token.text.as_str());
+                        if let (Some(word_offset), Some(token_offset)) =
+                            (word.offset.as_mut(), token.offset)
+                        {
+                            word_offset.end = token_offset.end;
+                        }
+                    }
+                    _ => words.push(token),
+                }
+            }
+            let label_scores = words
+                .iter()
+                .map(|word| word.label_scores.clone())
+                .collect::<Vec<Vec<f64>>>();
+            let tokens = words
+                .into_iter()
+                .zip(viterbi_decode(&label_scores, label_mapping))
+                .map(|(word, label_index)| Token {
+                    text: word.text,
+                    score: word.label_scores[label_index as usize],
+                    label: label_mapping[&label_index].clone(),
+                    label_index,
+                    sentence: 0,
+                    index: word.index,
+                    word_index: word.word_index,
+                    offset: word.offset,
+                    mask: word.mask,
+                })
+                .collect::<Vec<Token>>();
+            entities.push(self.keep_top_entities(Self::consolidate_entities(&tokens)));
         }
         entities
     }
@@ -302,6 +533,183 @@ impl NERModel {
     }
 }
 
+/// Score added to the path score of the Viterbi decoding for illegal label transitions
+const ILLEGAL_TRANSITION_SCORE: f64 = -1e4;
+
+/// Returns `false` for transitions to an `I-` or `E-` label that do not continue an entity of the same type
+fn is_allowed_transition(previous_label: Option<&str>, label: &str) -> bool {
+    match label.split_once('-') {
+        Some(("I", entity_type)) | Some(("E", entity_type)) => {
+            match previous_label.and_then(|previous_label| previous_label.split_once('-')) {
+                Some(("B", previous_type)) | Some(("I", previous_type)) => {
+                    previous_type == entity_type
+                }
+                _ => false,
+            }
+        }
+        _ => true,
+    }
+}
+
+/// Decodes the most likely sequence of labels from per-token label probabilities with the Viterbi
+/// algorithm, penalizing illegal BIO transitions (an `I-X` or `E-X` label that does not follow a `B-X`
+/// or `I-X` label, e.g. `O` -> `I-PER`) with a fixed transition score.
+///
+/// # Arguments
+///
+/// * `label_scores` - `&[Vec<f64>]` probabilities of all labels for each token, indexed by label index (e.g. `TokenScores::label_scores`)
+/// * `label_mapping` - `&HashMap<i64, String>` mapping from label index to label
+///
+/// # Returns
+///
+/// * `Vec<i64>` label index for each token
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::ner::viterbi_decode;
+/// use std::collections::HashMap;
+///
+/// let label_mapping = HashMap::from([
+///     (0, "O".to_string()),
+///     (1, "B-PER".to_string()),
+///     (2, "I-PER".to_string()),
+/// ]);
+/// let label_scores = [vec![0.9, 0.05, 0.05], vec![0.1, 0.4, 0.5]];
+/// let labels = viterbi_decode(&label_scores, &label_mapping);
+/// ```
+pub fn viterbi_decode(label_scores: &[Vec<f64>], label_mapping: &HashMap<i64, String>) -> Vec<i64> {
+    let num_labels = match label_scores.first() {
+        Some(scores) => scores.len(),
+        None => return vec![],
+    };
+    let labels = (0..num_labels as i64)
+        .map(|label_index| label_mapping.get(&label_index).map(String::as_str))
+        .collect::<Vec<Option<&str>>>();
+    let transition_score = |previous_label: Option<usize>, label: usize| match labels[label] {
+        Some(label) if !is_allowed_transition(previous_label.and_then(|i| labels[i]), label) => {
+            ILLEGAL_TRANSITION_SCORE
+        }
+        _ => 0.0,
+    };
+
+    let mut path_scores = label_scores[0]
+        .iter()
+        .enumerate()
+        .map(|(label, score)| score.ln() + transition_score(None, label))
+        .collect::<Vec<f64>>();
+    let mut back_pointers: Vec<Vec<usize>> = Vec::with_capacity(label_scores.len());
+    for scores in label_scores[1..].iter() {
+        let mut step_scores = Vec::with_capacity(num_labels);
+        let mut step_pointers = Vec::with_capacity(num_labels);
+        for (label, score) in scores.iter().enumerate() {
+            let (best_previous, best_score) = path_scores
+                .iter()
+                .enumerate()
+                .map(|(previous, path_score)| {
+                    (
+                        previous,
+                        path_score + transition_score(Some(previous), label),
+                    )
+                })
+                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .unwrap();
+            step_scores.push(best_score + score.ln());
+            step_pointers.push(best_previous);
+        }
+        path_scores = step_scores;
+        back_pointers.push(step_pointers);
+    }
+
+    let (mut label, _) = path_scores
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .unwrap();
+    let mut decoded_labels = vec![label as i64];
+    for step_pointers in back_pointers.iter().rev() {
+        label = step_pointers[label];
+        decoded_labels.push(label as i64);
+    }
+    decoded_labels.reverse();
+    decoded_labels
+}
+
+/// Inserts markup around the entities of a text, wrapping each entity in tags named after its label
+/// (e.g. `<PER>Amy</PER>`). Entity offsets are character offsets in the text, as returned by the `NERModel`.
+/// Entities contained in another entity are nested in its markup. An entity overlapping the end of an
+/// enclosing entity is truncated at the end of the enclosing entity, keeping the markup well-formed.
+/// Empty entities and entities outside of the text are ignored.
+///
+/// # Arguments
+///
+/// * `text` - `&str` text the entities were extracted from
+/// * `entities` - `&[Entity]` entities to mark up
+///
+/// # Returns
+///
+/// * `String` text with the entities wrapped in markup
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::pipelines::ner::{annotate, Entity};
+/// use rust_tokenizers::Offset;
+///
+/// let entities = [Entity {
+///     word: String::from("Amy"),
+///     score: 0.99,
+///     label: String::from("PER"),
+///     offset: Offset { begin: 11, end: 14 },
+/// }];
+/// let annotated = annotate("My name is Amy.", &entities);
+/// assert_eq!(annotated, "My name is <PER>Amy</PER>.");
+/// ```
+pub fn annotate(text: &str, entities: &[Entity]) -> String {
+    let characters = text.chars().collect::<Vec<char>>();
+    let mut sorted_entities = entities
+        .iter()
+        .map(|entity| {
+            (
+                entity.offset.begin as usize,
+                min(entity.offset.end as usize, characters.len()),
+                entity.label.as_str(),
+            )
+        })
+        .filter(|(begin, end, _)| begin < end)
+        .collect::<Vec<(usize, usize, &str)>>();
+    // Enclosing entities are opened before the entities they contain
+    sorted_entities.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut annotated = String::with_capacity(text.len());
+    let mut open_entities: Vec<(usize, &str)> = Vec::new();
+    let mut next_entity = sorted_entities.iter().peekable();
+    for position in 0..=characters.len() {
+        while let Some((end, label)) = open_entities.last() {
+            if *end > position {
+                break;
+            }
+            annotated.push_str(&format!("</{}>", label));
+            open_entities.pop();
+        }
+        while let Some((begin, end, label)) =
+            next_entity.next_if(|(begin, _, _)| *begin == position)
+        {
+            let end = open_entities
+                .last()
+                .map_or(*end, |(enclosing_end, _)| min(*end, *enclosing_end));
+            if end > *begin {
+                annotated.push_str(&format!("<{}>", label));
+                open_entities.push((end, label));
+            }
+        }
+        if let Some(character) = characters.get(position) {
+            annotated.push(*character);
+        }
+    }
+    annotated
+}
+
 struct EntityBuilder<'a> {
     previous_node: Option<(usize, Tag, &'a str)>,
 }
@@ -411,9 +819,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = NERConfig::default();
-        let _: Box<dyn Send> = Box::new(NERModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(NERModel::new(config));
     }
 }
@@ -0,0 +1,184 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Pipeline composition
+//! Sequencing of pipelines into a processing graph, the outputs of each stage being fed as inputs to the next stage.
+//!
+//! Stages implement the `Pipeline` trait, defining their input and output types. The trait is implemented for the
+//! summarization and named entity recognition pipelines, and can be implemented for custom processing steps.
+//! Stages are combined with `Pipeline::then`, optionally processing the inputs by batches with `PipelineChain::with_batch_size`.
+//!
+//! ```no_run
+//! use rust_bert::pipelines::pipeline_chain::Pipeline;
+//! use rust_bert::pipelines::summarization::SummarizationModel;
+//! use rust_bert::RustBertError;
+//!
+//! struct Truncate(usize);
+//!
+//! impl Pipeline for Truncate {
+//!     type Input = String;
+//!     type Output = String;
+//!
+//!     fn process(&self, inputs: &[String]) -> Result<Vec<String>, RustBertError> {
+//!         Ok(inputs
+//!             .iter()
+//!             .map(|input| input.chars().take(self.0).collect())
+//!             .collect())
+//!     }
+//! }
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let pipeline = SummarizationModel::new(Default::default())?
+//!     .then(Truncate(64))
+//!     .with_batch_size(8);
+//! let input = [String::from("In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+//! from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+//! from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+//! a planet circling a star in the constellation Leo.")];
+//! let output = pipeline.process(&input)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::common::error::RustBertError;
+use crate::pipelines::ner::{Entity, NERModel};
+use crate::pipelines::summarization::SummarizationModel;
+
+/// # Pipeline stage
+/// Shared interface of the stages composing a processing graph. Implementations must return exactly
+/// one output per input, in the order of the inputs.
+pub trait Pipeline {
+    /// Type of the inputs processed by the stage
+    type Input;
+    /// Type of the outputs returned by the stage
+    type Output;
+
+    /// Processes a batch of inputs
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[Self::Input]` Array of inputs
+    ///
+    /// # Returns
+    /// * `Vec<Self::Output>` Output for each input
+    fn process(&self, inputs: &[Self::Input]) -> Result<Vec<Self::Output>, RustBertError>;
+
+    /// Sequences a stage after the current pipeline, the outputs of the current pipeline being fed as inputs of `next`
+    ///
+    /// # Arguments
+    ///
+    /// * `next` - `Pipeline` processing the outputs of the current pipeline
+    ///
+    /// # Returns
+    /// * `PipelineChain` combining both stages
+    fn then<P>(self, next: P) -> PipelineChain<Self, P>
+    where
+        Self: Sized,
+        P: Pipeline<Input = Self::Output>,
+    {
+        PipelineChain::new(self, next)
+    }
+}
+
+/// # PipelineChain sequencing two pipelines
+/// The inputs are processed by the first stage, its outputs being processed by the second stage.
+/// Chains implement `Pipeline` and can be further extended with `Pipeline::then`.
+pub struct PipelineChain<A, B> {
+    first: A,
+    second: B,
+    batch_size: Option<usize>,
+}
+
+impl<A, B> PipelineChain<A, B>
+where
+    A: Pipeline,
+    B: Pipeline<Input = A::Output>,
+{
+    /// Build a new `PipelineChain`
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - `Pipeline` processing the inputs
+    /// * `second` - `Pipeline` processing the outputs of `first`
+    pub fn new(first: A, second: B) -> PipelineChain<A, B> {
+        PipelineChain {
+            first,
+            second,
+            batch_size: None,
+        }
+    }
+
+    /// Set the number of inputs passed through the chain at once (default: all inputs at once)
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_size` - maximum number of inputs processed by each stage at once, must be strictly greater than 0
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl<A, B> Pipeline for PipelineChain<A, B>
+where
+    A: Pipeline,
+    B: Pipeline<Input = A::Output>,
+{
+    type Input = A::Input;
+    type Output = B::Output;
+
+    fn process(&self, inputs: &[Self::Input]) -> Result<Vec<Self::Output>, RustBertError> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let batch_size = match self.batch_size {
+            Some(0) => {
+                return Err(RustBertError::ValueError(
+                    "The batch size of a pipeline chain must be strictly greater than 0"
+                        .to_string(),
+                ));
+            }
+            Some(batch_size) => batch_size,
+            None => inputs.len(),
+        };
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(batch_size) {
+            let intermediate_outputs = self.first.process(batch)?;
+            if intermediate_outputs.len() != batch.len() {
+                return Err(RustBertError::ValueError(format!(
+                    "Pipeline stage returned {} outputs for {} inputs",
+                    intermediate_outputs.len(),
+                    batch.len()
+                )));
+            }
+            outputs.extend(self.second.process(&intermediate_outputs)?);
+        }
+        Ok(outputs)
+    }
+}
+
+impl Pipeline for SummarizationModel {
+    type Input = String;
+    type Output = String;
+
+    fn process(&self, inputs: &[String]) -> Result<Vec<String>, RustBertError> {
+        self.summarize(inputs)
+    }
+}
+
+impl Pipeline for NERModel {
+    type Input = String;
+    type Output = Vec<Entity>;
+
+    fn process(&self, inputs: &[String]) -> Result<Vec<Vec<Entity>>, RustBertError> {
+        Ok(self.predict(inputs))
+    }
+}
@@ -134,8 +134,10 @@ impl Default for POSConfig {
                 strip_accents: Some(true),
                 add_prefix_space: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
                 label_aggregation_function: LabelAggregationOption::First,
                 batch_size: 64,
+                score_temperature: 1.0,
             },
         }
     }
@@ -176,6 +178,11 @@ impl POSModel {
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `TokenClassificationModel::unload`).
+    pub fn unload(self) {
+        self.token_classification_model.unload();
+    }
+
     /// Extract entities from a text
     ///
     /// # Arguments
@@ -240,9 +247,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = POSConfig::default();
-        let _: Box<dyn Send> = Box::new(POSModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(POSModel::new(config));
     }
 }
@@ -51,7 +51,10 @@ use crate::distilbert::DistilBertForQuestionAnswering;
 use crate::fnet::FNetForQuestionAnswering;
 use crate::longformer::LongformerForQuestionAnswering;
 use crate::mobilebert::MobileBertForQuestionAnswering;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    forward_in_mode, merge_empty_outputs, release_device_memory, set_num_threads,
+    set_var_store_mode, ConfigOption, ModelType, TokenizerOption,
+};
 use crate::reformer::ReformerForQuestionAnswering;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForQuestionAnswering;
@@ -63,9 +66,10 @@ use std::cmp::min;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tch::kind::Kind::Float;
 use tch::nn::VarStore;
-use tch::{nn, no_grad, Device, Tensor};
+use tch::{nn, Device, Tensor};
 
 use crate::deberta_v2::DebertaV2ForQuestionAnswering;
 #[cfg(feature = "remote")]
@@ -128,15 +132,17 @@ fn remove_duplicates<T: PartialEq + Clone>(vector: &mut Vec<T>) -> &mut Vec<T> {
 /// Contains information regarding the model to load and device to place the model on.
 pub struct QuestionAnsweringConfig {
     /// Model weights resource (default: pretrained DistilBERT model on SQuAD)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained DistilBERT model on SQuAD)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained DistilBERT model on SQuAD)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: None)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
     /// Model type
     pub model_type: ModelType,
     /// Flag indicating if the model expects a lower casing of the input
@@ -177,9 +183,9 @@ impl QuestionAnsweringConfig {
         add_prefix_space: impl Into<Option<bool>>,
     ) -> QuestionAnsweringConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         QuestionAnsweringConfig {
             model_type,
@@ -191,6 +197,7 @@ impl QuestionAnsweringConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
             max_seq_length: 384,
             doc_stride: 128,
             max_query_length: 64,
@@ -227,9 +234,9 @@ impl QuestionAnsweringConfig {
         max_answer_length: impl Into<Option<usize>>,
     ) -> QuestionAnsweringConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         QuestionAnsweringConfig {
             model_type,
@@ -241,6 +248,7 @@ impl QuestionAnsweringConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
             max_seq_length: max_seq_length.into().unwrap_or(384),
             doc_stride: doc_stride.into().unwrap_or(128),
             max_query_length: max_query_length.into().unwrap_or(64),
@@ -264,6 +272,7 @@ impl Default for QuestionAnsweringConfig {
             )),
             merges_resource: None,
             device: Device::cuda_if_available(),
+            num_threads: None,
             model_type: ModelType::DistilBert,
             lower_case: false,
             add_prefix_space: None,
@@ -554,7 +563,7 @@ impl QuestionAnsweringOption {
 
 /// # QuestionAnsweringModel to perform extractive question answering
 pub struct QuestionAnsweringModel {
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     pad_idx: i64,
     sep_idx: i64,
     max_seq_len: usize,
@@ -563,6 +572,7 @@ pub struct QuestionAnsweringModel {
     max_answer_len: usize,
     qa_model: QuestionAnsweringOption,
     var_store: VarStore,
+    train: bool,
 }
 
 impl QuestionAnsweringModel {
@@ -585,16 +595,13 @@ impl QuestionAnsweringModel {
     pub fn new(
         question_answering_config: QuestionAnsweringConfig,
     ) -> Result<QuestionAnsweringModel, RustBertError> {
-        let config_path = question_answering_config.config_resource.get_local_path()?;
         let vocab_path = question_answering_config.vocab_resource.get_local_path()?;
-        let weights_path = question_answering_config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &question_answering_config.merges_resource
         {
             Some(merges_resource.get_local_path()?)
         } else {
             None
         };
-        let device = question_answering_config.device;
 
         let tokenizer = TokenizerOption::from_file(
             question_answering_config.model_type,
@@ -604,6 +611,48 @@ impl QuestionAnsweringModel {
             question_answering_config.strip_accents,
             question_answering_config.add_prefix_space,
         )?;
+        Self::new_with_tokenizer(question_answering_config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `QuestionAnsweringModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `question_answering_config` - `QuestionAnsweringConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::question_answering::QuestionAnsweringModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Bert,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let model = QuestionAnsweringModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        question_answering_config: QuestionAnsweringConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<QuestionAnsweringModel, RustBertError> {
+        set_num_threads(question_answering_config.num_threads)?;
+        tokenizer.validate_model_type(question_answering_config.model_type)?;
+        let config_path = question_answering_config.config_resource.get_local_path()?;
+        let weights_path = question_answering_config.model_resource.get_local_path()?;
+        let device = question_answering_config.device;
+
         let pad_idx = tokenizer
             .get_pad_id()
             .expect("The Tokenizer used for Question Answering should contain a PAD id");
@@ -640,6 +689,7 @@ impl QuestionAnsweringModel {
         }
 
         var_store.load(weights_path)?;
+        set_var_store_mode(&mut var_store, false);
         Ok(QuestionAnsweringModel {
             tokenizer,
             pad_idx,
@@ -650,9 +700,36 @@ impl QuestionAnsweringModel {
             max_answer_len: question_answering_config.max_answer_length,
             qa_model,
             var_store,
+            train: false,
         })
     }
 
+    /// Sets the model in training mode: dropout is applied, the variables of the model are unfrozen and
+    /// the forward pass tracks gradients.
+    pub fn train(&mut self) {
+        self.train = true;
+        set_var_store_mode(&mut self.var_store, true);
+    }
+
+    /// Sets the model in evaluation mode (default): dropout is disabled, the variables of the model are
+    /// frozen and the forward pass runs under `no_grad`.
+    pub fn eval(&mut self) {
+        self.train = false;
+        set_var_store_mode(&mut self.var_store, false);
+    }
+
+    /// Returns `true` if the model is in training mode, `false` if it is in evaluation mode.
+    pub fn is_training(&self) -> bool {
+        self.train
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
     /// Perform extractive question answering given a list of `QaInputs`
     ///
     /// # Arguments
@@ -696,6 +773,20 @@ impl QuestionAnsweringModel {
         top_k: i64,
         batch_size: usize,
     ) -> Vec<Vec<Answer>> {
+        // Inputs with an empty or whitespace-only question or context have no answer and are not
+        // passed to the model
+        let empty_inputs = qa_inputs
+            .iter()
+            .map(|qa_input| {
+                qa_input.question.trim().is_empty() || qa_input.context.trim().is_empty()
+            })
+            .collect::<Vec<bool>>();
+        let qa_inputs = qa_inputs
+            .iter()
+            .zip(empty_inputs.iter())
+            .filter(|(_, &is_empty)| !is_empty)
+            .map(|(qa_input, _)| qa_input)
+            .collect::<Vec<&QaInput>>();
         let mut features: Vec<QaFeature> = qa_inputs
             .iter()
             .enumerate()
@@ -717,12 +808,12 @@ impl QuestionAnsweringModel {
         while start < len_features {
             let end = start + min(len_features - start, batch_size);
             let batch_features = &mut features[start..end];
-            no_grad(|| {
+            forward_in_mode(self.train, |train| {
                 let (input_ids, attention_masks) = self.pad_features(batch_features);
 
                 let (start_logits, end_logits) =
                     self.qa_model
-                        .forward_t(Some(&input_ids), Some(&attention_masks), None, false);
+                        .forward_t(Some(&input_ids), Some(&attention_masks), None, train);
 
                 let start_logits = start_logits.detach();
                 let end_logits = end_logits.detach();
@@ -739,6 +830,7 @@ impl QuestionAnsweringModel {
                 for (example_id, max_feature_id) in example_index_to_feature_end_position {
                     let mut answers: Vec<Answer> = vec![];
                     let example = &qa_inputs[example_id];
+                    let context_length = example.context.chars().count();
                     for feature_idx in feature_id_start..max_feature_id {
                         let feature = &batch_features[feature_idx as usize];
                         let p_mask = (Tensor::of_slice(&feature.p_mask) - 1)
@@ -761,6 +853,9 @@ impl QuestionAnsweringModel {
                             let end_pos = feature.offsets[ends[idx] as usize]
                                 .unwrap_or(Offset { begin: 0, end: 0 })
                                 .end as usize;
+                            // Offsets are character positions, clamped to the (possibly truncated) context
+                            let end_pos = min(end_pos, context_length);
+                            let start_pos = min(start_pos, end_pos);
                             let answer = example
                                 .context
                                 .chars()
@@ -794,7 +889,7 @@ impl QuestionAnsweringModel {
                 all_answers.push(vec![]);
             }
         }
-        all_answers
+        merge_empty_outputs(all_answers, &empty_inputs, Vec::new)
     }
 
     fn decode(&self, start: &Tensor, end: &Tensor, top_k: i64) -> (Vec<i64>, Vec<i64>, Vec<f64>) {
@@ -1005,9 +1100,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = QuestionAnsweringConfig::default();
-        let _: Box<dyn Send> = Box::new(QuestionAnsweringModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(QuestionAnsweringModel::new(config));
     }
 }
@@ -5,7 +5,8 @@ use tch::Device;
 
 use crate::pipelines::common::ModelType;
 use crate::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsConfig, SentenceEmbeddingsModel, SentenceEmbeddingsModulesConfig,
+    LayerSelection, SentenceEmbeddingsConfig, SentenceEmbeddingsModel,
+    SentenceEmbeddingsModulesConfig,
 };
 use crate::{Config, RustBertError};
 
@@ -21,6 +22,7 @@ use crate::{
 /// (configuration and weights).
 pub struct SentenceEmbeddingsBuilder<T> {
     device: Device,
+    layer: LayerSelection,
     inner: T,
 }
 
@@ -29,6 +31,12 @@ impl<T> SentenceEmbeddingsBuilder<T> {
         self.device = device;
         self
     }
+
+    /// Sets the transformer layer(s) whose output is pooled into the sentence embeddings
+    pub fn with_layer(mut self, layer: LayerSelection) -> Self {
+        self.layer = layer;
+        self
+    }
 }
 
 pub struct Local {
@@ -46,6 +54,7 @@ impl SentenceEmbeddingsBuilder<Local> {
     pub fn local<P: Into<PathBuf>>(model_dir: P) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            layer: LayerSelection::Last,
             inner: Local {
                 model_dir: model_dir.into(),
             },
@@ -107,6 +116,8 @@ impl SentenceEmbeddingsBuilder<Local> {
             tokenizer_vocab_resource: tokenizer_vocab.into(),
             tokenizer_merges_resource: tokenizer_merges.map(|r| r.into()),
             device: self.device,
+            num_threads: None,
+            layer: self.layer,
         };
 
         SentenceEmbeddingsModel::new(config)
@@ -123,6 +134,7 @@ impl SentenceEmbeddingsBuilder<Remote> {
     pub fn remote(model_type: SentenceEmbeddingsModelType) -> Self {
         Self {
             device: Device::cuda_if_available(),
+            layer: LayerSelection::Last,
             inner: Remote {
                 config: SentenceEmbeddingsConfig::from(model_type),
             },
@@ -180,6 +192,8 @@ impl SentenceEmbeddingsBuilder<Remote> {
     }
 
     pub fn create_model(self) -> Result<SentenceEmbeddingsModel, RustBertError> {
-        SentenceEmbeddingsModel::new(self.inner.config)
+        let mut config = self.inner.config;
+        config.layer = self.layer;
+        SentenceEmbeddingsModel::new(config)
     }
 }
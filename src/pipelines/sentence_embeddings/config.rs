@@ -32,29 +32,51 @@ use crate::{
 /// layers, and device to place the model on.
 pub struct SentenceEmbeddingsConfig {
     /// Modules configuration resource, contains layers definition
-    pub modules_config_resource: Box<dyn ResourceProvider + Send>,
+    pub modules_config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Transformer model type
     pub transformer_type: ModelType,
     /// Transformer model configuration resource
-    pub transformer_config_resource: Box<dyn ResourceProvider + Send>,
+    pub transformer_config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Transformer weights resource
-    pub transformer_weights_resource: Box<dyn ResourceProvider + Send>,
+    pub transformer_weights_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Pooling layer configuration resource
-    pub pooling_config_resource: Box<dyn ResourceProvider + Send>,
+    pub pooling_config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Optional dense layer configuration resource
-    pub dense_config_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub dense_config_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Optional dense layer weights resource
-    pub dense_weights_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub dense_weights_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Sentence BERT specific configuration resource
-    pub sentence_bert_config_resource: Box<dyn ResourceProvider + Send>,
+    pub sentence_bert_config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Transformer's tokenizer configuration resource
-    pub tokenizer_config_resource: Box<dyn ResourceProvider + Send>,
+    pub tokenizer_config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Transformer's tokenizer vocab resource
-    pub tokenizer_vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub tokenizer_vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Optional transformer's tokenizer merges resource
-    pub tokenizer_merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub tokenizer_merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Device to place the transformer model on
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
+    /// Transformer layer(s) whose output is pooled into the sentence embeddings (default: `LayerSelection::Last`)
+    pub layer: LayerSelection,
+}
+
+/// # Selection of the transformer layer(s) pooled into the sentence embeddings
+///
+/// Layers are indexed from 0 (output of the first transformer layer) to the number of layers minus one
+/// (output of the last layer). Selecting intermediate layers requires the transformer to output all
+/// of its hidden states, which is enabled automatically.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LayerSelection {
+    /// Output of the last transformer layer
+    #[default]
+    Last,
+    /// Output of the n-th transformer layer
+    Nth(usize),
+    /// Sum of the outputs of the n last transformer layers
+    SumLast(usize),
+    /// Concatenation of the outputs of the given transformer layers along the hidden dimension
+    Concat(Vec<usize>),
 }
 
 #[cfg(feature = "remote")]
@@ -92,6 +114,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::BertBaseNliMeanTokens => SentenceEmbeddingsConfig {
@@ -121,6 +145,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::AllMiniLmL12V2 => SentenceEmbeddingsConfig {
@@ -150,6 +176,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::AllMiniLmL6V2 => SentenceEmbeddingsConfig {
@@ -179,6 +207,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::AllDistilrobertaV1 => SentenceEmbeddingsConfig {
@@ -210,6 +240,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                     RobertaMergesResources::ALL_DISTILROBERTA_V1,
                 ))),
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::ParaphraseAlbertSmallV2 => SentenceEmbeddingsConfig {
@@ -239,6 +271,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
 
             SentenceEmbeddingsModelType::SentenceT5Base => SentenceEmbeddingsConfig {
@@ -272,6 +306,8 @@ impl From<SentenceEmbeddingsModelType> for SentenceEmbeddingsConfig {
                 )),
                 tokenizer_merges_resource: None,
                 device: Device::cuda_if_available(),
+                num_threads: None,
+                layer: LayerSelection::Last,
             },
         }
     }
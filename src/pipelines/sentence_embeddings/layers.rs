@@ -23,6 +23,9 @@ pub struct PoolingConfig {
 
 impl Config for PoolingConfig {}
 
+/// Default minimum value of the number of tokens dividing the sum of their embeddings for mean pooling
+pub const DEFAULT_POOLING_EPS: f64 = 10e-9;
+
 /// Performs pooling (max or mean) on the token embeddings.
 ///
 /// Using pooling, it generates from a variable sized sentence a fixed sized sentence
@@ -36,7 +39,18 @@ impl Pooling {
         Pooling { conf }
     }
 
-    pub fn forward(&self, mut token_embeddings: Tensor, attention_mask: &Tensor) -> Tensor {
+    pub fn forward(&self, token_embeddings: Tensor, attention_mask: &Tensor) -> Tensor {
+        self.forward_with_eps(token_embeddings, attention_mask, DEFAULT_POOLING_EPS)
+    }
+
+    /// Pools the token embeddings, the number of tokens dividing the sum of their embeddings
+    /// for mean pooling being clamped to a minimum value of `eps`
+    pub fn forward_with_eps(
+        &self,
+        mut token_embeddings: Tensor,
+        attention_mask: &Tensor,
+        eps: f64,
+    ) -> Tensor {
         let mut output_vectors = Vec::new();
 
         if self.conf.pooling_mode_cls_token {
@@ -48,7 +62,7 @@ impl Pooling {
             let input_mask_expanded = attention_mask.unsqueeze(-1).expand_as(&token_embeddings);
             // Set padding tokens to large negative value
             token_embeddings = token_embeddings.masked_fill_(&input_mask_expanded.eq(0), -1e9);
-            let max_over_time = token_embeddings.max_dim(1, true).0;
+            let max_over_time = token_embeddings.max_dim(1, false).0;
             output_vectors.push(max_over_time);
         }
 
@@ -60,7 +74,7 @@ impl Pooling {
                 Kind::Float,
             );
             let sum_mask = input_mask_expanded.sum_dim_intlist([1].as_slice(), false, Kind::Float);
-            let sum_mask = sum_mask.clamp_min(10e-9);
+            let sum_mask = sum_mask.clamp_min(eps);
 
             if self.conf.pooling_mode_mean_tokens {
                 output_vectors.push(&sum_embeddings / &sum_mask);
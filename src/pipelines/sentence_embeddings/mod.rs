@@ -36,12 +36,12 @@ mod resources;
 
 pub use builder::SentenceEmbeddingsBuilder;
 pub use config::{
-    SentenceEmbeddingsConfig, SentenceEmbeddingsModuleConfig, SentenceEmbeddingsModuleType,
-    SentenceEmbeddingsModulesConfig, SentenceEmbeddingsSentenceBertConfig,
-    SentenceEmbeddingsTokenizerConfig,
+    LayerSelection, SentenceEmbeddingsConfig, SentenceEmbeddingsModuleConfig,
+    SentenceEmbeddingsModuleType, SentenceEmbeddingsModulesConfig,
+    SentenceEmbeddingsSentenceBertConfig, SentenceEmbeddingsTokenizerConfig,
 };
 pub use pipeline::{
-    SentenceEmbeddingsModel, SentenceEmbeddingsModelOuput, SentenceEmbeddingsOption,
+    MultiPooled, SentenceEmbeddingsModel, SentenceEmbeddingsModelOuput, SentenceEmbeddingsOption,
     SentenceEmbeddingsTokenizerOuput,
 };
 
@@ -1,23 +1,36 @@
 use std::borrow::Borrow;
+use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::ops::Range;
 
 use rust_tokenizers::tokenizer::TruncationStrategy;
-use tch::{nn, Tensor};
+use rust_tokenizers::TokenIdsWithOffsets;
+use tch::nn::ModuleT;
+use tch::{nn, Device, Kind, Tensor};
 
 use crate::albert::AlbertForSentenceEmbeddings;
 use crate::bert::BertForSentenceEmbeddings;
+use crate::common::dropout::Dropout;
 use crate::distilbert::DistilBertForSentenceEmbeddings;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
-use crate::pipelines::sentence_embeddings::layers::{Dense, DenseConfig, Pooling, PoolingConfig};
+use crate::pipelines::common::{
+    release_device_memory, set_num_threads, ConfigOption, ModelType, TokenizerOption,
+};
+use crate::pipelines::sentence_embeddings::layers::{
+    Dense, DenseConfig, Pooling, PoolingConfig, DEFAULT_POOLING_EPS,
+};
 use crate::pipelines::sentence_embeddings::{
-    AttentionHead, AttentionLayer, AttentionOutput, Embedding, SentenceEmbeddingsConfig,
-    SentenceEmbeddingsModulesConfig, SentenceEmbeddingsSentenceBertConfig,
-    SentenceEmbeddingsTokenizerConfig,
+    AttentionHead, AttentionLayer, AttentionOutput, Embedding, LayerSelection,
+    SentenceEmbeddingsConfig, SentenceEmbeddingsModulesConfig,
+    SentenceEmbeddingsSentenceBertConfig, SentenceEmbeddingsTokenizerConfig,
 };
 use crate::roberta::RobertaForSentenceEmbeddings;
 use crate::t5::T5ForSentenceEmbeddings;
 use crate::{Config, RustBertError};
 
+/// Default minimum value of the norm of the embeddings dividing them for normalization
+const DEFAULT_NORMALIZATION_EPS: f64 = 1e-12;
+
 /// # Abstraction that holds one particular sentence embeddings model, for any of the supported models
 pub enum SentenceEmbeddingsOption {
     /// Bert for Sentence Embeddings
@@ -80,7 +93,19 @@ impl SentenceEmbeddingsOption {
         tokens_ids: &Tensor,
         tokens_masks: &Tensor,
     ) -> Result<(Tensor, Option<Vec<Tensor>>), RustBertError> {
-        match self {
+        self.forward_with_hidden_states(tokens_ids, tokens_masks)
+            .map(|(hidden_state, _, all_attentions)| (hidden_state, all_attentions))
+    }
+
+    /// Interface method to forward() of the particular transformer models, also returning the
+    /// outputs of all transformer layers if the model was created with `output_hidden_states` set in
+    /// its configuration. The last element of the layer outputs is the last hidden state.
+    pub fn forward_with_hidden_states(
+        &self,
+        tokens_ids: &Tensor,
+        tokens_masks: &Tensor,
+    ) -> Result<(Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>), RustBertError> {
+        let (hidden_state, all_hidden_states, all_attentions) = match self {
             Self::Bert(transformer) => transformer
                 .forward_t(
                     Some(tokens_ids),
@@ -95,17 +120,19 @@ impl SentenceEmbeddingsOption {
                 .map(|transformer_output| {
                     (
                         transformer_output.hidden_state,
+                        transformer_output.all_hidden_states,
                         transformer_output.all_attentions,
                     )
-                }),
+                })?,
             Self::DistilBert(transformer) => transformer
                 .forward_t(Some(tokens_ids), Some(tokens_masks), None, false)
                 .map(|transformer_output| {
                     (
                         transformer_output.hidden_state,
+                        transformer_output.all_hidden_states,
                         transformer_output.all_attentions,
                     )
-                }),
+                })?,
             Self::Roberta(transformer) => transformer
                 .forward_t(
                     Some(tokens_ids),
@@ -120,9 +147,10 @@ impl SentenceEmbeddingsOption {
                 .map(|transformer_output| {
                     (
                         transformer_output.hidden_state,
+                        transformer_output.all_hidden_states,
                         transformer_output.all_attentions,
                     )
-                }),
+                })?,
             Self::Albert(transformer) => transformer
                 .forward_t(
                     Some(tokens_ids),
@@ -133,8 +161,19 @@ impl SentenceEmbeddingsOption {
                     false,
                 )
                 .map(|transformer_output| {
+                    // ALBERT stores the hidden states at the input of each layer
+                    let last_hidden_state = transformer_output.hidden_state.copy();
+                    let all_hidden_states =
+                        transformer_output.all_hidden_states.map(|hidden_states| {
+                            hidden_states
+                                .into_iter()
+                                .skip(1)
+                                .chain(std::iter::once(last_hidden_state))
+                                .collect()
+                        });
                     (
                         transformer_output.hidden_state,
+                        all_hidden_states,
                         transformer_output.all_attentions.map(|attentions| {
                             attentions
                                 .into_iter()
@@ -145,9 +184,21 @@ impl SentenceEmbeddingsOption {
                                 .collect()
                         }),
                     )
-                }),
-            Self::T5(transformer) => transformer.forward(tokens_ids, tokens_masks),
-        }
+                })?,
+            Self::T5(transformer) => {
+                let (hidden_state, all_hidden_states, all_attentions) =
+                    transformer.forward_with_hidden_states(tokens_ids, tokens_masks)?;
+                // The last T5 layer output is followed by a final layer normalization
+                let all_hidden_states = all_hidden_states.map(|mut hidden_states| {
+                    if let Some(last_hidden_state) = hidden_states.last_mut() {
+                        *last_hidden_state = hidden_state.copy();
+                    }
+                    hidden_states
+                });
+                (hidden_state, all_hidden_states, all_attentions)
+            }
+        };
+        Ok((hidden_state, all_hidden_states, all_attentions))
     }
 }
 
@@ -168,6 +219,13 @@ pub struct SentenceEmbeddingsModel {
     pooling_layer: Pooling,
     dense_layer: Option<Dense>,
     normalize_embeddings: bool,
+    layer: LayerSelection,
+    pooling_eps: f64,
+    normalization_eps: f64,
+    query_prefix: Option<String>,
+    passage_prefix: Option<String>,
+    pooling_dropout: Dropout,
+    train: bool,
 }
 
 impl SentenceEmbeddingsModel {
@@ -190,7 +248,10 @@ impl SentenceEmbeddingsModel {
             dense_config_resource,
             dense_weights_resource,
             device,
+            num_threads,
+            layer,
         } = config;
+        set_num_threads(num_threads)?;
 
         let modules =
             SentenceEmbeddingsModulesConfig::from_file(modules_config_resource.get_local_path()?)
@@ -226,10 +287,13 @@ impl SentenceEmbeddingsModel {
         // Setup transformer
 
         let mut var_store = nn::VarStore::new(device);
-        let transformer_config = ConfigOption::from_file(
+        let mut transformer_config = ConfigOption::from_file(
             transformer_type,
             transformer_config_resource.get_local_path()?,
         );
+        if layer != LayerSelection::Last {
+            enable_hidden_states(&mut transformer_config);
+        }
         let transformer =
             SentenceEmbeddingsOption::new(transformer_type, var_store.root(), &transformer_config)?;
         var_store.load(transformer_weights_resource.get_local_path()?)?;
@@ -253,6 +317,14 @@ impl SentenceEmbeddingsModel {
             None
         };
 
+        if let LayerSelection::Concat(layers) = &layer {
+            if layers.is_empty() || (layers.len() > 1 && dense_layer.is_some()) {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "The concatenation of layers must contain at least one layer, and a single layer if the model has a dense layer".to_string(),
+                ));
+            }
+        }
+
         let normalize_embeddings = modules.has_normalization();
 
         Ok(Self {
@@ -265,14 +337,74 @@ impl SentenceEmbeddingsModel {
             pooling_layer,
             dense_layer,
             normalize_embeddings,
+            layer,
+            pooling_eps: DEFAULT_POOLING_EPS,
+            normalization_eps: DEFAULT_NORMALIZATION_EPS,
+            query_prefix: None,
+            passage_prefix: None,
+            pooling_dropout: Dropout::new(0.0),
+            train: false,
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
     /// Sets the tokenizer's truncation strategy
     pub fn set_tokenizer_truncation(&mut self, truncation_strategy: TruncationStrategy) {
         self.tokenizer_truncation_strategy = truncation_strategy;
     }
 
+    /// Sets the minimum value of the number of tokens dividing the sum of their embeddings for mean pooling,
+    /// preventing divisions by zero (default: 10e-9)
+    pub fn set_pooling_eps(&mut self, eps: f64) {
+        self.pooling_eps = eps;
+    }
+
+    /// Sets the minimum value of the norm of the embeddings dividing them for normalization, preventing
+    /// divisions by zero (default: 1e-12)
+    pub fn set_normalization_eps(&mut self, eps: f64) {
+        self.normalization_eps = eps;
+    }
+
+    /// Sets the prefix prepended to the queries by `encode_queries`, required by instruction-tuned
+    /// models (e.g. `"query: "` for E5 models, default: None)
+    pub fn set_query_prefix(&mut self, query_prefix: Option<String>) {
+        self.query_prefix = query_prefix;
+    }
+
+    /// Sets the prefix prepended to the passages by `encode_passages`, required by instruction-tuned
+    /// models (e.g. `"passage: "` for E5 models, default: None)
+    pub fn set_passage_prefix(&mut self, passage_prefix: Option<String>) {
+        self.passage_prefix = passage_prefix;
+    }
+
+    /// Sets the dropout probability applied to the pooled output in training mode, e.g. to fine-tune a custom head
+    /// on top of the embeddings of the frozen encoder (default: 0.0, no dropout)
+    pub fn set_pooling_dropout(&mut self, pooling_dropout: f64) {
+        self.pooling_dropout = Dropout::new(pooling_dropout);
+    }
+
+    /// Sets the model in training mode: the pooling dropout is applied to the pooled output. The encoder remains
+    /// frozen, and is run in evaluation mode without tracking gradients.
+    pub fn train(&mut self) {
+        self.train = true;
+    }
+
+    /// Sets the model in evaluation mode (default): no pooling dropout is applied.
+    pub fn eval(&mut self) {
+        self.train = false;
+    }
+
+    /// Returns `true` if the model is in training mode, `false` if it is in evaluation mode.
+    pub fn is_training(&self) -> bool {
+        self.train
+    }
+
     /// Tokenizes the inputs
     pub fn tokenize<S>(&self, inputs: &[S]) -> SentenceEmbeddingsTokenizerOuput
     where
@@ -336,14 +468,23 @@ impl SentenceEmbeddingsModel {
             tokens_ids,
             tokens_masks,
         } = self.tokenize(inputs);
-        let tokens_ids = Tensor::stack(&tokens_ids, 0).to(self.var_store.device());
-        let tokens_masks = Tensor::stack(&tokens_masks, 0).to(self.var_store.device());
+        self.encode_tokens(&tokens_ids, &tokens_masks)
+    }
 
+    fn encode_tokens(
+        &self,
+        tokens_ids: &[Tensor],
+        tokens_masks: &[Tensor],
+    ) -> Result<SentenceEmbeddingsModelOuput, RustBertError> {
+        let tokens_masks = Tensor::stack(tokens_masks, 0).to(self.var_store.device());
         let (tokens_embeddings, all_attentions) =
-            tch::no_grad(|| self.transformer.forward(&tokens_ids, &tokens_masks))?;
+            self.forward_token_embeddings(tokens_ids, &tokens_masks)?;
 
-        let mean_pool =
-            tch::no_grad(|| self.pooling_layer.forward(tokens_embeddings, &tokens_masks));
+        let mean_pool = tch::no_grad(|| {
+            self.pooling_layer
+                .forward_with_eps(tokens_embeddings, &tokens_masks, self.pooling_eps)
+        });
+        let mean_pool = self.pooling_dropout.forward_t(&mean_pool, self.train);
         let maybe_linear = if let Some(dense_layer) = &self.dense_layer {
             tch::no_grad(|| dense_layer.forward(&mean_pool))
         } else {
@@ -352,7 +493,7 @@ impl SentenceEmbeddingsModel {
         let maybe_normalized = if self.normalize_embeddings {
             let norm = &maybe_linear
                 .norm_scalaropt_dim(2, &[1], true)
-                .clamp_min(1e-12)
+                .clamp_min(self.normalization_eps)
                 .expand_as(&maybe_linear);
             maybe_linear / norm
         } else {
@@ -365,6 +506,78 @@ impl SentenceEmbeddingsModel {
         })
     }
 
+    fn forward_token_embeddings(
+        &self,
+        tokens_ids: &[Tensor],
+        tokens_masks: &Tensor,
+    ) -> Result<(Tensor, Option<Vec<Tensor>>), RustBertError> {
+        let tokens_ids = Tensor::stack(tokens_ids, 0).to(self.var_store.device());
+        if self.layer == LayerSelection::Last {
+            tch::no_grad(|| self.transformer.forward(&tokens_ids, tokens_masks))
+        } else {
+            let (_, all_hidden_states, all_attentions) = tch::no_grad(|| {
+                self.transformer
+                    .forward_with_hidden_states(&tokens_ids, tokens_masks)
+            })?;
+            Ok((self.select_layers(all_hidden_states)?, all_attentions))
+        }
+    }
+
+    /// Computes the mean, max and CLS poolings of the token embeddings of the inputs from a single
+    /// forward pass of the transformer. The pooled vectors are returned as is, the dense layer and
+    /// normalization of the model (which depend on its configured pooling) are not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - slice of texts to embed
+    ///
+    /// # Returns
+    /// * `MultiPooled` mean, max and CLS pooled vectors of each input, of the dimension of the transformer hidden states
+    pub fn encode_multi_pool<S>(&self, inputs: &[S]) -> Result<MultiPooled, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let SentenceEmbeddingsTokenizerOuput {
+            tokens_ids,
+            tokens_masks,
+        } = self.tokenize(inputs);
+        let tokens_masks = Tensor::stack(&tokens_masks, 0).to(self.var_store.device());
+        let (tokens_embeddings, _) = self.forward_token_embeddings(&tokens_ids, &tokens_masks)?;
+
+        let pool = |pooling_config: PoolingConfig, tokens_embeddings: Tensor| {
+            let pooled = tch::no_grad(|| {
+                Pooling::new(pooling_config).forward_with_eps(
+                    tokens_embeddings,
+                    &tokens_masks,
+                    self.pooling_eps,
+                )
+            });
+            Vec::<Embedding>::from(pooled)
+        };
+        let pooling_config = |cls: bool, max: bool, mean: bool| PoolingConfig {
+            word_embedding_dimension: tokens_embeddings.size()[2],
+            pooling_mode_cls_token: cls,
+            pooling_mode_max_tokens: max,
+            pooling_mode_mean_tokens: mean,
+            pooling_mode_mean_sqrt_len_tokens: false,
+        };
+        let cls = pool(
+            pooling_config(true, false, false),
+            tokens_embeddings.shallow_clone(),
+        );
+        let mean = pool(
+            pooling_config(false, false, true),
+            tokens_embeddings.shallow_clone(),
+        );
+        // Max pooling masks the padding tokens in place and is computed last
+        let max = pool(
+            pooling_config(false, true, false),
+            tokens_embeddings.shallow_clone(),
+        );
+
+        Ok(MultiPooled { mean, max, cls })
+    }
+
     /// Computes sentence embeddings.
     pub fn encode<S>(&self, inputs: &[S]) -> Result<Vec<Embedding>, RustBertError>
     where
@@ -374,6 +587,344 @@ impl SentenceEmbeddingsModel {
         Ok(Vec::from(embeddings))
     }
 
+    /// Computes the embeddings of search queries, prepending the query prefix of the model (see `set_query_prefix`)
+    /// to the inputs.
+    pub fn encode_queries<S>(&self, inputs: &[S]) -> Result<Vec<Embedding>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.encode_with_prefix(inputs, self.query_prefix.as_deref())
+    }
+
+    /// Computes the embeddings of the passages searched, prepending the passage prefix of the model
+    /// (see `set_passage_prefix`) to the inputs.
+    pub fn encode_passages<S>(&self, inputs: &[S]) -> Result<Vec<Embedding>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        self.encode_with_prefix(inputs, self.passage_prefix.as_deref())
+    }
+
+    fn encode_with_prefix<S>(
+        &self,
+        inputs: &[S],
+        prefix: Option<&str>,
+    ) -> Result<Vec<Embedding>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        match prefix {
+            Some(prefix) => {
+                let prefixed_inputs = inputs
+                    .iter()
+                    .map(|input| format!("{}{}", prefix, input.as_ref()))
+                    .collect::<Vec<String>>();
+                self.encode(&prefixed_inputs)
+            }
+            None => self.encode(inputs),
+        }
+    }
+
+    /// Computes sentence embeddings, writing them contiguously (one embedding after the other)
+    /// into a pre-allocated buffer. Returns the number of values written, the buffer is left
+    /// untouched if it is too small to hold all embeddings.
+    pub fn encode_into<S>(&self, inputs: &[S], out: &mut [f32]) -> Result<usize, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let SentenceEmbeddingsModelOuput { embeddings, .. } = self.encode_as_tensor(inputs)?;
+        let embeddings = embeddings.to_kind(Kind::Float).to(Device::Cpu).contiguous();
+        let num_values = embeddings.numel();
+        if num_values > out.len() {
+            return Err(RustBertError::ValueError(format!(
+                "Output buffer of length {} is too small to hold {} embeddings values",
+                out.len(),
+                num_values
+            )));
+        }
+        embeddings.f_copy_data(&mut out[..num_values], num_values)?;
+        Ok(num_values)
+    }
+
+    /// Computes the cosine similarities between all pairs of inputs from a single batched forward pass,
+    /// e.g. as a building block for clustering or deduplication. The returned matrix is symmetric.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - slice of texts to compare
+    ///
+    /// # Returns
+    /// * `Vec<Vec<f32>>` matrix of size *number of inputs* x *number of inputs*, the value at (i, j) being the cosine similarity between inputs i and j
+    pub fn pairwise_similarity_matrix<S>(
+        &self,
+        inputs: &[S],
+    ) -> Result<Vec<Vec<f32>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let SentenceEmbeddingsModelOuput { embeddings, .. } = self.encode_as_tensor(inputs)?;
+        let embeddings = embeddings.to_kind(Kind::Float);
+        let norm = embeddings
+            .linalg_norm(2.0, [1i64].as_slice(), true, Kind::Float)
+            .clamp_min(1e-12);
+        let embeddings = &embeddings / norm;
+        let similarities = tch::no_grad(|| embeddings.matmul(&embeddings.transpose(0, 1)));
+        let mut similarities = Vec::<Vec<f32>>::from(similarities.to(Device::Cpu));
+        // Mirrors the upper triangle to remove the rounding differences of the batched product
+        for i in 0..similarities.len() {
+            for j in 0..i {
+                similarities[i][j] = similarities[j][i];
+            }
+        }
+        Ok(similarities)
+    }
+
+    /// Groups similar inputs with a single-linkage agglomerative clustering on the cosine distance
+    /// (1 - cosine similarity) of their embeddings. Clusters are merged as long as the distance between
+    /// their closest members does not exceed the threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - slice of texts to cluster
+    /// * `threshold` - maximum cosine distance between two inputs linked in a cluster (between 0 and 2)
+    ///
+    /// # Returns
+    /// * `Vec<Vec<usize>>` indices of the inputs of each cluster, in increasing order. Clusters are ordered by their first input.
+    pub fn cluster<S>(&self, inputs: &[S], threshold: f32) -> Result<Vec<Vec<usize>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let similarities = self.pairwise_similarity_matrix(inputs)?;
+
+        // Merging the clusters in order of increasing distance up to the threshold links the same
+        // inputs as the connected components of the inputs within the threshold distance
+        let mut parents = (0..inputs.len()).collect::<Vec<usize>>();
+        for (i, row) in similarities.iter().enumerate() {
+            for (j, similarity) in row.iter().enumerate().skip(i + 1) {
+                if 1.0 - similarity <= threshold {
+                    let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                    parents[max(root_i, root_j)] = min(root_i, root_j);
+                }
+            }
+        }
+
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        let mut cluster_positions: HashMap<usize, usize> = HashMap::new();
+        for index in 0..inputs.len() {
+            let root = find_root(&mut parents, index);
+            match cluster_positions.get(&root) {
+                Some(position) => clusters[*position].push(index),
+                None => {
+                    cluster_positions.insert(root, clusters.len());
+                    clusters.push(vec![index]);
+                }
+            }
+        }
+        Ok(clusters)
+    }
+
+    /// Computes the embeddings of the overlapping chunks of a long document, e.g. for retrieval over
+    /// documents exceeding the maximum sequence length of the model. The chunk boundaries are aligned
+    /// to token boundaries, and each embedding is returned with the character span of its chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` document to embed
+    /// * `chunk_tokens` - maximum number of tokens of each chunk (excluding special tokens)
+    /// * `stride` - number of tokens overlapping between consecutive chunks, lower than `chunk_tokens`
+    ///
+    /// # Returns
+    /// * `Vec<(Range<usize>, Embedding)>` character span in `text` and embedding of each chunk
+    pub fn encode_chunks(
+        &self,
+        text: &str,
+        chunk_tokens: usize,
+        stride: usize,
+    ) -> Result<Vec<(Range<usize>, Embedding)>, RustBertError> {
+        if stride >= chunk_tokens {
+            return Err(RustBertError::ValueError(format!(
+                "The stride ({}) must be lower than the number of tokens per chunk ({})",
+                stride, chunk_tokens
+            )));
+        }
+        let tokenized_text = self.tokenizer.tokenize_with_offsets(text);
+        let token_ids = self.tokenizer.convert_tokens_to_ids(&tokenized_text.tokens);
+        if token_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut spans = Vec::new();
+        let mut chunks_ids = Vec::new();
+        let mut start_token = 0;
+        loop {
+            let end_token = min(start_token + chunk_tokens, token_ids.len());
+            let offsets = &tokenized_text.offsets[start_token..end_token];
+            let span_start = offsets.iter().flatten().next().map(|offset| offset.begin);
+            let span_end = offsets.iter().flatten().last().map(|offset| offset.end);
+            if let (Some(span_start), Some(span_end)) = (span_start, span_end) {
+                spans.push(span_start as usize..span_end as usize);
+                chunks_ids.push(
+                    self.tokenizer
+                        .build_input_with_special_tokens(
+                            TokenIdsWithOffsets {
+                                ids: token_ids[start_token..end_token].to_vec(),
+                                offsets: offsets.to_vec(),
+                                reference_offsets: tokenized_text.reference_offsets
+                                    [start_token..end_token]
+                                    .to_vec(),
+                                masks: tokenized_text.masks[start_token..end_token].to_vec(),
+                            },
+                            None,
+                        )
+                        .token_ids,
+                );
+            }
+            if end_token == token_ids.len() {
+                break;
+            }
+            start_token = end_token - stride;
+        }
+
+        let max_len = chunks_ids.iter().map(Vec::len).max().unwrap_or(0);
+        let pad_token_id = self.tokenizer.get_pad_id().unwrap_or(0);
+        let tokens_masks = chunks_ids
+            .iter()
+            .map(|chunk_ids| {
+                let mut mask = vec![1i64; chunk_ids.len()];
+                mask.resize(max_len, 0);
+                Tensor::of_slice(&mask)
+            })
+            .collect::<Vec<Tensor>>();
+        let tokens_ids = chunks_ids
+            .into_iter()
+            .map(|mut chunk_ids| {
+                chunk_ids.resize(max_len, pad_token_id);
+                Tensor::of_slice(&chunk_ids)
+            })
+            .collect::<Vec<Tensor>>();
+
+        let SentenceEmbeddingsModelOuput { embeddings, .. } =
+            self.encode_tokens(&tokens_ids, &tokens_masks)?;
+        Ok(spans
+            .into_iter()
+            .zip(Vec::<Embedding>::from(embeddings))
+            .collect())
+    }
+
+    /// Computes a contextual embedding for each word of a text, averaging the token embeddings of its
+    /// sub-words (e.g. for word alignment). The text is split on whitespace, and the words are mapped to
+    /// their tokens with `TokenizerOption::encode_with_word_ids`. As for `encode_multi_pool`, the dense
+    /// layer and normalization of the model are not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to embed
+    ///
+    /// # Returns
+    /// * `Vec<(String, Embedding)>` each word and its embedding, of the dimension of the transformer hidden states.
+    /// Words without tokens (e.g. beyond the maximum sequence length of the model) are excluded.
+    pub fn encode_word_embeddings(
+        &self,
+        text: &str,
+    ) -> Result<Vec<(String, Embedding)>, RustBertError> {
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        let encoding = self.tokenizer.encode_with_word_ids(
+            &words,
+            true,
+            self.sentence_bert_config.max_seq_length,
+            &self.tokenizer_truncation_strategy,
+            0,
+        )?;
+
+        // Averaging weights of each token (column) for each word with at least one token (row)
+        let num_tokens = encoding.word_ids.len();
+        let mut word_tokens = vec![vec![]; words.len()];
+        for (position, word_id) in encoding.word_ids.iter().enumerate() {
+            if let Some(word_id) = word_id {
+                word_tokens[*word_id].push(position);
+            }
+        }
+        let mut embedded_words = Vec::new();
+        let mut weights = Vec::new();
+        for (word, token_positions) in words.iter().zip(word_tokens) {
+            if token_positions.is_empty() {
+                continue;
+            }
+            let mut word_weights = vec![0f32; num_tokens];
+            for position in token_positions.iter() {
+                word_weights[*position] = 1.0 / token_positions.len() as f32;
+            }
+            embedded_words.push(word.to_string());
+            weights.extend(word_weights);
+        }
+        if embedded_words.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let device = self.var_store.device();
+        let tokens_ids = [Tensor::of_slice(&encoding.tokenized_input.token_ids)];
+        let tokens_masks = Tensor::ones(&[1, num_tokens as i64], (Kind::Int64, device));
+        let (tokens_embeddings, _) = self.forward_token_embeddings(&tokens_ids, &tokens_masks)?;
+        let weights = Tensor::of_slice(&weights)
+            .view([embedded_words.len() as i64, num_tokens as i64])
+            .to(device);
+        let word_embeddings =
+            tch::no_grad(|| weights.matmul(&tokens_embeddings.get(0).to_kind(Kind::Float)));
+
+        Ok(embedded_words
+            .into_iter()
+            .zip(Vec::<Embedding>::from(word_embeddings.to(Device::Cpu)))
+            .collect())
+    }
+
+    fn select_layers(
+        &self,
+        all_hidden_states: Option<Vec<Tensor>>,
+    ) -> Result<Tensor, RustBertError> {
+        let all_hidden_states = all_hidden_states.ok_or_else(|| {
+            RustBertError::InvalidConfigurationError("No hidden states outputted".into())
+        })?;
+        let num_layers = all_hidden_states.len();
+        let check_layer = |layer: usize| {
+            if layer < num_layers {
+                Ok(layer)
+            } else {
+                Err(RustBertError::ValueError(format!(
+                    "Layer {} selected but the transformer only has {} layers",
+                    layer, num_layers
+                )))
+            }
+        };
+        match &self.layer {
+            LayerSelection::Last => Ok(all_hidden_states[num_layers - 1].shallow_clone()),
+            LayerSelection::Nth(layer) => {
+                Ok(all_hidden_states[check_layer(*layer)?].shallow_clone())
+            }
+            LayerSelection::SumLast(count) => {
+                if *count == 0 || *count > num_layers {
+                    return Err(RustBertError::ValueError(format!(
+                        "Cannot sum the last {} layers of a transformer with {} layers",
+                        count, num_layers
+                    )));
+                }
+                Ok(all_hidden_states[num_layers - count..]
+                    .iter()
+                    .map(|hidden_state| hidden_state.shallow_clone())
+                    .sum::<Tensor>())
+            }
+            LayerSelection::Concat(layers) => {
+                let selected_layers = layers
+                    .iter()
+                    .map(|layer| Ok(all_hidden_states[check_layer(*layer)?].shallow_clone()))
+                    .collect::<Result<Vec<Tensor>, RustBertError>>()?;
+                Ok(Tensor::cat(&selected_layers, -1))
+            }
+        }
+    }
+
     fn nb_layers(&self) -> usize {
         use SentenceEmbeddingsOption::*;
         match (&self.transformer, &self.transformer_config) {
@@ -453,8 +1004,39 @@ pub struct SentenceEmbeddingsTokenizerOuput {
     pub tokens_masks: Vec<Tensor>,
 }
 
+/// Container for the mean, max and CLS poolings of the token embeddings of a batch of inputs.
+pub struct MultiPooled {
+    /// Average of the embeddings of the (non-padding) tokens
+    pub mean: Vec<Embedding>,
+    /// Maximum over the (non-padding) tokens in each dimension
+    pub max: Vec<Embedding>,
+    /// Embedding of the first (CLS) token
+    pub cls: Vec<Embedding>,
+}
+
 /// Container for the SentenceEmbeddings model output.
 pub struct SentenceEmbeddingsModelOuput {
     pub embeddings: Tensor,
     pub all_attentions: Option<Vec<Tensor>>,
 }
+
+/// Returns the root of the cluster of an input in a union-find forest, compressing the path to the root
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+fn enable_hidden_states(config: &mut ConfigOption) {
+    match config {
+        ConfigOption::Bert(config) | ConfigOption::Roberta(config) => {
+            config.output_hidden_states = Some(true)
+        }
+        ConfigOption::DistilBert(config) => config.output_hidden_states = Some(true),
+        ConfigOption::Albert(config) => config.output_hidden_states = Some(true),
+        ConfigOption::T5(config) => config.output_hidden_states = Some(true),
+        _ => {}
+    }
+}
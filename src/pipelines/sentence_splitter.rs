@@ -0,0 +1,229 @@
+// Copyright 2023 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Sentence splitting
+//! Splitting of documents into sentences, used by the pipelines processing documents sentence by sentence
+//! (hybrid summarization, sentence-level named entity recognition).
+//!
+//! The default `PunctuationSentenceSplitter` ends sentences at terminal punctuation marks (`.`, `!`, `?`)
+//! followed by a whitespace, except after common abbreviations (e.g. "Dr.", "e.g.") and single-letter initials.
+//! Custom splitting strategies can be provided by implementing the `SentenceSplitter` trait.
+//!
+//! ```no_run
+//! use rust_bert::pipelines::sentence_splitter::{PunctuationSentenceSplitter, SentenceSplitter};
+//!
+//! let splitter = PunctuationSentenceSplitter::default();
+//! let sentences = splitter.split("Dr. Smith arrived. He was late, e.g. by an hour.");
+//! ```
+//!
+//! Output: \
+//! ```no_run
+//! # let output =
+//! ["Dr. Smith arrived.", "He was late, e.g. by an hour."]
+//! # ;
+//! ```
+
+use crate::pipelines::translation::Language;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// # Sentence splitting strategy
+/// Shared by the pipelines requiring sentence boundaries. Implementations must return the byte
+/// ranges of the (non-empty, trimmed) sentences of a text, in order.
+pub trait SentenceSplitter: Send + Sync {
+    /// Returns the byte ranges of the sentences of a text
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to split
+    ///
+    /// # Returns
+    /// * `Vec<Range<usize>>` byte ranges of the sentences in `text`
+    fn sentence_spans(&self, text: &str) -> Vec<Range<usize>>;
+
+    /// Splits a text into sentences
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to split
+    ///
+    /// # Returns
+    /// * `Vec<&str>` sentences of `text`
+    fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        self.sentence_spans(text)
+            .into_iter()
+            .map(|span| &text[span])
+            .collect()
+    }
+}
+
+const ENGLISH_ABBREVIATIONS: &[&str] = &[
+    "mr.", "mrs.", "ms.", "dr.", "prof.", "sr.", "jr.", "st.", "mt.", "gen.", "gov.", "sen.",
+    "rep.", "rev.", "capt.", "lt.", "col.", "sgt.", "e.g.", "i.e.", "vs.", "cf.", "al.", "approx.",
+    "no.", "vol.", "fig.", "inc.", "ltd.", "co.", "corp.", "dept.", "est.", "jan.", "feb.", "mar.",
+    "apr.", "jun.", "jul.", "aug.", "sep.", "sept.", "oct.", "nov.", "dec.", "u.s.", "u.k.",
+    "a.m.", "p.m.",
+];
+
+const FRENCH_ABBREVIATIONS: &[&str] = &[
+    "m.", "mm.", "mme.", "mlle.", "dr.", "pr.", "st.", "ste.", "cf.", "p.ex.", "c.-à-d.", "env.",
+    "av.", "bd.", "n°.", "vol.", "p.", "etc.",
+];
+
+const GERMAN_ABBREVIATIONS: &[&str] = &[
+    "hr.", "fr.", "dr.", "prof.", "st.", "z.b.", "d.h.", "u.a.", "usw.", "bzw.", "ca.", "vgl.",
+    "nr.", "str.", "s.", "inkl.", "evtl.", "ggf.",
+];
+
+const SPANISH_ABBREVIATIONS: &[&str] = &[
+    "sr.", "sra.", "srta.", "dr.", "dra.", "d.", "dña.", "ud.", "uds.", "p.ej.", "etc.", "pág.",
+    "núm.", "av.", "aprox.",
+];
+
+/// # Punctuation-based sentence splitter
+/// Ends sentences at terminal punctuation marks (`.`, `!`, `?`, optionally followed by closing quotes
+/// or brackets) followed by a whitespace or the end of the text. Periods ending a known abbreviation
+/// or a single-letter initial, or followed by a lowercase word, do not end a sentence.
+pub struct PunctuationSentenceSplitter {
+    abbreviations: HashSet<String>,
+}
+
+impl PunctuationSentenceSplitter {
+    /// Build a new `PunctuationSentenceSplitter` with a custom list of abbreviations
+    ///
+    /// # Arguments
+    ///
+    /// * `abbreviations` - abbreviations (including their trailing period, e.g. "Dr.") that do not end a sentence. The matching is case-insensitive.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+    ///
+    /// let splitter = PunctuationSentenceSplitter::new(&["Dr.", "approx."]);
+    /// ```
+    pub fn new<S: AsRef<str>>(abbreviations: &[S]) -> PunctuationSentenceSplitter {
+        PunctuationSentenceSplitter {
+            abbreviations: abbreviations
+                .iter()
+                .map(|abbreviation| abbreviation.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Build a new `PunctuationSentenceSplitter` with the common abbreviations of a language.
+    /// English, French, German and Spanish abbreviations are available, other languages only
+    /// rely on the punctuation.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - `Language` of the texts to split
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+    /// use rust_bert::pipelines::translation::Language;
+    ///
+    /// let splitter = PunctuationSentenceSplitter::for_language(Language::German);
+    /// ```
+    pub fn for_language(language: Language) -> PunctuationSentenceSplitter {
+        let abbreviations: &[&str] = match language {
+            Language::English => ENGLISH_ABBREVIATIONS,
+            Language::French => FRENCH_ABBREVIATIONS,
+            Language::German => GERMAN_ABBREVIATIONS,
+            Language::Spanish => SPANISH_ABBREVIATIONS,
+            _ => &[],
+        };
+        Self::new(abbreviations)
+    }
+
+    fn is_abbreviation(&self, word: &str) -> bool {
+        let word = word.trim_start_matches(|character: char| !character.is_alphanumeric());
+        let mut characters = word.chars();
+        // Single-letter initials (e.g. "J. R. R. Tolkien")
+        if let (Some(first), Some('.'), None) =
+            (characters.next(), characters.next(), characters.next())
+        {
+            if first.is_uppercase() {
+                return true;
+            }
+        }
+        self.abbreviations.contains(&word.to_lowercase())
+    }
+}
+
+impl Default for PunctuationSentenceSplitter {
+    fn default() -> Self {
+        Self::for_language(Language::English)
+    }
+}
+
+impl SentenceSplitter for PunctuationSentenceSplitter {
+    fn sentence_spans(&self, text: &str) -> Vec<Range<usize>> {
+        let characters = text.char_indices().collect::<Vec<(usize, char)>>();
+        let mut spans = Vec::new();
+        let mut sentence_start = 0;
+        let mut position = 0;
+        while position < characters.len() {
+            let (_, character) = characters[position];
+            if !matches!(character, '.' | '!' | '?') {
+                position += 1;
+                continue;
+            }
+            let mut boundary = position + 1;
+            while boundary < characters.len()
+                && matches!(
+                    characters[boundary].1,
+                    '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '»' | '”' | '’'
+                )
+            {
+                boundary += 1;
+            }
+            let sentence_end = characters
+                .get(boundary)
+                .map_or(text.len(), |(offset, _)| *offset);
+            let followed_by_space =
+                boundary == characters.len() || characters[boundary].1.is_whitespace();
+            let is_sentence_end = followed_by_space && {
+                if (boundary - position) == 1 && character == '.' {
+                    let word_start = text[..sentence_end]
+                        .rfind(char::is_whitespace)
+                        .map_or(0, |offset| offset + 1);
+                    let next_character = text[sentence_end..]
+                        .chars()
+                        .find(|character| !character.is_whitespace());
+                    !self.is_abbreviation(&text[word_start..sentence_end])
+                        && !next_character.map_or(false, char::is_lowercase)
+                } else {
+                    true
+                }
+            };
+            if is_sentence_end {
+                push_trimmed_span(text, sentence_start..sentence_end, &mut spans);
+                sentence_start = sentence_end;
+            }
+            position = boundary;
+        }
+        push_trimmed_span(text, sentence_start..text.len(), &mut spans);
+        spans
+    }
+}
+
+fn push_trimmed_span(text: &str, span: Range<usize>, spans: &mut Vec<Range<usize>>) {
+    let sentence = &text[span.clone()];
+    let trimmed_start = sentence.trim_start();
+    let start = span.start + sentence.len() - trimmed_start.len();
+    let end = start + trimmed_start.trim_end().len();
+    if end > start {
+        spans.push(start..end);
+    }
+}
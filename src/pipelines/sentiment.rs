@@ -26,7 +26,7 @@
 //!     "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
 //!     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
 //! ];
-//! let output = sentiment_classifier.predict(&input);
+//! let output = sentiment_classifier.predict(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -55,16 +55,21 @@
 //! ```
 
 use crate::common::error::RustBertError;
+use crate::pipelines::common::{merge_empty_outputs, split_empty_inputs};
+use crate::pipelines::sentence_splitter::SentenceSplitter;
 use crate::pipelines::sequence_classification::{
     SequenceClassificationConfig, SequenceClassificationModel,
 };
 use serde::{Deserialize, Serialize};
+use tch::{Device, Kind};
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-/// Enum with the possible sentiment polarities. Note that the pre-trained SST2 model does not include neutral sentiment.
+/// Enum with the possible sentiment polarities. Note that the pre-trained SST2 model does not include neutral sentiment:
+/// the `Neutral` polarity is only returned for empty or whitespace-only inputs.
 pub enum SentimentPolarity {
     Positive,
     Negative,
+    Neutral,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,6 +81,17 @@ pub struct Sentiment {
     pub score: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+/// Sentiment returned by the model with the raw logits of the classification head (e.g. for calibration).
+pub struct DetailedSentiment {
+    /// Polarity of the sentiment
+    pub polarity: SentimentPolarity,
+    /// Confidence score (probability of the polarity)
+    pub score: f64,
+    /// Raw logits of all labels, indexed by label id (empty for empty or whitespace-only inputs)
+    pub logits: Vec<f64>,
+}
+
 pub type SentimentConfig = SequenceClassificationConfig;
 
 /// # SentimentClassifier to perform sentiment analysis
@@ -107,6 +123,21 @@ impl SentimentModel {
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `SequenceClassificationModel::unload`).
+    pub fn unload(self) {
+        self.sequence_classification_model.unload();
+    }
+
+    /// Loads the model weights if their loading was deferred (see `SequenceClassificationModel::warmup`).
+    pub fn warmup(&self) -> Result<(), RustBertError> {
+        self.sequence_classification_model.warmup()
+    }
+
+    /// Returns `true` if the model weights are loaded (see `SequenceClassificationModel::is_loaded`).
+    pub fn is_loaded(&self) -> bool {
+        self.sequence_classification_model.is_loaded()
+    }
+
     /// Extract sentiment form an array of text inputs
     ///
     /// # Arguments
@@ -114,7 +145,7 @@ impl SentimentModel {
     /// * `input` - `&[&str]` Array of texts to extract the sentiment from.
     ///
     /// # Returns
-    /// * `Vec<Sentiment>` Sentiments extracted from texts.
+    /// * `Result<Vec<Sentiment>, RustBertError>` Sentiments extracted from texts.
     ///
     /// # Example
     ///
@@ -130,15 +161,20 @@ impl SentimentModel {
     ///     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
     /// ];
     ///
-    /// let output = sentiment_classifier.predict(&input);
+    /// let output = sentiment_classifier.predict(&input)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn predict<'a, S>(&self, input: S) -> Vec<Sentiment>
+    pub fn predict<'a, S>(&self, input: S) -> Result<Vec<Sentiment>, RustBertError>
     where
         S: AsRef<[&'a str]>,
     {
-        let labels = self.sequence_classification_model.predict(input);
+        let (input, empty_inputs) = split_empty_inputs(input.as_ref());
+        let labels = if input.is_empty() {
+            vec![]
+        } else {
+            self.sequence_classification_model.predict(input)?
+        };
         let mut sentiments = Vec::with_capacity(labels.len());
         for label in labels {
             let polarity = if label.id == 1 {
@@ -151,7 +187,122 @@ impl SentimentModel {
                 score: label.score,
             })
         }
-        sentiments
+        Ok(merge_empty_outputs(sentiments, &empty_inputs, || {
+            Sentiment {
+                polarity: SentimentPolarity::Neutral,
+                score: 0.0,
+            }
+        }))
+    }
+
+    /// Extract sentiment form an array of text inputs, returning the raw logits of the model alongside the
+    /// probability of the predicted polarity. The probability is the softmax of the raw logits, the score temperature
+    /// of the configuration is not applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to extract the sentiment from.
+    ///
+    /// # Returns
+    /// * `Result<Vec<DetailedSentiment>, RustBertError>` Sentiments extracted from texts with their logits.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::sentiment::SentimentModel;
+    ///
+    /// let sentiment_classifier = SentimentModel::new(Default::default())?;
+    /// let output = sentiment_classifier.predict_detailed(&["Probably my all-time favorite movie."])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_detailed<'a, S>(&self, input: S) -> Result<Vec<DetailedSentiment>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let (input, empty_inputs) = split_empty_inputs(input.as_ref());
+        let mut sentiments = Vec::with_capacity(input.len());
+        if !input.is_empty() {
+            let logits = self
+                .sequence_classification_model
+                .forward_logits(&input)?
+                .to_kind(Kind::Double)
+                .to(Device::Cpu);
+            let probabilities = logits.softmax(-1, Kind::Double);
+            for sentence_idx in 0..input.len() as i64 {
+                let label_id = probabilities
+                    .get(sentence_idx)
+                    .argmax(-1, false)
+                    .int64_value(&[]);
+                let polarity = if label_id == 1 {
+                    SentimentPolarity::Positive
+                } else {
+                    SentimentPolarity::Negative
+                };
+                sentiments.push(DetailedSentiment {
+                    polarity,
+                    score: probabilities.double_value(&[sentence_idx, label_id]),
+                    logits: logits.get(sentence_idx).iter::<f64>()?.collect(),
+                });
+            }
+        }
+        Ok(merge_empty_outputs(sentiments, &empty_inputs, || {
+            DetailedSentiment {
+                polarity: SentimentPolarity::Neutral,
+                score: 0.0,
+                logits: vec![],
+            }
+        }))
+    }
+
+    /// Extract the sentiment of each sentence of a text (e.g. the individual opinions of a review)
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` Text to extract the sentence sentiments from.
+    /// * `sentence_splitter` - `&dyn SentenceSplitter` splitting the text into sentences.
+    ///
+    /// # Returns
+    /// * `Result<Vec<(String, Sentiment, f64)>, RustBertError>` Sentences of the text with their sentiment and their probability of being
+    /// positive (0.5 for the `Neutral` empty sentences), allowing to aggregate the sentiments of the sentences.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+    /// use rust_bert::pipelines::sentiment::SentimentModel;
+    ///
+    /// let sentiment_classifier = SentimentModel::new(Default::default())?;
+    /// let sentence_splitter = PunctuationSentenceSplitter::default();
+    ///
+    /// let output = sentiment_classifier.predict_sentences(
+    ///     "The screen is gorgeous. The battery barely lasts a day.",
+    ///     &sentence_splitter,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_sentences(
+        &self,
+        text: &str,
+        sentence_splitter: &dyn SentenceSplitter,
+    ) -> Result<Vec<(String, Sentiment, f64)>, RustBertError> {
+        let sentences = sentence_splitter.split(text);
+        let sentiments = self.predict(&sentences)?;
+        Ok(sentences
+            .into_iter()
+            .zip(sentiments)
+            .map(|(sentence, sentiment)| {
+                let positive_probability = match sentiment.polarity {
+                    SentimentPolarity::Positive => sentiment.score,
+                    SentimentPolarity::Negative => 1.0 - sentiment.score,
+                    SentimentPolarity::Neutral => 0.5,
+                };
+                (sentence.to_string(), sentiment, positive_probability)
+            })
+            .collect())
     }
 }
 #[cfg(test)]
@@ -159,9 +310,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = SentimentConfig::default();
-        let _: Box<dyn Send> = Box::new(SentimentModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(SentimentModel::new(config));
     }
 }
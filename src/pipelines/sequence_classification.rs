@@ -40,7 +40,7 @@
 //!     "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
 //!     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
 //! ];
-//! let output = sequence_classification_model.predict(&input);
+//! let output = sequence_classification_model.predict(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -66,7 +66,10 @@ use crate::distilbert::DistilBertModelClassifier;
 use crate::fnet::FNetForSequenceClassification;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    forward_in_mode, release_device_memory, set_num_threads, set_var_store_mode,
+    synchronize_device, ConfigOption, ModelType, TokenizerOption,
+};
 use crate::reformer::ReformerForSequenceClassification;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
@@ -76,8 +79,10 @@ use rust_tokenizers::TokenizedInput;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use tch::nn::VarStore;
-use tch::{nn, no_grad, Device, Kind, Tensor};
+use tch::{nn, Device, Kind, Tensor};
 
 use crate::deberta_v2::DebertaV2ForSequenceClassification;
 #[cfg(feature = "remote")]
@@ -106,13 +111,13 @@ pub struct SequenceClassificationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BERT model on CoNLL)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BERT model on CoNLL)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BERT model on CoNLL)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: None)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
     pub lower_case: bool,
     /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
@@ -121,6 +126,19 @@ pub struct SequenceClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
+    /// Temperature dividing the logits before the softmax, allowing calibration of the output scores (default: 1.0)
+    pub temperature: f64,
+    /// Defer the loading of the model weights until the first prediction or an explicit call to `warmup` (default: false).
+    /// The resources are still retrieved and validated when the model is created.
+    pub lazy_loading: bool,
+    /// Use non-blocking transfers of the inputs to the model device and of the outputs back to the CPU (default: false).
+    /// The inputs are copied to page-locked memory first, as copies from pageable memory are synchronous.
+    /// This allows overlapping host and device work on CUDA devices: the prediction methods synchronize the device
+    /// before reading their results, and `SequenceClassificationModel::synchronize` must be called before reading
+    /// tensors transferred from the output of `forward_logits`. Has no effect on CPU.
+    pub non_blocking_transfers: bool,
 }
 
 impl SequenceClassificationConfig {
@@ -145,9 +163,9 @@ impl SequenceClassificationConfig {
         add_prefix_space: impl Into<Option<bool>>,
     ) -> SequenceClassificationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         SequenceClassificationConfig {
             model_type,
@@ -159,6 +177,10 @@ impl SequenceClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
+            temperature: 1.0,
+            lazy_loading: false,
+            non_blocking_transfers: false,
         }
     }
 }
@@ -539,13 +561,30 @@ impl SequenceClassificationOption {
     }
 }
 
+#[allow(clippy::large_enum_variant)]
+/// Sequence classifier of a `SequenceClassificationModel`, whose creation and weights loading may be deferred
+enum ClassifierState {
+    Loaded {
+        sequence_classifier: SequenceClassificationOption,
+        var_store: VarStore,
+    },
+    Pending {
+        model_type: ModelType,
+        model_config: ConfigOption,
+        weights_path: PathBuf,
+    },
+}
+
 /// # SequenceClassificationModel for Classification (e.g. Sentiment Analysis)
 pub struct SequenceClassificationModel {
-    tokenizer: TokenizerOption,
-    sequence_classifier: SequenceClassificationOption,
+    tokenizer: Arc<TokenizerOption>,
+    classifier: RwLock<ClassifierState>,
     label_mapping: HashMap<i64, String>,
-    var_store: VarStore,
+    device: Device,
     max_length: usize,
+    temperature: f64,
+    train: bool,
+    non_blocking_transfers: bool,
 }
 
 impl SequenceClassificationModel {
@@ -568,15 +607,12 @@ impl SequenceClassificationModel {
     pub fn new(
         config: SequenceClassificationConfig,
     ) -> Result<SequenceClassificationModel, RustBertError> {
-        let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
             None
         };
-        let device = config.device;
 
         let tokenizer = TokenizerOption::from_file(
             config.model_type,
@@ -586,25 +622,242 @@ impl SequenceClassificationModel {
             config.strip_accents,
             config.add_prefix_space,
         )?;
-        let mut var_store = VarStore::new(device);
+        Self::new_with_tokenizer(config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `SequenceClassificationModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `SequenceClassificationConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::DistilBert,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let model = SequenceClassificationModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        config: SequenceClassificationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<SequenceClassificationModel, RustBertError> {
+        set_num_threads(config.num_threads)?;
+        tokenizer.validate_model_type(config.model_type)?;
+        let config_path = config.config_resource.get_local_path()?;
+        let weights_path = config.model_resource.get_local_path()?;
+        let device = config.device;
+
         let model_config = ConfigOption::from_file(config.model_type, config_path);
-        let max_length = model_config
-            .get_max_len()
-            .map(|v| v as usize)
-            .unwrap_or(usize::MAX);
-        let sequence_classifier =
-            SequenceClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
-        let label_mapping = model_config.get_label_mapping().clone();
-        var_store.load(weights_path)?;
+        let max_length = model_config.get_max_sequence_length().unwrap_or(usize::MAX);
+        let label_mapping = build_label_mapping(&model_config)?;
+        // With lazy loading, the model is neither created nor loaded until the first prediction
+        let classifier = if config.lazy_loading {
+            if !weights_path.is_file() {
+                return Err(RustBertError::IOError(format!(
+                    "Cannot find the model weights file {}",
+                    weights_path.display()
+                )));
+            }
+            ClassifierState::Pending {
+                model_type: config.model_type,
+                model_config,
+                weights_path,
+            }
+        } else {
+            let (sequence_classifier, var_store) = load_classifier(
+                config.model_type,
+                &model_config,
+                &weights_path,
+                device,
+                label_mapping.len(),
+                false,
+            )?;
+            ClassifierState::Loaded {
+                sequence_classifier,
+                var_store,
+            }
+        };
         Ok(SequenceClassificationModel {
             tokenizer,
-            sequence_classifier,
+            classifier: RwLock::new(classifier),
             label_mapping,
-            var_store,
+            device,
             max_length,
+            temperature: config.temperature,
+            train: false,
+            non_blocking_transfers: config.non_blocking_transfers,
         })
     }
 
+    /// Sets the model in training mode: dropout is applied, the variables of the model are unfrozen and
+    /// the forward pass tracks gradients, allowing to fine-tune the model from the output of `forward_logits`.
+    pub fn train(&mut self) {
+        self.set_mode(true);
+    }
+
+    /// Sets the model in evaluation mode (default): dropout is disabled, the variables of the model are
+    /// frozen and the forward pass runs under `no_grad`, so that no gradient-tracking tensor is created.
+    pub fn eval(&mut self) {
+        self.set_mode(false);
+    }
+
+    fn set_mode(&mut self, train: bool) {
+        self.train = train;
+        if let ClassifierState::Loaded { var_store, .. } = self.classifier.get_mut().unwrap() {
+            set_var_store_mode(var_store, train);
+        }
+    }
+
+    /// Returns `true` if the model is in training mode, `false` if it is in evaluation mode.
+    pub fn is_training(&self) -> bool {
+        self.train
+    }
+
+    /// Blocks until all pending operations on the model device are completed, including the non-blocking
+    /// transfers enabled by `SequenceClassificationConfig::non_blocking_transfers`. This is a no-op on CPU.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::sequence_classification::{
+    /// #     SequenceClassificationConfig, SequenceClassificationModel,
+    /// # };
+    /// # use tch::{Device, Kind};
+    /// let config = SequenceClassificationConfig {
+    ///     non_blocking_transfers: true,
+    ///     ..Default::default()
+    /// };
+    /// let sequence_classification_model = SequenceClassificationModel::new(config)?;
+    /// let logits = sequence_classification_model
+    ///     .forward_logits(&["Probably my all-time favorite movie."])?
+    ///     .to_device_(Device::Cpu, Kind::Float, true, false);
+    /// sequence_classification_model.synchronize();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn synchronize(&self) {
+        synchronize_device(self.device);
+    }
+
+    fn transfer(&self, tensor: &Tensor, device: Device) -> Tensor {
+        if !self.non_blocking_transfers {
+            return tensor.to(device);
+        }
+        // Copies from the host are only asynchronous from page-locked memory. Copies to the host are
+        // made to page-locked memory allocated by Torch.
+        let tensor = if tensor.device() == Device::Cpu && matches!(device, Device::Cuda(_)) {
+            tensor.pin_memory(device)
+        } else {
+            tensor.shallow_clone()
+        };
+        tensor.to_device_(device, tensor.kind(), true, false)
+    }
+
+    /// Moves the output of the model to the CPU, waiting for the completion of a non-blocking transfer
+    /// so that the output can be read.
+    fn output_to_cpu(&self, output: &Tensor) -> Tensor {
+        let output = self.transfer(output, Device::Cpu);
+        if self.non_blocking_transfers {
+            self.synchronize();
+        }
+        output
+    }
+
+    /// Creates the model and loads its weights if this was deferred (see `SequenceClassificationConfig::lazy_loading`).
+    /// This is done automatically on the first prediction, and can be called explicitly to avoid slowing
+    /// down the first request. Calling this method on a loaded model has no effect.
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` Error if the model cannot be created or its weights cannot be loaded
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::sequence_classification::{
+    ///     SequenceClassificationConfig, SequenceClassificationModel,
+    /// };
+    ///
+    /// let config = SequenceClassificationConfig {
+    ///     lazy_loading: true,
+    ///     ..Default::default()
+    /// };
+    /// let model = SequenceClassificationModel::new(config)?;
+    /// model.warmup()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn warmup(&self) -> Result<(), RustBertError> {
+        if self.is_loaded() {
+            return Ok(());
+        }
+        let mut classifier = self.classifier.write().unwrap();
+        if let ClassifierState::Pending {
+            model_type,
+            model_config,
+            weights_path,
+        } = &*classifier
+        {
+            let (sequence_classifier, var_store) = load_classifier(
+                *model_type,
+                model_config,
+                weights_path,
+                self.device,
+                self.label_mapping.len(),
+                self.train,
+            )?;
+            *classifier = ClassifierState::Loaded {
+                sequence_classifier,
+                var_store,
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the model is created and its weights are loaded, `false` if this is still pending
+    /// (see `SequenceClassificationConfig::lazy_loading`).
+    pub fn is_loaded(&self) -> bool {
+        matches!(
+            *self.classifier.read().unwrap(),
+            ClassifierState::Loaded { .. }
+        )
+    }
+
+    /// Returns the maximum sequence length supported by the model (see `ConfigOption::get_max_sequence_length`),
+    /// or `None` if the model has no limit.
+    pub fn max_sequence_length(&self) -> Option<usize> {
+        if self.max_length == usize::MAX {
+            None
+        } else {
+            Some(self.max_length)
+        }
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.device;
+        drop(self);
+        release_device_memory(device);
+    }
+
     fn prepare_for_model<'a, S>(&self, input: S) -> Tensor
     where
         S: AsRef<[&'a str]>,
@@ -631,7 +884,62 @@ impl SequenceClassificationModel {
                 Tensor::of_slice(&(input.token_ids))
             })
             .collect::<Vec<_>>();
-        Tensor::stack(tokenized_input_tensors.as_slice(), 0).to(self.var_store.device())
+        self.transfer(
+            &Tensor::stack(tokenized_input_tensors.as_slice(), 0),
+            self.device,
+        )
+    }
+
+    fn forward(&self, input_tensor: &Tensor) -> Result<Tensor, RustBertError> {
+        self.warmup()?;
+        let classifier = self.classifier.read().unwrap();
+        let sequence_classifier = match &*classifier {
+            ClassifierState::Loaded {
+                sequence_classifier,
+                ..
+            } => sequence_classifier,
+            ClassifierState::Pending { .. } => unreachable!("the model is loaded by warmup"),
+        };
+        Ok(forward_in_mode(self.train, |train| {
+            sequence_classifier.forward_t(Some(input_tensor), None, None, None, None, train)
+        }))
+    }
+
+    /// Runs the model on texts and returns its raw logits, before the temperature scaling and softmax applied by the
+    /// prediction methods (e.g. to apply custom operations without running the model a second time).
+    ///
+    /// This is an advanced API: the logits are returned on the device of the model (e.g. a GPU) and must be moved
+    /// with `Tensor::to` before being combined with tensors on another device or read on the CPU. The forward pass runs
+    /// under `no_grad` in evaluation mode (default), while the logits track gradients in training mode (see `train`).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Tensor, RustBertError>` logits of shape (*batch size*, *number of labels*), on the device of the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+    /// # use tch::Device;
+    /// let sequence_classification_model = SequenceClassificationModel::new(Default::default())?;
+    /// let input = ["Probably my all-time favorite movie."];
+    /// let logits = sequence_classification_model
+    ///     .forward_logits(&input)?
+    ///     .to(Device::Cpu);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn forward_logits<'a, S>(&self, input: S) -> Result<Tensor, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let input_tensor = self.prepare_for_model(input.as_ref());
+        self.forward(&input_tensor)
     }
 
     /// Classify texts
@@ -642,7 +950,8 @@ impl SequenceClassificationModel {
     ///
     /// # Returns
     ///
-    /// * `Vec<Label>` containing labels for input texts
+    /// * `Result<Vec<Label>, RustBertError>` containing labels for input texts, or an error if the weights of a
+    /// lazily loaded model cannot be loaded
     ///
     /// # Example
     ///
@@ -656,26 +965,20 @@ impl SequenceClassificationModel {
     ///     "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
     ///     "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
     /// ];
-    /// let output = sequence_classification_model.predict(&input);
+    /// let output = sequence_classification_model.predict(&input)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn predict<'a, S>(&self, input: S) -> Vec<Label>
+    pub fn predict<'a, S>(&self, input: S) -> Result<Vec<Label>, RustBertError>
     where
         S: AsRef<[&'a str]>,
     {
         let input_tensor = self.prepare_for_model(input.as_ref());
-        let output = no_grad(|| {
-            let output = self.sequence_classifier.forward_t(
-                Some(&input_tensor),
-                None,
-                None,
-                None,
-                None,
-                false,
-            );
-            output.softmax(-1, Kind::Float).detach().to(Device::Cpu)
-        });
+        let output = self.output_to_cpu(
+            &(self.forward(&input_tensor)? / self.temperature)
+                .softmax(-1, Kind::Float)
+                .detach(),
+        );
         let label_indices = output.as_ref().argmax(-1, true).squeeze_dim(1);
         let scores = output
             .gather(1, &label_indices.unsqueeze(-1), false)
@@ -698,7 +1001,61 @@ impl SequenceClassificationModel {
             };
             labels.push(label)
         }
-        labels
+        Ok(labels)
+    }
+
+    /// Classify texts, returning the scores of all labels for each text, ranked by decreasing score
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<Vec<Label>>, RustBertError>` containing all labels and their scores for each input text, ranked by
+    /// decreasing score, or an error if the weights of a lazily loaded model cannot be loaded
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::sequence_classification::SequenceClassificationModel;
+    ///
+    /// let sequence_classification_model = SequenceClassificationModel::new(Default::default())?;
+    /// let input = ["Probably my all-time favorite movie."];
+    /// let output = sequence_classification_model.predict_label_scores(&input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_label_scores<'a, S>(&self, input: S) -> Result<Vec<Vec<Label>>, RustBertError>
+    where
+        S: AsRef<[&'a str]>,
+    {
+        let input_tensor = self.prepare_for_model(input.as_ref());
+        let output = self.output_to_cpu(
+            &(self.forward(&input_tensor)? / self.temperature)
+                .softmax(-1, Kind::Float)
+                .detach(),
+        );
+
+        Ok((0..output.size()[0])
+            .map(|sentence_idx| {
+                let mut labels = output
+                    .get(sentence_idx)
+                    .iter::<f64>()
+                    .unwrap()
+                    .enumerate()
+                    .map(|(label_idx, score)| Label {
+                        text: self.label_mapping.get(&(label_idx as i64)).unwrap().clone(),
+                        score,
+                        id: label_idx as i64,
+                        sentence: sentence_idx as usize,
+                    })
+                    .collect::<Vec<Label>>();
+                labels.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+                labels
+            })
+            .collect())
     }
 
     /// Multi-label classification of texts
@@ -734,17 +1091,11 @@ impl SequenceClassificationModel {
         threshold: f64,
     ) -> Result<Vec<Vec<Label>>, RustBertError> {
         let input_tensor = self.prepare_for_model(input);
-        let output = no_grad(|| {
-            let output = self.sequence_classifier.forward_t(
-                Some(&input_tensor),
-                None,
-                None,
-                None,
-                None,
-                false,
-            );
-            output.sigmoid().detach().to(Device::Cpu)
-        });
+        let output = self.output_to_cpu(
+            &(self.forward(&input_tensor)? / self.temperature)
+                .sigmoid()
+                .detach(),
+        );
         let label_indices = output.as_ref().ge(threshold).nonzero();
 
         let mut labels: Vec<Vec<Label>> = vec![];
@@ -778,14 +1129,100 @@ impl SequenceClassificationModel {
     }
 }
 
+/// Creates a sequence classifier and loads its weights.
+fn load_classifier(
+    model_type: ModelType,
+    model_config: &ConfigOption,
+    weights_path: &Path,
+    device: Device,
+    num_labels: usize,
+    train: bool,
+) -> Result<(SequenceClassificationOption, VarStore), RustBertError> {
+    let mut var_store = VarStore::new(device);
+    let sequence_classifier =
+        SequenceClassificationOption::new(model_type, var_store.root(), model_config)?;
+    if let Err(error) = var_store.load(weights_path) {
+        return Err(
+            match classifier_output_dim(weights_path, &var_store, num_labels) {
+                Some(output_dim) => RustBertError::InvalidConfigurationError(format!(
+                    "The classification head of the model weights has {} outputs but the configuration defines {} labels",
+                    output_dim, num_labels
+                )),
+                None => error.into(),
+            },
+        );
+    }
+    set_var_store_mode(&mut var_store, train);
+    Ok((sequence_classifier, var_store))
+}
+
+/// Returns the output dimension of the classification head stored in the weights file if it differs
+/// from the number of labels, i.e. the size of a weights tensor whose variable in the var store has
+/// `num_labels` outputs.
+fn classifier_output_dim(
+    weights_path: &Path,
+    var_store: &VarStore,
+    num_labels: usize,
+) -> Option<i64> {
+    let weights = Tensor::load_multi(weights_path).ok()?;
+    let variables = var_store.variables();
+    weights.iter().find_map(|(name, weight)| {
+        let expected_dim = *variables.get(name)?.size().first()?;
+        let output_dim = *weight.size().first()?;
+        if expected_dim == num_labels as i64 && output_dim != expected_dim {
+            Some(output_dim)
+        } else {
+            None
+        }
+    })
+}
+
+/// Builds the label mapping from the `id2label` dictionary of the configuration, falling back to
+/// generic `LABEL_{id}` labels if only `num_labels` is provided. The label ids must cover the
+/// `0..num_labels` range of the classification head outputs.
+fn build_label_mapping(model_config: &ConfigOption) -> Result<HashMap<i64, String>, RustBertError> {
+    let label_mapping = match (
+        model_config.get_label_mapping_opt(),
+        model_config.get_num_labels(),
+    ) {
+        (Some(id2label), Some(num_labels)) if id2label.len() as i64 != num_labels => {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The label dictionary (id2label) contains {} labels but num_labels is set to {}",
+                id2label.len(),
+                num_labels
+            )));
+        }
+        (Some(id2label), _) => id2label.clone(),
+        (None, Some(num_labels)) => (0..num_labels)
+            .map(|id| (id, format!("LABEL_{}", id)))
+            .collect(),
+        (None, None) => {
+            return Err(RustBertError::InvalidConfigurationError(
+                "No label dictionary (id2label) or num_labels provided in configuration file"
+                    .to_string(),
+            ));
+        }
+    };
+    if let Some(missing_id) =
+        (0..label_mapping.len() as i64).find(|id| !label_mapping.contains_key(id))
+    {
+        return Err(RustBertError::InvalidConfigurationError(format!(
+            "The label dictionary (id2label) does not contain the label id {}, expected ids from 0 to {}",
+            missing_id,
+            label_mapping.len() - 1
+        )));
+    }
+    Ok(label_mapping)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = SequenceClassificationConfig::default();
-        let _: Box<dyn Send> = Box::new(SequenceClassificationModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(SequenceClassificationModel::new(config));
     }
 }
@@ -47,7 +47,7 @@
 //! telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
 //! about exoplanets like K2-18b."];
 //!
-//! let output = model.summarize(&input);
+//! let output = model.summarize(&input)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -62,13 +62,24 @@
 //! # ;
 //! ```
 
-use tch::Device;
+use rust_tokenizers::tokenizer::{Tokenizer, TruncationStrategy};
+use rust_tokenizers::vocab::Vocab;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tch::{no_grad, Device, Kind, Tensor};
 
 use crate::bart::BartGenerator;
 use crate::common::error::RustBertError;
 use crate::pegasus::PegasusConditionalGenerator;
-use crate::pipelines::common::ModelType;
-use crate::pipelines::generation_utils::{GenerateConfig, LanguageGenerator};
+use crate::pipelines::common::{
+    merge_empty_outputs, normalize_whitespace, set_num_threads, split_empty_inputs,
+    truncate_to_word_boundary, ModelType, PaddingSide, TokenizerOption,
+};
+use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
+use crate::pipelines::generation_utils::{
+    ControlCharacters, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator, ScoreType,
+};
+use crate::pipelines::sentence_splitter::{PunctuationSentenceSplitter, SentenceSplitter};
 use crate::prophetnet::ProphetNetConditionalGenerator;
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
@@ -86,17 +97,19 @@ pub struct SummarizationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BART model on CNN-DM)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BART model on CNN-DM)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BART model on CNN-DM)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: pretrained BART model on CNN-DM)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Minimum sequence length (default: 0)
     pub min_length: i64,
     /// Maximum sequence length (default: 20)
     pub max_length: Option<i64>,
+    /// Maximum number of generated tokens, regardless of the length of the input. Takes precedence over `max_length` when set (default: None)
+    pub max_new_tokens: Option<i64>,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
     pub do_sample: bool,
     /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated (default: false)
@@ -105,24 +118,95 @@ pub struct SummarizationConfig {
     pub num_beams: i64,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
     pub temperature: f64,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature`. The last value persists for the steps past the end of the schedule (default: None)
+    pub temperature_schedule: Option<Vec<f64>>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling. If provided, the
+    /// temperature is raised towards `adaptive_temperature_max` with the fraction of repeated tokens in this window (default: None)
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature (default: 2.0)
+    pub adaptive_temperature_max: f64,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
     pub top_k: i64,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
     pub top_p: f64,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token, applied after the temperature (default: None)
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
     pub repetition_penalty: f64,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence, penalizing the tokens
+    /// repeated often more than the multiplicative `repetition_penalty` (default: 0.0)
+    pub frequency_penalty: f64,
+    /// Presence penalty subtracted from the logit of the tokens present in the sequence, regardless of their number of
+    /// occurrences (default: 0.0)
+    pub presence_penalty: f64,
     /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
     pub no_repeat_ngram_size: i64,
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking (default: None, the full sequence is considered)
+    pub repetition_window: Option<usize>,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
+    /// Score used to rank the sequences returned by beam search, and returned as their score (default: `ScoreType::Normalized`)
+    pub rank_by: ScoreType,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Strings terminating the generation of a sequence when generated (default: empty)
+    pub stop_strings: Vec<String>,
+    /// Opening and closing characters terminating the generation of a sequence once the brackets opened in the generated text
+    /// are balanced, accounting for nested brackets. Only supported by greedy decoding and sampling (default: None)
+    pub stop_brackets: Option<(char, char)>,
+    /// Bias added to the logits of the given token ids at every generation step (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled (default: 0)
+    pub greedy_prefix_len: usize,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection (default: false)
+    pub sanitize_logits: bool,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection, for the numerical stability of models producing extreme logits. Banned tokens are still excluded (default: None)
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance. Only supported by greedy decoding and sampling (default: None)
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)` (default: 0.0, disabled)
+    pub guidance_scale: f64,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention, trading quality for bounded memory. Only supported by GPT2 and T5 (default: None, unbounded cache)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache never evicted by the cache window (attention sink), counted within the window (default: 0)
+    pub kv_cache_sink_tokens: usize,
+    /// Device the key/value cache is kept on between generation steps, transferred to the model device at each step at the cost of a higher latency (default: None, the cache stays on the model device)
+    pub kv_cache_device: Option<Device>,
+    /// Flag indicating if special tokens should be removed from the decoded output (default: true)
+    pub skip_special_tokens: bool,
+    /// Placeholder replacing the unknown tokens in the decoded output, an empty placeholder drops them (default: None, unknown tokens are decoded as the other tokens)
+    pub unk_replacement: Option<String>,
+    /// Handling of the control characters (other than newlines and tabs) of the decoded output, e.g. to serialize it as JSON (default: None, control characters are kept)
+    pub control_characters: Option<ControlCharacters>,
+    /// Flag indicating if runs of whitespace characters (including newlines) in the decoded output should be collapsed
+    /// into single spaces, and leading and trailing whitespace trimmed (default: false)
+    pub normalize_whitespace: bool,
+    /// Flag indicating if a trailing sentence fragment lacking terminal punctuation (e.g. when the generation is
+    /// stopped by `max_length`) should be removed from the summaries, using a `PunctuationSentenceSplitter`.
+    /// Summaries made of a single fragment are preserved (default: false)
+    pub trim_incomplete_sentence: bool,
+    /// Maximum number of characters of the decoded output. Longer outputs are truncated at the last whitespace before
+    /// the limit so that no word is split (default: None, no truncation)
+    pub max_output_chars: Option<usize>,
+    /// Flag indicating if an ellipsis (`…`), counted in `max_output_chars`, should be appended to the truncated outputs (default: false)
+    pub output_ellipsis: bool,
+    /// Optional prefix prepended to each input text before tokenization. If not provided, the task
+    /// prefix expected by the model is used (`summarize: ` for T5) (default: None)
+    pub prefix: Option<String>,
+    /// Token id used as the first decoder input, overriding the decoder start token of the model configuration
+    /// (e.g. for fine-tuned models trained with a different decoder start token) (default: None)
+    pub decoder_start_token_id: Option<i64>,
+    /// Flag indicating if the model should output the attention weights, required to extract the
+    /// input keywords with `summarize_with_keywords` (default: false)
+    pub output_attentions: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
 }
 
 impl SummarizationConfig {
@@ -143,9 +227,9 @@ impl SummarizationConfig {
         merges_resource: Option<RV>,
     ) -> SummarizationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         SummarizationConfig {
             model_type,
@@ -155,19 +239,50 @@ impl SummarizationConfig {
             merges_resource: merges_resource.map(|r| Box::new(r) as Box<_>),
             min_length: 56,
             max_length: Some(142),
+            max_new_tokens: None,
             do_sample: false,
             early_stopping: true,
             num_beams: 3,
             temperature: 1.0,
+            temperature_schedule: None,
+            adaptive_temperature_window: None,
+            adaptive_temperature_max: 2.0,
             top_k: 50,
             top_p: 1.0,
+            min_p: None,
             repetition_penalty: 1.0,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 3,
+            repetition_window: None,
             num_return_sequences: 1,
+            rank_by: ScoreType::Normalized,
             num_beam_groups: None,
             diversity_penalty: None,
+            stop_strings: vec![],
+            stop_brackets: None,
+            logit_bias: HashMap::new(),
+            greedy_prefix_len: 0,
+            sanitize_logits: false,
+            logit_clamp: None,
+            negative_prompt: None,
+            guidance_scale: 0.0,
+            kv_cache_window: None,
+            kv_cache_sink_tokens: 0,
+            kv_cache_device: None,
+            skip_special_tokens: true,
+            unk_replacement: None,
+            control_characters: None,
+            normalize_whitespace: false,
+            trim_incomplete_sentence: false,
+            max_output_chars: None,
+            output_ellipsis: false,
+            prefix: None,
+            decoder_start_token_id: None,
+            output_attentions: false,
             device: Device::cuda_if_available(),
+            num_threads: None,
         }
     }
 }
@@ -196,18 +311,45 @@ impl From<SummarizationConfig> for GenerateConfig {
             vocab_resource: config.vocab_resource,
             min_length: config.min_length,
             max_length: config.max_length,
+            max_new_tokens: config.max_new_tokens,
             do_sample: config.do_sample,
             early_stopping: config.early_stopping,
             num_beams: config.num_beams,
             temperature: config.temperature,
+            temperature_schedule: config.temperature_schedule,
+            adaptive_temperature_window: config.adaptive_temperature_window,
+            adaptive_temperature_max: config.adaptive_temperature_max,
             top_k: config.top_k,
             top_p: config.top_p,
+            min_p: config.min_p,
             repetition_penalty: config.repetition_penalty,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            repetition_window: config.repetition_window,
             num_return_sequences: config.num_return_sequences,
+            rank_by: config.rank_by,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            stop_strings: config.stop_strings,
+            stop_brackets: config.stop_brackets,
+            logit_bias: config.logit_bias,
+            greedy_prefix_len: config.greedy_prefix_len,
+            sanitize_logits: config.sanitize_logits,
+            logit_clamp: config.logit_clamp,
+            negative_prompt: config.negative_prompt,
+            guidance_scale: config.guidance_scale,
+            kv_cache_window: config.kv_cache_window,
+            kv_cache_sink_tokens: config.kv_cache_sink_tokens,
+            kv_cache_device: config.kv_cache_device,
+            // Encoder-decoder models pad their inputs on the right
+            padding_side: PaddingSide::Right,
+            skip_special_tokens: config.skip_special_tokens,
+            keep_pad_tokens: false,
+            unk_replacement: config.unk_replacement,
+            control_characters: config.control_characters,
+            output_attentions: config.output_attentions,
             device: config.device,
         }
     }
@@ -245,6 +387,32 @@ impl SummarizationOption {
         }
     }
 
+    pub fn new_with_tokenizer(
+        config: SummarizationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<Self, RustBertError> {
+        tokenizer.validate_model_type(config.model_type)?;
+        match config.model_type {
+            ModelType::Bart => Ok(SummarizationOption::Bart(
+                BartGenerator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            ModelType::T5 => Ok(SummarizationOption::T5(T5Generator::new_with_tokenizer(
+                config.into(),
+                tokenizer,
+            )?)),
+            ModelType::ProphetNet => Ok(SummarizationOption::ProphetNet(
+                ProphetNetConditionalGenerator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            ModelType::Pegasus => Ok(SummarizationOption::Pegasus(
+                PegasusConditionalGenerator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Summarization not implemented for {:?}!",
+                config.model_type
+            ))),
+        }
+    }
+
     /// Returns the `ModelType` for this SummarizationOption
     pub fn model_type(&self) -> ModelType {
         match *self {
@@ -255,40 +423,181 @@ impl SummarizationOption {
         }
     }
 
+    /// Interface method to access tokenizer
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        match self {
+            Self::Bart(model_ref) => model_ref._get_tokenizer(),
+            Self::T5(model_ref) => model_ref._get_tokenizer(),
+            Self::ProphetNet(model_ref) => model_ref._get_tokenizer(),
+            Self::Pegasus(model_ref) => model_ref._get_tokenizer(),
+        }
+    }
+
+    /// Interface method to get_max_positions_embeddings() of the particular models.
+    pub fn get_max_positions_embeddings(&self) -> i64 {
+        match self {
+            Self::Bart(model_ref) => model_ref.get_max_positions_embeddings(),
+            Self::T5(model_ref) => model_ref.get_max_positions_embeddings(),
+            Self::ProphetNet(model_ref) => model_ref.get_max_positions_embeddings(),
+            Self::Pegasus(model_ref) => model_ref.get_max_positions_embeddings(),
+        }
+    }
+
+    /// Interface method to unload() of the particular models.
+    pub fn unload(self) {
+        match self {
+            Self::Bart(model) => model.unload(),
+            Self::T5(model) => model.unload(),
+            Self::ProphetNet(model) => model.unload(),
+            Self::Pegasus(model) => model.unload(),
+        }
+    }
+
     /// Interface method to generate() of the particular models.
-    pub fn generate<S>(&self, prompt_texts: Option<&[S]>) -> Vec<String>
+    pub fn generate<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        decoder_start_token_id: Option<i64>,
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        match *self {
+        let generate_options =
+            decoder_start_token_id.map(|decoder_start_token_id| GenerateOptions {
+                decoder_start_token_id: Some(decoder_start_token_id),
+                ..Default::default()
+            });
+        Ok(match *self {
             Self::Bart(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::ProphetNet(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::Pegasus(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, generate_options)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
+        })
+    }
+
+    /// Generates the summary of a single sequence of token ids and returns the generated token ids
+    /// along with the cross-attention weights of the decoder layers for the generated sequence.
+    fn generate_with_cross_attentions(
+        &self,
+        token_ids: &[i64],
+        decoder_start_token_id: Option<i64>,
+    ) -> Result<(Vec<i64>, Vec<Tensor>), RustBertError> {
+        let generate_options = GenerateOptions {
+            decoder_start_token_id,
+            num_return_sequences: Some(1),
+            ..Default::default()
+        };
+        let (generated_ids, cross_attentions) = match *self {
+            Self::Bart(ref model) => generate_and_forward(
+                model,
+                token_ids,
+                generate_options,
+                |input_ids, decoder_input_ids| {
+                    model
+                        .get_model()
+                        .forward_t(
+                            Some(input_ids),
+                            None,
+                            None,
+                            Some(decoder_input_ids),
+                            None,
+                            None,
+                            false,
+                        )
+                        .all_cross_attentions
+                },
+            )?,
+            Self::Pegasus(ref model) => generate_and_forward(
+                model,
+                token_ids,
+                generate_options,
+                |input_ids, decoder_input_ids| {
+                    model
+                        .get_model()
+                        .forward_t(
+                            Some(input_ids),
+                            None,
+                            None,
+                            Some(decoder_input_ids),
+                            None,
+                            None,
+                            false,
+                        )
+                        .all_cross_attentions
+                },
+            )?,
+            Self::T5(_) | Self::ProphetNet(_) => {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "Keywords extraction is not supported for {:?} summarization models",
+                    self.model_type()
+                )));
+            }
+        };
+        match cross_attentions {
+            Some(cross_attentions) => Ok((generated_ids, cross_attentions)),
+            None => Err(RustBertError::InvalidConfigurationError(
+                "Keywords extraction requires the summarization model to be created with `output_attentions` set to true"
+                    .to_string(),
+            )),
         }
     }
 }
 
+/// Generates the summary of a single sequence of token ids, then runs `forward` without gradients on the
+/// input ids and the decoder input ids of the generated sequence (shifted right), returning its output
+/// along with the generated token ids.
+fn generate_and_forward<T, V, U, G, O>(
+    model: &G,
+    token_ids: &[i64],
+    generate_options: GenerateOptions,
+    forward: impl FnOnce(&Tensor, &Tensor) -> O,
+) -> Result<(Vec<i64>, O), RustBertError>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: LanguageGenerator<T, V, U>,
+{
+    let input_ids = Tensor::of_slice(token_ids)
+        .unsqueeze(0)
+        .to(model.get_var_store().device());
+    let generated_ids = model
+        .generate_from_ids_and_past(input_ids.copy(), None, Some(generate_options))?
+        .remove(0)
+        .indices;
+    let decoder_input_ids = Tensor::of_slice(&generated_ids[..generated_ids.len() - 1])
+        .unsqueeze(0)
+        .to(input_ids.device());
+    let output = no_grad(|| forward(&input_ids, &decoder_input_ids));
+    Ok((generated_ids, output))
+}
+
 /// # SummarizationModel to perform summarization
 pub struct SummarizationModel {
     model: SummarizationOption,
     prefix: Option<String>,
+    normalize_whitespace: bool,
+    trim_incomplete_sentence: bool,
+    max_output_chars: Option<usize>,
+    output_ellipsis: bool,
+    skip_special_tokens: bool,
+    decoder_start_token_id: Option<i64>,
 }
 
 impl SummarizationModel {
@@ -311,13 +620,101 @@ impl SummarizationModel {
     pub fn new(
         summarization_config: SummarizationConfig,
     ) -> Result<SummarizationModel, RustBertError> {
-        let prefix = match summarization_config.model_type {
-            ModelType::T5 => Some("summarize: ".to_string()),
-            _ => None,
-        };
-        let model = SummarizationOption::new(summarization_config)?;
+        Self::new_with_model(summarization_config, SummarizationOption::new)
+    }
+
+    /// Build a new `SummarizationModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources of the configuration are ignored, and an error is returned if the tokenizer
+    /// cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `summarization_config` - `SummarizationConfig` object containing the resource references (model, configuration), summarization options and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::summarization::SummarizationModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Bart,
+    ///     "path/to/vocab.json",
+    ///     Some("path/to/merges.txt"),
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let summarization_model = SummarizationModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        summarization_config: SummarizationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<SummarizationModel, RustBertError> {
+        Self::new_with_model(summarization_config, |config| {
+            SummarizationOption::new_with_tokenizer(config, tokenizer)
+        })
+    }
+
+    fn new_with_model<F>(
+        summarization_config: SummarizationConfig,
+        build_model: F,
+    ) -> Result<SummarizationModel, RustBertError>
+    where
+        F: FnOnce(SummarizationConfig) -> Result<SummarizationOption, RustBertError>,
+    {
+        set_num_threads(summarization_config.num_threads)?;
+        let prefix =
+            summarization_config
+                .prefix
+                .clone()
+                .or_else(|| match summarization_config.model_type {
+                    ModelType::T5 => Some("summarize: ".to_string()),
+                    _ => None,
+                });
+        let normalize_whitespace = summarization_config.normalize_whitespace;
+        let trim_incomplete_sentence = summarization_config.trim_incomplete_sentence;
+        let max_output_chars = summarization_config.max_output_chars;
+        let output_ellipsis = summarization_config.output_ellipsis;
+        let skip_special_tokens = summarization_config.skip_special_tokens;
+        let decoder_start_token_id = summarization_config.decoder_start_token_id;
+        let model = build_model(summarization_config)?;
+
+        Ok(SummarizationModel {
+            model,
+            prefix,
+            normalize_whitespace,
+            trim_incomplete_sentence,
+            max_output_chars,
+            output_ellipsis,
+            skip_special_tokens,
+            decoder_start_token_id,
+        })
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        self.model.unload();
+    }
+
+    /// Get a reference to the model tokenizer.
+    pub fn get_tokenizer(&self) -> &TokenizerOption {
+        self.model.get_tokenizer()
+    }
 
-        Ok(SummarizationModel { model, prefix })
+    /// Maximum number of tokens of a text to summarize, excluding the special tokens and the
+    /// task prefix added by the model.
+    pub(crate) fn max_input_tokens(&self) -> usize {
+        let prefix_length = self
+            .prefix
+            .as_ref()
+            .map_or(0, |prefix| self.get_tokenizer().tokenize(prefix).len());
+        (self.model.get_max_positions_embeddings() as usize).saturating_sub(prefix_length + 2)
     }
 
     /// Summarize texts provided
@@ -327,7 +724,7 @@ impl SummarizationModel {
     /// * `input` - `&[&str]` Array of texts to summarize.
     ///
     /// # Returns
-    /// * `Vec<String>` Summarized texts
+    /// * `Result<Vec<String>, RustBertError>` Summarized texts
     ///
     /// # Example
     ///
@@ -359,25 +756,185 @@ impl SummarizationModel {
     /// telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more
     /// about exoplanets like K2-18b."];
     ///
-    /// let output = model.summarize(&input);
+    /// let output = model.summarize(&input)?;
     /// # Ok(())
     /// # }
     /// ```
     /// (New sample credits: [WikiNews](https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b))
-    pub fn summarize<S>(&self, texts: &[S]) -> Vec<String>
+    pub fn summarize<S>(&self, texts: &[S]) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        match &self.prefix {
-            None => self.model.generate(Some(texts)),
-            Some(prefix) => {
-                let texts = texts
-                    .iter()
-                    .map(|text| format!("{}{}", prefix, text.as_ref()))
-                    .collect::<Vec<String>>();
-                self.model.generate(Some(&texts))
+        let (texts, empty_inputs) = split_empty_inputs(texts);
+        let mut summaries = if texts.is_empty() {
+            vec![]
+        } else {
+            match &self.prefix {
+                None => self
+                    .model
+                    .generate(Some(&texts), self.decoder_start_token_id)?,
+                Some(prefix) => {
+                    let texts = texts
+                        .iter()
+                        .map(|text| format!("{}{}", prefix, text))
+                        .collect::<Vec<String>>();
+                    self.model
+                        .generate(Some(&texts), self.decoder_start_token_id)?
+                }
+            }
+        };
+        if self.normalize_whitespace {
+            summaries = summaries
+                .iter()
+                .map(|summary| normalize_whitespace(summary))
+                .collect();
+        }
+        if self.trim_incomplete_sentence {
+            let sentence_splitter = PunctuationSentenceSplitter::default();
+            summaries = summaries
+                .iter()
+                .map(|summary| trim_incomplete_sentence(summary, &sentence_splitter))
+                .collect();
+        }
+        if let Some(max_output_chars) = self.max_output_chars {
+            summaries = summaries
+                .iter()
+                .map(|summary| {
+                    truncate_to_word_boundary(summary, max_output_chars, self.output_ellipsis)
+                })
+                .collect();
+        }
+        Ok(merge_empty_outputs(summaries, &empty_inputs, String::new))
+    }
+
+    /// Summarize texts provided and returns the `top_k` input tokens receiving the most cross-attention
+    /// from the generated summary as keywords, with their attention scores (averaged over the layers, heads and
+    /// summary positions, and summed over the occurrences of a token). Special tokens and tokens without
+    /// alphanumeric characters are ignored.
+    /// The model must be created with `output_attentions` set to true in its `SummarizationConfig`.
+    /// Only BART and Pegasus models are supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to summarize.
+    /// * `top_k` - `usize` Number of keywords to return for each text
+    ///
+    /// # Returns
+    /// * `Vec<(String, Vec<(String, f32)>)>` Summarized texts and their keywords, ranked by decreasing score
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+    ///
+    /// let summarization_config = SummarizationConfig {
+    ///     output_attentions: true,
+    ///     ..Default::default()
+    /// };
+    /// let model = SummarizationModel::new(summarization_config)?;
+    ///
+    /// let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+    /// from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+    /// from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+    /// a planet circling a star in the constellation Leo."];
+    ///
+    /// let output = model.summarize_with_keywords(&input, 5)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summarize_with_keywords<S>(
+        &self,
+        texts: &[S],
+        top_k: usize,
+    ) -> Result<Vec<(String, Vec<(String, f32)>)>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let tokenizer = self.get_tokenizer();
+        let max_length = self.model.get_max_positions_embeddings() as usize;
+        let mut output = Vec::with_capacity(texts.len());
+        for text in texts {
+            if text.as_ref().trim().is_empty() {
+                output.push((String::new(), vec![]));
+                continue;
+            }
+            let text = match &self.prefix {
+                None => text.as_ref().to_string(),
+                Some(prefix) => format!("{}{}", prefix, text.as_ref()),
+            };
+            let source_ids = tokenizer
+                .encode_pair(
+                    &text,
+                    None,
+                    max_length,
+                    &TruncationStrategy::LongestFirst,
+                    0,
+                )
+                .token_ids;
+            let (generated_ids, cross_attentions) = self
+                .model
+                .generate_with_cross_attentions(&source_ids, self.decoder_start_token_id)?;
+
+            // Average the attention weights over layers, heads and summary positions: (source length)
+            let token_scores = Tensor::stack(&cross_attentions, 0)
+                .mean_dim([0, 2, 3].as_slice(), false, Kind::Float)
+                .get(0)
+                .iter::<f64>()?
+                .collect::<Vec<f64>>();
+            let mut keyword_scores: HashMap<String, f32> = HashMap::new();
+            for (token_id, score) in source_ids.iter().zip(token_scores) {
+                if tokenizer.is_special_token_id(*token_id) {
+                    continue;
+                }
+                let token = tokenizer
+                    .decode(&[*token_id], true, false)
+                    .trim()
+                    .to_string();
+                if token.chars().any(char::is_alphanumeric) {
+                    *keyword_scores.entry(token).or_insert(0f32) += score as f32;
+                }
+            }
+            let mut keywords = keyword_scores.into_iter().collect::<Vec<(String, f32)>>();
+            keywords.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            keywords.truncate(top_k);
+
+            let mut summary = tokenizer.decode(&generated_ids, self.skip_special_tokens, true);
+            if self.normalize_whitespace {
+                summary = normalize_whitespace(&summary);
+            }
+            if self.trim_incomplete_sentence {
+                summary =
+                    trim_incomplete_sentence(&summary, &PunctuationSentenceSplitter::default());
+            }
+            if let Some(max_output_chars) = self.max_output_chars {
+                summary =
+                    truncate_to_word_boundary(&summary, max_output_chars, self.output_ellipsis);
+            }
+            output.push((summary, keywords));
+        }
+        Ok(output)
+    }
+}
+
+/// Removes the last sentence of a summary if it does not end with a terminal punctuation mark,
+/// unless the summary is made of this single sentence.
+fn trim_incomplete_sentence(summary: &str, sentence_splitter: &dyn SentenceSplitter) -> String {
+    let sentence_spans = sentence_splitter.sentence_spans(summary);
+    match sentence_spans.as_slice() {
+        [.., previous_sentence, last_sentence] => {
+            let is_complete = summary[last_sentence.clone()]
+                .trim_end_matches(|character| {
+                    matches!(character, '"' | '\'' | ')' | ']' | '»' | '”' | '’')
+                })
+                .ends_with(|character| matches!(character, '.' | '!' | '?'));
+            if is_complete {
+                summary.to_string()
+            } else {
+                summary[..previous_sentence.end].to_string()
             }
         }
+        _ => summary.to_string(),
     }
 }
 
@@ -386,9 +943,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = SummarizationConfig::default();
-        let _: Box<dyn Send> = Box::new(SummarizationModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(SummarizationModel::new(config));
     }
 }
@@ -31,15 +31,28 @@
 //!
 //! Customized text generation models models can be loaded by overwriting the resources in the configuration.
 //! The dependencies will be downloaded to the user's home directory, e.g. under ~/.cache/.rustbert/gpt2
-use tch::Device;
+use std::borrow::Borrow;
+use std::cmp::min;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rust_tokenizers::tokenizer::Tokenizer;
+use rust_tokenizers::vocab::Vocab;
+use tch::{no_grad, Device, Kind, Tensor};
 
 use crate::common::error::RustBertError;
 use crate::gpt2::GPT2Generator;
 use crate::gpt_neo::GptNeoGenerator;
 use crate::openai_gpt::OpenAIGenerator;
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    normalize_whitespace, set_num_threads, truncate_to_word_boundary,
+    truncate_tokens_to_byte_fallback_boundary, ModelType, PaddingSide, TokenizerOption,
+};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    Cache, ControlCharacters, GenerateConfig, GenerateOptions, GeneratedIndicesOutput, LMHeadModel,
+    LMModelOutput, LanguageGenerator, ScoreType,
+};
 use crate::reformer::ReformerGenerator;
 use crate::resources::ResourceProvider;
 use crate::xlnet::XLNetGenerator;
@@ -57,17 +70,19 @@ pub struct TextGenerationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BART model on CNN-DM)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BART model on CNN-DM)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BART model on CNN-DM)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: pretrained BART model on CNN-DM)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Minimum sequence length (default: 0)
     pub min_length: i64,
     /// Maximum sequence length (default: 56)
     pub max_length: Option<i64>,
+    /// Maximum number of generated tokens, regardless of the length of the input. Takes precedence over `max_length` when set (default: None)
+    pub max_new_tokens: Option<i64>,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
     pub do_sample: bool,
     /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated (default: false)
@@ -76,24 +91,88 @@ pub struct TextGenerationConfig {
     pub num_beams: i64,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
     pub temperature: f64,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature`. The last value persists for the steps past the end of the schedule (default: None)
+    pub temperature_schedule: Option<Vec<f64>>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling. If provided, the
+    /// temperature is raised towards `adaptive_temperature_max` with the fraction of repeated tokens in this window (default: None)
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature (default: 2.0)
+    pub adaptive_temperature_max: f64,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
     pub top_k: i64,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
     pub top_p: f64,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token, applied after the temperature (default: None)
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
     pub repetition_penalty: f64,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence, penalizing the tokens
+    /// repeated often more than the multiplicative `repetition_penalty` (default: 0.0)
+    pub frequency_penalty: f64,
+    /// Presence penalty subtracted from the logit of the tokens present in the sequence, regardless of their number of
+    /// occurrences (default: 0.0)
+    pub presence_penalty: f64,
     /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature and will prevent repeats of n-grams with a length equal or greater to this value (default: 0)
     pub no_repeat_ngram_size: i64,
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking (default: None, the full sequence is considered)
+    pub repetition_window: Option<usize>,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
+    /// Score used to rank the sequences returned by beam search, and returned as their score (default: `ScoreType::Normalized`)
+    pub rank_by: ScoreType,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Strings terminating the generation of a sequence when generated (default: empty)
+    pub stop_strings: Vec<String>,
+    /// Opening and closing characters terminating the generation of a sequence once the brackets opened in the generated text
+    /// are balanced, accounting for nested brackets. Only supported by greedy decoding and sampling (default: None)
+    pub stop_brackets: Option<(char, char)>,
+    /// Bias added to the logits of the given token ids at every generation step (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled (default: 0)
+    pub greedy_prefix_len: usize,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection (default: false)
+    pub sanitize_logits: bool,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection, for the numerical stability of models producing extreme logits. Banned tokens are still excluded (default: None)
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance. Only supported by greedy decoding and sampling, an `InvalidConfigurationError` being returned for beam search (default: None)
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)` (default: 0.0, disabled)
+    pub guidance_scale: f64,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention, trading quality for bounded memory. Only supported by GPT2 and T5 (default: None, unbounded cache)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache never evicted by the cache window (attention sink), counted within the window (default: 0)
+    pub kv_cache_sink_tokens: usize,
+    /// Device the key/value cache is kept on between generation steps, transferred to the model device at each step at the cost of a higher latency (default: None, the cache stays on the model device)
+    pub kv_cache_device: Option<Device>,
+    /// Flag indicating if special tokens should be removed from the decoded output (default: true)
+    pub skip_special_tokens: bool,
+    /// Flag indicating if the padding tokens should be kept in the decoded output, e.g. for debugging. Padding tokens
+    /// are otherwise always removed, even if the other special tokens are kept (default: false)
+    pub keep_pad_tokens: bool,
+    /// Placeholder replacing the unknown tokens in the decoded output, an empty placeholder drops them (default: None, unknown tokens are decoded as the other tokens)
+    pub unk_replacement: Option<String>,
+    /// Handling of the control characters (other than newlines and tabs) of the decoded output, e.g. to serialize it as JSON (default: None, control characters are kept)
+    pub control_characters: Option<ControlCharacters>,
+    /// Flag indicating if runs of whitespace characters (including newlines) in the decoded output should be collapsed
+    /// into single spaces, and leading and trailing whitespace trimmed (default: false)
+    pub normalize_whitespace: bool,
+    /// Maximum number of characters of the generated text. Longer texts are truncated at the last whitespace before
+    /// the limit so that no word is split. The prompt returned with `return_full_text` is not counted in the limit
+    /// and is not truncated (default: None, no truncation)
+    pub max_output_chars: Option<usize>,
+    /// Flag indicating if an ellipsis (`…`), counted in `max_output_chars`, should be appended to the truncated outputs (default: false)
+    pub output_ellipsis: bool,
+    /// Flag indicating if the decoded output should include the prompt, or only the generated tokens (default: true)
+    pub return_full_text: bool,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
 }
 
 impl TextGenerationConfig {
@@ -114,9 +193,9 @@ impl TextGenerationConfig {
         merges_resource: Option<RV>,
     ) -> TextGenerationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         TextGenerationConfig {
             model_type,
@@ -126,19 +205,48 @@ impl TextGenerationConfig {
             merges_resource: merges_resource.map(|r| Box::new(r) as Box<_>),
             min_length: 0,
             max_length: Some(56),
+            max_new_tokens: None,
             do_sample: true,
             early_stopping: true,
             num_beams: 5,
             temperature: 1.0,
+            temperature_schedule: None,
+            adaptive_temperature_window: None,
+            adaptive_temperature_max: 2.0,
             top_k: 0,
             top_p: 0.9,
+            min_p: None,
             repetition_penalty: 1.0,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 0,
+            repetition_window: None,
             num_return_sequences: 1,
+            rank_by: ScoreType::Normalized,
             num_beam_groups: None,
             diversity_penalty: None,
+            stop_strings: vec![],
+            stop_brackets: None,
+            logit_bias: HashMap::new(),
+            greedy_prefix_len: 0,
+            sanitize_logits: false,
+            logit_clamp: None,
+            negative_prompt: None,
+            guidance_scale: 0.0,
+            kv_cache_window: None,
+            kv_cache_sink_tokens: 0,
+            kv_cache_device: None,
+            skip_special_tokens: true,
+            keep_pad_tokens: false,
+            unk_replacement: None,
+            control_characters: None,
+            normalize_whitespace: false,
+            max_output_chars: None,
+            output_ellipsis: false,
+            return_full_text: true,
             device: Device::cuda_if_available(),
+            num_threads: None,
         }
     }
 }
@@ -167,18 +275,44 @@ impl From<TextGenerationConfig> for GenerateConfig {
             vocab_resource: config.vocab_resource,
             min_length: config.min_length,
             max_length: config.max_length,
+            max_new_tokens: config.max_new_tokens,
             do_sample: config.do_sample,
             early_stopping: config.early_stopping,
             num_beams: config.num_beams,
             temperature: config.temperature,
+            temperature_schedule: config.temperature_schedule,
+            adaptive_temperature_window: config.adaptive_temperature_window,
+            adaptive_temperature_max: config.adaptive_temperature_max,
             top_k: config.top_k,
             top_p: config.top_p,
+            min_p: config.min_p,
             repetition_penalty: config.repetition_penalty,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            repetition_window: config.repetition_window,
             num_return_sequences: config.num_return_sequences,
+            rank_by: config.rank_by,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            stop_strings: config.stop_strings,
+            stop_brackets: config.stop_brackets,
+            logit_bias: config.logit_bias,
+            greedy_prefix_len: config.greedy_prefix_len,
+            sanitize_logits: config.sanitize_logits,
+            logit_clamp: config.logit_clamp,
+            negative_prompt: config.negative_prompt,
+            guidance_scale: config.guidance_scale,
+            kv_cache_window: config.kv_cache_window,
+            kv_cache_sink_tokens: config.kv_cache_sink_tokens,
+            kv_cache_device: config.kv_cache_device,
+            padding_side: PaddingSide::Left,
+            skip_special_tokens: config.skip_special_tokens,
+            keep_pad_tokens: config.keep_pad_tokens,
+            unk_replacement: config.unk_replacement,
+            control_characters: config.control_characters,
+            output_attentions: false,
             device: config.device,
         }
     }
@@ -234,6 +368,57 @@ impl TextGenerationOption {
         }
     }
 
+    /// Interface method to unload() of the particular models.
+    pub fn unload(self) {
+        match self {
+            Self::GPT(model) => model.unload(),
+            Self::GPT2(model) => model.unload(),
+            Self::GPTNeo(model) => model.unload(),
+            Self::XLNet(model) => model.unload(),
+            Self::Reformer(model) => model.unload(),
+        }
+    }
+
+    /// Interface method to compute_perplexity() of the particular models.
+    pub fn perplexity(&self, text: &str) -> Result<f64, RustBertError> {
+        match *self {
+            Self::GPT(ref model) => model.compute_perplexity(text),
+            Self::GPT2(ref model) => model.compute_perplexity(text),
+            Self::GPTNeo(ref model) => model.compute_perplexity(text),
+            Self::Reformer(ref model) => model.compute_perplexity(text),
+            Self::XLNet(_) => Err(RustBertError::InvalidConfigurationError(
+                "Perplexity computation is only available for causal language models, not for XLNet"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Interface method to compute_continuation_log_probabilities() of the particular models.
+    pub fn continuation_log_probabilities(
+        &self,
+        prompt: &str,
+        continuation: &str,
+    ) -> Result<Vec<f64>, RustBertError> {
+        match *self {
+            Self::GPT(ref model) => {
+                model.compute_continuation_log_probabilities(prompt, continuation)
+            }
+            Self::GPT2(ref model) => {
+                model.compute_continuation_log_probabilities(prompt, continuation)
+            }
+            Self::GPTNeo(ref model) => {
+                model.compute_continuation_log_probabilities(prompt, continuation)
+            }
+            Self::Reformer(ref model) => {
+                model.compute_continuation_log_probabilities(prompt, continuation)
+            }
+            Self::XLNet(_) => Err(RustBertError::InvalidConfigurationError(
+                "Continuation scoring is only available for causal language models, not for XLNet"
+                    .to_string(),
+            )),
+        }
+    }
+
     /// Interface method to access tokenizer
     pub fn get_tokenizer(&self) -> &TokenizerOption {
         match self {
@@ -251,7 +436,7 @@ impl TextGenerationOption {
         prompt_texts: Option<&[S]>,
         min_length: Option<i64>,
         max_length: Option<i64>,
-    ) -> Vec<Vec<i64>>
+    ) -> Result<Vec<GeneratedIndicesOutput>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
@@ -261,31 +446,11 @@ impl TextGenerationOption {
             ..Default::default()
         });
         match *self {
-            Self::GPT(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
-                .into_iter()
-                .map(|output| output.indices)
-                .collect(),
-            Self::GPT2(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
-                .into_iter()
-                .map(|output| output.indices)
-                .collect(),
-            Self::GPTNeo(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
-                .into_iter()
-                .map(|output| output.indices)
-                .collect(),
-            Self::XLNet(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
-                .into_iter()
-                .map(|output| output.indices)
-                .collect(),
-            Self::Reformer(ref model) => model
-                .generate_indices(prompt_texts, generate_options)
-                .into_iter()
-                .map(|output| output.indices)
-                .collect(),
+            Self::GPT(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::GPT2(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::GPTNeo(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::XLNet(ref model) => model.generate_indices(prompt_texts, generate_options),
+            Self::Reformer(ref model) => model.generate_indices(prompt_texts, generate_options),
         }
     }
 
@@ -318,6 +483,309 @@ impl TextGenerationOption {
             Self::Reformer(model_ref) => model_ref.set_device(device),
         }
     }
+
+    fn get_config(&self) -> &GenerateConfig {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_config(),
+            Self::GPT2(model_ref) => model_ref.get_config(),
+            Self::GPTNeo(model_ref) => model_ref.get_config(),
+            Self::XLNet(model_ref) => model_ref.get_config(),
+            Self::Reformer(model_ref) => model_ref.get_config(),
+        }
+    }
+
+    fn get_eos_ids(&self) -> Option<&Vec<i64>> {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_eos_ids(),
+            Self::GPT2(model_ref) => model_ref.get_eos_ids(),
+            Self::GPTNeo(model_ref) => model_ref.get_eos_ids(),
+            Self::XLNet(model_ref) => model_ref.get_eos_ids(),
+            Self::Reformer(model_ref) => model_ref.get_eos_ids(),
+        }
+    }
+
+    fn get_pad_id(&self) -> Option<i64> {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_pad_id(),
+            Self::GPT2(model_ref) => model_ref.get_pad_id(),
+            Self::GPTNeo(model_ref) => model_ref.get_pad_id(),
+            Self::XLNet(model_ref) => model_ref.get_pad_id(),
+            Self::Reformer(model_ref) => model_ref.get_pad_id(),
+        }
+    }
+
+    fn get_vocab_size(&self) -> i64 {
+        match self {
+            Self::GPT(model_ref) => model_ref.get_vocab_size(),
+            Self::GPT2(model_ref) => model_ref.get_vocab_size(),
+            Self::GPTNeo(model_ref) => model_ref.get_vocab_size(),
+            Self::XLNet(model_ref) => model_ref.get_vocab_size(),
+            Self::Reformer(model_ref) => model_ref.get_vocab_size(),
+        }
+    }
+
+    /// Runs the language model on a single sequence of token ids following the positions stored in the cache,
+    /// returning the logits of these token ids as a tensor of shape (*sequence_length*, *vocab_size*) on the CPU,
+    /// and the cache extended with these positions.
+    fn forward_with_cache(
+        &self,
+        token_ids: &[i64],
+        cache: Cache,
+    ) -> Result<(Tensor, Cache), RustBertError> {
+        let output = match *self {
+            Self::GPT(ref model) => causal_lm_forward(model, token_ids, cache),
+            Self::GPT2(ref model) => causal_lm_forward(model, token_ids, cache),
+            Self::GPTNeo(ref model) => causal_lm_forward(model, token_ids, cache),
+            // The Reformer cache cannot be truncated to discard rejected tokens
+            Self::Reformer(ref model) => {
+                causal_lm_forward(model, token_ids, Cache::None).map(|output| LMModelOutput {
+                    cache: Cache::None,
+                    ..output
+                })
+            }
+            Self::XLNet(_) => Err(RustBertError::InvalidConfigurationError(
+                "Speculative decoding is only available for causal language models, not for XLNet"
+                    .to_string(),
+            )),
+        }?;
+        Ok((
+            output.lm_logits.get(0).to_kind(Kind::Float).to(Device::Cpu),
+            output.cache,
+        ))
+    }
+}
+
+fn causal_lm_forward<T, V, U, G>(
+    model: &G,
+    token_ids: &[i64],
+    cache: Cache,
+) -> Result<LMModelOutput, RustBertError>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: PrivateLanguageGenerator<T, V, U>,
+{
+    let input_ids = Tensor::of_slice(token_ids)
+        .unsqueeze(0)
+        .to(model.get_var_store().device());
+    no_grad(|| {
+        model.get_model().forward_t(
+            Some(&input_ids),
+            cache,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    })
+}
+
+/// Cache of a model for speculative decoding, holding the key/value states of the first `length` tokens of the sequence.
+struct SpeculativeCache {
+    cache: Cache,
+    length: usize,
+}
+
+impl SpeculativeCache {
+    fn new() -> Self {
+        SpeculativeCache {
+            cache: Cache::None,
+            length: 0,
+        }
+    }
+
+    /// Runs the model on the tokens of the sequence that are not cached yet, returning their logits.
+    fn forward(
+        &mut self,
+        model: &TextGenerationOption,
+        token_ids: &[i64],
+    ) -> Result<Tensor, RustBertError> {
+        let cache = std::mem::replace(&mut self.cache, Cache::None);
+        let (logits, cache) = model.forward_with_cache(&token_ids[self.length..], cache)?;
+        // Models without cache (e.g. GPT) process the full sequence at every step
+        self.length = match cache {
+            Cache::None => 0,
+            _ => token_ids.len(),
+        };
+        self.cache = cache;
+        Ok(logits)
+    }
+
+    /// Drops the cached positions after the first `length` tokens, e.g. the positions of rejected speculated tokens.
+    /// The cache is reset if it does not support truncation.
+    fn truncate(&mut self, length: usize) {
+        if self.length > length {
+            if self.cache.truncate_to_length(length as i64).is_ok() {
+                self.length = length;
+            } else {
+                self.cache = Cache::None;
+                self.length = 0;
+            }
+        }
+    }
+}
+
+/// Returns the tokens that would repeat an n-gram of the sequence if generated next.
+fn banned_ngram_tokens(
+    token_ids: &[i64],
+    no_repeat_ngram_size: usize,
+    repetition_window: Option<usize>,
+) -> Vec<i64> {
+    if no_repeat_ngram_size == 0 || token_ids.len() + 1 < no_repeat_ngram_size {
+        return vec![];
+    }
+    let window_start = repetition_window.map_or(0, |repetition_window| {
+        token_ids.len().saturating_sub(repetition_window)
+    });
+    let query = &token_ids[token_ids.len() + 1 - no_repeat_ngram_size..];
+    token_ids[window_start..]
+        .windows(no_repeat_ngram_size)
+        .filter(|ngram| &ngram[..no_repeat_ngram_size - 1] == query)
+        .map(|ngram| ngram[no_repeat_ngram_size - 1])
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateSegment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// # Continuation score
+/// Log-probability assigned by the model to the tokens of a continuation given a prompt.
+#[derive(Debug, Clone, Copy)]
+pub struct ContinuationScore {
+    /// Sum of the log-probabilities of the continuation tokens
+    pub sum_log_probability: f64,
+    /// Mean of the log-probabilities of the continuation tokens, comparable across continuations of different lengths
+    pub mean_log_probability: f64,
+    /// Number of tokens in the continuation
+    pub num_tokens: usize,
+}
+
+/// # Prompt template
+/// Prompt containing named placeholders (e.g. `{name}`) substituted by the provided values before tokenization.
+/// Literal braces are escaped by doubling them (`{{` and `}}`).
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// use rust_bert::pipelines::text_generation::PromptTemplate;
+/// use std::collections::HashMap;
+///
+/// let template = PromptTemplate::new("Translate {word} to {language}:")?;
+/// let variables = HashMap::from([("word", "cheese"), ("language", "French")]);
+///
+/// let prompt = template.render(&variables)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    segments: Vec<TemplateSegment>,
+    placeholders: Vec<String>,
+}
+
+impl PromptTemplate {
+    /// Parses a new `PromptTemplate`, returning an error if a placeholder is empty or a brace is not closed or escaped.
+    pub fn new(template: &str) -> Result<PromptTemplate, RustBertError> {
+        let mut segments = vec![];
+        let mut placeholders: Vec<String> = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(RustBertError::ValueError(format!(
+                                    "Unclosed placeholder in prompt template: {}",
+                                    template
+                                )));
+                            }
+                            Some(c) => name.push(c),
+                        }
+                    }
+                    let name = name.trim().to_string();
+                    if name.is_empty() {
+                        return Err(RustBertError::ValueError(format!(
+                            "Empty placeholder in prompt template: {}",
+                            template
+                        )));
+                    }
+                    if !text.is_empty() {
+                        segments.push(TemplateSegment::Text(std::mem::take(&mut text)));
+                    }
+                    if !placeholders.contains(&name) {
+                        placeholders.push(name.clone());
+                    }
+                    segments.push(TemplateSegment::Placeholder(name));
+                }
+                '}' => {
+                    return Err(RustBertError::ValueError(format!(
+                        "Unmatched closing brace in prompt template (literal braces must be escaped as `}}}}`): {}",
+                        template
+                    )));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(TemplateSegment::Text(text));
+        }
+        Ok(PromptTemplate {
+            segments,
+            placeholders,
+        })
+    }
+
+    /// Returns the names of the placeholders of the template, in order of first occurrence
+    pub fn placeholders(&self) -> &[String] {
+        &self.placeholders
+    }
+
+    /// Renders the template, substituting each placeholder by its value.
+    /// Returns an error if a value is missing for any of the placeholders (additional values are ignored).
+    pub fn render<K, V>(&self, variables: &HashMap<K, V>) -> Result<String, RustBertError>
+    where
+        K: Borrow<str> + Eq + Hash,
+        V: AsRef<str>,
+    {
+        let missing_placeholders = self
+            .placeholders
+            .iter()
+            .filter(|name| !variables.contains_key(name.as_str()))
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>();
+        if !missing_placeholders.is_empty() {
+            return Err(RustBertError::ValueError(format!(
+                "Missing values for the prompt template placeholders: {}",
+                missing_placeholders.join(", ")
+            )));
+        }
+        Ok(self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                TemplateSegment::Text(text) => text.as_str(),
+                TemplateSegment::Placeholder(name) => variables[name.as_str()].as_ref(),
+            })
+            .collect())
+    }
 }
 
 /// # TextGenerationModel to generate texts from a prompt
@@ -327,6 +795,14 @@ pub struct TextGenerationModel {
     prefix_length: Option<i64>,
     min_length: i64,
     max_length: Option<i64>,
+    skip_special_tokens: bool,
+    keep_pad_tokens: bool,
+    unk_replacement: Option<String>,
+    control_characters: Option<ControlCharacters>,
+    normalize_whitespace: bool,
+    max_output_chars: Option<usize>,
+    output_ellipsis: bool,
+    return_full_text: bool,
 }
 
 impl TextGenerationModel {
@@ -351,6 +827,7 @@ impl TextGenerationModel {
     pub fn new(
         generation_config: TextGenerationConfig,
     ) -> Result<TextGenerationModel, RustBertError> {
+        set_num_threads(generation_config.num_threads)?;
         let prefix = match generation_config.model_type {
             ModelType::XLNet => Some(
                 "In 1991, the remains of Russian Tsar Nicholas II and his family \
@@ -369,7 +846,20 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
         };
 
         let min_length = generation_config.min_length;
-        let max_length = generation_config.max_length;
+        // The number of generated tokens is capped by the generation config when `max_new_tokens` is set
+        let max_length = if generation_config.max_new_tokens.is_some() {
+            None
+        } else {
+            generation_config.max_length
+        };
+        let skip_special_tokens = generation_config.skip_special_tokens;
+        let keep_pad_tokens = generation_config.keep_pad_tokens;
+        let unk_replacement = generation_config.unk_replacement.clone();
+        let control_characters = generation_config.control_characters;
+        let normalize_whitespace = generation_config.normalize_whitespace;
+        let max_output_chars = generation_config.max_output_chars;
+        let output_ellipsis = generation_config.output_ellipsis;
+        let return_full_text = generation_config.return_full_text;
         let model = TextGenerationOption::new(generation_config)?;
         let prefix_length = prefix
             .as_ref()
@@ -381,6 +871,90 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
             prefix_length,
             min_length,
             max_length,
+            skip_special_tokens,
+            keep_pad_tokens,
+            unk_replacement,
+            control_characters,
+            normalize_whitespace,
+            max_output_chars,
+            output_ellipsis,
+            return_full_text,
+        })
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        self.model.unload();
+    }
+
+    /// Computes the perplexity of a text, i.e. the exponential of the mean cross-entropy of each token given
+    /// the tokens preceding it. Lower values indicate a text that is more likely for the model.
+    /// Only available for causal language models (not XLNet). Texts longer than the model maximum number of
+    /// positions are truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - `&str` text to compute the perplexity for. Must contain at least 2 tokens.
+    ///
+    /// # Returns
+    /// * `Result<f64, RustBertError>` perplexity of the text
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::TextGenerationModel;
+    ///
+    /// let model = TextGenerationModel::new(Default::default())?;
+    ///
+    /// let perplexity = model.perplexity("The dog was running in the park.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn perplexity(&self, text: &str) -> Result<f64, RustBertError> {
+        self.model.perplexity(text)
+    }
+
+    /// Scores a continuation of a prompt, returning the sum and mean log-probability of the continuation
+    /// tokens given the prompt and the preceding continuation tokens, computed in a single forward pass.
+    /// The prompt and continuation are tokenized separately: the continuation should include its leading
+    /// whitespace if any (e.g. `" park"`). Prompts that do not fit in the model maximum number of positions
+    /// are truncated from the left. Only available for causal language models (not XLNet).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - `&str` prompt conditioning the continuation. Must contain at least 1 token.
+    /// * `continuation` - `&str` continuation to score. Must contain at least 1 token.
+    ///
+    /// # Returns
+    /// * `Result<ContinuationScore, RustBertError>` log-probability of the continuation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::TextGenerationModel;
+    ///
+    /// let model = TextGenerationModel::new(Default::default())?;
+    ///
+    /// let score = model.score_continuation("The dog was running in the", " park")?;
+    /// println!("{}", score.sum_log_probability);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn score_continuation(
+        &self,
+        prompt: &str,
+        continuation: &str,
+    ) -> Result<ContinuationScore, RustBertError> {
+        let log_probabilities = self
+            .model
+            .continuation_log_probabilities(prompt, continuation)?;
+        let sum_log_probability: f64 = log_probabilities.iter().sum();
+        Ok(ContinuationScore {
+            sum_log_probability,
+            mean_log_probability: sum_log_probability / log_probabilities.len() as f64,
+            num_tokens: log_probabilities.len(),
         })
     }
 
@@ -404,7 +978,7 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
     /// * `prefix` - `impl Into<Option<&'a str>>`: Optional string to pass as a prefix for generation. Will be excluded from generated sequences.
     ///
     /// # Returns
-    /// * `Vec<String>` Generated texts
+    /// * `Result<Vec<String>, RustBertError>` Generated texts
     ///
     /// # Example
     ///
@@ -418,11 +992,15 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
     /// let input = ["The dog", "The cat was"];
     /// let prefix = None;
     ///
-    /// let output = model.generate(&input, prefix);
+    /// let output = model.generate(&input, prefix)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn generate<'a, S>(&self, texts: &[S], prefix: impl Into<Option<&'a str>>) -> Vec<String>
+    pub fn generate<'a, S>(
+        &self,
+        texts: &[S],
+        prefix: impl Into<Option<&'a str>>,
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
@@ -435,7 +1013,7 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
             (None, None) => (None, None),
         };
         let generated_indices = match (prefix, prefix_length) {
-            (None, _) => self.model.generate_indices(Some(texts), None, None),
+            (None, _) => self.model.generate_indices(Some(texts), None, None)?,
             (Some(prefix), Some(prefix_length)) => {
                 let texts = texts
                     .as_ref()
@@ -446,20 +1024,351 @@ with people, even a bishop, begging for his blessing. <eod> </s> <eos>"
                     Some(&texts),
                     Some(self.min_length + prefix_length),
                     self.max_length.map(|max_length| max_length + prefix_length),
-                )
+                )?
             }
             _ => panic!("Prefix length not defined but prefix provided!"),
         };
 
+        // The sequences of a batch share the prompt padded to the length of the longest prompt, followed by the
+        // generated tokens
+        let prompt_end = generated_indices
+            .iter()
+            .map(|output| output.usage.prompt_tokens)
+            .max()
+            .unwrap_or(0);
+        let output_start = if self.return_full_text {
+            prefix_length.unwrap_or(0) as usize
+        } else {
+            prompt_end
+        };
         let mut output = Vec::with_capacity(generated_indices.len());
         for generated_sequence in generated_indices {
-            output.push(self.model.get_tokenizer().decode(
-                &generated_sequence[prefix_length.unwrap_or(0) as usize..],
-                true,
-                true,
+            // Empty prompts return empty sequences
+            let output_indices = generated_sequence
+                .indices
+                .get(output_start..)
+                .unwrap_or(&[]);
+            output.push(
+                self.postprocess_output(output_indices, prompt_end.saturating_sub(output_start)),
+            );
+        }
+        Ok(output)
+    }
+
+    /// Generate texts from prompts rendered from a template, substituting its placeholders by the values
+    /// provided for each prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - `&PromptTemplate` template of the prompts.
+    /// * `variables` - `&[HashMap]` Values of the template placeholders for each prompt.
+    /// * `prefix` - `impl Into<Option<&'a str>>`: Optional string to pass as a prefix for generation. Will be excluded from generated sequences.
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Generated texts, or an error if a placeholder value is missing
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::text_generation::{PromptTemplate, TextGenerationModel};
+    /// use std::collections::HashMap;
+    ///
+    /// let model = TextGenerationModel::new(Default::default())?;
+    ///
+    /// let template = PromptTemplate::new("The {animal} was {action}")?;
+    /// let variables = [
+    ///     HashMap::from([("animal", "dog"), ("action", "running")]),
+    ///     HashMap::from([("animal", "cat"), ("action", "sleeping")]),
+    /// ];
+    ///
+    /// let output = model.generate_from_template(&template, &variables, None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_from_template<'a, K, V>(
+        &self,
+        template: &PromptTemplate,
+        variables: &[HashMap<K, V>],
+        prefix: impl Into<Option<&'a str>>,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        K: Borrow<str> + Eq + Hash,
+        V: AsRef<str>,
+    {
+        let texts = variables
+            .iter()
+            .map(|prompt_variables| template.render(prompt_variables))
+            .collect::<Result<Vec<String>, RustBertError>>()?;
+        self.generate(&texts, prefix)
+    }
+
+    fn decode(&self, token_ids: &[i64]) -> String {
+        let tokenizer = self.model.get_tokenizer();
+        let token_ids = match self.model.get_pad_id() {
+            Some(pad_token_id) if !self.keep_pad_tokens => token_ids
+                .iter()
+                .filter(|token_id| **token_id != pad_token_id)
+                .copied()
+                .collect::<Vec<i64>>(),
+            _ => token_ids.to_vec(),
+        };
+        let token_ids = token_ids.as_slice();
+        let text = match self.unk_replacement.as_deref() {
+            Some(unk_replacement) => {
+                tokenizer
+                    .decode_lossy_with_unk_replacement(
+                        token_ids,
+                        self.skip_special_tokens,
+                        true,
+                        unk_replacement,
+                    )
+                    .0
+            }
+            None => tokenizer.decode(token_ids, self.skip_special_tokens, true),
+        };
+        match self.control_characters {
+            Some(control_characters) => control_characters.apply(&text),
+            None => text,
+        }
+    }
+
+    /// Decodes an output, the first `prompt_length` tokens (prompt returned with the full text) being
+    /// excluded from the `max_output_chars` limit
+    fn postprocess_output(&self, token_ids: &[i64], prompt_length: usize) -> String {
+        let decode = |token_ids: &[i64]| {
+            let text = self.decode(token_ids);
+            if self.normalize_whitespace {
+                normalize_whitespace(&text)
+            } else {
+                text
+            }
+        };
+        let text = decode(token_ids);
+        let max_output_chars = match self.max_output_chars {
+            Some(max_output_chars) => max_output_chars,
+            None => return text,
+        };
+        let prompt_text = if prompt_length > 0 {
+            decode(&token_ids[..prompt_length.min(token_ids.len())])
+        } else {
+            String::new()
+        };
+        match text.strip_prefix(prompt_text.as_str()) {
+            Some(generated_text) if !prompt_text.is_empty() => format!(
+                "{}{}",
+                prompt_text,
+                truncate_to_word_boundary(generated_text, max_output_chars, self.output_ellipsis)
+            ),
+            _ => truncate_to_word_boundary(&text, max_output_chars, self.output_ellipsis),
+        }
+    }
+
+    /// Generate texts from provided prompts with greedy speculative decoding. A smaller draft model
+    /// proposes `speculation_length` tokens, which are verified with a single forward pass of this
+    /// (target) model. The longest prefix of proposed tokens matching the greedy predictions of the
+    /// target model is accepted, followed by the next token predicted by the target model. The
+    /// key/value cache of both models is kept across steps, the positions of rejected tokens being
+    /// dropped (models whose cache cannot be truncated run on the full sequence at every step).
+    ///
+    /// The output is identical to the greedy output of the target model, including its n-gram blocking
+    /// (`no_repeat_ngram_size`) and prompt truncation. The target model must be configured for greedy decoding
+    /// (no sampling, a single beam, no repetition penalty, logit bias, min-p filtering, logit sanitization or
+    /// clamping, negative prompt, key/value cache window, stop strings or stop brackets), an
+    /// `InvalidConfigurationError` being returned otherwise, and both models must share the same vocabulary.
+    /// Only available for causal language models (not XLNet).
+    ///
+    /// # Arguments
+    ///
+    /// * `draft_model` - `&TextGenerationModel` model proposing the speculated tokens
+    /// * `texts` - `&[&str]` Array of prompts to generate from.
+    /// * `speculation_length` - Number of tokens proposed by the draft model at each step
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, RustBertError>` Generated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::gpt2::{
+    ///     Gpt2ConfigResources, Gpt2MergesResources, Gpt2ModelResources, Gpt2VocabResources,
+    /// };
+    /// use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
+    /// use rust_bert::resources::RemoteResource;
+    ///
+    /// let model = TextGenerationModel::new(TextGenerationConfig {
+    ///     do_sample: false,
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// })?;
+    /// let draft_model = TextGenerationModel::new(TextGenerationConfig {
+    ///     model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+    ///     config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+    ///     vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+    ///     merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+    ///         Gpt2MergesResources::GPT2,
+    ///     ))),
+    ///     do_sample: false,
+    ///     num_beams: 1,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let input = ["The dog", "The cat was"];
+    ///
+    /// let output = model.generate_speculative(&draft_model, &input, 4)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_speculative<S>(
+        &self,
+        draft_model: &TextGenerationModel,
+        texts: &[S],
+        speculation_length: usize,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let config = self.model.get_config();
+        if config.do_sample
+            || config.num_beams > 1
+            || config.repetition_penalty != 1.0
+            || config.frequency_penalty != 0.0
+            || config.presence_penalty != 0.0
+            || !config.logit_bias.is_empty()
+            || config.min_p.is_some()
+            || config.sanitize_logits
+            || config.logit_clamp.is_some()
+            || config.negative_prompt.is_some()
+            || config.kv_cache_window.is_some()
+            || !config.stop_strings.is_empty()
+            || config.stop_brackets.is_some()
+        {
+            return Err(RustBertError::InvalidConfigurationError(
+                "Speculative decoding requires a greedy decoding configuration \
+                (no sampling, a single beam, no repetition penalty, logit bias, min-p filtering, logit sanitization \
+                or clamping, negative prompt, key/value cache window, stop strings or stop brackets)"
+                    .to_string(),
+            ));
+        }
+        if self.model.get_vocab_size() != draft_model.model.get_vocab_size() {
+            return Err(RustBertError::InvalidConfigurationError(
+                "The draft and target models must share the same vocabulary".to_string(),
+            ));
+        }
+        if speculation_length == 0 {
+            return Err(RustBertError::ValueError(
+                "The speculation length must be strictly greater than 0".to_string(),
             ));
         }
-        output
+
+        let tokenizer = self.model.get_tokenizer();
+        let eos_token_ids = self.model.get_eos_ids().cloned().unwrap_or_default();
+        let max_length =
+            self.max_length
+                .unwrap_or_else(|| config.max_length.unwrap_or(i64::MAX)) as usize;
+        let min_length = self.min_length as usize;
+        let no_repeat_ngram_size = config.no_repeat_ngram_size as usize;
+        let repetition_window = config.repetition_window;
+
+        // Greedy prediction of the token following `token_ids`, given the logits of its last position
+        let greedy_prediction = |logits: Tensor, token_ids: &[i64]| -> i64 {
+            let mut banned_token_ids =
+                banned_ngram_tokens(token_ids, no_repeat_ngram_size, repetition_window);
+            if token_ids.len() < min_length {
+                banned_token_ids.extend(eos_token_ids.iter());
+            }
+            let logits = if banned_token_ids.is_empty() {
+                logits
+            } else {
+                logits.index_fill(0, &Tensor::of_slice(&banned_token_ids), f64::NEG_INFINITY)
+            };
+            logits.argmax(-1, false).int64_value(&[])
+        };
+
+        let mut output = Vec::with_capacity(texts.len());
+        for text in texts {
+            // Prompts are truncated to the maximum length as in the plain generation, unless the number of generated
+            // tokens is capped instead
+            let tokens = tokenizer.tokenize(text.as_ref());
+            let tokens = match config.max_new_tokens {
+                None => truncate_tokens_to_byte_fallback_boundary(&tokens, max_length),
+                Some(_) => tokens.as_slice(),
+            };
+            let mut token_ids = tokenizer.convert_tokens_to_ids(tokens);
+            if token_ids.is_empty() {
+                return Err(RustBertError::ValueError(
+                    "Speculative decoding requires a non-empty prompt".to_string(),
+                ));
+            }
+            let prompt_length = token_ids.len();
+            let max_length = config.max_new_tokens.map_or(max_length, |max_new_tokens| {
+                prompt_length + max_new_tokens as usize
+            });
+            // The key/value states of both models are cached across steps, and truncated when tokens are rejected
+            let mut target_cache = SpeculativeCache::new();
+            let mut draft_cache = SpeculativeCache::new();
+            let mut finished = false;
+            while !finished && token_ids.len() < max_length {
+                let current_length = token_ids.len();
+                let num_speculated_tokens = min(speculation_length, max_length - current_length);
+
+                // The draft model proposes tokens greedily
+                let mut speculated_ids = token_ids.clone();
+                for _ in 0..num_speculated_tokens {
+                    let logits = draft_cache.forward(&draft_model.model, &speculated_ids)?;
+                    let next_token_id =
+                        greedy_prediction(logits.get(logits.size()[0] - 1), &speculated_ids);
+                    speculated_ids.push(next_token_id);
+                    if eos_token_ids.contains(&next_token_id) {
+                        break;
+                    }
+                }
+
+                // The target model verifies all proposed tokens in a single forward pass
+                let logits_start = target_cache.length;
+                let logits = target_cache.forward(&self.model, &speculated_ids)?;
+                for position in current_length..=speculated_ids.len() {
+                    if token_ids.len() >= max_length {
+                        break;
+                    }
+                    let target_token_id = greedy_prediction(
+                        logits.get((position - 1 - logits_start) as i64),
+                        &speculated_ids[..position],
+                    );
+                    token_ids.push(target_token_id);
+                    if eos_token_ids.contains(&target_token_id) {
+                        finished = true;
+                        break;
+                    }
+                    if position == speculated_ids.len()
+                        || speculated_ids[position] != target_token_id
+                    {
+                        break;
+                    }
+                }
+
+                // The positions of the rejected tokens are dropped from the caches. The last accepted token is not
+                // cached yet, as at least one token must be passed to the models at the next step.
+                let accepted_length = speculated_ids
+                    .iter()
+                    .zip(token_ids.iter())
+                    .take_while(|(speculated_id, token_id)| speculated_id == token_id)
+                    .count()
+                    .min(token_ids.len() - 1);
+                target_cache.truncate(accepted_length);
+                draft_cache.truncate(accepted_length);
+            }
+            let output_start = if self.return_full_text {
+                0
+            } else {
+                prompt_length
+            };
+            output.push(
+                self.postprocess_output(&token_ids[output_start..], prompt_length - output_start),
+            );
+        }
+        Ok(output)
     }
 }
 
@@ -468,9 +1377,9 @@ mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = TextGenerationConfig::default();
-        let _: Box<dyn Send> = Box::new(TextGenerationModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(TextGenerationModel::new(config));
     }
 }
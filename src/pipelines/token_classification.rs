@@ -120,7 +120,10 @@ use crate::electra::ElectraForTokenClassification;
 use crate::fnet::FNetForTokenClassification;
 use crate::longformer::LongformerForTokenClassification;
 use crate::mobilebert::MobileBertForTokenClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    forward_in_mode, merge_empty_outputs, release_device_memory, set_num_threads,
+    set_var_store_mode, split_empty_inputs, ConfigOption, ModelType, TokenizerOption,
+};
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForTokenClassification;
 use crate::xlnet::XLNetForTokenClassification;
@@ -134,8 +137,9 @@ use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::cmp::min;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tch::nn::VarStore;
-use tch::{nn, no_grad, Device, Kind, Tensor};
+use tch::{nn, Device, Kind, Tensor};
 
 use crate::deberta_v2::DebertaV2ForTokenClassification;
 #[cfg(feature = "remote")]
@@ -167,6 +171,23 @@ pub struct Token {
     pub mask: Mask,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// # Token with its full label distribution generated by a `TokenClassificationModel`
+pub struct TokenScores {
+    /// String representation of the Token
+    pub text: String,
+    /// Probabilities of all labels for the token, indexed by label index (see `TokenClassificationModel::get_label_mapping`)
+    pub label_scores: Vec<f64>,
+    /// Token position index
+    pub index: u16,
+    /// Token word position index
+    pub word_index: u16,
+    /// Token offsets
+    pub offset: Option<Offset>,
+    /// Token mask
+    pub mask: Mask,
+}
+
 impl TokenTrait for Token {
     fn offset(&self) -> Option<Offset> {
         self.offset
@@ -222,13 +243,13 @@ pub struct TokenClassificationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BERT model on CoNLL)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BERT model on CoNLL)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BERT model on CoNLL)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: pretrained BERT model on CoNLL)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
     pub lower_case: bool,
     /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
@@ -237,10 +258,14 @@ pub struct TokenClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
     /// Sub-tokens aggregation method (default: `LabelAggregationOption::First`)
     pub label_aggregation_function: LabelAggregationOption,
     /// Batch size for predictions
     pub batch_size: usize,
+    /// Temperature dividing the token logits before the softmax, allowing calibration of the output scores (default: 1.0)
+    pub score_temperature: f64,
 }
 
 impl TokenClassificationConfig {
@@ -266,9 +291,9 @@ impl TokenClassificationConfig {
         label_aggregation_function: LabelAggregationOption,
     ) -> TokenClassificationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         TokenClassificationConfig {
             model_type,
@@ -280,8 +305,10 @@ impl TokenClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
             label_aggregation_function,
             batch_size: 64,
+            score_temperature: 1.0,
         }
     }
 }
@@ -644,13 +671,15 @@ impl TokenClassificationOption {
 
 /// # TokenClassificationModel for Named Entity Recognition or Part-of-Speech tagging
 pub struct TokenClassificationModel {
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     token_sequence_classifier: TokenClassificationOption,
     label_mapping: HashMap<i64, String>,
     var_store: VarStore,
     label_aggregation_function: LabelAggregationOption,
     max_length: usize,
     batch_size: usize,
+    score_temperature: f64,
+    train: bool,
 }
 
 impl TokenClassificationModel {
@@ -673,16 +702,12 @@ impl TokenClassificationModel {
     pub fn new(
         config: TokenClassificationConfig,
     ) -> Result<TokenClassificationModel, RustBertError> {
-        let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
             None
         };
-        let device = config.device;
-        let label_aggregation_function = config.label_aggregation_function;
 
         let tokenizer = TokenizerOption::from_file(
             config.model_type,
@@ -692,17 +717,59 @@ impl TokenClassificationModel {
             config.strip_accents,
             config.add_prefix_space,
         )?;
+        Self::new_with_tokenizer(config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `TokenClassificationModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `TokenClassificationConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::token_classification::TokenClassificationModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Bert,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let model = TokenClassificationModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        config: TokenClassificationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<TokenClassificationModel, RustBertError> {
+        set_num_threads(config.num_threads)?;
+        tokenizer.validate_model_type(config.model_type)?;
+        let score_temperature = validate_score_temperature(config.score_temperature)?;
+        let config_path = config.config_resource.get_local_path()?;
+        let weights_path = config.model_resource.get_local_path()?;
+        let device = config.device;
+        let label_aggregation_function = config.label_aggregation_function;
+
         let mut var_store = VarStore::new(device);
         let model_config = ConfigOption::from_file(config.model_type, config_path);
-        let max_length = model_config
-            .get_max_len()
-            .map(|v| v as usize)
-            .unwrap_or(usize::MAX);
+        let max_length = model_config.get_max_sequence_length().unwrap_or(usize::MAX);
         let token_sequence_classifier =
             TokenClassificationOption::new(config.model_type, var_store.root(), &model_config)?;
         let label_mapping = model_config.get_label_mapping().clone();
         let batch_size = config.batch_size;
         var_store.load(weights_path)?;
+        set_var_store_mode(&mut var_store, false);
         Ok(TokenClassificationModel {
             tokenizer,
             token_sequence_classifier,
@@ -711,9 +778,61 @@ impl TokenClassificationModel {
             label_aggregation_function,
             max_length,
             batch_size,
+            score_temperature,
+            train: false,
         })
     }
 
+    /// Sets the model in training mode: dropout is applied, the variables of the model are unfrozen and
+    /// the forward pass tracks gradients.
+    pub fn train(&mut self) {
+        self.train = true;
+        set_var_store_mode(&mut self.var_store, true);
+    }
+
+    /// Sets the model in evaluation mode (default): dropout is disabled, the variables of the model are
+    /// frozen and the forward pass runs under `no_grad`.
+    pub fn eval(&mut self) {
+        self.train = false;
+        set_var_store_mode(&mut self.var_store, false);
+    }
+
+    /// Returns `true` if the model is in training mode, `false` if it is in evaluation mode.
+    pub fn is_training(&self) -> bool {
+        self.train
+    }
+
+    /// Sets the temperature dividing the token logits before the softmax, e.g. after calibrating the
+    /// scores on a development set.
+    ///
+    /// # Arguments
+    ///
+    /// * `score_temperature` - `f64` temperature, strictly positive. Values greater than 1 lower the scores of the predicted labels.
+    ///
+    /// # Returns
+    /// * `Result<(), RustBertError>` error if the temperature is not strictly positive
+    pub fn set_score_temperature(&mut self, score_temperature: f64) -> Result<(), RustBertError> {
+        self.score_temperature = validate_score_temperature(score_temperature)?;
+        Ok(())
+    }
+
+    /// Returns the maximum sequence length supported by the model (see `ConfigOption::get_max_sequence_length`),
+    /// or `None` if the model has no limit.
+    pub fn max_sequence_length(&self) -> Option<usize> {
+        if self.max_length == usize::MAX {
+            None
+        } else {
+            Some(self.max_length)
+        }
+    }
+
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
     fn generate_features<S>(&self, input: S, example_index: usize) -> Vec<InputFeature>
     where
         S: AsRef<str>,
@@ -867,6 +986,8 @@ impl TokenClassificationModel {
     where
         S: AsRef<str>,
     {
+        // Empty and whitespace-only inputs contain no token and are not passed to the model
+        let (input, empty_inputs) = split_empty_inputs(input);
         let mut features: Vec<InputFeature> = input
             .iter()
             .enumerate()
@@ -880,7 +1001,7 @@ impl TokenClassificationModel {
         while start < len_features {
             let end = start + min(len_features - start, self.batch_size);
 
-            no_grad(|| {
+            forward_in_mode(self.train, |train| {
                 let batch_features = &mut features[start..end];
                 let (input_ids, attention_masks) = self.pad_features(batch_features);
                 let output = self.token_sequence_classifier.forward_t(
@@ -889,8 +1010,8 @@ impl TokenClassificationModel {
                     None,
                     None,
                     None,
-                    false,
-                );
+                    train,
+                ) / self.score_temperature;
                 let score = output.exp()
                     / output
                         .exp()
@@ -900,10 +1021,8 @@ impl TokenClassificationModel {
                     let labels = label_indices.get(sentence_idx);
                     let feature = &features[sentence_idx as usize];
                     let sentence_reference_flag = &feature.reference_feature;
-                    let original_chars = input[feature.example_index]
-                        .as_ref()
-                        .chars()
-                        .collect::<Vec<char>>();
+                    let original_chars =
+                        input[feature.example_index].chars().collect::<Vec<char>>();
                     let mut word_idx: u16 = 0;
                     for position_idx in sentence_reference_flag
                         .iter()
@@ -941,7 +1060,105 @@ impl TokenClassificationModel {
         if consolidate_sub_tokens {
             self.consolidate_tokens(&mut tokens, &self.label_aggregation_function);
         }
-        tokens
+        merge_empty_outputs(tokens, &empty_inputs, Vec::new)
+    }
+
+    /// Returns the full label distribution of the tokens in a text sequence, e.g. to perform a custom
+    /// decoding of the label sequence. Sub-tokens are not consolidated.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - `&[&str]` Array of texts to classify tokens from.
+    /// * `return_special` - bool flag indicating if scores for special tokens should be returned
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<TokenScores>>` containing the tokens with the probabilities of all labels for each input provided
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// # use rust_bert::pipelines::token_classification::TokenClassificationModel;
+    ///
+    /// let ner_model = TokenClassificationModel::new(Default::default())?;
+    /// let input = ["My name is Amy. I live in Paris."];
+    /// let output = ner_model.predict_scores(&input, false);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_scores<S>(&self, input: &[S], return_special: bool) -> Vec<Vec<TokenScores>>
+    where
+        S: AsRef<str>,
+    {
+        let (input, empty_inputs) = split_empty_inputs(input);
+        let mut features: Vec<InputFeature> = input
+            .iter()
+            .enumerate()
+            .flat_map(|(example_index, example)| self.generate_features(example, example_index))
+            .collect();
+
+        let mut example_tokens_map: Vec<Vec<TokenScores>> = vec![Vec::new(); input.len()];
+        for batch_features in features.chunks_mut(self.batch_size) {
+            forward_in_mode(self.train, |train| {
+                let (input_ids, attention_masks) = self.pad_features(batch_features);
+                let output = self.token_sequence_classifier.forward_t(
+                    Some(&input_ids),
+                    Some(&attention_masks),
+                    None,
+                    None,
+                    None,
+                    train,
+                ) / self.score_temperature;
+                let score = output.softmax(-1, Kind::Float).to(Device::Cpu);
+                for (sentence_idx, feature) in batch_features.iter().enumerate() {
+                    let original_chars =
+                        input[feature.example_index].chars().collect::<Vec<char>>();
+                    let mut word_idx: u16 = 0;
+                    for position_idx in feature
+                        .reference_feature
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, flag)| **flag)
+                        .map(|(pos, _)| pos)
+                    {
+                        let mask = feature.mask[position_idx];
+                        if (mask == Mask::Special) & (!return_special) {
+                            continue;
+                        }
+                        if !(mask == Mask::Continuation) {
+                            word_idx += 1;
+                        }
+                        let token = TokenScores {
+                            text: self.decode_token_text(
+                                &original_chars,
+                                feature,
+                                &input_ids,
+                                sentence_idx as i64,
+                                position_idx as i64,
+                            ),
+                            label_scores: score
+                                .get(sentence_idx as i64)
+                                .get(position_idx as i64)
+                                .iter::<f64>()
+                                .unwrap()
+                                .collect::<Vec<f64>>(),
+                            index: position_idx as u16,
+                            word_index: word_idx,
+                            offset: feature.offsets[position_idx],
+                            mask,
+                        };
+                        example_tokens_map[feature.example_index].push(token);
+                    }
+                }
+            });
+        }
+        merge_empty_outputs(example_tokens_map, &empty_inputs, Vec::new)
+    }
+
+    /// Returns the mapping from label index to label of the model
+    pub fn get_label_mapping(&self) -> &HashMap<i64, String> {
+        &self.label_mapping
     }
 
     fn pad_features(&self, features: &mut [InputFeature]) -> (Tensor, Tensor) {
@@ -995,12 +1212,43 @@ impl TokenClassificationModel {
         word_index: u16,
     ) -> Token {
         let label_id = labels.int64_value(&[position_idx]);
-        let token_id = input_tensor.int64_value(&[sentence_idx, position_idx]);
-
         let offsets = &sentence_tokens.offsets[position_idx as usize];
+        let text = self.decode_token_text(
+            original_sentence_chars,
+            sentence_tokens,
+            input_tensor,
+            sentence_idx,
+            position_idx,
+        );
+
+        Token {
+            text,
+            score: score.double_value(&[sentence_idx, position_idx, label_id]),
+            label: self
+                .label_mapping
+                .get(&label_id)
+                .expect("Index out of vocabulary bounds.")
+                .to_owned(),
+            label_index: label_id,
+            sentence: sentence_idx as usize,
+            index: position_idx as u16,
+            word_index,
+            offset: offsets.to_owned(),
+            mask: sentence_tokens.mask[position_idx as usize],
+        }
+    }
 
-        let text = match offsets {
-            None => match self.tokenizer {
+    fn decode_token_text(
+        &self,
+        original_sentence_chars: &[char],
+        sentence_tokens: &InputFeature,
+        input_tensor: &Tensor,
+        sentence_idx: i64,
+        position_idx: i64,
+    ) -> String {
+        let token_id = input_tensor.int64_value(&[sentence_idx, position_idx]);
+        match &sentence_tokens.offsets[position_idx as usize] {
+            None => match *self.tokenizer {
                 TokenizerOption::Bert(ref tokenizer) => {
                     Tokenizer::decode(tokenizer, &[token_id], false, false)
                 }
@@ -1016,6 +1264,12 @@ impl TokenClassificationModel {
                 TokenizerOption::XLNet(ref tokenizer) => {
                     Tokenizer::decode(tokenizer, &[token_id], false, false)
                 }
+                TokenizerOption::Custom(ref tokenizer) => {
+                    tokenizer.decode(&[token_id], false, false)
+                }
+                TokenizerOption::WithOovPolicy(ref tokenizer, _) => {
+                    tokenizer.decode(&[token_id], false, false)
+                }
                 _ => panic!(
                     "Token classification not implemented for {:?}!",
                     self.tokenizer.model_type()
@@ -1024,27 +1278,11 @@ impl TokenClassificationModel {
             Some(offsets) => {
                 let (start_char, end_char) = (offsets.begin as usize, offsets.end as usize);
                 let end_char = min(end_char, original_sentence_chars.len());
-                let text = original_sentence_chars[start_char..end_char]
+                let start_char = min(start_char, end_char);
+                original_sentence_chars[start_char..end_char]
                     .iter()
-                    .collect();
-                text
+                    .collect()
             }
-        };
-
-        Token {
-            text,
-            score: score.double_value(&[sentence_idx, position_idx, label_id]),
-            label: self
-                .label_mapping
-                .get(&label_id)
-                .expect("Index out of vocabulary bounds.")
-                .to_owned(),
-            label_index: label_id,
-            sentence: sentence_idx as usize,
-            index: position_idx as u16,
-            word_index,
-            offset: offsets.to_owned(),
-            mask: sentence_tokens.mask[position_idx as usize],
         }
     }
 
@@ -1148,3 +1386,14 @@ impl TokenClassificationModel {
         }
     }
 }
+
+fn validate_score_temperature(score_temperature: f64) -> Result<f64, RustBertError> {
+    if score_temperature > 0.0 && score_temperature.is_finite() {
+        Ok(score_temperature)
+    } else {
+        Err(RustBertError::InvalidConfigurationError(format!(
+            "The score temperature must be strictly positive and finite, got {}",
+            score_temperature
+        )))
+    }
+}
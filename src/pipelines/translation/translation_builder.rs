@@ -413,7 +413,7 @@ mod model_fetchers {
 
     pub(super) struct TranslationResources<R>
     where
-        R: ResourceProvider + Send + 'static,
+        R: ResourceProvider + Send + Sync + 'static,
     {
         pub(super) model_type: ModelType,
         pub(super) model_resource: R,
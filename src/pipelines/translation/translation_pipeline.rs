@@ -11,21 +11,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use tch::Device;
+use tch::{no_grad, Device, Kind, Tensor};
 
 use crate::common::error::RustBertError;
 use crate::m2m_100::M2M100Generator;
 use crate::marian::MarianGenerator;
 use crate::mbart::MBartGenerator;
-use crate::pipelines::common::ModelType;
+use crate::pipelines::common::{
+    merge_empty_outputs, normalize_whitespace, set_num_threads, split_empty_inputs,
+    truncate_to_word_boundary, ModelType, PaddingSide, TokenizerOption,
+};
 use crate::pipelines::generation_utils::private_generation_utils::PrivateLanguageGenerator;
-use crate::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use crate::pipelines::generation_utils::{
+    ControlCharacters, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator, ScoreType,
+};
+use crate::pipelines::sentence_splitter::SentenceSplitter;
 use crate::resources::ResourceProvider;
 use crate::t5::T5Generator;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Debug, Display};
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Language
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -156,6 +165,110 @@ impl Display for Language {
 }
 
 impl Language {
+    /// All the supported languages
+    pub(crate) const ALL: [Language; 100] = [
+        Language::Afrikaans,
+        Language::Danish,
+        Language::Dutch,
+        Language::German,
+        Language::English,
+        Language::Icelandic,
+        Language::Luxembourgish,
+        Language::Norwegian,
+        Language::Swedish,
+        Language::WesternFrisian,
+        Language::Yiddish,
+        Language::Asturian,
+        Language::Catalan,
+        Language::French,
+        Language::Galician,
+        Language::Italian,
+        Language::Occitan,
+        Language::Portuguese,
+        Language::Romanian,
+        Language::Spanish,
+        Language::Belarusian,
+        Language::Bosnian,
+        Language::Bulgarian,
+        Language::Croatian,
+        Language::Czech,
+        Language::Macedonian,
+        Language::Polish,
+        Language::Russian,
+        Language::Serbian,
+        Language::Slovak,
+        Language::Slovenian,
+        Language::Ukrainian,
+        Language::Estonian,
+        Language::Finnish,
+        Language::Hungarian,
+        Language::Latvian,
+        Language::Lithuanian,
+        Language::Albanian,
+        Language::Armenian,
+        Language::Georgian,
+        Language::Greek,
+        Language::Breton,
+        Language::Irish,
+        Language::ScottishGaelic,
+        Language::Welsh,
+        Language::Azerbaijani,
+        Language::Bashkir,
+        Language::Kazakh,
+        Language::Turkish,
+        Language::Uzbek,
+        Language::Japanese,
+        Language::Korean,
+        Language::Vietnamese,
+        Language::ChineseMandarin,
+        Language::Bengali,
+        Language::Gujarati,
+        Language::Hindi,
+        Language::Kannada,
+        Language::Marathi,
+        Language::Nepali,
+        Language::Oriya,
+        Language::Panjabi,
+        Language::Sindhi,
+        Language::Sinhala,
+        Language::Urdu,
+        Language::Tamil,
+        Language::Cebuano,
+        Language::Iloko,
+        Language::Indonesian,
+        Language::Javanese,
+        Language::Malagasy,
+        Language::Malay,
+        Language::Malayalam,
+        Language::Sundanese,
+        Language::Tagalog,
+        Language::Burmese,
+        Language::CentralKhmer,
+        Language::Lao,
+        Language::Thai,
+        Language::Mongolian,
+        Language::Arabic,
+        Language::Hebrew,
+        Language::Pashto,
+        Language::Farsi,
+        Language::Amharic,
+        Language::Fulah,
+        Language::Hausa,
+        Language::Igbo,
+        Language::Lingala,
+        Language::Luganda,
+        Language::NorthernSotho,
+        Language::Somali,
+        Language::Swahili,
+        Language::Swati,
+        Language::Tswana,
+        Language::Wolof,
+        Language::Xhosa,
+        Language::Yoruba,
+        Language::Zulu,
+        Language::HaitianCreole,
+    ];
+
     pub fn get_iso_639_1_code(&self) -> &'static str {
         match self {
             Language::Afrikaans => "af",
@@ -261,6 +374,121 @@ impl Language {
         }
     }
 
+    /// Returns the `Language` matching an ISO 639-1 code (e.g. `"en"`), or the ISO 639-3 code used by
+    /// `get_iso_639_1_code` for the languages without ISO 639-1 code. The matching is case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - `&str` language code
+    ///
+    /// # Returns
+    /// * `Option<Language>` matching language, `None` if the code is unknown
+    pub fn from_iso_639_1_code(code: &str) -> Option<Language> {
+        match code.to_lowercase().as_str() {
+            "af" => Some(Language::Afrikaans),
+            "da" => Some(Language::Danish),
+            "nl" => Some(Language::Dutch),
+            "de" => Some(Language::German),
+            "en" => Some(Language::English),
+            "is" => Some(Language::Icelandic),
+            "lb" => Some(Language::Luxembourgish),
+            "no" => Some(Language::Norwegian),
+            "sv" => Some(Language::Swedish),
+            "fy" => Some(Language::WesternFrisian),
+            "yi" => Some(Language::Yiddish),
+            "ast" => Some(Language::Asturian),
+            "ca" => Some(Language::Catalan),
+            "fr" => Some(Language::French),
+            "gl" => Some(Language::Galician),
+            "it" => Some(Language::Italian),
+            "oc" => Some(Language::Occitan),
+            "pt" => Some(Language::Portuguese),
+            "ro" => Some(Language::Romanian),
+            "es" => Some(Language::Spanish),
+            "be" => Some(Language::Belarusian),
+            "bs" => Some(Language::Bosnian),
+            "bg" => Some(Language::Bulgarian),
+            "hr" => Some(Language::Croatian),
+            "cs" => Some(Language::Czech),
+            "mk" => Some(Language::Macedonian),
+            "pl" => Some(Language::Polish),
+            "ru" => Some(Language::Russian),
+            "sr" => Some(Language::Serbian),
+            "sk" => Some(Language::Slovak),
+            "sl" => Some(Language::Slovenian),
+            "uk" => Some(Language::Ukrainian),
+            "et" => Some(Language::Estonian),
+            "fi" => Some(Language::Finnish),
+            "hu" => Some(Language::Hungarian),
+            "lv" => Some(Language::Latvian),
+            "lt" => Some(Language::Lithuanian),
+            "sq" => Some(Language::Albanian),
+            "hy" => Some(Language::Armenian),
+            "ka" => Some(Language::Georgian),
+            "el" => Some(Language::Greek),
+            "br" => Some(Language::Breton),
+            "ga" => Some(Language::Irish),
+            "gd" => Some(Language::ScottishGaelic),
+            "cy" => Some(Language::Welsh),
+            "az" => Some(Language::Azerbaijani),
+            "ba" => Some(Language::Bashkir),
+            "kk" => Some(Language::Kazakh),
+            "tr" => Some(Language::Turkish),
+            "uz" => Some(Language::Uzbek),
+            "ja" => Some(Language::Japanese),
+            "ko" => Some(Language::Korean),
+            "vi" => Some(Language::Vietnamese),
+            "zh" => Some(Language::ChineseMandarin),
+            "bn" => Some(Language::Bengali),
+            "gu" => Some(Language::Gujarati),
+            "hi" => Some(Language::Hindi),
+            "kn" => Some(Language::Kannada),
+            "mr" => Some(Language::Marathi),
+            "ne" => Some(Language::Nepali),
+            "or" => Some(Language::Oriya),
+            "pa" => Some(Language::Panjabi),
+            "sd" => Some(Language::Sindhi),
+            "si" => Some(Language::Sinhala),
+            "ur" => Some(Language::Urdu),
+            "ta" => Some(Language::Tamil),
+            "ceb" => Some(Language::Cebuano),
+            "ilo" => Some(Language::Iloko),
+            "id" => Some(Language::Indonesian),
+            "jv" => Some(Language::Javanese),
+            "mg" => Some(Language::Malagasy),
+            "ms" => Some(Language::Malay),
+            "ml" => Some(Language::Malayalam),
+            "su" => Some(Language::Sundanese),
+            "tl" => Some(Language::Tagalog),
+            "my" => Some(Language::Burmese),
+            "km" => Some(Language::CentralKhmer),
+            "lo" => Some(Language::Lao),
+            "th" => Some(Language::Thai),
+            "mn" => Some(Language::Mongolian),
+            "ar" => Some(Language::Arabic),
+            "he" => Some(Language::Hebrew),
+            "ps" => Some(Language::Pashto),
+            "fa" => Some(Language::Farsi),
+            "am" => Some(Language::Amharic),
+            "ff" => Some(Language::Fulah),
+            "ha" => Some(Language::Hausa),
+            "ig" => Some(Language::Igbo),
+            "ln" => Some(Language::Lingala),
+            "lg" => Some(Language::Luganda),
+            "nso" => Some(Language::NorthernSotho),
+            "so" => Some(Language::Somali),
+            "sw" => Some(Language::Swahili),
+            "ss" => Some(Language::Swati),
+            "tn" => Some(Language::Tswana),
+            "wo" => Some(Language::Wolof),
+            "xh" => Some(Language::Xhosa),
+            "yo" => Some(Language::Yoruba),
+            "zu" => Some(Language::Zulu),
+            "ht" => Some(Language::HaitianCreole),
+            _ => None,
+        }
+    }
+
     pub fn get_iso_639_3_code(&self) -> &'static str {
         match self {
             Language::Afrikaans => "afr",
@@ -365,6 +593,54 @@ impl Language {
             Language::HaitianCreole => "hat",
         }
     }
+
+    /// Returns the `Language` matching an ISO 639-3 code (e.g. `"eng"`). The matching is case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - `&str` language code
+    ///
+    /// # Returns
+    /// * `Option<Language>` matching language, `None` if the code is unknown
+    pub fn from_iso_639_3_code(code: &str) -> Option<Language> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|language| language.get_iso_639_3_code().eq_ignore_ascii_case(code))
+    }
+
+    /// Returns the `Language` matching an ISO 639-1 (e.g. `"en"`) or ISO 639-3 (e.g. `"eng"`) code.
+    /// The matching is case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - `&str` language code
+    ///
+    /// # Returns
+    /// * `Result<Language, RustBertError>` matching language, or an error if the code is unknown
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::translation::Language;
+    ///
+    /// let language = Language::from_iso639("en")?;
+    /// assert_eq!(language, Language::English);
+    /// assert_eq!(language.get_iso_639_1_code(), "en");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_iso639(code: &str) -> Result<Language, RustBertError> {
+        Self::from_iso_639_1_code(code)
+            .or_else(|| Self::from_iso_639_3_code(code))
+            .ok_or_else(|| {
+                RustBertError::ValueError(format!(
+                    "Unknown ISO 639 language code: {}. Supported languages are identified by their ISO 639-1 or ISO 639-3 code.",
+                    code
+                ))
+            })
+    }
 }
 
 /// # Configuration for text translation
@@ -374,13 +650,13 @@ pub struct TranslationConfig {
     /// Model type used for translation
     pub model_type: ModelType,
     /// Model weights resource
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Supported source languages
     pub source_languages: HashSet<Language>,
     /// Supported target languages
@@ -389,6 +665,8 @@ pub struct TranslationConfig {
     pub min_length: i64,
     /// Maximum sequence length (default: 512)
     pub max_length: Option<i64>,
+    /// Maximum number of generated tokens, regardless of the length of the input. Takes precedence over `max_length` when set (default: None)
+    pub max_new_tokens: Option<usize>,
     /// Sampling flag. If true, will perform top-k and/or nucleus sampling on generated tokens, otherwise greedy (deterministic) decoding (default: true)
     pub do_sample: bool,
     /// Early stopping flag indicating if the beam search should stop as soon as `num_beam` hypotheses have been generated (default: false)
@@ -397,24 +675,89 @@ pub struct TranslationConfig {
     pub num_beams: i64,
     /// Temperature setting. Values higher than 1 will improve originality at the risk of reducing relevance (default: 1.0)
     pub temperature: f64,
+    /// Temperatures applied at each generation step when sampling, replacing `temperature`. The last value persists for the steps past the end of the schedule (default: None)
+    pub temperature_schedule: Option<Vec<f64>>,
+    /// Number of most recent tokens inspected by the repetition-aware adaptive temperature when sampling. If provided, the
+    /// temperature is raised towards `adaptive_temperature_max` with the fraction of repeated tokens in this window (default: None)
+    pub adaptive_temperature_window: Option<usize>,
+    /// Maximum temperature reached by the adaptive temperature (default: 2.0)
+    pub adaptive_temperature_max: f64,
     /// Top_k values for sampling tokens. Value higher than 0 will enable the feature (default: 0)
     pub top_k: i64,
     /// Top_p value for [Nucleus sampling, Holtzman et al.](http://arxiv.org/abs/1904.09751). Keep top tokens until cumulative probability reaches top_p (default: 0.9)
     pub top_p: f64,
+    /// Min_p value for sampling. Keep tokens with a probability of at least `min_p` times the probability of the most likely token, applied after the temperature (default: None)
+    pub min_p: Option<f64>,
     /// Repetition penalty (mostly useful for CTRL decoders). Values higher than 1 will penalize tokens that have been already generated. (default: 1.0)
     pub repetition_penalty: f64,
+    /// Frequency penalty subtracted from the logit of a token for each of its occurrences in the sequence, penalizing the tokens
+    /// repeated often more than the multiplicative `repetition_penalty` (default: 0.0)
+    pub frequency_penalty: f64,
+    /// Presence penalty subtracted from the logit of the tokens present in the sequence, regardless of their number of
+    /// occurrences (default: 0.0)
+    pub presence_penalty: f64,
     /// Exponential penalty based on the length of the hypotheses generated (default: 1.0)
     pub length_penalty: f64,
     /// Number of allowed repetitions of n-grams. Values higher than 0 turn on this feature (default: 3)
     pub no_repeat_ngram_size: i64,
+    /// Number of most recent tokens considered by the repetition penalty and n-gram repetition blocking (default: None, the full sequence is considered)
+    pub repetition_window: Option<usize>,
     /// Number of sequences to return for each prompt text (default: 1)
     pub num_return_sequences: i64,
+    /// Score used to rank the sequences returned by beam search, and returned as their score (default: `ScoreType::Normalized`)
+    pub rank_by: ScoreType,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
     /// Number of beam groups for diverse beam generation. If provided and higher than 1, will split the beams into beam subgroups leading to more diverse generation.
     pub num_beam_groups: Option<i64>,
     /// Diversity penalty for diverse beam search. High values will enforce more difference between beam groups (default: 5.5)
     pub diversity_penalty: Option<f64>,
+    /// Strings terminating the generation of a sequence when generated (default: empty)
+    pub stop_strings: Vec<String>,
+    /// Opening and closing characters terminating the generation of a sequence once the brackets opened in the generated text
+    /// are balanced, accounting for nested brackets. Only supported by greedy decoding and sampling (default: None)
+    pub stop_brackets: Option<(char, char)>,
+    /// Bias added to the logits of the given token ids at every generation step (default: empty)
+    pub logit_bias: HashMap<i64, f64>,
+    /// Number of tokens decoded greedily before sampling the rest of the sequence, when sampling is enabled (default: 0)
+    pub greedy_prefix_len: usize,
+    /// Flag indicating if NaN and infinite logits of the model should be replaced by a large negative value before sampling or greedy selection (default: false)
+    pub sanitize_logits: bool,
+    /// Range (minimum, maximum) the logits are clamped to before the softmax and sampling or greedy selection, for the numerical stability of models producing extreme logits. Banned tokens are still excluded (default: None)
+    pub logit_clamp: Option<(f64, f64)>,
+    /// Negative prompt steering the generation away from its content with classifier-free guidance. Only supported by greedy decoding and sampling (default: None)
+    pub negative_prompt: Option<String>,
+    /// Classifier-free guidance scale `g` combining the logits conditioned on the prompt and negative prompt as `logits + g * (logits - negative_logits)` (default: 0.0, disabled)
+    pub guidance_scale: f64,
+    /// Maximum number of past positions kept in the key/value cache of the decoder self-attention, trading quality for bounded memory. Only supported by GPT2 and T5 (default: None, unbounded cache)
+    pub kv_cache_window: Option<usize>,
+    /// Number of first positions of the key/value cache never evicted by the cache window (attention sink), counted within the window (default: 0)
+    pub kv_cache_sink_tokens: usize,
+    /// Device the key/value cache is kept on between generation steps, transferred to the model device at each step at the cost of a higher latency (default: None, the cache stays on the model device)
+    pub kv_cache_device: Option<Device>,
+    /// Flag indicating if special tokens should be removed from the decoded output (default: true)
+    pub skip_special_tokens: bool,
+    /// Placeholder replacing the unknown tokens in the decoded output, an empty placeholder drops them (default: None, unknown tokens are decoded as the other tokens)
+    pub unk_replacement: Option<String>,
+    /// Handling of the control characters (other than newlines and tabs) of the decoded output, e.g. to serialize it as JSON (default: None, control characters are kept)
+    pub control_characters: Option<ControlCharacters>,
+    /// Flag indicating if runs of whitespace characters (including newlines) in the decoded output should be collapsed
+    /// into single spaces, and leading and trailing whitespace trimmed (default: false)
+    pub normalize_whitespace: bool,
+    /// Maximum number of characters of the decoded output. Longer outputs are truncated at the last whitespace before
+    /// the limit so that no word is split (default: None, no truncation)
+    pub max_output_chars: Option<usize>,
+    /// Flag indicating if an ellipsis (`…`), counted in `max_output_chars`, should be appended to the truncated outputs (default: false)
+    pub output_ellipsis: bool,
+    /// Optional prefix prepended to each input text before tokenization. For T5 models, it replaces the
+    /// `translate {source} to {target}:` task prefix. For other models, it is inserted after the
+    /// language code expected by the model (default: None)
+    pub prefix: Option<String>,
+    /// Flag indicating if the model should output the attention weights, required to compute
+    /// word alignments with `translate_with_alignments` (default: false)
+    pub output_attentions: bool,
 }
 
 impl TranslationConfig {
@@ -470,9 +813,9 @@ impl TranslationConfig {
         device: impl Into<Option<Device>>,
     ) -> TranslationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
         S: AsRef<[Language]>,
         T: AsRef<[Language]>,
     {
@@ -487,20 +830,49 @@ impl TranslationConfig {
             source_languages: source_languages.as_ref().iter().cloned().collect(),
             target_languages: target_languages.as_ref().iter().cloned().collect(),
             device,
+            num_threads: None,
             min_length: 0,
             max_length: Some(512),
+            max_new_tokens: None,
             do_sample: false,
             early_stopping: true,
             num_beams: 3,
             temperature: 1.0,
+            temperature_schedule: None,
+            adaptive_temperature_window: None,
+            adaptive_temperature_max: 2.0,
             top_k: 50,
             top_p: 1.0,
+            min_p: None,
             repetition_penalty: 1.0,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
             length_penalty: 1.0,
             no_repeat_ngram_size: 0,
+            repetition_window: None,
             num_return_sequences: 1,
+            rank_by: ScoreType::Normalized,
             num_beam_groups: None,
             diversity_penalty: None,
+            stop_strings: vec![],
+            stop_brackets: None,
+            logit_bias: HashMap::new(),
+            greedy_prefix_len: 0,
+            sanitize_logits: false,
+            logit_clamp: None,
+            negative_prompt: None,
+            guidance_scale: 0.0,
+            kv_cache_window: None,
+            kv_cache_sink_tokens: 0,
+            kv_cache_device: None,
+            skip_special_tokens: true,
+            unk_replacement: None,
+            control_characters: None,
+            normalize_whitespace: false,
+            max_output_chars: None,
+            output_ellipsis: false,
+            prefix: None,
+            output_attentions: false,
         }
     }
 }
@@ -514,18 +886,45 @@ impl From<TranslationConfig> for GenerateConfig {
             vocab_resource: config.vocab_resource,
             min_length: config.min_length,
             max_length: config.max_length,
+            max_new_tokens: config.max_new_tokens,
             do_sample: config.do_sample,
             early_stopping: config.early_stopping,
             num_beams: config.num_beams,
             temperature: config.temperature,
+            temperature_schedule: config.temperature_schedule,
+            adaptive_temperature_window: config.adaptive_temperature_window,
+            adaptive_temperature_max: config.adaptive_temperature_max,
             top_k: config.top_k,
             top_p: config.top_p,
+            min_p: config.min_p,
             repetition_penalty: config.repetition_penalty,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
             length_penalty: config.length_penalty,
             no_repeat_ngram_size: config.no_repeat_ngram_size,
+            repetition_window: config.repetition_window,
             num_return_sequences: config.num_return_sequences,
+            rank_by: config.rank_by,
             num_beam_groups: config.num_beam_groups,
             diversity_penalty: config.diversity_penalty,
+            stop_strings: config.stop_strings,
+            stop_brackets: config.stop_brackets,
+            logit_bias: config.logit_bias,
+            greedy_prefix_len: config.greedy_prefix_len,
+            sanitize_logits: config.sanitize_logits,
+            logit_clamp: config.logit_clamp,
+            negative_prompt: config.negative_prompt,
+            guidance_scale: config.guidance_scale,
+            kv_cache_window: config.kv_cache_window,
+            kv_cache_sink_tokens: config.kv_cache_sink_tokens,
+            kv_cache_device: config.kv_cache_device,
+            // Encoder-decoder models pad their inputs on the right
+            padding_side: PaddingSide::Right,
+            skip_special_tokens: config.skip_special_tokens,
+            keep_pad_tokens: false,
+            unk_replacement: config.unk_replacement,
+            control_characters: config.control_characters,
+            output_attentions: config.output_attentions,
             device: config.device,
         }
     }
@@ -564,6 +963,32 @@ impl TranslationOption {
         }
     }
 
+    pub fn new_with_tokenizer(
+        config: TranslationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<Self, RustBertError> {
+        tokenizer.validate_model_type(config.model_type)?;
+        match config.model_type {
+            ModelType::Marian => Ok(TranslationOption::Marian(
+                MarianGenerator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            ModelType::T5 => Ok(TranslationOption::T5(T5Generator::new_with_tokenizer(
+                config.into(),
+                tokenizer,
+            )?)),
+            ModelType::MBart => Ok(TranslationOption::MBart(
+                MBartGenerator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            ModelType::M2M100 => Ok(TranslationOption::M2M100(
+                M2M100Generator::new_with_tokenizer(config.into(), tokenizer)?,
+            )),
+            _ => Err(RustBertError::InvalidConfigurationError(format!(
+                "Translation not implemented for {:?}!",
+                config.model_type
+            ))),
+        }
+    }
+
     /// Returns the `ModelType` for this TranslationOption
     pub fn model_type(&self) -> ModelType {
         match *self {
@@ -574,6 +999,16 @@ impl TranslationOption {
         }
     }
 
+    /// Interface method to unload() of the particular models.
+    pub fn unload(self) {
+        match self {
+            Self::Marian(model) => model.unload(),
+            Self::T5(model) => model.unload(),
+            Self::MBart(model) => model.unload(),
+            Self::M2M100(model) => model.unload(),
+        }
+    }
+
     fn validate_and_get_prefix_and_forced_bos_id(
         &self,
         source_language: Option<&Language>,
@@ -731,18 +1166,18 @@ impl TranslationOption {
         &self,
         prompt_texts: Option<&[S]>,
         forced_bos_token_id: Option<i64>,
-    ) -> Vec<String>
+    ) -> Result<Vec<String>, RustBertError>
     where
         S: AsRef<str> + Sync,
     {
-        match *self {
+        Ok(match *self {
             Self::Marian(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, None)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
             Self::T5(ref model) => model
-                .generate(prompt_texts, None)
+                .generate(prompt_texts, None)?
                 .into_iter()
                 .map(|output| output.text)
                 .collect(),
@@ -752,7 +1187,7 @@ impl TranslationOption {
                     ..Default::default()
                 };
                 model
-                    .generate(prompt_texts, Some(generate_options))
+                    .generate(prompt_texts, Some(generate_options))?
                     .into_iter()
                     .map(|output| output.text)
                     .collect()
@@ -763,18 +1198,287 @@ impl TranslationOption {
                     ..Default::default()
                 };
                 model
-                    .generate(prompt_texts, Some(generate_options))
+                    .generate(prompt_texts, Some(generate_options))?
                     .into_iter()
                     .map(|output| output.text)
                     .collect()
             }
+        })
+    }
+
+    /// Interface method to generate `num_candidates` translations for each input using beam search,
+    /// returned along with their beam scores. The number of beams is increased to `num_candidates`
+    /// if the configured number of beams is lower.
+    pub fn generate_topn<S>(
+        &self,
+        prompt_texts: Option<&[S]>,
+        forced_bos_token_id: Option<i64>,
+        num_candidates: i64,
+    ) -> Result<Vec<(String, f32)>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let num_beams = match *self {
+            Self::Marian(ref model) => model.get_config().num_beams,
+            Self::T5(ref model) => model.get_config().num_beams,
+            Self::MBart(ref model) => model.get_config().num_beams,
+            Self::M2M100(ref model) => model.get_config().num_beams,
+        };
+        let generate_options = GenerateOptions {
+            forced_bos_token_id,
+            do_sample: Some(false),
+            num_beams: Some(num_beams.max(num_candidates)),
+            num_return_sequences: Some(num_candidates),
+            output_scores: true,
+            ..Default::default()
+        };
+        let output = match *self {
+            Self::Marian(ref model) => model.generate(prompt_texts, Some(generate_options))?,
+            Self::T5(ref model) => model.generate(prompt_texts, Some(generate_options))?,
+            Self::MBart(ref model) => model.generate(prompt_texts, Some(generate_options))?,
+            Self::M2M100(ref model) => model.generate(prompt_texts, Some(generate_options))?,
+        };
+        Ok(output
+            .into_iter()
+            .map(|output| {
+                (
+                    output.text,
+                    output.score.unwrap_or(f64::NEG_INFINITY) as f32,
+                )
+            })
+            .collect())
+    }
+
+    fn get_tokenizer(&self) -> &TokenizerOption {
+        match *self {
+            Self::Marian(ref model) => model._get_tokenizer(),
+            Self::T5(ref model) => model._get_tokenizer(),
+            Self::MBart(ref model) => model._get_tokenizer(),
+            Self::M2M100(ref model) => model._get_tokenizer(),
         }
     }
+
+    /// Generates the translation of a single text, encoded and decoded as by `generate`, and returns the
+    /// translated text, the source and generated token ids, and the cross-attention weights of the decoder
+    /// layers for the generated sequence.
+    fn generate_with_cross_attentions(
+        &self,
+        prompt_text: &str,
+        forced_bos_token_id: Option<i64>,
+    ) -> Result<(String, Vec<i64>, Vec<i64>, Vec<Tensor>), RustBertError> {
+        let generate_options = GenerateOptions {
+            forced_bos_token_id,
+            num_return_sequences: Some(1),
+            ..Default::default()
+        };
+        let (text, input_ids, generated_ids, cross_attentions) = match *self {
+            Self::Marian(ref model) => {
+                let (text, input_ids, generated_ids, decoder_input_ids) =
+                    generate_for_alignments(model, prompt_text, generate_options)?;
+                let cross_attentions = no_grad(|| {
+                    model
+                        .get_model()
+                        .forward_t(
+                            Some(&input_ids),
+                            None,
+                            None,
+                            Some(&decoder_input_ids),
+                            None,
+                            None,
+                            false,
+                        )
+                        .all_cross_attentions
+                });
+                (text, input_ids, generated_ids, cross_attentions)
+            }
+            Self::MBart(ref model) => {
+                let (text, input_ids, generated_ids, decoder_input_ids) =
+                    generate_for_alignments(model, prompt_text, generate_options)?;
+                let cross_attentions = no_grad(|| {
+                    model
+                        .get_model()
+                        .forward_t(
+                            Some(&input_ids),
+                            None,
+                            None,
+                            Some(&decoder_input_ids),
+                            None,
+                            None,
+                            false,
+                        )
+                        .all_cross_attentions
+                });
+                (text, input_ids, generated_ids, cross_attentions)
+            }
+            Self::M2M100(ref model) => {
+                let (text, input_ids, generated_ids, decoder_input_ids) =
+                    generate_for_alignments(model, prompt_text, generate_options)?;
+                let cross_attentions = no_grad(|| {
+                    model
+                        .get_model()
+                        .forward_t(
+                            Some(&input_ids),
+                            None,
+                            None,
+                            Some(&decoder_input_ids),
+                            None,
+                            None,
+                            false,
+                        )
+                        .all_cross_attentions
+                });
+                (text, input_ids, generated_ids, cross_attentions)
+            }
+            Self::T5(_) => {
+                return Err(RustBertError::InvalidConfigurationError(
+                    "Word alignments are not supported for T5 translation models".to_string(),
+                ));
+            }
+        };
+        match cross_attentions {
+            Some(cross_attentions) => Ok((
+                text,
+                input_ids.get(0).iter::<i64>()?.collect(),
+                generated_ids,
+                cross_attentions,
+            )),
+            None => Err(RustBertError::InvalidConfigurationError(
+                "Word alignments require the translation model to be created with `output_attentions` set to true"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Interface method to generate translations along with the word alignments between the
+    /// source texts and their translations.
+    pub fn generate_with_alignments<S>(
+        &self,
+        prompt_texts: &[S],
+        forced_bos_token_id: Option<i64>,
+    ) -> Result<Vec<(String, Vec<(usize, usize)>)>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let tokenizer = self.get_tokenizer();
+        let mut output = Vec::with_capacity(prompt_texts.len());
+        for prompt_text in prompt_texts {
+            let (text, source_ids, generated_ids, cross_attentions) =
+                self.generate_with_cross_attentions(prompt_text.as_ref(), forced_bos_token_id)?;
+            let source_tokens = tokenizer.convert_ids_to_tokens(&source_ids);
+
+            // The cross-attention at a given decoder position is used to generate the next token
+            let target_ids = &generated_ids[1..];
+            let target_tokens = tokenizer.convert_ids_to_tokens(target_ids);
+            let source_word_indices = get_word_indices(tokenizer, &source_tokens, &source_ids);
+            let target_word_indices = get_word_indices(tokenizer, &target_tokens, target_ids);
+
+            let source_word_mask = Tensor::of_slice(
+                &source_word_indices
+                    .iter()
+                    .map(|word_index| if word_index.is_some() { 1f32 } else { 0f32 })
+                    .collect::<Vec<f32>>(),
+            )
+            .to(cross_attentions[0].device());
+            // Average the attention weights over layers and heads: (target length, source length)
+            let attention_weights = Tensor::stack(&cross_attentions, 0)
+                .mean_dim([0, 2].as_slice(), false, Kind::Float)
+                .get(0);
+            let aligned_source_positions = (attention_weights * source_word_mask.unsqueeze(0))
+                .argmax(-1, false)
+                .iter::<i64>()?
+                .collect::<Vec<i64>>();
+
+            let mut alignments = target_word_indices
+                .iter()
+                .zip(aligned_source_positions)
+                .filter_map(|(target_word_index, source_position)| {
+                    Some((
+                        source_word_indices[source_position as usize]?,
+                        (*target_word_index)?,
+                    ))
+                })
+                .collect::<Vec<(usize, usize)>>();
+            alignments.sort_unstable();
+            alignments.dedup();
+
+            output.push((text, alignments));
+        }
+        Ok(output)
+    }
+}
+
+/// Generates the translation of a single text, encoding the text and decoding the generated tokens
+/// as `LanguageGenerator::generate`. Returns the translated text, the encoded source (batch of 1),
+/// the generated token ids and the decoder inputs that generated them.
+fn generate_for_alignments<T, V, U, G>(
+    model: &G,
+    prompt_text: &str,
+    generate_options: GenerateOptions,
+) -> Result<(String, Tensor, Vec<i64>, Tensor), RustBertError>
+where
+    T: LMHeadModel,
+    V: Vocab,
+    U: Tokenizer<V>,
+    G: LanguageGenerator<T, V, U>,
+{
+    let (input_ids, attention_mask) = model
+        .encode_generation_inputs(Some(&[prompt_text]), Some(generate_options))
+        .ok_or_else(|| RustBertError::ValueError("The text to translate is empty".to_string()))?;
+    let generated_ids = model
+        .generate_from_ids_and_past(input_ids.copy(), attention_mask, Some(generate_options))?
+        .remove(0)
+        .indices;
+    let config = PrivateLanguageGenerator::get_config(model);
+    let (text, _) = model.decode_generated_indices(
+        &generated_ids,
+        config.skip_special_tokens,
+        config.keep_pad_tokens,
+        config.unk_replacement.as_deref(),
+        config.control_characters,
+    );
+    let decoder_input_ids = Tensor::of_slice(&generated_ids[..generated_ids.len() - 1])
+        .unsqueeze(0)
+        .to(input_ids.device());
+    Ok((text, input_ids, generated_ids, decoder_input_ids))
+}
+
+/// Maps sub-word tokens to the index of the word they belong to, using the SentencePiece word start
+/// marker. Special tokens and language codes do not belong to any word.
+fn get_word_indices<S>(
+    tokenizer: &TokenizerOption,
+    tokens: &[S],
+    token_ids: &[i64],
+) -> Vec<Option<usize>>
+where
+    S: AsRef<str>,
+{
+    let mut word_index: Option<usize> = None;
+    tokens
+        .iter()
+        .zip(token_ids)
+        .map(|(token, token_id)| {
+            let token = token.as_ref();
+            if tokenizer.is_special_token_id(*token_id)
+                || (token.starts_with(">>") && token.ends_with("<<"))
+            {
+                None
+            } else {
+                if token.starts_with('\u{2581}') || word_index.is_none() {
+                    word_index = Some(word_index.map_or(0, |word_index| word_index + 1));
+                }
+                word_index
+            }
+        })
+        .collect()
 }
 
 /// # TranslationModel to perform translation
 pub struct TranslationModel {
     model: TranslationOption,
+    prefix: Option<String>,
+    normalize_whitespace: bool,
+    max_output_chars: Option<usize>,
+    output_ellipsis: bool,
     supported_source_languages: HashSet<Language>,
     supported_target_languages: HashSet<Language>,
 }
@@ -822,18 +1526,109 @@ impl TranslationModel {
     /// # }
     /// ```
     pub fn new(translation_config: TranslationConfig) -> Result<TranslationModel, RustBertError> {
+        Self::new_with_model(translation_config, TranslationOption::new)
+    }
+
+    /// Build a new `TranslationModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources of the configuration are ignored, and an error is returned if the tokenizer
+    /// cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `translation_config` - `TranslationConfig` object containing the resource references (model, configuration), translation options and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::marian::{
+    ///     MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    ///     MarianTargetLanguages, MarianVocabResources,
+    /// };
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::translation::{TranslationConfig, TranslationModel};
+    /// use rust_bert::resources::RemoteResource;
+    /// use std::sync::Arc;
+    /// use tch::Device;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Marian,
+    ///     "path/to/vocab.json",
+    ///     Some("path/to/spiece.model"),
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let translation_config = TranslationConfig::new(
+    ///     ModelType::Marian,
+    ///     RemoteResource::from_pretrained(MarianModelResources::ROMANCE2ENGLISH),
+    ///     RemoteResource::from_pretrained(MarianConfigResources::ROMANCE2ENGLISH),
+    ///     RemoteResource::from_pretrained(MarianVocabResources::ROMANCE2ENGLISH),
+    ///     Some(RemoteResource::from_pretrained(
+    ///         MarianSpmResources::ROMANCE2ENGLISH,
+    ///     )),
+    ///     MarianSourceLanguages::ROMANCE2ENGLISH,
+    ///     MarianTargetLanguages::ROMANCE2ENGLISH,
+    ///     Device::cuda_if_available(),
+    /// );
+    /// let translation_model = TranslationModel::new_with_tokenizer(translation_config, tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        translation_config: TranslationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<TranslationModel, RustBertError> {
+        Self::new_with_model(translation_config, |config| {
+            TranslationOption::new_with_tokenizer(config, tokenizer)
+        })
+    }
+
+    fn new_with_model<F>(
+        translation_config: TranslationConfig,
+        build_model: F,
+    ) -> Result<TranslationModel, RustBertError>
+    where
+        F: FnOnce(TranslationConfig) -> Result<TranslationOption, RustBertError>,
+    {
+        set_num_threads(translation_config.num_threads)?;
         let supported_source_languages = translation_config.source_languages.clone();
         let supported_target_languages = translation_config.target_languages.clone();
+        let prefix = translation_config.prefix.clone();
+        let normalize_whitespace = translation_config.normalize_whitespace;
+        let max_output_chars = translation_config.max_output_chars;
+        let output_ellipsis = translation_config.output_ellipsis;
 
-        let model = TranslationOption::new(translation_config)?;
+        let model = build_model(translation_config)?;
 
         Ok(TranslationModel {
             model,
+            prefix,
+            normalize_whitespace,
+            max_output_chars,
+            output_ellipsis,
             supported_source_languages,
             supported_target_languages,
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        self.model.unload();
+    }
+
+    /// Combines the prefix expected by the model (task prefix or language code) with the prefix
+    /// provided in the configuration.
+    fn get_input_prefix(&self, model_prefix: Option<String>) -> Option<String> {
+        match (&self.prefix, model_prefix) {
+            (None, model_prefix) => model_prefix,
+            (Some(prefix), _) if self.model.model_type() == ModelType::T5 => Some(prefix.clone()),
+            (Some(prefix), Some(model_prefix)) => Some(format!("{}{}", model_prefix, prefix)),
+            (Some(prefix), None) => Some(prefix.clone()),
+        }
+    }
+
     /// Translates texts provided
     ///
     /// # Arguments
@@ -897,17 +1692,458 @@ impl TranslationModel {
             &self.supported_source_languages,
             &self.supported_target_languages,
         )?;
+        let prefix = self.get_input_prefix(prefix);
+
+        let (texts, empty_inputs) = split_empty_inputs(texts);
+        let mut translations = if texts.is_empty() {
+            vec![]
+        } else {
+            match prefix {
+                Some(value) => {
+                    let texts = texts
+                        .iter()
+                        .map(|v| format!("{}{}", value, v))
+                        .collect::<Vec<String>>();
+                    self.model.generate(Some(&texts), forced_bos_token_id)?
+                }
+                None => self.model.generate(Some(&texts), forced_bos_token_id)?,
+            }
+        };
+        if self.normalize_whitespace || self.max_output_chars.is_some() {
+            translations = translations
+                .iter()
+                .map(|translation| self.postprocess_translation(translation))
+                .collect();
+        }
+        Ok(merge_empty_outputs(
+            translations,
+            &empty_inputs,
+            String::new,
+        ))
+    }
 
-        Ok(match prefix {
+    /// Translates texts provided and returns the `n` best candidate translations for each input,
+    /// ranked by decreasing beam score. Candidates are generated using beam search, with a number
+    /// of beams at least equal to `n`.
+    ///
+    /// # Arguments
+    /// * `input` - `&[&str]` Array of texts to translate.
+    /// * `n` - `usize` Number of candidate translations to return for each input.
+    ///
+    /// # Returns
+    /// * `Vec<Vec<(String, f32)>>` Candidate translations and their scores for each input
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::marian::{
+    ///     MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    ///     MarianTargetLanguages, MarianVocabResources,
+    /// };
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
+    /// use rust_bert::resources::RemoteResource;
+    /// use tch::Device;
+    ///
+    /// let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    /// let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    /// let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    /// let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+    /// let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    /// let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+    ///
+    /// let translation_config = TranslationConfig::new(
+    ///     ModelType::Marian,
+    ///     model_resource,
+    ///     config_resource,
+    ///     vocab_resource,
+    ///     Some(merges_resource),
+    ///     source_languages,
+    ///     target_languages,
+    ///     Device::cuda_if_available(),
+    /// );
+    /// let model = TranslationModel::new(translation_config)?;
+    ///
+    /// let input = ["This is a sentence to be translated"];
+    ///
+    /// let output = model.translate_topn(&input, 3, None, Language::French)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_topn<S>(
+        &self,
+        texts: &[S],
+        n: usize,
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+    ) -> Result<Vec<Vec<(String, f32)>>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        if n == 0 {
+            return Err(RustBertError::ValueError(
+                "The number of candidate translations must be strictly greater than 0".to_string(),
+            ));
+        }
+        let (prefix, forced_bos_token_id) = self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.into().as_ref(),
+            target_language.into().as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+        let prefix = self.get_input_prefix(prefix);
+
+        let (texts, empty_inputs) = split_empty_inputs(texts);
+        let candidates = if texts.is_empty() {
+            vec![]
+        } else {
+            match prefix {
+                Some(value) => {
+                    let texts = texts
+                        .iter()
+                        .map(|v| format!("{}{}", value, v))
+                        .collect::<Vec<String>>();
+                    self.model
+                        .generate_topn(Some(&texts), forced_bos_token_id, n as i64)?
+                }
+                None => self
+                    .model
+                    .generate_topn(Some(&texts), forced_bos_token_id, n as i64)?,
+            }
+        };
+        let candidates = candidates
+            .chunks(n)
+            .map(|input_candidates| {
+                let mut input_candidates = input_candidates
+                    .iter()
+                    .map(|(translation, score)| (self.postprocess_translation(translation), *score))
+                    .collect::<Vec<(String, f32)>>();
+                input_candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+                input_candidates
+            })
+            .collect::<Vec<Vec<(String, f32)>>>();
+        Ok(merge_empty_outputs(candidates, &empty_inputs, Vec::new))
+    }
+
+    /// Translates texts provided and computes the word alignments between the source texts and their translations.
+    /// Each target word is aligned to the source word receiving the highest cross-attention weight
+    /// (averaged over the decoder layers and attention heads) when the target word is generated.
+    /// The model must be created with `output_attentions` set to true in its `TranslationConfig`.
+    /// Alignments are not supported for T5 models.
+    ///
+    /// # Arguments
+    /// * `input` - `&[&str]` Array of texts to translate.
+    ///
+    /// # Returns
+    /// * `Vec<(String, Vec<(usize, usize)>)>` Translated texts and their alignments, given as
+    /// pairs of (source word index, target word index)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::marian::{
+    ///     MarianConfigResources, MarianModelResources, MarianSourceLanguages, MarianSpmResources,
+    ///     MarianTargetLanguages, MarianVocabResources,
+    /// };
+    /// use rust_bert::pipelines::common::ModelType;
+    /// use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
+    /// use rust_bert::resources::RemoteResource;
+    /// use tch::Device;
+    ///
+    /// let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    /// let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    /// let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    /// let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+    /// let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    /// let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+    ///
+    /// let mut translation_config = TranslationConfig::new(
+    ///     ModelType::Marian,
+    ///     model_resource,
+    ///     config_resource,
+    ///     vocab_resource,
+    ///     Some(merges_resource),
+    ///     source_languages,
+    ///     target_languages,
+    ///     Device::cuda_if_available(),
+    /// );
+    /// translation_config.output_attentions = true;
+    /// let model = TranslationModel::new(translation_config)?;
+    ///
+    /// let input = ["This is a sentence to be translated"];
+    ///
+    /// let output = model.translate_with_alignments(&input, None, Language::French)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_with_alignments<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+    ) -> Result<Vec<(String, Vec<(usize, usize)>)>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let (prefix, forced_bos_token_id) = self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.into().as_ref(),
+            target_language.into().as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+        let prefix = self.get_input_prefix(prefix);
+
+        let (texts, empty_inputs) = split_empty_inputs(texts);
+        let mut translations = match prefix {
             Some(value) => {
                 let texts = texts
                     .iter()
-                    .map(|v| format!("{}{}", value, v.as_ref()))
+                    .map(|v| format!("{}{}", value, v))
                     .collect::<Vec<String>>();
-                self.model.generate(Some(&texts), forced_bos_token_id)
+                self.model
+                    .generate_with_alignments(&texts, forced_bos_token_id)?
             }
-            None => self.model.generate(Some(texts), forced_bos_token_id),
-        })
+            None => self
+                .model
+                .generate_with_alignments(&texts, forced_bos_token_id)?,
+        };
+        if self.normalize_whitespace || self.max_output_chars.is_some() {
+            for (translation, alignments) in translations.iter_mut() {
+                *translation = self.postprocess_translation(translation);
+                // The alignments of the words removed by the truncation are dropped
+                let num_words = translation.split_whitespace().count();
+                alignments.retain(|(_, target_word_index)| *target_word_index < num_words);
+            }
+        }
+        Ok(merge_empty_outputs(translations, &empty_inputs, || {
+            (String::new(), vec![])
+        }))
+    }
+
+    fn postprocess_translation(&self, translation: &str) -> String {
+        let translation = if self.normalize_whitespace {
+            normalize_whitespace(translation)
+        } else {
+            translation.to_string()
+        };
+        match self.max_output_chars {
+            Some(max_output_chars) => {
+                truncate_to_word_boundary(&translation, max_output_chars, self.output_ellipsis)
+            }
+            None => translation,
+        }
+    }
+
+    /// Translates the lines read from `reader` and writes the translations to `writer`, one line per
+    /// input line and in the input order. Lines are translated in batches of `batch_size`, so that the
+    /// memory usage is bounded regardless of the size of the input. Line breaks within a translation are
+    /// replaced by spaces to preserve the alignment between input and output lines.
+    ///
+    /// Lines that cannot be processed (invalid UTF-8 or translation error) either abort the stream, or are
+    /// reported and written as empty lines if `skip_line_errors` is set. If the translation of a batch fails,
+    /// its lines are then translated one by one to identify the failing lines. Failures to read from `reader`
+    /// or write to `writer` always abort the stream.
+    ///
+    /// # Arguments
+    /// * `reader` - `BufRead` source of the texts to translate, one text per line
+    /// * `writer` - `Write` destination of the translations
+    /// * `batch_size` - `usize` number of lines translated together
+    /// * `source_language` - Optional source `Language`
+    /// * `target_language` - Optional target `Language`
+    /// * `skip_line_errors` - `bool` flag reporting invalid lines instead of aborting the stream
+    ///
+    /// # Returns
+    /// * `Vec<(usize, RustBertError)>` Index (starting at 0) and error of the lines that could not be translated
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::translation::{Language, TranslationModelBuilder};
+    /// use std::fs::File;
+    /// use std::io::{BufReader, BufWriter};
+    ///
+    /// let model = TranslationModelBuilder::new()
+    ///     .with_source_languages(vec![Language::English])
+    ///     .with_target_languages(vec![Language::French])
+    ///     .create_model()?;
+    ///
+    /// let reader = BufReader::new(File::open("path/to/corpus.txt")?);
+    /// let writer = BufWriter::new(File::create("path/to/corpus.fr.txt")?);
+    /// let line_errors =
+    ///     model.translate_stream(reader, writer, 32, Language::English, Language::French, true)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_stream<R, W>(
+        &self,
+        reader: R,
+        mut writer: W,
+        batch_size: usize,
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        skip_line_errors: bool,
+    ) -> Result<Vec<(usize, RustBertError)>, RustBertError>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        if batch_size == 0 {
+            return Err(RustBertError::ValueError(
+                "The batch size of the translation stream must be greater than 0".to_string(),
+            ));
+        }
+        let source_language = source_language.into();
+        let target_language = target_language.into();
+        // Validates the languages before consuming the stream
+        self.model.validate_and_get_prefix_and_forced_bos_id(
+            source_language.as_ref(),
+            target_language.as_ref(),
+            &self.supported_source_languages,
+            &self.supported_target_languages,
+        )?;
+
+        let mut line_errors = Vec::new();
+        // Index and text of the lines of the current batch, `None` for the lines that could not be processed
+        let mut batch: Vec<(usize, Option<String>)> = Vec::with_capacity(batch_size);
+        let mut lines = reader.split(b'\n').enumerate().peekable();
+        while let Some((line_index, line)) = lines.next() {
+            let mut line = line?;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            match String::from_utf8(line) {
+                Ok(line) => batch.push((line_index, Some(line))),
+                Err(error) => {
+                    let error = RustBertError::ValueError(format!(
+                        "Line {} is not valid UTF-8: {}",
+                        line_index, error
+                    ));
+                    if !skip_line_errors {
+                        return Err(error);
+                    }
+                    line_errors.push((line_index, error));
+                    batch.push((line_index, None));
+                }
+            }
+
+            if batch.len() == batch_size || lines.peek().is_none() {
+                let texts = batch
+                    .iter()
+                    .filter_map(|(_, text)| text.as_ref())
+                    .collect::<Vec<&String>>();
+                let translations = if texts.is_empty() {
+                    Vec::new()
+                } else {
+                    match self.translate(&texts, source_language, target_language) {
+                        Ok(translations) => translations,
+                        Err(error) if !skip_line_errors => return Err(error),
+                        Err(_) => batch
+                            .iter()
+                            .filter_map(|(line_index, text)| {
+                                text.as_ref().map(|text| (*line_index, text))
+                            })
+                            .map(|(line_index, text)| {
+                                match self.translate(&[text], source_language, target_language) {
+                                    Ok(mut translations) => translations.pop().unwrap_or_default(),
+                                    Err(error) => {
+                                        line_errors.push((line_index, error));
+                                        String::new()
+                                    }
+                                }
+                            })
+                            .collect(),
+                    }
+                };
+                let mut translations = translations.into_iter();
+                for (_, text) in batch.drain(..) {
+                    let translation = match text {
+                        Some(_) => translations.next().unwrap_or_default(),
+                        None => String::new(),
+                    };
+                    writeln!(writer, "{}", translation.replace(['\r', '\n'], " "))?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(line_errors)
+    }
+
+    /// Translates texts sentence by sentence, so that long paragraphs are translated fully rather than truncated to
+    /// the maximum input length of the model. The sentences of all texts are translated together in a single batch and
+    /// rejoined with the whitespace separating them in the source text (e.g. spaces or paragraph breaks).
+    ///
+    /// # Arguments
+    /// * `texts` - `&[&str]` Array of texts to translate.
+    /// * `source_language` - Language of the texts
+    /// * `target_language` - Language to translate the texts to
+    /// * `sentence_splitter` - `&dyn SentenceSplitter` splitting the texts into sentences.
+    ///
+    /// # Returns
+    /// * `Vec<String>` Translated texts
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+    /// use rust_bert::pipelines::translation::{Language, TranslationModelBuilder};
+    ///
+    /// let model = TranslationModelBuilder::new()
+    ///     .with_source_languages(vec![Language::English])
+    ///     .with_target_languages(vec![Language::French])
+    ///     .create_model()?;
+    ///
+    /// let input = ["The dog did not wake up. The cat was sleeping on the sofa."];
+    /// let sentence_splitter = PunctuationSentenceSplitter::for_language(Language::English);
+    /// let output =
+    ///     model.translate_sentences(&input, Language::English, Language::French, &sentence_splitter)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_sentences<S>(
+        &self,
+        texts: &[S],
+        source_language: impl Into<Option<Language>>,
+        target_language: impl Into<Option<Language>>,
+        sentence_splitter: &dyn SentenceSplitter,
+    ) -> Result<Vec<String>, RustBertError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let sentence_spans = texts
+            .iter()
+            .map(|text| sentence_splitter.sentence_spans(text.as_ref()))
+            .collect::<Vec<Vec<Range<usize>>>>();
+        let sentences = texts
+            .iter()
+            .zip(sentence_spans.iter())
+            .flat_map(|(text, spans)| spans.iter().map(move |span| &text.as_ref()[span.clone()]))
+            .collect::<Vec<&str>>();
+        let mut translations = self
+            .translate(&sentences, source_language, target_language)?
+            .into_iter();
+
+        Ok(texts
+            .iter()
+            .zip(sentence_spans.iter())
+            .map(|(text, spans)| {
+                let text = text.as_ref();
+                let mut output = String::new();
+                let mut position = 0;
+                for span in spans {
+                    output.push_str(&text[position..span.start]);
+                    output.push_str(translations.next().unwrap_or_default().trim());
+                    position = span.end;
+                }
+                if !spans.is_empty() {
+                    output.push_str(&text[position..]);
+                }
+                output
+            })
+            .collect())
     }
 }
 
@@ -921,7 +2157,7 @@ mod test {
     use crate::resources::RemoteResource;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let model_resource = RemoteResource::from_pretrained(MarianModelResources::ROMANCE2ENGLISH);
         let config_resource =
@@ -942,6 +2178,6 @@ mod test {
             target_languages,
             Device::cuda_if_available(),
         );
-        let _: Box<dyn Send> = Box::new(TranslationModel::new(translation_config));
+        let _: Box<dyn Send + Sync> = Box::new(TranslationModel::new(translation_config));
     }
 }
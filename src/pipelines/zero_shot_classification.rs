@@ -16,10 +16,12 @@
 //! The default model is a BART model fine-tuned on a MNLI. From a list of input sequences to classify and a list of target labels,
 //! single-class or multi-label classification is performed, translating the classification task to an inference task.
 //! The default template for translation to inference task is `This example is about {}.`. This template can be updated to a more specific
-//! value that may match better the use case, for example `This review is about a {product_class}`.
+//! value that may match better the use case, for example `This review is about a {product_class}`, either with the `hypothesis_template`
+//! of the `ZeroShotClassificationConfig` or with a template closure provided to the prediction methods.
 //!
 //! - `predict` performs single-class classification (one and exactly one label must be true for each provided input)
 //! - `predict_multilabel` performs multi-label classification (zero, one or more labels may be true for each provided input)
+//! - `predict_nli` scores arbitrary (premise, hypothesis) pairs, returning the contradiction, neutral and entailment probabilities
 //!
 //! ```no_run
 //! # use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
@@ -105,16 +107,19 @@ use crate::deberta::DebertaForSequenceClassification;
 use crate::distilbert::DistilBertModelClassifier;
 use crate::longformer::LongformerForSequenceClassification;
 use crate::mobilebert::MobileBertForSequenceClassification;
-use crate::pipelines::common::{ConfigOption, ModelType, TokenizerOption};
+use crate::pipelines::common::{
+    merge_empty_outputs, release_device_memory, set_num_threads, split_empty_inputs, ConfigOption,
+    ModelType, PaddingSide, TokenizerOption,
+};
 use crate::pipelines::sequence_classification::Label;
 use crate::resources::ResourceProvider;
 use crate::roberta::RobertaForSequenceClassification;
 use crate::xlnet::XLNetForSequenceClassification;
 use crate::RustBertError;
-use rust_tokenizers::tokenizer::TruncationStrategy;
-use rust_tokenizers::TokenizedInput;
+use rust_tokenizers::tokenizer::{truncate_sequences, TruncationStrategy};
+use rust_tokenizers::{TokenIdsWithOffsets, TokenizedInput};
 use std::borrow::Borrow;
-use std::ops::Deref;
+use std::sync::Arc;
 use tch::kind::Kind::{Bool, Float};
 use tch::nn::VarStore;
 use tch::{nn, no_grad, Device, Tensor};
@@ -125,19 +130,21 @@ use crate::{
     resources::RemoteResource,
 };
 
+const DEFAULT_HYPOTHESIS_TEMPLATE: &str = "This example is about {}.";
+
 /// # Configuration for ZeroShotClassificationModel
 /// Contains information regarding the model to load and device to place the model on.
 pub struct ZeroShotClassificationConfig {
     /// Model type
     pub model_type: ModelType,
     /// Model weights resource (default: pretrained BERT model on CoNLL)
-    pub model_resource: Box<dyn ResourceProvider + Send>,
+    pub model_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Config resource (default: pretrained BERT model on CoNLL)
-    pub config_resource: Box<dyn ResourceProvider + Send>,
+    pub config_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Vocab resource (default: pretrained BERT model on CoNLL)
-    pub vocab_resource: Box<dyn ResourceProvider + Send>,
+    pub vocab_resource: Box<dyn ResourceProvider + Send + Sync>,
     /// Merges resource (default: None)
-    pub merges_resource: Option<Box<dyn ResourceProvider + Send>>,
+    pub merges_resource: Option<Box<dyn ResourceProvider + Send + Sync>>,
     /// Automatically lower case all input upon tokenization (assumes a lower-cased model)
     pub lower_case: bool,
     /// Flag indicating if the tokenizer should strip accents (normalization). Only used for BERT / ALBERT models
@@ -146,6 +153,13 @@ pub struct ZeroShotClassificationConfig {
     pub add_prefix_space: Option<bool>,
     /// Device to place the model on (default: CUDA/GPU when available)
     pub device: Device,
+    /// Number of threads used for intra-op parallelism on CPU, applied process-wide when the model is created (default: None, keeping the Torch setting)
+    pub num_threads: Option<i32>,
+    /// Temperature dividing the logits before the softmax, allowing calibration of the output scores (default: 1.0)
+    pub temperature: f64,
+    /// Template of the hypotheses built from the labels, containing exactly one `{}` placeholder substituted by
+    /// the label. Used when no template closure is provided to the prediction methods (default: `"This example is about {}."`)
+    pub hypothesis_template: String,
 }
 
 impl ZeroShotClassificationConfig {
@@ -170,9 +184,9 @@ impl ZeroShotClassificationConfig {
         add_prefix_space: impl Into<Option<bool>>,
     ) -> ZeroShotClassificationConfig
     where
-        RM: ResourceProvider + Send + 'static,
-        RC: ResourceProvider + Send + 'static,
-        RV: ResourceProvider + Send + 'static,
+        RM: ResourceProvider + Send + Sync + 'static,
+        RC: ResourceProvider + Send + Sync + 'static,
+        RV: ResourceProvider + Send + Sync + 'static,
     {
         ZeroShotClassificationConfig {
             model_type,
@@ -184,6 +198,9 @@ impl ZeroShotClassificationConfig {
             strip_accents: strip_accents.into(),
             add_prefix_space: add_prefix_space.into(),
             device: Device::cuda_if_available(),
+            num_threads: None,
+            temperature: 1.0,
+            hypothesis_template: DEFAULT_HYPOTHESIS_TEMPLATE.to_string(),
         }
     }
 }
@@ -210,6 +227,9 @@ impl Default for ZeroShotClassificationConfig {
             strip_accents: None,
             add_prefix_space: None,
             device: Device::cuda_if_available(),
+            num_threads: None,
+            temperature: 1.0,
+            hypothesis_template: DEFAULT_HYPOTHESIS_TEMPLATE.to_string(),
         }
     }
 }
@@ -518,8 +538,8 @@ pub type ZeroShotTemplate = Box<dyn Fn(&str) -> String>;
 /// The function should take a `&str` as an input and return the formatted String.
 ///
 /// This transformation has a strong impact on the resulting classification accuracy.
-/// If no function is provided for zero-shot classification, the default templating
-/// function will be used:
+/// If no function is provided for zero-shot classification, the `hypothesis_template` of the
+/// `ZeroShotClassificationConfig` is used, by default equivalent to:
 ///
 /// ```rust
 /// fn default_template(label: &str) -> String {
@@ -529,9 +549,11 @@ pub type ZeroShotTemplate = Box<dyn Fn(&str) -> String>;
 
 /// # ZeroShotClassificationModel for Zero Shot Classification
 pub struct ZeroShotClassificationModel {
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     zero_shot_classifier: ZeroShotClassificationOption,
     var_store: VarStore,
+    temperature: f64,
+    hypothesis_template: String,
 }
 
 impl ZeroShotClassificationModel {
@@ -554,15 +576,12 @@ impl ZeroShotClassificationModel {
     pub fn new(
         config: ZeroShotClassificationConfig,
     ) -> Result<ZeroShotClassificationModel, RustBertError> {
-        let config_path = config.config_resource.get_local_path()?;
         let vocab_path = config.vocab_resource.get_local_path()?;
-        let weights_path = config.model_resource.get_local_path()?;
         let merges_path = if let Some(merges_resource) = &config.merges_resource {
             Some(merges_resource.get_local_path()?)
         } else {
             None
         };
-        let device = config.device;
 
         let tokenizer = TokenizerOption::from_file(
             config.model_type,
@@ -572,6 +591,55 @@ impl ZeroShotClassificationModel {
             config.strip_accents,
             config.add_prefix_space,
         )?;
+        Self::new_with_tokenizer(config, Arc::new(tokenizer))
+    }
+
+    /// Build a new `ZeroShotClassificationModel` with a provided tokenizer, that may be shared with other pipelines.
+    /// The vocabulary resources and tokenizer settings of the configuration are ignored, and an error is returned
+    /// if the tokenizer cannot be used by the model type of the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - `ZeroShotClassificationConfig` object containing the resource references (model, configuration) and device placement (CPU/GPU)
+    /// * `tokenizer` - `Arc<TokenizerOption>` tokenizer to use for the model
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::common::{ModelType, TokenizerOption};
+    /// use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+    /// use std::sync::Arc;
+    ///
+    /// let tokenizer = Arc::new(TokenizerOption::from_file(
+    ///     ModelType::Bart,
+    ///     "path/to/vocab.txt",
+    ///     None,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// )?);
+    /// let model = ZeroShotClassificationModel::new_with_tokenizer(Default::default(), tokenizer)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_tokenizer(
+        config: ZeroShotClassificationConfig,
+        tokenizer: Arc<TokenizerOption>,
+    ) -> Result<ZeroShotClassificationModel, RustBertError> {
+        set_num_threads(config.num_threads)?;
+        tokenizer.validate_model_type(config.model_type)?;
+        let num_placeholders = config.hypothesis_template.matches("{}").count();
+        if num_placeholders != 1 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The hypothesis template must contain exactly one {{}} placeholder for the label, got {} in {:?}",
+                num_placeholders, config.hypothesis_template
+            )));
+        }
+        let config_path = config.config_resource.get_local_path()?;
+        let weights_path = config.model_resource.get_local_path()?;
+        let device = config.device;
+
         let mut var_store = VarStore::new(device);
         let model_config = ConfigOption::from_file(config.model_type, config_path);
         let zero_shot_classifier =
@@ -581,9 +649,18 @@ impl ZeroShotClassificationModel {
             tokenizer,
             zero_shot_classifier,
             var_store,
+            temperature: config.temperature,
+            hypothesis_template: config.hypothesis_template,
         })
     }
 
+    /// Unloads the model, releasing the memory held by its weights (see `LanguageGenerator::unload`).
+    pub fn unload(self) {
+        let device = self.var_store.device();
+        drop(self);
+        release_device_memory(device);
+    }
+
     fn prepare_for_model<'a, S, T>(
         &self,
         inputs: S,
@@ -604,55 +681,41 @@ impl ZeroShotClassificationModel {
             None => labels
                 .as_ref()
                 .iter()
-                .map(|label| format!("This example is about {}.", label))
+                .map(|label| self.hypothesis_template.replacen("{}", label, 1))
                 .collect(),
         };
 
-        let text_pair_list = inputs
-            .as_ref()
-            .iter()
-            .flat_map(|input| {
-                label_sentences
-                    .iter()
-                    .map(move |label_sentence| (input.deref(), label_sentence.as_str()))
-            })
-            .collect::<Vec<(&str, &str)>>();
-
-        let tokenized_input: Vec<TokenizedInput> = self.tokenizer.encode_pair_list(
-            text_pair_list.as_ref(),
-            max_len,
-            &TruncationStrategy::LongestFirst,
-            0,
-        );
-        let max_len = tokenized_input
+        // Inputs and hypotheses are tokenized once and combined for every (input, label) pair,
+        // avoiding repeated tokenization of hypotheses shared by all inputs.
+        let input_ids = self
+            .tokenizer
+            .tokenize_list(inputs.as_ref())
             .iter()
-            .map(|input| input.token_ids.len())
-            .max()
-            .ok_or_else(|| RustBertError::ValueError("Got empty iterator as input".to_string()))?;
-
-        let pad_id = self
+            .map(|tokens| self.tokenizer.convert_tokens_to_ids(tokens))
+            .collect::<Vec<Vec<i64>>>();
+        let label_ids = self
             .tokenizer
-            .get_pad_id()
-            .expect("The Tokenizer used for sequence classification should contain a PAD id");
-        let tokenized_input_tensors = tokenized_input
-            .into_iter()
-            .map(|mut input| {
-                input.token_ids.resize(max_len, pad_id);
-                Tensor::of_slice(&(input.token_ids))
-            })
-            .collect::<Vec<_>>();
-
-        let tokenized_input_tensors =
-            Tensor::stack(tokenized_input_tensors.as_slice(), 0).to(self.var_store.device());
+            .tokenize_list(label_sentences.as_slice())
+            .iter()
+            .map(|tokens| self.tokenizer.convert_tokens_to_ids(tokens))
+            .collect::<Vec<Vec<i64>>>();
 
-        let mask = tokenized_input_tensors
-            .ne(self
-                .tokenizer
-                .get_pad_id()
-                .expect("The Tokenizer used for zero shot classification should contain a PAD id"))
-            .to_kind(Bool);
+        let pairs = input_ids
+            .iter()
+            .flat_map(|input| label_ids.iter().map(move |label| (input, label)))
+            .collect::<Vec<(&Vec<i64>, &Vec<i64>)>>();
+        self.encode_pairs(&pairs, max_len)
+    }
 
-        Ok((tokenized_input_tensors, mask))
+    fn encode_pairs(
+        &self,
+        pairs: &[(&Vec<i64>, &Vec<i64>)],
+        max_len: usize,
+    ) -> Result<(Tensor, Tensor), RustBertError> {
+        let (tokenized_input_tensors, mask) =
+            encode_token_id_pairs(&self.tokenizer, pairs, max_len)?;
+        let device = self.var_store.device();
+        Ok((tokenized_input_tensors.to(device), mask.to(device)))
     }
 
     /// Zero shot classification with 1 (and exactly 1) true label.
@@ -661,7 +724,7 @@ impl ZeroShotClassificationModel {
     ///
     /// * `input` - `&[&str]` Array of texts to classify.
     /// * `labels` - `&[&str]` Possible labels for the inputs.
-    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions. If None, will default to `"This example is {}."`.
+    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions. If None, will default to the `hypothesis_template` of the configuration.
     /// * `max_length` -`usize` Maximum sequence length for the inputs. If needed, the input sequence will be truncated before the label template.
     ///
     /// # Returns
@@ -733,7 +796,7 @@ impl ZeroShotClassificationModel {
                 None,
                 false,
             );
-            output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64))
+            output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64)) / self.temperature
         });
 
         let scores = output.softmax(1, Float).select(-1, -1);
@@ -764,7 +827,7 @@ impl ZeroShotClassificationModel {
     ///
     /// * `input` - `&[&str]` Array of texts to classify.
     /// * `labels` - `&[&str]` Possible labels for the inputs.
-    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions. If None, will default to `"This example is about {}."`.
+    /// * `template` - `Option<Box<dyn Fn(&str) -> String>>` closure to build label propositions. If None, will default to the `hypothesis_template` of the configuration.
     /// * `max_length` -`usize` Maximum sequence length for the inputs. If needed, the input sequence will be truncated before the label template.
     ///
     /// # Returns
@@ -862,9 +925,15 @@ impl ZeroShotClassificationModel {
         S: AsRef<[&'a str]>,
         T: AsRef<[&'a str]>,
     {
-        let num_inputs = inputs.as_ref().len();
+        // Empty and whitespace-only inputs have no label and are not passed to the model. An empty
+        // list of inputs is still rejected.
+        let (inputs, empty_inputs) = split_empty_inputs(inputs.as_ref());
+        if inputs.is_empty() && !empty_inputs.is_empty() {
+            return Ok(vec![Vec::new(); empty_inputs.len()]);
+        }
+        let num_inputs = inputs.len();
         let (input_tensor, mask) =
-            self.prepare_for_model(inputs.as_ref(), labels.as_ref(), template, max_length)?;
+            self.prepare_for_model(&inputs, labels.as_ref(), template, max_length)?;
 
         let output = no_grad(|| {
             let output = self.zero_shot_classifier.forward_t(
@@ -875,7 +944,7 @@ impl ZeroShotClassificationModel {
                 None,
                 false,
             );
-            output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64))
+            output.view((num_inputs as i64, labels.as_ref().len() as i64, -1i64)) / self.temperature
         });
         let scores = output.slice(-1, 0, 3, 2).softmax(-1, Float).select(-1, -1);
 
@@ -899,17 +968,276 @@ impl ZeroShotClassificationModel {
             }
             output_labels.push(sentence_labels);
         }
+        let mut output_labels = merge_empty_outputs(output_labels, &empty_inputs, Vec::new);
+        for (sentence_idx, sentence_labels) in output_labels.iter_mut().enumerate() {
+            for label in sentence_labels.iter_mut() {
+                label.sentence = sentence_idx;
+            }
+        }
         Ok(output_labels)
     }
+
+    /// Natural language inference on (premise, hypothesis) pairs, returning the probabilities of
+    /// contradiction, neutrality and entailment of each pair. The model is expected to follow the
+    /// MNLI label ordering (contradiction, neutral, entailment) of the default model.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - `&[(S, S)]` Array of (premise, hypothesis) pairs.
+    /// * `max_length` -`usize` Maximum sequence length for the pairs. If needed, the longest of the premise and hypothesis will be truncated.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<NLIScores>, RustBertError>` containing the inference probabilities for each pair or error, if any.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::pipelines::zero_shot_classification::ZeroShotClassificationModel;
+    ///
+    /// let nli_model = ZeroShotClassificationModel::new(Default::default())?;
+    ///
+    /// let output = nli_model.predict_nli(
+    ///     &[(
+    ///         "A man is playing a guitar on stage.",
+    ///         "A man is performing music.",
+    ///     )],
+    ///     128,
+    /// )?;
+    /// let label = output[0].label();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predict_nli<S>(
+        &self,
+        pairs: &[(S, S)],
+        max_length: usize,
+    ) -> Result<Vec<NLIScores>, RustBertError>
+    where
+        S: AsRef<str>,
+    {
+        let tokenize = |text: &str| {
+            let tokens = self.tokenizer.tokenize(text);
+            self.tokenizer.convert_tokens_to_ids(&tokens)
+        };
+        let pair_ids = pairs
+            .iter()
+            .map(|(premise, hypothesis)| {
+                (tokenize(premise.as_ref()), tokenize(hypothesis.as_ref()))
+            })
+            .collect::<Vec<(Vec<i64>, Vec<i64>)>>();
+        let (input_tensor, mask) = self.encode_pairs(
+            &pair_ids
+                .iter()
+                .map(|(premise, hypothesis)| (premise, hypothesis))
+                .collect::<Vec<(&Vec<i64>, &Vec<i64>)>>(),
+            max_length,
+        )?;
+
+        let output = no_grad(|| {
+            self.zero_shot_classifier.forward_t(
+                Some(&input_tensor),
+                Some(&mask),
+                None,
+                None,
+                None,
+                false,
+            ) / self.temperature
+        });
+        let scores = output.softmax(-1, Float);
+        if scores.size()[1] != 3 {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Natural language inference requires a model with 3 labels, got {}",
+                scores.size()[1]
+            )));
+        }
+
+        let mut output_scores = Vec::with_capacity(pairs.len());
+        for pair_idx in 0..pairs.len() {
+            let pair_scores = scores
+                .select(0, pair_idx as i64)
+                .iter::<f64>()?
+                .collect::<Vec<f64>>();
+            output_scores.push(NLIScores {
+                contradiction: pair_scores[0],
+                neutral: pair_scores[1],
+                entailment: pair_scores[2],
+            });
+        }
+        Ok(output_scores)
+    }
+}
+
+/// # Natural language inference label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NLILabel {
+    Contradiction,
+    Neutral,
+    Entailment,
+}
+
+/// # Natural language inference probabilities of a (premise, hypothesis) pair
+#[derive(Debug, Clone, Copy)]
+pub struct NLIScores {
+    /// Probability that the hypothesis contradicts the premise
+    pub contradiction: f64,
+    /// Probability that the hypothesis is neither entailed nor contradicted by the premise
+    pub neutral: f64,
+    /// Probability that the hypothesis is entailed by the premise
+    pub entailment: f64,
+}
+
+impl NLIScores {
+    /// Returns the most likely inference label
+    pub fn label(&self) -> NLILabel {
+        if self.entailment >= self.contradiction && self.entailment >= self.neutral {
+            NLILabel::Entailment
+        } else if self.contradiction >= self.neutral {
+            NLILabel::Contradiction
+        } else {
+            NLILabel::Neutral
+        }
+    }
 }
+
+/// Builds the model input of (premise, hypothesis) pairs of token ids, truncated to `max_len` tokens
+/// and padded on the right. Returns the input ids and the attention mask on the CPU.
+fn encode_token_id_pairs(
+    tokenizer: &TokenizerOption,
+    pairs: &[(&Vec<i64>, &Vec<i64>)],
+    max_len: usize,
+) -> Result<(Tensor, Tensor), RustBertError> {
+    let sequence_pair_added_tokens = tokenizer
+        .build_input_with_special_tokens(
+            TokenIdsWithOffsets {
+                ids: vec![],
+                offsets: vec![],
+                reference_offsets: vec![],
+                masks: vec![],
+            },
+            Some(TokenIdsWithOffsets {
+                ids: vec![],
+                offsets: vec![],
+                reference_offsets: vec![],
+                masks: vec![],
+            }),
+        )
+        .token_ids
+        .len();
+
+    let mut tokenized_input: Vec<TokenizedInput> = Vec::with_capacity(pairs.len());
+    for (input, label) in pairs.iter() {
+        let num_truncated_tokens =
+            (input.len() + label.len() + sequence_pair_added_tokens).saturating_sub(max_len);
+        let (input, label, _, _) = truncate_sequences(
+            TokenIdsWithOffsets {
+                ids: input.to_vec(),
+                offsets: vec![],
+                reference_offsets: vec![],
+                masks: vec![],
+            },
+            Some(TokenIdsWithOffsets {
+                ids: label.to_vec(),
+                offsets: vec![],
+                reference_offsets: vec![],
+                masks: vec![],
+            }),
+            num_truncated_tokens,
+            &TruncationStrategy::LongestFirst,
+            0,
+        )?;
+        tokenized_input.push(tokenizer.build_input_with_special_tokens(input, label));
+    }
+    if tokenized_input.is_empty() {
+        return Err(RustBertError::ValueError(
+            "Got empty iterator as input".to_string(),
+        ));
+    }
+
+    let pad_id = tokenizer
+        .get_pad_id()
+        .expect("The Tokenizer used for zero shot classification should contain a PAD id");
+    let (tokenized_input_tensors, mask) = tokenizer.pad_token_ids(
+        tokenized_input
+            .into_iter()
+            .map(|input| input.token_ids)
+            .collect(),
+        Some(pad_id),
+        PaddingSide::Right,
+    );
+    Ok((tokenized_input_tensors, mask.to_kind(Bool)))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
-    #[ignore] // no need to run, compilation is enough to verify it is Send
+    #[ignore] // no need to run, compilation is enough to verify it is Send and Sync
     fn test() {
         let config = ZeroShotClassificationConfig::default();
-        let _: Box<dyn Send> = Box::new(ZeroShotClassificationModel::new(config));
+        let _: Box<dyn Send + Sync> = Box::new(ZeroShotClassificationModel::new(config));
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn encode_token_id_pairs_matches_pair_encoding() -> Result<(), RustBertError> {
+        let vocab_path =
+            RemoteResource::from_pretrained(BartVocabResources::BART_MNLI).get_local_path()?;
+        let merges_path =
+            RemoteResource::from_pretrained(BartMergesResources::BART_MNLI).get_local_path()?;
+        let tokenizer = TokenizerOption::from_file(
+            ModelType::Bart,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?;
+
+        let inputs = [
+            "Who are you voting for in 2020?",
+            "The prime minister has announced a stimulus package which was widely criticized by the opposition.",
+        ];
+        let hypotheses = [
+            "This example is about politics.",
+            "This example is about economy.",
+        ];
+        let tokenize = |text: &str| tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(text));
+        let input_ids = inputs
+            .iter()
+            .map(|input| tokenize(input))
+            .collect::<Vec<_>>();
+        let hypothesis_ids = hypotheses
+            .iter()
+            .map(|hypothesis| tokenize(hypothesis))
+            .collect::<Vec<_>>();
+
+        //    The pairs are truncated with a maximum length of 16
+        for max_len in [16, 128] {
+            let pairs = input_ids
+                .iter()
+                .flat_map(|input| hypothesis_ids.iter().map(move |label| (input, label)))
+                .collect::<Vec<(&Vec<i64>, &Vec<i64>)>>();
+            let (input_tensor, mask) = encode_token_id_pairs(&tokenizer, &pairs, max_len)?;
+
+            let text_pairs = inputs
+                .iter()
+                .flat_map(|input| hypotheses.iter().map(move |label| (*input, *label)))
+                .collect::<Vec<(&str, &str)>>();
+            let (expected_input_tensor, expected_mask) = tokenizer.pad_token_ids(
+                tokenizer
+                    .encode_pair_list(&text_pairs, max_len, &TruncationStrategy::LongestFirst, 0)
+                    .into_iter()
+                    .map(|input| input.token_ids)
+                    .collect(),
+                tokenizer.get_pad_id(),
+                PaddingSide::Right,
+            );
+            assert!(input_tensor.equal(&expected_input_tensor));
+            assert!(mask.equal(&expected_mask.to_kind(Bool)));
+        }
+        Ok(())
     }
 }
@@ -14,17 +14,20 @@ use crate::common::activations::TensorFunction;
 use crate::common::dropout::Dropout;
 use crate::prophetnet::ProphetNetConfig;
 use crate::RustBertError;
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use tch::nn::ModuleT;
 use tch::{nn, Kind, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for ProphetNet attention layers
 /// Stores the cached value of key and value
 pub struct LayerState {
     /// Cached keys
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_key: Tensor,
     /// Cached values
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_value: Tensor,
 }
 
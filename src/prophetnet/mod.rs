@@ -71,7 +71,7 @@
 //! about exoplanets like K2-18b."];
 //!
 //!     //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
-//!     let _output = summarization_model.summarize(&input);
+//!     let _output = summarization_model.summarize(&input)?;
 //!     for sentence in _output {
 //!         println!("{}", sentence);
 //!     }
@@ -25,13 +25,15 @@ use std::convert::{TryFrom, TryInto};
 use tch::nn::LinearConfig;
 use tch::{nn, Kind, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for Reformer attention layers
 /// Stores the cached value of buckets and states to avoid recalculation (e.g. at each generation step)
 pub struct LayerState {
     /// Cached buckets
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_buckets: Option<Tensor>,
     /// Cached states
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_states: Tensor,
 }
 
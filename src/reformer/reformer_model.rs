@@ -1067,6 +1067,7 @@ pub struct ReformerGenerator {
 
 impl ReformerGenerator {
     pub fn new(generate_config: GenerateConfig) -> Result<ReformerGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
 
         let tokenizer = TokenizerOption::from_file(
@@ -1085,13 +1086,17 @@ impl ReformerGenerator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<ReformerGenerator, RustBertError> {
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
-        let config = ReformerConfig::from_file(config_path);
+        let mut config = ReformerConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = ReformerModelWithLMHead::new(var_store.root(), &config)?;
         var_store.load(weights_path)?;
 
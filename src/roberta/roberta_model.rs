@@ -435,12 +435,13 @@ impl RobertaClassificationHead {
         let num_labels = config
             .id2label
             .as_ref()
+            .map(|id2label| id2label.len() as i64)
+            .or(config.num_labels)
             .ok_or_else(|| {
                 RustBertError::InvalidConfigurationError(
                     "num_labels not provided in configuration".to_string(),
                 )
-            })?
-            .len() as i64;
+            })?;
         let out_proj = nn::linear(
             p / "out_proj",
             config.hidden_size,
@@ -12,18 +12,21 @@
 
 use crate::common::dropout::Dropout;
 use crate::t5::layer_norm::T5LayerNorm;
-use crate::t5::T5Config;
+use crate::t5::{AttentionImplementation, T5Config};
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use tch::nn::LinearConfig;
 use tch::{nn, Device, Kind, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for T5 attention layers
 /// Stores the cached value of key, value and key to avoid recalculation (e.g. at each generation step)
 pub struct LayerState {
     /// Cached keys
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_key: Tensor,
     /// Cached values
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_value: Tensor,
 }
 
@@ -38,11 +41,16 @@ impl Clone for LayerState {
 
 impl LayerState {
     pub(crate) fn reorder_cache(&mut self, new_indices: &Tensor) {
-        self.prev_key = self.prev_key.index_select(0, new_indices);
-        self.prev_value = self.prev_value.index_select(0, new_indices);
+        // The cache may be on another device than the indices when the decoder is placed on a separate device
+        let new_indices = new_indices.to_device(self.prev_key.device());
+        self.prev_key = self.prev_key.index_select(0, &new_indices);
+        self.prev_value = self.prev_value.index_select(0, &new_indices);
     }
 }
 
+/// Number of queries processed at once by the memory-efficient attention implementation
+const ATTENTION_QUERY_CHUNK_SIZE: i64 = 128;
+
 #[derive(Debug)]
 pub struct T5Attention {
     is_decoder: bool,
@@ -55,6 +63,7 @@ pub struct T5Attention {
     dropout: Dropout,
     inner_dim: i64,
     output_attentions: bool,
+    attention_implementation: AttentionImplementation,
     store_cache: bool,
     query: nn::Linear,
     key: nn::Linear,
@@ -112,6 +121,7 @@ impl T5Attention {
             dropout,
             inner_dim,
             output_attentions,
+            attention_implementation: config.attention_implementation.unwrap_or_default(),
             store_cache,
             query,
             key,
@@ -192,7 +202,11 @@ impl T5Attention {
             None
         };
 
-        let mut scores = Tensor::einsum("bnqd,bnkd->bnqk", &[q, k], None);
+        // The memory-efficient implementation adds the attention mask to each chunk of scores: the
+        // position bias shared by the layers is then the relative position bias, without batch dimension
+        let efficient_attention = (self.attention_implementation
+            == AttentionImplementation::Efficient)
+            & !self.output_attentions;
 
         let calculated_position_bias = if position_bias.is_none() {
             let mut temp_value = if self.has_relative_attention_bias {
@@ -200,14 +214,14 @@ impl T5Attention {
             } else {
                 Tensor::zeros(
                     &[1, self.n_heads, real_seq_length, key_length],
-                    (scores.kind(), scores.device()),
+                    (q.kind(), q.device()),
                 )
             };
             if layer_state.is_some() {
                 let length = temp_value.size()[2];
                 temp_value = temp_value.slice(2, length - seq_length, length, 1);
             };
-            if let Some(attention_mask) = attention_mask {
+            if let (Some(attention_mask), false) = (attention_mask, efficient_attention) {
                 temp_value = temp_value + attention_mask
             };
             Some(temp_value)
@@ -221,20 +235,25 @@ impl T5Attention {
             calculated_position_bias.as_ref().unwrap()
         };
 
-        scores += position_bias;
-
-        let attention_weights = scores
-            .softmax(-1, scores.kind())
-            .apply_t(&self.dropout, train);
-        let context = self
-            .unshape(attention_weights.matmul(&v), bs)
-            .apply(&self.output);
-
-        let attention_weights = if self.output_attentions {
-            Some(attention_weights)
+        let (context, attention_weights) = if efficient_attention {
+            (
+                self.chunked_attention(&q, &k, &v, position_bias, attention_mask, train),
+                None,
+            )
         } else {
-            None
+            let scores = Tensor::einsum("bnqd,bnkd->bnqk", &[q, k], None) + position_bias;
+            let attention_weights = scores
+                .softmax(-1, scores.kind())
+                .apply_t(&self.dropout, train);
+            let context = attention_weights.matmul(&v);
+            let attention_weights = if self.output_attentions {
+                Some(attention_weights)
+            } else {
+                None
+            };
+            (context, attention_weights)
         };
+        let context = self.unshape(context, bs).apply(&self.output);
 
         let position_bias = if self.has_relative_attention_bias {
             calculated_position_bias
@@ -245,6 +264,48 @@ impl T5Attention {
         (context, attention_weights, position_bias, layer_state)
     }
 
+    /// Computes the attention context by chunks of queries, so that only the attention scores of
+    /// a chunk are materialized at once. The position bias (shared by the batch) and the attention
+    /// mask are sliced along the query dimension and added to the scores of each chunk, unless they
+    /// are broadcast over it.
+    fn chunked_attention(
+        &self,
+        q: &Tensor,
+        k: &Tensor,
+        v: &Tensor,
+        position_bias: &Tensor,
+        attention_mask: Option<&Tensor>,
+        train: bool,
+    ) -> Tensor {
+        let query_length = q.size()[2];
+        let query_chunk = |tensor: &Tensor, start: i64, chunk_length: i64| {
+            if tensor.size()[2] == 1 {
+                tensor.shallow_clone()
+            } else {
+                tensor.narrow(2, start, chunk_length)
+            }
+        };
+        let key_transposed = k.transpose(-1, -2);
+        let mut contexts = Vec::with_capacity(
+            ((query_length + ATTENTION_QUERY_CHUNK_SIZE - 1) / ATTENTION_QUERY_CHUNK_SIZE) as usize,
+        );
+        let mut start = 0;
+        while start < query_length {
+            let chunk_length = ATTENTION_QUERY_CHUNK_SIZE.min(query_length - start);
+            let mut scores = q.narrow(2, start, chunk_length).matmul(&key_transposed)
+                + query_chunk(position_bias, start, chunk_length);
+            if let Some(attention_mask) = attention_mask {
+                scores += query_chunk(attention_mask, start, chunk_length);
+            }
+            let attention_weights = scores
+                .softmax(-1, scores.kind())
+                .apply_t(&self.dropout, train);
+            contexts.push(attention_weights.matmul(v));
+            start += chunk_length;
+        }
+        Tensor::cat(&contexts, 2)
+    }
+
     fn get_relative_position_bucket(
         &self,
         relative_position: &Tensor,
@@ -21,7 +21,65 @@ use crate::Activation::gelu_new;
 use crate::RustBertError;
 use std::borrow::{Borrow, BorrowMut};
 use tch::nn::LinearConfig;
-use tch::{nn, Kind, Scalar, Tensor};
+use tch::{nn, Device, Kind, Scalar, Tensor};
+
+/// Builds a block-diagonal attention mask for batches of packed sequences, where each sequence of
+/// the batch is made of several documents concatenated. Tokens only attend to the tokens of their
+/// own document, and padding positions (after the last document) are masked.
+/// The mask can be passed as the `attention_mask` of the T5 encoder.
+///
+/// # Arguments
+///
+/// * `document_lengths` - Lengths of the documents packed in each sequence of the batch, in order
+/// * `sequence_length` - Length of the packed sequences (including padding)
+/// * `device` - Device to create the mask on
+///
+/// # Returns
+///
+/// * `Tensor` of shape (*batch size*, *sequence_length*, *sequence_length*) with value 1 for the key positions
+/// (last dimension) a query position (second dimension) can attend to and 0 otherwise
+///
+/// # Example
+///
+/// ```no_run
+/// use rust_bert::t5::get_block_diagonal_attention_mask;
+/// use tch::Device;
+///
+/// // 2 documents of 3 and 2 tokens packed, followed by a padding token
+/// let attention_mask = get_block_diagonal_attention_mask(&[vec![3, 2]], 6, Device::Cpu)?;
+/// # Ok::<(), rust_bert::RustBertError>(())
+/// ```
+pub fn get_block_diagonal_attention_mask(
+    document_lengths: &[Vec<i64>],
+    sequence_length: i64,
+    device: Device,
+) -> Result<Tensor, RustBertError> {
+    let mut document_ids = Vec::with_capacity(document_lengths.len() * sequence_length as usize);
+    for sequence_document_lengths in document_lengths {
+        let packed_length = sequence_document_lengths.iter().sum::<i64>();
+        if packed_length > sequence_length
+            || sequence_document_lengths.iter().any(|length| *length < 0)
+        {
+            return Err(RustBertError::ValueError(format!(
+                "Invalid document lengths {:?} for packed sequences of length {}",
+                sequence_document_lengths, sequence_length
+            )));
+        }
+        // Documents are numbered from 1, padding positions are assigned to document 0
+        for (document_index, length) in sequence_document_lengths.iter().enumerate() {
+            document_ids.extend(vec![document_index as i64 + 1; *length as usize]);
+        }
+        document_ids.extend(vec![0; (sequence_length - packed_length) as usize]);
+    }
+    let document_ids = Tensor::of_slice(&document_ids)
+        .view([document_lengths.len() as i64, sequence_length])
+        .to(device);
+    let same_document = document_ids
+        .unsqueeze(-1)
+        .eq_tensor(&document_ids.unsqueeze(1));
+    let not_padding = document_ids.ne(0).unsqueeze(-1);
+    Ok(same_document.logical_and(&not_padding).to_kind(Kind::Int64))
+}
 
 pub struct T5DenseReluDense {
     wi: nn::Linear,
@@ -306,6 +364,7 @@ pub struct T5Stack {
     output_hidden_states: bool,
     is_decoder: bool,
     store_cache: bool,
+    block_devices: Option<Vec<Device>>,
 }
 
 impl T5Stack {
@@ -350,9 +409,20 @@ impl T5Stack {
             output_hidden_states,
             is_decoder,
             store_cache,
+            block_devices: None,
         }
     }
 
+    pub(crate) fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Sets the device of each block, the hidden states being transferred between blocks placed on
+    /// different devices. The output is returned on the device of the inputs.
+    pub(crate) fn set_block_devices(&mut self, block_devices: Option<Vec<Device>>) {
+        self.block_devices = block_devices;
+    }
+
     pub fn forward_t(
         &self,
         input_ids: Option<&Tensor>,
@@ -425,12 +495,14 @@ impl T5Stack {
             }
         };
 
-        let extended_attention_mask: Option<Tensor> = Some(
+        let mut extended_attention_mask: Option<Tensor> = Some(
             ((extended_attention_mask.ones_like() - extended_attention_mask) * -1e4)
                 .to_kind(input_embeddings.kind()),
         );
 
-        let extended_encoder_attention_mask = if self.is_decoder & encoder_hidden_states.is_some() {
+        let mut extended_encoder_attention_mask = if self.is_decoder
+            & encoder_hidden_states.is_some()
+        {
             let encoder_hidden_states = encoder_hidden_states.as_ref().unwrap();
             let encoder_hidden_states_shape = encoder_hidden_states.size();
             let encoder_mask = match encoder_attention_mask {
@@ -483,8 +555,22 @@ impl T5Stack {
         let mut encoder_decoder_position_bias = None;
         let mut attention_weights: Option<Tensor>;
         let mut hidden_state = input_embeddings.apply_t(&self.dropout, train);
+        let mut transferred_encoder_hidden_states: Option<Tensor> = None;
 
         for (layer_idx, layer) in self.blocks.iter().enumerate() {
+            if let Some(block_devices) = &self.block_devices {
+                let device = block_devices[layer_idx];
+                if hidden_state.device() != device {
+                    let to_device = |tensor: Option<Tensor>| tensor.map(|t| t.to_device(device));
+                    hidden_state = hidden_state.to_device(device);
+                    position_bias = to_device(position_bias);
+                    encoder_decoder_position_bias = to_device(encoder_decoder_position_bias);
+                    extended_attention_mask = to_device(extended_attention_mask);
+                    extended_encoder_attention_mask = to_device(extended_encoder_attention_mask);
+                    transferred_encoder_hidden_states =
+                        encoder_hidden_states.map(|t| t.to_device(device));
+                }
+            }
             let layer_state = match &next_cache {
                 Some(values) => values[layer_idx].to_owned(),
                 None => (None, None),
@@ -493,7 +579,9 @@ impl T5Stack {
                 &hidden_state,
                 position_bias.as_ref(),
                 extended_attention_mask.as_ref(),
-                encoder_hidden_states,
+                transferred_encoder_hidden_states
+                    .as_ref()
+                    .or(encoder_hidden_states),
                 extended_encoder_attention_mask.as_ref(),
                 encoder_decoder_position_bias.as_ref(),
                 layer_state,
@@ -516,6 +604,9 @@ impl T5Stack {
             };
         }
 
+        if self.block_devices.is_some() {
+            hidden_state = hidden_state.to_device(input_embeddings.device());
+        }
         let hidden_state = hidden_state
             .apply(&self.final_layer_norm)
             .apply_t(&self.dropout, train);
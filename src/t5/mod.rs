@@ -54,8 +54,9 @@ mod layer_norm;
 mod t5_model;
 
 pub use attention::LayerState;
+pub use encoder::get_block_diagonal_attention_mask;
 pub use t5_model::{
-    T5Config, T5ConfigResources, T5ForConditionalGeneration, T5ForSentenceEmbeddings, T5Generator,
-    T5Model, T5ModelOutput, T5ModelResources, T5Prefix, T5SourceLanguages, T5TargetLanguages,
-    T5VocabResources,
+    AttentionImplementation, T5Config, T5ConfigResources, T5DeviceMap, T5ForConditionalGeneration,
+    T5ForSentenceEmbeddings, T5Generator, T5Model, T5ModelOutput, T5ModelResources, T5Prefix,
+    T5SourceLanguages, T5TargetLanguages, T5VocabResources,
 };
@@ -11,19 +11,23 @@
 // limitations under the License.
 
 use std::borrow::Borrow;
+use std::ops::Range;
+use std::sync::Arc;
 
 use rust_tokenizers::tokenizer::{T5Tokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::T5Vocab;
 use serde::{Deserialize, Serialize};
 use tch::nn::{embedding, LinearConfig};
-use tch::{nn, Tensor};
+use tch::{nn, no_grad, Device, Tensor};
 
-use crate::pipelines::common::{ModelType, TokenizerOption};
+use crate::common::loss::{token_cross_entropy, IGNORE_LABEL_INDEX};
+use crate::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
 use crate::pipelines::generation_utils::private_generation_utils::{
     PreparedInput, PrivateLanguageGenerator,
 };
 use crate::pipelines::generation_utils::{
-    Cache, GenerateConfig, LMHeadModel, LMModelOutput, LanguageGenerator,
+    Cache, GenerateConfig, GenerateOptions, GeneratedTextOutput, LMHeadModel, LMModelOutput,
+    LanguageGenerator,
 };
 use crate::pipelines::translation::Language;
 use crate::t5::attention::LayerState;
@@ -124,6 +128,20 @@ pub enum FeedForwardProj {
     GatedGelu,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+/// # Options for the T5 attention implementation
+pub enum AttentionImplementation {
+    /// Attention scores computed for all queries at once
+    #[default]
+    Eager,
+    /// Memory-efficient attention computed by chunks of queries, avoiding the materialization of
+    /// the attention scores and of the masked position bias for the full sequence and batch (only
+    /// the relative position bias, shared by the batch, is computed for the full sequence). Falls
+    /// back to the eager implementation when attention weights are returned.
+    Efficient,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// # T5 model configuration
 /// Defines the T5 model architecture (e.g. number of layers, hidden layer size, label mapping...)
@@ -144,9 +162,17 @@ pub struct T5Config {
     pub pad_token_id: Option<i64>,
     pub relative_attention_num_buckets: i64,
     pub relative_attention_max_distance: Option<i64>,
+    /// Practical maximum sequence length of the model. T5 relies on relative positions and has no
+    /// hard limit on the sequence length, but its quality degrades beyond the sequence length used
+    /// during training (512 tokens for the original checkpoints).
+    pub n_positions: Option<i64>,
     pub vocab_size: i64,
     pub feed_forward_proj: Option<FeedForwardProj>,
+    /// Share the weights of the output projection with the input word embeddings (default: true).
+    /// Checkpoints with untied embeddings (e.g. T5 v1.1) store a separate `lm_head` projection.
     pub tie_word_embeddings: Option<bool>,
+    /// Attention implementation used by the attention layers (default: eager)
+    pub attention_implementation: Option<AttentionImplementation>,
     task_specific_params: Option<TaskSpecificParams>,
     pub output_attentions: Option<bool>,
     pub output_hidden_states: Option<bool>,
@@ -222,9 +248,11 @@ impl Default for T5Config {
             pad_token_id: Some(0),
             relative_attention_num_buckets: 32,
             relative_attention_max_distance: Some(128),
+            n_positions: Some(512),
             vocab_size: 32128,
             feed_forward_proj: Some(FeedForwardProj::Relu),
             tie_word_embeddings: None,
+            attention_implementation: None,
             task_specific_params: None,
             output_attentions: None,
             output_hidden_states: None,
@@ -232,6 +260,83 @@ impl Default for T5Config {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// # Device placement of the T5 encoder and decoder
+/// Splits a T5 model across devices, e.g. for a model too large to fit on a single GPU. The shared embeddings,
+/// the encoder and the language model head are placed on the `encoder` device, and the decoder on the `decoder` device.
+/// Ranges of encoder or decoder blocks can optionally be placed on other devices.
+/// The hidden states are transferred between devices in the forward pass.
+pub struct T5DeviceMap {
+    /// Device of the shared embeddings, encoder and language model head
+    pub encoder: Device,
+    /// Device of the decoder
+    pub decoder: Device,
+    /// Ranges of encoder block indices placed on another device than the `encoder` device (default: empty)
+    pub encoder_layers: Vec<(Range<usize>, Device)>,
+    /// Ranges of decoder block indices placed on another device than the `decoder` device (default: empty)
+    pub decoder_layers: Vec<(Range<usize>, Device)>,
+}
+
+impl T5DeviceMap {
+    /// Creates a device map placing the encoder and decoder on the given devices, without layer ranges
+    pub fn new(encoder: Device, decoder: Device) -> T5DeviceMap {
+        T5DeviceMap {
+            encoder,
+            decoder,
+            encoder_layers: vec![],
+            decoder_layers: vec![],
+        }
+    }
+
+    fn block_devices(
+        stack_device: Device,
+        layers: &[(Range<usize>, Device)],
+        num_blocks: usize,
+    ) -> Option<Vec<Device>> {
+        if layers.is_empty() {
+            return None;
+        }
+        Some(
+            (0..num_blocks)
+                .map(|block_index| {
+                    layers
+                        .iter()
+                        .find(|(range, _)| range.contains(&block_index))
+                        .map_or(stack_device, |(_, device)| *device)
+                })
+                .collect(),
+        )
+    }
+
+    /// Device of a variable of the model, from its name (e.g. `decoder.block.3.layer.0.SelfAttention.q.weight`)
+    fn variable_device(
+        &self,
+        name: &str,
+        encoder_blocks: &[Device],
+        decoder_blocks: &[Device],
+    ) -> Device {
+        let components = name.split('.').collect::<Vec<&str>>();
+        let (stack_position, stack_device, block_devices) = match components
+            .iter()
+            .position(|component| *component == "decoder")
+        {
+            Some(position) => (Some(position), self.decoder, decoder_blocks),
+            None => (
+                components
+                    .iter()
+                    .position(|component| *component == "encoder"),
+                self.encoder,
+                encoder_blocks,
+            ),
+        };
+        stack_position
+            .filter(|position| components.get(position + 1) == Some(&"block"))
+            .and_then(|position| components.get(position + 2)?.parse::<usize>().ok())
+            .and_then(|block_index| block_devices.get(block_index).copied())
+            .unwrap_or(stack_device)
+    }
+}
+
 /// # T5 Base model
 /// Base architecture for T5 model. Usually complemented with a task-specific head, such as a language model head.
 /// It is made of the following blocks:
@@ -243,6 +348,7 @@ pub struct T5Model {
     pub(crate) encoder: T5Stack,
     decoder: T5Stack,
     pub(crate) embeddings: nn::Embedding,
+    device_map: Option<T5DeviceMap>,
 }
 
 impl T5Model {
@@ -301,7 +407,95 @@ impl T5Model {
             encoder,
             decoder,
             embeddings,
+            device_map: None,
+        }
+    }
+
+    /// Places the encoder and decoder of the model on separate devices. The variables of the decoder (with `decoder`
+    /// in their name) are moved to the decoder device, the other variables of the variable store to the encoder device.
+    /// The blocks within the layer ranges of the device map are moved to the device of their range.
+    /// May be called before or after loading the weights.
+    ///
+    /// The encoder device must be the device of the variable store: inputs are expected on this device, and the decoder
+    /// output is returned on it. The decoder cache remains on the decoder device.
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - `VarStore` holding the variables of the model
+    /// * `device_map` - `T5DeviceMap` devices of the encoder and decoder
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rust_bert::t5::{T5Config, T5DeviceMap, T5Model};
+    /// use rust_bert::Config;
+    /// use std::path::Path;
+    /// use tch::{nn, Device};
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config_path = Path::new("path/to/config.json");
+    /// let p = nn::VarStore::new(Device::Cuda(0));
+    /// let config = T5Config::from_file(config_path);
+    /// let mut t5: T5Model = T5Model::new(&p.root() / "t5", &config);
+    /// t5.set_device_map(
+    ///     &p,
+    ///     T5DeviceMap {
+    ///         decoder_layers: vec![(3..6, Device::Cuda(2))],
+    ///         ..T5DeviceMap::new(Device::Cuda(0), Device::Cuda(1))
+    ///     },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_device_map(
+        &mut self,
+        var_store: &nn::VarStore,
+        device_map: T5DeviceMap,
+    ) -> Result<(), RustBertError> {
+        if device_map.encoder != var_store.device() {
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "The encoder device ({:?}) must be the device of the variable store ({:?})",
+                device_map.encoder,
+                var_store.device()
+            )));
         }
+        let (num_encoder_blocks, num_decoder_blocks) =
+            (self.encoder.num_blocks(), self.decoder.num_blocks());
+        for (layers, num_blocks) in [
+            (&device_map.encoder_layers, num_encoder_blocks),
+            (&device_map.decoder_layers, num_decoder_blocks),
+        ] {
+            if let Some((range, _)) = layers.iter().find(|(range, _)| range.end > num_blocks) {
+                return Err(RustBertError::InvalidConfigurationError(format!(
+                    "The layer range {:?} exceeds the number of blocks ({})",
+                    range, num_blocks
+                )));
+            }
+        }
+        let encoder_blocks = T5DeviceMap::block_devices(
+            device_map.encoder,
+            &device_map.encoder_layers,
+            num_encoder_blocks,
+        );
+        let decoder_blocks = T5DeviceMap::block_devices(
+            device_map.decoder,
+            &device_map.decoder_layers,
+            num_decoder_blocks,
+        );
+        no_grad(|| {
+            for (name, mut variable) in var_store.variables() {
+                let device = device_map.variable_device(
+                    &name,
+                    encoder_blocks.as_deref().unwrap_or(&[]),
+                    decoder_blocks.as_deref().unwrap_or(&[]),
+                );
+                variable.set_data(&variable.to_device(device));
+            }
+        });
+        self.encoder.set_block_devices(encoder_blocks);
+        self.decoder.set_block_devices(decoder_blocks);
+        self.device_map = Some(device_map);
+        Ok(())
     }
 
     /// Forward pass through the model
@@ -411,19 +605,51 @@ impl T5Model {
         let encoder_output =
             encoder_outputs.unwrap_or_else(|| calc_hidden_states.as_ref().unwrap());
 
-        let decoder_output = self
-            .decoder
-            .forward_t(
-                decoder_input_ids,
-                decoder_attention_mask,
-                Some(encoder_output),
-                attention_mask,
-                decoder_input_embeds,
-                &self.embeddings,
-                old_layer_states,
-                train,
-            )
-            .unwrap();
+        let decoder_output = if let Some(device_map) = &self.device_map {
+            // The decoder inputs are embedded on the encoder device, holding the shared embeddings
+            let decoder_input_embeds = match decoder_input_embeds {
+                Some(decoder_input_embeds) => {
+                    Some(decoder_input_embeds.to_device(device_map.decoder))
+                }
+                None => decoder_input_ids.map(|decoder_input_ids| {
+                    decoder_input_ids
+                        .apply(&self.embeddings)
+                        .to_device(device_map.decoder)
+                }),
+            };
+            let decoder_attention_mask = decoder_attention_mask
+                .map(|decoder_attention_mask| decoder_attention_mask.to_device(device_map.decoder));
+            let attention_mask =
+                attention_mask.map(|attention_mask| attention_mask.to_device(device_map.decoder));
+            let mut decoder_output = self
+                .decoder
+                .forward_t(
+                    None,
+                    decoder_attention_mask.as_ref(),
+                    Some(&encoder_output.to_device(device_map.decoder)),
+                    attention_mask.as_ref(),
+                    decoder_input_embeds.as_ref(),
+                    &self.embeddings,
+                    old_layer_states,
+                    train,
+                )
+                .unwrap();
+            decoder_output.hidden_state = decoder_output.hidden_state.to_device(device_map.encoder);
+            decoder_output
+        } else {
+            self.decoder
+                .forward_t(
+                    decoder_input_ids,
+                    decoder_attention_mask,
+                    Some(encoder_output),
+                    attention_mask,
+                    decoder_input_embeds,
+                    &self.embeddings,
+                    old_layer_states,
+                    train,
+                )
+                .unwrap()
+        };
         T5ModelOutput {
             decoder_output: decoder_output.hidden_state,
             encoder_hidden_state: calc_hidden_states,
@@ -434,6 +660,85 @@ impl T5Model {
             all_encoder_attentions,
         }
     }
+
+    /// Forward pass through the encoder only, without running the decoder
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *source_sequence_length*).
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *source_sequence_length*, *hidden_size*) representing the activations of the last encoder hidden state
+    pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
+        self.encoder
+            .forward_t(
+                Some(input_ids),
+                attention_mask,
+                None,
+                None,
+                None,
+                &self.embeddings,
+                None,
+                false,
+            )
+            .unwrap()
+            .hidden_state
+    }
+
+    /// Sentence embeddings from the encoder only, averaging the last encoder hidden state over the
+    /// non-masked positions of each sequence
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *source_sequence_length*).
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked and excluded from the average.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` of shape (*batch size*, *hidden_size*) containing the mean-pooled embeddings
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor, no_grad};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Int64;
+    /// use rust_bert::t5::{T5Config, T5Model};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = T5Config::from_file(config_path);
+    /// # let t5_model: T5Model = T5Model::new(&vs.root(), &config);
+    /// let (batch_size, source_sequence_length) = (64, 128);
+    /// let input_tensor = Tensor::rand(&[batch_size, source_sequence_length], (Int64, device));
+    /// let attention_mask = Tensor::ones(&[batch_size, source_sequence_length], (Int64, device));
+    ///
+    /// let embeddings = no_grad(|| t5_model.encode_mean_pooled(&input_tensor, Some(&attention_mask)));
+    /// ```
+    pub fn encode_mean_pooled(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+    ) -> Tensor {
+        let hidden_state = self.encode(input_ids, attention_mask);
+        let mask = match attention_mask {
+            Some(attention_mask) => attention_mask.to_kind(hidden_state.kind()),
+            None => Tensor::ones(
+                input_ids.size().as_slice(),
+                (hidden_state.kind(), hidden_state.device()),
+            ),
+        }
+        .unsqueeze(-1);
+        let sum_embeddings =
+            (&hidden_state * &mask).sum_dim_intlist([1].as_slice(), false, hidden_state.kind());
+        let num_tokens = mask
+            .sum_dim_intlist([1].as_slice(), false, hidden_state.kind())
+            .clamp_min(1e-9);
+        sum_embeddings / num_tokens
+    }
 }
 
 /// # T5 Model for conditional generation
@@ -446,6 +751,8 @@ pub struct T5ForConditionalGeneration {
     model_dim: f64,
     tie_word_embeddings: bool,
     lm_head: Option<nn::Linear>,
+    pad_token_id: i64,
+    decoder_start_token_id: i64,
 }
 
 impl T5ForConditionalGeneration {
@@ -493,14 +800,34 @@ impl T5ForConditionalGeneration {
             None
         };
 
+        let pad_token_id = config.pad_token_id.unwrap_or(0);
+        let decoder_start_token_id = config.decoder_start_token_id.unwrap_or(pad_token_id);
+
         T5ForConditionalGeneration {
             base_model,
             model_dim: config.d_model as f64,
             tie_word_embeddings,
             lm_head,
+            pad_token_id,
+            decoder_start_token_id,
         }
     }
 
+    /// Places the encoder and decoder of the model on separate devices (see `T5Model::set_device_map`).
+    /// The language model head is placed on the encoder device, with the shared embeddings.
+    ///
+    /// # Arguments
+    ///
+    /// * `var_store` - `VarStore` holding the variables of the model
+    /// * `device_map` - `T5DeviceMap` devices of the encoder and decoder
+    pub fn set_device_map(
+        &mut self,
+        var_store: &nn::VarStore,
+        device_map: T5DeviceMap,
+    ) -> Result<(), RustBertError> {
+        self.base_model.set_device_map(var_store, device_map)
+    }
+
     /// Forward pass through the model
     ///
     /// # Arguments
@@ -604,21 +931,80 @@ impl T5ForConditionalGeneration {
         }
     }
 
-    pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
-        self.base_model
-            .encoder
+    /// Computes the sequence-to-sequence language modeling loss for labeled inputs.
+    /// The decoder inputs are obtained by shifting the labels to the right, starting with the decoder start token.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input tensor of shape (*batch size*, *source_sequence_length*)
+    /// * `attention_mask` - Optional attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked.
+    /// * `labels` - Target token ids of shape (*batch size*, *target_sequence_length*). Positions with a label of -100 are ignored in the loss computation.
+    /// * `train` - boolean flag to turn on/off the dropout layers in the model. Should be set to false for inference.
+    ///
+    /// # Returns
+    ///
+    /// * `Tensor` scalar mean cross-entropy over the non-ignored target positions
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tch::{nn, Device, Tensor};
+    /// # use rust_bert::Config;
+    /// # use std::path::Path;
+    /// # use tch::kind::Kind::Int64;
+    /// use rust_bert::t5::{T5Config, T5ForConditionalGeneration};
+    /// # let config_path = Path::new("path/to/config.json");
+    /// # let device = Device::Cpu;
+    /// # let vs = nn::VarStore::new(device);
+    /// # let config = T5Config::from_file(config_path);
+    /// # let t5_model: T5ForConditionalGeneration = T5ForConditionalGeneration::new(&vs.root(), &config);
+    /// let (batch_size, source_sequence_length, target_sequence_length) = (64, 128, 56);
+    /// let input_tensor = Tensor::rand(&[batch_size, source_sequence_length], (Int64, device));
+    /// let labels = Tensor::rand(&[batch_size, target_sequence_length], (Int64, device));
+    /// let encoder_attention_mask =
+    ///     Tensor::ones(&[batch_size, source_sequence_length], (Int64, device));
+    ///
+    /// let loss = t5_model.forward_loss(&input_tensor, Some(&encoder_attention_mask), &labels, true);
+    /// loss.backward();
+    /// ```
+    pub fn forward_loss(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: Option<&Tensor>,
+        labels: &Tensor,
+        train: bool,
+    ) -> Tensor {
+        let decoder_input_ids = self.shift_right(labels);
+        let lm_logits = self
             .forward_t(
                 Some(input_ids),
                 attention_mask,
                 None,
+                Some(&decoder_input_ids),
                 None,
                 None,
-                &self.base_model.embeddings,
                 None,
-                false,
+                None,
+                train,
             )
-            .unwrap()
-            .hidden_state
+            .decoder_output;
+        token_cross_entropy(&lm_logits, labels)
+    }
+
+    fn shift_right(&self, labels: &Tensor) -> Tensor {
+        let target_length = labels.size()[1];
+        let shifted_labels = labels.zeros_like();
+        shifted_labels
+            .slice(1, 1, target_length, 1)
+            .copy_(&labels.slice(1, 0, target_length - 1, 1));
+        let _ = shifted_labels
+            .select(1, 0)
+            .fill_(self.decoder_start_token_id);
+        shifted_labels.masked_fill(&shifted_labels.eq(IGNORE_LABEL_INDEX), self.pad_token_id)
+    }
+
+    pub fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Tensor {
+        self.base_model.encode(input_ids, attention_mask)
     }
 }
 
@@ -801,6 +1187,28 @@ impl T5ForSentenceEmbeddings {
         input_ids: &Tensor,
         mask: &Tensor,
     ) -> Result<(Tensor, Option<Vec<Tensor>>), RustBertError> {
+        self.forward_with_hidden_states(input_ids, mask)
+            .map(|(hidden_state, _, all_attentions)| (hidden_state, all_attentions))
+    }
+
+    /// Forward pass through the model, also returning the hidden states of all encoder layers
+    ///
+    /// # Arguments
+    ///
+    /// * `input_ids` - Input of shape (*batch size*, *source_sequence_length*).
+    /// * `mask` - Attention mask of shape (*batch size*, *source_sequence_length*) for the encoder positions. Positions with a mask with value 0 will be masked.
+    ///
+    /// # Returns
+    ///
+    /// * Tuple containing:
+    ///   - `Tensor` of shape (*batch size*, *target_sequence_length*, *hidden_size*) representing the activations of the last encoder hidden state
+    ///   - `Option<Vec<Tensor>>` of length *num_encoder_layers* of shape (*batch size*, *target_sequence_length*, *hidden_size*) representing the outputs of all layers of the encoder (before the final layer normalization), if `output_hidden_states` is set in the configuration
+    ///   - `Option<Vec<Tensor>>` of length *num_encoder_layers* of shape (*batch size*, *target_sequence_length*, *hidden_size*)  representing attention weights for all layers of the encoder
+    pub fn forward_with_hidden_states(
+        &self,
+        input_ids: &Tensor,
+        mask: &Tensor,
+    ) -> Result<(Tensor, Option<Vec<Tensor>>, Option<Vec<Tensor>>), RustBertError> {
         let transformer_output = self.encoder.forward_t(
             Some(input_ids),
             Some(mask),
@@ -811,8 +1219,15 @@ impl T5ForSentenceEmbeddings {
             None,
             false,
         )?;
+        let all_hidden_states = transformer_output.all_hidden_states.map(|hidden_states| {
+            hidden_states
+                .into_iter()
+                .map(|hidden_state| hidden_state.transpose(0, 1))
+                .collect()
+        });
         Ok((
             transformer_output.hidden_state,
+            all_hidden_states,
             transformer_output.all_attentions,
         ))
     }
@@ -841,7 +1256,7 @@ pub struct T5ModelOutput {
 
 pub struct T5Generator {
     model: T5ForConditionalGeneration,
-    tokenizer: TokenizerOption,
+    tokenizer: Arc<TokenizerOption>,
     var_store: nn::VarStore,
     generate_config: GenerateConfig,
     bos_token_id: Option<i64>,
@@ -855,6 +1270,7 @@ pub struct T5Generator {
 
 impl T5Generator {
     pub fn new(generate_config: GenerateConfig) -> Result<T5Generator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
 
         let tokenizer = TokenizerOption::from_file(
@@ -871,16 +1287,21 @@ impl T5Generator {
 
     pub fn new_with_tokenizer(
         generate_config: GenerateConfig,
-        tokenizer: TokenizerOption,
+        tokenizer: impl Into<Arc<TokenizerOption>>,
     ) -> Result<T5Generator, RustBertError> {
+        let tokenizer = tokenizer.into();
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = T5Config::from_file(config_path);
+        let mut config = T5Config::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = T5ForConditionalGeneration::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -910,6 +1331,59 @@ impl T5Generator {
             max_position_embeddings,
         })
     }
+
+    /// Places the encoder and decoder of the model on separate devices, e.g. the encoder on the generator device
+    /// and the decoder on a second GPU (see `T5Model::set_device_map`).
+    ///
+    /// # Arguments
+    ///
+    /// * `device_map` - `T5DeviceMap` devices of the encoder and decoder. The encoder device must be the device of the generator.
+    pub fn set_device_map(&mut self, device_map: T5DeviceMap) -> Result<(), RustBertError> {
+        self.model.set_device_map(&self.var_store, device_map)
+    }
+
+    /// Generate text for inputs with individual task prefixes (e.g. mixing summarization and translation
+    /// inputs of a multitask model in a single call). The prefixed inputs are generated as a single batch
+    /// and the outputs are returned in the order of the inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - `&[(P, S)]` Array of (task prefix, text) pairs. The prefix is prepended to the text as is (e.g. `"summarize: "`).
+    /// * `generate_options` - `Option<GenerateOptions>` Optional set of generate options shared by all tasks. If not (or partially) provided, will use the settings provided when creating the generator
+    ///
+    /// # Returns
+    /// * `Result<Vec<GeneratedTextOutput>, RustBertError>` Vector of length *number_of_inputs* x *num_return_sequences* containing the generated texts in the order of the inputs
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use rust_bert::t5::T5Generator;
+    ///
+    /// let t5_generator = T5Generator::new(Default::default())?;
+    /// let inputs = [
+    ///     ("summarize: ", "The tower is 324 metres (1,063 ft) tall, about the same height as an 81-storey building."),
+    ///     ("translate English to French: ", "The house is wonderful."),
+    /// ];
+    /// let output = t5_generator.generate_with_prefixes(&inputs, None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn generate_with_prefixes<P, S>(
+        &self,
+        inputs: &[(P, S)],
+        generate_options: Option<GenerateOptions>,
+    ) -> Result<Vec<GeneratedTextOutput>, RustBertError>
+    where
+        P: AsRef<str>,
+        S: AsRef<str>,
+    {
+        let texts = inputs
+            .iter()
+            .map(|(prefix, text)| format!("{}{}", prefix.as_ref(), text.as_ref()))
+            .collect::<Vec<String>>();
+        self.generate(Some(&texts), generate_options)
+    }
 }
 
 impl PrivateLanguageGenerator<T5ForConditionalGeneration, T5Vocab, T5Tokenizer> for T5Generator {
@@ -950,6 +1424,10 @@ impl PrivateLanguageGenerator<T5ForConditionalGeneration, T5Vocab, T5Tokenizer>
         self.max_position_embeddings
     }
 
+    fn supports_kv_cache_window(&self) -> bool {
+        true
+    }
+
     fn encode(&self, input_ids: &Tensor, attention_mask: Option<&Tensor>) -> Option<Tensor> {
         Some(self.get_model().encode(input_ids, attention_mask))
     }
@@ -987,7 +1465,7 @@ impl PrivateLanguageGenerator<T5ForConditionalGeneration, T5Vocab, T5Tokenizer>
         prompt_text: &[S],
         max_len: Option<i64>,
         pad_token_id: Option<i64>,
-    ) -> Tensor
+    ) -> (Tensor, Tensor)
     where
         S: AsRef<str> + Sync,
     {
@@ -1004,24 +1482,16 @@ impl PrivateLanguageGenerator<T5ForConditionalGeneration, T5Vocab, T5Tokenizer>
             .map(|tokenized_input| tokenized_input.token_ids)
             .collect::<Vec<Vec<i64>>>();
 
-        let max_len = token_ids.iter().map(|input| input.len()).max().unwrap();
-
         let pad_token = match pad_token_id {
             Some(value) => value,
             None => self._get_tokenizer().get_unk_id(),
         };
 
-        let token_ids = token_ids
-            .into_iter()
-            .map(|mut input| {
-                let temp = vec![pad_token; max_len - input.len()];
-                input.extend(temp);
-                input
-            })
-            .map(|tokens| Tensor::of_slice(&tokens).to(self.get_var_store().device()))
-            .collect::<Vec<Tensor>>();
-
-        Tensor::stack(&token_ids, 0)
+        let (token_ids, attention_mask) =
+            self._get_tokenizer()
+                .pad_token_ids(token_ids, Some(pad_token), PaddingSide::Right);
+        let device = self.get_var_store().device();
+        (token_ids.to(device), attention_mask.to(device))
     }
 
     fn reorder_cache(
@@ -14,15 +14,17 @@
 
 use crate::common::dropout::Dropout;
 use crate::xlnet::XLNetConfig;
+use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use tch::nn::init::DEFAULT_KAIMING_UNIFORM;
 use tch::{nn, Kind, Tensor};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 /// # Cache for XLNet attention layers
 /// Stores the cached value of the attention
 pub struct LayerState {
     /// Cached content
+    #[serde(with = "crate::common::tensor_serde")]
     pub prev_content: Tensor,
 }
 
@@ -48,7 +48,7 @@
 //! };
 //! let model = TextGenerationModel::new(generate_config)?;
 //! let input_context = "Once upon a time,";
-//! let output = model.generate(&[input_context], None);
+//! let output = model.generate(&[input_context], None)?;
 //!
 //! # Ok(())
 //! # }
@@ -1630,6 +1630,7 @@ impl XLNetGenerator {
     /// # }
     /// ```
     pub fn new(generate_config: GenerateConfig) -> Result<XLNetGenerator, RustBertError> {
+        generate_config.validate()?;
         let vocab_path = generate_config.vocab_resource.get_local_path()?;
 
         let tokenizer = TokenizerOption::from_file(
@@ -1648,14 +1649,18 @@ impl XLNetGenerator {
         generate_config: GenerateConfig,
         tokenizer: TokenizerOption,
     ) -> Result<XLNetGenerator, RustBertError> {
+        generate_config.validate()?;
         let config_path = generate_config.config_resource.get_local_path()?;
         let weights_path = generate_config.model_resource.get_local_path()?;
         let device = generate_config.device;
 
-        generate_config.validate();
+        generate_config.validate_token_ids(&tokenizer)?;
         let mut var_store = nn::VarStore::new(device);
 
-        let config = XLNetConfig::from_file(config_path);
+        let mut config = XLNetConfig::from_file(config_path);
+        if generate_config.output_attentions {
+            config.output_attentions = Some(true);
+        }
         let model = XLNetLMHeadModel::new(var_store.root(), &config);
         var_store.load(weights_path)?;
 
@@ -1,15 +1,26 @@
 use rust_bert::bart::{
-    BartConfig, BartConfigResources, BartMergesResources, BartModel, BartModelResources,
-    BartVocabResources,
+    BartConfig, BartConfigResources, BartGenerator, BartMergesResources, BartModel, BartModelParts,
+    BartModelResources, BartVocabResources,
 };
+use rust_bert::pipelines::common::{CustomTokenizer, ModelType, TokenizerOption};
+use rust_bert::pipelines::cross_encoder::{CrossEncoderConfig, CrossEncoderModel};
+use rust_bert::pipelines::generation_utils::{GenerateConfig, GenerateOptions, LanguageGenerator};
+use rust_bert::pipelines::hybrid_summarization::{
+    HybridSummarizationConfig, HybridSummarizationModel,
+};
+use rust_bert::pipelines::language_detection::{LanguageDetector, ZeroShotLanguageDetector};
 use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
+use rust_bert::pipelines::translation::Language;
 use rust_bert::pipelines::zero_shot_classification::{
-    ZeroShotClassificationConfig, ZeroShotClassificationModel,
+    NLILabel, ZeroShotClassificationConfig, ZeroShotClassificationModel,
 };
 use rust_bert::resources::{RemoteResource, ResourceProvider};
 use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{RobertaTokenizer, Tokenizer, TruncationStrategy};
-use tch::{nn, Device, Tensor};
+use rust_tokenizers::{TokenIdsWithOffsets, TokenIdsWithSpecialTokens, TokensWithOffsets};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tch::{nn, no_grad, Device, Tensor};
 
 #[test]
 fn bart_lm_model() -> anyhow::Result<()> {
@@ -75,6 +86,59 @@ fn bart_lm_model() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bart_encoder_only_model() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let weights_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+
+    //    Set-up encoder-only model, the decoder weights of the checkpoint are skipped
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let tokenizer: RobertaTokenizer = RobertaTokenizer::from_file(
+        vocab_path.to_str().unwrap(),
+        merges_path.to_str().unwrap(),
+        false,
+        false,
+    )?;
+    let config = BartConfig::from_file(config_path);
+    let bart_model =
+        BartModel::new_with_parts(&vs.root() / "model", &config, BartModelParts::EncoderOnly);
+    vs.load(weights_path)?;
+
+    let variables = vs.variables();
+    assert!(variables
+        .keys()
+        .any(|name| name.starts_with("model.encoder.")));
+    assert!(!variables.keys().any(|name| name.contains("decoder")));
+
+    //    Encode input
+    let input = ["One two three four"];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let input_tensor = Tensor::of_slice(&tokenized_input[0].token_ids)
+        .unsqueeze(0)
+        .to(device);
+    let encoder_output = no_grad(|| bart_model.encode(&input_tensor, None, false));
+
+    assert_eq!(encoder_output.size(), vec!(1, 6, 1024));
+
+    Ok(())
+}
+
 #[test]
 fn bart_summarization_greedy() -> anyhow::Result<()> {
     let config_resource = Box::new(RemoteResource::from_pretrained(
@@ -126,7 +190,7 @@ telescope — scheduled for launch in 2021 — and the European Space Agency's 2
 about exoplanets like K2-18b."];
 
     //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
-    let output = model.summarize(&input);
+    let output = model.summarize(&input)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], " K2-18b is not too hot and not too cold for liquid water to exist. \
@@ -136,6 +200,242 @@ about exoplanets like K2-18b."];
     Ok(())
 }
 
+#[test]
+fn bart_summarization_empty_inputs() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        num_beams: 1,
+        max_length: Some(32),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["", "   "];
+    let output = model.summarize(&input)?;
+
+    assert_eq!(output, vec![String::new(), String::new()]);
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_trim_incomplete_sentence() -> anyhow::Result<()> {
+    let summarization_config = |trim_incomplete_sentence: bool| SummarizationConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::DISTILBART_CNN_6_6,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::DISTILBART_CNN_6_6,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::DISTILBART_CNN_6_6,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::DISTILBART_CNN_6_6,
+        ))),
+        min_length: 0,
+        num_beams: 1,
+        max_length: Some(40),
+        trim_incomplete_sentence,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone - not too hot and not too cold for liquid water to exist. The Montreal team, led by Björn Benneke, \
+used data from the NASA's Hubble telescope to assess changes in the light coming from K2-18b's star as the planet \
+passed between it and Earth."];
+
+    //    The summary is stopped by the maximum length in the middle of a sentence
+    let model = SummarizationModel::new(summarization_config(false))?;
+    let output = model.summarize(&input)?;
+    assert!(!output[0].trim_end().ends_with('.'));
+
+    //    The trailing fragment is removed
+    let model = SummarizationModel::new(summarization_config(true))?;
+    let trimmed_output = model.summarize(&input)?;
+    assert!(trimmed_output[0].ends_with('.'));
+    assert!(trimmed_output[0].len() < output[0].len());
+    assert!(output[0].starts_with(&trimmed_output[0]));
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_decoder_start_token_id() -> anyhow::Result<()> {
+    let summarization_config = SummarizationConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::DISTILBART_CNN_6_6,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::DISTILBART_CNN_6_6,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::DISTILBART_CNN_6_6,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::DISTILBART_CNN_6_6,
+        ))),
+        min_length: 0,
+        num_beams: 1,
+        max_length: Some(32),
+        decoder_start_token_id: Some(0),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::DISTILBART_CNN_6_6,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::DISTILBART_CNN_6_6,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::DISTILBART_CNN_6_6,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::DISTILBART_CNN_6_6,
+        ))),
+        min_length: 0,
+        num_beams: 1,
+        max_length: Some(32),
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let generator = BartGenerator::new(generate_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo."];
+
+    let default_output = generator.generate_indices(Some(&input), None)?;
+    assert_eq!(default_output[0].indices[0], 2);
+
+    //    The overridden decoder start token is used as the first decoder input
+    let generate_options = GenerateOptions {
+        decoder_start_token_id: Some(0),
+        ..Default::default()
+    };
+    let expected_output = generator.generate(Some(&input), Some(generate_options))?;
+    let expected_indices = generator.generate_indices(Some(&input), Some(generate_options))?;
+    assert_eq!(expected_indices[0].indices[0], 0);
+
+    let output = model.summarize(&input)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0], expected_output[0].text);
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_keywords() -> anyhow::Result<()> {
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        BartConfigResources::DISTILBART_CNN_6_6,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        BartVocabResources::DISTILBART_CNN_6_6,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        BartMergesResources::DISTILBART_CNN_6_6,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        BartModelResources::DISTILBART_CNN_6_6,
+    ));
+    let summarization_config = SummarizationConfig {
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        num_beams: 1,
+        max_length: Some(64),
+        output_attentions: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone — not too hot and not too cold for liquid water to exist."];
+
+    let output = model.summarize_with_keywords(&input, 5)?;
+
+    assert_eq!(output.len(), 1);
+    let (summary, keywords) = &output[0];
+    assert!(!summary.trim().is_empty());
+    assert_eq!(keywords.len(), 5);
+    for (keyword, score) in keywords {
+        assert!(input[0].contains(keyword.as_str()));
+        assert!(*score > 0f32);
+    }
+    for window in keywords.windows(2) {
+        assert!(window[0].1 >= window[1].1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bart_summarization_keywords_special_tokens() -> anyhow::Result<()> {
+    let summarization_config = SummarizationConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::DISTILBART_CNN_6_6,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::DISTILBART_CNN_6_6,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::DISTILBART_CNN_6_6,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::DISTILBART_CNN_6_6,
+        ))),
+        num_beams: 1,
+        max_length: Some(64),
+        output_attentions: true,
+        skip_special_tokens: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = SummarizationModel::new(summarization_config)?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo."];
+
+    //    The summary keeps the start and end of sequence tokens
+    let output = model.summarize_with_keywords(&input, 5)?;
+    assert!(output[0].0.contains("</s>"));
+
+    Ok(())
+}
+
 #[test]
 fn bart_summarization_beam_search() -> anyhow::Result<()> {
     let config_resource = Box::new(RemoteResource::from_pretrained(
@@ -187,7 +487,7 @@ telescope — scheduled for launch in 2021 — and the European Space Agency's 2
 about exoplanets like K2-18b."];
 
     //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
-    let output = model.summarize(&input);
+    let output = model.summarize(&input)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], " K2-18b, a planet circling a star in the constellation Leo, is not too hot and not too cold for liquid water to exist. \
@@ -197,6 +497,78 @@ about exoplanets like K2-18b."];
     Ok(())
 }
 
+#[test]
+fn bart_hybrid_summarization() -> anyhow::Result<()> {
+    let config_resource = RemoteResource::from_pretrained(BartConfigResources::DISTILBART_CNN_6_6);
+    let vocab_resource = RemoteResource::from_pretrained(BartVocabResources::DISTILBART_CNN_6_6);
+    let merges_resource = RemoteResource::from_pretrained(BartMergesResources::DISTILBART_CNN_6_6);
+    let model_resource = RemoteResource::from_pretrained(BartModelResources::DISTILBART_CNN_6_6);
+    let tokenizer: RobertaTokenizer = RobertaTokenizer::from_file(
+        vocab_resource.get_local_path()?.to_str().unwrap(),
+        merges_resource.get_local_path()?.to_str().unwrap(),
+        false,
+        false,
+    )?;
+
+    let summarization_config = SummarizationConfig {
+        model_resource: Box::new(model_resource),
+        config_resource: Box::new(config_resource),
+        vocab_resource: Box::new(vocab_resource),
+        merges_resource: Some(Box::new(merges_resource)),
+        num_beams: 1,
+        max_length: Some(64),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let max_input_tokens = 128;
+    let model = HybridSummarizationModel::new(HybridSummarizationConfig {
+        summarization_config,
+        max_input_tokens: Some(max_input_tokens),
+        ..Default::default()
+    })?;
+
+    let input = ["In findings published Tuesday in Cornell University's arXiv by a team of scientists \
+from the University of Montreal and a separate report published Wednesday in Nature Astronomy by a team \
+from University College London (UCL), the presence of water vapour was confirmed in the atmosphere of K2-18b, \
+a planet circling a star in the constellation Leo. This is the first such discovery in a planet in its star's \
+habitable zone — not too hot and not too cold for liquid water to exist. The Montreal team, led by Björn Benneke, \
+used data from the NASA's Hubble telescope to assess changes in the light coming from K2-18b's star as the planet \
+passed between it and Earth. They found that certain wavelengths of light, which are usually absorbed by water, \
+weakened when the planet was in the way, indicating not only does K2-18b have an atmosphere, but the atmosphere \
+contains water in vapour form. The team from UCL then analyzed the Montreal team's data using their own software \
+and confirmed their conclusion. This was not the first time scientists have found signs of water on an exoplanet, \
+but previous discoveries were made on planets with high temperatures or other pronounced differences from Earth. \
+K2-18b was first identified in 2015 by the Kepler space telescope. It is about 110 light-years from Earth and larger \
+but less dense. Its star, a red dwarf, is cooler than the Sun, but the planet's orbit is much closer, such that a year \
+on K2-18b lasts 33 Earth days. According to The Guardian, astronomers were optimistic that NASA's James Webb space \
+telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more \
+about exoplanets like K2-18b."];
+
+    //    Credits: WikiNews, CC BY 2.5 license (https://en.wikinews.org/wiki/Astronomers_find_water_vapour_in_atmosphere_of_exoplanet_K2-18b)
+    let extracted = model.extract(&input)?;
+    let output = model.summarize(&input)?;
+
+    //    The document exceeds the token budget, the extracted sentences fit in it
+    assert!(tokenizer.tokenize(input[0]).len() > max_input_tokens);
+    assert_eq!(extracted.len(), 1);
+    assert!(!extracted[0].is_empty());
+    assert!(tokenizer.tokenize(&extracted[0]).len() <= max_input_tokens);
+    assert!(extracted[0].len() < input[0].len());
+
+    assert_eq!(output.len(), 1);
+    assert!(!output[0].trim().is_empty());
+
+    //    A single sentence exceeding the token budget is truncated
+    let input = [input[0].replace(". ", ", ")];
+    let extracted = model.extract(&input)?;
+    assert!(tokenizer.tokenize(&input[0]).len() > max_input_tokens);
+    assert!(!extracted[0].is_empty());
+    assert!(tokenizer.tokenize(&extracted[0]).len() <= max_input_tokens);
+    assert!(input[0].starts_with(&extracted[0]));
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn bart_zero_shot_classification() -> anyhow::Result<()> {
@@ -230,6 +602,207 @@ fn bart_zero_shot_classification() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_zero_shot_classification_hypothesis_template() -> anyhow::Result<()> {
+    //    Set-up models
+    let default_model = ZeroShotClassificationModel::new(ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    let custom_model = ZeroShotClassificationModel::new(ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        hypothesis_template: "The topic of this text is {}.".to_string(),
+        ..Default::default()
+    })?;
+
+    let input_sentence = "Who are you voting for in 2020?";
+    let candidate_labels = &["politics", "public health", "economy", "sports"];
+
+    let default_output = default_model.predict([input_sentence], candidate_labels, None, 128)?;
+    let custom_output = custom_model.predict([input_sentence], candidate_labels, None, 128)?;
+    let closure_output = default_model.predict(
+        [input_sentence],
+        candidate_labels,
+        Some(Box::new(|label: &str| {
+            format!("The topic of this text is {}.", label)
+        })),
+        128,
+    )?;
+
+    //    The configuration template is applied when no template closure is provided
+    assert_eq!(custom_output[0].text, closure_output[0].text);
+    assert!((custom_output[0].score - closure_output[0].score).abs() < 1e-6);
+    assert!((custom_output[0].score - default_output[0].score).abs() > 1e-4);
+
+    //    The template must contain exactly one placeholder
+    for hypothesis_template in ["This example is about a label.", "This {} is about {}."] {
+        let config = ZeroShotClassificationConfig {
+            device: Device::Cpu,
+            hypothesis_template: hypothesis_template.to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZeroShotClassificationModel::new(config),
+            Err(RustBertError::InvalidConfigurationError(_))
+        ));
+    }
+
+    Ok(())
+}
+
+/// Tokenizer delegating to the BART tokenizer and counting the number of times each text is tokenized
+struct CountingTokenizer {
+    tokenizer: TokenizerOption,
+    calls: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl CustomTokenizer for CountingTokenizer {
+    fn model_type(&self) -> ModelType {
+        ModelType::Bart
+    }
+
+    fn tokenize_with_offsets(&self, text: &str) -> TokensWithOffsets {
+        *self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(text.to_string())
+            .or_insert(0) += 1;
+        self.tokenizer.tokenize_with_offsets(text)
+    }
+
+    fn convert_tokens_to_ids(&self, tokens: &[&str]) -> Vec<i64> {
+        self.tokenizer.convert_tokens_to_ids(tokens)
+    }
+
+    fn convert_ids_to_tokens(&self, token_ids: &[i64]) -> Vec<String> {
+        self.tokenizer.convert_ids_to_tokens(token_ids)
+    }
+
+    fn decode(
+        &self,
+        token_ids: &[i64],
+        skip_special_tokens: bool,
+        clean_up_tokenization_spaces: bool,
+    ) -> String {
+        self.tokenizer
+            .decode(token_ids, skip_special_tokens, clean_up_tokenization_spaces)
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.tokenizer.get_vocab_size()
+    }
+
+    fn unk_id(&self) -> i64 {
+        self.tokenizer.get_unk_id()
+    }
+
+    fn build_input_with_special_tokens(
+        &self,
+        tokens_ids_with_offsets_1: TokenIdsWithOffsets,
+        tokens_ids_with_offsets_2: Option<TokenIdsWithOffsets>,
+    ) -> TokenIdsWithSpecialTokens {
+        let tokenized_input = self
+            .tokenizer
+            .build_input_with_special_tokens(tokens_ids_with_offsets_1, tokens_ids_with_offsets_2);
+        TokenIdsWithSpecialTokens {
+            token_ids: tokenized_input.token_ids,
+            segment_ids: tokenized_input.segment_ids,
+            special_tokens_mask: tokenized_input.special_tokens_mask,
+            token_offsets: tokenized_input.token_offsets,
+            reference_offsets: tokenized_input.reference_offsets,
+            mask: tokenized_input.mask,
+        }
+    }
+
+    fn pad_id(&self) -> Option<i64> {
+        self.tokenizer.get_pad_id()
+    }
+
+    fn sep_id(&self) -> Option<i64> {
+        self.tokenizer.get_sep_id()
+    }
+
+    fn mask_id(&self) -> Option<i64> {
+        self.tokenizer.get_mask_id()
+    }
+
+    fn mask_value(&self) -> Option<&str> {
+        self.tokenizer.get_mask_value()
+    }
+
+    fn bos_id(&self) -> Option<i64> {
+        self.tokenizer.get_bos_id()
+    }
+
+    fn eos_id(&self) -> Option<i64> {
+        self.tokenizer.get_eos_id()
+    }
+
+    fn is_special_token_id(&self, token_id: i64) -> bool {
+        self.tokenizer.is_special_token_id(token_id)
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_zero_shot_classification_shared_hypotheses() -> anyhow::Result<()> {
+    //    Set-up model with a tokenizer counting the tokenized texts
+    let vocab_path =
+        RemoteResource::from_pretrained(BartVocabResources::BART_MNLI).get_local_path()?;
+    let merges_path =
+        RemoteResource::from_pretrained(BartMergesResources::BART_MNLI).get_local_path()?;
+    let calls = Arc::new(Mutex::new(HashMap::new()));
+    let tokenizer = TokenizerOption::Custom(Box::new(CountingTokenizer {
+        tokenizer: TokenizerOption::from_file(
+            ModelType::Bart,
+            vocab_path.to_str().unwrap(),
+            Some(merges_path.to_str().unwrap()),
+            false,
+            None,
+            None,
+        )?,
+        calls: calls.clone(),
+    }));
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let sequence_classification_model =
+        ZeroShotClassificationModel::new_with_tokenizer(zero_shot_config, Arc::new(tokenizer))?;
+
+    let input_sentence = "Who are you voting for in 2020?";
+    let input_sequence_2 = "The prime minister has announced a stimulus package which was widely criticized by the opposition.";
+    let inputs = [input_sentence, input_sequence_2].repeat(50);
+    let candidate_labels = &["politics", "public health", "economy", "sports", "science"];
+
+    let output = sequence_classification_model.predict(
+        inputs.as_slice(),
+        candidate_labels,
+        Some(Box::new(|label: &str| {
+            format!("This example is about {}.", label)
+        })),
+        128,
+    )?;
+
+    //    Each hypothesis is tokenized once for the whole batch, and each input once per occurrence
+    let calls = calls.lock().unwrap();
+    for label in candidate_labels {
+        assert_eq!(calls[&format!("This example is about {}.", label)], 1);
+    }
+    assert_eq!(calls[input_sentence], 50);
+    assert_eq!(calls[input_sequence_2], 50);
+    assert_eq!(calls.values().sum::<usize>(), 105);
+
+    assert_eq!(output.len(), 100);
+    for (label, reference_label) in output.iter().zip(output[..2].iter().cycle()) {
+        assert_eq!(label.text, reference_label.text);
+        assert!((label.score - reference_label.score).abs() < 1e-4);
+    }
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn bart_zero_shot_classification_try_error() -> anyhow::Result<()> {
@@ -305,6 +878,70 @@ fn bart_zero_shot_classification_multilabel() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_zero_shot_classification_multilabel_empty_inputs() -> anyhow::Result<()> {
+    //    Set-up model
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let sequence_classification_model = ZeroShotClassificationModel::new(zero_shot_config)?;
+
+    let candidate_labels = &["politics", "public health", "economy", "sports"];
+    let output = sequence_classification_model.predict_multilabel(
+        ["", "Who are you voting for in 2020?", "  "],
+        candidate_labels,
+        None,
+        128,
+    )?;
+
+    assert_eq!(output.len(), 3);
+    assert!(output[0].is_empty());
+    assert_eq!(output[1].len(), candidate_labels.len());
+    assert!(output[1].iter().all(|label| label.sentence == 1));
+    assert!(output[2].is_empty());
+
+    //    Only empty inputs are not passed to the model
+    let output =
+        sequence_classification_model.predict_multilabel([" "], candidate_labels, None, 128)?;
+    assert_eq!(output.len(), 1);
+    assert!(output[0].is_empty());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_nli_pairs() -> anyhow::Result<()> {
+    //    Set-up model
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let nli_model = ZeroShotClassificationModel::new(zero_shot_config)?;
+
+    let pairs = [
+        (
+            "A man is playing a guitar on stage.",
+            "A man is performing music.",
+        ),
+        (
+            "A man is playing a guitar on stage.",
+            "Nobody is playing an instrument.",
+        ),
+    ];
+    let output = nli_model.predict_nli(&pairs, 128)?;
+
+    assert_eq!(output.len(), 2);
+    for scores in output.iter() {
+        let total = scores.contradiction + scores.neutral + scores.entailment;
+        assert!((total - 1.0).abs() < 1e-4);
+    }
+    assert_eq!(output[0].label(), NLILabel::Entailment);
+    assert_eq!(output[1].label(), NLILabel::Contradiction);
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn bart_zero_shot_classification_multilabel_try_error() -> anyhow::Result<()> {
@@ -332,3 +969,77 @@ fn bart_zero_shot_classification_multilabel_try_error() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_cross_encoder_rank() -> anyhow::Result<()> {
+    //    Set-up model, using the entailment probability of a NLI model as relevance score
+    let cross_encoder_config = CrossEncoderConfig {
+        model_type: ModelType::Bart,
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            BartModelResources::BART_MNLI,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BartConfigResources::BART_MNLI,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            BartVocabResources::BART_MNLI,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            BartMergesResources::BART_MNLI,
+        ))),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let cross_encoder = CrossEncoderModel::new(cross_encoder_config)?;
+
+    let query = "A man is playing a guitar on stage.";
+    let documents = [
+        "The stock market fell sharply this morning.",
+        "A musician is performing.",
+        "Nobody is playing any instrument.",
+    ];
+    let output = cross_encoder.rank(query, &documents)?;
+
+    assert_eq!(output.len(), 3);
+    assert!(output.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    assert_eq!(output[0].index, 1);
+    assert_eq!(output[0].text, documents[1]);
+    let mut indices = output
+        .iter()
+        .map(|document| document.index)
+        .collect::<Vec<usize>>();
+    indices.sort_unstable();
+    assert_eq!(indices, [0, 1, 2]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn bart_zero_shot_language_detection() -> anyhow::Result<()> {
+    //    Set-up model
+    let zero_shot_config = ZeroShotClassificationConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let language_detector = ZeroShotLanguageDetector::new(
+        ZeroShotClassificationModel::new(zero_shot_config)?,
+        vec![
+            Language::French,
+            Language::English,
+            Language::German,
+            Language::Spanish,
+        ],
+    );
+
+    let input = ["The weather is lovely today, so we are going for a walk in the park."];
+    let output = language_detector.detect_languages(&input)?;
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].len(), 4);
+    assert_eq!(output[0][0].0, Language::English);
+    assert!(output[0].windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+    Ok(())
+}
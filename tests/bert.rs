@@ -2,22 +2,36 @@ extern crate anyhow;
 extern crate dirs;
 
 use rust_bert::bert::{
-    BertConfig, BertConfigResources, BertForMaskedLM, BertForMultipleChoice,
-    BertForQuestionAnswering, BertForSequenceClassification, BertForTokenClassification,
+    BertConfig, BertConfigResources, BertEmbeddings, BertForMaskedLM, BertForMultipleChoice,
+    BertForQuestionAnswering, BertForSequenceClassification, BertForTokenClassification, BertModel,
     BertModelResources, BertVocabResources,
 };
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{
+    attention_entropy, set_parallel_tokenization, top_attention_heads, truncate_to_char_boundary,
+    truncate_tokens_to_byte_fallback_boundary, ModelType, OovPolicy, TokenizerOption,
+};
+use rust_bert::pipelines::cross_encoder::CrossEncoderModel;
 use rust_bert::pipelines::masked_language::{MaskedLanguageConfig, MaskedLanguageModel};
-use rust_bert::pipelines::ner::NERModel;
+use rust_bert::pipelines::ner::{annotate, viterbi_decode, Entity, NERModel};
+use rust_bert::pipelines::pipeline_chain::{Pipeline, PipelineChain};
 use rust_bert::pipelines::question_answering::{
     QaInput, QuestionAnsweringConfig, QuestionAnsweringModel,
 };
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::pipelines::sentence_splitter::{PunctuationSentenceSplitter, SentenceSplitter};
+use rust_bert::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
+use rust_bert::weights::load_weights;
 use rust_bert::Config;
+use rust_bert::RustBertError;
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
 use rust_tokenizers::vocab::Vocab;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use tch::{nn, no_grad, Device, Tensor};
+use std::rc::Rc;
+use std::sync::Arc;
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[test]
 fn bert_masked_lm() -> anyhow::Result<()> {
@@ -101,6 +115,90 @@ fn bert_masked_lm() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_custom_position_ids() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let weights_resource = RemoteResource::from_pretrained(BertModelResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+
+    //    Set-up masked LM model
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let config = BertConfig::from_file(config_path);
+    let bert_model = BertForMaskedLM::new(vs.root(), &config);
+    vs.load(weights_path)?;
+
+    //    Define input
+    let input = ["Looks like one thing is missing"];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let input_tensor = Tensor::of_slice(&tokenized_input[0].token_ids)
+        .unsqueeze(0)
+        .to(device);
+    let sequence_length = input_tensor.size()[1];
+
+    //    Default positions (0..sequence_length) and positions shifted as if the sequence
+    //    was packed after another one
+    let default_position_ids = Tensor::arange(sequence_length, (Kind::Int64, device)).unsqueeze(0);
+    let shifted_position_ids = &default_position_ids + 64;
+
+    //    Forward passes
+    let (implicit_output, default_output, shifted_output) = no_grad(|| {
+        let implicit_output = bert_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        );
+        let default_output = bert_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            Some(&default_position_ids),
+            None,
+            None,
+            None,
+            false,
+        );
+        let shifted_output = bert_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            Some(&shifted_position_ids),
+            None,
+            None,
+            None,
+            false,
+        );
+        (implicit_output, default_output, shifted_output)
+    });
+
+    let default_difference = (&implicit_output.prediction_scores
+        - &default_output.prediction_scores)
+        .abs()
+        .max()
+        .double_value(&[]);
+    let shifted_difference = (&implicit_output.prediction_scores
+        - &shifted_output.prediction_scores)
+        .abs()
+        .max()
+        .double_value(&[]);
+
+    assert!(default_difference < 1e-6);
+    assert!(shifted_difference > 1e-3);
+
+    Ok(())
+}
+
 #[test]
 fn bert_masked_lm_pipeline() -> anyhow::Result<()> {
     //    Set-up model
@@ -141,6 +239,154 @@ fn bert_masked_lm_pipeline() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn bert_masked_lm_pipeline_logits() -> anyhow::Result<()> {
+    //    Set-up model
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let bert_config = BertConfig::from_file(config_resource.get_local_path()?);
+    let config = MaskedLanguageConfig {
+        device: Device::Cpu,
+        ..MaskedLanguageConfig::new(
+            ModelType::Bert,
+            RemoteResource::from_pretrained(BertModelResources::BERT),
+            RemoteResource::from_pretrained(BertConfigResources::BERT),
+            RemoteResource::from_pretrained(BertVocabResources::BERT),
+            None,
+            true,
+            None,
+            None,
+            Some(String::from("<mask>")),
+        )
+    };
+    let mask_language_model = MaskedLanguageModel::new(config)?;
+
+    //    Get raw logits
+    let input = [
+        "Hello I am a <mask> student",
+        "Paris is the <mask> of France. It is <mask> in Europe.",
+    ];
+    let logits = mask_language_model.forward_logits(input)?;
+
+    let size = logits.size();
+    assert_eq!(size.len(), 3);
+    assert_eq!(size[0], 2);
+    assert_eq!(size[2], bert_config.vocab_size);
+    assert_eq!(logits.device(), Device::Cpu);
+    //    The top prediction for the mask of the first input ([CLS] Hello I am a [MASK])
+    assert_eq!(
+        logits.get(0).get(5).argmax(-1, false).int64_value(&[]),
+        2267
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bert_max_sequence_length() -> anyhow::Result<()> {
+    //    Set-up model
+    let config = MaskedLanguageConfig::new(
+        ModelType::Bert,
+        RemoteResource::from_pretrained(BertModelResources::BERT),
+        RemoteResource::from_pretrained(BertConfigResources::BERT),
+        RemoteResource::from_pretrained(BertVocabResources::BERT),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<mask>")),
+    );
+    let mask_language_model = MaskedLanguageModel::new(config)?;
+
+    assert_eq!(mask_language_model.max_sequence_length(), Some(512));
+
+    Ok(())
+}
+
+#[test]
+fn bert_masked_lm_pipeline_candidates() -> anyhow::Result<()> {
+    //    Set-up model
+    let config = MaskedLanguageConfig::new(
+        ModelType::Bert,
+        RemoteResource::from_pretrained(BertModelResources::BERT),
+        RemoteResource::from_pretrained(BertConfigResources::BERT),
+        RemoteResource::from_pretrained(BertVocabResources::BERT),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<mask>")),
+    );
+
+    let mask_language_model = MaskedLanguageModel::new(config)?;
+    //    Define input
+    let input = ["Paris is the <mask> of France."];
+    //    "capital" and "college"
+    let candidate_ids = [3007, 2267];
+
+    //    Run model
+    let output = mask_language_model.score_candidates(input, &candidate_ids)?;
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].len(), 1);
+    assert_eq!(output[0][0].len(), 2);
+    assert_eq!(output[0][0][0].id, 3007);
+    assert_eq!(output[0][0][0].text, "capital");
+    assert_eq!(output[0][0][1].id, 2267);
+    assert_eq!(output[0][0][1].text, "college");
+    assert!(output[0][0][0].score > output[0][0][1].score);
+    assert!(output[0][0][0].score + output[0][0][1].score <= 1.0);
+    assert!(output[0][0][1].score > 0.0);
+    Ok(())
+}
+
+#[test]
+fn bert_masked_lm_pseudo_log_likelihood() -> anyhow::Result<()> {
+    //    Set-up model
+    let mask_language_model = MaskedLanguageModel::new(Default::default())?;
+
+    //    Run model
+    let grammatical_score =
+        mask_language_model.pseudo_log_likelihood("The cat is sleeping on the sofa.")?;
+    let ungrammatical_score =
+        mask_language_model.pseudo_log_likelihood("The cat are sleep on sofa the.")?;
+
+    assert!(grammatical_score < 0.0);
+    assert!(grammatical_score > ungrammatical_score);
+    //    Texts longer than a batch of masked variants are scored
+    let long_text = "The cat is sleeping on the sofa. ".repeat(8);
+    assert!(mask_language_model.pseudo_log_likelihood(&long_text)? < grammatical_score);
+    assert_eq!(mask_language_model.pseudo_log_likelihood("")?, 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn bert_estimated_memory_bytes() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let config = BertConfig::from_file(config_path);
+
+    //    The estimate is derived from the configuration before loading the model
+    let estimated_memory_bytes = config.estimated_memory_bytes(Kind::Float);
+
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _bert_model = BertModel::<BertEmbeddings>::new(vs.root(), &config);
+    let num_parameters = vs
+        .variables()
+        .values()
+        .map(|variable| variable.numel() as i64)
+        .sum::<i64>();
+    let actual_memory_bytes = num_parameters * 4;
+
+    assert_eq!(config.num_parameters(), num_parameters);
+    assert!(estimated_memory_bytes >= actual_memory_bytes);
+    assert!(estimated_memory_bytes <= actual_memory_bytes * 3 / 2);
+    assert!((config.estimated_memory_bytes(Kind::Half) - estimated_memory_bytes / 2).abs() <= 1);
+
+    Ok(())
+}
+
 #[test]
 fn bert_for_sequence_classification() -> anyhow::Result<()> {
     //    Resources paths
@@ -204,128 +450,176 @@ fn bert_for_sequence_classification() -> anyhow::Result<()> {
 }
 
 #[test]
-fn bert_for_multiple_choice() -> anyhow::Result<()> {
+fn bert_sequence_classification_num_labels() -> anyhow::Result<()> {
     //    Resources paths
     let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
     let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
     let config_path = config_resource.get_local_path()?;
-    let vocab_path = vocab_resource.get_local_path()?;
 
-    //    Set-up model
-    let device = Device::Cpu;
-    let vs = nn::VarStore::new(device);
-    let tokenizer: BertTokenizer =
-        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    //    Save a randomly initialized classifier with 5 labels, provided as `num_labels` only
+    let model_dir = tempfile::tempdir()?;
     let mut config = BertConfig::from_file(config_path);
-    config.output_attentions = Some(true);
-    config.output_hidden_states = Some(true);
-    let bert_model = BertForMultipleChoice::new(vs.root(), &config);
+    config.id2label = None;
+    config.num_labels = Some(5);
+    let updated_config_path = model_dir.path().join("config.json");
+    std::fs::write(&updated_config_path, serde_json::to_string(&config)?)?;
+    let weights_path = model_dir.path().join("model.ot");
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForSequenceClassification::new(vs.root(), &config)?;
+    vs.save(&weights_path)?;
+
+    //    Set-up classification pipeline
+    let sequence_classification_config = SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..SequenceClassificationConfig::new(
+            ModelType::Bert,
+            LocalResource::from(weights_path.clone()),
+            LocalResource::from(updated_config_path.clone()),
+            vocab_resource.clone(),
+            None,
+            true,
+            None,
+            None,
+        )
+    };
+    let sequence_classification_model =
+        SequenceClassificationModel::new(sequence_classification_config)?;
 
     //    Define input
     let input = [
         "Looks like one thing is missing",
         "It\'s like comparing oranges to apples",
     ];
-    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
-    let max_len = tokenized_input
-        .iter()
-        .map(|input| input.token_ids.len())
-        .max()
-        .unwrap();
-    let tokenized_input = tokenized_input
-        .iter()
-        .map(|input| input.token_ids.clone())
-        .map(|mut input| {
-            input.extend(vec![0; max_len - input.len()]);
-            input
-        })
-        .map(|input| Tensor::of_slice(&(input)))
-        .collect::<Vec<_>>();
-    let input_tensor = Tensor::stack(tokenized_input.as_slice(), 0)
-        .to(device)
-        .unsqueeze(0);
+    let output = sequence_classification_model.predict_multilabel(&input, 0.0)?;
 
-    //    Forward pass
-    let model_output = no_grad(|| bert_model.forward_t(&input_tensor, None, None, None, false));
+    assert_eq!(output.len(), 2);
+    for labels in output.iter() {
+        assert_eq!(labels.len(), 5);
+        for (id, label) in labels.iter().enumerate() {
+            assert_eq!(label.id, id as i64);
+            assert_eq!(label.text, format!("LABEL_{}", id));
+        }
+    }
 
-    assert_eq!(model_output.logits.size(), &[1, 2]);
-    assert_eq!(
-        config.num_hidden_layers as usize,
-        model_output.all_hidden_states.unwrap().len()
-    );
-    assert_eq!(
-        config.num_hidden_layers as usize,
-        model_output.all_attentions.unwrap().len()
-    );
+    //    A configuration mismatching the classification head of the weights is rejected
+    let mut mismatched_config = config.clone();
+    mismatched_config.num_labels = Some(3);
+    let mismatched_config_path = model_dir.path().join("mismatched_config.json");
+    std::fs::write(
+        &mismatched_config_path,
+        serde_json::to_string(&mismatched_config)?,
+    )?;
+    let mismatched_classification_config = SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..SequenceClassificationConfig::new(
+            ModelType::Bert,
+            LocalResource::from(weights_path),
+            LocalResource::from(mismatched_config_path),
+            vocab_resource,
+            None,
+            true,
+            None,
+            None,
+        )
+    };
+    assert!(matches!(
+        SequenceClassificationModel::new(mismatched_classification_config),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
 
     Ok(())
 }
 
 #[test]
-fn bert_for_token_classification() -> anyhow::Result<()> {
+fn bert_cross_encoder_token_type_ids() -> anyhow::Result<()> {
     //    Resources paths
     let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
     let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
     let config_path = config_resource.get_local_path()?;
-    let vocab_path = vocab_resource.get_local_path()?;
 
-    //    Set-up model
-    let device = Device::Cpu;
-    let vs = nn::VarStore::new(device);
-    let tokenizer: BertTokenizer =
-        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    //    Save a randomly initialized classifier with a single relevance label
+    let model_dir = tempfile::tempdir()?;
     let mut config = BertConfig::from_file(config_path);
-    let mut dummy_label_mapping = HashMap::new();
-    dummy_label_mapping.insert(0, String::from("O"));
-    dummy_label_mapping.insert(1, String::from("LOC"));
-    dummy_label_mapping.insert(2, String::from("PER"));
-    dummy_label_mapping.insert(3, String::from("ORG"));
-    config.id2label = Some(dummy_label_mapping);
-    config.output_attentions = Some(true);
-    config.output_hidden_states = Some(true);
-    let bert_model = BertForTokenClassification::new(vs.root(), &config)?;
+    config.id2label = None;
+    config.num_labels = Some(1);
+    let updated_config_path = model_dir.path().join("config.json");
+    std::fs::write(&updated_config_path, serde_json::to_string(&config)?)?;
+    let weights_path = model_dir.path().join("model.ot");
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForSequenceClassification::new(vs.root(), &config)?;
+    vs.save(&weights_path)?;
 
-    //    Define input
-    let input = [
-        "Looks like one thing is missing",
-        "It\'s like comparing oranges to apples",
-    ];
-    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
-    let max_len = tokenized_input
-        .iter()
-        .map(|input| input.token_ids.len())
-        .max()
-        .unwrap();
-    let tokenized_input = tokenized_input
-        .iter()
-        .map(|input| input.token_ids.clone())
-        .map(|mut input| {
-            input.extend(vec![0; max_len - input.len()]);
-            input
-        })
-        .map(|input| Tensor::of_slice(&(input)))
-        .collect::<Vec<_>>();
-    let input_tensor = Tensor::stack(tokenized_input.as_slice(), 0).to(device);
+    //    Set-up cross-encoder
+    let cross_encoder = CrossEncoderModel::new(SequenceClassificationConfig {
+        device: Device::Cpu,
+        ..SequenceClassificationConfig::new(
+            ModelType::Bert,
+            LocalResource::from(weights_path),
+            LocalResource::from(updated_config_path),
+            vocab_resource,
+            None,
+            true,
+            None,
+            None,
+        )
+    })?;
 
-    //    Forward pass
-    let model_output =
-        no_grad(|| bert_model.forward_t(Some(&input_tensor), None, None, None, None, false));
+    //    Encode the pairs
+    let query = "Where is the Eiffel tower?";
+    let documents = ["The Eiffel tower is located in Paris.", "It is tall."];
+    let mut input = cross_encoder.encode(query, &documents)?;
+    assert_eq!(input.token_type_ids.size(), input.input_ids.size());
 
-    assert_eq!(model_output.logits.size(), &[2, 11, 4]);
-    assert_eq!(
-        config.num_hidden_layers as usize,
-        model_output.all_hidden_states.unwrap().len()
-    );
-    assert_eq!(
-        config.num_hidden_layers as usize,
-        model_output.all_attentions.unwrap().len()
-    );
+    //    The query tokens, up to the first separator, belong to the first segment and the document tokens to the second
+    let sep_id = 102;
+    for document_index in 0..documents.len() as i64 {
+        let token_ids = input
+            .input_ids
+            .get(document_index)
+            .iter::<i64>()?
+            .collect::<Vec<i64>>();
+        let attention_mask = input
+            .attention_mask
+            .get(document_index)
+            .iter::<i64>()?
+            .collect::<Vec<i64>>();
+        let token_type_ids = input
+            .token_type_ids
+            .get(document_index)
+            .iter::<i64>()?
+            .collect::<Vec<i64>>();
+        let first_sep_position = token_ids.iter().position(|id| *id == sep_id).unwrap();
+        for (position, (token_type_id, mask)) in
+            token_type_ids.iter().zip(attention_mask.iter()).enumerate()
+        {
+            let expected_token_type_id = if position > first_sep_position && *mask == 1 {
+                1
+            } else {
+                0
+            };
+            assert_eq!(*token_type_id, expected_token_type_id);
+        }
+    }
+
+    //    Custom token type ids are passed to the model
+    let scores = cross_encoder.score(&input)?;
+    let ranked_documents = cross_encoder.rank(query, &documents)?;
+    for document in ranked_documents.iter() {
+        assert!((document.score - scores[document.index]).abs() < 1e-6);
+    }
+    input.token_type_ids = input.token_type_ids.zeros_like();
+    let single_segment_scores = cross_encoder.score(&input)?;
+    assert_eq!(single_segment_scores.len(), 2);
+    assert!(scores
+        .iter()
+        .zip(single_segment_scores.iter())
+        .any(|(score, single_segment_score)| (score - single_segment_score).abs() > 1e-6));
 
     Ok(())
 }
 
 #[test]
-fn bert_for_question_answering() -> anyhow::Result<()> {
+fn bert_attention_entropy() -> anyhow::Result<()> {
     //    Resources paths
     let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
     let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
@@ -339,13 +633,248 @@ fn bert_for_question_answering() -> anyhow::Result<()> {
         BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
     let mut config = BertConfig::from_file(config_path);
     config.output_attentions = Some(true);
-    config.output_hidden_states = Some(true);
-    let bert_model = BertForQuestionAnswering::new(vs.root(), &config);
+    let bert_model = BertForMaskedLM::new(vs.root(), &config);
 
     //    Define input
-    let input = [
-        "Looks like one thing is missing",
-        "It\'s like comparing oranges to apples",
+    let input = ["Looks like one thing is missing"];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let seq_len = tokenized_input[0].token_ids.len();
+    let input_tensor = Tensor::of_slice(&tokenized_input[0].token_ids)
+        .unsqueeze(0)
+        .to(device);
+
+    //    Forward pass
+    let model_output = no_grad(|| {
+        bert_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let entropy = attention_entropy(&model_output.all_attentions.unwrap())?;
+
+    assert_eq!(entropy.len(), config.num_hidden_layers as usize);
+    let max_entropy = (seq_len as f32).ln();
+    for layer_entropy in entropy {
+        assert_eq!(layer_entropy.len(), config.num_attention_heads as usize);
+        for head_entropy in layer_entropy {
+            assert!(head_entropy >= 0.0);
+            assert!(head_entropy <= max_entropy + 1e-4);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bert_top_attention_heads() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let mut config = BertConfig::from_file(config_path);
+    config.output_attentions = Some(true);
+    let bert_model = BertForMaskedLM::new(vs.root(), &config);
+
+    //    Define input
+    let input = ["My name is Amy. I live in Paris."];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let seq_len = tokenized_input[0].token_ids.len() as i64;
+    let input_tensor = Tensor::of_slice(&tokenized_input[0].token_ids)
+        .unsqueeze(0)
+        .to(device);
+
+    //    Forward pass
+    let model_output = no_grad(|| {
+        bert_model.forward_t(
+            Some(&input_tensor),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let attentions = model_output.all_attentions.unwrap();
+    let num_heads = (config.num_hidden_layers * config.num_attention_heads) as usize;
+
+    //    Heads attending to the `[CLS]` token and to the tokens of an entity
+    for token_positions in [vec![0], vec![4, 5]] {
+        let top_heads = top_attention_heads(&attentions, &token_positions, usize::MAX)?;
+        assert_eq!(top_heads.len(), num_heads);
+        assert!(top_heads.windows(2).all(|pair| pair[0].2 >= pair[1].2));
+        for (layer, head, score) in &top_heads {
+            assert!(*layer < config.num_hidden_layers as usize);
+            assert!(*head < config.num_attention_heads as usize);
+            assert!((0.0..=1.0 + 1e-4).contains(score));
+        }
+
+        let top_5_heads = top_attention_heads(&attentions, &token_positions, 5)?;
+        assert_eq!(top_5_heads, top_heads[..5]);
+    }
+
+    assert!(top_attention_heads(&attentions, &[seq_len], 5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bert_for_multiple_choice() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let mut config = BertConfig::from_file(config_path);
+    config.output_attentions = Some(true);
+    config.output_hidden_states = Some(true);
+    let bert_model = BertForMultipleChoice::new(vs.root(), &config);
+
+    //    Define input
+    let input = [
+        "Looks like one thing is missing",
+        "It\'s like comparing oranges to apples",
+    ];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let max_len = tokenized_input
+        .iter()
+        .map(|input| input.token_ids.len())
+        .max()
+        .unwrap();
+    let tokenized_input = tokenized_input
+        .iter()
+        .map(|input| input.token_ids.clone())
+        .map(|mut input| {
+            input.extend(vec![0; max_len - input.len()]);
+            input
+        })
+        .map(|input| Tensor::of_slice(&(input)))
+        .collect::<Vec<_>>();
+    let input_tensor = Tensor::stack(tokenized_input.as_slice(), 0)
+        .to(device)
+        .unsqueeze(0);
+
+    //    Forward pass
+    let model_output = no_grad(|| bert_model.forward_t(&input_tensor, None, None, None, false));
+
+    assert_eq!(model_output.logits.size(), &[1, 2]);
+    assert_eq!(
+        config.num_hidden_layers as usize,
+        model_output.all_hidden_states.unwrap().len()
+    );
+    assert_eq!(
+        config.num_hidden_layers as usize,
+        model_output.all_attentions.unwrap().len()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bert_for_token_classification() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let mut config = BertConfig::from_file(config_path);
+    let mut dummy_label_mapping = HashMap::new();
+    dummy_label_mapping.insert(0, String::from("O"));
+    dummy_label_mapping.insert(1, String::from("LOC"));
+    dummy_label_mapping.insert(2, String::from("PER"));
+    dummy_label_mapping.insert(3, String::from("ORG"));
+    config.id2label = Some(dummy_label_mapping);
+    config.output_attentions = Some(true);
+    config.output_hidden_states = Some(true);
+    let bert_model = BertForTokenClassification::new(vs.root(), &config)?;
+
+    //    Define input
+    let input = [
+        "Looks like one thing is missing",
+        "It\'s like comparing oranges to apples",
+    ];
+    let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let max_len = tokenized_input
+        .iter()
+        .map(|input| input.token_ids.len())
+        .max()
+        .unwrap();
+    let tokenized_input = tokenized_input
+        .iter()
+        .map(|input| input.token_ids.clone())
+        .map(|mut input| {
+            input.extend(vec![0; max_len - input.len()]);
+            input
+        })
+        .map(|input| Tensor::of_slice(&(input)))
+        .collect::<Vec<_>>();
+    let input_tensor = Tensor::stack(tokenized_input.as_slice(), 0).to(device);
+
+    //    Forward pass
+    let model_output =
+        no_grad(|| bert_model.forward_t(Some(&input_tensor), None, None, None, None, false));
+
+    assert_eq!(model_output.logits.size(), &[2, 11, 4]);
+    assert_eq!(
+        config.num_hidden_layers as usize,
+        model_output.all_hidden_states.unwrap().len()
+    );
+    assert_eq!(
+        config.num_hidden_layers as usize,
+        model_output.all_attentions.unwrap().len()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bert_for_question_answering() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let mut config = BertConfig::from_file(config_path);
+    config.output_attentions = Some(true);
+    config.output_hidden_states = Some(true);
+    let bert_model = BertForQuestionAnswering::new(vs.root(), &config);
+
+    //    Define input
+    let input = [
+        "Looks like one thing is missing",
+        "It\'s like comparing oranges to apples",
     ];
     let tokenized_input = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
     let max_len = tokenized_input
@@ -420,65 +949,836 @@ fn bert_pre_trained_ner() -> anyhow::Result<()> {
 }
 
 #[test]
-fn bert_pre_trained_ner_full_entities() -> anyhow::Result<()> {
+fn bert_pre_trained_ner_score_temperature() -> anyhow::Result<()> {
     //    Set-up model
-    let ner_model = NERModel::new(Default::default())?;
+    let mut ner_model = NERModel::new(Default::default())?;
 
     //    Define input
-    let input = ["Asked John Smith about Acme Corp", "Let's go to New York!"];
+    let input = ["My name is Amy. I live in Paris."];
 
-    //    Run model
-    let output = ner_model.predict_full_entities(&input);
+    //    Run model with the default and a higher temperature
+    let output = ner_model.predict(&input);
+    assert!(ner_model.set_score_temperature(0.0).is_err());
+    ner_model.set_score_temperature(2.0)?;
+    let calibrated_output = ner_model.predict(&input);
 
-    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].len(), calibrated_output[0].len());
+    for (entity, calibrated_entity) in output[0].iter().zip(calibrated_output[0].iter()) {
+        assert_eq!(entity.word, calibrated_entity.word);
+        assert_eq!(entity.label, calibrated_entity.label);
+        assert!(calibrated_entity.score < entity.score);
+    }
 
-    assert_eq!(output[0][0].word, "John Smith");
-    assert!((output[0][0].score - 0.9872).abs() < 1e-4);
-    assert_eq!(output[0][0].label, "PER");
+    Ok(())
+}
 
-    assert_eq!(output[0][1].word, "Acme Corp");
-    assert!((output[0][1].score - 0.9622).abs() < 1e-4);
-    assert_eq!(output[0][1].label, "ORG");
+#[test]
+fn bert_pre_trained_ner_max_entities() -> anyhow::Result<()> {
+    //    Set-up model
+    let mut ner_model = NERModel::new(Default::default())?;
 
-    assert_eq!(output[1][0].word, "New York");
-    assert!((output[1][0].score - 0.9991).abs() < 1e-4);
-    assert_eq!(output[1][0].label, "LOC");
+    //    Define input
+    let input = ["Amy, John and Paul met Mary in Paris, London, Berlin and Rome."];
+
+    //    Run model with and without an entity limit
+    let output = ner_model.predict(&input);
+    ner_model.set_max_entities(Some(3));
+    let limited_output = ner_model.predict(&input);
+
+    assert!(output[0].len() > 3);
+    assert_eq!(limited_output[0].len(), 3);
+    let mut scores = output[0]
+        .iter()
+        .map(|entity| entity.score)
+        .collect::<Vec<f64>>();
+    scores.sort_by(|a, b| b.partial_cmp(a).unwrap());
+    for entity in limited_output[0].iter() {
+        assert!(entity.score >= scores[2]);
+    }
+    for pair in limited_output[0].windows(2) {
+        assert!(pair[0].offset.begin < pair[1].offset.begin);
+    }
 
     Ok(())
 }
 
 #[test]
-fn bert_question_answering() -> anyhow::Result<()> {
-    //    Set-up question answering model
-    let config = QuestionAnsweringConfig {
-        model_type: ModelType::Bert,
-        model_resource: Box::new(RemoteResource::from_pretrained(BertModelResources::BERT_QA)),
-        config_resource: Box::new(RemoteResource::from_pretrained(
-            BertConfigResources::BERT_QA,
-        )),
-        vocab_resource: Box::new(RemoteResource::from_pretrained(BertVocabResources::BERT_QA)),
-        lower_case: false,
-        strip_accents: Some(false),
-        add_prefix_space: None,
-        device: Device::Cpu,
-        ..Default::default()
-    };
-
-    let qa_model = QuestionAnsweringModel::new(config)?;
+fn bert_pre_trained_ner_empty_inputs() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
 
     //    Define input
-    let question = String::from("Where does Amy live ?");
-    let context = String::from("Amy lives in Amsterdam");
-    let qa_input = QaInput { question, context };
+    let input = ["", "My name is Amy. I live in Paris.", "  "];
 
-    let answers = qa_model.predict(&[qa_input], 1, 32);
+    //    Run model
+    let output = ner_model.predict(&input);
 
-    assert_eq!(answers.len(), 1usize);
-    assert_eq!(answers[0].len(), 1usize);
-    assert_eq!(answers[0][0].start, 13);
-    assert_eq!(answers[0][0].end, 22);
+    assert_eq!(output.len(), 3);
+    assert!(output[0].is_empty());
+    assert_eq!(output[1].len(), 2);
+    assert!(output[2].is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn bert_pre_trained_ner_multibyte_truncation() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
+
+    //    Define input, truncated in the middle of the 2-bytes character "ü"
+    let text = "My name is Amélie and I live in Zürich.";
+    let truncation_point = text.find('ü').unwrap() + 1;
+    assert!(!text.is_char_boundary(truncation_point));
+    let input = [truncate_to_char_boundary(text, truncation_point)];
+    assert_eq!(input[0], "My name is Amélie and I live in Z");
+
+    //    Run model
+    let output = ner_model.predict(&input);
+
+    assert_eq!(output.len(), 1);
+    assert!(!output[0].is_empty());
+    let input_length = input[0].chars().count() as u32;
+    for entity in &output[0] {
+        assert!(!entity.word.is_empty());
+        assert!(entity.offset.begin <= entity.offset.end);
+        assert!(entity.offset.end <= input_length);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn byte_fallback_truncation() -> anyhow::Result<()> {
+    //    "😀" is encoded over 4 byte-fallback tokens
+    let tokens = [
+        "▁Smile", "<0xF0>", "<0x9F>", "<0x98>", "<0x80>", "▁now", "<0xC3>", "<0xA9>",
+    ];
+
+    //    Truncating just before the end of the emoji drops the partial sequence
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 4),
+        ["▁Smile"]
+    );
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 5),
+        &tokens[..5]
+    );
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 7),
+        &tokens[..6]
+    );
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 10),
+        tokens
+    );
+
+    //    Regular tokens are truncated as usual
+    let tokens = ["▁Hello", "▁world", "<0x0A>", "!"];
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 3),
+        &tokens[..3]
+    );
+    assert_eq!(
+        truncate_tokens_to_byte_fallback_boundary(&tokens, 1),
+        ["▁Hello"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bert_pre_trained_ner_full_entities() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
+
+    //    Define input
+    let input = ["Asked John Smith about Acme Corp", "Let's go to New York!"];
+
+    //    Run model
+    let output = ner_model.predict_full_entities(&input);
+
+    assert_eq!(output.len(), 2);
+
+    assert_eq!(output[0][0].word, "John Smith");
+    assert!((output[0][0].score - 0.9872).abs() < 1e-4);
+    assert_eq!(output[0][0].label, "PER");
+
+    assert_eq!(output[0][1].word, "Acme Corp");
+    assert!((output[0][1].score - 0.9622).abs() < 1e-4);
+    assert_eq!(output[0][1].label, "ORG");
+
+    assert_eq!(output[1][0].word, "New York");
+    assert!((output[1][0].score - 0.9991).abs() < 1e-4);
+    assert_eq!(output[1][0].label, "LOC");
+
+    Ok(())
+}
+
+#[test]
+fn sentence_splitter_abbreviations() -> anyhow::Result<()> {
+    let splitter = PunctuationSentenceSplitter::default();
+    let text = "Dr. Smith met J. R. Tolkien at 10 a.m. today. Bring fruits, e.g. apples or pears! \
+        Is it \"ready?\" It costs 3.50 dollars.  ";
+
+    let sentences = splitter.split(text);
+
+    assert_eq!(
+        sentences,
+        [
+            "Dr. Smith met J. R. Tolkien at 10 a.m. today.",
+            "Bring fruits, e.g. apples or pears!",
+            "Is it \"ready?\"",
+            "It costs 3.50 dollars.",
+        ]
+    );
+    for (span, sentence) in splitter.sentence_spans(text).into_iter().zip(sentences) {
+        assert_eq!(&text[span], sentence);
+    }
+
+    //    Without abbreviations, splitting only relies on the punctuation
+    let splitter = PunctuationSentenceSplitter::new::<&str>(&[]);
+    assert_eq!(
+        splitter.split("Ask Dr. Smith. He knows."),
+        ["Ask Dr.", "Smith.", "He knows."]
+    );
+
+    Ok(())
+}
+
+struct SplitWords;
+
+impl Pipeline for SplitWords {
+    type Input = String;
+    type Output = Vec<String>;
+
+    fn process(&self, inputs: &[String]) -> Result<Vec<Vec<String>>, RustBertError> {
+        Ok(inputs
+            .iter()
+            .map(|input| input.split_whitespace().map(String::from).collect())
+            .collect())
+    }
+}
+
+struct CountWords {
+    batch_sizes: Rc<RefCell<Vec<usize>>>,
+}
+
+impl Pipeline for CountWords {
+    type Input = Vec<String>;
+    type Output = usize;
+
+    fn process(&self, inputs: &[Vec<String>]) -> Result<Vec<usize>, RustBertError> {
+        self.batch_sizes.borrow_mut().push(inputs.len());
+        Ok(inputs.iter().map(|words| words.len()).collect())
+    }
+}
+
+#[test]
+fn pipeline_chain_trivial_stages() -> anyhow::Result<()> {
+    let input = [
+        String::from("One"),
+        String::from("Two words"),
+        String::from("Now three words"),
+    ];
+
+    //    Without batch size, all inputs are processed at once
+    let batch_sizes = Rc::new(RefCell::new(vec![]));
+    let pipeline = SplitWords.then(CountWords {
+        batch_sizes: batch_sizes.clone(),
+    });
+    let output = pipeline.process(&input)?;
+    assert_eq!(output, [1, 2, 3]);
+    assert_eq!(*batch_sizes.borrow(), [3]);
+
+    //    Inputs are passed through the chain by batches
+    let batch_sizes = Rc::new(RefCell::new(vec![]));
+    let counter = CountWords {
+        batch_sizes: batch_sizes.clone(),
+    };
+    let pipeline = PipelineChain::new(SplitWords, counter).with_batch_size(2);
+    let output = pipeline.process(&input)?;
+    assert_eq!(output, [1, 2, 3]);
+    assert_eq!(*batch_sizes.borrow(), [2, 1]);
+    assert!(pipeline.process(&[])?.is_empty());
+
+    //    Invalid batch size
+    let pipeline = SplitWords
+        .then(CountWords {
+            batch_sizes: Rc::new(RefCell::new(vec![])),
+        })
+        .with_batch_size(0);
+    assert!(pipeline.process(&input).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bert_pre_trained_ner_scores() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
+
+    //    Define input
+    let input = ["My name is Amy. I live in Paris."];
+
+    //    Run model
+    let output = ner_model.predict_scores(&input);
+    let label_mapping = ner_model.get_label_mapping();
+
+    assert_eq!(output.len(), 1);
+    assert!(!output[0].is_empty());
+    for token in output[0].iter() {
+        assert_eq!(token.label_scores.len(), label_mapping.len());
+        assert!((token.label_scores.iter().sum::<f64>() - 1.0).abs() < 1e-4);
+    }
+
+    //    The argmax of the distributions matches the predicted entities
+    let amy_token = output[0].iter().find(|token| token.text == "Amy").unwrap();
+    let (best_label_index, _) = amy_token
+        .label_scores
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .unwrap();
+    assert_eq!(label_mapping[&(best_label_index as i64)], "I-PER");
+
+    Ok(())
+}
+
+#[test]
+fn ner_viterbi_decoding_bio_transitions() {
+    let label_mapping = HashMap::from([
+        (0, "O".to_string()),
+        (1, "B-PER".to_string()),
+        (2, "I-PER".to_string()),
+    ]);
+
+    //    The per-token argmax (O, I-PER, I-PER) contains an illegal O -> I-PER transition
+    let label_scores = [
+        vec![0.9, 0.05, 0.05],
+        vec![0.1, 0.3, 0.6],
+        vec![0.2, 0.1, 0.7],
+    ];
+    assert_eq!(viterbi_decode(&label_scores, &label_mapping), [0, 1, 2]);
+
+    //    Sequences cannot start with an I- label
+    let label_scores = [vec![0.2, 0.3, 0.5]];
+    assert_eq!(viterbi_decode(&label_scores, &label_mapping), [1]);
+
+    assert!(viterbi_decode(&[], &label_mapping).is_empty());
+}
+
+#[test]
+fn ner_annotate_entities() {
+    let entity = |label: &str, begin: u32, end: u32| Entity {
+        word: String::new(),
+        score: 1.0,
+        label: label.to_string(),
+        offset: Offset { begin, end },
+    };
+
+    //    Nested entities
+    let text = "The University of Paris is old.";
+    let entities = [entity("LOC", 18, 23), entity("ORG", 4, 23)];
+    assert_eq!(
+        annotate(text, &entities),
+        "The <ORG>University of <LOC>Paris</LOC></ORG> is old."
+    );
+
+    //    Adjacent entities and entities overlapping the end of an enclosing entity
+    let text = "Amy Smith lives in Paris.";
+    let entities = [
+        entity("PER", 0, 3),
+        entity("PER", 3, 9),
+        entity("LOC", 19, 24),
+        entity("MISC", 16, 22),
+        entity("LOC", 10, 10),
+    ];
+    assert_eq!(
+        annotate(text, &entities),
+        "<PER>Amy</PER><PER> Smith</PER> lives <MISC>in <LOC>Par</LOC></MISC>is."
+    );
+}
+
+#[test]
+fn bert_pre_trained_ner_sentences() -> anyhow::Result<()> {
+    //    Set-up model
+    let ner_model = NERModel::new(Default::default())?;
+    let sentence_splitter = PunctuationSentenceSplitter::default();
+
+    //    Define input
+    let input = [
+        "My name is Amy. I live in Paris.",
+        "Paris is a city in France.",
+    ];
+
+    //    Run model
+    let output = ner_model.predict_sentences(&input, &sentence_splitter);
+    let reference_output = ner_model.predict(&input);
+
+    assert_eq!(output.len(), 2);
+    for (entities, reference_entities) in output.iter().zip(reference_output.iter()) {
+        assert_eq!(entities.len(), reference_entities.len());
+        for (entity, reference_entity) in entities.iter().zip(reference_entities.iter()) {
+            assert_eq!(entity.word, reference_entity.word);
+            assert_eq!(entity.label, reference_entity.label);
+            assert_eq!(entity.offset, reference_entity.offset);
+        }
+    }
+    assert_eq!(output[0][1].word, "Paris");
+    assert_eq!(output[0][1].offset.begin, 26);
+
+    Ok(())
+}
+
+#[test]
+fn bert_question_answering() -> anyhow::Result<()> {
+    //    Set-up question answering model
+    let config = QuestionAnsweringConfig {
+        model_type: ModelType::Bert,
+        model_resource: Box::new(RemoteResource::from_pretrained(BertModelResources::BERT_QA)),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            BertConfigResources::BERT_QA,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(BertVocabResources::BERT_QA)),
+        lower_case: false,
+        strip_accents: Some(false),
+        add_prefix_space: None,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+
+    let qa_model = QuestionAnsweringModel::new(config)?;
+
+    //    Define input
+    let question = String::from("Where does Amy live ?");
+    let context = String::from("Amy lives in Amsterdam");
+    let qa_input = QaInput { question, context };
+
+    let answers = qa_model.predict(&[qa_input], 1, 32);
+
+    assert_eq!(answers.len(), 1usize);
+    assert_eq!(answers[0].len(), 1usize);
+    assert_eq!(answers[0][0].start, 13);
+    assert_eq!(answers[0][0].end, 22);
     assert!((answers[0][0].score - 0.9806).abs() < 1e-4);
     assert_eq!(answers[0][0].answer, "Amsterdam");
 
     Ok(())
 }
+
+#[test]
+fn bert_decode_unk_replacement() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up tokenizer
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        true,
+        None,
+        None,
+    )?;
+
+    let unk_id = tokenizer.get_unk_id();
+    let mut token_ids = tokenizer.convert_tokens_to_ids(&["[CLS]"]);
+    token_ids.extend(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize("the dog")));
+    token_ids.push(unk_id);
+    token_ids.extend(tokenizer.convert_tokens_to_ids(&tokenizer.tokenize("barked")));
+    token_ids.push(tokenizer.get_sep_id().unwrap());
+
+    //    Unknown tokens are skipped as special tokens by default
+    assert_eq!(tokenizer.decode(&token_ids, true, true), "the dog barked");
+
+    let (text, _) =
+        tokenizer.decode_lossy_with_unk_replacement(&token_ids, true, true, "<unknown>");
+    assert_eq!(text, "the dog <unknown> barked");
+    let (text, _) = tokenizer.decode_lossy_with_unk_replacement(&token_ids, false, true, "_");
+    assert_eq!(text, "[CLS] the dog _ barked [SEP]");
+    let (text, _) = tokenizer.decode_lossy_with_unk_replacement(&token_ids, false, true, "");
+    assert_eq!(text, "[CLS] the dog barked [SEP]");
+
+    Ok(())
+}
+
+#[test]
+fn bert_parallel_tokenization() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up tokenizer
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        true,
+        None,
+        None,
+    )?;
+
+    //    Define input
+    let input = (0..64)
+        .map(|index| {
+            format!(
+                "Sentence number {} of the batch, with {} words.",
+                index,
+                index % 7
+            )
+        })
+        .collect::<Vec<String>>();
+    let input_pairs = input
+        .iter()
+        .map(|text| (text.as_str(), "Looks like one thing is missing"))
+        .collect::<Vec<(&str, &str)>>();
+
+    set_parallel_tokenization(false, 0);
+    let serial_output = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let serial_pair_output =
+        tokenizer.encode_pair_list(&input_pairs, 128, &TruncationStrategy::LongestFirst, 0);
+
+    set_parallel_tokenization(true, 8);
+    let parallel_output = tokenizer.encode_list(&input, 128, &TruncationStrategy::LongestFirst, 0);
+    let parallel_pair_output =
+        tokenizer.encode_pair_list(&input_pairs, 128, &TruncationStrategy::LongestFirst, 0);
+
+    assert_eq!(serial_output.len(), input.len());
+    for (serial, parallel) in serial_output
+        .iter()
+        .chain(serial_pair_output.iter())
+        .zip(parallel_output.iter().chain(parallel_pair_output.iter()))
+    {
+        assert_eq!(serial.token_ids, parallel.token_ids);
+        assert_eq!(serial.segment_ids, parallel.segment_ids);
+        assert_eq!(serial.token_offsets, parallel.token_offsets);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bert_pipelines_shared_tokenizer() -> anyhow::Result<()> {
+    //    Set-up a tokenizer shared by the pipelines (DistilBERT SST-2 uses the BERT uncased vocabulary)
+    let vocab_path = RemoteResource::from_pretrained(BertVocabResources::BERT).get_local_path()?;
+    let tokenizer = Arc::new(TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        true,
+        None,
+        None,
+    )?);
+
+    let masked_lm_config = MaskedLanguageConfig::new(
+        ModelType::Bert,
+        RemoteResource::from_pretrained(BertModelResources::BERT),
+        RemoteResource::from_pretrained(BertConfigResources::BERT),
+        RemoteResource::from_pretrained(BertVocabResources::BERT),
+        None,
+        true,
+        None,
+        None,
+        Some(String::from("<mask>")),
+    );
+    let mask_language_model =
+        MaskedLanguageModel::new_with_tokenizer(masked_lm_config, tokenizer.clone())?;
+    let sequence_classification_model = SequenceClassificationModel::new_with_tokenizer(
+        SequenceClassificationConfig::default(),
+        tokenizer.clone(),
+    )?;
+    assert_eq!(Arc::strong_count(&tokenizer), 3);
+
+    //    Run models
+    let masked_lm_output = mask_language_model.predict(["Hello I am a <mask> student"])?;
+    let sequence_classification_output =
+        sequence_classification_model.predict(["Probably my all-time favorite movie."])?;
+
+    assert_eq!(masked_lm_output[0][0].text, "college");
+    assert!((masked_lm_output[0][0].score - 8.0919).abs() < 1e-4);
+    assert_eq!(sequence_classification_output[0].text, "POSITIVE");
+
+    Ok(())
+}
+
+#[test]
+fn bert_load_weights_name_mapping() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let weights_resource = RemoteResource::from_pretrained(BertModelResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+    let config = BertConfig::from_file(config_path);
+
+    //    Save the pretrained weights with a different naming scheme and an extra parameter
+    let model_dir = tempfile::tempdir()?;
+    let mut name_mapping = HashMap::new();
+    let mut renamed_tensors = Tensor::load_multi(&weights_path)?
+        .into_iter()
+        .map(|(name, tensor)| {
+            let renamed = name.replacen("bert.", "encoder.", 1);
+            name_mapping.insert(renamed.clone(), name);
+            (renamed, tensor)
+        })
+        .collect::<Vec<(String, Tensor)>>();
+    renamed_tensors.push((
+        "unused.weight".to_string(),
+        Tensor::ones(&[2], (Kind::Float, Device::Cpu)),
+    ));
+    let renamed_weights_path = model_dir.path().join("renamed_model.ot");
+    Tensor::save_multi(
+        &renamed_tensors
+            .iter()
+            .map(|(name, tensor)| (name.as_str(), tensor))
+            .collect::<Vec<(&str, &Tensor)>>(),
+        &renamed_weights_path,
+    )?;
+
+    //    Load the renamed weights using the name mapping
+    let mut reference_vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForMaskedLM::new(reference_vs.root(), &config);
+    reference_vs.load(&weights_path)?;
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForMaskedLM::new(vs.root(), &config);
+    load_weights(&mut vs, &renamed_weights_path, Some(&name_mapping))?;
+
+    let reference_variables = reference_vs.variables();
+    for (name, variable) in vs.variables() {
+        assert!(variable.equal(&reference_variables[&name]));
+    }
+
+    //    Loading without the name mapping fails as the expected variables are missing
+    let mut vs = nn::VarStore::new(Device::Cpu);
+    let _ = BertForMaskedLM::new(vs.root(), &config);
+    assert!(matches!(
+        load_weights(&mut vs, &renamed_weights_path, None),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn bert_masked_lm_loss() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(BertConfigResources::BERT);
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let weights_resource = RemoteResource::from_pretrained(BertModelResources::BERT);
+    let config_path = config_resource.get_local_path()?;
+    let vocab_path = vocab_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+
+    //    Set-up masked LM model
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let tokenizer: BertTokenizer =
+        BertTokenizer::from_file(vocab_path.to_str().unwrap(), true, true)?;
+    let config = BertConfig::from_file(config_path);
+    let bert_model = BertForMaskedLM::new(vs.root(), &config);
+    vs.load(weights_path)?;
+
+    //    Masking the tokens [thing] and [missing], only labeling the masked positions
+    let mut input_ids = tokenizer
+        .encode(
+            "Looks like one thing is missing",
+            None,
+            128,
+            &TruncationStrategy::LongestFirst,
+            0,
+        )
+        .token_ids;
+    let mut labels = vec![-100; input_ids.len()];
+    for position in [4, 6] {
+        labels[position] = input_ids[position];
+        input_ids[position] = 103;
+    }
+    let input_tensor = Tensor::of_slice(&input_ids).unsqueeze(0).to(device);
+    let labels = Tensor::of_slice(&labels).unsqueeze(0).to(device);
+
+    //    Forward passes
+    let loss = no_grad(|| bert_model.forward_loss(&input_tensor, None, &labels, false));
+    let log_probabilities = no_grad(|| {
+        bert_model
+            .forward_t(
+                Some(&input_tensor),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .prediction_scores
+            .log_softmax(-1, Kind::Float)
+    });
+
+    //    The loss only averages the negative log-likelihood of the labeled positions
+    let expected_loss = -(log_probabilities.double_value(&[0, 4, labels.int64_value(&[0, 4])])
+        + log_probabilities.double_value(&[0, 6, labels.int64_value(&[0, 6])]))
+        / 2.0;
+    assert_eq!(loss.size(), Vec::<i64>::new());
+    assert!((loss.double_value(&[]) - expected_loss).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn bert_normalized_text() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up tokenizers, with and without lowercasing and accent stripping
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        true,
+        None,
+        None,
+    )?;
+    let cased_tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+
+    let input = "Héllo WORLD, café!";
+    let output =
+        tokenizer.encode_with_normalized_text(input, 128, &TruncationStrategy::LongestFirst, 0);
+    assert_eq!(output.normalized_text, "hello world, cafe!");
+    assert_eq!(
+        output.tokenized_input.token_ids,
+        tokenizer
+            .encode_pair(input, None, 128, &TruncationStrategy::LongestFirst, 0)
+            .token_ids
+    );
+    assert_eq!(cased_tokenizer.get_normalized_text(input), input);
+
+    Ok(())
+}
+
+#[test]
+fn bert_oov_policy() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up tokenizers
+    let load_tokenizer = || {
+        TokenizerOption::from_file(
+            ModelType::Bert,
+            vocab_path.to_str().unwrap(),
+            None,
+            true,
+            None,
+            None,
+        )
+    };
+    let tokenizer = load_tokenizer()?;
+    let unk_id = tokenizer.get_unk_id();
+    let unk_tokenizer = load_tokenizer()?.with_oov_policy(OovPolicy::Unk)?;
+    let drop_tokenizer = load_tokenizer()?.with_oov_policy(OovPolicy::Drop)?;
+    assert_eq!(unk_tokenizer.oov_policy(), OovPolicy::Unk);
+    assert_eq!(drop_tokenizer.oov_policy(), OovPolicy::Drop);
+
+    //    The crab emoji is out of the vocabulary
+    let input = "Hello \u{1F980} world";
+    let unk_output = unk_tokenizer.encode_list(&[input], 128, &TruncationStrategy::LongestFirst, 0);
+    assert_eq!(
+        unk_output[0].token_ids,
+        tokenizer
+            .encode_pair(input, None, 128, &TruncationStrategy::LongestFirst, 0)
+            .token_ids
+    );
+    assert_eq!(
+        unk_output[0]
+            .token_ids
+            .iter()
+            .filter(|id| **id == unk_id)
+            .count(),
+        1
+    );
+
+    let drop_output =
+        drop_tokenizer.encode_list(&[input], 128, &TruncationStrategy::LongestFirst, 0);
+    assert!(!drop_output[0].token_ids.contains(&unk_id));
+    assert_eq!(
+        drop_output[0].token_ids,
+        tokenizer
+            .encode_pair(
+                "Hello world",
+                None,
+                128,
+                &TruncationStrategy::LongestFirst,
+                0
+            )
+            .token_ids
+    );
+    assert_eq!(
+        drop_tokenizer.tokenize(input),
+        tokenizer.tokenize("Hello world")
+    );
+    assert_eq!(
+        drop_tokenizer
+            .encode_pair(
+                input,
+                Some(input),
+                128,
+                &TruncationStrategy::LongestFirst,
+                0
+            )
+            .token_ids,
+        tokenizer
+            .encode_pair(
+                "Hello world",
+                Some("Hello world"),
+                128,
+                &TruncationStrategy::LongestFirst,
+                0
+            )
+            .token_ids
+    );
+
+    //    The BERT vocabulary has no byte tokens to fall back to
+    assert!(load_tokenizer()?
+        .with_oov_policy(OovPolicy::ByteFallback)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn bert_oov_rate() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(BertVocabResources::BERT);
+    let vocab_path = vocab_resource.get_local_path()?;
+
+    //    Set-up tokenizer
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Bert,
+        vocab_path.to_str().unwrap(),
+        None,
+        true,
+        None,
+        None,
+    )?;
+
+    //    English inputs are covered by the vocabulary
+    let oov_rate = tokenizer.oov_rate("The quick brown fox jumps over the lazy dog.");
+    assert!(oov_rate < 0.01);
+    assert_eq!(tokenizer.oov_rate("  "), 0.0);
+
+    //    The crab emoji is out of the vocabulary
+    assert!((tokenizer.oov_rate("Hello \u{1F980}") - 1.0 / 6.0).abs() < 1e-6);
+    let oov_rate = tokenizer.oov_rate("\u{1F980}\u{1F991} \u{1F980}\u{2603}\u{1F980}");
+    assert!(oov_rate > 0.9);
+
+    Ok(())
+}
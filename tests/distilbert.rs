@@ -3,14 +3,27 @@ use rust_bert::distilbert::{
     DistilBertForTokenClassification, DistilBertModelMaskedLM, DistilBertModelResources,
     DistilBertVocabResources,
 };
+use rust_bert::pipelines::common::{
+    empty_cuda_cache, truncate_to_char_boundary, CustomTokenizer, ModelType, TokenizerOption,
+};
 use rust_bert::pipelines::question_answering::{QaInput, QuestionAnsweringModel};
-use rust_bert::pipelines::sentiment::{SentimentModel, SentimentPolarity};
-use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
+use rust_bert::pipelines::sentiment::{SentimentConfig, SentimentModel, SentimentPolarity};
+use rust_bert::pipelines::sequence_classification::{
+    SequenceClassificationConfig, SequenceClassificationModel,
+};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
 use rust_bert::Config;
 use rust_tokenizers::tokenizer::{BertTokenizer, MultiThreadedTokenizer, TruncationStrategy};
-use rust_tokenizers::vocab::Vocab;
+use rust_tokenizers::vocab::{BertVocab, Vocab};
+use rust_tokenizers::{
+    Mask, Offset, TokenIdsWithOffsets, TokenIdsWithSpecialTokens, TokensWithOffsets,
+};
 use std::collections::HashMap;
-use tch::{nn, no_grad, Device, Tensor};
+use std::io::Write;
+use std::sync::Arc;
+use std::thread;
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 extern crate anyhow;
 
@@ -26,7 +39,7 @@ fn distilbert_sentiment_classifier() -> anyhow::Result<()> {
         "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
     ];
 
-    let output = sentiment_classifier.predict(input);
+    let output = sentiment_classifier.predict(input)?;
 
     assert_eq!(output.len(), 3usize);
     assert_eq!(output[0].polarity, SentimentPolarity::Positive);
@@ -39,6 +52,544 @@ fn distilbert_sentiment_classifier() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn distilbert_sentiment_classifier_detailed() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let sentiment_classifier = SentimentModel::new(Default::default())?;
+
+    //    Get sentiments with their logits
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+        " ",
+    ];
+    let output = sentiment_classifier.predict_detailed(input)?;
+    let expected_output = sentiment_classifier.predict(input)?;
+
+    assert_eq!(output.len(), 3);
+    for (sentiment, expected_sentiment) in output.iter().zip(expected_output.iter()).take(2) {
+        assert_eq!(sentiment.polarity, expected_sentiment.polarity);
+        assert!((sentiment.score - expected_sentiment.score).abs() < 1e-4);
+        //    The softmax of the logits reproduces the probability of the polarity
+        assert_eq!(sentiment.logits.len(), 2);
+        let normalizer = sentiment
+            .logits
+            .iter()
+            .map(|logit| logit.exp())
+            .sum::<f64>();
+        let label_id = match sentiment.polarity {
+            SentimentPolarity::Positive => 1,
+            _ => 0,
+        };
+        assert!((sentiment.logits[label_id].exp() / normalizer - sentiment.score).abs() < 1e-6);
+    }
+    assert_eq!(output[2].polarity, SentimentPolarity::Neutral);
+    assert!(output[2].logits.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_sentences() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let sentiment_classifier = SentimentModel::new(Default::default())?;
+    let sentence_splitter = PunctuationSentenceSplitter::default();
+
+    //    Get the sentiment of each sentence
+    let input = "I absolutely loved the acting and the soundtrack. The ending was a boring and terrible mess.";
+    let output = sentiment_classifier.predict_sentences(input, &sentence_splitter)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        output[0].0,
+        "I absolutely loved the acting and the soundtrack."
+    );
+    assert_eq!(output[0].1.polarity, SentimentPolarity::Positive);
+    assert!(output[0].2 > 0.5);
+    assert_eq!(output[1].0, "The ending was a boring and terrible mess.");
+    assert_eq!(output[1].1.polarity, SentimentPolarity::Negative);
+    assert!(output[1].2 < 0.5);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_shared_across_threads() -> anyhow::Result<()> {
+    //    Set-up classifier shared by the threads
+    let sentiment_classifier = Arc::new(SentimentModel::new(Default::default())?);
+
+    //    Get sentiments concurrently
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+        "If you like original gut wrenching laughter you will like this movie. If you are young or old then you will love this movie, hell even my mom liked it.",
+    ];
+    let expected_output = sentiment_classifier.predict(input)?;
+
+    let handles = (0..4)
+        .map(|_| {
+            let sentiment_classifier = Arc::clone(&sentiment_classifier);
+            thread::spawn(move || sentiment_classifier.predict(input))
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        let output = handle.join().expect("Prediction thread panicked")?;
+        assert_eq!(output.len(), expected_output.len());
+        for (sentiment, expected_sentiment) in output.iter().zip(expected_output.iter()) {
+            assert_eq!(sentiment.polarity, expected_sentiment.polarity);
+            assert!((sentiment.score - expected_sentiment.score).abs() < 1e-4);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_num_threads() -> anyhow::Result<()> {
+    //    Set-up classifier using 2 threads on CPU
+    let sentiment_classifier = SentimentModel::new(SentimentConfig {
+        device: Device::Cpu,
+        num_threads: Some(2),
+        ..Default::default()
+    })?;
+    assert_eq!(tch::get_num_threads(), 2);
+
+    //    Get sentiments
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+    ];
+    let output = sentiment_classifier.predict(input)?;
+
+    assert_eq!(output.len(), 1usize);
+    assert_eq!(output[0].polarity, SentimentPolarity::Positive);
+
+    //    Invalid number of threads
+    let invalid_config = SentimentConfig {
+        device: Device::Cpu,
+        num_threads: Some(0),
+        ..Default::default()
+    };
+    assert!(SentimentModel::new(invalid_config).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_temperature() -> anyhow::Result<()> {
+    //    Set-up classifiers
+    let sentiment_classifier = SentimentModel::new(Default::default())?;
+    let calibrated_sentiment_classifier = SentimentModel::new(SentimentConfig {
+        temperature: 2.0,
+        ..Default::default()
+    })?;
+
+    //    Get sentiments
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+    ];
+
+    let output = sentiment_classifier.predict(input)?;
+    let calibrated_output = calibrated_sentiment_classifier.predict(input)?;
+
+    assert_eq!(calibrated_output.len(), 2usize);
+    for (reference, calibrated) in output.iter().zip(calibrated_output.iter()) {
+        assert_eq!(reference.polarity, calibrated.polarity);
+        assert!(calibrated.score < reference.score);
+        assert!(calibrated.score > 0.5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_logits() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let sequence_classification_model =
+        SequenceClassificationModel::new(SequenceClassificationConfig {
+            device: Device::Cpu,
+            ..Default::default()
+        })?;
+
+    //    Get raw logits
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+    ];
+    let logits = sequence_classification_model.forward_logits(input)?;
+    let output = sequence_classification_model.predict(input)?;
+
+    assert_eq!(logits.size(), vec![2, 2]);
+    assert_eq!(logits.device(), Device::Cpu);
+    let label_indices = logits
+        .argmax(-1, false)
+        .iter::<i64>()?
+        .collect::<Vec<i64>>();
+    for (label_index, label) in label_indices.iter().zip(output.iter()) {
+        assert_eq!(*label_index, label.id);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_train_eval() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let mut sequence_classification_model =
+        SequenceClassificationModel::new(SequenceClassificationConfig {
+            device: Device::Cpu,
+            ..Default::default()
+        })?;
+    assert!(!sequence_classification_model.is_training());
+
+    //    Evaluation mode forward pass does not track gradients and is deterministic
+    let input = ["Probably my all-time favorite movie."];
+    let eval_logits = sequence_classification_model.forward_logits(input)?;
+    assert!(!eval_logits.requires_grad());
+    let eval_logits_again = sequence_classification_model.forward_logits(input)?;
+    assert!(eval_logits.allclose(&eval_logits_again, 1e-6, 1e-6, false));
+
+    //    Training mode forward pass tracks gradients
+    sequence_classification_model.train();
+    assert!(sequence_classification_model.is_training());
+    let train_logits = sequence_classification_model.forward_logits(input)?;
+    assert!(train_logits.requires_grad());
+
+    sequence_classification_model.eval();
+    let eval_logits_after_training = sequence_classification_model.forward_logits(input)?;
+    assert!(!eval_logits_after_training.requires_grad());
+    assert!(eval_logits.allclose(&eval_logits_after_training, 1e-6, 1e-6, false));
+
+    Ok(())
+}
+
+/// Lowercasing whitespace tokenizer looking up the words in the BERT vocabulary
+struct WhitespaceTokenizer {
+    vocab: BertVocab,
+}
+
+impl CustomTokenizer for WhitespaceTokenizer {
+    fn model_type(&self) -> ModelType {
+        ModelType::DistilBert
+    }
+
+    fn tokenize_with_offsets(&self, text: &str) -> TokensWithOffsets {
+        let mut output = TokensWithOffsets {
+            tokens: vec![],
+            offsets: vec![],
+            reference_offsets: vec![],
+            masks: vec![],
+        };
+        let mut word_start = None;
+        for (position, character) in text.chars().chain(std::iter::once(' ')).enumerate() {
+            let position = position as u32;
+            match (character.is_whitespace(), word_start) {
+                (false, None) => word_start = Some(position),
+                (true, Some(begin)) => {
+                    let word = text
+                        .chars()
+                        .skip(begin as usize)
+                        .take((position - begin) as usize)
+                        .collect::<String>();
+                    output.tokens.push(word.to_lowercase());
+                    output.offsets.push(Some(Offset {
+                        begin,
+                        end: position,
+                    }));
+                    output.reference_offsets.push((begin..position).collect());
+                    output.masks.push(Mask::None);
+                    word_start = None;
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn convert_tokens_to_ids(&self, tokens: &[&str]) -> Vec<i64> {
+        tokens
+            .iter()
+            .map(|token| self.vocab.token_to_id(token))
+            .collect()
+    }
+
+    fn convert_ids_to_tokens(&self, token_ids: &[i64]) -> Vec<String> {
+        token_ids
+            .iter()
+            .map(|token_id| self.vocab.id_to_token(token_id))
+            .collect()
+    }
+
+    fn decode(&self, token_ids: &[i64], skip_special_tokens: bool, _: bool) -> String {
+        token_ids
+            .iter()
+            .filter(|token_id| !(skip_special_tokens && self.is_special_token_id(**token_id)))
+            .map(|token_id| self.vocab.id_to_token(token_id))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.vocab.values.len()
+    }
+
+    fn unk_id(&self) -> i64 {
+        self.vocab.token_to_id(BertVocab::unknown_value())
+    }
+
+    fn build_input_with_special_tokens(
+        &self,
+        tokens_ids_with_offsets_1: TokenIdsWithOffsets,
+        tokens_ids_with_offsets_2: Option<TokenIdsWithOffsets>,
+    ) -> TokenIdsWithSpecialTokens {
+        assert!(tokens_ids_with_offsets_2.is_none());
+        let length = tokens_ids_with_offsets_1.ids.len();
+        let mut token_ids = vec![self.vocab.token_to_id(BertVocab::cls_value())];
+        token_ids.extend(tokens_ids_with_offsets_1.ids);
+        token_ids.push(self.sep_id().unwrap());
+        let mut token_offsets = vec![None];
+        token_offsets.extend(tokens_ids_with_offsets_1.offsets);
+        token_offsets.push(None);
+        let mut reference_offsets = vec![vec![]];
+        reference_offsets.extend(tokens_ids_with_offsets_1.reference_offsets);
+        reference_offsets.push(vec![]);
+        let mut mask = vec![Mask::Special];
+        mask.extend(tokens_ids_with_offsets_1.masks);
+        mask.push(Mask::Special);
+        let mut special_tokens_mask = vec![0; length + 2];
+        special_tokens_mask[0] = 1;
+        special_tokens_mask[length + 1] = 1;
+        TokenIdsWithSpecialTokens {
+            token_ids,
+            segment_ids: vec![0; length + 2],
+            special_tokens_mask,
+            token_offsets,
+            reference_offsets,
+            mask,
+        }
+    }
+
+    fn pad_id(&self) -> Option<i64> {
+        Some(self.vocab.token_to_id(BertVocab::pad_value()))
+    }
+
+    fn sep_id(&self) -> Option<i64> {
+        Some(self.vocab.token_to_id(BertVocab::sep_value()))
+    }
+}
+
+#[test]
+fn distilbert_sequence_classification_custom_tokenizer() -> anyhow::Result<()> {
+    //    Set-up classifiers with the built-in and a custom tokenizer
+    let config = SequenceClassificationConfig::default();
+    let vocab_path = config.vocab_resource.get_local_path()?;
+    let custom_tokenizer = WhitespaceTokenizer {
+        vocab: BertVocab::from_file(vocab_path)?,
+    };
+    let custom_model = SequenceClassificationModel::new_with_tokenizer(
+        config,
+        Arc::new(TokenizerOption::Custom(Box::new(custom_tokenizer))),
+    )?;
+    let model = SequenceClassificationModel::new(Default::default())?;
+
+    //    The custom tokenizer encodes these words like the built-in tokenizer
+    let input = ["This movie is great", "What a terrible  waste of time"];
+    let custom_output = custom_model.predict(input)?;
+    let output = model.predict(input)?;
+
+    assert_eq!(custom_output.len(), 2);
+    assert_eq!(custom_output[0].text, "POSITIVE");
+    assert_eq!(custom_output[1].text, "NEGATIVE");
+    for (custom_label, label) in custom_output.iter().zip(output.iter()) {
+        assert_eq!(custom_label.id, label.id);
+        assert!((custom_label.score - label.score).abs() < 1e-4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_custom_tokenizer_truncation_error() -> anyhow::Result<()> {
+    let config = SequenceClassificationConfig::default();
+    let vocab_path = config.vocab_resource.get_local_path()?;
+    let tokenizer = TokenizerOption::Custom(Box::new(WhitespaceTokenizer {
+        vocab: BertVocab::from_file(vocab_path)?,
+    }));
+
+    //    The input exceeds the maximum length and may not be truncated
+    let input = "This movie is great";
+    assert!(tokenizer
+        .try_encode_pair(input, None, 4, &TruncationStrategy::DoNotTruncate, 0)
+        .is_err());
+
+    let encoded =
+        tokenizer.try_encode_pair(input, None, 4, &TruncationStrategy::LongestFirst, 0)?;
+    assert_eq!(encoded.token_ids.len(), 4);
+    assert_eq!(encoded.num_truncated_tokens, 2);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_lazy_loading() -> anyhow::Result<()> {
+    //    Set-up classifiers
+    let sentiment_classifier = SentimentModel::new(Default::default())?;
+    let lazy_sentiment_classifier = SentimentModel::new(SentimentConfig {
+        lazy_loading: true,
+        ..Default::default()
+    })?;
+    assert!(sentiment_classifier.is_loaded());
+    assert!(!lazy_sentiment_classifier.is_loaded());
+
+    //    Get sentiments, loading the weights on the first prediction
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+    ];
+
+    let output = sentiment_classifier.predict(input)?;
+    let lazy_output = lazy_sentiment_classifier.predict(input)?;
+    assert!(lazy_sentiment_classifier.is_loaded());
+
+    for (reference, lazy) in output.iter().zip(lazy_output.iter()) {
+        assert_eq!(reference.polarity, lazy.polarity);
+        assert!((reference.score - lazy.score).abs() < 1e-6);
+    }
+
+    //    Explicit warmup
+    let warmed_up_classifier = SentimentModel::new(SentimentConfig {
+        lazy_loading: true,
+        ..Default::default()
+    })?;
+    warmed_up_classifier.warmup()?;
+    assert!(warmed_up_classifier.is_loaded());
+    let warmed_up_output = warmed_up_classifier.predict(input)?;
+    for (reference, warmed_up) in output.iter().zip(warmed_up_output.iter()) {
+        assert_eq!(reference.polarity, warmed_up.polarity);
+        assert!((reference.score - warmed_up.score).abs() < 1e-6);
+    }
+
+    //    Weights loading errors are returned by the first prediction
+    let mut invalid_weights_file = tempfile::NamedTempFile::new()?;
+    invalid_weights_file.write_all(b"invalid weights")?;
+    let invalid_classifier = SentimentModel::new(SentimentConfig {
+        model_resource: Box::new(LocalResource::from(
+            invalid_weights_file.path().to_path_buf(),
+        )),
+        lazy_loading: true,
+        ..Default::default()
+    })?;
+    assert!(invalid_classifier.predict(input).is_err());
+    assert!(!invalid_classifier.is_loaded());
+
+    Ok(())
+}
+
+/// Free memory of the current CUDA device in bytes, queried from the CUDA runtime loaded by Torch
+#[cfg(unix)]
+fn cuda_free_memory() -> usize {
+    let symbol =
+        unsafe { libc::dlsym(libc::RTLD_DEFAULT, b"cudaMemGetInfo\0".as_ptr() as *const _) };
+    assert!(!symbol.is_null(), "The CUDA runtime is not loaded");
+    let mem_get_info: extern "C" fn(*mut usize, *mut usize) -> i32 =
+        unsafe { std::mem::transmute(symbol) };
+    let (mut free, mut total) = (0usize, 0usize);
+    assert_eq!(mem_get_info(&mut free, &mut total), 0);
+    free
+}
+
+#[test]
+#[cfg(unix)]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn distilbert_sentiment_classifier_unload() -> anyhow::Result<()> {
+    if !tch::Cuda::is_available() {
+        return Ok(());
+    }
+    //    Initialize the CUDA context so that it is not counted in the memory of the model
+    let _ = Tensor::ones(&[1], (Kind::Float, Device::Cuda(0)));
+    empty_cuda_cache();
+    let free_before_loading = cuda_free_memory();
+
+    let sentiment_classifier = SentimentModel::new(SentimentConfig {
+        device: Device::Cuda(0),
+        ..Default::default()
+    })?;
+    let output = sentiment_classifier.predict(["This is a great movie."])?;
+    assert_eq!(output[0].polarity, SentimentPolarity::Positive);
+    let free_after_loading = cuda_free_memory();
+    assert!(free_after_loading < free_before_loading);
+
+    //    The memory of the weights is returned to the device and not kept by the caching allocator
+    sentiment_classifier.unload();
+    let free_after_unloading = cuda_free_memory();
+    let model_memory = free_before_loading - free_after_loading;
+    assert!(free_after_unloading.saturating_sub(free_after_loading) > model_memory / 2);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sequence_classification_non_blocking_transfers() -> anyhow::Result<()> {
+    if !tch::Cuda::is_available() {
+        return Ok(());
+    }
+    let input = [
+        "Probably my all-time favorite movie, a story of selflessness, sacrifice and dedication to a noble cause, but it's not preachy or boring.",
+        "This film tried to be too many things all at once: stinging political satire, Hollywood blockbuster, sappy romantic comedy, family values promo...",
+    ];
+
+    let blocking_model = SequenceClassificationModel::new(SequenceClassificationConfig {
+        device: Device::Cuda(0),
+        ..Default::default()
+    })?;
+    let non_blocking_model = SequenceClassificationModel::new(SequenceClassificationConfig {
+        device: Device::Cuda(0),
+        non_blocking_transfers: true,
+        ..Default::default()
+    })?;
+
+    let expected_output = blocking_model.predict_label_scores(input)?;
+    let output = non_blocking_model.predict_label_scores(input)?;
+    assert_eq!(output.len(), expected_output.len());
+    for (labels, expected_labels) in output.iter().zip(expected_output.iter()) {
+        for (label, expected_label) in labels.iter().zip(expected_labels.iter()) {
+            assert_eq!(label.text, expected_label.text);
+            assert!((label.score - expected_label.score).abs() < 1e-6);
+        }
+    }
+
+    //    Logits transferred without blocking are valid after an explicit synchronization
+    let expected_logits = blocking_model.forward_logits(input)?.to(Device::Cpu);
+    let logits =
+        non_blocking_model
+            .forward_logits(input)?
+            .to_device_(Device::Cpu, Kind::Float, true, false);
+    non_blocking_model.synchronize();
+    assert!(logits.allclose(&expected_logits, 1e-5, 1e-6, false));
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_sentiment_classifier_empty_inputs() -> anyhow::Result<()> {
+    //    Set-up classifier
+    let sentiment_classifier = SentimentModel::new(Default::default())?;
+
+    //    Get sentiments
+    let input = ["", "This is a great movie.", "   "];
+    let output = sentiment_classifier.predict(input)?;
+
+    assert_eq!(output.len(), 3);
+    assert_eq!(output[0].polarity, SentimentPolarity::Neutral);
+    assert_eq!(output[0].score, 0.0);
+    assert_eq!(output[1].polarity, SentimentPolarity::Positive);
+    assert_eq!(output[2].polarity, SentimentPolarity::Neutral);
+    assert_eq!(output[2].score, 0.0);
+
+    Ok(())
+}
+
 #[test]
 fn distilbert_masked_lm() -> anyhow::Result<()> {
     //    Resources paths
@@ -276,3 +827,97 @@ fn distilbert_question_answering() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn distilbert_question_answering_train_eval() -> anyhow::Result<()> {
+    //    Set-up question answering model
+    let mut qa_model = QuestionAnsweringModel::new(Default::default())?;
+    assert!(!qa_model.is_training());
+
+    let qa_input = || QaInput {
+        question: String::from("Where does Amy live ?"),
+        context: String::from("Amy lives in Amsterdam"),
+    };
+    let answers = qa_model.predict(&[qa_input()], 1, 32);
+
+    //    Dropout is applied in training mode, the evaluation mode predictions are unchanged afterwards
+    qa_model.train();
+    assert!(qa_model.is_training());
+    let train_answers = qa_model.predict(&[qa_input()], 1, 32);
+    assert_eq!(train_answers[0].len(), 1);
+
+    qa_model.eval();
+    assert!(!qa_model.is_training());
+    let eval_answers = qa_model.predict(&[qa_input()], 1, 32);
+    assert_eq!(eval_answers[0][0].answer, answers[0][0].answer);
+    assert!((eval_answers[0][0].score - answers[0][0].score).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_question_answering_empty_inputs() -> anyhow::Result<()> {
+    //    Set-up question answering model
+    let qa_model = QuestionAnsweringModel::new(Default::default())?;
+
+    //    Define input
+    let qa_inputs = [
+        QaInput {
+            question: String::from("Where does Amy live ?"),
+            context: String::from("  "),
+        },
+        QaInput {
+            question: String::from("Where does Amy live ?"),
+            context: String::from("Amy lives in Amsterdam"),
+        },
+        QaInput {
+            question: String::new(),
+            context: String::from("Amy lives in Amsterdam"),
+        },
+    ];
+
+    let answers = qa_model.predict(&qa_inputs, 1, 32);
+
+    assert_eq!(answers.len(), 3);
+    assert!(answers[0].is_empty());
+    assert_eq!(answers[1].len(), 1);
+    assert_eq!(answers[1][0].answer, "Amsterdam");
+    assert!(answers[2].is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn distilbert_question_answering_multibyte_truncation() -> anyhow::Result<()> {
+    //    Set-up question answering model
+    let qa_model = QuestionAnsweringModel::new(Default::default())?;
+
+    //    Define input, the context being truncated in the middle of the 2-bytes character "ü"
+    let text = "Amélie lives in Zürich";
+    let truncation_point = text.find('ü').unwrap() + 1;
+    assert!(!text.is_char_boundary(truncation_point));
+    let context = truncate_to_char_boundary(text, truncation_point).to_string();
+    assert_eq!(context, "Amélie lives in Z");
+    let qa_input = QaInput {
+        question: String::from("Where does Amélie live ?"),
+        context: context.clone(),
+    };
+
+    let answers = qa_model.predict(&[qa_input], 3, 32);
+
+    assert_eq!(answers.len(), 1);
+    assert!(!answers[0].is_empty());
+    let context_length = context.chars().count();
+    for answer in &answers[0] {
+        assert!(answer.start <= answer.end);
+        assert!(answer.end <= context_length);
+        let expected_answer = context
+            .chars()
+            .skip(answer.start)
+            .take(answer.end - answer.start)
+            .collect::<String>();
+        assert_eq!(answer.answer, expected_answer);
+    }
+
+    Ok(())
+}
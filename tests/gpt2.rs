@@ -1,19 +1,26 @@
 use rust_bert::gpt2::{
     GPT2Generator, GPT2LMHeadModel, Gpt2Config, Gpt2ConfigResources, Gpt2MergesResources,
-    Gpt2ModelResources, Gpt2VocabResources,
+    Gpt2Model, Gpt2ModelResources, Gpt2VocabResources,
 };
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{validate_resources, ModelType, PaddingSide, TokenizerOption};
 use rust_bert::pipelines::conversation::{
     ConversationConfig, ConversationManager, ConversationModel,
 };
 use rust_bert::pipelines::generation_utils::{
-    Cache, GenerateConfig, GenerateOptions, LMHeadModel, LanguageGenerator,
+    BeamSearchProgress, BeamSearchState, Cache, ControlCharacters, EntropyStage, FinishReason,
+    GenerateConfig, GenerateConfigBuilder, GenerateOptions, GenerationConfigFile,
+    GenerationControl, LMHeadModel, LanguageGenerator,
 };
-use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
-use rust_bert::resources::{RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::pipelines::text_generation::{
+    PromptTemplate, TextGenerationConfig, TextGenerationModel,
+};
+use rust_bert::resources::{LocalResource, RemoteResource, ResourceProvider};
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{Gpt2Tokenizer, Tokenizer, TruncationStrategy};
-use tch::{nn, Device, Tensor};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use tch::{nn, Device, Kind, Tensor};
 
 #[test]
 fn gpt2_lm_model() -> anyhow::Result<()> {
@@ -131,7 +138,7 @@ fn gpt2_generation_greedy() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "The cat";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], "The cat was found in a field near the town of Keflavik, about 30 miles (48 kilometers) south-east of Moscow.\n\n\n");
@@ -139,6 +146,349 @@ fn gpt2_generation_greedy() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn gpt2_generation_greedy_normalize_whitespace() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        max_length: Some(40),
+        do_sample: false,
+        num_beams: 1,
+        temperature: 1.1,
+        repetition_penalty: 1.1,
+        normalize_whitespace: true,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let input_context = "The cat";
+    let output = model.generate(&[input_context], None)?;
+
+    //    The trailing newlines of the raw output are removed
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0], "The cat was found in a field near the town of Keflavik, about 30 miles (48 kilometers) south-east of Moscow.");
+    assert!(!output[0].contains("  "));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_max_output_chars() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config =
+        |max_output_chars, output_ellipsis, return_full_text| TextGenerationConfig {
+            model_type: ModelType::GPT2,
+            model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+            config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+            merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+                Gpt2MergesResources::GPT2,
+            ))),
+            max_length: Some(40),
+            do_sample: false,
+            num_beams: 1,
+            temperature: 1.1,
+            repetition_penalty: 1.1,
+            normalize_whitespace: true,
+            max_output_chars: Some(max_output_chars),
+            output_ellipsis,
+            return_full_text,
+            ..Default::default()
+        };
+    let input_context = "The cat";
+    let full_output = "The cat was found in a field near the town of Keflavik, about 30 miles (48 kilometers) south-east of Moscow.";
+
+    //    The limit applies to the generated text, the prompt returned with the full text is not truncated
+    let model = TextGenerationModel::new(generate_config(20, false, true))?;
+    let output = model.generate(&[input_context], None)?;
+    assert_eq!(output[0], "The cat was found in a");
+
+    let model = TextGenerationModel::new(generate_config(20, false, false))?;
+    let output = model.generate(&[input_context], None)?;
+    assert_eq!(output[0], "was found in a");
+
+    //    The ellipsis counts in the limit
+    let model = TextGenerationModel::new(generate_config(27, true, true))?;
+    let output = model.generate(&[input_context], None)?;
+    assert_eq!(output[0], "The cat was found in a field near\u{2026}");
+
+    //    Outputs within the limit are not truncated
+    let model = TextGenerationModel::new(generate_config(200, true, true))?;
+    let output = model.generate(&[input_context], None)?;
+    assert_eq!(output[0], full_output);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_return_full_text() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = |return_full_text| TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        max_length: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        return_full_text,
+        ..Default::default()
+    };
+    let full_text_model = TextGenerationModel::new(generate_config(true))?;
+    let generated_text_model = TextGenerationModel::new(generate_config(false))?;
+
+    //    Prompts of different lengths are padded in the batch
+    let input = ["The cat", "The dog was running in the"];
+    let full_text_output = full_text_model.generate(&input, None)?;
+    let generated_text_output = generated_text_model.generate(&input, None)?;
+
+    assert_eq!(full_text_output.len(), 2);
+    assert_eq!(generated_text_output.len(), 2);
+    for ((prompt, full_text), generated_text) in input
+        .iter()
+        .zip(full_text_output.iter())
+        .zip(generated_text_output.iter())
+    {
+        assert!(full_text.starts_with(prompt));
+        assert!(!generated_text.starts_with(prompt));
+        assert_eq!(&full_text[prompt.len()..], generated_text);
+    }
+
+    //    Empty prompts do not shift the start of the generated text of the other prompts
+    let output = generated_text_model.generate(&["The cat", ""], None)?;
+    assert_eq!(
+        output,
+        [
+            generated_text_model.generate(&["The cat"], None)?.remove(0),
+            String::new()
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_invalid_lengths() -> anyhow::Result<()> {
+    //    Inconsistent length settings are rejected when the model is created
+    let generate_config = GenerateConfig {
+        min_length: 30,
+        max_length: Some(20),
+        ..Default::default()
+    };
+    assert!(GPT2Generator::new(generate_config).is_err());
+
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(0),
+        ..Default::default()
+    };
+    assert!(GPT2Generator::new(generate_config).is_err());
+
+    let generate_config = TextGenerationConfig {
+        min_length: 30,
+        max_length: Some(20),
+        ..Default::default()
+    };
+    assert!(TextGenerationModel::new(generate_config).is_err());
+
+    //    `max_length` is ignored when `max_new_tokens` is set
+    let generate_config = GenerateConfig {
+        min_length: 30,
+        max_length: Some(20),
+        max_new_tokens: Some(40),
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    Settings of the generate options are validated with the configuration before generating
+    let generate_options = GenerateOptions {
+        max_length: Some(20),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&["The dog"]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+    let generate_options = GenerateOptions {
+        temperature: Some(0.0),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&["The dog"]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    //    Invalid settings are rejected before the resources are retrieved
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(LocalResource::from(PathBuf::from("missing/model.ot"))),
+        config_resource: Box::new(LocalResource::from(PathBuf::from("missing/config.json"))),
+        vocab_resource: Box::new(LocalResource::from(PathBuf::from("missing/vocab.json"))),
+        merges_resource: Some(Box::new(LocalResource::from(PathBuf::from(
+            "missing/merges.txt",
+        )))),
+        top_p: 2.0,
+        ..Default::default()
+    };
+    assert!(matches!(
+        GPT2Generator::new(generate_config),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_invalid_token_ids() -> anyhow::Result<()> {
+    let model = GPT2Generator::new(GenerateConfig {
+        device: Device::Cpu,
+        ..Default::default()
+    })?;
+    let tokenizer = model.get_tokenizer();
+    assert_eq!(tokenizer.get_vocab_size(), 50257);
+    assert!(tokenizer.is_valid_id(0));
+    assert!(tokenizer.is_valid_id(50256));
+    assert!(!tokenizer.is_valid_id(50257));
+    assert!(!tokenizer.is_valid_id(-1));
+
+    //    Out-of-range ids of the generation constraints are rejected
+    let bad_word_ids = vec![vec![262], vec![50257]];
+    let generate_options = GenerateOptions {
+        bad_word_ids: Some(&bad_word_ids),
+        ..Default::default()
+    };
+    assert!(matches!(
+        generate_options.validate_token_ids(tokenizer),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+    assert!(matches!(
+        model.generate(Some(&["The dog"]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+    let generate_options = GenerateOptions {
+        forced_bos_token_id: Some(60000),
+        ..Default::default()
+    };
+    assert!(generate_options.validate_token_ids(tokenizer).is_err());
+    let generate_options = GenerateOptions {
+        forced_bos_token_id: Some(262),
+        ..Default::default()
+    };
+    assert!(generate_options.validate_token_ids(tokenizer).is_ok());
+
+    let mut logit_bias = HashMap::new();
+    logit_bias.insert(50257, 10.0);
+    let generate_config = GenerateConfig {
+        logit_bias,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    assert!(matches!(
+        GPT2Generator::new(generate_config),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_speculative_greedy_generation() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config =
+        |model_resource, config_resource, vocab_resource, merges_resource| TextGenerationConfig {
+            model_type: ModelType::GPT2,
+            model_resource: Box::new(RemoteResource::from_pretrained(model_resource)),
+            config_resource: Box::new(RemoteResource::from_pretrained(config_resource)),
+            vocab_resource: Box::new(RemoteResource::from_pretrained(vocab_resource)),
+            merges_resource: Some(Box::new(RemoteResource::from_pretrained(merges_resource))),
+            max_length: Some(32),
+            do_sample: false,
+            num_beams: 1,
+            no_repeat_ngram_size: 3,
+            device: Device::Cpu,
+            ..Default::default()
+        };
+    let model = TextGenerationModel::new(generate_config(
+        Gpt2ModelResources::GPT2,
+        Gpt2ConfigResources::GPT2,
+        Gpt2VocabResources::GPT2,
+        Gpt2MergesResources::GPT2,
+    ))?;
+    let draft_model = TextGenerationModel::new(generate_config(
+        Gpt2ModelResources::DISTIL_GPT2,
+        Gpt2ConfigResources::DISTIL_GPT2,
+        Gpt2VocabResources::DISTIL_GPT2,
+        Gpt2MergesResources::DISTIL_GPT2,
+    ))?;
+
+    for input in ["The cat", "Hello, my name is"] {
+        let greedy_output = model.generate(&[input], None)?;
+        for speculation_length in [1, 4] {
+            let speculative_output =
+                model.generate_speculative(&draft_model, &[input], speculation_length)?;
+            assert_eq!(speculative_output, greedy_output);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_speculative_greedy_generation_settings() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = || TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2ModelResources::DISTIL_GPT2,
+        )),
+        config_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2ConfigResources::DISTIL_GPT2,
+        )),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(
+            Gpt2VocabResources::DISTIL_GPT2,
+        )),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::DISTIL_GPT2,
+        ))),
+        max_length: Some(8),
+        do_sample: false,
+        num_beams: 1,
+        return_full_text: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config())?;
+    let draft_model = TextGenerationModel::new(generate_config())?;
+
+    //    Prompts longer than the maximum length are truncated as in the plain generation
+    let input = "The quick brown fox jumps over the lazy dog and runs into the forest.";
+    let greedy_output = model.generate(&[input], None)?;
+    let speculative_output = model.generate_speculative(&draft_model, &[input], 4)?;
+    assert_eq!(speculative_output, greedy_output);
+
+    //    Settings modifying the greedy predictions are rejected
+    let clamped_model = TextGenerationModel::new(TextGenerationConfig {
+        logit_clamp: Some((-10.0, 10.0)),
+        ..generate_config()
+    })?;
+    assert!(matches!(
+        clamped_model.generate_speculative(&draft_model, &[input], 4),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn gpt2_generation_beam_search() -> anyhow::Result<()> {
     //    Resources definition
@@ -164,7 +514,7 @@ fn gpt2_generation_beam_search() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "The dog";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 3);
     assert_eq!(
@@ -183,6 +533,44 @@ fn gpt2_generation_beam_search() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn gpt2_generation_sampling_multiple_return_sequences() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        max_length: Some(20),
+        do_sample: true,
+        num_beams: 1,
+        device: Device::Cpu,
+        num_return_sequences: 5,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was";
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
+
+    assert_eq!(output.len(), 10);
+    assert!(output[..5]
+        .iter()
+        .all(|sequence| sequence.starts_with(input_context_1)));
+    assert!(output[5..]
+        .iter()
+        .all(|sequence| sequence.starts_with(input_context_2)));
+
+    Ok(())
+}
+
 #[test]
 fn gpt2_generation_beam_search_multiple_prompts_without_padding() -> anyhow::Result<()> {
     //    Resources definition
@@ -209,7 +597,7 @@ fn gpt2_generation_beam_search_multiple_prompts_without_padding() -> anyhow::Res
 
     let input_context_1 = "The dog";
     let input_context_2 = "The cat";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -265,7 +653,7 @@ fn gpt2_generation_beam_search_multiple_prompts_with_padding() -> anyhow::Result
 
     let input_context_1 = "The dog";
     let input_context_2 = "The cat was";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -323,7 +711,7 @@ fn gpt2_diverse_beam_search_multiple_prompts_with_padding() -> anyhow::Result<()
 
     let input_context_1 = "It was a nice and";
     let input_context_2 = "Language models can generate";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     assert_eq!(
@@ -402,7 +790,7 @@ fn gpt2_prefix_allowed_token_greedy() -> anyhow::Result<()> {
     let output = model.generate(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -465,8 +853,9 @@ fn gpt2_bad_tokens_greedy() -> anyhow::Result<()> {
         ..Default::default()
     };
 
-    let baseline_output = model.generate(Some(&[input_context_1]), Some(baseline_generate_options));
-    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options));
+    let baseline_output =
+        model.generate(Some(&[input_context_1]), Some(baseline_generate_options))?;
+    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options))?;
 
     assert_eq!(baseline_output.len(), 1);
     assert_eq!(
@@ -486,7 +875,7 @@ fn gpt2_bad_tokens_greedy() -> anyhow::Result<()> {
 }
 
 #[test]
-fn gpt2_bad_tokens_beam_search() -> anyhow::Result<()> {
+fn gpt2_sampling_min_p() -> anyhow::Result<()> {
     //    Resources definition
     let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
     let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
@@ -494,130 +883,90 @@ fn gpt2_bad_tokens_beam_search() -> anyhow::Result<()> {
     let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
     let generate_config = GenerateConfig {
-        max_length: Some(36),
+        max_length: Some(20),
         model_resource,
         config_resource,
         vocab_resource,
         merges_resource: Some(merges_resource),
-        do_sample: false,
-        num_beams: 3,
+        do_sample: true,
+        num_beams: 1,
+        top_p: 1.0,
+        num_return_sequences: 3,
         device: Device::Cpu,
         ..Default::default()
     };
     let model = GPT2Generator::new(generate_config)?;
 
-    let bad_words = vec![" honeybees", " a member"];
-    let bad_word_ids = model
-        .get_tokenizer()
-        .encode_list(
-            bad_words.as_slice(),
-            512,
-            &TruncationStrategy::DoNotTruncate,
-            0,
-        )
-        .into_iter()
-        .map(|tokenized_input| tokenized_input.token_ids)
-        .collect::<Vec<Vec<i64>>>();
-
-    let input_context_1 = "Hello, my name is";
-
-    let baseline_generate_options = GenerateOptions {
-        output_scores: true,
+    let input_context = "The dog";
+    let greedy_options = GenerateOptions {
+        do_sample: Some(false),
+        num_return_sequences: Some(1),
         ..Default::default()
     };
-    let test_generate_options = GenerateOptions {
-        bad_word_ids: Some(&bad_word_ids),
-        output_scores: true,
+    //    A min_p of 1.0 only keeps the most likely token at each step
+    let min_p_options = GenerateOptions {
+        min_p: Some(1.0),
         ..Default::default()
     };
 
-    let baseline_output = model.generate(Some(&[input_context_1]), Some(baseline_generate_options));
-    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options));
-
-    assert_eq!(baseline_output.len(), 1);
-    assert_eq!(
-        baseline_output[0].text,
-        "Hello, my name is John, and I am a member of the Church of Jesus Christ of Latter-day Saints.\n\nI am a Mormon. I have been a member"
-    );
-    assert!((baseline_output[0].score.unwrap() - (-1.0503)).abs() < 1e-4);
+    let greedy_output = model.generate(Some(&[input_context]), Some(greedy_options))?;
+    let min_p_output = model.generate(Some(&[input_context]), Some(min_p_options))?;
 
-    assert_eq!(output.len(), 1);
-    assert_eq!(
-        output[0].text,
-        "Hello, my name is John, and I am the owner of a small business.\n\nI have been in business for over 20 years. I have been a business owner for"
-    );
-    assert!((output[0].score.unwrap() - (-1.3742)).abs() < 1e-4);
+    assert_eq!(greedy_output.len(), 1);
+    assert_eq!(min_p_output.len(), 3);
+    for generated_output in min_p_output {
+        assert_eq!(generated_output.text, greedy_output[0].text);
+    }
 
     Ok(())
 }
 
 #[test]
-fn gpt2_prefix_allowed_token_beam_search() -> anyhow::Result<()> {
+fn gpt2_greedy_top_token_scores() -> anyhow::Result<()> {
     //    Resources definition
     let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
     let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
     let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
     let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
-    fn force_one_paragraph(_batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
-        let paragraph_tokens = [198, 628];
-
-        for paragraph_token in paragraph_tokens.iter() {
-            if previous_token_ids
-                .iter::<i64>()
-                .unwrap()
-                .any(|x| x == *paragraph_token)
-            {
-                return vec![50256];
-            }
-        }
-        (0..50255).collect()
-    }
-
     let generate_config = GenerateConfig {
-        max_length: Some(32),
+        max_length: Some(36),
         model_resource,
         config_resource,
         vocab_resource,
         merges_resource: Some(merges_resource),
         do_sample: false,
-        num_beams: 3,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
     let model = GPT2Generator::new(generate_config)?;
 
-    let input_context_1 = "Rust is a";
-    let input_context_2 = "There was a ";
-
+    let input_context_1 = "Hello, my name is";
     let generate_options = GenerateOptions {
-        prefix_allowed_tokens_fn: Some(&force_one_paragraph),
-        output_scores: true,
+        output_top_token_scores: true,
         ..Default::default()
     };
 
-    let output = model.generate(
-        Some(&[input_context_1, input_context_2]),
-        Some(generate_options),
-    );
+    let output = model.generate_indices(Some(&[input_context_1]), Some(generate_options))?;
 
-    assert_eq!(output.len(), 2);
-    assert_eq!(
-        output[0].text,
-        "Rust is a simple, fast, and easy-to-use framework for building web applications. It is designed to be easy to use and maintain, and"
-    );
-    assert!((output[0].score.unwrap() - (-1.2750)).abs() < 1e-4);
-    assert_eq!(
-        output[1].text,
-        "There was a urn in the back of the room, and I was sitting on it, and it looked like it was going to explode. And then I"
-    );
-    assert!((output[1].score.unwrap() - (-1.3326)).abs() < 1e-4);
+    assert_eq!(output.len(), 1);
+    let top_token_scores = output[0].top_token_scores.as_ref().unwrap();
+    //    One score per generated token (the prompt is encoded into 5 tokens)
+    assert_eq!(top_token_scores.len(), output[0].indices.len() - 5);
+    for top_token_score in top_token_scores {
+        assert!(top_token_score.probability >= 0.0);
+        assert!(top_token_score.probability <= 1.0);
+        assert!(top_token_score.logit.is_finite());
+    }
+    //    Without sampling, the first generated token is the top token predicted by the model
+    assert_eq!(top_token_scores[0].token_id, output[0].indices[5]);
 
     Ok(())
 }
 
 #[test]
-fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
+fn gpt2_entropy() -> anyhow::Result<()> {
     //    Resources definition
     let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
     let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
@@ -625,7 +974,375 @@ fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
     let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
     let generate_config = GenerateConfig {
-        max_length: Some(16),
+        max_length: Some(20),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: true,
+        top_k: 5,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    for entropy_stage in [EntropyStage::RawLogits, EntropyStage::ProcessedLogits] {
+        let generate_options = GenerateOptions {
+            output_entropy: Some(entropy_stage),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+        assert_eq!(output.len(), 1);
+        let entropy = output[0].entropy.as_ref().unwrap();
+        //    One entropy per generated token (the prompt is encoded into 5 tokens)
+        assert_eq!(entropy.len(), output[0].usage.completion_tokens);
+        assert_eq!(entropy.len(), output[0].indices.len() - 5);
+        for step_entropy in entropy {
+            assert!(*step_entropy >= 0.0);
+            if entropy_stage == EntropyStage::ProcessedLogits {
+                //    The entropy of the top-5 filtered distribution is at most ln(5)
+                assert!(*step_entropy <= 5f32.ln() + 1e-4);
+            }
+        }
+    }
+
+    //    The entropy is only returned if requested
+    let output = model.generate_indices(Some(&[input_context]), None)?;
+    assert!(output[0].entropy.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_repetition_blocking_batch_independent() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(30),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 2,
+        repetition_penalty: 1.3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "The quarterly financial report of the company";
+    let input_context_2 = "My cat";
+    // GPT2 pads the shorter prompts with its end of sequence token
+    let pad_token_id = 50256;
+    let strip_padding = |indices: &[i64]| {
+        indices
+            .iter()
+            .skip_while(|&&index| index == pad_token_id)
+            .take_while(|&&index| index != pad_token_id)
+            .copied()
+            .collect::<Vec<i64>>()
+    };
+
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            ..Default::default()
+        };
+        let batch_output = model.generate_indices(
+            Some(&[input_context_1, input_context_2]),
+            Some(generate_options),
+        )?;
+        let single_outputs = [input_context_1, input_context_2]
+            .iter()
+            .map(|input_context| {
+                Ok(model
+                    .generate_indices(Some(&[*input_context]), Some(generate_options))?
+                    .remove(0))
+            })
+            .collect::<Result<Vec<_>, RustBertError>>()?;
+
+        assert_eq!(batch_output.len(), 2);
+        for (batch_sequence, single_sequence) in batch_output.iter().zip(single_outputs.iter()) {
+            let batch_indices = strip_padding(&batch_sequence.indices);
+            assert_eq!(batch_indices, strip_padding(&single_sequence.indices));
+            // No bigram is repeated within a sequence
+            let mut bigrams = batch_indices.windows(2).collect::<Vec<_>>();
+            let num_bigrams = bigrams.len();
+            bigrams.sort_unstable();
+            bigrams.dedup();
+            assert_eq!(bigrams.len(), num_bigrams);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_usage() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(20),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "The dog";
+
+    let output = model.generate(Some(&[input_context_1, input_context_2]), None)?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].usage.prompt_tokens, 5);
+    assert_eq!(output[1].usage.prompt_tokens, 2);
+    for generated_output in output {
+        assert!(generated_output.usage.completion_tokens > 0);
+        assert_eq!(
+            generated_output.usage.total_tokens,
+            generated_output.usage.prompt_tokens + generated_output.usage.completion_tokens
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_paused_beam_search() -> anyhow::Result<()> {
+    //    Resources definition
+    let generate_config = GenerateConfig {
+        max_length: Some(24),
+        model_resource: Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2)),
+        config_resource: Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2)),
+        merges_resource: Some(Box::new(RemoteResource::from_pretrained(
+            Gpt2MergesResources::GPT2,
+        ))),
+        do_sample: false,
+        num_beams: 3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    let generate_options = GenerateOptions {
+        output_scores: true,
+        ..Default::default()
+    };
+    let reference_output = model.generate_indices(Some(&input_context), Some(generate_options))?;
+
+    //    The search is paused every 5 steps and resumed until completion, its state being serialized at each pause
+    let mut progress =
+        model.generate_beam_search_steps(Some(&input_context), Some(generate_options), 5)?;
+    let mut num_pauses = 0;
+    let output = loop {
+        match progress {
+            BeamSearchProgress::Paused(state) => {
+                num_pauses += 1;
+                assert_eq!(state.current_length(), 6 + 5 * num_pauses);
+                let serialized_state = serde_json::to_string(&state)?;
+                let state: BeamSearchState = serde_json::from_str(&serialized_state)?;
+                assert_eq!(state.current_length(), 6 + 5 * num_pauses);
+                progress = model.resume_beam_search(state, Some(generate_options), Some(5))?;
+            }
+            BeamSearchProgress::Finished(output) => break output,
+        }
+    };
+    assert!(num_pauses > 0);
+    assert_eq!(output.len(), reference_output.len());
+    for (sequence, reference_sequence) in output.iter().zip(reference_output.iter()) {
+        assert_eq!(sequence.indices, reference_sequence.indices);
+        assert!((sequence.score.unwrap() - reference_sequence.score.unwrap()).abs() < 1e-5);
+    }
+
+    //    The search must be resumed with the same number of beams
+    let progress =
+        model.generate_beam_search_steps(Some(&input_context), Some(generate_options), 5)?;
+    if let BeamSearchProgress::Paused(state) = progress {
+        let invalid_options = GenerateOptions {
+            num_beams: Some(2),
+            ..generate_options
+        };
+        assert!(model
+            .resume_beam_search(state, Some(invalid_options), None)
+            .is_err());
+    } else {
+        panic!("The beam search should be paused");
+    }
+    //    Pausing is only supported by beam search
+    let greedy_options = GenerateOptions {
+        num_beams: Some(1),
+        ..Default::default()
+    };
+    assert!(model
+        .generate_beam_search_steps(Some(&input_context), Some(greedy_options), 5)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_bad_tokens_beam_search() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(36),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let bad_words = vec![" honeybees", " a member"];
+    let bad_word_ids = model
+        .get_tokenizer()
+        .encode_list(
+            bad_words.as_slice(),
+            512,
+            &TruncationStrategy::DoNotTruncate,
+            0,
+        )
+        .into_iter()
+        .map(|tokenized_input| tokenized_input.token_ids)
+        .collect::<Vec<Vec<i64>>>();
+
+    let input_context_1 = "Hello, my name is";
+
+    let baseline_generate_options = GenerateOptions {
+        output_scores: true,
+        ..Default::default()
+    };
+    let test_generate_options = GenerateOptions {
+        bad_word_ids: Some(&bad_word_ids),
+        output_scores: true,
+        ..Default::default()
+    };
+
+    let baseline_output =
+        model.generate(Some(&[input_context_1]), Some(baseline_generate_options))?;
+    let output = model.generate(Some(&[input_context_1]), Some(test_generate_options))?;
+
+    assert_eq!(baseline_output.len(), 1);
+    assert_eq!(
+        baseline_output[0].text,
+        "Hello, my name is John, and I am a member of the Church of Jesus Christ of Latter-day Saints.\n\nI am a Mormon. I have been a member"
+    );
+    assert!((baseline_output[0].score.unwrap() - (-1.0503)).abs() < 1e-4);
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(
+        output[0].text,
+        "Hello, my name is John, and I am the owner of a small business.\n\nI have been in business for over 20 years. I have been a business owner for"
+    );
+    assert!((output[0].score.unwrap() - (-1.3742)).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_prefix_allowed_token_beam_search() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    fn force_one_paragraph(_batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
+        let paragraph_tokens = [198, 628];
+
+        for paragraph_token in paragraph_tokens.iter() {
+            if previous_token_ids
+                .iter::<i64>()
+                .unwrap()
+                .any(|x| x == *paragraph_token)
+            {
+                return vec![50256];
+            }
+        }
+        (0..50255).collect()
+    }
+
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Rust is a";
+    let input_context_2 = "There was a ";
+
+    let generate_options = GenerateOptions {
+        prefix_allowed_tokens_fn: Some(&force_one_paragraph),
+        output_scores: true,
+        ..Default::default()
+    };
+
+    let output = model.generate(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(
+        output[0].text,
+        "Rust is a simple, fast, and easy-to-use framework for building web applications. It is designed to be easy to use and maintain, and"
+    );
+    assert!((output[0].score.unwrap() - (-1.2750)).abs() < 1e-4);
+    assert_eq!(
+        output[1].text,
+        "There was a urn in the back of the room, and I was sitting on it, and it looked like it was going to explode. And then I"
+    );
+    assert!((output[1].score.unwrap() - (-1.3326)).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
         model_resource,
         config_resource,
         vocab_resource,
@@ -648,7 +1365,7 @@ fn gpt2_greedy_token_scores() -> anyhow::Result<()> {
     let output = model.generate_indices(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -704,7 +1421,7 @@ fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
     let output = model.generate_indices(
         Some(&[input_context_1, input_context_2]),
         Some(generate_options),
-    );
+    )?;
 
     assert_eq!(output.len(), 2);
     assert_eq!(
@@ -729,175 +1446,2415 @@ fn gpt2_beam_search_token_scores() -> anyhow::Result<()> {
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_single_multi_turn_conversation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
-        do_sample: false,
-        device: Device::Cpu,
-        ..Default::default()
-    };
-    let conversation_model = ConversationModel::new(conversation_config)?;
-
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
+fn gpt2_finish_reason() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big Lebowski");
+    //    Forces the first prompt to end immediately, leaves the second unconstrained
+    fn force_first_eos(batch_id: i64, _previous_token_ids: &Tensor) -> Vec<i64> {
+        if batch_id == 0 {
+            vec![50256]
+        } else {
+            (0..50255).collect()
+        }
+    }
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_id).unwrap(), &"It\'s a comedy.");
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+
+    for num_beams in [1, 2] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            prefix_allowed_tokens_fn: Some(&force_first_eos),
+            ..Default::default()
+        };
+
+        let output = model.generate(
+            Some(&[input_context_1, input_context_2]),
+            Some(generate_options),
+        )?;
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].text, input_context_1);
+        assert_eq!(output[0].finish_reason, FinishReason::Stop);
+        assert_eq!(output[1].finish_reason, FinishReason::Length);
+    }
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
+fn gpt2_eos_step() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    //    Forces the first prompt (5 tokens) to end after 3 generated tokens, prevents the second from ending
+    fn force_first_eos_after_3_tokens(batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
+        if batch_id == 0 && previous_token_ids.size()[0] == 8 {
+            vec![50256]
+        } else {
+            (0..50255).collect()
+        }
+    }
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_1_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
-    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "It is a beautiful";
+    let generate_options = GenerateOptions {
+        prefix_allowed_tokens_fn: Some(&force_first_eos_after_3_tokens),
+        ..Default::default()
+    };
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
     assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(output[0].finish_reason, FinishReason::Stop);
+    assert_eq!(output[0].eos_step, Some(3));
+    assert_eq!(output[0].usage.completion_tokens, 4);
+    //    The output of the first sequence is padded to the length of the second sequence
+    assert_eq!(output[0].indices[8], 50256);
+    assert_eq!(output[0].indices.len(), 16);
+    assert_eq!(output[1].finish_reason, FinishReason::Length);
+    assert_eq!(output[1].eos_step, None);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_soft_stop_tokens() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "It is a beautiful";
+    // "." token, generated after "thing to see"
+    let period_token_id = 13;
+
+    //    The period is a hard stop: the generation halts on its first occurrence
+    let hard_stop_token_ids = [period_token_id, 50256];
+    let generate_options = GenerateOptions {
+        stop_token_ids: Some(&hard_stop_token_ids),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 1);
     assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"The Last Question"
+        output[0].indices,
+        vec![1026, 318, 257, 4950, 1517, 284, 766, 13]
     );
+    assert_eq!(output[0].finish_reason, FinishReason::Stop);
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_1_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+    //    The period is a soft stop: the generation continues past it
+    let soft_stop_token_ids = [period_token_id];
+    let generate_options = GenerateOptions {
+        stop_token_ids: Some(&hard_stop_token_ids),
+        soft_stop_token_ids: Some(&soft_stop_token_ids),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].indices[7], period_token_id);
+    assert!(output[0].indices.len() > 8);
+    assert_eq!(output[0].finish_reason, FinishReason::Length);
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation_with_truncation() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
-        max_length: Some(36),
-        min_length_for_response: 24,
+fn gpt2_stop_strings() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    //    Forces two consecutive single line breaks after two free tokens, so that the stop string
+    //    spans over two tokens. Line breaks are banned otherwise.
+    fn force_line_breaks(_batch_id: i64, previous_token_ids: &Tensor) -> Vec<i64> {
+        let sequence_length = previous_token_ids.size()[0];
+        if (sequence_length == 7) | (sequence_length == 8) {
+            vec![198]
+        } else {
+            (0..50256)
+                .filter(|token_id| ![198, 628].contains(token_id))
+                .collect()
+        }
+    }
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
+        stop_strings: vec!["\n\n".to_string()],
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
-
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_1_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
-    let conversation_2_id = conversation_manager.create("Hello how are you?");
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
-    assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"I'm good, how are you?"
-    );
+    let input_context = "Hello, my name is";
 
-    // Turn 2
-    let _ = conversation_manager
-        .get(&conversation_1_id)
-        .unwrap()
-        .add_user_input("Is it an action movie?");
-    let _ = conversation_manager
-        .get(&conversation_2_id)
-        .unwrap()
-        .add_user_input("Fine.");
+    for num_beams in [1, 2] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            prefix_allowed_tokens_fn: Some(&force_line_breaks),
+            ..Default::default()
+        };
 
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+        let output = model.generate(Some(&[input_context]), Some(generate_options))?;
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+        assert_eq!(output.len(), 1);
+        assert!(output[0].text.starts_with(input_context));
+        assert!(output[0].text.len() > input_context.len());
+        assert!(!output[0].text.contains('\n'));
+        assert_eq!(output[0].finish_reason, FinishReason::StopString);
+    }
 
     Ok(())
 }
 
 #[test]
-#[cfg_attr(not(feature = "all-tests"), ignore)]
-fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> anyhow::Result<()> {
-    //    Set-up conversation model
-    let conversation_config = ConversationConfig {
+fn gpt2_logit_bias() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    //    Strong positive bias for the " the" token (id 262)
+    let mut logit_bias = HashMap::new();
+    logit_bias.insert(262, 100.0);
+
+    let generate_config = GenerateConfig {
+        max_length: Some(12),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
         do_sample: false,
+        num_beams: 1,
+        logit_bias,
         device: Device::Cpu,
         ..Default::default()
     };
-    let conversation_model = ConversationModel::new(conversation_config)?;
+    let model = GPT2Generator::new(generate_config)?;
 
-    // Set-up conversation manager and add a conversation
-    let mut conversation_manager = ConversationManager::new();
-    let conversation_1_id =
-        conversation_manager.create("Going to the movies tonight - any suggestions?");
-    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+    let input_context = "Hello, my name is";
+    let output = model.generate_indices(Some(&[input_context]), None)?;
 
-    // Turn 1
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 2);
-    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].indices[5], 262);
+
+    //    The bias provided at generation time takes priority over the configuration
+    let no_bias = HashMap::new();
+    let generate_options = GenerateOptions {
+        logit_bias: Some(&no_bias),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 1);
+    assert_ne!(output[0].indices[5], 262);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_control_characters() -> anyhow::Result<()> {
+    //    Strong positive bias for the bell control character byte token (id 195)
+    let mut logit_bias = HashMap::new();
+    logit_bias.insert(195, 100.0);
+
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(3),
+        do_sample: false,
+        num_beams: 1,
+        logit_bias,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "Hello, my name is";
+    let output = model.generate(Some(&[input_context]), None)?;
     assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"The Last Question"
+        output[0].text,
+        format!("{}{}", input_context, "\u{7}".repeat(3))
     );
 
-    // Turn 2
-    let _ = conversation_manager.remove(&conversation_1_id);
-    let _ = conversation_manager
-        .get(&conversation_2_id)
-        .unwrap()
-        .add_user_input("Why do you recommend it?");
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 1);
+    //    Control characters are removed or escaped when enabled
+    let generate_options = GenerateOptions {
+        control_characters: Some(ControlCharacters::Strip),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+    assert_eq!(output[0].text, input_context);
+
+    let generate_options = GenerateOptions {
+        control_characters: Some(ControlCharacters::Escape),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
     assert_eq!(
-        output.get(&conversation_2_id).unwrap(),
-        &"It's a good book."
+        output[0].text,
+        format!("{}{}", input_context, "\\u0007".repeat(3))
     );
 
-    // Turn 3 (no new user input)
-    let output = conversation_model.generate_responses(&mut conversation_manager);
-    assert_eq!(output.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn gpt2_generate_config_builder() -> anyhow::Result<()> {
+    let built_config = GenerateConfigBuilder::new()
+        .with_max_length(Some(32))
+        .with_do_sample(false)
+        .with_num_beams(3)
+        .with_num_beam_groups(3)
+        .with_stop_strings(vec!["\n".to_string()])
+        .with_device(Device::Cpu)
+        .build();
+
+    let manual_config = GenerateConfig {
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 3,
+        num_beam_groups: Some(3),
+        stop_strings: vec!["\n".to_string()],
+        device: Device::Cpu,
+        ..Default::default()
+    };
+
+    assert_eq!(built_config.min_length, manual_config.min_length);
+    assert_eq!(built_config.max_length, manual_config.max_length);
+    assert_eq!(built_config.do_sample, manual_config.do_sample);
+    assert_eq!(built_config.early_stopping, manual_config.early_stopping);
+    assert_eq!(built_config.num_beams, manual_config.num_beams);
+    assert_eq!(built_config.temperature, manual_config.temperature);
+    assert_eq!(built_config.top_k, manual_config.top_k);
+    assert_eq!(built_config.top_p, manual_config.top_p);
+    assert_eq!(
+        built_config.repetition_penalty,
+        manual_config.repetition_penalty
+    );
+    assert_eq!(built_config.length_penalty, manual_config.length_penalty);
+    assert_eq!(
+        built_config.no_repeat_ngram_size,
+        manual_config.no_repeat_ngram_size
+    );
+    assert_eq!(
+        built_config.num_return_sequences,
+        manual_config.num_return_sequences
+    );
+    assert_eq!(built_config.num_beam_groups, manual_config.num_beam_groups);
+    assert_eq!(
+        built_config.diversity_penalty,
+        manual_config.diversity_penalty
+    );
+    assert_eq!(built_config.stop_strings, manual_config.stop_strings);
+    assert_eq!(built_config.logit_bias, manual_config.logit_bias);
+    assert_eq!(built_config.device, manual_config.device);
+    assert_eq!(
+        built_config.model_resource.get_local_path()?,
+        manual_config.model_resource.get_local_path()?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_config_file() -> anyhow::Result<()> {
+    let mut generation_config_file = tempfile::NamedTempFile::new()?;
+    generation_config_file.write_all(
+        br#"{
+            "_from_model_config": true,
+            "bos_token_id": 50256,
+            "eos_token_id": 50256,
+            "do_sample": false,
+            "num_beams": 4,
+            "max_length": 64,
+            "min_length": 8,
+            "early_stopping": "never",
+            "no_repeat_ngram_size": 0,
+            "length_penalty": 2.0,
+            "transformers_version": "4.27.0"
+        }"#,
+    )?;
+    let generation_config = GenerationConfigFile::from_file(generation_config_file.path());
+
+    //    Settings of the file override the defaults, explicit settings take precedence
+    let generate_config = GenerateConfigBuilder::from_generation_config(&generation_config)
+        .with_num_beams(2)
+        .build();
+    let default_config = GenerateConfig::default();
+
+    assert!(!generate_config.do_sample);
+    assert_eq!(generate_config.num_beams, 2);
+    assert_eq!(generate_config.max_length, Some(64));
+    assert_eq!(generate_config.min_length, 8);
+    assert!(!generate_config.early_stopping);
+    assert_eq!(generate_config.no_repeat_ngram_size, 0);
+    assert_eq!(generate_config.length_penalty, 2.0);
+    assert_eq!(generate_config.temperature, default_config.temperature);
+    assert_eq!(generate_config.top_p, default_config.top_p);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_with_ids() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(20),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "Hello, my name is";
+    let input_context_2 = "The dog";
+
+    for skip_special_tokens in [true, false] {
+        let output = model.generate_with_ids(
+            Some(&[input_context_1, input_context_2]),
+            None,
+            skip_special_tokens,
+        )?;
+
+        assert_eq!(output.len(), 2);
+        for (token_ids, text) in output.iter() {
+            assert_eq!(
+                &model
+                    .get_tokenizer()
+                    .decode(token_ids, skip_special_tokens, true),
+                text
+            );
+        }
+        //    The shorter prompt is left-padded with the end of sequence token
+        assert_eq!(
+            output[1].0.contains(&50256),
+            !skip_special_tokens,
+            "special tokens should only be returned when skip_special_tokens is false"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_tokenizer_padding_side() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(Gpt2VocabResources::GPT2);
+    let merges_resource = RemoteResource::from_pretrained(Gpt2MergesResources::GPT2);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource.get_local_path()?;
+
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::GPT2,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        None,
+    )?;
+
+    let input = ["Hello, my name is", "The dog"];
+    let token_ids = tokenizer
+        .tokenize_list(&input)
+        .iter()
+        .map(|tokens| tokenizer.convert_tokens_to_ids(tokens))
+        .collect::<Vec<Vec<i64>>>();
+    let content = token_ids[1].clone();
+    let padding_length = token_ids[0].len() - content.len();
+
+    let (left_padded, left_mask) =
+        tokenizer.pad_token_ids(token_ids.clone(), Some(50256), PaddingSide::Left);
+    let left_padded = left_padded.get(1).iter::<i64>()?.collect::<Vec<i64>>();
+    let left_mask = left_mask.get(1).iter::<i64>()?.collect::<Vec<i64>>();
+    assert_eq!(left_padded[..padding_length], vec![50256; padding_length]);
+    assert_eq!(left_padded[padding_length..], content);
+    assert_eq!(left_mask[..padding_length], vec![0; padding_length]);
+    assert_eq!(left_mask[padding_length..], vec![1; content.len()]);
+
+    let (right_padded, right_mask) =
+        tokenizer.pad_token_ids(token_ids, Some(50256), PaddingSide::Right);
+    let right_padded = right_padded.get(1).iter::<i64>()?.collect::<Vec<i64>>();
+    let right_mask = right_mask.get(1).iter::<i64>()?.collect::<Vec<i64>>();
+    assert_eq!(right_padded[..content.len()], content);
+    assert_eq!(right_padded[content.len()..], vec![50256; padding_length]);
+    assert_eq!(right_mask[..content.len()], vec![1; content.len()]);
+    assert_eq!(right_mask[content.len()..], vec![0; padding_length]);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_left_padded_batch_generation() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    The positions of the left-padded prompts start after their padding: the batched generation matches the
+    //    generation of each prompt on its own
+    let input_context = ["The dog", "The cat was sleeping on the sofa of the"];
+    let batch_output = model.generate_indices(Some(&input_context), None)?;
+    assert_eq!(batch_output.len(), 2);
+    for (prompt, batch_output) in input_context.iter().zip(batch_output.iter()) {
+        let output = model.generate_indices(Some(&[prompt]), None)?;
+        let padding_length = batch_output.indices.len() - output[0].indices.len();
+        assert_eq!(batch_output.indices[padding_length..], output[0].indices);
+    }
+
+    //    Decoder-only models must be padded on the left
+    let generate_config = GenerateConfig {
+        padding_side: PaddingSide::Right,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    assert!(matches!(
+        model.generate_indices(Some(&input_context), None),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_tokenizer_encode_batch() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(Gpt2VocabResources::GPT2);
+    let merges_resource = RemoteResource::from_pretrained(Gpt2MergesResources::GPT2);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource.get_local_path()?;
+
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::GPT2,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        None,
+    )?;
+
+    let input = [
+        "Hello, my name is",
+        "The dog",
+        "One two three four five six seven",
+    ];
+    let max_len = input
+        .iter()
+        .map(|text| tokenizer.tokenize(text).len())
+        .max()
+        .unwrap();
+
+    let (token_ids, attention_mask) = tokenizer.encode_batch(
+        &input,
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        Device::Cpu,
+    );
+    assert_eq!(token_ids.size(), vec![3, max_len as i64]);
+    assert_eq!(attention_mask.size(), vec![3, max_len as i64]);
+    assert_eq!(
+        attention_mask.sum(Kind::Int64).int64_value(&[]),
+        input
+            .iter()
+            .map(|text| tokenizer.tokenize(text).len() as i64)
+            .sum::<i64>()
+    );
+
+    // Truncation to the maximum length
+    let (token_ids, attention_mask) = tokenizer.encode_batch(
+        &input,
+        4,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        Device::Cpu,
+    );
+    assert_eq!(token_ids.size(), vec![3, 4]);
+    assert_eq!(attention_mask.size(), vec![3, 4]);
+
+    // Empty batch
+    let (token_ids, attention_mask) = tokenizer.encode_batch(
+        &[] as &[&str],
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        Device::Cpu,
+    );
+    assert_eq!(token_ids.size(), vec![0, 0]);
+    assert_eq!(attention_mask.size(), vec![0, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_perplexity() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let fluent_perplexity = model.perplexity("The dog was running in the park with its owner.")?;
+    let shuffled_perplexity =
+        model.perplexity("park owner its The the in running with dog was.")?;
+
+    assert!(fluent_perplexity > 1.0);
+    assert!(fluent_perplexity < shuffled_perplexity);
+
+    //    A single token does not allow computing a perplexity
+    assert!(model.perplexity("The").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_score_continuation() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let prompt = "The capital city of France is";
+    let likely_score = model.score_continuation(prompt, " Paris")?;
+    let unlikely_score = model.score_continuation(prompt, " banana peel")?;
+
+    assert_eq!(likely_score.num_tokens, 1);
+    assert_eq!(unlikely_score.num_tokens, 2);
+    assert!(likely_score.sum_log_probability < 0.0);
+    assert!(likely_score.sum_log_probability > unlikely_score.sum_log_probability);
+    assert!(likely_score.mean_log_probability > unlikely_score.mean_log_probability);
+    assert!(
+        (unlikely_score.mean_log_probability * 2.0 - unlikely_score.sum_log_probability).abs()
+            < 1e-6
+    );
+
+    //    Empty prompts or continuations cannot be scored
+    assert!(model.score_continuation("", " Paris").is_err());
+    assert!(model.score_continuation(prompt, "").is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_single_multi_turn_conversation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big Lebowski");
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_conversation_special_tokens() -> anyhow::Result<()> {
+    //    Set-up conversation model keeping the special tokens in the responses
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        skip_special_tokens: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(
+        output.get(&conversation_id).unwrap(),
+        &"The Big Lebowski<|endoftext|>"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_conversation_max_output_chars() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        max_output_chars: Some(12),
+        output_ellipsis: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+
+    // Turn 1: "The Big Lebowski" is truncated
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.get(&conversation_id).unwrap(), &"The Big\u{2026}");
+
+    // Turn 2: the history keeps the full response, "It's a comedy." is truncated
+    let _ = conversation_manager
+        .get(&conversation_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.get(&conversation_id).unwrap(), &"It\'s a\u{2026}");
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"The Last Question"
+    );
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_1_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation_with_truncation() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        max_length: Some(36),
+        min_length_for_response: 24,
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("Hello how are you?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"I'm good, how are you?"
+    );
+
+    // Turn 2
+    let _ = conversation_manager
+        .get(&conversation_1_id)
+        .unwrap()
+        .add_user_input("Is it an action movie?");
+    let _ = conversation_manager
+        .get(&conversation_2_id)
+        .unwrap()
+        .add_user_input("Fine.");
+
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"It\'s a comedy.");
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "all-tests"), ignore)]
+fn dialogpt_multiple_multi_turn_conversation_with_conversation_deletion() -> anyhow::Result<()> {
+    //    Set-up conversation model
+    let conversation_config = ConversationConfig {
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let conversation_model = ConversationModel::new(conversation_config)?;
+
+    // Set-up conversation manager and add a conversation
+    let mut conversation_manager = ConversationManager::new();
+    let conversation_1_id =
+        conversation_manager.create("Going to the movies tonight - any suggestions?");
+    let conversation_2_id = conversation_manager.create("What's the last book you have read?");
+
+    // Turn 1
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&conversation_1_id).unwrap(), &"The Big Lebowski");
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"The Last Question"
+    );
+
+    // Turn 2
+    let _ = conversation_manager.remove(&conversation_1_id);
+    let _ = conversation_manager
+        .get(&conversation_2_id)
+        .unwrap()
+        .add_user_input("Why do you recommend it?");
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 1);
+    assert_eq!(
+        output.get(&conversation_2_id).unwrap(),
+        &"It's a good book."
+    );
+
+    // Turn 3 (no new user input)
+    let output = conversation_model.generate_responses(&mut conversation_manager)?;
+    assert_eq!(output.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_validate_resources() -> anyhow::Result<()> {
+    let config_resource = RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2);
+    let vocab_resource = RemoteResource::from_pretrained(Gpt2VocabResources::GPT2);
+    let merges_resource = RemoteResource::from_pretrained(Gpt2MergesResources::GPT2);
+    let model_resource = RemoteResource::from_pretrained(Gpt2ModelResources::GPT2);
+    let bogus_model_resource = RemoteResource::new(
+        "https://huggingface.co/gpt2/resolve/main/does_not_exist.ot",
+        "gpt2",
+    );
+
+    assert!(model_resource.validate().is_ok());
+    assert!(bogus_model_resource.validate().is_err());
+
+    validate_resources(
+        ModelType::GPT2,
+        &config_resource,
+        &vocab_resource,
+        Some(&merges_resource),
+        &model_resource,
+    )?;
+    assert!(validate_resources(
+        ModelType::GPT2,
+        &config_resource,
+        &vocab_resource,
+        Some(&merges_resource),
+        &bogus_model_resource,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_trace() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "It is a beautiful";
+
+    //    The trace is opt-in
+    let output = model.generate_indices(Some(&[input_context]), None)?;
+    assert!(output[0].trace.is_none());
+
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            min_length: Some(16),
+            trace: true,
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].finish_reason, FinishReason::Length);
+        let trace = output[0].trace.as_ref().unwrap();
+        assert_eq!(trace.len(), output[0].usage.completion_tokens);
+        for step_trace in trace {
+            if num_beams == 1 {
+                assert_eq!(step_trace.candidates.len(), 5);
+            } else {
+                assert_eq!(step_trace.candidates.len(), 2 * num_beams as usize);
+            }
+            assert_eq!(step_trace.chosen.len(), num_beams as usize);
+        }
+        if num_beams == 1 {
+            let generated_ids = &output[0].indices[output[0].usage.prompt_tokens..];
+            assert!(trace
+                .iter()
+                .zip(generated_ids)
+                .all(|(step_trace, &token_id)| step_trace.chosen[0].token_id == token_id));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_prompt_template() -> anyhow::Result<()> {
+    let template = PromptTemplate::new("The {animal} was {action} in the {{garden}}")?;
+    assert_eq!(template.placeholders(), ["animal", "action"]);
+
+    let variables = HashMap::from([("animal", "cat"), ("action", "sleeping")]);
+    assert_eq!(
+        template.render(&variables)?,
+        "The cat was sleeping in the {garden}"
+    );
+
+    //    All placeholders must be provided
+    let missing_variables = HashMap::from([("animal", "cat")]);
+    assert!(template.render(&missing_variables).is_err());
+    assert!(PromptTemplate::new("The {animal was").is_err());
+
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::GPT2,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        max_length: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    let output = model.generate_from_template(&template, &[variables], None)?;
+    let expected_output = model.generate(&["The cat was sleeping in the {garden}"], None)?;
+
+    assert_eq!(output, expected_output);
+    assert!(model
+        .generate_from_template(&template, &[missing_variables], None)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_beam_search_shrink_finished_batch() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 3,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "It is a beautiful";
+    let input_context_2 = "The dog";
+    let input_context_3 = "Once upon a time, in a small village far away, there lived";
+    //    The "." and "," tokens terminate the generation, finishing some inputs before the others
+    let stop_token_ids = [13, 11, 50256];
+
+    let base_options = GenerateOptions {
+        stop_token_ids: Some(&stop_token_ids),
+        num_return_sequences: Some(2),
+        ..Default::default()
+    };
+    //    Plain beam search with scores, diverse beam search and beam search with a trace
+    let test_options = [
+        GenerateOptions {
+            output_scores: true,
+            ..base_options
+        },
+        GenerateOptions {
+            num_beams: Some(4),
+            num_beam_groups: Some(2),
+            diversity_penalty: Some(0.5),
+            output_scores: true,
+            ..base_options
+        },
+        GenerateOptions {
+            trace: true,
+            ..base_options
+        },
+    ];
+
+    for generate_options in test_options {
+        let expected_output = model.generate_indices(
+            Some(&[input_context_1, input_context_2, input_context_3]),
+            Some(generate_options),
+        )?;
+
+        let generate_options = GenerateOptions {
+            shrink_finished_batch: true,
+            ..generate_options
+        };
+        let output = model.generate_indices(
+            Some(&[input_context_1, input_context_2, input_context_3]),
+            Some(generate_options),
+        )?;
+
+        assert_eq!(output.len(), 6);
+        assert!(expected_output
+            .iter()
+            .any(|sequence| sequence.finish_reason == FinishReason::Stop));
+        for (sequence, expected_sequence) in output.iter().zip(expected_output.iter()) {
+            assert_eq!(sequence.indices, expected_sequence.indices);
+            assert_eq!(sequence.finish_reason, expected_sequence.finish_reason);
+            if generate_options.output_scores {
+                assert!((sequence.score.unwrap() - expected_sequence.score.unwrap()).abs() < 1e-4);
+                let token_scores = sequence.token_scores.as_ref().unwrap();
+                let expected_token_scores = expected_sequence.token_scores.as_ref().unwrap();
+                assert_eq!(token_scores.len(), expected_token_scores.len());
+                for (score, expected_score) in token_scores.iter().zip(expected_token_scores) {
+                    assert!((score - expected_score).abs() < 1e-4);
+                }
+            }
+            if generate_options.trace {
+                let trace = sequence.trace.as_ref().unwrap();
+                let expected_trace = expected_sequence.trace.as_ref().unwrap();
+                assert_eq!(trace.len(), expected_trace.len());
+                for (step, expected_step) in trace.iter().zip(expected_trace) {
+                    assert_eq!(step.chosen.len(), expected_step.chosen.len());
+                    for (chosen, expected_chosen) in step.chosen.iter().zip(&expected_step.chosen) {
+                        assert_eq!(chosen.beam_index, expected_chosen.beam_index);
+                        assert_eq!(chosen.token_id, expected_chosen.token_id);
+                        assert!((chosen.score - expected_chosen.score).abs() < 1e-4);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_beam_search_final_rescorer() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let generate_config = GenerateConfig {
+        max_length: Some(20),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 4,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+
+    //    Reference finished hypotheses, sorted by decreasing score
+    let generate_options = GenerateOptions {
+        num_return_sequences: Some(4),
+        output_scores: true,
+        ..Default::default()
+    };
+    let hypotheses = model.generate(Some(&[input_context]), Some(generate_options))?;
+    assert_eq!(hypotheses.len(), 4);
+    let last_hypothesis = &hypotheses[3];
+
+    //    Rescoring the hypotheses with a bonus for the lowest-ranked one selects it
+    let rescorer = |text: &str, score: f64| {
+        if text == last_hypothesis.text {
+            score + 100.0
+        } else {
+            score
+        }
+    };
+    let generate_options = GenerateOptions {
+        num_return_sequences: Some(2),
+        output_scores: true,
+        final_rescorer: Some(&rescorer),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].text, last_hypothesis.text);
+    assert!((output[0].score.unwrap() - (last_hypothesis.score.unwrap() + 100.0)).abs() < 1e-4);
+    assert_eq!(output[1].text, hypotheses[0].text);
+    assert!((output[1].score.unwrap() - hypotheses[0].score.unwrap()).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_bytes_lossy_decoding() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_path = RemoteResource::from_pretrained(Gpt2VocabResources::GPT2).get_local_path()?;
+    let merges_path =
+        RemoteResource::from_pretrained(Gpt2MergesResources::GPT2).get_local_path()?;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::GPT2,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        None,
+    )?;
+
+    //    "é" is encoded as the bytes [0xC3, 0xA9], represented by the byte-level tokens "Ã" and "©"
+    let mut token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize("The caf"));
+    token_ids.extend(tokenizer.convert_tokens_to_ids(&["Ã"]));
+    let (text, num_replaced_bytes) = tokenizer.decode_lossy(&token_ids, true, true);
+    assert_eq!(text, "The caf\u{FFFD}");
+    assert_eq!(num_replaced_bytes, 1);
+
+    token_ids.extend(tokenizer.convert_tokens_to_ids(&["©"]));
+    let (text, num_replaced_bytes) = tokenizer.decode_lossy(&token_ids, true, true);
+    assert_eq!(text, "The café");
+    assert_eq!(text, tokenizer.decode(&token_ids, true, true));
+    assert_eq!(num_replaced_bytes, 0);
+
+    //    Generated bytes match the generated texts
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let input_context = ["The dog", "Le café"];
+    let output = model.generate_bytes(Some(&input_context), None)?;
+    let text_output = model.generate(Some(&input_context), None)?;
+
+    assert_eq!(output.len(), 2);
+    for (bytes_output, text_output) in output.iter().zip(text_output.iter()) {
+        assert_eq!(std::str::from_utf8(&bytes_output.bytes)?, text_output.text);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_greedy_prefix_sampling() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    let greedy_prefix_len = 4;
+    let generate_config = GenerateConfig {
+        max_length: Some(24),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: true,
+        num_beams: 1,
+        num_return_sequences: 3,
+        greedy_prefix_len,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let prompt_length = 2;
+
+    //    Reference greedy decoding
+    let greedy_options = GenerateOptions {
+        do_sample: Some(false),
+        num_return_sequences: Some(1),
+        ..Default::default()
+    };
+    let greedy_output = model.generate_indices(Some(&[input_context]), Some(greedy_options))?;
+    let greedy_prefix = &greedy_output[0].indices[..prompt_length + greedy_prefix_len];
+
+    //    The first tokens of the sampled sequences are decoded greedily across runs
+    for _ in 0..2 {
+        let output = model.generate_indices(Some(&[input_context]), None)?;
+        assert_eq!(output.len(), 3);
+        for sequence in output.iter() {
+            assert_eq!(
+                &sequence.indices[..prompt_length + greedy_prefix_len],
+                greedy_prefix
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_sanitize_nan_logits() -> anyhow::Result<()> {
+    //    Resources definition
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(Gpt2VocabResources::GPT2));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(Gpt2MergesResources::GPT2));
+    let model_resource = Box::new(RemoteResource::from_pretrained(Gpt2ModelResources::GPT2));
+
+    //    A NaN bias injects an invalid logit for the "." token at every generation step
+    let nan_token_id = 13;
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        do_sample: false,
+        num_beams: 1,
+        logit_bias: HashMap::from([(nan_token_id, f64::NAN)]),
+        sanitize_logits: true,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let prompt_length = 2;
+    for num_beams in [1, 3] {
+        let generate_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            output_scores: true,
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+        assert_eq!(output.len(), 1);
+        let generated_indices = &output[0].indices[prompt_length..];
+        assert!(!generated_indices.is_empty());
+        assert!(generated_indices
+            .iter()
+            .all(|index| (0..50257).contains(index) && *index != nan_token_id));
+        assert!(output[0].score.unwrap().is_finite());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_negative_prompt_guidance() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let unguided_output = model.generate(Some(&[input_context]), None)?;
+    let unguided_continuation = unguided_output[0].text[input_context.len()..].to_string();
+
+    //    Count the words of a continuation also present in the negative prompt
+    let negative_words = unguided_continuation
+        .split_whitespace()
+        .collect::<std::collections::HashSet<&str>>();
+    let count_negative_words = |text: &str| {
+        text[input_context.len()..]
+            .split_whitespace()
+            .filter(|word| negative_words.contains(word))
+            .count()
+    };
+
+    //    A null guidance scale leaves the generation unchanged
+    let generate_options = GenerateOptions {
+        negative_prompt: Some(unguided_continuation.as_str()),
+        guidance_scale: Some(0.0),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+    assert_eq!(output[0].text, unguided_output[0].text);
+
+    //    A high guidance scale steers the generation away from the negative prompt
+    let generate_options = GenerateOptions {
+        negative_prompt: Some(unguided_continuation.as_str()),
+        guidance_scale: Some(3.0),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[input_context]), Some(generate_options))?;
+    assert_ne!(output[0].text, unguided_output[0].text);
+    assert!(count_negative_words(&output[0].text) < count_negative_words(&unguided_output[0].text));
+
+    //    Negative prompts are not supported by beam search
+    let generate_options = GenerateOptions {
+        negative_prompt: Some(unguided_continuation.as_str()),
+        guidance_scale: Some(3.0),
+        num_beams: Some(3),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&[input_context]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_timing() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(12),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = "The dog";
+    let prompt_length = 2;
+    let generate_options = GenerateOptions {
+        output_timing: true,
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&[input_context]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 1);
+    let timing = output[0].timing.unwrap();
+    assert!(timing.elapsed > std::time::Duration::ZERO);
+    assert_eq!(
+        timing.tokens_generated,
+        output[0].indices.len() - prompt_length
+    );
+    assert_eq!(timing.tokens_generated, output[0].usage.completion_tokens);
+    assert!(timing.tokens_per_second() > 0.0);
+
+    //    Timings are only returned when requested
+    let output = model.generate(Some(&[input_context]), None)?;
+    assert!(output[0].timing.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_pad_to_length() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    let pad_token_id = 50256;
+    let reference_output = model.generate_indices(Some(&input_context), None)?;
+    let generate_options = GenerateOptions {
+        pad_to_length: Some(24),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&input_context), Some(generate_options))?;
+
+    assert_eq!(output.len(), 2);
+    //    Prompts are padded to the length of the longest prompt
+    let padded_prompt_length = output
+        .iter()
+        .map(|sequence| sequence.usage.prompt_tokens)
+        .max()
+        .unwrap();
+    for (padded, reference) in output.iter().zip(reference_output.iter()) {
+        assert!(reference.sequence_length.is_none());
+        let sequence_length = padded.sequence_length.unwrap();
+        assert_eq!(padded.indices.len(), 24);
+        assert_eq!(
+            padded.indices[..sequence_length],
+            reference.indices[..sequence_length]
+        );
+        assert!(padded.indices[sequence_length..]
+            .iter()
+            .all(|token_id| *token_id == pad_token_id));
+        assert!(reference.indices[sequence_length..]
+            .iter()
+            .all(|token_id| *token_id == pad_token_id));
+        assert_eq!(
+            sequence_length,
+            padded_prompt_length + reference.usage.completion_tokens
+        );
+    }
+
+    //    A padding length lower than the maximum generation length is rejected before generating
+    let generate_options = GenerateOptions {
+        pad_to_length: Some(12),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate_indices(Some(&input_context), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_kv_cache_window() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2);
+    let weights_resource = RemoteResource::from_pretrained(Gpt2ModelResources::GPT2);
+    let config_path = config_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let config = Gpt2Config::from_file(config_path);
+    let gpt2_model = GPT2LMHeadModel::new(vs.root(), &config);
+    vs.load(weights_path)?;
+
+    //    The cache stays bounded by the window over a long generation
+    let window = 8;
+    let mut past = Cache::None;
+    let mut next_token = Tensor::of_slice(&[464i64]).unsqueeze(0);
+    for step in 0..64 {
+        let cached_length = past.cached_length().unwrap_or(0);
+        let output = gpt2_model.forward_t(
+            Some(&next_token),
+            past,
+            Some(&Tensor::ones(
+                &[1, cached_length + 1],
+                (Kind::Int64, device),
+            )),
+            None,
+            Some(&Tensor::of_slice(&[step]).unsqueeze(0)),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        past = output.cache;
+        past.truncate_to_window(window)?;
+        assert_eq!(past.cached_length(), Some(std::cmp::min(step + 1, window)));
+        next_token = output.lm_logits.select(1, -1).argmax(-1, true);
+    }
+
+    //    A window covering the whole sequence does not change the generated output
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        min_length: 32,
+        do_sample: false,
+        num_beams: 1,
+        device,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    let reference_output = model.generate_indices(Some(&input_context), None)?;
+    let full_window_output = model.generate_indices(
+        Some(&input_context),
+        Some(GenerateOptions {
+            kv_cache_window: Some(64),
+            ..Default::default()
+        }),
+    )?;
+    let small_window_output = model.generate_indices(
+        Some(&input_context),
+        Some(GenerateOptions {
+            kv_cache_window: Some(4),
+            ..Default::default()
+        }),
+    )?;
+
+    for ((reference, full_window), small_window) in reference_output
+        .iter()
+        .zip(full_window_output.iter())
+        .zip(small_window_output.iter())
+    {
+        assert_eq!(reference.indices, full_window.indices);
+        assert_eq!(small_window.indices.len(), reference.indices.len());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_kv_cache_sink_tokens() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2);
+    let weights_resource = RemoteResource::from_pretrained(Gpt2ModelResources::GPT2);
+    let config_path = config_resource.get_local_path()?;
+    let weights_path = weights_resource.get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let config = Gpt2Config::from_file(config_path);
+    let gpt2_model = GPT2LMHeadModel::new(vs.root(), &config);
+    vs.load(weights_path)?;
+
+    let first_layer_positions = |cache: &Cache, start: i64, length: i64| match cache {
+        Cache::GPT2Cache(Some(layer_pasts)) => layer_pasts[0].narrow(3, start, length),
+        _ => panic!("Expected a GPT2 cache"),
+    };
+
+    //    The first positions of the cache are kept after the window has advanced
+    let window = 8;
+    let sink_tokens = 2;
+    let mut past = Cache::None;
+    let mut sink_cache = None;
+    let mut next_token = Tensor::of_slice(&[464i64]).unsqueeze(0);
+    for step in 0..32 {
+        let cached_length = past.cached_length().unwrap_or(0);
+        let output = gpt2_model.forward_t(
+            Some(&next_token),
+            past,
+            Some(&Tensor::ones(
+                &[1, cached_length + 1],
+                (Kind::Int64, device),
+            )),
+            None,
+            Some(&Tensor::of_slice(&[step]).unsqueeze(0)),
+            None,
+            None,
+            None,
+            false,
+        )?;
+        past = output.cache;
+        past.truncate_to_window_with_sink_tokens(window, sink_tokens)?;
+        assert_eq!(past.cached_length(), Some(std::cmp::min(step + 1, window)));
+        if step + 1 == sink_tokens {
+            sink_cache = Some(first_layer_positions(&past, 0, sink_tokens));
+        }
+        if step + 1 > window {
+            assert!(
+                first_layer_positions(&past, 0, sink_tokens).equal(sink_cache.as_ref().unwrap())
+            );
+        }
+        next_token = output.lm_logits.select(1, -1).argmax(-1, true);
+    }
+
+    //    The sink tokens must fit in the window
+    assert!(past.truncate_to_window_with_sink_tokens(4, 4).is_err());
+
+    //    Generation with a windowed cache and sink tokens
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        min_length: 32,
+        do_sample: false,
+        num_beams: 1,
+        kv_cache_window: Some(6),
+        kv_cache_sink_tokens: 2,
+        device,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    let output = model.generate_indices(Some(&input_context), None)?;
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].indices.len(), output[1].indices.len());
+
+    //    The kept positions are selected independently for each sequence, e.g. after their left padding
+    let mut cache = Cache::GPT2Cache(Some(vec![
+        Tensor::arange(16, (Kind::Int64, device)).view([2, 2, 1, 4, 1])
+    ]));
+    cache.select_positions(&Tensor::of_slice(&[0i64, 3, 1, 2]).view([2, 2]))?;
+    let selected = match &cache {
+        Cache::GPT2Cache(Some(layer_pasts)) => {
+            layer_pasts[0].view(-1).iter::<i64>()?.collect::<Vec<i64>>()
+        }
+        _ => panic!("Expected a GPT2 cache"),
+    };
+    assert_eq!(selected, vec![0, 3, 5, 6, 8, 11, 13, 14]);
+
+    //    The sink tokens of the generate options must fit in the window
+    let generate_options = GenerateOptions {
+        kv_cache_window: Some(4),
+        kv_cache_sink_tokens: Some(4),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate_indices(Some(&input_context), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_kv_cache_device() -> anyhow::Result<()> {
+    //    Cached tensors are moved to the cache device
+    let mut cache = Cache::GPT2Cache(Some(
+        (0..2)
+            .map(|_| Tensor::zeros(&[2, 1, 12, 4, 64], (Kind::Float, Device::Cpu)))
+            .collect(),
+    ));
+    cache.to_device(Device::Cpu);
+    match &cache {
+        Cache::GPT2Cache(Some(layer_pasts)) => {
+            assert!(layer_pasts
+                .iter()
+                .all(|layer_past| layer_past.device() == Device::Cpu));
+        }
+        _ => panic!("Wrong cache returned for GPT2"),
+    }
+
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    //    Greedy decoding and beam search outputs are not affected by the cache placement
+    for num_beams in [1, 3] {
+        let reference_options = GenerateOptions {
+            num_beams: Some(num_beams),
+            ..Default::default()
+        };
+        let generate_options = GenerateOptions {
+            kv_cache_device: Some(Device::Cpu),
+            ..reference_options
+        };
+        let reference_output =
+            model.generate_indices(Some(&input_context), Some(reference_options))?;
+        let output = model.generate_indices(Some(&input_context), Some(generate_options))?;
+        assert_eq!(output.len(), 2);
+        for (sequence, reference) in output.iter().zip(reference_output.iter()) {
+            assert_eq!(sequence.indices, reference.indices);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_repetition_window() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(1),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        repetition_penalty: 10.0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context =
+        ["1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11,"];
+    //    The only occurrence of " 12" precedes the window and is not penalized
+    let generate_options = GenerateOptions {
+        repetition_window: Some(8),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&input_context), Some(generate_options))?;
+    assert_eq!(output.len(), 1);
+    assert!(output[0].text.ends_with(" 12"));
+
+    //    Without a window, the full sequence is penalized
+    let output = model.generate(Some(&input_context), None)?;
+    assert_eq!(output.len(), 1);
+    assert!(!output[0].text.ends_with(" 12"));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_frequency_presence_penalty() -> anyhow::Result<()> {
+    //    Set-up model, forcing the generation of " the" with a logit bias
+    let mut logit_bias = HashMap::new();
+    logit_bias.insert(262, 100.0);
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(24),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        logit_bias,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let input_context = ["The dog"];
+    let count_forced_tokens = |generate_options: GenerateOptions| {
+        let output = model.generate_indices(Some(&input_context), Some(generate_options))?;
+        output[0].indices.iter().filter(|id| **id == 262).count()
+    };
+
+    let reference_count = count_forced_tokens(GenerateOptions::default());
+    assert_eq!(reference_count, 24);
+
+    //    The fixed presence penalty does not overcome the bias
+    let presence_count = count_forced_tokens(GenerateOptions {
+        presence_penalty: Some(10.0),
+        ..Default::default()
+    });
+    assert_eq!(presence_count, 24);
+
+    //    The frequency penalty grows with every occurrence until the token is no longer generated
+    let frequency_count = count_forced_tokens(GenerateOptions {
+        frequency_penalty: Some(10.0),
+        ..Default::default()
+    });
+    assert!(frequency_count > 0);
+    assert!(frequency_count < 24);
+    let stronger_frequency_count = count_forced_tokens(GenerateOptions {
+        frequency_penalty: Some(20.0),
+        ..Default::default()
+    });
+    assert!(stronger_frequency_count < frequency_count);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_grouped_indices() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 3,
+        num_return_sequences: 2,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context = ["The dog", "The cat was sleeping on the"];
+    let output = model.generate_grouped_indices(Some(&input_context), None)?;
+    let reference_options = GenerateOptions {
+        output_scores: true,
+        ..Default::default()
+    };
+    let reference_output = model.generate_indices(Some(&input_context), Some(reference_options))?;
+
+    //    The sequences are grouped by prompt
+    assert_eq!(output.len(), 2);
+    assert!(output.iter().all(|sequences| sequences.len() == 2));
+    for (sequence, reference) in output.iter().flatten().zip(reference_output.iter()) {
+        assert_eq!(sequence.0, reference.indices);
+        assert!((sequence.1 as f64 - reference.score.unwrap()).abs() < 1e-4);
+    }
+
+    //    The number of returned sequences can be overridden
+    let generate_options = GenerateOptions {
+        num_return_sequences: Some(3),
+        ..Default::default()
+    };
+    let output = model.generate_grouped_indices(Some(&input_context), Some(generate_options))?;
+    assert_eq!(output.len(), 2);
+    assert!(output.iter().all(|sequences| sequences.len() == 3));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_estimated_memory_bytes() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = Box::new(RemoteResource::from_pretrained(Gpt2ConfigResources::GPT2));
+    let config_path = config_resource.get_local_path()?;
+    let config = Gpt2Config::from_file(config_path);
+
+    let vs = nn::VarStore::new(Device::Cpu);
+    let _gpt2_model = Gpt2Model::new(vs.root(), &config);
+    let num_parameters = vs
+        .variables()
+        .values()
+        .map(|variable| variable.numel() as i64)
+        .sum::<i64>();
+
+    assert_eq!(config.num_parameters(), num_parameters);
+    let estimated_memory_bytes = config.estimated_memory_bytes(Kind::Float);
+    assert!(estimated_memory_bytes >= num_parameters * 4);
+    assert!(estimated_memory_bytes <= num_parameters * 6);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_attention_mask_kinds() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(12),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    Left-padded "The dog" and "The cat was sleeping"
+    let input_ids =
+        Tensor::of_slice(&[50256i64, 50256, 464, 3290, 464, 3797, 373, 11029]).view((2, 4));
+    let attention_mask = Tensor::of_slice(&[0i64, 0, 1, 1, 1, 1, 1, 1]).view((2, 4));
+
+    let reference_output =
+        model.generate_from_ids_and_past(input_ids.copy(), Some(attention_mask.copy()), None)?;
+    for kind in [Kind::Bool, Kind::Int, Kind::Float, Kind::Double] {
+        let output = model.generate_from_ids_and_past(
+            input_ids.copy(),
+            Some(attention_mask.to_kind(kind)),
+            None,
+        )?;
+        assert_eq!(output.len(), 2);
+        for (sequence, reference) in output.iter().zip(reference_output.iter()) {
+            assert_eq!(sequence.indices, reference.indices);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_prompt_cache() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(10),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let prompt = "The weather forecast for tomorrow";
+    let suffixes = [" is", " in London says that it will be"];
+    let prompt_cache = model.cache_prompt(prompt)?;
+    let output = model.generate_from_prompt_cache(&prompt_cache, &suffixes, None)?;
+
+    //    Generation from the cached prompt matches the generation from the full prompt
+    assert_eq!(output.len(), 2);
+    let tokenizer = model.get_tokenizer();
+    for (suffix, cached_output) in suffixes.iter().zip(output.iter()) {
+        let full_prompt_ids = [
+            prompt_cache.token_ids(),
+            tokenizer
+                .convert_tokens_to_ids(&tokenizer.tokenize(suffix))
+                .as_slice(),
+        ]
+        .concat();
+        let full_prompt_output = model.generate_from_ids_and_past(
+            Tensor::of_slice(&full_prompt_ids).unsqueeze(0),
+            None,
+            None,
+        )?;
+        assert_eq!(cached_output.indices, full_prompt_output[0].indices);
+        assert!(cached_output.indices.len() > full_prompt_ids.len());
+    }
+
+    //    The cache is left unchanged and can be reused
+    let reused_output = model.generate_from_prompt_cache(&prompt_cache, &suffixes[..1], None)?;
+    assert_eq!(reused_output[0].indices, output[0].indices);
+
+    //    Caches not matching the batch of the input or the model are rejected before generating
+    let input_ids = Tensor::of_slice(prompt_cache.token_ids()).unsqueeze(0);
+    let (_, past) = model.generate_from_ids_and_past_with_cache(input_ids.copy(), None, None)?;
+    let generate_options = GenerateOptions {
+        past: Some(&past),
+        ..Default::default()
+    };
+    assert!(model
+        .generate_from_ids_and_past(input_ids.repeat(&[2, 1]), None, Some(generate_options))
+        .is_err());
+    let t5_past = Cache::T5Cache(None);
+    let generate_options = GenerateOptions {
+        past: Some(&t5_past),
+        ..Default::default()
+    };
+    assert!(model
+        .generate_from_ids_and_past(input_ids, None, Some(generate_options))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generate_resumable() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(6),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let tokenizer = model.get_tokenizer();
+
+    let prompt_cache = model.cache_prompt("The weather forecast")?;
+    let (first_output, first_cache) = model
+        .generate_resumable(&prompt_cache, " for tomorrow", None)?
+        .remove(0);
+    assert_eq!(first_cache.token_ids(), first_output.indices.as_slice());
+
+    //    Resuming from the returned cache matches the generation from the full sequence
+    let suffix = " In London,";
+    let (resumed_output, _) = model
+        .generate_resumable(&first_cache, suffix, None)?
+        .remove(0);
+    let full_ids = [
+        first_output.indices.as_slice(),
+        tokenizer
+            .convert_tokens_to_ids(&tokenizer.tokenize(suffix))
+            .as_slice(),
+    ]
+    .concat();
+    //    The end of sequence token possibly generated is attended, as by the resumed generation
+    let full_output = model.generate_from_ids_and_past(
+        Tensor::of_slice(&full_ids).unsqueeze(0),
+        Some(Tensor::ones(
+            &[1, full_ids.len() as i64],
+            (Kind::Int64, Device::Cpu),
+        )),
+        None,
+    )?;
+    assert_eq!(resumed_output.indices, full_output[0].indices);
+    assert!(resumed_output.indices.len() > full_ids.len());
+
+    //    Beam search cannot be resumed
+    let generate_options = GenerateOptions {
+        num_beams: Some(2),
+        ..Default::default()
+    };
+    assert!(model
+        .generate_resumable(&prompt_cache, "", Some(generate_options))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_logit_clamp() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_new_tokens: Some(8),
+        do_sample: false,
+        num_beams: 1,
+        logit_clamp: Some((-1.0, 1.0)),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let generate_options = GenerateOptions {
+        trace: true,
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&["The dog"]), Some(generate_options))?;
+
+    //    With all logits in [min, max], the log-probability of any token is within
+    //    [min - max - ln(vocab_size), max - min - ln(vocab_size)]
+    let log_vocab_size = (50257f64).ln();
+    let trace = output[0].trace.as_ref().unwrap();
+    assert!(!trace.is_empty());
+    for candidate in trace
+        .iter()
+        .flat_map(|step_trace| step_trace.candidates.iter())
+    {
+        assert!(candidate.score <= 2.0 - log_vocab_size + 1e-4);
+        assert!(candidate.score >= -2.0 - log_vocab_size - 1e-4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_max_new_tokens() -> anyhow::Result<()> {
+    //    Set-up model, the maximum number of new tokens takes precedence over the maximum length
+    let generate_config = GenerateConfig {
+        max_length: Some(4),
+        max_new_tokens: Some(20),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let prompts = [
+        "The dog",
+        "The quick brown fox jumps over the lazy dog while the cat was sleeping on the sofa of the living room",
+    ];
+    for prompt in prompts {
+        let prompt_length = model
+            .get_tokenizer()
+            .convert_tokens_to_ids(&model.get_tokenizer().tokenize(prompt))
+            .len();
+        let output = model.generate_indices(Some(&[prompt]), None)?;
+
+        assert_eq!(output.len(), 1);
+        let num_new_tokens = output[0].indices.len() - prompt_length;
+        assert!(num_new_tokens > 0);
+        assert!(num_new_tokens <= 20);
+
+        //    The maximum number of new tokens of the generate options takes precedence over the configuration,
+        //    and their maximum length over their maximum number of new tokens
+        let generate_options = GenerateOptions {
+            max_new_tokens: Some(5),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[prompt]), Some(generate_options))?;
+        let num_new_tokens = output[0].indices.len() - prompt_length;
+        assert!(num_new_tokens > 0);
+        assert!(num_new_tokens <= 5);
+
+        let generate_options = GenerateOptions {
+            max_length: Some(prompt_length as i64 + 2),
+            max_new_tokens: Some(5),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[prompt]), Some(generate_options))?;
+        assert!(output[0].indices.len() <= prompt_length + 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_temperature_schedule() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(24),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let prompt_length = 2;
+
+    //    A very low temperature for the first tokens reproduces the greedy decoding, the last
+    //    (very high) temperature persists until the end of the sequence
+    let temperature_schedule = [0.01, 0.01, 0.01, 0.01, 100.0];
+    let generate_options = GenerateOptions {
+        do_sample: Some(true),
+        top_k: Some(0),
+        top_p: Some(1.0),
+        temperature_schedule: Some(&temperature_schedule),
+        ..Default::default()
+    };
+    tch::manual_seed(42);
+    let greedy_output = model.generate_indices(Some(&["The dog"]), None)?;
+    let scheduled_output = model.generate_indices(Some(&["The dog"]), Some(generate_options))?;
+
+    let greedy_indices = &greedy_output[0].indices[prompt_length..];
+    let scheduled_indices = &scheduled_output[0].indices[prompt_length..];
+    assert_eq!(scheduled_indices.len(), 22);
+    assert_eq!(&scheduled_indices[..4], &greedy_indices[..4]);
+    assert!(scheduled_indices[4..]
+        .iter()
+        .zip(greedy_indices[4..].iter())
+        .any(|(scheduled, greedy)| scheduled != greedy));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_adaptive_temperature() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: None,
+        do_sample: true,
+        top_k: 0,
+        top_p: 1.0,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    The entropy of the sampled distribution at the first step only depends on the prompt
+    let first_step_entropy = |prompt: &str, adaptive_temperature_window: Option<usize>| {
+        let generate_options = GenerateOptions {
+            max_new_tokens: Some(1),
+            adaptive_temperature_window,
+            adaptive_temperature_max: Some(5.0),
+            output_entropy: Some(EntropyStage::ProcessedLogits),
+            ..Default::default()
+        };
+        let output = model.generate_indices(Some(&[prompt]), Some(generate_options))?;
+        output[0].entropy.as_ref().unwrap()[0]
+    };
+
+    //    A repetitive context raises the temperature, flattening the distribution
+    let repetitive_prompt = "the the the the the the the the";
+    assert!(
+        first_step_entropy(repetitive_prompt, Some(6))
+            > first_step_entropy(repetitive_prompt, None) + 0.1
+    );
+
+    //    Without repeated tokens in the window, the temperature is unchanged
+    let prompt = "The quick brown fox jumps over";
+    assert!((first_step_entropy(prompt, Some(6)) - first_step_entropy(prompt, None)).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generate_with_callback_injection() -> anyhow::Result<()> {
+    //    Set-up model
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 1,
+        no_repeat_ngram_size: 0,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+    let tokenizer = model.get_tokenizer();
+    let injected_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize(" purple elephant"));
+
+    //    Inject a fixed continuation after the first generated token, then stop after 4 more tokens
+    let mut num_calls = 0;
+    let output = model.generate_with_callback("The dog", None, &mut |completion: &[i64]| {
+        num_calls += 1;
+        match num_calls {
+            1 => {
+                assert_eq!(completion.len(), 1);
+                GenerationControl::InjectTokens(injected_ids.clone())
+            }
+            2..=4 => GenerationControl::Continue,
+            _ => GenerationControl::Stop,
+        }
+    })?;
+
+    let prompt_length = 2;
+    let generated_indices = &output.indices[prompt_length..];
+    assert_eq!(output.finish_reason, FinishReason::Callback);
+    assert_eq!(
+        &generated_indices[1..1 + injected_ids.len()],
+        injected_ids.as_slice()
+    );
+    assert_eq!(generated_indices.len(), 1 + injected_ids.len() + 4);
+    assert_eq!(output.usage.prompt_tokens, prompt_length);
+    assert_eq!(output.usage.completion_tokens, generated_indices.len());
+
+    //    Without injection, the greedy generation matches the standard generation, including its penalties
+    let generate_options = GenerateOptions {
+        repetition_penalty: Some(1.5),
+        no_repeat_ngram_size: Some(2),
+        ..Default::default()
+    };
+    let reference_output = model.generate_indices(Some(&["The dog"]), Some(generate_options))?;
+    let output =
+        model.generate_with_callback("The dog", Some(generate_options), &mut |_: &[i64]| {
+            GenerationControl::Continue
+        })?;
+    assert_eq!(output.indices, reference_output[0].indices);
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_empty_prompts() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    Mixed batch: the empty prompts return empty sequences in their position
+    let input_context = "The dog";
+    let reference_output = model.generate(Some(&[input_context]), None)?;
+    let output = model.generate(Some(&["", input_context, ""]), None)?;
+
+    assert_eq!(output.len(), 3);
+    assert_eq!(output[0].text, "");
+    assert_eq!(output[0].finish_reason, FinishReason::EmptyInput);
+    assert_eq!(output[0].usage.total_tokens, 0);
+    assert_eq!(output[1].text, reference_output[0].text);
+    assert_ne!(output[1].finish_reason, FinishReason::EmptyInput);
+    assert_eq!(output[2].text, "");
+    assert_eq!(output[2].finish_reason, FinishReason::EmptyInput);
+
+    //    All-empty batch: no forward pass is run
+    let generate_options = GenerateOptions {
+        num_return_sequences: Some(2),
+        ..Default::default()
+    };
+    let output = model.generate_indices(Some(&["", ""]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 4);
+    assert!(output.iter().all(|sequence| sequence.indices.is_empty()
+        && sequence.finish_reason == FinishReason::EmptyInput));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_stop_brackets() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 1,
+        stop_brackets: Some(('{', '}')),
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    //    Forces the generation of `{a{b}c}d{e}...`, the generation must stop after the outer brackets are closed
+    let prompt = "The JSON object is";
+    let prompt_length = model.get_tokenizer().tokenize(prompt).len();
+    let forced_tokens = model
+        .get_tokenizer()
+        .convert_tokens_to_ids(&["{", "a", "{", "b", "}", "c", "}", "d", "{", "e", "}"]);
+    let force_brackets = |_batch_id: i64, previous_token_ids: &Tensor| -> Vec<i64> {
+        let step = previous_token_ids.size()[0] as usize - prompt_length;
+        vec![forced_tokens[step % forced_tokens.len()]]
+    };
+    let generate_options = GenerateOptions {
+        prefix_allowed_tokens_fn: Some(&force_brackets),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&[prompt]), Some(generate_options))?;
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].text, "The JSON object is{a{b}c}");
+    assert_eq!(output[0].finish_reason, FinishReason::StopBrackets);
+    assert_eq!(output[0].usage.completion_tokens, 7);
+
+    //    Identical opening and closing characters, and beam search, are rejected
+    let generate_options = GenerateOptions {
+        stop_brackets: Some(('"', '"')),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&[prompt]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+    let generate_options = GenerateOptions {
+        num_beams: Some(3),
+        ..Default::default()
+    };
+    assert!(matches!(
+        model.generate(Some(&[prompt]), Some(generate_options)),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gpt2_generation_output_attention_mask() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = GPT2Generator::new(generate_config)?;
+
+    let input_context_1 = "The dog";
+    let input_context_2 = "The cat was sitting on the";
+    let length_1 = model.get_tokenizer().tokenize(input_context_1).len();
+    let length_2 = model.get_tokenizer().tokenize(input_context_2).len();
+
+    //    No attention mask is returned by default
+    let output = model.generate(Some(&[input_context_1, input_context_2]), None)?;
+    assert!(output
+        .iter()
+        .all(|sequence| sequence.attention_mask.is_none()));
+
+    //    Decoder-only models pad the prompts on the left
+    let generate_options = GenerateOptions {
+        output_attention_mask: true,
+        ..Default::default()
+    };
+    let output = model.generate_indices(
+        Some(&[input_context_1, input_context_2]),
+        Some(generate_options),
+    )?;
+
+    assert_eq!(output.len(), 2);
+    let mut expected_mask_1 = vec![0; length_2 - length_1];
+    expected_mask_1.extend(vec![1; length_1]);
+    assert_eq!(output[0].attention_mask.as_ref().unwrap(), &expected_mask_1);
+    assert_eq!(
+        output[1].attention_mask.as_ref().unwrap(),
+        &vec![1; length_2]
+    );
 
     Ok(())
 }
@@ -3,6 +3,7 @@ use rust_bert::marian::{
     MarianTargetLanguages, MarianVocabResources,
 };
 use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::sentence_splitter::PunctuationSentenceSplitter;
 use rust_bert::pipelines::translation::{
     Language, TranslationConfig, TranslationModel, TranslationModelBuilder,
 };
@@ -48,6 +49,40 @@ fn test_translation() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_translation_max_output_chars() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let mut translation_config = TranslationConfig::new(
+        ModelType::Marian,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        Some(merges_resource),
+        source_languages,
+        target_languages,
+        Device::cuda_if_available(),
+    );
+    translation_config.max_output_chars = Some(20);
+    let model = TranslationModel::new(translation_config)?;
+
+    let input_context_1 = "The quick brown fox jumps over the lazy dog";
+    let input_context_2 = "The dog did not wake up";
+
+    //    The translations are cut at the last whitespace before the limit
+    let outputs = model.translate(&[input_context_1, input_context_2], None, Language::French)?;
+    assert_eq!(outputs, [" Le rapide renard", " Le chien ne s'est"]);
+
+    Ok(())
+}
+
 #[test]
 // #[cfg_attr(not(feature = "all-tests"), ignore)]
 fn test_translation_builder() -> anyhow::Result<()> {
@@ -72,3 +107,261 @@ fn test_translation_builder() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_with_alignments() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let mut translation_config = TranslationConfig::new(
+        ModelType::Marian,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        Some(merges_resource),
+        source_languages,
+        target_languages,
+        Device::cuda_if_available(),
+    );
+    translation_config.output_attentions = true;
+    let model = TranslationModel::new(translation_config)?;
+
+    let input_context = "The dog did not wake up";
+
+    let outputs = model.translate_with_alignments(&[input_context], None, Language::French)?;
+
+    assert_eq!(outputs.len(), 1);
+    let (translation, alignments) = &outputs[0];
+    assert_eq!(translation, " Le chien ne s'est pas réveillé");
+    // The texts are encoded and decoded as for the translation without alignments
+    let translations = model.translate(&[input_context], None, Language::French)?;
+    assert_eq!(translation, &translations[0]);
+
+    // Every target word is aligned to at least one source word
+    let num_source_words = input_context.split_whitespace().count();
+    let num_target_words = translation.split_whitespace().count();
+    for target_word_index in 0..num_target_words {
+        assert!(alignments
+            .iter()
+            .any(|(_, aligned_target_word_index)| *aligned_target_word_index == target_word_index));
+    }
+    assert!(alignments
+        .iter()
+        .all(
+            |(source_word_index, target_word_index)| *source_word_index < num_source_words
+                && *target_word_index < num_target_words
+        ));
+
+    Ok(())
+}
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_topn() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let translation_config = TranslationConfig::new(
+        ModelType::Marian,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        Some(merges_resource),
+        source_languages,
+        target_languages,
+        Device::cuda_if_available(),
+    );
+    let model = TranslationModel::new(translation_config)?;
+
+    let input = [
+        "The quick brown fox jumps over the lazy dog",
+        "",
+        "The dog did not wake up",
+    ];
+
+    let outputs = model.translate_topn(&input, 4, None, Language::French)?;
+
+    assert_eq!(outputs.len(), 3);
+    assert_eq!(outputs[0].len(), 4);
+    assert!(outputs[1].is_empty());
+    assert_eq!(outputs[2].len(), 4);
+    for candidates in outputs.iter().filter(|candidates| !candidates.is_empty()) {
+        assert!(candidates.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+    assert_eq!(outputs[2][0].0, " Le chien ne s'est pas réveillé");
+
+    Ok(())
+}
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_stream() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let translation_config = TranslationConfig::new(
+        ModelType::Marian,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        Some(merges_resource),
+        source_languages,
+        target_languages,
+        Device::cuda_if_available(),
+    );
+    let model = TranslationModel::new(translation_config)?;
+
+    //    Build a 1000 lines input, with an invalid UTF-8 line
+    let sentences = [
+        "The quick brown fox jumps over the lazy dog",
+        "The dog did not wake up",
+        "",
+    ];
+    let invalid_line_index = 500;
+    let mut input = Vec::new();
+    for line_index in 0..1000 {
+        if line_index == invalid_line_index {
+            input.extend_from_slice(&[0xff, 0xfe]);
+        } else {
+            input.extend_from_slice(sentences[line_index % sentences.len()].as_bytes());
+        }
+        input.push(b'\n');
+    }
+    let references = model.translate(&sentences, None, Language::French)?;
+
+    let mut output = Vec::new();
+    let line_errors = model.translate_stream(
+        input.as_slice(),
+        &mut output,
+        64,
+        None,
+        Language::French,
+        true,
+    )?;
+
+    assert_eq!(line_errors.len(), 1);
+    assert_eq!(line_errors[0].0, invalid_line_index);
+    let output = String::from_utf8(output)?;
+    let translations = output.lines().collect::<Vec<&str>>();
+    assert_eq!(translations.len(), 1000);
+    for (line_index, translation) in translations.iter().enumerate() {
+        if line_index == invalid_line_index {
+            assert!(translation.is_empty());
+        } else {
+            assert_eq!(*translation, references[line_index % sentences.len()]);
+        }
+    }
+
+    //    Aborts the stream on invalid lines if errors are not skipped
+    let mut output = Vec::new();
+    assert!(model
+        .translate_stream(
+            input.as_slice(),
+            &mut output,
+            64,
+            None,
+            Language::French,
+            false
+        )
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+// #[cfg_attr(not(feature = "all-tests"), ignore)]
+fn test_translation_sentences() -> anyhow::Result<()> {
+    //    Set-up translation model
+    let model_resource = RemoteResource::from_pretrained(MarianModelResources::ENGLISH2ROMANCE);
+    let config_resource = RemoteResource::from_pretrained(MarianConfigResources::ENGLISH2ROMANCE);
+    let vocab_resource = RemoteResource::from_pretrained(MarianVocabResources::ENGLISH2ROMANCE);
+    let merges_resource = RemoteResource::from_pretrained(MarianSpmResources::ENGLISH2ROMANCE);
+
+    let source_languages = MarianSourceLanguages::ENGLISH2ROMANCE;
+    let target_languages = MarianTargetLanguages::ENGLISH2ROMANCE;
+
+    let translation_config = TranslationConfig::new(
+        ModelType::Marian,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        Some(merges_resource),
+        source_languages,
+        target_languages,
+        Device::cuda_if_available(),
+    );
+    let model = TranslationModel::new(translation_config)?;
+
+    let sentences = [
+        "The quick brown fox jumps over the lazy dog.",
+        "The dog did not wake up.",
+        "The cat was sleeping on the sofa.",
+    ];
+    let paragraph = format!("{} {}\n\n{}", sentences[0], sentences[1], sentences[2]);
+    let sentence_splitter = PunctuationSentenceSplitter::for_language(Language::English);
+
+    let outputs = model.translate_sentences(
+        &[paragraph.as_str(), ""],
+        None,
+        Language::French,
+        &sentence_splitter,
+    )?;
+    let references = model.translate(&sentences, None, Language::French)?;
+
+    //    All sentences are translated and rejoined with the spacing of the source
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(
+        outputs[0],
+        format!(
+            "{} {}\n\n{}",
+            references[0].trim(),
+            references[1].trim(),
+            references[2].trim()
+        )
+    );
+    assert!(outputs[1].is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn translation_language_iso_codes() -> anyhow::Result<()> {
+    for code in ["en", "fr", "de", "zh", "ast", "ceb"] {
+        let language = Language::from_iso639(code)?;
+        assert_eq!(language.get_iso_639_1_code(), code);
+    }
+    assert_eq!(Language::from_iso639("EN")?, Language::English);
+
+    //    ISO 639-3 codes are accepted and converted to their ISO 639-1 equivalent
+    let language = Language::from_iso639("fra")?;
+    assert_eq!(language, Language::French);
+    assert_eq!(language.get_iso_639_1_code(), "fr");
+    assert_eq!(
+        Language::from_iso639(language.get_iso_639_3_code())?,
+        language
+    );
+
+    assert!(Language::from_iso639("xx").is_err());
+    assert!(Language::from_iso639("").is_err());
+
+    Ok(())
+}
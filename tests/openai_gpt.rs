@@ -6,7 +6,7 @@ use rust_bert::pipelines::common::ModelType;
 use rust_bert::pipelines::generation_utils::{Cache, LMHeadModel};
 use rust_bert::pipelines::text_generation::{TextGenerationConfig, TextGenerationModel};
 use rust_bert::resources::{RemoteResource, ResourceProvider};
-use rust_bert::Config;
+use rust_bert::{Config, RustBertError};
 use rust_tokenizers::tokenizer::{OpenAiGptTokenizer, Tokenizer, TruncationStrategy};
 use tch::{nn, Device, Tensor};
 
@@ -134,7 +134,7 @@ fn openai_gpt_generation_greedy() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "It was an intense machine dialogue. ";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 1);
     assert_eq!(output[0], "it was an intense machine dialogue. \n \" i\'m sorry, but we have to go now! the police are on their way and they\'re going after you - or at least that\'s what my");
@@ -142,6 +142,46 @@ fn openai_gpt_generation_greedy() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn openai_gpt_generation_unsupported_kv_cache_window() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = Box::new(RemoteResource::from_pretrained(
+        OpenAiGptConfigResources::GPT,
+    ));
+    let vocab_resource = Box::new(RemoteResource::from_pretrained(
+        OpenAiGptVocabResources::GPT,
+    ));
+    let merges_resource = Box::new(RemoteResource::from_pretrained(
+        OpenAiGptMergesResources::GPT,
+    ));
+    let model_resource = Box::new(RemoteResource::from_pretrained(
+        OpenAiGptModelResources::GPT,
+    ));
+
+    //    Set-up model
+    let generate_config = TextGenerationConfig {
+        model_type: ModelType::OpenAiGpt,
+        model_resource,
+        config_resource,
+        vocab_resource,
+        merges_resource: Some(merges_resource),
+        max_length: Some(20),
+        do_sample: false,
+        kv_cache_window: Some(8),
+        ..Default::default()
+    };
+    let model = TextGenerationModel::new(generate_config)?;
+
+    //    The cache of the model does not support windowing: an error is returned before generating
+    let input_context = "It was an intense machine dialogue. ";
+    assert!(matches!(
+        model.generate(&[input_context], None),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn openai_gpt_generation_beam_search() -> anyhow::Result<()> {
     //    Resources paths
@@ -176,7 +216,7 @@ fn openai_gpt_generation_beam_search() -> anyhow::Result<()> {
     let model = TextGenerationModel::new(generate_config)?;
 
     let input_context = "The dog is";
-    let output = model.generate(&[input_context], None);
+    let output = model.generate(&[input_context], None)?;
 
     assert_eq!(output.len(), 3);
     assert_eq!(
@@ -230,7 +270,7 @@ fn openai_gpt_generation_beam_search_multiple_prompts_without_padding() -> anyho
 
     let input_context_1 = "The dog is";
     let input_context_2 = "The cat";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
 
@@ -298,7 +338,7 @@ fn openai_gpt_generation_beam_search_multiple_prompts_with_padding() -> anyhow::
 
     let input_context_1 = "The dog is";
     let input_context_2 = "The cat was in";
-    let output = model.generate(&[input_context_1, input_context_2], None);
+    let output = model.generate(&[input_context_1, input_context_2], None)?;
 
     assert_eq!(output.len(), 6);
     //    Left padding impacts the generated sentences output
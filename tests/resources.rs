@@ -0,0 +1,27 @@
+use rust_bert::resources::{FallbackResource, RemoteResource, ResourceProvider};
+use std::io::Write;
+
+#[test]
+fn fallback_resource() -> anyhow::Result<()> {
+    //    Nothing listens on the discard port of the loopback interface: the download fails without network access
+    let bogus_config_resource =
+        RemoteResource::new("http://127.0.0.1:9/config.json", "fallback-resource-test");
+    //    Local mirror of the configuration, the path of local files is returned as is
+    let mut mirror_config_file = tempfile::NamedTempFile::new()?;
+    mirror_config_file.write_all(b"{}")?;
+    let mirror_config_path = mirror_config_file.path().to_path_buf();
+    let mirror_config_resource = RemoteResource::new(
+        mirror_config_path.to_str().unwrap(),
+        "fallback-resource-test-mirror",
+    );
+
+    let config_resource =
+        FallbackResource::new(vec![bogus_config_resource.clone(), mirror_config_resource]);
+    assert_eq!(config_resource.get_local_path()?, mirror_config_path);
+
+    let bogus_config_resource = FallbackResource::new(vec![bogus_config_resource]);
+    assert!(bogus_config_resource.get_local_path().is_err());
+    assert!(FallbackResource::new(vec![]).get_local_path().is_err());
+
+    Ok(())
+}
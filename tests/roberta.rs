@@ -1,4 +1,4 @@
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{ModelType, TokenizerOption};
 use rust_bert::pipelines::ner::NERModel;
 use rust_bert::pipelines::question_answering::{
     QaInput, QuestionAnsweringConfig, QuestionAnsweringModel,
@@ -314,6 +314,71 @@ fn roberta_for_token_classification() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn roberta_tokenizer_add_prefix_space() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(RobertaVocabResources::DISTILROBERTA_BASE);
+    let merges_resource =
+        RemoteResource::from_pretrained(RobertaMergesResources::DISTILROBERTA_BASE);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource.get_local_path()?;
+
+    //    Set-up tokenizers
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Roberta,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        false,
+    )?;
+    let prefix_space_tokenizer = TokenizerOption::from_file(
+        ModelType::Roberta,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        true,
+    )?;
+
+    //    Single inputs
+    assert_eq!(tokenizer.tokenize("Hello"), vec!["Hello"]);
+    assert_eq!(
+        prefix_space_tokenizer.tokenize("Hello"),
+        vec!["\u{0120}Hello"]
+    );
+    assert_eq!(
+        prefix_space_tokenizer.tokenize("Hello"),
+        tokenizer.tokenize(" Hello")
+    );
+
+    //    Paired inputs: the prefix space is added to both sequences
+    let hello_id = tokenizer.convert_tokens_to_ids(&["Hello"])[0];
+    let prefixed_hello_id = tokenizer.convert_tokens_to_ids(&["\u{0120}Hello"])[0];
+    let encoded = tokenizer.encode_pair(
+        "Hello",
+        Some("Hello"),
+        128,
+        &TruncationStrategy::LongestFirst,
+        0,
+    );
+    let prefix_space_encoded = prefix_space_tokenizer.encode_pair(
+        "Hello",
+        Some("Hello"),
+        128,
+        &TruncationStrategy::LongestFirst,
+        0,
+    );
+    assert_eq!(encoded.token_ids.len(), 6);
+    assert_eq!(encoded.token_ids[1], hello_id);
+    assert_eq!(encoded.token_ids[4], hello_id);
+    assert_eq!(prefix_space_encoded.token_ids.len(), 6);
+    assert_eq!(prefix_space_encoded.token_ids[1], prefixed_hello_id);
+    assert_eq!(prefix_space_encoded.token_ids[4], prefixed_hello_id);
+
+    Ok(())
+}
+
 #[test]
 fn roberta_question_answering() -> anyhow::Result<()> {
     //    Set-up question answering model
@@ -400,3 +465,72 @@ fn xlm_roberta_german_ner() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn roberta_tokenizer_word_ids() -> anyhow::Result<()> {
+    //    Resources paths
+    let vocab_resource = RemoteResource::from_pretrained(RobertaVocabResources::DISTILROBERTA_BASE);
+    let merges_resource =
+        RemoteResource::from_pretrained(RobertaMergesResources::DISTILROBERTA_BASE);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let merges_path = merges_resource.get_local_path()?;
+
+    //    Set-up tokenizer
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::Roberta,
+        vocab_path.to_str().unwrap(),
+        Some(merges_path.to_str().unwrap()),
+        false,
+        None,
+        false,
+    )?;
+
+    let words = ["Brooklynites", "love", "Hugging", "Face", "!"];
+    let output =
+        tokenizer.encode_with_word_ids(&words, true, 128, &TruncationStrategy::LongestFirst, 0)?;
+
+    //    The encoding matches the encoding of the words joined by spaces
+    assert_eq!(
+        output.tokenized_input.token_ids,
+        tokenizer
+            .encode_pair(
+                &words.join(" "),
+                None,
+                128,
+                &TruncationStrategy::LongestFirst,
+                0
+            )
+            .token_ids
+    );
+    assert_eq!(
+        output.word_ids.len(),
+        output.tokenized_input.token_ids.len()
+    );
+    assert!(output.tokenized_input.token_ids.len() > words.len() + 2);
+    assert_eq!(output.word_ids[0], None);
+    assert_eq!(*output.word_ids.last().unwrap(), None);
+
+    //    Sub-tokens are aligned back to the original words
+    let tokens = tokenizer.convert_ids_to_tokens(&output.tokenized_input.token_ids);
+    for (word_index, word) in words.iter().enumerate() {
+        let word_tokens = tokens
+            .iter()
+            .zip(output.word_ids.iter())
+            .filter(|(_, word_id)| **word_id == Some(word_index))
+            .map(|(token, _)| token.trim_start_matches('\u{0120}'))
+            .collect::<String>();
+        assert_eq!(word_tokens, *word);
+    }
+
+    //    Whitespace-split inputs result in the same word alignment
+    let split_output = tokenizer.encode_with_word_ids(
+        &["Brooklynites love", "Hugging Face !"],
+        false,
+        128,
+        &TruncationStrategy::LongestFirst,
+        0,
+    )?;
+    assert_eq!(split_output.word_ids, output.word_ids);
+
+    Ok(())
+}
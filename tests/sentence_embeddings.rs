@@ -1,9 +1,12 @@
 use rust_bert::pipelines::keywords_extraction::{
     KeywordExtractionConfig, KeywordExtractionModel, KeywordScorerType,
 };
+use rust_bert::pipelines::sentence_embeddings::layers::{Pooling, PoolingConfig};
 use rust_bert::pipelines::sentence_embeddings::{
-    SentenceEmbeddingsBuilder, SentenceEmbeddingsConfig, SentenceEmbeddingsModelType,
+    LayerSelection, SentenceEmbeddingsBuilder, SentenceEmbeddingsConfig,
+    SentenceEmbeddingsModelType,
 };
+use tch::Tensor;
 
 #[test]
 fn sbert_distilbert() -> anyhow::Result<()> {
@@ -83,6 +86,300 @@ fn sbert_bert_small() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn sbert_bert_small_layer_selection() -> anyhow::Result<()> {
+    let sentences = ["this is an example sentence", "each sentence is converted"];
+    let encode_layer = |layer: LayerSelection| -> anyhow::Result<Vec<Vec<f32>>> {
+        let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL6V2)
+            .with_layer(layer)
+            .create_model()?;
+        Ok(model.encode(&sentences)?)
+    };
+
+    let last_embeddings = encode_layer(LayerSelection::Last)?;
+    let first_layer_embeddings = encode_layer(LayerSelection::Nth(0))?;
+    let last_layer_embeddings = encode_layer(LayerSelection::Nth(5))?;
+    let concat_embeddings = encode_layer(LayerSelection::Concat(vec![0, 5]))?;
+
+    let max_difference = |left: &[f32], right: &[f32]| {
+        left.iter()
+            .zip(right.iter())
+            .map(|(left, right)| (left - right).abs())
+            .fold(0f32, f32::max)
+    };
+    for sentence_index in 0..sentences.len() {
+        assert!(
+            max_difference(
+                &first_layer_embeddings[sentence_index],
+                &last_embeddings[sentence_index]
+            ) > 1e-2
+        );
+        assert!(
+            max_difference(
+                &last_layer_embeddings[sentence_index],
+                &last_embeddings[sentence_index]
+            ) < 1e-5
+        );
+        assert_eq!(concat_embeddings[sentence_index].len(), 2 * 384);
+    }
+    assert!(encode_layer(LayerSelection::Nth(6)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_encode_into() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let sentences = ["this is an example sentence", "each sentence is converted"];
+    let embeddings = model.encode(&sentences)?;
+
+    //    Embeddings are written contiguously in the buffer, the remaining values are untouched
+    let mut buffer = vec![-1f32; 2 * 384 + 1];
+    let num_values = model.encode_into(&sentences, &mut buffer)?;
+    assert_eq!(num_values, 2 * 384);
+    for (sentence_index, embedding) in embeddings.iter().enumerate() {
+        let written_embedding = &buffer[sentence_index * 384..(sentence_index + 1) * 384];
+        for (written_value, value) in written_embedding.iter().zip(embedding.iter()) {
+            assert!((written_value - value).abs() < 1e-6);
+        }
+    }
+    assert_eq!(buffer[2 * 384], -1f32);
+
+    //    A buffer too small for the embeddings is rejected
+    let mut small_buffer = vec![0f32; 384];
+    assert!(model.encode_into(&sentences, &mut small_buffer).is_err());
+    assert!(small_buffer.iter().all(|value| *value == 0f32));
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_pairwise_similarity_matrix() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let sentences = [
+        "this is an example sentence",
+        "this is a sample sentence",
+        "the weather is sunny today",
+    ];
+    let similarities = model.pairwise_similarity_matrix(&sentences)?;
+
+    assert_eq!(similarities.len(), 3);
+    for (i, row) in similarities.iter().enumerate() {
+        assert_eq!(row.len(), 3);
+        assert!((row[i] - 1.0).abs() < 1e-4);
+        for (j, similarity) in row.iter().enumerate() {
+            assert_eq!(*similarity, similarities[j][i]);
+        }
+    }
+    assert!(similarities[0][1] > similarities[0][2]);
+    assert!(model.pairwise_similarity_matrix::<&str>(&[])?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_cluster() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let sentences = [
+        "The cat sits on the mat.",
+        "The stock market fell sharply today.",
+        "A cat is sitting on the mat.",
+    ];
+    let clusters = model.cluster(&sentences, 0.3)?;
+
+    //    Near-duplicate sentences land in the same cluster
+    assert_eq!(clusters, vec![vec![0, 2], vec![1]]);
+
+    //    A zero threshold only groups identical sentences
+    let clusters = model.cluster(&sentences, 0.0)?;
+    assert_eq!(clusters, vec![vec![0], vec![1], vec![2]]);
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_encode_chunks() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let text = "The quick brown fox jumps over the lazy dog. The dog barks at the fox, \
+    which runs back into the forest. Later that day, the fox returns to the river bank.";
+    let num_chars = text.chars().count();
+
+    //    The chunks cover the whole document and overlap with their neighbours
+    let chunks = model.encode_chunks(text, 8, 3)?;
+    assert!(chunks.len() > 2);
+    assert_eq!(chunks.first().unwrap().0.start, 0);
+    assert_eq!(chunks.last().unwrap().0.end, num_chars);
+    for window in chunks.windows(2) {
+        let (previous_span, current_span) = (&window[0].0, &window[1].0);
+        assert!(current_span.start > previous_span.start);
+        assert!(current_span.start < previous_span.end);
+        assert!(current_span.end > previous_span.end);
+    }
+    assert!(chunks.iter().all(|(_, embedding)| embedding.len() == 384));
+
+    //    A document shorter than a chunk is embedded as a whole
+    let chunks = model.encode_chunks(text, 256, 32)?;
+    let embedding = model.encode(&[text])?.remove(0);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].0, 0..num_chars);
+    for (chunk_value, value) in chunks[0].1.iter().zip(embedding.iter()) {
+        assert!((chunk_value - value).abs() < 1e-5);
+    }
+
+    //    The overlap must be smaller than the chunks
+    assert!(model.encode_chunks(text, 8, 8).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_word_embeddings() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    //    "unaffordable" and "housing," are split into several sub-words
+    let text = "The unaffordable housing, crisis";
+    let word_embeddings = model.encode_word_embeddings(text)?;
+
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    assert_eq!(word_embeddings.len(), words.len());
+    for ((word, embedding), expected_word) in word_embeddings.iter().zip(words) {
+        assert_eq!(word, expected_word);
+        assert_eq!(embedding.len(), 384);
+    }
+    assert!(model.encode_word_embeddings("")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_eps() -> anyhow::Result<()> {
+    let mut model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let sentence = ["this is an example sentence"];
+    let norm = |embedding: &[f32]| {
+        embedding
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt()
+    };
+    let embedding = model.encode(&sentence)?.remove(0);
+    assert!((norm(&embedding) - 1.0).abs() < 1e-5);
+
+    //    The normalization divides by its `eps` when it exceeds the norm of the mean pooled embedding
+    let pooled_norm = norm(&model.encode_multi_pool(&sentence)?.mean[0]);
+    model.set_normalization_eps(1.01 * pooled_norm as f64);
+    let clamped_embedding = model.encode(&sentence)?.remove(0);
+    assert!((norm(&clamped_embedding) - 1.0 / 1.01).abs() < 1e-4);
+    for (clamped_value, value) in clamped_embedding.iter().zip(embedding.iter()) {
+        assert!((clamped_value * 1.01 - value).abs() < 1e-4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_query_passage_prefixes() -> anyhow::Result<()> {
+    let mut model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let queries = ["how much protein should a female eat"];
+    let passages = ["The recommended daily protein intake for women is 46 grams."];
+    let differs = |a: &[f32], b: &[f32]| a.iter().zip(b).any(|(a, b)| (a - b).abs() > 1e-4);
+
+    //    Without prefixes, queries and passages are encoded as the other inputs
+    let unprefixed_queries = model.encode(&queries)?;
+    let unprefixed_passages = model.encode(&passages)?;
+    assert!(!differs(
+        &model.encode_queries(&queries)?[0],
+        &unprefixed_queries[0]
+    ));
+    assert!(!differs(
+        &model.encode_passages(&passages)?[0],
+        &unprefixed_passages[0]
+    ));
+
+    model.set_query_prefix(Some("query: ".to_string()));
+    model.set_passage_prefix(Some("passage: ".to_string()));
+    let prefixed_queries = model.encode_queries(&queries)?;
+    let prefixed_passages = model.encode_passages(&passages)?;
+    assert!(differs(&prefixed_queries[0], &unprefixed_queries[0]));
+    assert!(differs(&prefixed_passages[0], &unprefixed_passages[0]));
+    assert!(!differs(
+        &prefixed_queries[0],
+        &model.encode(&["query: how much protein should a female eat"])?[0]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn sbert_bert_small_multi_pool() -> anyhow::Result<()> {
+    let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL12V2)
+        .create_model()?;
+
+    let sentences = ["this is an example sentence", "each sentence is converted"];
+    let pooled = model.encode_multi_pool(&sentences)?;
+
+    for poolings in [&pooled.mean, &pooled.max, &pooled.cls] {
+        assert_eq!(poolings.len(), 2);
+        assert!(poolings.iter().all(|embedding| embedding.len() == 384));
+    }
+    let differs = |a: &[f32], b: &[f32]| a.iter().zip(b).any(|(a, b)| (a - b).abs() > 1e-4);
+    for i in 0..sentences.len() {
+        assert!(differs(&pooled.mean[i], &pooled.max[i]));
+        assert!(differs(&pooled.mean[i], &pooled.cls[i]));
+        assert!(differs(&pooled.max[i], &pooled.cls[i]));
+        //    The max pooling bounds the mean pooling in each dimension
+        assert!(pooled.mean[i]
+            .iter()
+            .zip(&pooled.max[i])
+            .all(|(mean, max)| mean <= max));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sbert_max_pooling() -> anyhow::Result<()> {
+    let pooling = |cls: bool, max: bool, mean: bool| {
+        Pooling::new(PoolingConfig {
+            word_embedding_dimension: 2,
+            pooling_mode_cls_token: cls,
+            pooling_mode_max_tokens: max,
+            pooling_mode_mean_tokens: mean,
+            pooling_mode_mean_sqrt_len_tokens: false,
+        })
+    };
+    //    Batch of 2 inputs of 3 tokens, the last token of the second input being padding
+    let token_embeddings =
+        || Tensor::of_slice(&[1f32, 6., 3., 2., 5., 4., 2., 1., 0., 3., 9., 9.]).view((2, 3, 2));
+    let attention_mask = Tensor::of_slice(&[1i64, 1, 1, 1, 1, 0]).view((2, 3));
+
+    let max_pooled = pooling(false, true, false).forward(token_embeddings(), &attention_mask);
+    assert_eq!(max_pooled.size(), [2, 2]);
+    assert_eq!(
+        Vec::<Vec<f32>>::from(max_pooled),
+        [vec![5., 6.], vec![2., 3.]]
+    );
+
+    //    The max pooling is concatenated with the other poolings along the embedding dimension
+    let pooled = pooling(true, true, true).forward(token_embeddings(), &attention_mask);
+    assert_eq!(pooled.size(), [2, 6]);
+
+    Ok(())
+}
+
 #[test]
 fn sbert_distilroberta() -> anyhow::Result<()> {
     let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllDistilrobertaV1)
@@ -356,3 +653,40 @@ fn keyword_extraction_cosine_similarity_n_grams() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn sbert_pooling_dropout() -> anyhow::Result<()> {
+    let mut model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL6V2)
+        .create_model()?;
+    let sentences = ["This is an example sentence", "Each sentence is converted"];
+    let reference_embeddings = model.encode(&sentences)?;
+
+    model.set_pooling_dropout(0.5);
+
+    //    Evaluation mode embeddings are stable and not affected by the pooling dropout
+    let embeddings = model.encode(&sentences)?;
+    for (embedding, reference_embedding) in embeddings.iter().zip(reference_embeddings.iter()) {
+        for (value, reference_value) in embedding.iter().zip(reference_embedding.iter()) {
+            assert!((value - reference_value).abs() < 1e-6);
+        }
+    }
+
+    //    Training mode embeddings vary between passes
+    model.train();
+    assert!(model.is_training());
+    let first_embeddings = model.encode(&sentences)?;
+    let second_embeddings = model.encode(&sentences)?;
+    assert_ne!(first_embeddings, second_embeddings);
+    assert_ne!(first_embeddings, reference_embeddings);
+
+    model.eval();
+    assert!(!model.is_training());
+    let embeddings = model.encode(&sentences)?;
+    for (embedding, reference_embedding) in embeddings.iter().zip(reference_embeddings.iter()) {
+        for (value, reference_value) in embedding.iter().zip(reference_embedding.iter()) {
+            assert!((value - reference_value).abs() < 1e-6);
+        }
+    }
+
+    Ok(())
+}
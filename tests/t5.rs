@@ -1,9 +1,19 @@
-use rust_bert::pipelines::common::ModelType;
+use rust_bert::pipelines::common::{ModelType, PaddingSide, TokenizerOption};
+use rust_bert::pipelines::generation_utils::{
+    GenerateConfig, GenerateOptions, LanguageGenerator, ScoreType,
+};
 use rust_bert::pipelines::summarization::{SummarizationConfig, SummarizationModel};
 use rust_bert::pipelines::translation::{Language, TranslationConfig, TranslationModel};
-use rust_bert::resources::RemoteResource;
-use rust_bert::t5::{T5ConfigResources, T5ModelResources, T5VocabResources};
-use tch::Device;
+use rust_bert::resources::{RemoteResource, ResourceProvider};
+use rust_bert::t5::{
+    get_block_diagonal_attention_mask, AttentionImplementation, T5Config, T5ConfigResources,
+    T5DeviceMap, T5ForConditionalGeneration, T5Generator, T5Model, T5ModelResources,
+    T5VocabResources,
+};
+use rust_bert::{Config, RustBertError};
+use rust_tokenizers::tokenizer::TruncationStrategy;
+use std::sync::Arc;
+use tch::{nn, no_grad, Device, Kind, Tensor};
 
 #[test]
 fn test_translation_t5() -> anyhow::Result<()> {
@@ -100,7 +110,7 @@ on K2-18b lasts 33 Earth days. According to The Guardian, astronomers were optim
 telescope — scheduled for launch in 2021 — and the European Space Agency's 2028 ARIEL program, could reveal more \
 about exoplanets like K2-18b."];
 
-    let output = model.summarize(&input);
+    let output = model.summarize(&input)?;
 
     assert_eq! (
     output[0],
@@ -111,3 +121,915 @@ about exoplanets like K2-18b."];
 
     Ok(())
 }
+
+#[test]
+fn t5_pipelines_shared_tokenizer() -> anyhow::Result<()> {
+    //    Set-up a tokenizer shared by the translation and summarization pipelines
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let tokenizer = Arc::new(TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?);
+
+    let translation_config = TranslationConfig::new(
+        ModelType::T5,
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL),
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL),
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL),
+        None,
+        [Language::English],
+        [Language::French],
+        Device::cuda_if_available(),
+    );
+    let translation_model =
+        TranslationModel::new_with_tokenizer(translation_config, tokenizer.clone())?;
+    let summarization_config = SummarizationConfig {
+        model_type: ModelType::T5,
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(32),
+        ..Default::default()
+    };
+    let summarization_model =
+        SummarizationModel::new_with_tokenizer(summarization_config, tokenizer.clone())?;
+    assert_eq!(Arc::strong_count(&tokenizer), 3);
+
+    //    Run models
+    let translation_output = translation_model.translate(
+        &["This sentence will be translated in multiple languages."],
+        Language::English,
+        Language::French,
+    )?;
+    let summarization_output = summarization_model.summarize(&[
+        "The presence of water vapour was confirmed in the atmosphere of K2-18b, a planet circling a star in the constellation Leo.",
+    ])?;
+
+    assert_eq!(
+        translation_output[0],
+        " Cette phrase sera traduite dans plusieurs langues."
+    );
+    assert!(!summarization_output[0].trim().is_empty());
+
+    //    A tokenizer not matching the model type of the configuration is rejected
+    assert!(matches!(
+        SummarizationModel::new_with_tokenizer(
+            SummarizationConfig {
+                model_type: ModelType::Bart,
+                ..Default::default()
+            },
+            tokenizer
+        ),
+        Err(RustBertError::InvalidConfigurationError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_summarization_t5_custom_prefix() -> anyhow::Result<()> {
+    let summarization_config = |prefix: Option<String>| SummarizationConfig {
+        model_type: ModelType::T5,
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        min_length: 0,
+        max_length: Some(64),
+        num_beams: 4,
+        prefix,
+        ..Default::default()
+    };
+    let default_model = SummarizationModel::new(summarization_config(None))?;
+    let custom_model = SummarizationModel::new(summarization_config(Some(
+        "translate English to German: ".to_string(),
+    )))?;
+
+    let input = ["This sentence will be translated in multiple languages."];
+
+    let default_output = default_model.summarize(&input)?;
+    let custom_output = custom_model.summarize(&input)?;
+
+    assert_ne!(default_output[0], custom_output[0]);
+    assert!(custom_output[0].contains("Sprachen"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generation_t5_special_tokens() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(16),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = T5Generator::new(generate_config)?;
+
+    let input = ["The <extra_id_0> walks in <extra_id_1> park."];
+
+    let output = model.generate(Some(&input), None)?;
+    assert!(!output[0].text.contains("<extra_id_0>"));
+
+    let generate_options = GenerateOptions {
+        skip_special_tokens: Some(false),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&input), Some(generate_options))?;
+    assert!(output[0].text.contains("<extra_id_0>"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generation_t5_rank_by_score_type() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 6,
+        num_return_sequences: 6,
+        length_penalty: 2.0,
+        early_stopping: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = T5Generator::new(generate_config)?;
+
+    let input = ["translate English to German: The house is wonderful and the garden is large."];
+
+    let normalized_output = model.generate(
+        Some(&input),
+        Some(GenerateOptions {
+            rank_by: Some(ScoreType::Normalized),
+            output_scores: true,
+            ..Default::default()
+        }),
+    )?;
+    let raw_output = model.generate(
+        Some(&input),
+        Some(GenerateOptions {
+            rank_by: Some(ScoreType::Raw),
+            output_scores: true,
+            ..Default::default()
+        }),
+    )?;
+
+    assert_eq!(normalized_output.len(), 6);
+    assert_eq!(raw_output.len(), 6);
+    for output in [&normalized_output, &raw_output] {
+        let scores = output
+            .iter()
+            .map(|generated| generated.score.unwrap())
+            .collect::<Vec<f64>>();
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    // The same candidates are returned, but a strong length penalty favours longer sequences
+    // when ranking by normalized score while the raw log-likelihood favours shorter ones
+    let mut normalized_texts = normalized_output
+        .iter()
+        .map(|generated| generated.text.as_str())
+        .collect::<Vec<&str>>();
+    let mut raw_texts = raw_output
+        .iter()
+        .map(|generated| generated.text.as_str())
+        .collect::<Vec<&str>>();
+    assert_ne!(normalized_texts, raw_texts);
+    normalized_texts.sort_unstable();
+    raw_texts.sort_unstable();
+    assert_eq!(normalized_texts, raw_texts);
+
+    Ok(())
+}
+
+#[test]
+fn test_generation_t5_pad_tokens() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 1,
+        skip_special_tokens: false,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = T5Generator::new(generate_config)?;
+
+    //    The decoder start token and the padding of the shorter output are padding tokens
+    let input = [
+        "translate English to German: The dog.",
+        "translate English to German: The dog did not wake up this morning because it was tired.",
+    ];
+    let indices_output = model.generate_indices(Some(&input), None)?;
+    assert!(indices_output[0].indices.contains(&0));
+
+    let output = model.generate(Some(&input), None)?;
+    assert_eq!(output.len(), 2);
+    for generated in output.iter() {
+        assert!(!generated.text.contains("<pad>"));
+    }
+    assert!(output[0].text.contains("</s>"));
+
+    //    The padding tokens can be kept for debugging
+    let generate_options = GenerateOptions {
+        keep_pad_tokens: Some(true),
+        ..Default::default()
+    };
+    let output = model.generate(Some(&input), Some(generate_options))?;
+    assert!(output[0].text.contains("<pad>"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generation_t5_continue_from_decoder_input_ids() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = T5Generator::new(generate_config)?;
+
+    let input = ["translate English to German: The dog did not wake up this morning."];
+
+    let full_output = model.generate_indices(Some(&input), None)?;
+    let full_indices = &full_output[0].indices;
+    assert!(full_indices.len() > 4);
+
+    // Resume the generation from the first tokens generated
+    let partial_indices = Tensor::of_slice(&full_indices[..4]).unsqueeze(0);
+    let generate_options = GenerateOptions {
+        decoder_input_ids: Some(&partial_indices),
+        ..Default::default()
+    };
+    let continued_output = model.generate_indices(Some(&input), Some(generate_options))?;
+
+    assert_eq!(&continued_output[0].indices, full_indices);
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_decode_leading_space() -> anyhow::Result<()> {
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+
+    //    The first token of the sentence starts with the word boundary marker
+    let token_ids = tokenizer.convert_tokens_to_ids(&tokenizer.tokenize("Hello world"));
+    assert!(tokenizer.convert_ids_to_tokens(&token_ids)[0].starts_with('\u{2581}'));
+
+    let with_leading_space = tokenizer.decode_with_leading_space(&token_ids, true, true, true);
+    let without_leading_space = tokenizer.decode_with_leading_space(&token_ids, true, true, false);
+    assert_eq!(with_leading_space, " Hello world");
+    assert_eq!(without_leading_space, "Hello world");
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_encoder_mean_pooled_embeddings() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL).get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let mut vs = nn::VarStore::new(device);
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+    let config = T5Config::from_file(config_path);
+    let t5_model = T5Model::new(vs.root(), &config);
+    vs.load(weights_path)?;
+
+    //    Define input
+    let input = [
+        "This is a sentence.",
+        "This is a longer sentence with more tokens.",
+    ];
+    let (input_ids, attention_mask) = tokenizer.encode_batch(
+        &input,
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        device,
+    );
+    let decoder_input_ids = Tensor::zeros(&[2, 1], (Kind::Int64, device));
+
+    //    Forward passes
+    let encoder_output = no_grad(|| t5_model.encode(&input_ids, Some(&attention_mask)));
+    let full_output = no_grad(|| {
+        t5_model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let embeddings = no_grad(|| t5_model.encode_mean_pooled(&input_ids, Some(&attention_mask)));
+
+    let full_encoder_output = full_output.encoder_hidden_state.unwrap();
+    assert_eq!(encoder_output.size(), full_encoder_output.size());
+    assert!(encoder_output.allclose(&full_encoder_output, 1e-5, 1e-6, false));
+
+    assert_eq!(embeddings.size(), &[2, config.d_model]);
+    //    Padding positions are excluded from the average
+    let num_tokens = i64::from(attention_mask.get(0).sum(Kind::Int64));
+    let expected_embedding = full_encoder_output
+        .get(0)
+        .slice(0, 0, num_tokens, 1)
+        .mean_dim([0].as_slice(), false, Kind::Float);
+    assert!(embeddings
+        .get(0)
+        .allclose(&expected_embedding, 1e-5, 1e-6, false));
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_efficient_attention() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL).get_local_path()?;
+
+    //    Set-up models
+    let device = Device::Cpu;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+    let mut eager_config = T5Config::from_file(&config_path);
+    eager_config.attention_implementation = Some(AttentionImplementation::Eager);
+    let mut eager_vs = nn::VarStore::new(device);
+    let eager_model = T5ForConditionalGeneration::new(eager_vs.root(), &eager_config);
+    eager_vs.load(&weights_path)?;
+
+    let mut efficient_config = T5Config::from_file(&config_path);
+    efficient_config.attention_implementation = Some(AttentionImplementation::Efficient);
+    let mut efficient_vs = nn::VarStore::new(device);
+    let efficient_model = T5ForConditionalGeneration::new(efficient_vs.root(), &efficient_config);
+    efficient_vs.load(&weights_path)?;
+
+    //    Define input, longer than a chunk of queries
+    let input = [
+        "This is a sentence. ".repeat(40),
+        "This is a shorter sentence.".to_string(),
+    ];
+    let (input_ids, attention_mask) = tokenizer.encode_batch(
+        &input,
+        512,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        device,
+    );
+    assert!(input_ids.size()[1] > 128);
+    let decoder_input_ids = input_ids.slice(1, 0, 150, 1);
+
+    //    Forward passes
+    let eager_output = no_grad(|| {
+        eager_model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let efficient_output = no_grad(|| {
+        efficient_model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+
+    assert_eq!(
+        eager_output.decoder_output.size(),
+        efficient_output.decoder_output.size()
+    );
+    assert!(eager_output.decoder_output.allclose(
+        &efficient_output.decoder_output,
+        1e-4,
+        1e-4,
+        false
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_loss() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL).get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+    let config = T5Config::from_file(&config_path);
+    let mut vs = nn::VarStore::new(device);
+    let model = T5ForConditionalGeneration::new(vs.root(), &config);
+    vs.load(&weights_path)?;
+
+    //    Define input and labels, with a copy of the labels padded with ignored positions
+    let (input_ids, attention_mask) = tokenizer.encode_batch(
+        &["translate English to German: The house is wonderful."],
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        device,
+    );
+    let (labels, _) = tokenizer.encode_batch(
+        &["Das Haus ist wunderbar."],
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        device,
+    );
+    let padded_labels = Tensor::cat(
+        &[
+            labels.copy(),
+            Tensor::full(&[1, 5], -100, (Kind::Int64, device)),
+        ],
+        1,
+    );
+
+    //    Forward passes
+    let loss = no_grad(|| model.forward_loss(&input_ids, Some(&attention_mask), &labels, false));
+    let padded_loss =
+        no_grad(|| model.forward_loss(&input_ids, Some(&attention_mask), &padded_labels, false));
+
+    //    Ignored positions do not contribute to the loss
+    assert_eq!(loss.size(), Vec::<i64>::new());
+    assert!(loss.double_value(&[]) > 0.0);
+    assert!((loss.double_value(&[]) - padded_loss.double_value(&[])).abs() < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_block_diagonal_attention_mask() -> anyhow::Result<()> {
+    //    Mask values: no attention across documents or to padding positions
+    let attention_mask = get_block_diagonal_attention_mask(&[vec![2, 3], vec![4]], 6, Device::Cpu)?;
+    assert_eq!(attention_mask.size(), [2, 6, 6]);
+    let expected_first_document = [1, 1, 0, 0, 0, 0];
+    let expected_second_document = [0, 0, 1, 1, 1, 0];
+    for query_position in 0..6 {
+        let expected_row = if query_position < 2 {
+            expected_first_document
+        } else if query_position < 5 {
+            expected_second_document
+        } else {
+            [0; 6]
+        };
+        assert_eq!(
+            Vec::<i64>::from(attention_mask.get(0).get(query_position)),
+            expected_row
+        );
+    }
+    assert_eq!(
+        Vec::<i64>::from(attention_mask.get(1).get(0)),
+        [1, 1, 1, 1, 0, 0]
+    );
+    assert!(get_block_diagonal_attention_mask(&[vec![4, 3]], 6, Device::Cpu).is_err());
+
+    //    Resources paths
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL).get_local_path()?;
+
+    //    Set-up model
+    let device = Device::Cpu;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+    let config = T5Config::from_file(&config_path);
+    let mut vs = nn::VarStore::new(device);
+    let model = T5ForConditionalGeneration::new(vs.root(), &config);
+    vs.load(&weights_path)?;
+
+    //    Pack two documents in a single sequence
+    let encode = |text: &str| {
+        tokenizer
+            .encode_batch(
+                &[text],
+                128,
+                &TruncationStrategy::LongestFirst,
+                PaddingSide::Right,
+                device,
+            )
+            .0
+    };
+    let first_document = encode("The house is wonderful.");
+    let second_document = encode("The cat sat on the mat and looked outside.");
+    let first_length = first_document.size()[1];
+    let second_length = second_document.size()[1];
+    let packed_input = Tensor::cat(&[&first_document, &second_document], 1);
+    let attention_mask = get_block_diagonal_attention_mask(
+        &[vec![first_length, second_length]],
+        first_length + second_length,
+        device,
+    )?;
+
+    //    Packed documents are encoded as if they were encoded separately
+    let packed_output = no_grad(|| model.encode(&packed_input, Some(&attention_mask)));
+    let first_output = no_grad(|| model.encode(&first_document, None));
+    let second_output = no_grad(|| model.encode(&second_document, None));
+    assert!(packed_output
+        .slice(1, 0, first_length, 1)
+        .allclose(&first_output, 1e-4, 1e-4, false));
+    assert!(packed_output
+        .slice(1, first_length, first_length + second_length, 1)
+        .allclose(&second_output, 1e-4, 1e-4, false));
+
+    Ok(())
+}
+
+#[test]
+fn t5_untied_word_embeddings() -> anyhow::Result<()> {
+    //    Resources paths
+    let config_resource = RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL);
+    let config_path = config_resource.get_local_path()?;
+
+    //    Set-up models with tied and untied embeddings
+    let device = Device::Cpu;
+    let tied_config = T5Config::from_file(&config_path);
+    let mut untied_config = T5Config::from_file(&config_path);
+    untied_config.tie_word_embeddings = Some(false);
+
+    let tied_vs = nn::VarStore::new(device);
+    let tied_model = T5ForConditionalGeneration::new(tied_vs.root(), &tied_config);
+    let untied_vs = nn::VarStore::new(device);
+    let untied_model = T5ForConditionalGeneration::new(untied_vs.root(), &untied_config);
+
+    assert!(!tied_vs.variables().contains_key("lm_head.weight"));
+    let mut lm_head_weight = untied_vs
+        .variables()
+        .get("lm_head.weight")
+        .expect("untied model should have a separate output projection")
+        .shallow_clone();
+    no_grad(|| lm_head_weight.zero_());
+
+    let input_ids = Tensor::of_slice(&[100i64, 200, 300, 1]).unsqueeze(0);
+    let decoder_input_ids = Tensor::of_slice(&[0i64, 100]).unsqueeze(0);
+
+    //    Forward passes
+    let tied_output = no_grad(|| {
+        tied_model.forward_t(
+            Some(&input_ids),
+            None,
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let untied_output = no_grad(|| {
+        untied_model.forward_t(
+            Some(&input_ids),
+            None,
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+
+    assert_eq!(
+        untied_output.decoder_output.size(),
+        vec!(1, 2, untied_config.vocab_size)
+    );
+    assert!(
+        tied_output
+            .decoder_output
+            .abs()
+            .sum(Kind::Float)
+            .double_value(&[])
+            > 0.0
+    );
+    assert_eq!(
+        untied_output
+            .decoder_output
+            .abs()
+            .sum(Kind::Float)
+            .double_value(&[]),
+        0.0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn t5_special_token_ids() -> anyhow::Result<()> {
+    //    Set-up tokenizer
+    let vocab_resource = RemoteResource::from_pretrained(T5VocabResources::T5_SMALL);
+    let vocab_path = vocab_resource.get_local_path()?;
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+
+    let special_token_ids = tokenizer.get_special_token_ids();
+    assert_eq!(
+        special_token_ids.eos,
+        Some(tokenizer.convert_tokens_to_ids(&["</s>"])[0])
+    );
+    assert_eq!(
+        special_token_ids.pad,
+        Some(tokenizer.convert_tokens_to_ids(&["<pad>"])[0])
+    );
+    assert_eq!(
+        special_token_ids.unk,
+        Some(tokenizer.convert_tokens_to_ids(&["<unk>"])[0])
+    );
+    assert_eq!(special_token_ids.mask, None);
+
+    Ok(())
+}
+
+#[test]
+fn t5_generate_with_prefixes() -> anyhow::Result<()> {
+    let generate_config = GenerateConfig {
+        model_resource: Box::new(RemoteResource::from_pretrained(T5ModelResources::T5_SMALL)),
+        config_resource: Box::new(RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL)),
+        vocab_resource: Box::new(RemoteResource::from_pretrained(T5VocabResources::T5_SMALL)),
+        merges_resource: None,
+        max_length: Some(32),
+        do_sample: false,
+        num_beams: 1,
+        device: Device::Cpu,
+        ..Default::default()
+    };
+    let model = T5Generator::new(generate_config)?;
+
+    let summarization_text = "The tower is 324 metres (1,063 ft) tall, about the same height as an 81-storey \
+building, and the tallest structure in Paris. Its base is square, measuring 125 metres (410 ft) on each side.";
+    let translation_text = "The house is wonderful.";
+    let inputs = [
+        ("translate English to German: ", translation_text),
+        ("summarize: ", summarization_text),
+        ("translate English to German: ", "This is a short sentence."),
+    ];
+
+    let output = model.generate_with_prefixes(&inputs, None)?;
+
+    //    The outputs are returned in the order of the inputs and match the single-task generation
+    assert_eq!(output.len(), 3);
+    for ((prefix, text), generated) in inputs.iter().zip(output.iter()) {
+        let expected = model.generate(Some(&[format!("{}{}", prefix, text)]), None)?;
+        assert_eq!(generated.text, expected[0].text);
+    }
+    assert!(output[0].text.contains("Haus"));
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_device_map() -> anyhow::Result<()> {
+    if tch::Cuda::device_count() < 2 {
+        return Ok(());
+    }
+    //    Resources paths
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let vocab_path =
+        RemoteResource::from_pretrained(T5VocabResources::T5_SMALL).get_local_path()?;
+    let weights_path =
+        RemoteResource::from_pretrained(T5ModelResources::T5_SMALL).get_local_path()?;
+
+    //    Set-up models, on a single device and split across two devices
+    let device = Device::Cuda(0);
+    let tokenizer = TokenizerOption::from_file(
+        ModelType::T5,
+        vocab_path.to_str().unwrap(),
+        None,
+        false,
+        None,
+        None,
+    )?;
+    let config = T5Config::from_file(&config_path);
+    let mut vs = nn::VarStore::new(device);
+    let model = T5ForConditionalGeneration::new(vs.root(), &config);
+    vs.load(&weights_path)?;
+
+    let mut split_vs = nn::VarStore::new(device);
+    let mut split_model = T5ForConditionalGeneration::new(split_vs.root(), &config);
+    let device_map = T5DeviceMap::new(Device::Cuda(0), Device::Cuda(1));
+    split_model.set_device_map(&split_vs, device_map)?;
+    split_vs.load(&weights_path)?;
+
+    //    Variables placement
+    for (name, variable) in split_vs.variables() {
+        if name.starts_with("decoder.") {
+            assert_eq!(variable.device(), Device::Cuda(1));
+        } else {
+            assert_eq!(variable.device(), Device::Cuda(0));
+        }
+    }
+    assert!(split_model
+        .set_device_map(
+            &split_vs,
+            T5DeviceMap::new(Device::Cuda(1), Device::Cuda(0)),
+        )
+        .is_err());
+
+    //    Forward passes
+    let (input_ids, attention_mask) = tokenizer.encode_batch(
+        &["translate English to German: The house is wonderful."],
+        128,
+        &TruncationStrategy::LongestFirst,
+        PaddingSide::Right,
+        device,
+    );
+    let decoder_input_ids = Tensor::zeros(&[1, 1], (Kind::Int64, device));
+    let output = no_grad(|| {
+        model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    let split_output = no_grad(|| {
+        split_model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    assert_eq!(split_output.decoder_output.device(), Device::Cuda(0));
+    let max_difference = (&output.decoder_output - split_output.decoder_output)
+        .abs()
+        .max()
+        .double_value(&[]);
+    assert!(max_difference < 1e-4);
+
+    //    Layer ranges: the last encoder blocks and the first decoder blocks on the other device
+    let mut layers_vs = nn::VarStore::new(device);
+    let mut layers_model = T5ForConditionalGeneration::new(layers_vs.root(), &config);
+    let device_map = T5DeviceMap {
+        encoder_layers: vec![(3..6, Device::Cuda(1))],
+        decoder_layers: vec![(0..3, Device::Cuda(0))],
+        ..T5DeviceMap::new(Device::Cuda(0), Device::Cuda(1))
+    };
+    layers_model.set_device_map(&layers_vs, device_map)?;
+    layers_vs.load(&weights_path)?;
+    for (name, variable) in layers_vs.variables() {
+        let expected_device = match name.split('.').take(3).collect::<Vec<&str>>()[..] {
+            ["encoder", "block", index] if index.parse::<usize>()? >= 3 => Device::Cuda(1),
+            ["decoder", "block", index] if index.parse::<usize>()? < 3 => Device::Cuda(0),
+            ["decoder", ..] => Device::Cuda(1),
+            _ => Device::Cuda(0),
+        };
+        assert_eq!(variable.device(), expected_device, "{}", name);
+    }
+    let layers_output = no_grad(|| {
+        layers_model.forward_t(
+            Some(&input_ids),
+            Some(&attention_mask),
+            None,
+            Some(&decoder_input_ids),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+    });
+    assert_eq!(layers_output.decoder_output.device(), Device::Cuda(0));
+    let max_difference = (output.decoder_output - layers_output.decoder_output)
+        .abs()
+        .max()
+        .double_value(&[]);
+    assert!(max_difference < 1e-4);
+
+    Ok(())
+}
+
+#[test]
+fn test_t5_device_map_invalid_layer_range() -> anyhow::Result<()> {
+    let config_path =
+        RemoteResource::from_pretrained(T5ConfigResources::T5_SMALL).get_local_path()?;
+    let config = T5Config::from_file(config_path);
+    let vs = nn::VarStore::new(Device::Cpu);
+    let mut model = T5ForConditionalGeneration::new(vs.root(), &config);
+
+    //    T5-small has 6 encoder and decoder blocks
+    let device_map = T5DeviceMap {
+        decoder_layers: vec![(4..8, Device::Cpu)],
+        ..T5DeviceMap::new(Device::Cpu, Device::Cpu)
+    };
+    assert!(model.set_device_map(&vs, device_map).is_err());
+    let device_map = T5DeviceMap {
+        encoder_layers: vec![(0..2, Device::Cpu)],
+        decoder_layers: vec![(4..6, Device::Cpu)],
+        ..T5DeviceMap::new(Device::Cpu, Device::Cpu)
+    };
+    model.set_device_map(&vs, device_map)?;
+
+    Ok(())
+}